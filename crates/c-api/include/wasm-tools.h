@@ -0,0 +1,86 @@
+/**
+ * \file wasm-tools.h
+ *
+ * C API for parsing, printing, and validating WebAssembly modules.
+ *
+ * All functions which can fail return a `wasm_tools_error_t*`, which is `NULL`
+ * on success. A non-`NULL` error is owned by the caller and must be deleted
+ * with `wasm_tools_error_delete`.
+ *
+ * Byte vectors returned through out-parameters are also owned by the caller
+ * and must be deleted with `wasm_tools_byte_vec_delete`.
+ */
+
+#ifndef WASM_TOOLS_H
+#define WASM_TOOLS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/**
+ * \brief An owned vector of bytes.
+ */
+typedef struct wasm_tools_byte_vec_t {
+  size_t size;
+  uint8_t *data;
+} wasm_tools_byte_vec_t;
+
+/**
+ * \brief An opaque error returned by the functions in this header.
+ */
+typedef struct wasm_tools_error_t wasm_tools_error_t;
+
+/**
+ * \brief Parses the WebAssembly text format in `wat` into a binary.
+ *
+ * The input may also already be a WebAssembly binary, in which case it's
+ * copied into `ret` as-is. On success the binary is stored in `ret`.
+ */
+wasm_tools_error_t *wat_parse(const uint8_t *wat, size_t wat_len,
+                              wasm_tools_byte_vec_t *ret);
+
+/**
+ * \brief Prints the WebAssembly binary `wasm` in the text format.
+ *
+ * On success the UTF-8 text, which isn't nul-terminated, is stored in `ret`.
+ */
+wasm_tools_error_t *wasm_print(const uint8_t *wasm, size_t wasm_len,
+                               wasm_tools_byte_vec_t *ret);
+
+/**
+ * \brief Validates the WebAssembly binary `wasm` with the default set of
+ * enabled proposals.
+ */
+wasm_tools_error_t *wasm_validate(const uint8_t *wasm, size_t wasm_len);
+
+/**
+ * \brief Stores the UTF-8 message of `error`, which isn't nul-terminated, in
+ * `ret`.
+ */
+void wasm_tools_error_message(const wasm_tools_error_t *error,
+                              wasm_tools_byte_vec_t *ret);
+
+/**
+ * \brief Deletes an error returned by one of the functions in this header.
+ *
+ * Does nothing if `error` is `NULL`.
+ */
+void wasm_tools_error_delete(wasm_tools_error_t *error);
+
+/**
+ * \brief Deletes the contents of a byte vector filled in by one of the
+ * functions in this header.
+ *
+ * Does nothing if `vec` is `NULL`.
+ */
+void wasm_tools_byte_vec_delete(wasm_tools_byte_vec_t *vec);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif
+
+#endif // WASM_TOOLS_H
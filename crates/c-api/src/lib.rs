@@ -0,0 +1,103 @@
+//! A C API for the crates in this repository, exposing parsing of the text
+//! format, printing of binaries, and validation.
+//!
+//! The declarations of these functions live in `include/wasm-tools.h`, which
+//! is the documentation for this API. The ABI of this crate only changes in
+//! semver-breaking releases.
+
+use std::slice;
+
+/// An owned vector of bytes, `wasm_tools_byte_vec_t` in C.
+#[repr(C)]
+pub struct ByteVec {
+    pub size: usize,
+    pub data: *mut u8,
+}
+
+impl ByteVec {
+    fn set(dst: &mut ByteVec, bytes: Vec<u8>) {
+        let bytes = bytes.into_boxed_slice();
+        dst.size = bytes.len();
+        dst.data = Box::into_raw(bytes) as *mut u8;
+    }
+}
+
+/// An error returned across the C API, `wasm_tools_error_t` in C.
+pub struct Error {
+    message: String,
+}
+
+impl Error {
+    fn new(message: impl ToString) -> Box<Error> {
+        Box::new(Error {
+            message: message.to_string(),
+        })
+    }
+}
+
+unsafe fn input<'a>(data: *const u8, len: usize) -> &'a [u8] {
+    if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(data, len)
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wat_parse(
+    wat: *const u8,
+    wat_len: usize,
+    ret: &mut ByteVec,
+) -> Option<Box<Error>> {
+    match wat::parse_bytes(input(wat, wat_len)) {
+        Ok(wasm) => {
+            ByteVec::set(ret, wasm.into_owned());
+            None
+        }
+        Err(e) => Some(Error::new(e)),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_print(
+    wasm: *const u8,
+    wasm_len: usize,
+    ret: &mut ByteVec,
+) -> Option<Box<Error>> {
+    match wasmprinter::print_bytes(input(wasm, wasm_len)) {
+        Ok(text) => {
+            ByteVec::set(ret, text.into_bytes());
+            None
+        }
+        Err(e) => Some(Error::new(e)),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_validate(wasm: *const u8, wasm_len: usize) -> Option<Box<Error>> {
+    wasmparser::Validator::new()
+        .validate_all(input(wasm, wasm_len))
+        .err()
+        .map(Error::new)
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_tools_error_message(error: &Error, ret: &mut ByteVec) {
+    ByteVec::set(ret, error.message.clone().into_bytes());
+}
+
+#[no_mangle]
+pub extern "C" fn wasm_tools_error_delete(_error: Option<Box<Error>>) {}
+
+#[no_mangle]
+pub unsafe extern "C" fn wasm_tools_byte_vec_delete(vec: Option<&mut ByteVec>) {
+    let vec = match vec {
+        Some(vec) => vec,
+        None => return,
+    };
+    if !vec.data.is_null() {
+        drop(Box::from_raw(slice::from_raw_parts_mut(vec.data, vec.size)));
+    }
+    vec.size = 0;
+    vec.data = std::ptr::null_mut();
+}
@@ -0,0 +1,57 @@
+use std::ptr;
+use std::slice;
+use wasm_tools_c_api::*;
+
+fn empty() -> ByteVec {
+    ByteVec {
+        size: 0,
+        data: ptr::null_mut(),
+    }
+}
+
+unsafe fn take(vec: &mut ByteVec) -> Vec<u8> {
+    let ret = slice::from_raw_parts(vec.data, vec.size).to_vec();
+    wasm_tools_byte_vec_delete(Some(vec));
+    ret
+}
+
+#[test]
+fn parse_print_validate() {
+    unsafe {
+        let wat = "(module (func (export \"f\")))";
+        let mut wasm = empty();
+        assert!(wat_parse(wat.as_ptr(), wat.len(), &mut wasm).is_none());
+        let wasm = take(&mut wasm);
+        assert!(wasm_validate(wasm.as_ptr(), wasm.len()).is_none());
+
+        let mut text = empty();
+        assert!(wasm_print(wasm.as_ptr(), wasm.len(), &mut text).is_none());
+        let text = String::from_utf8(take(&mut text)).unwrap();
+        assert!(text.contains("(export \"f\" (func 0))"), "{}", text);
+    }
+}
+
+#[test]
+fn errors() {
+    unsafe {
+        let wat = "(module";
+        let mut wasm = empty();
+        let err = wat_parse(wat.as_ptr(), wat.len(), &mut wasm).unwrap();
+        let mut message = empty();
+        wasm_tools_error_message(&err, &mut message);
+        let message = String::from_utf8(take(&mut message)).unwrap();
+        assert!(message.contains("expected"), "{}", message);
+        wasm_tools_error_delete(Some(err));
+
+        let wasm = b"\0asm\x01\0\0\0\x0a";
+        assert!(wasm_validate(wasm.as_ptr(), wasm.len()).is_some());
+    }
+}
+
+#[test]
+fn delete_null() {
+    unsafe {
+        wasm_tools_error_delete(None);
+        wasm_tools_byte_vec_delete(None);
+    }
+}
@@ -185,6 +185,9 @@ impl BlockType {
     }
 }
 
+/// The index of a lane in a SIMD vector, the immediate of lane instructions.
+pub type Lane = u8;
+
 /// WebAssembly instructions.
 #[derive(Clone, Debug)]
 #[non_exhaustive]
@@ -205,6 +208,17 @@ pub enum Instruction<'a> {
     Call(u32),
     CallIndirect { ty: u32, table: u32 },
 
+    // Tail call instructions.
+    ReturnCall(u32),
+    ReturnCallIndirect { ty: u32, table: u32 },
+
+    // Exception handling instructions.
+    Try(BlockType),
+    Catch(u32),
+    Throw(u32),
+    Rethrow(u32),
+    Unwind,
+
     // Parametric instructions.
     Drop,
     Select,
@@ -389,8 +403,299 @@ pub enum Instruction<'a> {
     I64TruncSatF64S,
     I64TruncSatF64U,
 
+    // Wide arithmetic instructions.
+    I64Add128,
+    I64Sub128,
+    I64MulWideS,
+    I64MulWideU,
+
     // SIMD instructions.
     V128Const(i128),
+    V128Load(MemArg),
+    V128Load8x8S(MemArg),
+    V128Load8x8U(MemArg),
+    V128Load16x4S(MemArg),
+    V128Load16x4U(MemArg),
+    V128Load32x2S(MemArg),
+    V128Load32x2U(MemArg),
+    V128Load8Splat(MemArg),
+    V128Load16Splat(MemArg),
+    V128Load32Splat(MemArg),
+    V128Load64Splat(MemArg),
+    V128Store(MemArg),
+    I8x16Shuffle([Lane; 16]),
+    I8x16Swizzle,
+    I8x16Splat,
+    I16x8Splat,
+    I32x4Splat,
+    I64x2Splat,
+    F32x4Splat,
+    F64x2Splat,
+    I8x16ExtractLaneS(Lane),
+    I8x16ExtractLaneU(Lane),
+    I8x16ReplaceLane(Lane),
+    I16x8ExtractLaneS(Lane),
+    I16x8ExtractLaneU(Lane),
+    I16x8ReplaceLane(Lane),
+    I32x4ExtractLane(Lane),
+    I32x4ReplaceLane(Lane),
+    I64x2ExtractLane(Lane),
+    I64x2ReplaceLane(Lane),
+    F32x4ExtractLane(Lane),
+    F32x4ReplaceLane(Lane),
+    F64x2ExtractLane(Lane),
+    F64x2ReplaceLane(Lane),
+    I8x16Eq,
+    I8x16Ne,
+    I8x16LtS,
+    I8x16LtU,
+    I8x16GtS,
+    I8x16GtU,
+    I8x16LeS,
+    I8x16LeU,
+    I8x16GeS,
+    I8x16GeU,
+    I16x8Eq,
+    I16x8Ne,
+    I16x8LtS,
+    I16x8LtU,
+    I16x8GtS,
+    I16x8GtU,
+    I16x8LeS,
+    I16x8LeU,
+    I16x8GeS,
+    I16x8GeU,
+    I32x4Eq,
+    I32x4Ne,
+    I32x4LtS,
+    I32x4LtU,
+    I32x4GtS,
+    I32x4GtU,
+    I32x4LeS,
+    I32x4LeU,
+    I32x4GeS,
+    I32x4GeU,
+    F32x4Eq,
+    F32x4Ne,
+    F32x4Lt,
+    F32x4Gt,
+    F32x4Le,
+    F32x4Ge,
+    F64x2Eq,
+    F64x2Ne,
+    F64x2Lt,
+    F64x2Gt,
+    F64x2Le,
+    F64x2Ge,
+    V128Not,
+    V128And,
+    V128AndNot,
+    V128Or,
+    V128Xor,
+    V128Bitselect,
+    V128Load8Lane { memarg: MemArg, lane: Lane },
+    V128Load16Lane { memarg: MemArg, lane: Lane },
+    V128Load32Lane { memarg: MemArg, lane: Lane },
+    V128Load64Lane { memarg: MemArg, lane: Lane },
+    V128Store8Lane { memarg: MemArg, lane: Lane },
+    V128Store16Lane { memarg: MemArg, lane: Lane },
+    V128Store32Lane { memarg: MemArg, lane: Lane },
+    V128Store64Lane { memarg: MemArg, lane: Lane },
+    I8x16Abs,
+    I8x16Neg,
+    V128AnyTrue,
+    I8x16AllTrue,
+    I8x16Bitmask,
+    I8x16NarrowI16x8S,
+    I8x16NarrowI16x8U,
+    I8x16Shl,
+    I8x16ShrS,
+    I8x16ShrU,
+    I8x16Add,
+    I8x16AddSatS,
+    I8x16AddSatU,
+    I8x16Sub,
+    I8x16SubSatS,
+    I8x16SubSatU,
+    I8x16MinS,
+    I8x16MinU,
+    I8x16MaxS,
+    I8x16MaxU,
+    I8x16RoundingAverageU,
+    I16x8Abs,
+    I16x8Neg,
+    I16x8AllTrue,
+    I16x8Bitmask,
+    I16x8NarrowI32x4S,
+    I16x8NarrowI32x4U,
+    I16x8WidenLowI8x16S,
+    I16x8WidenHighI8x16S,
+    I16x8WidenLowI8x16U,
+    I16x8WidenHighI8x16U,
+    I16x8Shl,
+    I16x8ShrS,
+    I16x8ShrU,
+    I16x8Add,
+    I16x8AddSatS,
+    I16x8AddSatU,
+    I16x8Sub,
+    I16x8SubSatS,
+    I16x8SubSatU,
+    I16x8Mul,
+    I16x8MinS,
+    I16x8MinU,
+    I16x8MaxS,
+    I16x8MaxU,
+    I16x8ExtMulLowI8x16S,
+    I16x8RoundingAverageU,
+    I16x8Q15MulrSatS,
+    I16x8ExtMulHighI8x16S,
+    I16x8ExtMulLowI8x16U,
+    I16x8ExtMulHighI8x16U,
+    I32x4Abs,
+    I32x4Neg,
+    I32x4AllTrue,
+    I32x4Bitmask,
+    I32x4WidenLowI16x8S,
+    I32x4WidenHighI16x8S,
+    I32x4WidenLowI16x8U,
+    I32x4WidenHighI16x8U,
+    I32x4Shl,
+    I32x4ShrS,
+    I32x4ShrU,
+    I32x4Add,
+    I32x4Sub,
+    I32x4Mul,
+    I32x4MinS,
+    I32x4MinU,
+    I32x4MaxS,
+    I32x4MaxU,
+    I32x4DotI16x8S,
+    I32x4ExtMulLowI16x8S,
+    I32x4ExtMulHighI16x8S,
+    I32x4ExtMulLowI16x8U,
+    I32x4ExtMulHighI16x8U,
+    I64x2Neg,
+    I64x2Bitmask,
+    I64x2WidenLowI32x4S,
+    I64x2WidenHighI32x4S,
+    I64x2WidenLowI32x4U,
+    I64x2WidenHighI32x4U,
+    I64x2Shl,
+    I64x2ShrS,
+    I64x2ShrU,
+    I64x2Add,
+    I64x2Sub,
+    I64x2ExtMulLowI32x4S,
+    I64x2ExtMulHighI32x4S,
+    I64x2Mul,
+    I64x2ExtMulLowI32x4U,
+    I64x2ExtMulHighI32x4U,
+    F32x4Ceil,
+    F32x4Floor,
+    F32x4Trunc,
+    F32x4Nearest,
+    F64x2Ceil,
+    F64x2Floor,
+    F64x2Trunc,
+    F64x2Nearest,
+    F32x4Abs,
+    F32x4Neg,
+    F32x4Sqrt,
+    F32x4Add,
+    F32x4Sub,
+    F32x4Mul,
+    F32x4Div,
+    F32x4Min,
+    F32x4Max,
+    F32x4PMin,
+    F32x4PMax,
+    F64x2Abs,
+    F64x2Neg,
+    F64x2Sqrt,
+    F64x2Add,
+    F64x2Sub,
+    F64x2Mul,
+    F64x2Div,
+    F64x2Min,
+    F64x2Max,
+    F64x2PMin,
+    F64x2PMax,
+    I32x4TruncSatF32x4S,
+    I32x4TruncSatF32x4U,
+    F32x4ConvertI32x4S,
+    F32x4ConvertI32x4U,
+    V128Load32Zero(MemArg),
+    V128Load64Zero(MemArg),
+
+    // Atomic instructions.
+    MemoryAtomicNotify(MemArg),
+    MemoryAtomicWait32(MemArg),
+    MemoryAtomicWait64(MemArg),
+    AtomicFence,
+    I32AtomicLoad(MemArg),
+    I64AtomicLoad(MemArg),
+    I32AtomicLoad8U(MemArg),
+    I32AtomicLoad16U(MemArg),
+    I64AtomicLoad8U(MemArg),
+    I64AtomicLoad16U(MemArg),
+    I64AtomicLoad32U(MemArg),
+    I32AtomicStore(MemArg),
+    I64AtomicStore(MemArg),
+    I32AtomicStore8(MemArg),
+    I32AtomicStore16(MemArg),
+    I64AtomicStore8(MemArg),
+    I64AtomicStore16(MemArg),
+    I64AtomicStore32(MemArg),
+    I32AtomicRmwAdd(MemArg),
+    I64AtomicRmwAdd(MemArg),
+    I32AtomicRmw8AddU(MemArg),
+    I32AtomicRmw16AddU(MemArg),
+    I64AtomicRmw8AddU(MemArg),
+    I64AtomicRmw16AddU(MemArg),
+    I64AtomicRmw32AddU(MemArg),
+    I32AtomicRmwSub(MemArg),
+    I64AtomicRmwSub(MemArg),
+    I32AtomicRmw8SubU(MemArg),
+    I32AtomicRmw16SubU(MemArg),
+    I64AtomicRmw8SubU(MemArg),
+    I64AtomicRmw16SubU(MemArg),
+    I64AtomicRmw32SubU(MemArg),
+    I32AtomicRmwAnd(MemArg),
+    I64AtomicRmwAnd(MemArg),
+    I32AtomicRmw8AndU(MemArg),
+    I32AtomicRmw16AndU(MemArg),
+    I64AtomicRmw8AndU(MemArg),
+    I64AtomicRmw16AndU(MemArg),
+    I64AtomicRmw32AndU(MemArg),
+    I32AtomicRmwOr(MemArg),
+    I64AtomicRmwOr(MemArg),
+    I32AtomicRmw8OrU(MemArg),
+    I32AtomicRmw16OrU(MemArg),
+    I64AtomicRmw8OrU(MemArg),
+    I64AtomicRmw16OrU(MemArg),
+    I64AtomicRmw32OrU(MemArg),
+    I32AtomicRmwXor(MemArg),
+    I64AtomicRmwXor(MemArg),
+    I32AtomicRmw8XorU(MemArg),
+    I32AtomicRmw16XorU(MemArg),
+    I64AtomicRmw8XorU(MemArg),
+    I64AtomicRmw16XorU(MemArg),
+    I64AtomicRmw32XorU(MemArg),
+    I32AtomicRmwXchg(MemArg),
+    I64AtomicRmwXchg(MemArg),
+    I32AtomicRmw8XchgU(MemArg),
+    I32AtomicRmw16XchgU(MemArg),
+    I64AtomicRmw8XchgU(MemArg),
+    I64AtomicRmw16XchgU(MemArg),
+    I64AtomicRmw32XchgU(MemArg),
+    I32AtomicRmwCmpxchg(MemArg),
+    I64AtomicRmwCmpxchg(MemArg),
+    I32AtomicRmw8CmpxchgU(MemArg),
+    I32AtomicRmw16CmpxchgU(MemArg),
+    I64AtomicRmw8CmpxchgU(MemArg),
+    I64AtomicRmw16CmpxchgU(MemArg),
+    I64AtomicRmw32CmpxchgU(MemArg),
 
     // Reference types instructions.
     TypedSelect(ValType),
@@ -456,6 +761,36 @@ impl Instruction<'_> {
                 bytes.extend(encoders::u32(table));
             }
 
+            // Tail call instructions.
+            Instruction::ReturnCall(f) => {
+                bytes.push(0x12);
+                bytes.extend(encoders::u32(f));
+            }
+            Instruction::ReturnCallIndirect { ty, table } => {
+                bytes.push(0x13);
+                bytes.extend(encoders::u32(ty));
+                bytes.extend(encoders::u32(table));
+            }
+
+            // Exception handling instructions.
+            Instruction::Try(bt) => {
+                bytes.push(0x06);
+                bt.encode(bytes);
+            }
+            Instruction::Catch(e) => {
+                bytes.push(0x07);
+                bytes.extend(encoders::u32(e));
+            }
+            Instruction::Throw(e) => {
+                bytes.push(0x08);
+                bytes.extend(encoders::u32(e));
+            }
+            Instruction::Rethrow(l) => {
+                bytes.push(0x09);
+                bytes.extend(encoders::u32(l));
+            }
+            Instruction::Unwind => bytes.push(0x0A),
+
             // Parametric instructions.
             Instruction::Drop => bytes.push(0x1A),
             Instruction::Select => bytes.push(0x1B),
@@ -800,6 +1135,24 @@ impl Instruction<'_> {
                 bytes.extend(encoders::u32(7));
             }
 
+            // Wide arithmetic instructions.
+            Instruction::I64Add128 => {
+                bytes.push(0xFC);
+                bytes.extend(encoders::u32(19));
+            }
+            Instruction::I64Sub128 => {
+                bytes.push(0xFC);
+                bytes.extend(encoders::u32(20));
+            }
+            Instruction::I64MulWideS => {
+                bytes.push(0xFC);
+                bytes.extend(encoders::u32(21));
+            }
+            Instruction::I64MulWideU => {
+                bytes.push(0xFC);
+                bytes.extend(encoders::u32(22));
+            }
+
             // Reference types instructions.
             Instruction::RefNull(ty) => {
                 bytes.push(0xd0);
@@ -851,6 +1204,1680 @@ impl Instruction<'_> {
                 bytes.extend(encoders::u32(12));
                 bytes.extend(x.to_le_bytes().iter().copied());
             }
+            Instruction::V128Load(memarg) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x00));
+                memarg.encode(bytes);
+            }
+            Instruction::V128Load8x8S(memarg) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x01));
+                memarg.encode(bytes);
+            }
+            Instruction::V128Load8x8U(memarg) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x02));
+                memarg.encode(bytes);
+            }
+            Instruction::V128Load16x4S(memarg) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x03));
+                memarg.encode(bytes);
+            }
+            Instruction::V128Load16x4U(memarg) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x04));
+                memarg.encode(bytes);
+            }
+            Instruction::V128Load32x2S(memarg) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x05));
+                memarg.encode(bytes);
+            }
+            Instruction::V128Load32x2U(memarg) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x06));
+                memarg.encode(bytes);
+            }
+            Instruction::V128Load8Splat(memarg) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x07));
+                memarg.encode(bytes);
+            }
+            Instruction::V128Load16Splat(memarg) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x08));
+                memarg.encode(bytes);
+            }
+            Instruction::V128Load32Splat(memarg) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x09));
+                memarg.encode(bytes);
+            }
+            Instruction::V128Load64Splat(memarg) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x0a));
+                memarg.encode(bytes);
+            }
+            Instruction::V128Store(memarg) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x0b));
+                memarg.encode(bytes);
+            }
+            Instruction::I8x16Shuffle(lanes) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x0d));
+                bytes.extend_from_slice(&lanes);
+            }
+            Instruction::I8x16Swizzle => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x0e));
+            }
+            Instruction::I8x16Splat => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x0f));
+            }
+            Instruction::I16x8Splat => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x10));
+            }
+            Instruction::I32x4Splat => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x11));
+            }
+            Instruction::I64x2Splat => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x12));
+            }
+            Instruction::F32x4Splat => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x13));
+            }
+            Instruction::F64x2Splat => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x14));
+            }
+            Instruction::I8x16ExtractLaneS(lane) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x15));
+                bytes.push(lane);
+            }
+            Instruction::I8x16ExtractLaneU(lane) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x16));
+                bytes.push(lane);
+            }
+            Instruction::I8x16ReplaceLane(lane) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x17));
+                bytes.push(lane);
+            }
+            Instruction::I16x8ExtractLaneS(lane) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x18));
+                bytes.push(lane);
+            }
+            Instruction::I16x8ExtractLaneU(lane) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x19));
+                bytes.push(lane);
+            }
+            Instruction::I16x8ReplaceLane(lane) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x1a));
+                bytes.push(lane);
+            }
+            Instruction::I32x4ExtractLane(lane) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x1b));
+                bytes.push(lane);
+            }
+            Instruction::I32x4ReplaceLane(lane) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x1c));
+                bytes.push(lane);
+            }
+            Instruction::I64x2ExtractLane(lane) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x1d));
+                bytes.push(lane);
+            }
+            Instruction::I64x2ReplaceLane(lane) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x1e));
+                bytes.push(lane);
+            }
+            Instruction::F32x4ExtractLane(lane) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x1f));
+                bytes.push(lane);
+            }
+            Instruction::F32x4ReplaceLane(lane) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x20));
+                bytes.push(lane);
+            }
+            Instruction::F64x2ExtractLane(lane) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x21));
+                bytes.push(lane);
+            }
+            Instruction::F64x2ReplaceLane(lane) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x22));
+                bytes.push(lane);
+            }
+            Instruction::I8x16Eq => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x23));
+            }
+            Instruction::I8x16Ne => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x24));
+            }
+            Instruction::I8x16LtS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x25));
+            }
+            Instruction::I8x16LtU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x26));
+            }
+            Instruction::I8x16GtS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x27));
+            }
+            Instruction::I8x16GtU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x28));
+            }
+            Instruction::I8x16LeS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x29));
+            }
+            Instruction::I8x16LeU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x2a));
+            }
+            Instruction::I8x16GeS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x2b));
+            }
+            Instruction::I8x16GeU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x2c));
+            }
+            Instruction::I16x8Eq => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x2d));
+            }
+            Instruction::I16x8Ne => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x2e));
+            }
+            Instruction::I16x8LtS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x2f));
+            }
+            Instruction::I16x8LtU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x30));
+            }
+            Instruction::I16x8GtS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x31));
+            }
+            Instruction::I16x8GtU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x32));
+            }
+            Instruction::I16x8LeS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x33));
+            }
+            Instruction::I16x8LeU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x34));
+            }
+            Instruction::I16x8GeS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x35));
+            }
+            Instruction::I16x8GeU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x36));
+            }
+            Instruction::I32x4Eq => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x37));
+            }
+            Instruction::I32x4Ne => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x38));
+            }
+            Instruction::I32x4LtS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x39));
+            }
+            Instruction::I32x4LtU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x3a));
+            }
+            Instruction::I32x4GtS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x3b));
+            }
+            Instruction::I32x4GtU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x3c));
+            }
+            Instruction::I32x4LeS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x3d));
+            }
+            Instruction::I32x4LeU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x3e));
+            }
+            Instruction::I32x4GeS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x3f));
+            }
+            Instruction::I32x4GeU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x40));
+            }
+            Instruction::F32x4Eq => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x41));
+            }
+            Instruction::F32x4Ne => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x42));
+            }
+            Instruction::F32x4Lt => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x43));
+            }
+            Instruction::F32x4Gt => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x44));
+            }
+            Instruction::F32x4Le => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x45));
+            }
+            Instruction::F32x4Ge => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x46));
+            }
+            Instruction::F64x2Eq => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x47));
+            }
+            Instruction::F64x2Ne => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x48));
+            }
+            Instruction::F64x2Lt => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x49));
+            }
+            Instruction::F64x2Gt => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x4a));
+            }
+            Instruction::F64x2Le => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x4b));
+            }
+            Instruction::F64x2Ge => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x4c));
+            }
+            Instruction::V128Not => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x4d));
+            }
+            Instruction::V128And => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x4e));
+            }
+            Instruction::V128AndNot => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x4f));
+            }
+            Instruction::V128Or => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x50));
+            }
+            Instruction::V128Xor => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x51));
+            }
+            Instruction::V128Bitselect => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x52));
+            }
+            Instruction::V128Load8Lane { memarg, lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x58));
+                memarg.encode(bytes);
+                bytes.push(lane);
+            }
+            Instruction::V128Load16Lane { memarg, lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x59));
+                memarg.encode(bytes);
+                bytes.push(lane);
+            }
+            Instruction::V128Load32Lane { memarg, lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x5a));
+                memarg.encode(bytes);
+                bytes.push(lane);
+            }
+            Instruction::V128Load64Lane { memarg, lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x5b));
+                memarg.encode(bytes);
+                bytes.push(lane);
+            }
+            Instruction::V128Store8Lane { memarg, lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x5c));
+                memarg.encode(bytes);
+                bytes.push(lane);
+            }
+            Instruction::V128Store16Lane { memarg, lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x5d));
+                memarg.encode(bytes);
+                bytes.push(lane);
+            }
+            Instruction::V128Store32Lane { memarg, lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x5e));
+                memarg.encode(bytes);
+                bytes.push(lane);
+            }
+            Instruction::V128Store64Lane { memarg, lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x5f));
+                memarg.encode(bytes);
+                bytes.push(lane);
+            }
+            Instruction::I8x16Abs => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x60));
+            }
+            Instruction::I8x16Neg => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x61));
+            }
+            Instruction::V128AnyTrue => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x62));
+            }
+            Instruction::I8x16AllTrue => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x63));
+            }
+            Instruction::I8x16Bitmask => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x64));
+            }
+            Instruction::I8x16NarrowI16x8S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x65));
+            }
+            Instruction::I8x16NarrowI16x8U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x66));
+            }
+            Instruction::I8x16Shl => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x6b));
+            }
+            Instruction::I8x16ShrS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x6c));
+            }
+            Instruction::I8x16ShrU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x6d));
+            }
+            Instruction::I8x16Add => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x6e));
+            }
+            Instruction::I8x16AddSatS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x6f));
+            }
+            Instruction::I8x16AddSatU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x70));
+            }
+            Instruction::I8x16Sub => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x71));
+            }
+            Instruction::I8x16SubSatS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x72));
+            }
+            Instruction::I8x16SubSatU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x73));
+            }
+            Instruction::I8x16MinS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x76));
+            }
+            Instruction::I8x16MinU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x77));
+            }
+            Instruction::I8x16MaxS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x78));
+            }
+            Instruction::I8x16MaxU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x79));
+            }
+            Instruction::I8x16RoundingAverageU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x7b));
+            }
+            Instruction::I16x8Abs => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x80));
+            }
+            Instruction::I16x8Neg => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x81));
+            }
+            Instruction::I16x8AllTrue => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x83));
+            }
+            Instruction::I16x8Bitmask => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x84));
+            }
+            Instruction::I16x8NarrowI32x4S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x85));
+            }
+            Instruction::I16x8NarrowI32x4U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x86));
+            }
+            Instruction::I16x8WidenLowI8x16S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x87));
+            }
+            Instruction::I16x8WidenHighI8x16S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x88));
+            }
+            Instruction::I16x8WidenLowI8x16U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x89));
+            }
+            Instruction::I16x8WidenHighI8x16U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x8a));
+            }
+            Instruction::I16x8Shl => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x8b));
+            }
+            Instruction::I16x8ShrS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x8c));
+            }
+            Instruction::I16x8ShrU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x8d));
+            }
+            Instruction::I16x8Add => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x8e));
+            }
+            Instruction::I16x8AddSatS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x8f));
+            }
+            Instruction::I16x8AddSatU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x90));
+            }
+            Instruction::I16x8Sub => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x91));
+            }
+            Instruction::I16x8SubSatS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x92));
+            }
+            Instruction::I16x8SubSatU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x93));
+            }
+            Instruction::I16x8Mul => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x95));
+            }
+            Instruction::I16x8MinS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x96));
+            }
+            Instruction::I16x8MinU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x97));
+            }
+            Instruction::I16x8MaxS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x98));
+            }
+            Instruction::I16x8MaxU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x99));
+            }
+            Instruction::I16x8ExtMulLowI8x16S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x9a));
+            }
+            Instruction::I16x8RoundingAverageU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x9b));
+            }
+            Instruction::I16x8Q15MulrSatS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x9c));
+            }
+            Instruction::I16x8ExtMulHighI8x16S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x9d));
+            }
+            Instruction::I16x8ExtMulLowI8x16U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x9e));
+            }
+            Instruction::I16x8ExtMulHighI8x16U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x9f));
+            }
+            Instruction::I32x4Abs => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xa0));
+            }
+            Instruction::I32x4Neg => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xa1));
+            }
+            Instruction::I32x4AllTrue => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xa3));
+            }
+            Instruction::I32x4Bitmask => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xa4));
+            }
+            Instruction::I32x4WidenLowI16x8S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xa7));
+            }
+            Instruction::I32x4WidenHighI16x8S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xa8));
+            }
+            Instruction::I32x4WidenLowI16x8U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xa9));
+            }
+            Instruction::I32x4WidenHighI16x8U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xaa));
+            }
+            Instruction::I32x4Shl => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xab));
+            }
+            Instruction::I32x4ShrS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xac));
+            }
+            Instruction::I32x4ShrU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xad));
+            }
+            Instruction::I32x4Add => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xae));
+            }
+            Instruction::I32x4Sub => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xb1));
+            }
+            Instruction::I32x4Mul => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xb5));
+            }
+            Instruction::I32x4MinS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xb6));
+            }
+            Instruction::I32x4MinU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xb7));
+            }
+            Instruction::I32x4MaxS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xb8));
+            }
+            Instruction::I32x4MaxU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xb9));
+            }
+            Instruction::I32x4DotI16x8S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xba));
+            }
+            Instruction::I32x4ExtMulLowI16x8S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xbb));
+            }
+            Instruction::I32x4ExtMulHighI16x8S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xbd));
+            }
+            Instruction::I32x4ExtMulLowI16x8U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xbe));
+            }
+            Instruction::I32x4ExtMulHighI16x8U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xbf));
+            }
+            Instruction::I64x2Neg => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xc1));
+            }
+            Instruction::I64x2Bitmask => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xc4));
+            }
+            Instruction::I64x2WidenLowI32x4S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xc7));
+            }
+            Instruction::I64x2WidenHighI32x4S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xc8));
+            }
+            Instruction::I64x2WidenLowI32x4U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xc9));
+            }
+            Instruction::I64x2WidenHighI32x4U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xca));
+            }
+            Instruction::I64x2Shl => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xcb));
+            }
+            Instruction::I64x2ShrS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xcc));
+            }
+            Instruction::I64x2ShrU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xcd));
+            }
+            Instruction::I64x2Add => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xce));
+            }
+            Instruction::I64x2Sub => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xd1));
+            }
+            Instruction::I64x2ExtMulLowI32x4S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xd2));
+            }
+            Instruction::I64x2ExtMulHighI32x4S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xd3));
+            }
+            Instruction::I64x2Mul => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xd5));
+            }
+            Instruction::I64x2ExtMulLowI32x4U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xd6));
+            }
+            Instruction::I64x2ExtMulHighI32x4U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xd7));
+            }
+            Instruction::F32x4Ceil => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xd8));
+            }
+            Instruction::F32x4Floor => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xd9));
+            }
+            Instruction::F32x4Trunc => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xda));
+            }
+            Instruction::F32x4Nearest => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xdb));
+            }
+            Instruction::F64x2Ceil => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xdc));
+            }
+            Instruction::F64x2Floor => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xdd));
+            }
+            Instruction::F64x2Trunc => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xde));
+            }
+            Instruction::F64x2Nearest => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xdf));
+            }
+            Instruction::F32x4Abs => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xe0));
+            }
+            Instruction::F32x4Neg => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xe1));
+            }
+            Instruction::F32x4Sqrt => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xe3));
+            }
+            Instruction::F32x4Add => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xe4));
+            }
+            Instruction::F32x4Sub => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xe5));
+            }
+            Instruction::F32x4Mul => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xe6));
+            }
+            Instruction::F32x4Div => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xe7));
+            }
+            Instruction::F32x4Min => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xe8));
+            }
+            Instruction::F32x4Max => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xe9));
+            }
+            Instruction::F32x4PMin => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xea));
+            }
+            Instruction::F32x4PMax => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xeb));
+            }
+            Instruction::F64x2Abs => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xec));
+            }
+            Instruction::F64x2Neg => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xed));
+            }
+            Instruction::F64x2Sqrt => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xef));
+            }
+            Instruction::F64x2Add => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xf0));
+            }
+            Instruction::F64x2Sub => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xf1));
+            }
+            Instruction::F64x2Mul => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xf2));
+            }
+            Instruction::F64x2Div => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xf3));
+            }
+            Instruction::F64x2Min => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xf4));
+            }
+            Instruction::F64x2Max => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xf5));
+            }
+            Instruction::F64x2PMin => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xf6));
+            }
+            Instruction::F64x2PMax => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xf7));
+            }
+            Instruction::I32x4TruncSatF32x4S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xf8));
+            }
+            Instruction::I32x4TruncSatF32x4U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xf9));
+            }
+            Instruction::F32x4ConvertI32x4S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xfa));
+            }
+            Instruction::F32x4ConvertI32x4U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xfb));
+            }
+            Instruction::V128Load32Zero(memarg) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xfc));
+                memarg.encode(bytes);
+            }
+            Instruction::V128Load64Zero(memarg) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xfd));
+                memarg.encode(bytes);
+            }
+
+            // Atomic instructions.
+            Instruction::MemoryAtomicNotify(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x00));
+                memarg.encode(bytes);
+            }
+            Instruction::MemoryAtomicWait32(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x01));
+                memarg.encode(bytes);
+            }
+            Instruction::MemoryAtomicWait64(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x02));
+                memarg.encode(bytes);
+            }
+            Instruction::AtomicFence => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x03));
+                bytes.push(0x00);
+            }
+            Instruction::I32AtomicLoad(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x10));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicLoad(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x11));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicLoad8U(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x12));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicLoad16U(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x13));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicLoad8U(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x14));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicLoad16U(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x15));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicLoad32U(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x16));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicStore(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x17));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicStore(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x18));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicStore8(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x19));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicStore16(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x1a));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicStore8(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x1b));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicStore16(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x1c));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicStore32(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x1d));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicRmwAdd(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x1e));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmwAdd(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x1f));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicRmw8AddU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x20));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicRmw16AddU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x21));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmw8AddU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x22));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmw16AddU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x23));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmw32AddU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x24));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicRmwSub(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x25));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmwSub(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x26));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicRmw8SubU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x27));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicRmw16SubU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x28));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmw8SubU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x29));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmw16SubU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x2a));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmw32SubU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x2b));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicRmwAnd(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x2c));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmwAnd(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x2d));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicRmw8AndU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x2e));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicRmw16AndU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x2f));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmw8AndU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x30));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmw16AndU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x31));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmw32AndU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x32));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicRmwOr(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x33));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmwOr(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x34));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicRmw8OrU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x35));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicRmw16OrU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x36));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmw8OrU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x37));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmw16OrU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x38));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmw32OrU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x39));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicRmwXor(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x3a));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmwXor(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x3b));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicRmw8XorU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x3c));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicRmw16XorU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x3d));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmw8XorU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x3e));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmw16XorU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x3f));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmw32XorU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x40));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicRmwXchg(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x41));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmwXchg(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x42));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicRmw8XchgU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x43));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicRmw16XchgU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x44));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmw8XchgU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x45));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmw16XchgU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x46));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmw32XchgU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x47));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicRmwCmpxchg(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x48));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmwCmpxchg(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x49));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicRmw8CmpxchgU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x4a));
+                memarg.encode(bytes);
+            }
+            Instruction::I32AtomicRmw16CmpxchgU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x4b));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmw8CmpxchgU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x4c));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmw16CmpxchgU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x4d));
+                memarg.encode(bytes);
+            }
+            Instruction::I64AtomicRmw32CmpxchgU(memarg) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x4e));
+                memarg.encode(bytes);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmparser::{FunctionBody, MemoryImmediate, Operator, Type, TypeOrFuncType};
+
+    /// Encodes `instruction` and checks it decodes to exactly one operator,
+    /// which is passed to `check`.
+    fn decode(instruction: Instruction, check: impl FnOnce(Operator)) {
+        // A function body with no locals.
+        let mut bytes = vec![0];
+        instruction.encode(&mut bytes);
+        let body = FunctionBody::new(0, &bytes);
+        let mut reader = body.get_operators_reader().unwrap();
+        check(reader.read().unwrap());
+        assert!(reader.eof(), "{:?} has trailing bytes", instruction);
+    }
+
+    // Alignments are checked against the natural alignment when reading, so
+    // use one which is valid for every instruction.
+    const MEMARG: MemArg = MemArg {
+        offset: 300,
+        align: 0,
+        memory_index: 0,
+    };
+
+    // A memory other than the first, which is flagged in the alignment.
+    const MEMARG1: MemArg = MemArg {
+        offset: 0,
+        align: 0,
+        memory_index: 1,
+    };
+
+    #[test]
+    fn operators_without_immediates() {
+        macro_rules! check {
+            ($($name:ident)*) => {$(
+                decode(Instruction::$name, |op| match op {
+                    Operator::$name => {}
+                    op => panic!("{} decoded as {:?}", stringify!($name), op),
+                });
+            )*};
+        }
+        check! {
+            Unwind
+            I8x16Swizzle
+            I8x16Splat
+            I16x8Splat
+            I32x4Splat
+            I64x2Splat
+            F32x4Splat
+            F64x2Splat
+            I8x16Eq
+            I8x16Ne
+            I8x16LtS
+            I8x16LtU
+            I8x16GtS
+            I8x16GtU
+            I8x16LeS
+            I8x16LeU
+            I8x16GeS
+            I8x16GeU
+            I16x8Eq
+            I16x8Ne
+            I16x8LtS
+            I16x8LtU
+            I16x8GtS
+            I16x8GtU
+            I16x8LeS
+            I16x8LeU
+            I16x8GeS
+            I16x8GeU
+            I32x4Eq
+            I32x4Ne
+            I32x4LtS
+            I32x4LtU
+            I32x4GtS
+            I32x4GtU
+            I32x4LeS
+            I32x4LeU
+            I32x4GeS
+            I32x4GeU
+            F32x4Eq
+            F32x4Ne
+            F32x4Lt
+            F32x4Gt
+            F32x4Le
+            F32x4Ge
+            F64x2Eq
+            F64x2Ne
+            F64x2Lt
+            F64x2Gt
+            F64x2Le
+            F64x2Ge
+            V128Not
+            V128And
+            V128AndNot
+            V128Or
+            V128Xor
+            V128Bitselect
+            I8x16Abs
+            I8x16Neg
+            V128AnyTrue
+            I8x16AllTrue
+            I8x16Bitmask
+            I8x16NarrowI16x8S
+            I8x16NarrowI16x8U
+            I8x16Shl
+            I8x16ShrS
+            I8x16ShrU
+            I8x16Add
+            I8x16AddSatS
+            I8x16AddSatU
+            I8x16Sub
+            I8x16SubSatS
+            I8x16SubSatU
+            I8x16MinS
+            I8x16MinU
+            I8x16MaxS
+            I8x16MaxU
+            I8x16RoundingAverageU
+            I16x8Abs
+            I16x8Neg
+            I16x8AllTrue
+            I16x8Bitmask
+            I16x8NarrowI32x4S
+            I16x8NarrowI32x4U
+            I16x8WidenLowI8x16S
+            I16x8WidenHighI8x16S
+            I16x8WidenLowI8x16U
+            I16x8WidenHighI8x16U
+            I16x8Shl
+            I16x8ShrS
+            I16x8ShrU
+            I16x8Add
+            I16x8AddSatS
+            I16x8AddSatU
+            I16x8Sub
+            I16x8SubSatS
+            I16x8SubSatU
+            I16x8Mul
+            I16x8MinS
+            I16x8MinU
+            I16x8MaxS
+            I16x8MaxU
+            I16x8ExtMulLowI8x16S
+            I16x8RoundingAverageU
+            I16x8Q15MulrSatS
+            I16x8ExtMulHighI8x16S
+            I16x8ExtMulLowI8x16U
+            I16x8ExtMulHighI8x16U
+            I32x4Abs
+            I32x4Neg
+            I32x4AllTrue
+            I32x4Bitmask
+            I32x4WidenLowI16x8S
+            I32x4WidenHighI16x8S
+            I32x4WidenLowI16x8U
+            I32x4WidenHighI16x8U
+            I32x4Shl
+            I32x4ShrS
+            I32x4ShrU
+            I32x4Add
+            I32x4Sub
+            I32x4Mul
+            I32x4MinS
+            I32x4MinU
+            I32x4MaxS
+            I32x4MaxU
+            I32x4DotI16x8S
+            I32x4ExtMulLowI16x8S
+            I32x4ExtMulHighI16x8S
+            I32x4ExtMulLowI16x8U
+            I32x4ExtMulHighI16x8U
+            I64x2Neg
+            I64x2Bitmask
+            I64x2WidenLowI32x4S
+            I64x2WidenHighI32x4S
+            I64x2WidenLowI32x4U
+            I64x2WidenHighI32x4U
+            I64x2Shl
+            I64x2ShrS
+            I64x2ShrU
+            I64x2Add
+            I64x2Sub
+            I64x2ExtMulLowI32x4S
+            I64x2ExtMulHighI32x4S
+            I64x2Mul
+            I64x2ExtMulLowI32x4U
+            I64x2ExtMulHighI32x4U
+            F32x4Ceil
+            F32x4Floor
+            F32x4Trunc
+            F32x4Nearest
+            F64x2Ceil
+            F64x2Floor
+            F64x2Trunc
+            F64x2Nearest
+            F32x4Abs
+            F32x4Neg
+            F32x4Sqrt
+            F32x4Add
+            F32x4Sub
+            F32x4Mul
+            F32x4Div
+            F32x4Min
+            F32x4Max
+            F32x4PMin
+            F32x4PMax
+            F64x2Abs
+            F64x2Neg
+            F64x2Sqrt
+            F64x2Add
+            F64x2Sub
+            F64x2Mul
+            F64x2Div
+            F64x2Min
+            F64x2Max
+            F64x2PMin
+            F64x2PMax
+            I32x4TruncSatF32x4S
+            I32x4TruncSatF32x4U
+            F32x4ConvertI32x4S
+            F32x4ConvertI32x4U
         }
+        decode(Instruction::AtomicFence, |op| match op {
+            Operator::AtomicFence { flags: 0 } => {}
+            op => panic!("decoded as {:?}", op),
+        });
+    }
+
+    #[test]
+    fn memory_operators() {
+        macro_rules! check {
+            ($($name:ident)*) => {$(
+                for memarg in [MEMARG, MEMARG1].iter() {
+                    decode(Instruction::$name(*memarg), |op| match op {
+                        Operator::$name {
+                            memarg: MemoryImmediate { align, offset, memory },
+                        } if align as u32 == memarg.align
+                            && offset == memarg.offset
+                            && memory == memarg.memory_index => {}
+                        op => panic!("{} decoded as {:?}", stringify!($name), op),
+                    });
+                }
+            )*};
+        }
+        check! {
+            V128Load
+            V128Load8x8S
+            V128Load8x8U
+            V128Load16x4S
+            V128Load16x4U
+            V128Load32x2S
+            V128Load32x2U
+            V128Load8Splat
+            V128Load16Splat
+            V128Load32Splat
+            V128Load64Splat
+            V128Store
+            V128Load32Zero
+            V128Load64Zero
+            MemoryAtomicNotify
+            MemoryAtomicWait32
+            MemoryAtomicWait64
+            I32AtomicLoad
+            I64AtomicLoad
+            I32AtomicLoad8U
+            I32AtomicLoad16U
+            I64AtomicLoad8U
+            I64AtomicLoad16U
+            I64AtomicLoad32U
+            I32AtomicStore
+            I64AtomicStore
+            I32AtomicStore8
+            I32AtomicStore16
+            I64AtomicStore8
+            I64AtomicStore16
+            I64AtomicStore32
+            I32AtomicRmwAdd
+            I64AtomicRmwAdd
+            I32AtomicRmw8AddU
+            I32AtomicRmw16AddU
+            I64AtomicRmw8AddU
+            I64AtomicRmw16AddU
+            I64AtomicRmw32AddU
+            I32AtomicRmwSub
+            I64AtomicRmwSub
+            I32AtomicRmw8SubU
+            I32AtomicRmw16SubU
+            I64AtomicRmw8SubU
+            I64AtomicRmw16SubU
+            I64AtomicRmw32SubU
+            I32AtomicRmwAnd
+            I64AtomicRmwAnd
+            I32AtomicRmw8AndU
+            I32AtomicRmw16AndU
+            I64AtomicRmw8AndU
+            I64AtomicRmw16AndU
+            I64AtomicRmw32AndU
+            I32AtomicRmwOr
+            I64AtomicRmwOr
+            I32AtomicRmw8OrU
+            I32AtomicRmw16OrU
+            I64AtomicRmw8OrU
+            I64AtomicRmw16OrU
+            I64AtomicRmw32OrU
+            I32AtomicRmwXor
+            I64AtomicRmwXor
+            I32AtomicRmw8XorU
+            I32AtomicRmw16XorU
+            I64AtomicRmw8XorU
+            I64AtomicRmw16XorU
+            I64AtomicRmw32XorU
+            I32AtomicRmwXchg
+            I64AtomicRmwXchg
+            I32AtomicRmw8XchgU
+            I32AtomicRmw16XchgU
+            I64AtomicRmw8XchgU
+            I64AtomicRmw16XchgU
+            I64AtomicRmw32XchgU
+            I32AtomicRmwCmpxchg
+            I64AtomicRmwCmpxchg
+            I32AtomicRmw8CmpxchgU
+            I32AtomicRmw16CmpxchgU
+            I64AtomicRmw8CmpxchgU
+            I64AtomicRmw16CmpxchgU
+            I64AtomicRmw32CmpxchgU
+        }
+    }
+
+    #[test]
+    fn lane_operators() {
+        macro_rules! check {
+            ($($name:ident)*) => {$(
+                decode(Instruction::$name(1), |op| match op {
+                    Operator::$name { lane: 1 } => {}
+                    op => panic!("{} decoded as {:?}", stringify!($name), op),
+                });
+            )*};
+        }
+        check! {
+            I8x16ExtractLaneS
+            I8x16ExtractLaneU
+            I8x16ReplaceLane
+            I16x8ExtractLaneS
+            I16x8ExtractLaneU
+            I16x8ReplaceLane
+            I32x4ExtractLane
+            I32x4ReplaceLane
+            I64x2ExtractLane
+            I64x2ReplaceLane
+            F32x4ExtractLane
+            F32x4ReplaceLane
+            F64x2ExtractLane
+            F64x2ReplaceLane
+        }
+
+        macro_rules! check_memory {
+            ($($name:ident)*) => {$(
+                decode(Instruction::$name { memarg: MEMARG, lane: 1 }, |op| match op {
+                    Operator::$name {
+                        memarg: MemoryImmediate { align: 0, offset: 300, memory: 0 },
+                        lane: 1,
+                    } => {}
+                    op => panic!("{} decoded as {:?}", stringify!($name), op),
+                });
+            )*};
+        }
+        check_memory! {
+            V128Load8Lane
+            V128Load16Lane
+            V128Load32Lane
+            V128Load64Lane
+            V128Store8Lane
+            V128Store16Lane
+            V128Store32Lane
+            V128Store64Lane
+        }
+
+        let lanes = [0, 17, 2, 19, 4, 21, 6, 23, 8, 25, 10, 27, 12, 29, 14, 31];
+        decode(Instruction::I8x16Shuffle(lanes), |op| match op {
+            Operator::I8x16Shuffle { lanes: l } if l == lanes => {}
+            op => panic!("decoded as {:?}", op),
+        });
+    }
+
+    #[test]
+    fn control_operators() {
+        decode(Instruction::ReturnCall(300), |op| match op {
+            Operator::ReturnCall {
+                function_index: 300,
+            } => {}
+            op => panic!("decoded as {:?}", op),
+        });
+        decode(
+            Instruction::ReturnCallIndirect { ty: 2, table: 1 },
+            |op| match op {
+                Operator::ReturnCallIndirect {
+                    index: 2,
+                    table_index: 1,
+                } => {}
+                op => panic!("decoded as {:?}", op),
+            },
+        );
+        decode(
+            Instruction::Try(BlockType::Result(ValType::I32)),
+            |op| match op {
+                Operator::Try {
+                    ty: TypeOrFuncType::Type(Type::I32),
+                } => {}
+                op => panic!("decoded as {:?}", op),
+            },
+        );
+        decode(Instruction::Catch(3), |op| match op {
+            Operator::Catch { index: 3 } => {}
+            op => panic!("decoded as {:?}", op),
+        });
+        decode(Instruction::Throw(4), |op| match op {
+            Operator::Throw { index: 4 } => {}
+            op => panic!("decoded as {:?}", op),
+        });
+        decode(Instruction::Rethrow(5), |op| match op {
+            Operator::Rethrow { relative_depth: 5 } => {}
+            op => panic!("decoded as {:?}", op),
+        });
     }
 }
@@ -0,0 +1,306 @@
+use super::*;
+
+/// An encoder for the `core` custom section of coredumps, as described by the
+/// [tool conventions].
+///
+/// A coredump is itself a WebAssembly module. The memories and globals of the
+/// crashed instances are the coredump's own memories, whose contents are
+/// given by data segments, and globals. Its custom sections, encoded with
+/// this type, [`CoreDumpModulesSection`], [`CoreDumpInstancesSection`] and
+/// [`CoreDumpStackSection`], describe the process, the instances it was
+/// running and the stack of each thread.
+///
+/// [tool conventions]: https://github.com/WebAssembly/tool-conventions/blob/main/Coredump.md
+///
+/// # Example
+///
+/// ```
+/// use wasm_encoder::{
+///     CoreDumpInstancesSection, CoreDumpModulesSection, CoreDumpSection, CoreDumpStackSection,
+///     CoreDumpValue, DataSection, Instruction, Limits, MemorySection, MemoryType, Module,
+/// };
+///
+/// let mut memories = MemorySection::new();
+/// memories.memory(MemoryType {
+///     limits: Limits { min: 1, max: None },
+/// });
+///
+/// // Only the parts of the memory which aren't zero need to be saved.
+/// let mut data = DataSection::new();
+/// data.active(0, Instruction::I32Const(1024), b"crashed here".iter().copied());
+///
+/// let mut modules = CoreDumpModulesSection::new();
+/// modules.module("app.wasm");
+/// let mut instances = CoreDumpInstancesSection::new();
+/// instances.instance(0, &[0], &[]);
+/// let mut stack = CoreDumpStackSection::new("main");
+/// stack.frame(0, 3, 17, &[CoreDumpValue::I32(1024)], &[CoreDumpValue::Missing]);
+///
+/// let mut module = Module::new();
+/// module
+///     .section(&CoreDumpSection::new("app.wasm"))
+///     .section(&modules)
+///     .section(&instances)
+///     .section(&memories)
+///     .section(&data)
+///     .section(&stack);
+/// let wasm_bytes = module.finish();
+/// # wasmparser::validate(&wasm_bytes).unwrap();
+/// # for payload in wasmparser::Parser::new(0).parse_all(&wasm_bytes) {
+/// #     if let wasmparser::Payload::CustomSection { name: "corestack", data, data_offset } = payload.unwrap() {
+/// #         let stack = wasmparser::CoreDumpStackSection::new(data, data_offset).unwrap();
+/// #         assert_eq!(stack.name, "main");
+/// #         assert_eq!(stack.frames[0].code_offset, 17);
+/// #         assert_eq!(stack.frames[0].locals[..], [wasmparser::CoreDumpValue::I32(1024)]);
+/// #     }
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct CoreDumpSection {
+    bytes: Vec<u8>,
+}
+
+impl CoreDumpSection {
+    /// Create a new `core` section for a crash of the program
+    /// `executable_name`.
+    pub fn new(executable_name: &str) -> CoreDumpSection {
+        let mut bytes = vec![0];
+        bytes.extend(encoders::str(executable_name));
+        CoreDumpSection { bytes }
+    }
+}
+
+impl Section for CoreDumpSection {
+    fn id(&self) -> u8 {
+        SectionId::Custom.into()
+    }
+
+    fn encode<S>(&self, sink: &mut S)
+    where
+        S: Extend<u8>,
+    {
+        CustomSection {
+            name: "core",
+            data: &self.bytes,
+        }
+        .encode(sink);
+    }
+}
+
+/// An encoder for the `coremodules` custom section of coredumps, listing the
+/// modules of the crashed process.
+///
+/// See [`CoreDumpSection`] for an example.
+#[derive(Clone, Debug, Default)]
+pub struct CoreDumpModulesSection {
+    bytes: Vec<u8>,
+    num_added: u32,
+}
+
+impl CoreDumpModulesSection {
+    /// Create a new `coremodules` section encoder.
+    pub fn new() -> CoreDumpModulesSection {
+        CoreDumpModulesSection {
+            bytes: vec![],
+            num_added: 0,
+        }
+    }
+
+    /// Define a module with the given name, which instances refer to by its
+    /// index in this section.
+    pub fn module(&mut self, name: &str) -> &mut Self {
+        self.bytes.push(0);
+        self.bytes.extend(encoders::str(name));
+        self.num_added += 1;
+        self
+    }
+}
+
+impl Section for CoreDumpModulesSection {
+    fn id(&self) -> u8 {
+        SectionId::Custom.into()
+    }
+
+    fn encode<S>(&self, sink: &mut S)
+    where
+        S: Extend<u8>,
+    {
+        encode_vec_section("coremodules", self.num_added, &self.bytes, sink);
+    }
+}
+
+/// An encoder for the `coreinstances` custom section of coredumps, listing
+/// the instances of the crashed process.
+///
+/// See [`CoreDumpSection`] for an example.
+#[derive(Clone, Debug, Default)]
+pub struct CoreDumpInstancesSection {
+    bytes: Vec<u8>,
+    num_added: u32,
+}
+
+impl CoreDumpInstancesSection {
+    /// Create a new `coreinstances` section encoder.
+    pub fn new() -> CoreDumpInstancesSection {
+        CoreDumpInstancesSection {
+            bytes: vec![],
+            num_added: 0,
+        }
+    }
+
+    /// Define an instance of the module `module_index` in the `coremodules`
+    /// section, which stack frames refer to by its index in this section.
+    ///
+    /// `memories` and `globals` give, for each memory and global of the
+    /// instance, the index of the coredump's own memory or global which holds
+    /// its contents.
+    pub fn instance(&mut self, module_index: u32, memories: &[u32], globals: &[u32]) -> &mut Self {
+        self.bytes.push(0);
+        self.bytes.extend(encoders::u32(module_index));
+        for indices in &[memories, globals] {
+            self.bytes
+                .extend(encoders::u32(u32::try_from(indices.len()).unwrap()));
+            for index in indices.iter() {
+                self.bytes.extend(encoders::u32(*index));
+            }
+        }
+        self.num_added += 1;
+        self
+    }
+}
+
+impl Section for CoreDumpInstancesSection {
+    fn id(&self) -> u8 {
+        SectionId::Custom.into()
+    }
+
+    fn encode<S>(&self, sink: &mut S)
+    where
+        S: Extend<u8>,
+    {
+        encode_vec_section("coreinstances", self.num_added, &self.bytes, sink);
+    }
+}
+
+/// An encoder for a `corestack` custom section of coredumps, holding the
+/// stack of one thread of the crashed process.
+///
+/// See [`CoreDumpSection`] for an example.
+#[derive(Clone, Debug)]
+pub struct CoreDumpStackSection {
+    name: Vec<u8>,
+    frames: Vec<u8>,
+    num_added: u32,
+}
+
+impl CoreDumpStackSection {
+    /// Create a new `corestack` section encoder for the thread `name`.
+    pub fn new(name: &str) -> CoreDumpStackSection {
+        CoreDumpStackSection {
+            name: encoders::str(name).collect(),
+            frames: vec![],
+            num_added: 0,
+        }
+    }
+
+    /// Add a frame to the stack, starting with the innermost frame.
+    ///
+    /// The frame runs the function `func_index` of the instance
+    /// `instance_index` in the `coreinstances` section, and is executing the
+    /// instruction at `code_offset` bytes from the start of the function's
+    /// body. `locals` are the values of its locals and `stack` the values on
+    /// its operand stack, from the bottom up.
+    pub fn frame(
+        &mut self,
+        instance_index: u32,
+        func_index: u32,
+        code_offset: u32,
+        locals: &[CoreDumpValue],
+        stack: &[CoreDumpValue],
+    ) -> &mut Self {
+        self.frames.push(0);
+        self.frames.extend(encoders::u32(instance_index));
+        self.frames.extend(encoders::u32(func_index));
+        self.frames.extend(encoders::u32(code_offset));
+        for values in &[locals, stack] {
+            self.frames
+                .extend(encoders::u32(u32::try_from(values.len()).unwrap()));
+            for value in values.iter() {
+                value.encode(&mut self.frames);
+            }
+        }
+        self.num_added += 1;
+        self
+    }
+}
+
+impl Section for CoreDumpStackSection {
+    fn id(&self) -> u8 {
+        SectionId::Custom.into()
+    }
+
+    fn encode<S>(&self, sink: &mut S)
+    where
+        S: Extend<u8>,
+    {
+        let mut data = vec![0];
+        data.extend(self.name.iter().copied());
+        data.extend(encoders::u32(self.num_added));
+        data.extend(self.frames.iter().copied());
+        CustomSection {
+            name: "corestack",
+            data: &data,
+        }
+        .encode(sink);
+    }
+}
+
+/// A value of a local or on the operand stack of a frame, see
+/// [`CoreDumpStackSection::frame`].
+#[derive(Clone, Copy, Debug)]
+pub enum CoreDumpValue {
+    /// A value which wasn't recorded, for example because it was optimized
+    /// away.
+    Missing,
+    /// An `i32` value.
+    I32(i32),
+    /// An `i64` value.
+    I64(i64),
+    /// An `f32` value.
+    F32(f32),
+    /// An `f64` value.
+    F64(f64),
+}
+
+impl CoreDumpValue {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        match *self {
+            CoreDumpValue::Missing => bytes.push(0x01),
+            CoreDumpValue::I32(x) => {
+                bytes.push(0x7f);
+                bytes.extend(encoders::s32(x));
+            }
+            CoreDumpValue::I64(x) => {
+                bytes.push(0x7e);
+                bytes.extend(encoders::s64(x));
+            }
+            CoreDumpValue::F32(x) => {
+                bytes.push(0x7d);
+                bytes.extend(x.to_bits().to_le_bytes().iter().copied());
+            }
+            CoreDumpValue::F64(x) => {
+                bytes.push(0x7c);
+                bytes.extend(x.to_bits().to_le_bytes().iter().copied());
+            }
+        }
+    }
+}
+
+fn encode_vec_section<S>(name: &str, count: u32, bytes: &[u8], sink: &mut S)
+where
+    S: Extend<u8>,
+{
+    let mut data: Vec<u8> = encoders::u32(count).collect();
+    data.extend(bytes.iter().copied());
+    CustomSection { name, data: &data }.encode(sink);
+}
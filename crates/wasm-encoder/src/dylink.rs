@@ -0,0 +1,153 @@
+use super::*;
+
+/// An encoder for the `dylink.0` custom section of dynamic libraries, as
+/// described by the [tool conventions].
+///
+/// The section must be the first section of the module, before even the type
+/// section.
+///
+/// [tool conventions]: https://github.com/WebAssembly/tool-conventions/blob/master/DynamicLinking.md
+///
+/// # Example
+///
+/// ```
+/// use wasm_encoder::{DylinkSection, MemInfo, Module};
+///
+/// let mut dylink = DylinkSection::new();
+/// dylink
+///     .mem_info(MemInfo {
+///         memory_size: 16,
+///         memory_alignment: 2,
+///         table_size: 1,
+///         table_alignment: 0,
+///     })
+///     .needed(&["libc.so"]);
+///
+/// let mut module = Module::new();
+/// module.section(&dylink);
+/// let wasm_bytes = module.finish();
+///
+/// // Read the section back.
+/// for payload in wasmparser::Parser::new(0).parse_all(&wasm_bytes) {
+///     if let wasmparser::Payload::CustomSection { name: "dylink.0", data, data_offset } =
+///         payload.unwrap()
+///     {
+///         let reader = wasmparser::DylinkSectionReader::new(data, data_offset).unwrap();
+///         let subsections = reader.into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+///         assert_eq!(subsections.len(), 2);
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct DylinkSection {
+    bytes: Vec<u8>,
+}
+
+impl DylinkSection {
+    /// Create a new `dylink.0` section encoder.
+    pub fn new() -> DylinkSection {
+        DylinkSection { bytes: vec![] }
+    }
+
+    /// Define the memory and table space the library needs.
+    pub fn mem_info(&mut self, info: MemInfo) -> &mut Self {
+        let mut bytes = vec![];
+        bytes.extend(encoders::u32(info.memory_size));
+        bytes.extend(encoders::u32(info.memory_alignment));
+        bytes.extend(encoders::u32(info.table_size));
+        bytes.extend(encoders::u32(info.table_alignment));
+        self.subsection(1, &bytes)
+    }
+
+    /// Define the names of the libraries this library depends on.
+    pub fn needed(&mut self, libraries: &[&str]) -> &mut Self {
+        let mut bytes = vec![];
+        bytes.extend(encoders::u32(u32::try_from(libraries.len()).unwrap()));
+        for library in libraries {
+            bytes.extend(encoders::str(library));
+        }
+        self.subsection(2, &bytes)
+    }
+
+    /// Define the symbol flags of exports.
+    pub fn export_info(&mut self, exports: &[ExportInfo<'_>]) -> &mut Self {
+        let mut bytes = vec![];
+        bytes.extend(encoders::u32(u32::try_from(exports.len()).unwrap()));
+        for export in exports {
+            bytes.extend(encoders::str(export.name));
+            bytes.extend(encoders::u32(export.flags));
+        }
+        self.subsection(3, &bytes)
+    }
+
+    /// Define the symbol flags of imports.
+    pub fn import_info(&mut self, imports: &[ImportInfo<'_>]) -> &mut Self {
+        let mut bytes = vec![];
+        bytes.extend(encoders::u32(u32::try_from(imports.len()).unwrap()));
+        for import in imports {
+            bytes.extend(encoders::str(import.module));
+            bytes.extend(encoders::str(import.field));
+            bytes.extend(encoders::u32(import.flags));
+        }
+        self.subsection(4, &bytes)
+    }
+
+    fn subsection(&mut self, id: u8, bytes: &[u8]) -> &mut Self {
+        self.bytes.push(id);
+        self.bytes
+            .extend(encoders::u32(u32::try_from(bytes.len()).unwrap()));
+        self.bytes.extend(bytes.iter().copied());
+        self
+    }
+}
+
+impl Section for DylinkSection {
+    fn id(&self) -> u8 {
+        SectionId::Custom.into()
+    }
+
+    fn encode<S>(&self, sink: &mut S)
+    where
+        S: Extend<u8>,
+    {
+        CustomSection {
+            name: "dylink.0",
+            data: &self.bytes,
+        }
+        .encode(sink);
+    }
+}
+
+/// The memory and table requirements of a dynamic library, see
+/// [`DylinkSection::mem_info`].
+#[derive(Clone, Copy, Debug)]
+pub struct MemInfo {
+    /// The size of the library's static data, in bytes.
+    pub memory_size: u32,
+    /// The alignment of the library's static data, as a power of two.
+    pub memory_alignment: u32,
+    /// The number of table slots the library needs.
+    pub table_size: u32,
+    /// The alignment of the library's table slots, as a power of two.
+    pub table_alignment: u32,
+}
+
+/// The symbol flags of an export, see [`DylinkSection::export_info`].
+#[derive(Clone, Copy, Debug)]
+pub struct ExportInfo<'a> {
+    /// The name of the export.
+    pub name: &'a str,
+    /// The symbol flags of the export, such as [`SymbolTable::BINDING_WEAK`].
+    pub flags: u32,
+}
+
+/// The symbol flags of an import, see [`DylinkSection::import_info`].
+#[derive(Clone, Copy, Debug)]
+pub struct ImportInfo<'a> {
+    /// The module name of the import.
+    pub module: &'a str,
+    /// The field name of the import.
+    pub field: &'a str,
+    /// The symbol flags of the import, such as [`SymbolTable::BINDING_WEAK`].
+    pub flags: u32,
+}
@@ -3,19 +3,19 @@
 //! This module provides low-level encoders that can be used (for example) to
 //! define your own custom section encodings.
 
-use std::convert::TryFrom;
+use core::convert::TryFrom;
 
 /// Encode a `u32` as a ULEB128.
 pub fn u32(n: u32) -> impl ExactSizeIterator<Item = u8> {
     let mut buf = [0; 5];
-    let n = leb128::write::unsigned(&mut &mut buf[..], n.into()).unwrap();
+    let n = write_unsigned(&mut buf, n.into());
     Buf5Iter { buf, range: 0..n }
 }
 
 /// Encode an `i32` as a SLEB128.
 pub fn s32(x: i32) -> impl ExactSizeIterator<Item = u8> {
     let mut buf = [0; 5];
-    let n = leb128::write::signed(&mut &mut buf[..], x.into()).unwrap();
+    let n = write_signed(&mut buf, x.into());
     Buf5Iter { buf, range: 0..n }
 }
 
@@ -46,14 +46,14 @@ pub fn s33(x: i64) -> impl ExactSizeIterator<Item = u8> {
         x != mask && (x & mask == 0) == (x >= 0)
     });
     let mut buf = [0; 5];
-    let n = leb128::write::signed(&mut &mut buf[..], x).unwrap();
+    let n = write_signed(&mut buf, x);
     Buf5Iter { buf, range: 0..n }
 }
 
 /// Encode an `i64` as a SLEB128.
 pub fn s64(x: i64) -> impl ExactSizeIterator<Item = u8> {
     let mut buf = [0; 10];
-    let n = leb128::write::signed(&mut &mut buf[..], x).unwrap();
+    let n = write_signed(&mut buf, x);
     Buf10Iter { buf, range: 0..n }
 }
 
@@ -62,11 +62,43 @@ pub fn str<'a>(s: &'a str) -> impl Iterator<Item = u8> + 'a {
     u32(u32::try_from(s.len()).unwrap()).chain(s.as_bytes().iter().copied())
 }
 
+// Writes `n` as a ULEB128 to the start of `buf`, returning the number of bytes
+// written. The caller must provide a buffer large enough for `n`.
+fn write_unsigned(buf: &mut [u8], mut n: u64) -> usize {
+    let mut i = 0;
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            buf[i] = byte;
+            return i + 1;
+        }
+        buf[i] = byte | 0x80;
+        i += 1;
+    }
+}
+
+// Same as `write_unsigned`, but as a SLEB128.
+fn write_signed(buf: &mut [u8], mut x: i64) -> usize {
+    let mut i = 0;
+    loop {
+        let byte = (x & 0x7f) as u8;
+        x >>= 7;
+        let sign_bit = byte & 0x40 != 0;
+        if (x == 0 && !sign_bit) || (x == -1 && sign_bit) {
+            buf[i] = byte;
+            return i + 1;
+        }
+        buf[i] = byte | 0x80;
+        i += 1;
+    }
+}
+
 // Fixed size arrays don't have `into_iter()` so we can't simply do
 // `[..].into_iter().take(n)` :(
 struct Buf5Iter {
     buf: [u8; 5],
-    range: std::ops::Range<usize>,
+    range: core::ops::Range<usize>,
 }
 
 impl Iterator for Buf5Iter {
@@ -86,7 +118,7 @@ impl ExactSizeIterator for Buf5Iter {}
 
 struct Buf10Iter {
     buf: [u8; 10],
-    range: std::ops::Range<usize>,
+    range: core::ops::Range<usize>,
 }
 
 impl Iterator for Buf10Iter {
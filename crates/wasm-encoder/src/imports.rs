@@ -1,5 +1,5 @@
 use super::*;
-use std::convert::TryFrom;
+use core::convert::TryFrom;
 
 /// An encoder for the import section.
 ///
@@ -67,49 +67,105 @@
 //! // We generated a valid Wasm module!
 //! assert!(wasmparser::validate(&wasm_bytes).is_ok());
 //! ```
+//!
+//! This crate is `#![no_std]` and only requires the `alloc` crate.
 
 #![deny(missing_docs)]
+#![no_std]
+
+extern crate alloc;
 
 mod aliases;
 mod code;
+mod core_dump;
 mod custom;
 mod data;
+mod dylink;
 mod elements;
 mod exports;
 mod functions;
 mod globals;
 mod imports;
 mod instances;
+mod linking;
 mod memories;
 mod modules;
+mod raw;
 mod start;
 mod tables;
 mod types;
 
 pub use aliases::*;
 pub use code::*;
+pub use core_dump::*;
 pub use custom::*;
 pub use data::*;
+pub use dylink::*;
 pub use elements::*;
 pub use exports::*;
 pub use functions::*;
 pub use globals::*;
 pub use imports::*;
 pub use instances::*;
+pub use linking::*;
 pub use memories::*;
 pub use modules::*;
+pub use raw::*;
 pub use start::*;
 pub use tables::*;
 pub use types::*;
 
 pub mod encoders;
 
-use std::convert::TryFrom;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
 
 /// A Wasm module that is being encoded.
 #[derive(Clone, Debug)]
 pub struct Module {
     bytes: Vec<u8>,
+    /// Where each known section added so far is in `bytes`, in order.
+    known: Vec<Placed>,
+    /// The offset in `bytes` at which sections added with `section` are
+    /// written, which is before any custom sections placed last.
+    tail: usize,
+    /// Custom sections placed relative to known sections which haven't been
+    /// added yet, nor any section which follows them.
+    pending: Vec<(CustomPlacement, Vec<u8>)>,
+}
+
+/// The offsets in `Module::bytes` of a known section and of the custom sections
+/// placed just before and after it.
+#[derive(Clone, Debug)]
+struct Placed {
+    id: u8,
+    /// The start of the custom sections placed before this section.
+    before: usize,
+    /// The key from `pending_rank` and length of each custom section placed
+    /// next to a section which the module doesn't have, in order, which are
+    /// the first of those placed before this section.
+    orphans: Vec<((usize, u8), usize)>,
+    /// The start of this section.
+    start: usize,
+    /// The end of this section and the custom sections placed after it.
+    end: usize,
+}
+
+/// Where [`Module::custom_section`] places a custom section relative to the
+/// known sections of the module.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CustomPlacement {
+    /// Before all known sections.
+    First,
+    /// Just before the first section with this id, or where it would be if
+    /// the module doesn't have one.
+    Before(SectionId),
+    /// Just after the last section with this id, or where it would be if the
+    /// module doesn't have one.
+    After(SectionId),
+    /// After all other sections.
+    Last,
 }
 
 /// A WebAssembly section.
@@ -131,7 +187,10 @@ pub trait Section {
 
 /// A section made up of uninterpreted, raw bytes.
 ///
-/// Allows you to splat any data into a Wasm section.
+/// Allows you to splat any data into a Wasm section. The sections of an
+/// already encoded module can be found with [`RawSections`] to copy them
+/// into a new module unchanged.
+#[derive(Clone, Copy, Debug)]
 pub struct RawSection<'a> {
     /// The id for this section.
     pub id: u8,
@@ -165,6 +224,9 @@ impl Module {
                 // Version
                 0x01, 0x00, 0x00, 0x00,
             ],
+            known: Vec::new(),
+            tail: 8,
+            pending: Vec::new(),
         }
     }
 
@@ -176,12 +238,142 @@ impl Module {
     /// only defined once. While this is a potential footgun, it also allows you
     /// to use this crate to easily construct test cases for bad Wasm module
     /// encodings.
+    ///
+    /// Sections are written in the order they're added, except that custom
+    /// sections placed with [`Module::custom_section`] are kept in their
+    /// places.
     pub fn section(&mut self, section: &impl Section) -> &mut Self {
-        self.bytes.push(section.id());
-        section.encode(&mut self.bytes);
+        let id = section.id();
+        let mut encoded = vec![id];
+        section.encode(&mut encoded);
+        if id == u8::from(SectionId::Custom) {
+            self.insert_at_tail(encoded);
+            return self;
+        }
+
+        // Custom sections placed next to sections which the module doesn't
+        // have are written where those sections would have been.
+        let (mut due, pending) = self
+            .pending
+            .drain(..)
+            .partition::<Vec<_>, _>(|(placement, _)| pending_rank(*placement) < (rank(id), 1));
+        self.pending = pending;
+        due.sort_by_key(|(placement, _)| pending_rank(*placement));
+        let before = self.tail;
+        let mut orphans = Vec::new();
+        for (placement, encoded) in due {
+            orphans.push((pending_rank(placement), encoded.len()));
+            self.insert_at_tail(encoded);
+        }
+
+        let start = self.tail;
+        self.insert_at_tail(encoded);
+        self.known.push(Placed {
+            id,
+            before,
+            orphans,
+            start,
+            end: self.tail,
+        });
+
+        let mut i = 0;
+        while i < self.pending.len() {
+            match self.pending[i].0 {
+                CustomPlacement::After(anchor) if u8::from(anchor) == id => {
+                    let (placement, encoded) = self.pending.remove(i);
+                    self.place(placement, encoded);
+                }
+                _ => i += 1,
+            }
+        }
+        self
+    }
+
+    /// Write a custom section into this module at `placement` relative to its
+    /// known sections.
+    ///
+    /// Custom sections with the same placement are written in the order
+    /// they're added. Sections can be added in any order relative to the
+    /// sections they're placed next to, so placing each custom section of a
+    /// decoded module after the known section it followed, or first if none,
+    /// preserves its original position when the module is re-encoded.
+    ///
+    /// Custom sections placed relative to a known section which hasn't been
+    /// added yet, nor any section which follows it, are only written once such
+    /// a section is added or the module is finished, so they aren't included
+    /// in [`Module::as_slice`] until then.
+    pub fn custom_section(
+        &mut self,
+        section: &CustomSection,
+        placement: CustomPlacement,
+    ) -> &mut Self {
+        let mut encoded = vec![section.id()];
+        section.encode(&mut encoded);
+        self.place(placement, encoded);
         self
     }
 
+    fn place(&mut self, placement: CustomPlacement, encoded: Vec<u8>) {
+        let len = encoded.len();
+        let known = &self.known;
+        let (pos, i) = match placement {
+            CustomPlacement::First if known.is_empty() => return self.insert_at_tail(encoded),
+            CustomPlacement::First => (known[0].before, None),
+            CustomPlacement::Last => return self.bytes.extend(encoded),
+            CustomPlacement::Before(id) => match known.iter().position(|p| p.id == id as u8) {
+                Some(i) => (known[i].start, Some(i)),
+                None => return self.place_orphan(placement, id, encoded),
+            },
+            CustomPlacement::After(id) => match known.iter().rposition(|p| p.id == id as u8) {
+                Some(i) => (known[i].end, Some(i)),
+                None => return self.place_orphan(placement, id, encoded),
+            },
+        };
+        if let Some(i) = i {
+            let placed = &mut self.known[i];
+            if pos == placed.start {
+                placed.start += len;
+            }
+            placed.end += len;
+        }
+        self.insert(pos, i.map_or(0, |i| i + 1), encoded);
+    }
+
+    /// Places a custom section next to the section `id`, which the module
+    /// doesn't have, just before the first section which would follow it.
+    fn place_orphan(&mut self, placement: CustomPlacement, id: SectionId, encoded: Vec<u8>) {
+        let anchor = rank(id.into());
+        let i = match self.known.iter().position(|p| rank(p.id) > anchor) {
+            Some(i) => i,
+            None => return self.pending.push((placement, encoded)),
+        };
+        let placed = &mut self.known[i];
+        let key = pending_rank(placement);
+        let n = placed.orphans.iter().take_while(|o| o.0 <= key).count();
+        let pos = placed.before + placed.orphans[..n].iter().map(|o| o.1).sum::<usize>();
+        placed.orphans.insert(n, (key, encoded.len()));
+        placed.start += encoded.len();
+        placed.end += encoded.len();
+        self.insert(pos, i + 1, encoded);
+    }
+
+    /// Inserts `encoded` at `pos`, which is before the known sections from
+    /// `later` onwards.
+    fn insert(&mut self, pos: usize, later: usize, encoded: Vec<u8>) {
+        let len = encoded.len();
+        self.bytes.splice(pos..pos, encoded);
+        self.tail += len;
+        for placed in self.known[later..].iter_mut() {
+            placed.before += len;
+            placed.start += len;
+            placed.end += len;
+        }
+    }
+
+    fn insert_at_tail(&mut self, encoded: Vec<u8>) {
+        self.insert(self.tail, self.known.len(), encoded);
+    }
+
     /// Get the encoded Wasm module as a slice.
     pub fn as_slice(&self) -> &[u8] {
         &self.bytes
@@ -189,11 +381,33 @@ impl Module {
 
     /// Finish writing this Wasm module and extract ownership of the encoded
     /// bytes.
-    pub fn finish(self) -> Vec<u8> {
+    pub fn finish(mut self) -> Vec<u8> {
+        let mut pending = core::mem::take(&mut self.pending);
+        pending.sort_by_key(|(placement, _)| pending_rank(*placement));
+        for (_, encoded) in pending {
+            self.insert_at_tail(encoded);
+        }
         self.bytes
     }
 }
 
+/// Returns the key which custom sections waiting for the section they're
+/// placed next to are written in the order of.
+fn pending_rank(placement: CustomPlacement) -> (usize, u8) {
+    match placement {
+        CustomPlacement::Before(id) => (rank(id.into()), 0),
+        CustomPlacement::After(id) => (rank(id.into()), 1),
+        CustomPlacement::First | CustomPlacement::Last => unreachable!(),
+    }
+}
+
+/// Returns the position of the section `id` in the order which sections must
+/// appear in, with unknown sections last.
+fn rank(id: u8) -> usize {
+    const ORDER: &[u8] = &[1, 2, 14, 15, 16, 3, 4, 5, 13, 6, 7, 8, 9, 12, 10, 11];
+    ORDER.iter().position(|i| *i == id).unwrap_or(ORDER.len())
+}
+
 /// Known section IDs.
 ///
 /// Useful for implementing the `Section` trait, or for setting
@@ -227,6 +441,33 @@ impl From<SectionId> for u8 {
     }
 }
 
+impl TryFrom<u8> for SectionId {
+    type Error = u8;
+
+    /// Returns the known section with the id `id`, or `id` if there isn't one.
+    fn try_from(id: u8) -> Result<SectionId, u8> {
+        Ok(match id {
+            0 => SectionId::Custom,
+            1 => SectionId::Type,
+            2 => SectionId::Import,
+            3 => SectionId::Function,
+            4 => SectionId::Table,
+            5 => SectionId::Memory,
+            6 => SectionId::Global,
+            7 => SectionId::Export,
+            8 => SectionId::Start,
+            9 => SectionId::Element,
+            10 => SectionId::Code,
+            11 => SectionId::Data,
+            12 => SectionId::DataCount,
+            14 => SectionId::Module,
+            15 => SectionId::Instance,
+            16 => SectionId::Alias,
+            _ => return Err(id),
+        })
+    }
+}
+
 /// Limits for a table or memory.
 pub struct Limits {
     /// The minimum size.
@@ -281,3 +522,59 @@ impl From<ValType> for u8 {
         t as u8
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_section_placement() {
+        let custom = |name| CustomSection { name, data: &[] };
+        let mut types = TypeSection::new();
+        types.function(vec![], vec![]);
+        let mut functions = FunctionSection::new();
+        functions.function(0);
+        let mut code = CodeSection::new();
+        let mut body = Function::new(vec![]);
+        body.instruction(Instruction::End);
+        code.function(&body);
+
+        let mut module = Module::new();
+        module.custom_section(&custom("a"), CustomPlacement::Last);
+        module.custom_section(&custom("b"), CustomPlacement::After(SectionId::Type));
+        module.section(&types);
+        module.custom_section(&custom("c"), CustomPlacement::First);
+        module.custom_section(&custom("d"), CustomPlacement::Before(SectionId::Code));
+        module.section(&custom("e"));
+        module.section(&functions);
+        module.custom_section(&custom("f"), CustomPlacement::After(SectionId::Export));
+        module.section(&code);
+        module.custom_section(&custom("g"), CustomPlacement::Before(SectionId::Type));
+        module.custom_section(&custom("h"), CustomPlacement::After(SectionId::Function));
+        module.custom_section(&custom("i"), CustomPlacement::Before(SectionId::Data));
+        module.custom_section(&custom("j"), CustomPlacement::Before(SectionId::Global));
+        let wasm = module.finish();
+        assert!(wasmparser::validate(&wasm).is_ok());
+
+        let mut order = Vec::new();
+        let mut reader = wasmparser::BinaryReader::new(&wasm[8..]);
+        while !reader.eof() {
+            let id = reader.read_u8().unwrap() as u8;
+            let len = reader.read_var_u32().unwrap() as usize;
+            let contents = reader.read_bytes(len).unwrap();
+            order.push(match SectionId::try_from(id) {
+                Ok(SectionId::Custom) => wasmparser::BinaryReader::new(contents)
+                    .read_string()
+                    .unwrap(),
+                Ok(SectionId::Type) => "Type",
+                Ok(SectionId::Function) => "Function",
+                Ok(SectionId::Code) => "Code",
+                _ => panic!("unexpected section {}", id),
+            });
+        }
+        assert_eq!(
+            order,
+            ["c", "g", "Type", "b", "e", "Function", "h", "j", "f", "d", "Code", "i", "a"]
+        );
+    }
+}
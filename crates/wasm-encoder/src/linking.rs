@@ -0,0 +1,484 @@
+use super::*;
+
+/// The version of the `linking` section which is encoded.
+const VERSION: u32 = 2;
+
+/// An encoder for the `linking` custom section of relocatable object files, as
+/// described by the [tool conventions].
+///
+/// Relocations are encoded separately with a [`RelocSection`] for each section
+/// they apply to.
+///
+/// [tool conventions]: https://github.com/WebAssembly/tool-conventions/blob/master/Linking.md
+///
+/// # Example
+///
+/// ```
+/// use wasm_encoder::{
+///     CodeSection, Function, FunctionSection, Instruction, LinkingSection, Module,
+///     RelocSection, RelocType, SymbolTable, TypeSection,
+/// };
+///
+/// let mut types = TypeSection::new();
+/// types.function(vec![], vec![]);
+/// let mut functions = FunctionSection::new();
+/// functions.function(0).function(0);
+///
+/// // The call to the second function is padded to five bytes so the linker
+/// // can patch any index in.
+/// let mut codes = CodeSection::new();
+/// let mut f = Function::new(vec![]);
+/// f.raw(vec![0x10, 0x81, 0x80, 0x80, 0x80, 0x00]);
+/// f.instruction(Instruction::End);
+/// codes.function(&f);
+/// let mut g = Function::new(vec![]);
+/// g.instruction(Instruction::End);
+/// codes.function(&g);
+///
+/// let mut symbols = SymbolTable::new();
+/// symbols
+///     .function(0, 0, Some("f"))
+///     .function(SymbolTable::BINDING_LOCAL, 1, Some("g"));
+/// let mut linking = LinkingSection::new();
+/// linking.symbol_table(&symbols);
+///
+/// // The code section is the third section of the module, and the call's
+/// // index is at offset 4 of it.
+/// let mut relocs = RelocSection::new("reloc.CODE", 2);
+/// relocs.entry(RelocType::FunctionIndexLEB, 4, 1, None);
+///
+/// let mut module = Module::new();
+/// module
+///     .section(&types)
+///     .section(&functions)
+///     .section(&codes)
+///     .section(&linking)
+///     .section(&relocs);
+/// let wasm_bytes = module.finish();
+///
+/// // Read the sections back.
+/// for payload in wasmparser::Parser::new(0).parse_all(&wasm_bytes) {
+///     match payload.unwrap() {
+///         wasmparser::Payload::CustomSection { name: "linking", data, data_offset } => {
+///             let mut reader = wasmparser::LinkingSectionReader::new(data, data_offset).unwrap();
+///             match reader.read().unwrap() {
+///                 wasmparser::Linking::SymbolTable(symbols) => assert_eq!(symbols.len(), 2),
+///                 other => panic!("unexpected subsection {:?}", other),
+///             }
+///         }
+///         wasmparser::Payload::CustomSection { name: "reloc.CODE", data, data_offset } => {
+///             let mut reader = wasmparser::RelocSectionReader::new(data, data_offset).unwrap();
+///             assert_eq!(reader.get_section_index(), 2);
+///             assert_eq!(reader.read().unwrap().offset, 4);
+///         }
+///         _ => {}
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct LinkingSection {
+    bytes: Vec<u8>,
+}
+
+impl LinkingSection {
+    /// Create a new `linking` section encoder.
+    pub fn new() -> LinkingSection {
+        LinkingSection { bytes: vec![] }
+    }
+
+    /// Define the metadata of the data segments, in the order they're defined.
+    pub fn segment_info(&mut self, segments: &[SegmentInfo<'_>]) -> &mut Self {
+        let mut bytes = vec![];
+        bytes.extend(encoders::u32(u32::try_from(segments.len()).unwrap()));
+        for segment in segments {
+            bytes.extend(encoders::str(segment.name));
+            bytes.extend(encoders::u32(segment.alignment));
+            bytes.extend(encoders::u32(segment.flags));
+        }
+        self.subsection(5, &bytes)
+    }
+
+    /// Define the functions to call when the module is initialized.
+    pub fn init_funcs(&mut self, funcs: &[InitFunc]) -> &mut Self {
+        let mut bytes = vec![];
+        bytes.extend(encoders::u32(u32::try_from(funcs.len()).unwrap()));
+        for func in funcs {
+            bytes.extend(encoders::u32(func.priority));
+            bytes.extend(encoders::u32(func.symbol_index));
+        }
+        self.subsection(6, &bytes)
+    }
+
+    /// Define the groups of symbols of which only one copy is kept when
+    /// linking.
+    pub fn comdat_info(&mut self, comdats: &[Comdat<'_>]) -> &mut Self {
+        let mut bytes = vec![];
+        bytes.extend(encoders::u32(u32::try_from(comdats.len()).unwrap()));
+        for comdat in comdats {
+            bytes.extend(encoders::str(comdat.name));
+            bytes.extend(encoders::u32(comdat.flags));
+            bytes.extend(encoders::u32(u32::try_from(comdat.symbols.len()).unwrap()));
+            for symbol in comdat.symbols {
+                bytes.push(symbol.kind as u8);
+                bytes.extend(encoders::u32(symbol.index));
+            }
+        }
+        self.subsection(7, &bytes)
+    }
+
+    /// Define the symbol table.
+    pub fn symbol_table(&mut self, symbols: &SymbolTable) -> &mut Self {
+        let mut bytes = vec![];
+        bytes.extend(encoders::u32(symbols.num_added));
+        bytes.extend(symbols.bytes.iter().copied());
+        self.subsection(8, &bytes)
+    }
+
+    fn subsection(&mut self, id: u8, bytes: &[u8]) -> &mut Self {
+        self.bytes.push(id);
+        self.bytes
+            .extend(encoders::u32(u32::try_from(bytes.len()).unwrap()));
+        self.bytes.extend(bytes.iter().copied());
+        self
+    }
+}
+
+impl Section for LinkingSection {
+    fn id(&self) -> u8 {
+        SectionId::Custom.into()
+    }
+
+    fn encode<S>(&self, sink: &mut S)
+    where
+        S: Extend<u8>,
+    {
+        let mut data = encoders::u32(VERSION).collect::<Vec<_>>();
+        data.extend(self.bytes.iter().copied());
+        CustomSection {
+            name: "linking",
+            data: &data,
+        }
+        .encode(sink);
+    }
+}
+
+/// Metadata of a data segment, see [`LinkingSection::segment_info`].
+#[derive(Clone, Copy, Debug)]
+pub struct SegmentInfo<'a> {
+    /// The name of the segment.
+    pub name: &'a str,
+    /// The alignment of the segment, as a power of two.
+    pub alignment: u32,
+    /// The `WASM_SEG_FLAG_*` flags of the segment.
+    pub flags: u32,
+}
+
+/// A function to call when the module is initialized, see
+/// [`LinkingSection::init_funcs`].
+#[derive(Clone, Copy, Debug)]
+pub struct InitFunc {
+    /// Functions are called in increasing order of priority.
+    pub priority: u32,
+    /// The index of the function's symbol in the symbol table.
+    pub symbol_index: u32,
+}
+
+/// A group of symbols of which only one copy is kept when linking, see
+/// [`LinkingSection::comdat_info`].
+#[derive(Clone, Copy, Debug)]
+pub struct Comdat<'a> {
+    /// The name of the group.
+    pub name: &'a str,
+    /// Flags of the group, currently always zero.
+    pub flags: u32,
+    /// The items which are part of the group.
+    pub symbols: &'a [ComdatSymbol],
+}
+
+/// An item of a [`Comdat`].
+#[derive(Clone, Copy, Debug)]
+pub struct ComdatSymbol {
+    /// The kind of the item.
+    pub kind: ComdatSymbolKind,
+    /// The index of the item in the index space of its kind.
+    pub index: u32,
+}
+
+/// The kinds of items that can be part of a [`Comdat`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+#[allow(missing_docs)]
+pub enum ComdatSymbolKind {
+    Data = 0,
+    Function = 1,
+    Global = 2,
+    Event = 3,
+    Table = 4,
+    Section = 5,
+}
+
+/// An encoder for the symbol table of a [`LinkingSection`].
+///
+/// Symbols of imported items, which have the `UNDEFINED` flag, only have a
+/// name encoded if it's given, in which case the `EXPLICIT_NAME` flag is set
+/// too.
+#[derive(Clone, Debug)]
+pub struct SymbolTable {
+    bytes: Vec<u8>,
+    num_added: u32,
+}
+
+impl SymbolTable {
+    /// The symbol is weak, and may be overridden by a strong symbol.
+    pub const BINDING_WEAK: u32 = 0x1;
+    /// The symbol is only visible within the object file.
+    pub const BINDING_LOCAL: u32 = 0x2;
+    /// The symbol isn't exported from the linked module.
+    pub const VISIBILITY_HIDDEN: u32 = 0x4;
+    /// The symbol refers to an imported item.
+    pub const UNDEFINED: u32 = 0x10;
+    /// The symbol should be exported from the linked module.
+    pub const EXPORTED: u32 = 0x20;
+    /// The symbol of an import has a name of its own.
+    pub const EXPLICIT_NAME: u32 = 0x40;
+    /// The symbol must be kept when linking, even if it isn't used.
+    pub const NO_STRIP: u32 = 0x80;
+
+    /// Create a new symbol table encoder.
+    pub fn new() -> SymbolTable {
+        SymbolTable {
+            bytes: vec![],
+            num_added: 0,
+        }
+    }
+
+    /// Define a symbol for the `index`th function.
+    pub fn function(&mut self, flags: u32, index: u32, name: Option<&str>) -> &mut Self {
+        self.indexed(0, flags, index, name)
+    }
+
+    /// Define a symbol for data, which is defined at `definition` unless the
+    /// symbol is `UNDEFINED`.
+    pub fn data(
+        &mut self,
+        flags: u32,
+        name: &str,
+        definition: Option<DataSymbolDefinition>,
+    ) -> &mut Self {
+        let flags = match definition {
+            Some(_) => flags & !Self::UNDEFINED,
+            None => flags | Self::UNDEFINED,
+        };
+        self.bytes.push(1);
+        self.bytes.extend(encoders::u32(flags));
+        self.bytes.extend(encoders::str(name));
+        if let Some(definition) = definition {
+            self.bytes.extend(encoders::u32(definition.index));
+            self.bytes.extend(encoders::u32(definition.offset));
+            self.bytes.extend(encoders::u32(definition.size));
+        }
+        self.num_added += 1;
+        self
+    }
+
+    /// Define a symbol for the `index`th global.
+    pub fn global(&mut self, flags: u32, index: u32, name: Option<&str>) -> &mut Self {
+        self.indexed(2, flags, index, name)
+    }
+
+    /// Define a symbol for the `index`th section, used by relocations against
+    /// custom sections such as those for debug info.
+    pub fn section(&mut self, flags: u32, index: u32) -> &mut Self {
+        self.bytes.push(3);
+        self.bytes.extend(encoders::u32(flags));
+        self.bytes.extend(encoders::u32(index));
+        self.num_added += 1;
+        self
+    }
+
+    /// Define a symbol for the `index`th event.
+    pub fn event(&mut self, flags: u32, index: u32, name: Option<&str>) -> &mut Self {
+        self.indexed(4, flags, index, name)
+    }
+
+    /// Define a symbol for the `index`th table.
+    pub fn table(&mut self, flags: u32, index: u32, name: Option<&str>) -> &mut Self {
+        self.indexed(5, flags, index, name)
+    }
+
+    fn indexed(&mut self, kind: u8, flags: u32, index: u32, name: Option<&str>) -> &mut Self {
+        // Defined symbols always have a name, imported ones only an explicit
+        // one.
+        let flags = match name {
+            Some(_) if flags & Self::UNDEFINED != 0 => flags | Self::EXPLICIT_NAME,
+            Some(_) => flags,
+            None => {
+                assert!(
+                    flags & Self::UNDEFINED != 0,
+                    "defined symbols must have a name"
+                );
+                flags & !Self::EXPLICIT_NAME
+            }
+        };
+        self.bytes.push(kind);
+        self.bytes.extend(encoders::u32(flags));
+        self.bytes.extend(encoders::u32(index));
+        if let Some(name) = name {
+            self.bytes.extend(encoders::str(name));
+        }
+        self.num_added += 1;
+        self
+    }
+}
+
+/// Where a data symbol is defined, see [`SymbolTable::data`].
+#[derive(Clone, Copy, Debug)]
+pub struct DataSymbolDefinition {
+    /// The index of the data segment the symbol is in.
+    pub index: u32,
+    /// The offset of the symbol within its data segment.
+    pub offset: u32,
+    /// The size of the symbol in bytes.
+    pub size: u32,
+}
+
+/// An encoder for a `reloc.*` custom section, holding the relocations to apply
+/// to one of the other sections when linking.
+///
+/// See [`LinkingSection`] for an example.
+#[derive(Clone, Debug)]
+pub struct RelocSection<'a> {
+    name: &'a str,
+    section: u32,
+    bytes: Vec<u8>,
+    num_added: u32,
+}
+
+impl<'a> RelocSection<'a> {
+    /// Create a new encoder for the relocations of the `section`th section,
+    /// counting all sections of the module from zero, including custom
+    /// sections.
+    ///
+    /// The `name` is conventionally `reloc.` followed by the name of the
+    /// section, such as `reloc.CODE` or `reloc.DATA`.
+    pub fn new(name: &'a str, section: u32) -> RelocSection<'a> {
+        RelocSection {
+            name,
+            section,
+            bytes: vec![],
+            num_added: 0,
+        }
+    }
+
+    /// Define a relocation of the value at `offset` within the section, which
+    /// refers to the `index`th symbol, or type for `TypeIndexLEB` relocations.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `addend` is given for a type without one, or missing for a
+    /// type with one.
+    pub fn entry(
+        &mut self,
+        ty: RelocType,
+        offset: u32,
+        index: u32,
+        addend: Option<i64>,
+    ) -> &mut Self {
+        assert_eq!(
+            addend.is_some(),
+            ty.has_addend(),
+            "relocations of type {:?} {} an addend",
+            ty,
+            if ty.has_addend() {
+                "need"
+            } else {
+                "can't have"
+            },
+        );
+        self.bytes.push(ty as u8);
+        self.bytes.extend(encoders::u32(offset));
+        self.bytes.extend(encoders::u32(index));
+        if let Some(addend) = addend {
+            self.bytes.extend(encoders::s64(addend));
+        }
+        self.num_added += 1;
+        self
+    }
+}
+
+impl Section for RelocSection<'_> {
+    fn id(&self) -> u8 {
+        SectionId::Custom.into()
+    }
+
+    fn encode<S>(&self, sink: &mut S)
+    where
+        S: Extend<u8>,
+    {
+        let mut data = encoders::u32(self.section).collect::<Vec<_>>();
+        data.extend(encoders::u32(self.num_added));
+        data.extend(self.bytes.iter().copied());
+        CustomSection {
+            name: self.name,
+            data: &data,
+        }
+        .encode(sink);
+    }
+}
+
+/// The type of a relocation, see [`RelocSection::entry`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+#[allow(missing_docs)]
+pub enum RelocType {
+    FunctionIndexLEB = 0,
+    TableIndexSLEB = 1,
+    TableIndexI32 = 2,
+    MemoryAddrLEB = 3,
+    MemoryAddrSLEB = 4,
+    MemoryAddrI32 = 5,
+    TypeIndexLEB = 6,
+    GlobalIndexLEB = 7,
+    FunctionOffsetI32 = 8,
+    SectionOffsetI32 = 9,
+    EventIndexLEB = 10,
+    MemoryAddrRelSLEB = 11,
+    TableIndexRelSLEB = 12,
+    GlobalIndexI32 = 13,
+    MemoryAddrLEB64 = 14,
+    MemoryAddrSLEB64 = 15,
+    MemoryAddrI64 = 16,
+    MemoryAddrRelSLEB64 = 17,
+    TableIndexSLEB64 = 18,
+    TableIndexI64 = 19,
+    TableNumberLEB = 20,
+    MemoryAddrTlsSLEB = 21,
+    FunctionOffsetI64 = 22,
+    MemoryAddrLocRelI32 = 23,
+    TableIndexRelSLEB64 = 24,
+    MemoryAddrTlsSLEB64 = 25,
+    FunctionIndexI32 = 26,
+}
+
+impl RelocType {
+    /// Returns whether relocations of this type have an addend.
+    pub fn has_addend(&self) -> bool {
+        matches!(
+            self,
+            RelocType::MemoryAddrLEB
+                | RelocType::MemoryAddrSLEB
+                | RelocType::MemoryAddrI32
+                | RelocType::FunctionOffsetI32
+                | RelocType::SectionOffsetI32
+                | RelocType::MemoryAddrRelSLEB
+                | RelocType::MemoryAddrLEB64
+                | RelocType::MemoryAddrSLEB64
+                | RelocType::MemoryAddrI64
+                | RelocType::MemoryAddrRelSLEB64
+                | RelocType::MemoryAddrTlsSLEB
+                | RelocType::FunctionOffsetI64
+                | RelocType::MemoryAddrLocRelI32
+                | RelocType::MemoryAddrTlsSLEB64
+        )
+    }
+}
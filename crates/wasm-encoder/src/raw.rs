@@ -0,0 +1,185 @@
+use super::*;
+
+/// The magic number and version which start a module.
+const HEADER: [u8; 8] = [0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+
+/// An iterator over the sections of an already encoded module.
+///
+/// Each section is yielded as a [`RawSection`] borrowing its contents from the
+/// module, so a transform which only changes some sections of a module can
+/// copy all the others into the new [`Module`] as they are instead of decoding
+/// and encoding them again. Only the size of a copied section is encoded
+/// again, as a minimal LEB128, so it's identical to the original unless the
+/// original size was padded.
+///
+/// ```
+/// use wasm_encoder::{Export, ExportSection, Module, RawSections, SectionId};
+///
+/// # let wasm = &[0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00,
+/// #     0x01, 0x04, 0x01, 0x60, 0x00, 0x00,
+/// #     0x03, 0x02, 0x01, 0x00,
+/// #     0x07, 0x05, 0x01, 0x01, b'f', 0x00, 0x00,
+/// #     0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b];
+/// // Rename the only export of a module with one function, copying every
+/// // other section unchanged.
+/// let mut exports = ExportSection::new();
+/// exports.export("renamed", Export::Function(0));
+///
+/// let mut module = Module::new();
+/// for section in RawSections::new(wasm).unwrap() {
+///     let section = section.unwrap();
+///     if section.id == u8::from(SectionId::Export) {
+///         module.section(&exports);
+///     } else {
+///         module.section(&section);
+///     }
+/// }
+/// let wasm = module.finish();
+/// assert!(wasmparser::validate(&wasm).is_ok());
+/// ```
+#[derive(Clone, Debug)]
+pub struct RawSections<'a> {
+    wasm: &'a [u8],
+    offset: usize,
+}
+
+/// An error returned when the framing of an encoded module's sections is
+/// malformed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MalformedModule {
+    /// The offset in the module of the malformed bytes.
+    pub offset: usize,
+}
+
+impl core::fmt::Display for MalformedModule {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "malformed module at offset {}", self.offset)
+    }
+}
+
+impl<'a> RawSections<'a> {
+    /// Returns an iterator over the sections of the module `wasm`.
+    ///
+    /// Returns an error if `wasm` doesn't start with the magic number and
+    /// version of a module. The contents of sections are never checked.
+    pub fn new(wasm: &'a [u8]) -> Result<RawSections<'a>, MalformedModule> {
+        if wasm.get(..8) != Some(&HEADER[..]) {
+            return Err(MalformedModule { offset: 0 });
+        }
+        Ok(RawSections { wasm, offset: 8 })
+    }
+
+    /// Returns the offset in the module of the next section.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn read(&mut self) -> Result<RawSection<'a>, MalformedModule> {
+        let malformed = MalformedModule {
+            offset: self.offset,
+        };
+        let id = self.wasm[self.offset];
+        let mut pos = self.offset + 1;
+        let mut len = 0u32;
+        for shift in (0..35).step_by(7) {
+            let byte = *self.wasm.get(pos).ok_or(malformed)?;
+            pos += 1;
+            if shift == 28 && byte > 0x0f {
+                return Err(malformed);
+            }
+            len |= u32::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        let end = pos
+            .checked_add(len as usize)
+            .filter(|end| *end <= self.wasm.len())
+            .ok_or(malformed)?;
+        self.offset = end;
+        Ok(RawSection {
+            id,
+            data: &self.wasm[pos..end],
+        })
+    }
+}
+
+impl<'a> Iterator for RawSections<'a> {
+    type Item = Result<RawSection<'a>, MalformedModule>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.wasm.len() {
+            return None;
+        }
+        let section = self.read();
+        if section.is_err() {
+            // Stop after the first error since the end of the malformed
+            // section, and so the start of the next one, isn't known.
+            self.offset = self.wasm.len();
+        }
+        Some(section)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copy_sections() {
+        let mut types = TypeSection::new();
+        types.function(vec![ValType::I32], vec![]);
+        let mut functions = FunctionSection::new();
+        functions.function(0);
+        let mut code = CodeSection::new();
+        let mut body = Function::new(vec![]);
+        body.instruction(Instruction::End);
+        code.function(&body);
+        let mut module = Module::new();
+        module.section(&types);
+        module.section(&CustomSection {
+            name: "a",
+            data: &[1, 2, 3],
+        });
+        module.section(&functions);
+        module.section(&code);
+        let wasm = module.finish();
+
+        let mut copy = Module::new();
+        for section in RawSections::new(&wasm).unwrap() {
+            copy.section(&section.unwrap());
+        }
+        assert_eq!(copy.finish(), wasm);
+
+        // A padded size is encoded again minimally.
+        let mut padded = wasm.clone();
+        padded.splice(9..10, vec![wasm[9] | 0x80, 0x00]);
+        let sections = RawSections::new(&padded)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(sections.len(), 4);
+        let mut copy = Module::new();
+        for section in sections {
+            copy.section(&section);
+        }
+        assert_eq!(copy.finish(), wasm);
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(
+            RawSections::new(&[0, 0x61, 0x73]).unwrap_err(),
+            MalformedModule { offset: 0 }
+        );
+        let wasm = [
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x05, 0x00,
+        ];
+        let mut sections = RawSections::new(&wasm).unwrap();
+        assert_eq!(sections.next().unwrap().unwrap_err().offset, 8);
+        assert!(sections.next().is_none());
+        let wasm = [0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x80];
+        let mut sections = RawSections::new(&wasm).unwrap();
+        assert!(sections.next().unwrap().is_err());
+    }
+}
@@ -6,7 +6,9 @@ use criterion::Criterion;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
-use wasmparser::{DataKind, ElementKind, Parser, Payload, Validator, WasmFeatures};
+use wasmparser::{
+    DataKind, ElementKind, ModuleView, Operator, Parser, Payload, Validator, WasmFeatures,
+};
 
 /// A benchmark input.
 pub struct BenchmarkInput {
@@ -217,6 +219,8 @@ fn validate_benchmark(c: &mut Criterion) {
             tail_call: true,
             multi_memory: true,
             memory64: true,
+            wide_arithmetic: true,
+            function_references: true,
             deterministic_only: false,
         });
         return ret;
@@ -233,5 +237,83 @@ fn validate_benchmark(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benchmark, it_works_benchmark, validate_benchmark);
+/// Returns the text of a module with a `br_table` of `n` targets, like those
+/// generated for large `switch` statements.
+fn br_table_module(n: usize) -> String {
+    let mut wat = String::from("(module (func (param i32) (block (block (block\n");
+    wat.push_str("local.get 0 br_table");
+    for i in 0..n {
+        wat.push_str([" 0", " 1", " 2", " 2"][i % 4]);
+    }
+    wat.push_str(" 2))))\n)");
+    wat
+}
+
+fn br_table_benchmark(c: &mut Criterion) {
+    let text = br_table_module(50_000);
+    let wasm = wat::parse_str(&text).unwrap();
+    c.bench_function("br_table parse text", |b| {
+        b.iter(|| wat::parse_str(&text).unwrap())
+    });
+    c.bench_function("br_table read", |b| {
+        b.iter(|| {
+            for item in Parser::new(0).parse_all(&wasm) {
+                if let Payload::CodeSectionEntry(body) = item.unwrap() {
+                    for op in body.get_operators_reader().unwrap() {
+                        if let Operator::BrTable { table } = op.unwrap() {
+                            for target in table.targets() {
+                                target.unwrap();
+                            }
+                        }
+                    }
+                }
+            }
+        })
+    });
+    c.bench_function("br_table validate", |b| {
+        b.iter(|| Validator::new().validate_all(&wasm).unwrap())
+    });
+}
+
+fn exports_benchmark(c: &mut Criterion) {
+    let mut inputs = collect_benchmark_inputs();
+    inputs.retain(|input| ModuleView::new(&input.wasm).is_ok());
+    c.bench_function("exports parse", |b| {
+        b.iter(|| {
+            for input in inputs.iter() {
+                for item in Parser::new(0).parse_all(&input.wasm) {
+                    match item {
+                        Ok(Payload::ExportSection(reader)) => {
+                            for export in reader {
+                                export.unwrap();
+                            }
+                        }
+                        Ok(_) => {}
+                        Err(_) => break,
+                    }
+                }
+            }
+        })
+    });
+    c.bench_function("exports view", |b| {
+        b.iter(|| {
+            for input in inputs.iter() {
+                let view = ModuleView::new(&input.wasm).unwrap();
+                if let Ok(Some(reader)) = view.export_section() {
+                    for export in reader {
+                        export.unwrap();
+                    }
+                }
+            }
+        })
+    });
+}
+
+criterion_group!(
+    benchmark,
+    it_works_benchmark,
+    validate_benchmark,
+    br_table_benchmark,
+    exports_benchmark
+);
 criterion_main!(benchmark);
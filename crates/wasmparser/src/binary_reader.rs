@@ -13,30 +13,27 @@
  * limitations under the License.
  */
 
-use std::convert::TryInto;
-use std::fmt;
-use std::str;
-
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use core::fmt;
+use core::str;
+
+use crate::leb128;
 use crate::limits::*;
 
 use crate::primitives::{
-    BinaryReaderError, BrTable, CustomSectionKind, ExternalKind, FuncType, GlobalType, Ieee32,
-    Ieee64, LinkingType, MemoryImmediate, MemoryType, NameType, Operator, RelocType,
-    ResizableLimits, ResizableLimits64, Result, SIMDLaneIndex, SectionCode, TableType, Type,
-    TypeOrFuncType, V128,
+    BinaryReaderError, BrTable, ErrorCode, ExternalKind, FuncType, GlobalType, Ieee32, Ieee64,
+    LetLocals, MemoryImmediate, MemoryType, NameType, Operator, RelocType, ResizableLimits,
+    ResizableLimits64, Result, SIMDLaneIndex, SectionCode, TableType, Type, TypeOrFuncType, V128,
+};
+use crate::{
+    EventType, ExportType, Import, ImportSectionEntryType, InstanceType, ModuleType, VisitOperator,
 };
-use crate::{EventType, ExportType, Import, ImportSectionEntryType, InstanceType, ModuleType};
 
 const MAX_WASM_BR_TABLE_SIZE: usize = MAX_WASM_FUNCTION_SIZE;
 
-fn is_name(name: &str, expected: &'static str) -> bool {
-    name == expected
-}
-
-fn is_name_prefix(name: &str, prefix: &'static str) -> bool {
-    name.starts_with(prefix)
-}
-
 const WASM_MAGIC_NUMBER: &[u8; 4] = b"\0asm";
 const WASM_EXPERIMENTAL_VERSION: u32 = 0xd;
 const WASM_SUPPORTED_VERSION: u32 = 0x1;
@@ -50,6 +47,7 @@ pub(crate) struct SectionHeader<'a> {
 
 /// Bytecode range in the WebAssembly module.
 #[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Range {
     /// The start bound of the range.
     pub start: usize,
@@ -81,6 +79,23 @@ pub struct BinaryReader<'a> {
     pub(crate) original_offset: usize,
 }
 
+// Only the bytes which haven't been read yet are serialized, along with their
+// offset in the original wasm binary, matching how structures such as
+// `FunctionBody` are serialized.
+#[cfg(feature = "serde")]
+impl serde::Serialize for BinaryReader<'_> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut s = serializer.serialize_struct("BinaryReader", 2)?;
+        s.serialize_field("offset", &self.original_position())?;
+        s.serialize_field("data", &self.buffer[self.position..])?;
+        s.end()
+    }
+}
+
 impl<'a> BinaryReader<'a> {
     /// Constructs `BinaryReader` type.
     ///
@@ -147,6 +162,7 @@ impl<'a> BinaryReader<'a> {
         let b = self.read_u8()?;
         if (b & 0xFE) != 0 {
             return Err(BinaryReaderError::new(
+                ErrorCode::InvalidInteger,
                 "Invalid var_u1",
                 self.original_position() - 1,
             ));
@@ -158,6 +174,7 @@ impl<'a> BinaryReader<'a> {
         let b = self.read_u8()?;
         if (b & 0x80) != 0 {
             return Err(BinaryReaderError::new(
+                ErrorCode::InvalidInteger,
                 "Invalid var_i7",
                 self.original_position() - 1,
             ));
@@ -169,6 +186,7 @@ impl<'a> BinaryReader<'a> {
         let b = self.read_u8()?;
         if (b & 0x80) != 0 {
             return Err(BinaryReaderError::new(
+                ErrorCode::InvalidInteger,
                 "Invalid var_u7",
                 self.original_position() - 1,
             ));
@@ -190,6 +208,7 @@ impl<'a> BinaryReader<'a> {
             -0x20 => Ok(Type::Func),
             -0x40 => Ok(Type::EmptyBlockType),
             _ => Err(BinaryReaderError::new(
+                ErrorCode::InvalidEncoding,
                 "Invalid type",
                 self.original_position() - 1,
             )),
@@ -208,6 +227,7 @@ impl<'a> BinaryReader<'a> {
             6 => Ok(ExternalKind::Instance),
             7 => Ok(ExternalKind::Type),
             _ => Err(BinaryReaderError::new(
+                ErrorCode::InvalidEncoding,
                 "Invalid external kind",
                 self.original_position() - 1,
             )),
@@ -218,6 +238,7 @@ impl<'a> BinaryReader<'a> {
         let params_len = self.read_var_u32()? as usize;
         if params_len > MAX_WASM_FUNCTION_PARAMS {
             return Err(BinaryReaderError::new(
+                ErrorCode::LimitExceeded,
                 "function params size is out of bound",
                 self.original_position() - 1,
             ));
@@ -229,6 +250,7 @@ impl<'a> BinaryReader<'a> {
         let returns_len = self.read_var_u32()? as usize;
         if returns_len > MAX_WASM_FUNCTION_RETURNS {
             return Err(BinaryReaderError::new(
+                ErrorCode::LimitExceeded,
                 "function returns size is out of bound",
                 self.original_position() - 1,
             ));
@@ -247,7 +269,11 @@ impl<'a> BinaryReader<'a> {
         let pos = self.original_position();
         let imports_len = self.read_var_u32()? as usize;
         if imports_len > MAX_WASM_IMPORTS {
-            return Err(BinaryReaderError::new("imports size is out of bounds", pos));
+            return Err(BinaryReaderError::new(
+                ErrorCode::LimitExceeded,
+                "imports size is out of bounds",
+                pos,
+            ));
         }
         Ok(ModuleType {
             imports: (0..imports_len)
@@ -267,7 +293,11 @@ impl<'a> BinaryReader<'a> {
         let pos = self.original_position();
         let exports_len = self.read_var_u32()? as usize;
         if exports_len > MAX_WASM_EXPORTS {
-            return Err(BinaryReaderError::new("exports size is out of bound", pos));
+            return Err(BinaryReaderError::new(
+                ErrorCode::LimitExceeded,
+                "exports size is out of bound",
+                pos,
+            ));
         }
         (0..exports_len).map(|_| self.read_export_type()).collect()
     }
@@ -308,6 +338,7 @@ impl<'a> BinaryReader<'a> {
             ExternalKind::Instance => ImportSectionEntryType::Instance(self.read_var_u32()?),
             ExternalKind::Type => {
                 return Err(BinaryReaderError::new(
+                    ErrorCode::InvalidEncoding,
                     "cannot import types",
                     self.original_position() - 1,
                 ))
@@ -340,6 +371,7 @@ impl<'a> BinaryReader<'a> {
         let flags = self.read_var_u32()?;
         if (flags & !0x1) != 0 {
             return Err(BinaryReaderError::new(
+                ErrorCode::InvalidEncoding,
                 "invalid table resizable limits flags",
                 self.original_position() - 1,
             ));
@@ -356,6 +388,7 @@ impl<'a> BinaryReader<'a> {
         let flags = self.read_u8()?;
         if (flags & !0x7) != 0 {
             return Err(BinaryReaderError::new(
+                ErrorCode::InvalidEncoding,
                 "invalid table resizable limits flags",
                 pos,
             ));
@@ -375,6 +408,7 @@ impl<'a> BinaryReader<'a> {
         let attribute = self.read_var_u32()?;
         if attribute != 0 {
             return Err(BinaryReaderError::new(
+                ErrorCode::InvalidEncoding,
                 "invalid event attributes",
                 self.original_position() - 1,
             ));
@@ -407,7 +441,11 @@ impl<'a> BinaryReader<'a> {
             0
         };
         let align = if flags >= (1 << 6) {
-            return Err(BinaryReaderError::new("alignment too large", flags_pos));
+            return Err(BinaryReaderError::new(
+                ErrorCode::InvalidAlignment,
+                "alignment too large",
+                flags_pos,
+            ));
         } else {
             flags as u8
         };
@@ -418,59 +456,31 @@ impl<'a> BinaryReader<'a> {
         })
     }
 
-    pub(crate) fn read_section_code(&mut self, id: u32, offset: usize) -> Result<SectionCode<'a>> {
-        match id {
-            0 => {
-                let name = self.read_string()?;
-                let kind = if is_name(name, "name") {
-                    CustomSectionKind::Name
-                } else if is_name(name, "producers") {
-                    CustomSectionKind::Producers
-                } else if is_name(name, "sourceMappingURL") {
-                    CustomSectionKind::SourceMappingURL
-                } else if is_name_prefix(name, "reloc.") {
-                    CustomSectionKind::Reloc
-                } else if is_name(name, "linking") {
-                    CustomSectionKind::Linking
-                } else {
-                    CustomSectionKind::Unknown
-                };
-                Ok(SectionCode::Custom { name, kind })
-            }
-            1 => Ok(SectionCode::Type),
-            2 => Ok(SectionCode::Import),
-            3 => Ok(SectionCode::Function),
-            4 => Ok(SectionCode::Table),
-            5 => Ok(SectionCode::Memory),
-            6 => Ok(SectionCode::Global),
-            7 => Ok(SectionCode::Export),
-            8 => Ok(SectionCode::Start),
-            9 => Ok(SectionCode::Element),
-            10 => Ok(SectionCode::Code),
-            11 => Ok(SectionCode::Data),
-            12 => Ok(SectionCode::DataCount),
-            13 => Ok(SectionCode::Event),
-            14 => Ok(SectionCode::Module),
-            15 => Ok(SectionCode::Instance),
-            16 => Ok(SectionCode::Alias),
-            17 => Ok(SectionCode::ModuleCode),
-            _ => Err(BinaryReaderError::new("Invalid section code", offset)),
-        }
-    }
-
     fn read_br_table(&mut self) -> Result<BrTable<'a>> {
         let targets_len = self.read_var_u32()? as usize;
         if targets_len > MAX_WASM_BR_TABLE_SIZE {
             return Err(BinaryReaderError::new(
+                ErrorCode::LimitExceeded,
                 "br_table size is out of bound",
                 self.original_position() - 1,
             ));
         }
         let start = self.position;
-        for _ in 0..targets_len {
-            self.skip_var_32()?;
+        // Tables generated for large `switch` statements are typically dense,
+        // with every target fitting in a single byte, in which case the end of
+        // the table is found without decoding each target.
+        let dense_end = start + targets_len + 1;
+        match self.buffer.get(start..dense_end) {
+            Some(targets) if targets.iter().all(|b| b & 0x80 == 0) => {
+                self.position = dense_end;
+            }
+            _ => {
+                for _ in 0..targets_len {
+                    self.skip_var_32()?;
+                }
+                self.skip_var_32()?;
+            }
         }
-        self.skip_var_32()?;
         let end = self.position;
         Ok(BrTable {
             reader: BinaryReader::new_with_offset(&self.buffer[start..end], start),
@@ -478,6 +488,27 @@ impl<'a> BinaryReader<'a> {
         })
     }
 
+    fn read_let_locals(&mut self) -> Result<LetLocals<'a>> {
+        let cnt = self.read_var_u32()? as usize;
+        if cnt > MAX_WASM_FUNCTION_LOCALS {
+            return Err(BinaryReaderError::new(
+                ErrorCode::LimitExceeded,
+                "let locals count is out of bounds",
+                self.original_position() - 1,
+            ));
+        }
+        let start = self.position;
+        for _ in 0..cnt {
+            self.read_var_u32()?;
+            self.read_type()?;
+        }
+        let end = self.position;
+        Ok(LetLocals {
+            reader: BinaryReader::new_with_offset(&self.buffer[start..end], start),
+            cnt,
+        })
+    }
+
     /// Returns whether the `BinaryReader` has reached the end of the file.
     pub fn eof(&self) -> bool {
         self.position >= self.buffer.len()
@@ -563,6 +594,7 @@ impl<'a> BinaryReader<'a> {
         let result = (self.read_u8()? << 7) | (byte & 0x7F);
         if result >= 0x100 {
             return Err(BinaryReaderError::new(
+                ErrorCode::InvalidInteger,
                 "Invalid var_u8",
                 self.original_position() - 1,
             ));
@@ -578,29 +610,9 @@ impl<'a> BinaryReader<'a> {
     /// If `BinaryReader` has less than one or up to four bytes remaining, or
     /// the integer is larger than 32 bits.
     pub fn read_var_u32(&mut self) -> Result<u32> {
-        // Optimization for single byte i32.
-        let byte = self.read_u8()?;
-        if (byte & 0x80) == 0 {
-            return Ok(byte);
-        }
-
-        let mut result = byte & 0x7F;
-        let mut shift = 7;
-        loop {
-            let byte = self.read_u8()?;
-            result |= ((byte & 0x7F) as u32) << shift;
-            if shift >= 25 && (byte >> (32 - shift)) != 0 {
-                // The continuation bit or unused bits are set.
-                return Err(BinaryReaderError::new(
-                    "Invalid var_u32",
-                    self.original_position() - 1,
-                ));
-            }
-            shift += 7;
-            if (byte & 0x80) == 0 {
-                break;
-            }
-        }
+        let (result, len) =
+            leb128::read_u32(&self.buffer[self.position..], self.original_position())?;
+        self.position += len;
         Ok(result)
     }
 
@@ -612,29 +624,9 @@ impl<'a> BinaryReader<'a> {
     /// If `BinaryReader` has less than one or up to eight bytes remaining, or
     /// the integer is larger than 64 bits.
     pub fn read_var_u64(&mut self) -> Result<u64> {
-        // Optimization for single byte u64.
-        let byte = u64::from(self.read_u8()?);
-        if (byte & 0x80) == 0 {
-            return Ok(byte);
-        }
-
-        let mut result = byte & 0x7F;
-        let mut shift = 7;
-        loop {
-            let byte = u64::from(self.read_u8()?);
-            result |= (byte & 0x7F) << shift;
-            if shift >= 57 && (byte >> (64 - shift)) != 0 {
-                // The continuation bit or unused bits are set.
-                return Err(BinaryReaderError::new(
-                    "Invalid var_u64",
-                    self.original_position() - 1,
-                ));
-            }
-            shift += 7;
-            if (byte & 0x80) == 0 {
-                break;
-            }
-        }
+        let (result, len) =
+            leb128::read_u64(&self.buffer[self.position..], self.original_position())?;
+        self.position += len;
         Ok(result)
     }
 
@@ -651,6 +643,7 @@ impl<'a> BinaryReader<'a> {
             }
         }
         Err(BinaryReaderError::new(
+            ErrorCode::InvalidInteger,
             "Invalid var_32",
             self.original_position() - 1,
         ))
@@ -680,6 +673,7 @@ impl<'a> BinaryReader<'a> {
         let len = self.read_var_u32()? as usize;
         if len > MAX_WASM_STRING_SIZE {
             return Err(BinaryReaderError::new(
+                ErrorCode::LimitExceeded,
                 "string size in out of bounds",
                 self.original_position() - 1,
             ));
@@ -701,35 +695,10 @@ impl<'a> BinaryReader<'a> {
     /// If `BinaryReader` has less than one or up to four bytes remaining, or
     /// the integer is larger than 32 bits.
     pub fn read_var_i32(&mut self) -> Result<i32> {
-        // Optimization for single byte i32.
-        let byte = self.read_u8()?;
-        if (byte & 0x80) == 0 {
-            return Ok(((byte as i32) << 25) >> 25);
-        }
-
-        let mut result = (byte & 0x7F) as i32;
-        let mut shift = 7;
-        loop {
-            let byte = self.read_u8()?;
-            result |= ((byte & 0x7F) as i32) << shift;
-            if shift >= 25 {
-                let continuation_bit = (byte & 0x80) != 0;
-                let sign_and_unused_bit = (byte << 1) as i8 >> (32 - shift);
-                if continuation_bit || (sign_and_unused_bit != 0 && sign_and_unused_bit != -1) {
-                    return Err(BinaryReaderError::new(
-                        "Invalid var_i32",
-                        self.original_position() - 1,
-                    ));
-                }
-                return Ok(result);
-            }
-            shift += 7;
-            if (byte & 0x80) == 0 {
-                break;
-            }
-        }
-        let ashift = 32 - shift;
-        Ok((result << ashift) >> ashift)
+        let (result, len) =
+            leb128::read_i32(&self.buffer[self.position..], self.original_position())?;
+        self.position += len;
+        Ok(result)
     }
 
     /// Advances the `BinaryReader` up to four bytes to parse a variable
@@ -754,6 +723,7 @@ impl<'a> BinaryReader<'a> {
                 let sign_and_unused_bit = (byte << 1) as i8 >> (33 - shift);
                 if continuation_bit || (sign_and_unused_bit != 0 && sign_and_unused_bit != -1) {
                     return Err(BinaryReaderError::new(
+                        ErrorCode::InvalidInteger,
                         "Invalid var_s33",
                         self.original_position() - 1,
                     ));
@@ -775,29 +745,10 @@ impl<'a> BinaryReader<'a> {
     /// If `BinaryReader` has less than one or up to eight bytes remaining, or
     /// the integer is larger than 64 bits.
     pub fn read_var_i64(&mut self) -> Result<i64> {
-        let mut result: i64 = 0;
-        let mut shift = 0;
-        loop {
-            let byte = self.read_u8()?;
-            result |= i64::from(byte & 0x7F) << shift;
-            if shift >= 57 {
-                let continuation_bit = (byte & 0x80) != 0;
-                let sign_and_unused_bit = ((byte << 1) as i8) >> (64 - shift);
-                if continuation_bit || (sign_and_unused_bit != 0 && sign_and_unused_bit != -1) {
-                    return Err(BinaryReaderError::new(
-                        "Invalid var_i64",
-                        self.original_position() - 1,
-                    ));
-                }
-                return Ok(result);
-            }
-            shift += 7;
-            if (byte & 0x80) == 0 {
-                break;
-            }
-        }
-        let ashift = 64 - shift;
-        Ok((result << ashift) >> ashift)
+        let (result, len) =
+            leb128::read_i64(&self.buffer[self.position..], self.original_position())?;
+        self.position += len;
+        Ok(result)
     }
 
     /// Advances the `BinaryReader` up to four bytes to parse a variable
@@ -829,13 +780,18 @@ impl<'a> BinaryReader<'a> {
         let len = self.read_var_u32()? as usize;
         if len > MAX_WASM_STRING_SIZE {
             return Err(BinaryReaderError::new(
+                ErrorCode::LimitExceeded,
                 "string size in out of bounds",
                 self.original_position() - 1,
             ));
         }
         let bytes = self.read_bytes(len)?;
         str::from_utf8(bytes).map_err(|_| {
-            BinaryReaderError::new("invalid UTF-8 encoding", self.original_position() - 1)
+            BinaryReaderError::new(
+                ErrorCode::InvalidUtf8,
+                "invalid UTF-8 encoding",
+                self.original_position() - 1,
+            )
         })
     }
 
@@ -844,6 +800,7 @@ impl<'a> BinaryReader<'a> {
         let imm = self.read_memarg()?;
         if imm.align > max_align {
             return Err(BinaryReaderError::new(
+                ErrorCode::InvalidAlignment,
                 "alignment must not be larger than natural",
                 align_pos,
             ));
@@ -1058,6 +1015,7 @@ impl<'a> BinaryReader<'a> {
 
             _ => {
                 return Err(BinaryReaderError::new(
+                    ErrorCode::UnknownOpcode,
                     format!("Unknown 0xfe subopcode: 0x{:x}", code),
                     self.original_position() - 1,
                 ));
@@ -1072,13 +1030,30 @@ impl<'a> BinaryReader<'a> {
         } else {
             self.position = position;
             let idx = self.read_var_s33()?;
-            if idx < 0 || idx > (std::u32::MAX as i64) {
-                return Err(BinaryReaderError::new("invalid function type", position));
+            if idx < 0 || idx > (core::u32::MAX as i64) {
+                return Err(BinaryReaderError::new(
+                    ErrorCode::InvalidEncoding,
+                    "invalid function type",
+                    position,
+                ));
             }
             Ok(TypeOrFuncType::FuncType(idx as u32))
         }
     }
 
+    /// Reads the next available `Operator` and dispatches it to `visitor`.
+    /// # Errors
+    /// If `BinaryReader` has less bytes remaining than required to parse
+    /// the `Operator`.
+    pub fn visit_operator<T>(&mut self, visitor: &mut T) -> Result<T::Output>
+    where
+        T: VisitOperator<'a>,
+    {
+        let offset = self.original_position();
+        let op = self.read_operator()?;
+        Ok(visitor.visit_operator(offset, op))
+    }
+
     /// Reads the next available `Operator`.
     /// # Errors
     /// If `BinaryReader` has less bytes remaining than required to parse
@@ -1136,12 +1111,17 @@ impl<'a> BinaryReader<'a> {
                 index: self.read_var_u32()?,
                 table_index: self.read_var_u32()?,
             },
+            0x17 => Operator::Let {
+                ty: self.read_blocktype()?,
+                locals: self.read_let_locals()?,
+            },
             0x1a => Operator::Drop,
             0x1b => Operator::Select,
             0x1c => {
                 let results = self.read_var_u32()?;
                 if results != 1 {
                     return Err(BinaryReaderError::new(
+                        ErrorCode::InvalidEncoding,
                         "invalid result arity",
                         self.position,
                     ));
@@ -1404,6 +1384,7 @@ impl<'a> BinaryReader<'a> {
 
             _ => {
                 return Err(BinaryReaderError::new(
+                    ErrorCode::UnknownOpcode,
                     format!("Unknown opcode: 0x{:x}", code),
                     self.original_position() - 1,
                 ));
@@ -1473,8 +1454,14 @@ impl<'a> BinaryReader<'a> {
                 Operator::TableFill { table }
             }
 
+            0x13 => Operator::I64Add128,
+            0x14 => Operator::I64Sub128,
+            0x15 => Operator::I64MulWideS,
+            0x16 => Operator::I64MulWideU,
+
             _ => {
                 return Err(BinaryReaderError::new(
+                    ErrorCode::UnknownOpcode,
                     format!("Unknown 0xfc subopcode: 0x{:x}", code),
                     self.original_position() - 1,
                 ));
@@ -1486,6 +1473,7 @@ impl<'a> BinaryReader<'a> {
         let index = self.read_u8()?;
         if index >= max {
             return Err(BinaryReaderError::new(
+                ErrorCode::InvalidLaneIndex,
                 "invalid lane index",
                 self.original_position() - 1,
             ));
@@ -1809,6 +1797,7 @@ impl<'a> BinaryReader<'a> {
             },
             _ => {
                 return Err(BinaryReaderError::new(
+                    ErrorCode::UnknownOpcode,
                     format!("Unknown 0xfd subopcode: 0x{:x}", code),
                     self.original_position() - 1,
                 ));
@@ -1820,6 +1809,7 @@ impl<'a> BinaryReader<'a> {
         let magic_number = self.read_bytes(4)?;
         if magic_number != WASM_MAGIC_NUMBER {
             return Err(BinaryReaderError::new(
+                ErrorCode::InvalidHeader,
                 "Bad magic number",
                 self.original_position() - 4,
             ));
@@ -1827,6 +1817,7 @@ impl<'a> BinaryReader<'a> {
         let version = self.read_u32()?;
         if version != WASM_SUPPORTED_VERSION && version != WASM_EXPERIMENTAL_VERSION {
             return Err(BinaryReaderError::new(
+                ErrorCode::InvalidHeader,
                 "Bad version number",
                 self.original_position() - 4,
             ));
@@ -1841,43 +1832,53 @@ impl<'a> BinaryReader<'a> {
             1 => Ok(NameType::Function),
             2 => Ok(NameType::Local),
             _ => Err(BinaryReaderError::new(
+                ErrorCode::InvalidEncoding,
                 "Invalid name type",
                 self.original_position() - 1,
             )),
         }
     }
 
-    pub(crate) fn read_linking_type(&mut self) -> Result<LinkingType> {
-        let ty = self.read_var_u32()?;
-        Ok(match ty {
-            1 => LinkingType::StackPointer(self.read_var_u32()?),
+    pub(crate) fn read_reloc_type(&mut self) -> Result<RelocType> {
+        let code = self.read_u8()?;
+        Ok(match code {
+            0 => RelocType::FunctionIndexLEB,
+            1 => RelocType::TableIndexSLEB,
+            2 => RelocType::TableIndexI32,
+            3 => RelocType::MemoryAddrLEB,
+            4 => RelocType::MemoryAddrSLEB,
+            5 => RelocType::MemoryAddrI32,
+            6 => RelocType::TypeIndexLEB,
+            7 => RelocType::GlobalIndexLEB,
+            8 => RelocType::FunctionOffsetI32,
+            9 => RelocType::SectionOffsetI32,
+            10 => RelocType::EventIndexLEB,
+            11 => RelocType::MemoryAddrRelSLEB,
+            12 => RelocType::TableIndexRelSLEB,
+            13 => RelocType::GlobalIndexI32,
+            14 => RelocType::MemoryAddrLEB64,
+            15 => RelocType::MemoryAddrSLEB64,
+            16 => RelocType::MemoryAddrI64,
+            17 => RelocType::MemoryAddrRelSLEB64,
+            18 => RelocType::TableIndexSLEB64,
+            19 => RelocType::TableIndexI64,
+            20 => RelocType::TableNumberLEB,
+            21 => RelocType::MemoryAddrTlsSLEB,
+            22 => RelocType::FunctionOffsetI64,
+            23 => RelocType::MemoryAddrLocRelI32,
+            24 => RelocType::TableIndexRelSLEB64,
+            25 => RelocType::MemoryAddrTlsSLEB64,
+            26 => RelocType::FunctionIndexI32,
             _ => {
                 return Err(BinaryReaderError::new(
-                    "Invalid linking type",
+                    ErrorCode::InvalidEncoding,
+                    "Invalid reloc type",
                     self.original_position() - 1,
-                ));
+                ))
             }
         })
     }
 
-    pub(crate) fn read_reloc_type(&mut self) -> Result<RelocType> {
-        let code = self.read_var_u7()?;
-        match code {
-            0 => Ok(RelocType::FunctionIndexLEB),
-            1 => Ok(RelocType::TableIndexSLEB),
-            2 => Ok(RelocType::TableIndexI32),
-            3 => Ok(RelocType::GlobalAddrLEB),
-            4 => Ok(RelocType::GlobalAddrSLEB),
-            5 => Ok(RelocType::GlobalAddrI32),
-            6 => Ok(RelocType::TypeIndexLEB),
-            7 => Ok(RelocType::GlobalIndexLEB),
-            _ => Err(BinaryReaderError::new(
-                "Invalid reloc type",
-                self.original_position() - 1,
-            )),
-        }
-    }
-
     pub(crate) fn skip_init_expr(&mut self) -> Result<()> {
         // TODO add skip_operator() method and/or validate init_expr operators.
         loop {
@@ -1923,19 +1924,176 @@ impl<'a> BrTable<'a> {
     ///     assert_eq!(targets, [(1, false), (2, false), (0, true)]);
     /// }
     /// ```
-    pub fn targets<'b>(&'b self) -> impl Iterator<Item = Result<(u32, bool)>> + 'b {
-        let mut reader = self.reader.clone();
-        (0..self.cnt + 1).map(move |i| {
-            let label = reader.read_var_u32()?;
-            let ret = (label, i == self.cnt);
-            if ret.1 && !reader.eof() {
-                return Err(BinaryReaderError::new(
-                    "trailing data in br_table",
-                    reader.original_position(),
-                ));
+    pub fn targets(&self) -> BrTableTargets<'a> {
+        BrTableTargets {
+            reader: self.reader.clone(),
+            remaining: self.cnt + 1,
+        }
+    }
+}
+
+/// An iterator over the targets of a [`BrTable`], decoding each one as it's
+/// requested.
+///
+/// Created with [`BrTable::targets`].
+#[derive(Clone)]
+pub struct BrTableTargets<'a> {
+    reader: BinaryReader<'a>,
+    remaining: usize,
+}
+
+impl Iterator for BrTableTargets<'_> {
+    type Item = Result<(u32, bool)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let is_default = self.remaining == 0;
+        let label = match self.reader.buffer.get(self.reader.position) {
+            // Single byte labels are by far the most common, so decode them
+            // directly.
+            Some(&byte) if byte & 0x80 == 0 => {
+                self.reader.position += 1;
+                u32::from(byte)
             }
-            Ok(ret)
-        })
+            _ => match self.reader.read_var_u32() {
+                Ok(label) => label,
+                Err(e) => return Some(Err(e)),
+            },
+        };
+        if is_default && !self.reader.eof() {
+            return Some(Err(BinaryReaderError::new(
+                ErrorCode::SizeMismatch,
+                "trailing data in br_table",
+                self.reader.original_position(),
+            )));
+        }
+        Some(Ok((label, is_default)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for BrTableTargets<'_> {}
+
+impl<'a> LetLocals<'a> {
+    /// Returns the number of entries in the list of locals, each of which
+    /// declares some number of locals of one type.
+    pub fn len(&self) -> usize {
+        self.cnt
+    }
+
+    /// Returns whether the `let` doesn't declare any locals.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns an iterator over the entries of the list of locals, each of
+    /// which is the number of locals and their type.
+    ///
+    /// #Examples
+    ///
+    /// ```rust
+    /// let buf = [0x17, 0x40, 0x02, 0x01, 0x7f, 0x02, 0x7e];
+    /// let mut reader = wasmparser::BinaryReader::new(&buf);
+    /// let op = reader.read_operator().unwrap();
+    /// if let wasmparser::Operator::Let { locals, .. } = op {
+    ///     let locals = locals.locals().collect::<Result<Vec<_>, _>>().unwrap();
+    ///     assert_eq!(locals, [(1, wasmparser::Type::I32), (2, wasmparser::Type::I64)]);
+    /// }
+    /// ```
+    pub fn locals(&self) -> LetLocalsIter<'a> {
+        LetLocalsIter {
+            reader: self.reader.clone(),
+            remaining: self.cnt,
+        }
+    }
+}
+
+/// An iterator over the locals of a [`LetLocals`], decoding each entry as
+/// it's requested.
+///
+/// Created with [`LetLocals::locals`].
+#[derive(Clone)]
+pub struct LetLocalsIter<'a> {
+    reader: BinaryReader<'a>,
+    remaining: usize,
+}
+
+impl Iterator for LetLocalsIter<'_> {
+    type Item = Result<(u32, Type)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let count = match self.reader.read_var_u32() {
+            Ok(count) => count,
+            Err(e) => return Some(Err(e)),
+        };
+        Some(self.reader.read_type().map(|ty| (count, ty)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for LetLocalsIter<'_> {}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for LetLocals<'_> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        use serde::ser::Error;
+        let locals = self
+            .locals()
+            .collect::<Result<Vec<_>>>()
+            .map_err(S::Error::custom)?;
+        locals.serialize(serializer)
+    }
+}
+
+impl fmt::Debug for LetLocals<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.locals().collect::<Result<Vec<_>>>() {
+            Ok(locals) => f.debug_list().entries(locals).finish(),
+            Err(_) => f
+                .debug_struct("LetLocals")
+                .field("reader", &self.reader)
+                .finish(),
+        }
+    }
+}
+
+// Serialized as the targets it will jump to, like the `Debug` implementation,
+// rather than the raw bytes of the table.
+#[cfg(feature = "serde")]
+impl serde::Serialize for BrTable<'_> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        use serde::ser::{Error, SerializeStruct};
+        let mut targets = Vec::with_capacity(self.cnt);
+        let mut default = 0;
+        for target in self.targets() {
+            match target.map_err(S::Error::custom)? {
+                (label, false) => targets.push(label),
+                (label, true) => default = label,
+            }
+        }
+        let mut s = serializer.serialize_struct("BrTable", 2)?;
+        s.serialize_field("targets", &targets)?;
+        s.serialize_field("default", &default)?;
+        s.end()
     }
 }
 
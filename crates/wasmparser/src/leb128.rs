@@ -0,0 +1,323 @@
+/* Copyright 2021 Mozilla Foundation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Readers and writers for the LEB128 variable-length integer encoding used
+//! throughout the WebAssembly binary format.
+//!
+//! The readers enforce the limits of the WebAssembly specification: an
+//! encoding of an `N`-bit integer may use at most `ceil(N / 7)` bytes and the
+//! unused bits of the final byte must be zero (or, for signed integers, a sign
+//! extension of the value). They never panic, even on truncated or malicious
+//! input.
+//!
+//! The specification allows encodings to be padded with redundant bytes, for
+//! example `0x80 0x00` for zero. Tools which need the shortest encoding can
+//! check for it with [`is_canonical_unsigned`] and [`is_canonical_signed`].
+//!
+//! Each reader takes the `offset` of the first byte of its input in the
+//! original wasm binary, which is only used for error reporting, and returns
+//! the decoded value along with the number of bytes it occupied.
+
+use crate::{BinaryReaderError, ErrorCode, Result};
+use alloc::vec::Vec;
+
+#[inline]
+fn byte_at(bytes: &[u8], pos: usize, offset: usize) -> Result<u8> {
+    match bytes.get(pos) {
+        Some(b) => Ok(*b),
+        None => Err(BinaryReaderError::eof(offset + pos, 1)),
+    }
+}
+
+/// Reads a LEB128-encoded `u32` from the start of `bytes`.
+///
+/// # Errors
+///
+/// If `bytes` ends before the encoding does, the encoding is longer than five
+/// bytes, or the encoded integer is larger than 32 bits.
+#[inline]
+pub fn read_u32(bytes: &[u8], offset: usize) -> Result<(u32, usize)> {
+    // Optimization for single byte u32.
+    let byte = byte_at(bytes, 0, offset)?;
+    if (byte & 0x80) == 0 {
+        return Ok((u32::from(byte), 1));
+    }
+
+    let mut result = u32::from(byte & 0x7F);
+    let mut shift = 7;
+    let mut pos = 1;
+    loop {
+        let byte = byte_at(bytes, pos, offset)?;
+        result |= u32::from(byte & 0x7F) << shift;
+        if shift >= 25 && (byte >> (32 - shift)) != 0 {
+            // The continuation bit or unused bits are set.
+            return Err(BinaryReaderError::new(
+                ErrorCode::InvalidInteger,
+                "Invalid var_u32",
+                offset + pos,
+            ));
+        }
+        pos += 1;
+        shift += 7;
+        if (byte & 0x80) == 0 {
+            break;
+        }
+    }
+    Ok((result, pos))
+}
+
+/// Reads a LEB128-encoded `u64` from the start of `bytes`.
+///
+/// # Errors
+///
+/// If `bytes` ends before the encoding does, the encoding is longer than ten
+/// bytes, or the encoded integer is larger than 64 bits.
+#[inline]
+pub fn read_u64(bytes: &[u8], offset: usize) -> Result<(u64, usize)> {
+    // Optimization for single byte u64.
+    let byte = byte_at(bytes, 0, offset)?;
+    if (byte & 0x80) == 0 {
+        return Ok((u64::from(byte), 1));
+    }
+
+    let mut result = u64::from(byte & 0x7F);
+    let mut shift = 7;
+    let mut pos = 1;
+    loop {
+        let byte = byte_at(bytes, pos, offset)?;
+        result |= u64::from(byte & 0x7F) << shift;
+        if shift >= 57 && (byte >> (64 - shift)) != 0 {
+            // The continuation bit or unused bits are set.
+            return Err(BinaryReaderError::new(
+                ErrorCode::InvalidInteger,
+                "Invalid var_u64",
+                offset + pos,
+            ));
+        }
+        pos += 1;
+        shift += 7;
+        if (byte & 0x80) == 0 {
+            break;
+        }
+    }
+    Ok((result, pos))
+}
+
+/// Reads a signed LEB128-encoded `i32` from the start of `bytes`.
+///
+/// # Errors
+///
+/// If `bytes` ends before the encoding does, the encoding is longer than five
+/// bytes, or the encoded integer is larger than 32 bits.
+#[inline]
+pub fn read_i32(bytes: &[u8], offset: usize) -> Result<(i32, usize)> {
+    // Optimization for single byte i32.
+    let byte = byte_at(bytes, 0, offset)?;
+    if (byte & 0x80) == 0 {
+        return Ok((((byte as i32) << 25) >> 25, 1));
+    }
+
+    let mut result = (byte & 0x7F) as i32;
+    let mut shift = 7;
+    let mut pos = 1;
+    loop {
+        let byte = byte_at(bytes, pos, offset)?;
+        result |= ((byte & 0x7F) as i32) << shift;
+        if shift >= 25 {
+            let continuation_bit = (byte & 0x80) != 0;
+            let sign_and_unused_bit = (byte << 1) as i8 >> (32 - shift);
+            if continuation_bit || (sign_and_unused_bit != 0 && sign_and_unused_bit != -1) {
+                return Err(BinaryReaderError::new(
+                    ErrorCode::InvalidInteger,
+                    "Invalid var_i32",
+                    offset + pos,
+                ));
+            }
+            return Ok((result, pos + 1));
+        }
+        pos += 1;
+        shift += 7;
+        if (byte & 0x80) == 0 {
+            break;
+        }
+    }
+    let ashift = 32 - shift;
+    Ok(((result << ashift) >> ashift, pos))
+}
+
+/// Reads a signed LEB128-encoded `i64` from the start of `bytes`.
+///
+/// # Errors
+///
+/// If `bytes` ends before the encoding does, the encoding is longer than ten
+/// bytes, or the encoded integer is larger than 64 bits.
+#[inline]
+pub fn read_i64(bytes: &[u8], offset: usize) -> Result<(i64, usize)> {
+    let mut result: i64 = 0;
+    let mut shift = 0;
+    let mut pos = 0;
+    loop {
+        let byte = byte_at(bytes, pos, offset)?;
+        result |= i64::from(byte & 0x7F) << shift;
+        if shift >= 57 {
+            let continuation_bit = (byte & 0x80) != 0;
+            let sign_and_unused_bit = ((byte << 1) as i8) >> (64 - shift);
+            if continuation_bit || (sign_and_unused_bit != 0 && sign_and_unused_bit != -1) {
+                return Err(BinaryReaderError::new(
+                    ErrorCode::InvalidInteger,
+                    "Invalid var_i64",
+                    offset + pos,
+                ));
+            }
+            return Ok((result, pos + 1));
+        }
+        pos += 1;
+        shift += 7;
+        if (byte & 0x80) == 0 {
+            break;
+        }
+    }
+    let ashift = 64 - shift;
+    Ok(((result << ashift) >> ashift, pos))
+}
+
+/// Returns whether `encoding`, a complete unsigned LEB128 encoding such as the
+/// bytes consumed by [`read_u32`], is the shortest possible encoding of its
+/// value.
+pub fn is_canonical_unsigned(encoding: &[u8]) -> bool {
+    match encoding {
+        [] => false,
+        [_] => true,
+        [.., last] => *last != 0,
+    }
+}
+
+/// Returns whether `encoding`, a complete signed LEB128 encoding such as the
+/// bytes consumed by [`read_i32`], is the shortest possible encoding of its
+/// value.
+pub fn is_canonical_signed(encoding: &[u8]) -> bool {
+    match encoding {
+        [] => false,
+        [_] => true,
+        // The last byte is redundant if it only sign-extends the byte before
+        // it.
+        [.., prev, last] => {
+            let prev_sign = prev & 0x40 != 0;
+            !((*last == 0x00 && !prev_sign) || (*last == 0x7f && prev_sign))
+        }
+    }
+}
+
+/// Appends the shortest unsigned LEB128 encoding of `value` to `dst`,
+/// returning the number of bytes written.
+pub fn write_u32(dst: &mut Vec<u8>, value: u32) -> usize {
+    write_u64(dst, value.into())
+}
+
+/// Appends the shortest unsigned LEB128 encoding of `value` to `dst`,
+/// returning the number of bytes written.
+pub fn write_u64(dst: &mut Vec<u8>, mut value: u64) -> usize {
+    let start = dst.len();
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            dst.push(byte);
+            return dst.len() - start;
+        }
+        dst.push(byte | 0x80);
+    }
+}
+
+/// Appends the shortest signed LEB128 encoding of `value` to `dst`, returning
+/// the number of bytes written.
+pub fn write_i32(dst: &mut Vec<u8>, value: i32) -> usize {
+    write_i64(dst, value.into())
+}
+
+/// Appends the shortest signed LEB128 encoding of `value` to `dst`, returning
+/// the number of bytes written.
+pub fn write_i64(dst: &mut Vec<u8>, mut value: i64) -> usize {
+    let start = dst.len();
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        let sign_bit = byte & 0x40 != 0;
+        if (value == 0 && !sign_bit) || (value == -1 && sign_bit) {
+            dst.push(byte);
+            return dst.len() - start;
+        }
+        dst.push(byte | 0x80);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let mut buf = Vec::new();
+        for &v in &[0, 1, 63, 64, 127, 128, 624485, u32::max_value()] {
+            buf.clear();
+            let n = write_u32(&mut buf, v);
+            assert_eq!(read_u32(&buf, 0).unwrap(), (v, n));
+            assert!(is_canonical_unsigned(&buf));
+        }
+        for &v in &[
+            0,
+            1,
+            63,
+            64,
+            -64,
+            -65,
+            -123456,
+            i32::min_value(),
+            i32::max_value(),
+        ] {
+            buf.clear();
+            let n = write_i32(&mut buf, v);
+            assert_eq!(read_i32(&buf, 0).unwrap(), (v, n));
+            assert!(is_canonical_signed(&buf));
+        }
+        for &v in &[0, u64::max_value(), 1 << 63] {
+            buf.clear();
+            let n = write_u64(&mut buf, v);
+            assert_eq!(read_u64(&buf, 0).unwrap(), (v, n));
+        }
+        for &v in &[0, -1, i64::min_value(), i64::max_value()] {
+            buf.clear();
+            let n = write_i64(&mut buf, v);
+            assert_eq!(read_i64(&buf, 0).unwrap(), (v, n));
+        }
+    }
+
+    #[test]
+    fn limits() {
+        // Padded encodings are allowed but not canonical.
+        assert_eq!(read_u32(&[0x80, 0x00], 0).unwrap(), (0, 2));
+        assert!(!is_canonical_unsigned(&[0x80, 0x00]));
+        assert_eq!(read_i32(&[0xff, 0x7f], 0).unwrap(), (-1, 2));
+        assert!(!is_canonical_signed(&[0xff, 0x7f]));
+
+        // Too many bytes, unused bits set, or truncated.
+        assert!(read_u32(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x00], 0).is_err());
+        assert!(read_u32(&[0xff, 0xff, 0xff, 0xff, 0x1f], 0).is_err());
+        assert!(read_i32(&[0xff, 0xff, 0xff, 0xff, 0x4f], 0).is_err());
+        assert!(read_u64(&[0x80], 0).is_err());
+        let err = read_i64(&[0x80, 0x80], 10).unwrap_err();
+        assert_eq!(err.offset(), 12);
+    }
+}
@@ -22,21 +22,581 @@
 //! If you need random access to the entire WebAssembly data-structure,
 //! this is not the right library for you. You could however, build such
 //! a data-structure using this library.
+//!
+//! # `no_std` support
+//!
+//! This crate is `#![no_std]` compatible when its default `std` feature is
+//! disabled, in which case only the `alloc` crate is required. Without `std`
+//! the [`BinaryReaderError`] type doesn't implement `std::error::Error`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+/// A helper macro to conveniently iterate over all opcodes recognized by this
+/// crate. This can be used to work with either the [`Operator`] enumeration or
+/// the [`VisitOperator`] trait if your use case uniformly handles all operators
+/// the same way.
+///
+/// The macro is invoked with the name of another macro which is then invoked
+/// with the list of all operators. Each operator is listed as its name in the
+/// [`Operator`] enumeration, its payload fields (if any) in braces, and then
+/// the name of the corresponding [`VisitOperator`] method:
+///
+/// ```
+/// macro_rules! count_operators {
+///     ($($op:ident $({ $($arg:ident: $argty:ty),* })? => $visit:ident)*) => {
+///         0 $(+ { let _ = stringify!($op); 1 })*
+///     };
+/// }
+///
+/// let n: usize = wasmparser::for_each_operator!(count_operators);
+/// assert!(n > 400);
+/// ```
+///
+/// Adding a new instruction to this crate starts by adding it to this list,
+/// which keeps the enumeration and the visitor trait in sync with each other.
+#[macro_export]
+macro_rules! for_each_operator {
+    ($mac:ident) => {
+        $mac! {
+            Unreachable => visit_unreachable
+            Nop => visit_nop
+            Block { ty: $crate::TypeOrFuncType } => visit_block
+            Loop { ty: $crate::TypeOrFuncType } => visit_loop
+            If { ty: $crate::TypeOrFuncType } => visit_if
+            Else => visit_else
+            Try { ty: $crate::TypeOrFuncType } => visit_try
+            Catch { index: u32 } => visit_catch
+            Throw { index: u32 } => visit_throw
+            Rethrow { relative_depth: u32 } => visit_rethrow
+            Unwind => visit_unwind
+            End => visit_end
+            Br { relative_depth: u32 } => visit_br
+            BrIf { relative_depth: u32 } => visit_br_if
+            BrTable { table: $crate::BrTable<'a> } => visit_br_table
+            Return => visit_return
+            Call { function_index: u32 } => visit_call
+            CallIndirect { index: u32, table_index: u32 } => visit_call_indirect
+            ReturnCall { function_index: u32 } => visit_return_call
+            ReturnCallIndirect { index: u32, table_index: u32 } => visit_return_call_indirect
+            Let { ty: $crate::TypeOrFuncType, locals: $crate::LetLocals<'a> } => visit_let
+            Drop => visit_drop
+            Select => visit_select
+            TypedSelect { ty: $crate::Type } => visit_typed_select
+            LocalGet { local_index: u32 } => visit_local_get
+            LocalSet { local_index: u32 } => visit_local_set
+            LocalTee { local_index: u32 } => visit_local_tee
+            GlobalGet { global_index: u32 } => visit_global_get
+            GlobalSet { global_index: u32 } => visit_global_set
+            I32Load { memarg: $crate::MemoryImmediate } => visit_i32_load
+            I64Load { memarg: $crate::MemoryImmediate } => visit_i64_load
+            F32Load { memarg: $crate::MemoryImmediate } => visit_f32_load
+            F64Load { memarg: $crate::MemoryImmediate } => visit_f64_load
+            I32Load8S { memarg: $crate::MemoryImmediate } => visit_i32_load8_s
+            I32Load8U { memarg: $crate::MemoryImmediate } => visit_i32_load8_u
+            I32Load16S { memarg: $crate::MemoryImmediate } => visit_i32_load16_s
+            I32Load16U { memarg: $crate::MemoryImmediate } => visit_i32_load16_u
+            I64Load8S { memarg: $crate::MemoryImmediate } => visit_i64_load8_s
+            I64Load8U { memarg: $crate::MemoryImmediate } => visit_i64_load8_u
+            I64Load16S { memarg: $crate::MemoryImmediate } => visit_i64_load16_s
+            I64Load16U { memarg: $crate::MemoryImmediate } => visit_i64_load16_u
+            I64Load32S { memarg: $crate::MemoryImmediate } => visit_i64_load32_s
+            I64Load32U { memarg: $crate::MemoryImmediate } => visit_i64_load32_u
+            I32Store { memarg: $crate::MemoryImmediate } => visit_i32_store
+            I64Store { memarg: $crate::MemoryImmediate } => visit_i64_store
+            F32Store { memarg: $crate::MemoryImmediate } => visit_f32_store
+            F64Store { memarg: $crate::MemoryImmediate } => visit_f64_store
+            I32Store8 { memarg: $crate::MemoryImmediate } => visit_i32_store8
+            I32Store16 { memarg: $crate::MemoryImmediate } => visit_i32_store16
+            I64Store8 { memarg: $crate::MemoryImmediate } => visit_i64_store8
+            I64Store16 { memarg: $crate::MemoryImmediate } => visit_i64_store16
+            I64Store32 { memarg: $crate::MemoryImmediate } => visit_i64_store32
+            MemorySize { mem: u32, mem_byte: u8 } => visit_memory_size
+            MemoryGrow { mem: u32, mem_byte: u8 } => visit_memory_grow
+            I32Const { value: i32 } => visit_i32_const
+            I64Const { value: i64 } => visit_i64_const
+            F32Const { value: $crate::Ieee32 } => visit_f32_const
+            F64Const { value: $crate::Ieee64 } => visit_f64_const
+            RefNull { ty: $crate::Type } => visit_ref_null
+            RefIsNull => visit_ref_is_null
+            RefFunc { function_index: u32 } => visit_ref_func
+            I32Eqz => visit_i32_eqz
+            I32Eq => visit_i32_eq
+            I32Ne => visit_i32_ne
+            I32LtS => visit_i32_lt_s
+            I32LtU => visit_i32_lt_u
+            I32GtS => visit_i32_gt_s
+            I32GtU => visit_i32_gt_u
+            I32LeS => visit_i32_le_s
+            I32LeU => visit_i32_le_u
+            I32GeS => visit_i32_ge_s
+            I32GeU => visit_i32_ge_u
+            I64Eqz => visit_i64_eqz
+            I64Eq => visit_i64_eq
+            I64Ne => visit_i64_ne
+            I64LtS => visit_i64_lt_s
+            I64LtU => visit_i64_lt_u
+            I64GtS => visit_i64_gt_s
+            I64GtU => visit_i64_gt_u
+            I64LeS => visit_i64_le_s
+            I64LeU => visit_i64_le_u
+            I64GeS => visit_i64_ge_s
+            I64GeU => visit_i64_ge_u
+            F32Eq => visit_f32_eq
+            F32Ne => visit_f32_ne
+            F32Lt => visit_f32_lt
+            F32Gt => visit_f32_gt
+            F32Le => visit_f32_le
+            F32Ge => visit_f32_ge
+            F64Eq => visit_f64_eq
+            F64Ne => visit_f64_ne
+            F64Lt => visit_f64_lt
+            F64Gt => visit_f64_gt
+            F64Le => visit_f64_le
+            F64Ge => visit_f64_ge
+            I32Clz => visit_i32_clz
+            I32Ctz => visit_i32_ctz
+            I32Popcnt => visit_i32_popcnt
+            I32Add => visit_i32_add
+            I32Sub => visit_i32_sub
+            I32Mul => visit_i32_mul
+            I32DivS => visit_i32_div_s
+            I32DivU => visit_i32_div_u
+            I32RemS => visit_i32_rem_s
+            I32RemU => visit_i32_rem_u
+            I32And => visit_i32_and
+            I32Or => visit_i32_or
+            I32Xor => visit_i32_xor
+            I32Shl => visit_i32_shl
+            I32ShrS => visit_i32_shr_s
+            I32ShrU => visit_i32_shr_u
+            I32Rotl => visit_i32_rotl
+            I32Rotr => visit_i32_rotr
+            I64Clz => visit_i64_clz
+            I64Ctz => visit_i64_ctz
+            I64Popcnt => visit_i64_popcnt
+            I64Add => visit_i64_add
+            I64Sub => visit_i64_sub
+            I64Mul => visit_i64_mul
+            I64DivS => visit_i64_div_s
+            I64DivU => visit_i64_div_u
+            I64RemS => visit_i64_rem_s
+            I64RemU => visit_i64_rem_u
+            I64And => visit_i64_and
+            I64Or => visit_i64_or
+            I64Xor => visit_i64_xor
+            I64Shl => visit_i64_shl
+            I64ShrS => visit_i64_shr_s
+            I64ShrU => visit_i64_shr_u
+            I64Rotl => visit_i64_rotl
+            I64Rotr => visit_i64_rotr
+            F32Abs => visit_f32_abs
+            F32Neg => visit_f32_neg
+            F32Ceil => visit_f32_ceil
+            F32Floor => visit_f32_floor
+            F32Trunc => visit_f32_trunc
+            F32Nearest => visit_f32_nearest
+            F32Sqrt => visit_f32_sqrt
+            F32Add => visit_f32_add
+            F32Sub => visit_f32_sub
+            F32Mul => visit_f32_mul
+            F32Div => visit_f32_div
+            F32Min => visit_f32_min
+            F32Max => visit_f32_max
+            F32Copysign => visit_f32_copysign
+            F64Abs => visit_f64_abs
+            F64Neg => visit_f64_neg
+            F64Ceil => visit_f64_ceil
+            F64Floor => visit_f64_floor
+            F64Trunc => visit_f64_trunc
+            F64Nearest => visit_f64_nearest
+            F64Sqrt => visit_f64_sqrt
+            F64Add => visit_f64_add
+            F64Sub => visit_f64_sub
+            F64Mul => visit_f64_mul
+            F64Div => visit_f64_div
+            F64Min => visit_f64_min
+            F64Max => visit_f64_max
+            F64Copysign => visit_f64_copysign
+            I32WrapI64 => visit_i32_wrap_i64
+            I32TruncF32S => visit_i32_trunc_f32_s
+            I32TruncF32U => visit_i32_trunc_f32_u
+            I32TruncF64S => visit_i32_trunc_f64_s
+            I32TruncF64U => visit_i32_trunc_f64_u
+            I64ExtendI32S => visit_i64_extend_i32_s
+            I64ExtendI32U => visit_i64_extend_i32_u
+            I64TruncF32S => visit_i64_trunc_f32_s
+            I64TruncF32U => visit_i64_trunc_f32_u
+            I64TruncF64S => visit_i64_trunc_f64_s
+            I64TruncF64U => visit_i64_trunc_f64_u
+            F32ConvertI32S => visit_f32_convert_i32_s
+            F32ConvertI32U => visit_f32_convert_i32_u
+            F32ConvertI64S => visit_f32_convert_i64_s
+            F32ConvertI64U => visit_f32_convert_i64_u
+            F32DemoteF64 => visit_f32_demote_f64
+            F64ConvertI32S => visit_f64_convert_i32_s
+            F64ConvertI32U => visit_f64_convert_i32_u
+            F64ConvertI64S => visit_f64_convert_i64_s
+            F64ConvertI64U => visit_f64_convert_i64_u
+            F64PromoteF32 => visit_f64_promote_f32
+            I32ReinterpretF32 => visit_i32_reinterpret_f32
+            I64ReinterpretF64 => visit_i64_reinterpret_f64
+            F32ReinterpretI32 => visit_f32_reinterpret_i32
+            F64ReinterpretI64 => visit_f64_reinterpret_i64
+            I32Extend8S => visit_i32_extend8_s
+            I32Extend16S => visit_i32_extend16_s
+            I64Extend8S => visit_i64_extend8_s
+            I64Extend16S => visit_i64_extend16_s
+            I64Extend32S => visit_i64_extend32_s
+
+            // 0xFC operators
+            // Non-trapping Float-to-int Conversions
+            I32TruncSatF32S => visit_i32_trunc_sat_f32_s
+            I32TruncSatF32U => visit_i32_trunc_sat_f32_u
+            I32TruncSatF64S => visit_i32_trunc_sat_f64_s
+            I32TruncSatF64U => visit_i32_trunc_sat_f64_u
+            I64TruncSatF32S => visit_i64_trunc_sat_f32_s
+            I64TruncSatF32U => visit_i64_trunc_sat_f32_u
+            I64TruncSatF64S => visit_i64_trunc_sat_f64_s
+            I64TruncSatF64U => visit_i64_trunc_sat_f64_u
+
+            // 0xFC operators
+            // bulk memory https://github.com/WebAssembly/bulk-memory-operations/blob/master/proposals/bulk-memory-operations/Overview.md
+            MemoryInit { segment: u32, mem: u32 } => visit_memory_init
+            DataDrop { segment: u32 } => visit_data_drop
+            MemoryCopy { src: u32, dst: u32 } => visit_memory_copy
+            MemoryFill { mem: u32 } => visit_memory_fill
+            TableInit { segment: u32, table: u32 } => visit_table_init
+            ElemDrop { segment: u32 } => visit_elem_drop
+            TableCopy { dst_table: u32, src_table: u32 } => visit_table_copy
+            TableFill { table: u32 } => visit_table_fill
+            TableGet { table: u32 } => visit_table_get
+            TableSet { table: u32 } => visit_table_set
+            TableGrow { table: u32 } => visit_table_grow
+            TableSize { table: u32 } => visit_table_size
+
+            // 0xFC operators
+            // https://github.com/WebAssembly/wide-arithmetic/blob/main/proposals/wide-arithmetic/Overview.md
+            I64Add128 => visit_i64_add128
+            I64Sub128 => visit_i64_sub128
+            I64MulWideS => visit_i64_mul_wide_s
+            I64MulWideU => visit_i64_mul_wide_u
+
+            // 0xFE operators
+            // https://github.com/WebAssembly/threads/blob/master/proposals/threads/Overview.md
+            MemoryAtomicNotify { memarg: $crate::MemoryImmediate } => visit_memory_atomic_notify
+            MemoryAtomicWait32 { memarg: $crate::MemoryImmediate } => visit_memory_atomic_wait32
+            MemoryAtomicWait64 { memarg: $crate::MemoryImmediate } => visit_memory_atomic_wait64
+            AtomicFence { flags: u8 } => visit_atomic_fence
+            I32AtomicLoad { memarg: $crate::MemoryImmediate } => visit_i32_atomic_load
+            I64AtomicLoad { memarg: $crate::MemoryImmediate } => visit_i64_atomic_load
+            I32AtomicLoad8U { memarg: $crate::MemoryImmediate } => visit_i32_atomic_load8_u
+            I32AtomicLoad16U { memarg: $crate::MemoryImmediate } => visit_i32_atomic_load16_u
+            I64AtomicLoad8U { memarg: $crate::MemoryImmediate } => visit_i64_atomic_load8_u
+            I64AtomicLoad16U { memarg: $crate::MemoryImmediate } => visit_i64_atomic_load16_u
+            I64AtomicLoad32U { memarg: $crate::MemoryImmediate } => visit_i64_atomic_load32_u
+            I32AtomicStore { memarg: $crate::MemoryImmediate } => visit_i32_atomic_store
+            I64AtomicStore { memarg: $crate::MemoryImmediate } => visit_i64_atomic_store
+            I32AtomicStore8 { memarg: $crate::MemoryImmediate } => visit_i32_atomic_store8
+            I32AtomicStore16 { memarg: $crate::MemoryImmediate } => visit_i32_atomic_store16
+            I64AtomicStore8 { memarg: $crate::MemoryImmediate } => visit_i64_atomic_store8
+            I64AtomicStore16 { memarg: $crate::MemoryImmediate } => visit_i64_atomic_store16
+            I64AtomicStore32 { memarg: $crate::MemoryImmediate } => visit_i64_atomic_store32
+            I32AtomicRmwAdd { memarg: $crate::MemoryImmediate } => visit_i32_atomic_rmw_add
+            I64AtomicRmwAdd { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw_add
+            I32AtomicRmw8AddU { memarg: $crate::MemoryImmediate } => visit_i32_atomic_rmw8_add_u
+            I32AtomicRmw16AddU { memarg: $crate::MemoryImmediate } => visit_i32_atomic_rmw16_add_u
+            I64AtomicRmw8AddU { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw8_add_u
+            I64AtomicRmw16AddU { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw16_add_u
+            I64AtomicRmw32AddU { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw32_add_u
+            I32AtomicRmwSub { memarg: $crate::MemoryImmediate } => visit_i32_atomic_rmw_sub
+            I64AtomicRmwSub { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw_sub
+            I32AtomicRmw8SubU { memarg: $crate::MemoryImmediate } => visit_i32_atomic_rmw8_sub_u
+            I32AtomicRmw16SubU { memarg: $crate::MemoryImmediate } => visit_i32_atomic_rmw16_sub_u
+            I64AtomicRmw8SubU { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw8_sub_u
+            I64AtomicRmw16SubU { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw16_sub_u
+            I64AtomicRmw32SubU { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw32_sub_u
+            I32AtomicRmwAnd { memarg: $crate::MemoryImmediate } => visit_i32_atomic_rmw_and
+            I64AtomicRmwAnd { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw_and
+            I32AtomicRmw8AndU { memarg: $crate::MemoryImmediate } => visit_i32_atomic_rmw8_and_u
+            I32AtomicRmw16AndU { memarg: $crate::MemoryImmediate } => visit_i32_atomic_rmw16_and_u
+            I64AtomicRmw8AndU { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw8_and_u
+            I64AtomicRmw16AndU { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw16_and_u
+            I64AtomicRmw32AndU { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw32_and_u
+            I32AtomicRmwOr { memarg: $crate::MemoryImmediate } => visit_i32_atomic_rmw_or
+            I64AtomicRmwOr { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw_or
+            I32AtomicRmw8OrU { memarg: $crate::MemoryImmediate } => visit_i32_atomic_rmw8_or_u
+            I32AtomicRmw16OrU { memarg: $crate::MemoryImmediate } => visit_i32_atomic_rmw16_or_u
+            I64AtomicRmw8OrU { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw8_or_u
+            I64AtomicRmw16OrU { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw16_or_u
+            I64AtomicRmw32OrU { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw32_or_u
+            I32AtomicRmwXor { memarg: $crate::MemoryImmediate } => visit_i32_atomic_rmw_xor
+            I64AtomicRmwXor { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw_xor
+            I32AtomicRmw8XorU { memarg: $crate::MemoryImmediate } => visit_i32_atomic_rmw8_xor_u
+            I32AtomicRmw16XorU { memarg: $crate::MemoryImmediate } => visit_i32_atomic_rmw16_xor_u
+            I64AtomicRmw8XorU { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw8_xor_u
+            I64AtomicRmw16XorU { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw16_xor_u
+            I64AtomicRmw32XorU { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw32_xor_u
+            I32AtomicRmwXchg { memarg: $crate::MemoryImmediate } => visit_i32_atomic_rmw_xchg
+            I64AtomicRmwXchg { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw_xchg
+            I32AtomicRmw8XchgU { memarg: $crate::MemoryImmediate } => visit_i32_atomic_rmw8_xchg_u
+            I32AtomicRmw16XchgU { memarg: $crate::MemoryImmediate } => visit_i32_atomic_rmw16_xchg_u
+            I64AtomicRmw8XchgU { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw8_xchg_u
+            I64AtomicRmw16XchgU { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw16_xchg_u
+            I64AtomicRmw32XchgU { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw32_xchg_u
+            I32AtomicRmwCmpxchg { memarg: $crate::MemoryImmediate } => visit_i32_atomic_rmw_cmpxchg
+            I64AtomicRmwCmpxchg { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw_cmpxchg
+            I32AtomicRmw8CmpxchgU { memarg: $crate::MemoryImmediate } => visit_i32_atomic_rmw8_cmpxchg_u
+            I32AtomicRmw16CmpxchgU { memarg: $crate::MemoryImmediate } => visit_i32_atomic_rmw16_cmpxchg_u
+            I64AtomicRmw8CmpxchgU { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw8_cmpxchg_u
+            I64AtomicRmw16CmpxchgU { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw16_cmpxchg_u
+            I64AtomicRmw32CmpxchgU { memarg: $crate::MemoryImmediate } => visit_i64_atomic_rmw32_cmpxchg_u
+
+            // 0xFD operators
+            // SIMD https://github.com/WebAssembly/simd/blob/master/proposals/simd/BinarySIMD.md
+            V128Load { memarg: $crate::MemoryImmediate } => visit_v128_load
+            V128Store { memarg: $crate::MemoryImmediate } => visit_v128_store
+            V128Const { value: $crate::V128 } => visit_v128_const
+            I8x16Splat => visit_i8x16_splat
+            I8x16ExtractLaneS { lane: $crate::SIMDLaneIndex } => visit_i8x16_extract_lane_s
+            I8x16ExtractLaneU { lane: $crate::SIMDLaneIndex } => visit_i8x16_extract_lane_u
+            I8x16ReplaceLane { lane: $crate::SIMDLaneIndex } => visit_i8x16_replace_lane
+            I16x8Splat => visit_i16x8_splat
+            I16x8ExtractLaneS { lane: $crate::SIMDLaneIndex } => visit_i16x8_extract_lane_s
+            I16x8ExtractLaneU { lane: $crate::SIMDLaneIndex } => visit_i16x8_extract_lane_u
+            I16x8ReplaceLane { lane: $crate::SIMDLaneIndex } => visit_i16x8_replace_lane
+            I32x4Splat => visit_i32x4_splat
+            I32x4ExtractLane { lane: $crate::SIMDLaneIndex } => visit_i32x4_extract_lane
+            I32x4ReplaceLane { lane: $crate::SIMDLaneIndex } => visit_i32x4_replace_lane
+            I64x2Splat => visit_i64x2_splat
+            I64x2ExtractLane { lane: $crate::SIMDLaneIndex } => visit_i64x2_extract_lane
+            I64x2ReplaceLane { lane: $crate::SIMDLaneIndex } => visit_i64x2_replace_lane
+            F32x4Splat => visit_f32x4_splat
+            F32x4ExtractLane { lane: $crate::SIMDLaneIndex } => visit_f32x4_extract_lane
+            F32x4ReplaceLane { lane: $crate::SIMDLaneIndex } => visit_f32x4_replace_lane
+            F64x2Splat => visit_f64x2_splat
+            F64x2ExtractLane { lane: $crate::SIMDLaneIndex } => visit_f64x2_extract_lane
+            F64x2ReplaceLane { lane: $crate::SIMDLaneIndex } => visit_f64x2_replace_lane
+            I8x16Eq => visit_i8x16_eq
+            I8x16Ne => visit_i8x16_ne
+            I8x16LtS => visit_i8x16_lt_s
+            I8x16LtU => visit_i8x16_lt_u
+            I8x16GtS => visit_i8x16_gt_s
+            I8x16GtU => visit_i8x16_gt_u
+            I8x16LeS => visit_i8x16_le_s
+            I8x16LeU => visit_i8x16_le_u
+            I8x16GeS => visit_i8x16_ge_s
+            I8x16GeU => visit_i8x16_ge_u
+            I16x8Eq => visit_i16x8_eq
+            I16x8Ne => visit_i16x8_ne
+            I16x8LtS => visit_i16x8_lt_s
+            I16x8LtU => visit_i16x8_lt_u
+            I16x8GtS => visit_i16x8_gt_s
+            I16x8GtU => visit_i16x8_gt_u
+            I16x8LeS => visit_i16x8_le_s
+            I16x8LeU => visit_i16x8_le_u
+            I16x8GeS => visit_i16x8_ge_s
+            I16x8GeU => visit_i16x8_ge_u
+            I32x4Eq => visit_i32x4_eq
+            I32x4Ne => visit_i32x4_ne
+            I32x4LtS => visit_i32x4_lt_s
+            I32x4LtU => visit_i32x4_lt_u
+            I32x4GtS => visit_i32x4_gt_s
+            I32x4GtU => visit_i32x4_gt_u
+            I32x4LeS => visit_i32x4_le_s
+            I32x4LeU => visit_i32x4_le_u
+            I32x4GeS => visit_i32x4_ge_s
+            I32x4GeU => visit_i32x4_ge_u
+            F32x4Eq => visit_f32x4_eq
+            F32x4Ne => visit_f32x4_ne
+            F32x4Lt => visit_f32x4_lt
+            F32x4Gt => visit_f32x4_gt
+            F32x4Le => visit_f32x4_le
+            F32x4Ge => visit_f32x4_ge
+            F64x2Eq => visit_f64x2_eq
+            F64x2Ne => visit_f64x2_ne
+            F64x2Lt => visit_f64x2_lt
+            F64x2Gt => visit_f64x2_gt
+            F64x2Le => visit_f64x2_le
+            F64x2Ge => visit_f64x2_ge
+            V128Not => visit_v128_not
+            V128And => visit_v128_and
+            V128AndNot => visit_v128_and_not
+            V128Or => visit_v128_or
+            V128Xor => visit_v128_xor
+            V128Bitselect => visit_v128_bitselect
+            V128AnyTrue => visit_v128_any_true
+            I8x16Abs => visit_i8x16_abs
+            I8x16Neg => visit_i8x16_neg
+            I8x16AllTrue => visit_i8x16_all_true
+            I8x16Bitmask => visit_i8x16_bitmask
+            I8x16Shl => visit_i8x16_shl
+            I8x16ShrS => visit_i8x16_shr_s
+            I8x16ShrU => visit_i8x16_shr_u
+            I8x16Add => visit_i8x16_add
+            I8x16AddSatS => visit_i8x16_add_sat_s
+            I8x16AddSatU => visit_i8x16_add_sat_u
+            I8x16Sub => visit_i8x16_sub
+            I8x16SubSatS => visit_i8x16_sub_sat_s
+            I8x16SubSatU => visit_i8x16_sub_sat_u
+            I8x16MinS => visit_i8x16_min_s
+            I8x16MinU => visit_i8x16_min_u
+            I8x16MaxS => visit_i8x16_max_s
+            I8x16MaxU => visit_i8x16_max_u
+            I16x8Abs => visit_i16x8_abs
+            I16x8Neg => visit_i16x8_neg
+            I16x8AllTrue => visit_i16x8_all_true
+            I16x8Bitmask => visit_i16x8_bitmask
+            I16x8Shl => visit_i16x8_shl
+            I16x8ShrS => visit_i16x8_shr_s
+            I16x8ShrU => visit_i16x8_shr_u
+            I16x8Add => visit_i16x8_add
+            I16x8AddSatS => visit_i16x8_add_sat_s
+            I16x8AddSatU => visit_i16x8_add_sat_u
+            I16x8Sub => visit_i16x8_sub
+            I16x8SubSatS => visit_i16x8_sub_sat_s
+            I16x8SubSatU => visit_i16x8_sub_sat_u
+            I16x8Mul => visit_i16x8_mul
+            I16x8MinS => visit_i16x8_min_s
+            I16x8MinU => visit_i16x8_min_u
+            I16x8MaxS => visit_i16x8_max_s
+            I16x8MaxU => visit_i16x8_max_u
+            I32x4Abs => visit_i32x4_abs
+            I32x4Neg => visit_i32x4_neg
+            I32x4AllTrue => visit_i32x4_all_true
+            I32x4Bitmask => visit_i32x4_bitmask
+            I32x4Shl => visit_i32x4_shl
+            I32x4ShrS => visit_i32x4_shr_s
+            I32x4ShrU => visit_i32x4_shr_u
+            I32x4Add => visit_i32x4_add
+            I32x4Sub => visit_i32x4_sub
+            I32x4Mul => visit_i32x4_mul
+            I32x4MinS => visit_i32x4_min_s
+            I32x4MinU => visit_i32x4_min_u
+            I32x4MaxS => visit_i32x4_max_s
+            I32x4MaxU => visit_i32x4_max_u
+            I32x4DotI16x8S => visit_i32x4_dot_i16x8_s
+            I64x2Neg => visit_i64x2_neg
+            I64x2Bitmask => visit_i64x2_bitmask
+            I64x2Shl => visit_i64x2_shl
+            I64x2ShrS => visit_i64x2_shr_s
+            I64x2ShrU => visit_i64x2_shr_u
+            I64x2Add => visit_i64x2_add
+            I64x2Sub => visit_i64x2_sub
+            I64x2Mul => visit_i64x2_mul
+            F32x4Ceil => visit_f32x4_ceil
+            F32x4Floor => visit_f32x4_floor
+            F32x4Trunc => visit_f32x4_trunc
+            F32x4Nearest => visit_f32x4_nearest
+            F64x2Ceil => visit_f64x2_ceil
+            F64x2Floor => visit_f64x2_floor
+            F64x2Trunc => visit_f64x2_trunc
+            F64x2Nearest => visit_f64x2_nearest
+            F32x4Abs => visit_f32x4_abs
+            F32x4Neg => visit_f32x4_neg
+            F32x4Sqrt => visit_f32x4_sqrt
+            F32x4Add => visit_f32x4_add
+            F32x4Sub => visit_f32x4_sub
+            F32x4Mul => visit_f32x4_mul
+            F32x4Div => visit_f32x4_div
+            F32x4Min => visit_f32x4_min
+            F32x4Max => visit_f32x4_max
+            F32x4PMin => visit_f32x4_p_min
+            F32x4PMax => visit_f32x4_p_max
+            F64x2Abs => visit_f64x2_abs
+            F64x2Neg => visit_f64x2_neg
+            F64x2Sqrt => visit_f64x2_sqrt
+            F64x2Add => visit_f64x2_add
+            F64x2Sub => visit_f64x2_sub
+            F64x2Mul => visit_f64x2_mul
+            F64x2Div => visit_f64x2_div
+            F64x2Min => visit_f64x2_min
+            F64x2Max => visit_f64x2_max
+            F64x2PMin => visit_f64x2_p_min
+            F64x2PMax => visit_f64x2_p_max
+            I32x4TruncSatF32x4S => visit_i32x4_trunc_sat_f32x4_s
+            I32x4TruncSatF32x4U => visit_i32x4_trunc_sat_f32x4_u
+            F32x4ConvertI32x4S => visit_f32x4_convert_i32x4_s
+            F32x4ConvertI32x4U => visit_f32x4_convert_i32x4_u
+            I8x16Swizzle => visit_i8x16_swizzle
+            I8x16Shuffle { lanes: [$crate::SIMDLaneIndex; 16] } => visit_i8x16_shuffle
+            V128Load8Splat { memarg: $crate::MemoryImmediate } => visit_v128_load8_splat
+            V128Load16Splat { memarg: $crate::MemoryImmediate } => visit_v128_load16_splat
+            V128Load32Splat { memarg: $crate::MemoryImmediate } => visit_v128_load32_splat
+            V128Load32Zero { memarg: $crate::MemoryImmediate } => visit_v128_load32_zero
+            V128Load64Splat { memarg: $crate::MemoryImmediate } => visit_v128_load64_splat
+            V128Load64Zero { memarg: $crate::MemoryImmediate } => visit_v128_load64_zero
+            I8x16NarrowI16x8S => visit_i8x16_narrow_i16x8_s
+            I8x16NarrowI16x8U => visit_i8x16_narrow_i16x8_u
+            I16x8NarrowI32x4S => visit_i16x8_narrow_i32x4_s
+            I16x8NarrowI32x4U => visit_i16x8_narrow_i32x4_u
+            I16x8WidenLowI8x16S => visit_i16x8_widen_low_i8x16_s
+            I16x8WidenHighI8x16S => visit_i16x8_widen_high_i8x16_s
+            I16x8WidenLowI8x16U => visit_i16x8_widen_low_i8x16_u
+            I16x8WidenHighI8x16U => visit_i16x8_widen_high_i8x16_u
+            I32x4WidenLowI16x8S => visit_i32x4_widen_low_i16x8_s
+            I32x4WidenHighI16x8S => visit_i32x4_widen_high_i16x8_s
+            I32x4WidenLowI16x8U => visit_i32x4_widen_low_i16x8_u
+            I32x4WidenHighI16x8U => visit_i32x4_widen_high_i16x8_u
+            I64x2WidenLowI32x4S => visit_i64x2_widen_low_i32x4_s
+            I64x2WidenHighI32x4S => visit_i64x2_widen_high_i32x4_s
+            I64x2WidenLowI32x4U => visit_i64x2_widen_low_i32x4_u
+            I64x2WidenHighI32x4U => visit_i64x2_widen_high_i32x4_u
+            I16x8ExtMulLowI8x16S => visit_i16x8_ext_mul_low_i8x16_s
+            I16x8ExtMulHighI8x16S => visit_i16x8_ext_mul_high_i8x16_s
+            I16x8ExtMulLowI8x16U => visit_i16x8_ext_mul_low_i8x16_u
+            I16x8ExtMulHighI8x16U => visit_i16x8_ext_mul_high_i8x16_u
+            I32x4ExtMulLowI16x8S => visit_i32x4_ext_mul_low_i16x8_s
+            I32x4ExtMulHighI16x8S => visit_i32x4_ext_mul_high_i16x8_s
+            I32x4ExtMulLowI16x8U => visit_i32x4_ext_mul_low_i16x8_u
+            I32x4ExtMulHighI16x8U => visit_i32x4_ext_mul_high_i16x8_u
+            I64x2ExtMulLowI32x4S => visit_i64x2_ext_mul_low_i32x4_s
+            I64x2ExtMulHighI32x4S => visit_i64x2_ext_mul_high_i32x4_s
+            I64x2ExtMulLowI32x4U => visit_i64x2_ext_mul_low_i32x4_u
+            I64x2ExtMulHighI32x4U => visit_i64x2_ext_mul_high_i32x4_u
+            V128Load8x8S { memarg: $crate::MemoryImmediate } => visit_v128_load8x8_s
+            V128Load8x8U { memarg: $crate::MemoryImmediate } => visit_v128_load8x8_u
+            V128Load16x4S { memarg: $crate::MemoryImmediate } => visit_v128_load16x4_s
+            V128Load16x4U { memarg: $crate::MemoryImmediate } => visit_v128_load16x4_u
+            V128Load32x2S { memarg: $crate::MemoryImmediate } => visit_v128_load32x2_s
+            V128Load32x2U { memarg: $crate::MemoryImmediate } => visit_v128_load32x2_u
+            V128Load8Lane { memarg: $crate::MemoryImmediate, lane: $crate::SIMDLaneIndex } => visit_v128_load8_lane
+            V128Load16Lane { memarg: $crate::MemoryImmediate, lane: $crate::SIMDLaneIndex } => visit_v128_load16_lane
+            V128Load32Lane { memarg: $crate::MemoryImmediate, lane: $crate::SIMDLaneIndex } => visit_v128_load32_lane
+            V128Load64Lane { memarg: $crate::MemoryImmediate, lane: $crate::SIMDLaneIndex } => visit_v128_load64_lane
+            V128Store8Lane { memarg: $crate::MemoryImmediate, lane: $crate::SIMDLaneIndex } => visit_v128_store8_lane
+            V128Store16Lane { memarg: $crate::MemoryImmediate, lane: $crate::SIMDLaneIndex } => visit_v128_store16_lane
+            V128Store32Lane { memarg: $crate::MemoryImmediate, lane: $crate::SIMDLaneIndex } => visit_v128_store32_lane
+            V128Store64Lane { memarg: $crate::MemoryImmediate, lane: $crate::SIMDLaneIndex } => visit_v128_store64_lane
+            I8x16RoundingAverageU => visit_i8x16_rounding_average_u
+            I16x8RoundingAverageU => visit_i16x8_rounding_average_u
+            I16x8Q15MulrSatS => visit_i16x8_q15_mulr_sat_s
+        }
+    };
+}
 
 pub use crate::binary_reader::BinaryReader;
+pub use crate::binary_reader::BrTableTargets;
+pub use crate::binary_reader::LetLocalsIter;
 pub use crate::binary_reader::Range;
 
 pub use crate::module_resources::*;
+pub use crate::module_view::*;
 pub use crate::parser::*;
 pub use crate::primitives::*;
 pub use crate::readers::*;
+pub use crate::stack_effect::*;
 pub use crate::validator::*;
 
 mod binary_reader;
+pub mod leb128;
 mod limits;
 mod module_resources;
+mod module_view;
 mod operators_validator;
 mod parser;
 mod primitives;
 mod readers;
+mod stack_effect;
 mod validator;
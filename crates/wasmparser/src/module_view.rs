@@ -0,0 +1,271 @@
+/* Copyright 2018 Mozilla Foundation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::{AliasSectionReader, CodeSectionReader, EventSectionReader, InstanceSectionReader};
+use crate::{BinaryReader, BinaryReaderError, ErrorCode, Range, Result};
+use crate::{DataSectionReader, ElementSectionReader, ExportSectionReader};
+use crate::{FunctionSectionReader, ImportSectionReader, TypeSectionReader};
+use crate::{GlobalSectionReader, MemorySectionReader, TableSectionReader};
+use alloc::vec::Vec;
+
+/// A view of a WebAssembly module resident in memory which allows decoding
+/// its sections individually and in any order.
+///
+/// Creating a view only reads the header of each section to find where it
+/// is, skipping over its contents, so finding a section afterwards takes
+/// constant time and decoding it doesn't decode any other section. This makes
+/// it cheap to, for example, read only the exports of many modules:
+///
+/// ```
+/// use wasmparser::{ExternalKind, ModuleView};
+///
+/// # fn foo() -> wasmparser::Result<()> {
+/// # let wasm = &[0, 97, 115, 109, 1, 0, 0, 0];
+/// let view = ModuleView::new(wasm)?;
+/// if let Some(exports) = view.export_section()? {
+///     for export in exports {
+///         let export = export?;
+///         if let ExternalKind::Function = export.kind {
+///             println!("exports function `{}`", export.field);
+///         }
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Sections aren't validated, nor is their order. The module linking
+/// proposal allows some kinds of sections to appear more than once, in which
+/// case the methods returning a particular kind of section return the first
+/// one, and the others can be found with [`ModuleView::sections`].
+#[derive(Debug, Clone)]
+pub struct ModuleView<'a> {
+    version: u32,
+    sections: Vec<ViewSection<'a>>,
+    /// The index in `sections` of the first section with each known id.
+    known: [Option<usize>; 17],
+}
+
+/// A section of a module found by a [`ModuleView`].
+#[derive(Debug, Copy, Clone)]
+pub struct ViewSection<'a> {
+    /// The id of this section.
+    pub id: u8,
+    /// The range of the contents of this section in the module, excluding its
+    /// id and size.
+    pub range: Range,
+    /// The contents of this section.
+    pub data: &'a [u8],
+}
+
+/// A custom section of a module found by a [`ModuleView`].
+#[derive(Debug, Copy, Clone)]
+pub struct CustomSectionView<'a> {
+    /// The name of the custom section.
+    pub name: &'a str,
+    /// The offset in the module that the contents of the custom section,
+    /// after its name, start at.
+    pub data_offset: usize,
+    /// The contents of the custom section after its name.
+    pub data: &'a [u8],
+}
+
+impl<'a> ModuleView<'a> {
+    /// Finds the sections of the module `data`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `data` doesn't start with a module header or a
+    /// section extends past the end of `data`.
+    pub fn new(data: &'a [u8]) -> Result<ModuleView<'a>> {
+        let mut reader = BinaryReader::new(data);
+        let version = reader.read_file_header()?;
+        let mut sections = Vec::new();
+        let mut known = [None; 17];
+        while !reader.eof() {
+            let id = reader.read_var_u7()? as u8;
+            let len = reader.read_var_u32()? as usize;
+            let start = reader.original_position();
+            let data = reader.read_bytes(len)?;
+            if let Some(first @ None) = known.get_mut(usize::from(id)) {
+                *first = Some(sections.len());
+            }
+            sections.push(ViewSection {
+                id,
+                range: Range {
+                    start,
+                    end: start + len,
+                },
+                data,
+            });
+        }
+        Ok(ModuleView {
+            version,
+            sections,
+            known,
+        })
+    }
+
+    /// Returns the version number in the header of the module.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns all the sections of the module in order.
+    pub fn sections(&self) -> &[ViewSection<'a>] {
+        &self.sections
+    }
+
+    /// Returns the first section with the id `id`, if any.
+    pub fn section(&self, id: u8) -> Option<&ViewSection<'a>> {
+        match self.known.get(usize::from(id)) {
+            Some(index) => index.map(|i| &self.sections[i]),
+            None => self.sections.iter().find(|s| s.id == id),
+        }
+    }
+
+    fn reader<T>(&self, id: u8, ctor: fn(&'a [u8], usize) -> Result<T>) -> Result<Option<T>> {
+        match self.section(id) {
+            Some(section) => Ok(Some(ctor(section.data, section.range.start)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns a reader for the type section of the module, if it has one.
+    pub fn type_section(&self) -> Result<Option<TypeSectionReader<'a>>> {
+        self.reader(1, TypeSectionReader::new)
+    }
+
+    /// Returns a reader for the import section of the module, if it has one.
+    pub fn import_section(&self) -> Result<Option<ImportSectionReader<'a>>> {
+        self.reader(2, ImportSectionReader::new)
+    }
+
+    /// Returns a reader for the function section of the module, if it has
+    /// one.
+    pub fn function_section(&self) -> Result<Option<FunctionSectionReader<'a>>> {
+        self.reader(3, FunctionSectionReader::new)
+    }
+
+    /// Returns a reader for the table section of the module, if it has one.
+    pub fn table_section(&self) -> Result<Option<TableSectionReader<'a>>> {
+        self.reader(4, TableSectionReader::new)
+    }
+
+    /// Returns a reader for the memory section of the module, if it has one.
+    pub fn memory_section(&self) -> Result<Option<MemorySectionReader<'a>>> {
+        self.reader(5, MemorySectionReader::new)
+    }
+
+    /// Returns a reader for the global section of the module, if it has one.
+    pub fn global_section(&self) -> Result<Option<GlobalSectionReader<'a>>> {
+        self.reader(6, GlobalSectionReader::new)
+    }
+
+    /// Returns a reader for the export section of the module, if it has one.
+    pub fn export_section(&self) -> Result<Option<ExportSectionReader<'a>>> {
+        self.reader(7, ExportSectionReader::new)
+    }
+
+    /// Returns the index of the start function of the module, if it has one.
+    pub fn start_func(&self) -> Result<Option<u32>> {
+        self.single_u32(8, "start")
+    }
+
+    /// Returns a reader for the element section of the module, if it has
+    /// one.
+    pub fn element_section(&self) -> Result<Option<ElementSectionReader<'a>>> {
+        self.reader(9, ElementSectionReader::new)
+    }
+
+    /// Returns a reader for the code section of the module, if it has one.
+    ///
+    /// Function bodies are only decoded as they're read from the returned
+    /// reader.
+    pub fn code_section(&self) -> Result<Option<CodeSectionReader<'a>>> {
+        self.reader(10, CodeSectionReader::new)
+    }
+
+    /// Returns a reader for the data section of the module, if it has one.
+    pub fn data_section(&self) -> Result<Option<DataSectionReader<'a>>> {
+        self.reader(11, DataSectionReader::new)
+    }
+
+    /// Returns the count in the data count section of the module, if it has
+    /// one.
+    pub fn data_count(&self) -> Result<Option<u32>> {
+        self.single_u32(12, "data count")
+    }
+
+    /// Returns a reader for the event section of the module, if it has one.
+    pub fn event_section(&self) -> Result<Option<EventSectionReader<'a>>> {
+        self.reader(13, EventSectionReader::new)
+    }
+
+    /// Returns a reader for the first instance section of the module, if it
+    /// has one.
+    pub fn instance_section(&self) -> Result<Option<InstanceSectionReader<'a>>> {
+        self.reader(15, InstanceSectionReader::new)
+    }
+
+    /// Returns a reader for the first alias section of the module, if it has
+    /// one.
+    pub fn alias_section(&self) -> Result<Option<AliasSectionReader<'a>>> {
+        self.reader(16, AliasSectionReader::new)
+    }
+
+    fn single_u32(&self, id: u8, desc: &str) -> Result<Option<u32>> {
+        let section = match self.section(id) {
+            Some(section) => section,
+            None => return Ok(None),
+        };
+        let mut reader = BinaryReader::new_with_offset(section.data, section.range.start);
+        let value = reader.read_var_u32()?;
+        if !reader.eof() {
+            return Err(BinaryReaderError::new(
+                ErrorCode::SizeMismatch,
+                alloc::format!("Unexpected content in the {} section", desc),
+                reader.original_position(),
+            ));
+        }
+        Ok(Some(value))
+    }
+
+    /// Returns the custom sections of the module in order.
+    ///
+    /// Only the name of each custom section is read.
+    pub fn custom_sections(&self) -> impl Iterator<Item = Result<CustomSectionView<'a>>> + '_ {
+        self.sections.iter().filter(|s| s.id == 0).map(|section| {
+            let mut reader = BinaryReader::new_with_offset(section.data, section.range.start);
+            let name = reader.read_string()?;
+            Ok(CustomSectionView {
+                name,
+                data_offset: reader.original_position(),
+                data: reader.remaining_buffer(),
+            })
+        })
+    }
+
+    /// Returns the first custom section of the module named `name`, if any.
+    pub fn custom_section(&self, name: &str) -> Result<Option<CustomSectionView<'a>>> {
+        for section in self.custom_sections() {
+            let section = section?;
+            if section.name == name {
+                return Ok(Some(section));
+            }
+        }
+        Ok(None)
+    }
+}
@@ -22,9 +22,14 @@
 // confusing it's recomended to read over that section to see how it maps to
 // the various methods here.
 
-use crate::limits::MAX_WASM_FUNCTION_LOCALS;
 use crate::primitives::{MemoryImmediate, Operator, SIMDLaneIndex, Type, TypeOrFuncType};
-use crate::{BinaryReaderError, Result, WasmFeatures, WasmFuncType, WasmModuleResources};
+use crate::{
+    BinaryReaderError, ErrorCode, Result, WasmFeatures, WasmFuncType, WasmLimits,
+    WasmModuleResources,
+};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
 
 /// A wrapper around a `BinaryReaderError` where the inner error's offset is a
 /// temporary placeholder value. This can be converted into a proper
@@ -34,8 +39,8 @@ pub(crate) struct OperatorValidatorError(pub(crate) BinaryReaderError);
 
 /// Create an `OperatorValidatorError` with a format string.
 macro_rules! format_op_err {
-    ( $( $arg:expr ),* $(,)* ) => {
-        OperatorValidatorError::new(format!( $( $arg ),* ))
+    ( $code:expr, $( $arg:expr ),* $(,)* ) => {
+        OperatorValidatorError::new($code, format!( $( $arg ),* ))
     }
 }
 
@@ -48,26 +53,26 @@ macro_rules! bail_op_err {
 
 impl OperatorValidatorError {
     /// Create a new `OperatorValidatorError` with a placeholder offset.
-    pub(crate) fn new(message: impl Into<String>) -> Self {
-        let offset = std::usize::MAX;
-        let e = BinaryReaderError::new(message, offset);
+    pub(crate) fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        let offset = core::usize::MAX;
+        let e = BinaryReaderError::new(code, message, offset);
         OperatorValidatorError(e)
     }
 
     /// Convert this `OperatorValidatorError` into a `BinaryReaderError` by
     /// supplying an actual offset to replace the internal placeholder offset.
     pub(crate) fn set_offset(mut self, offset: usize) -> BinaryReaderError {
-        debug_assert_eq!(self.0.inner.offset, std::usize::MAX);
+        debug_assert_eq!(self.0.inner.offset, core::usize::MAX);
         self.0.inner.offset = offset;
         self.0
     }
 }
 
-type OperatorValidatorResult<T> = std::result::Result<T, OperatorValidatorError>;
+type OperatorValidatorResult<T> = core::result::Result<T, OperatorValidatorError>;
 
 pub(crate) struct OperatorValidator {
     // The total number of locals that this function contains
-    num_locals: u32,
+    pub(crate) num_locals: u32,
     // This is a "compressed" list of locals for this function. The list of
     // locals are represented as a list of tuples. The second element is the
     // type of the local, and the first element is monotonically increasing as
@@ -79,6 +84,10 @@ pub(crate) struct OperatorValidator {
     // it either lies in a "hole" where the maximum index is specified later,
     // or it's at the end of the list meaning it's out of bounds.
     locals: Vec<(u32, Type)>,
+    // The locals declared by the `let` blocks that we're currently in, in the
+    // same compressed form as `locals`. Each `let` frame owns a run of entries
+    // at the end of this list, with indices starting over at 0 for each run.
+    let_locals: Vec<(u32, Type)>,
 
     // The `operands` is the current type stack, and the `control` list is the
     // list of blocks that we're currently in.
@@ -88,6 +97,9 @@ pub(crate) struct OperatorValidator {
     // This is a list of flags for wasm features which are used to gate various
     // instructions.
     features: WasmFeatures,
+
+    // Implementation limits, such as the maximum number of locals.
+    pub(crate) limits: WasmLimits,
 }
 
 // This structure corresponds to `ctrl_frame` as specified at in the validation
@@ -102,6 +114,9 @@ struct Frame {
     height: usize,
     // Whether this frame is unreachable so far.
     unreachable: bool,
+    // The number of entries of `let_locals` declared by this frame, which is
+    // only non-zero for `let` blocks.
+    let_locals: usize,
 }
 
 #[derive(PartialEq, Copy, Clone)]
@@ -111,71 +126,135 @@ enum FrameKind {
     Else,
     Loop,
     Try,
+    Let,
     Catch,
     CatchAll,
     Unwind,
 }
 
+/// Heap allocations used by an `OperatorValidator` which can be reused
+/// between functions once validation of a function has finished.
+#[derive(Default)]
+pub(crate) struct OperatorValidatorAllocations {
+    locals: Vec<(u32, Type)>,
+    let_locals: Vec<(u32, Type)>,
+    operands: Vec<Option<Type>>,
+    control: Vec<Frame>,
+}
+
 impl OperatorValidator {
     pub fn new(
         ty: u32,
         offset: usize,
         features: &WasmFeatures,
+        limits: &WasmLimits,
         resources: &impl WasmModuleResources,
+        allocs: OperatorValidatorAllocations,
     ) -> Result<OperatorValidator> {
-        let locals = func_type_at(resources, ty)
-            .map_err(|e| e.set_offset(offset))?
-            .inputs()
-            .enumerate()
-            .map(|(i, ty)| (i as u32, ty))
-            .collect::<Vec<_>>();
+        let OperatorValidatorAllocations {
+            mut locals,
+            mut let_locals,
+            mut operands,
+            mut control,
+        } = allocs;
+        locals.clear();
+        let_locals.clear();
+        operands.clear();
+        control.clear();
+
+        locals.extend(
+            func_type_at(resources, ty)
+                .map_err(|e| e.set_offset(offset))?
+                .inputs()
+                .enumerate()
+                .map(|(i, ty)| (i as u32, ty)),
+        );
+        control.push(Frame {
+            kind: FrameKind::Block,
+            block_type: TypeOrFuncType::FuncType(ty),
+            height: 0,
+            unreachable: false,
+            let_locals: 0,
+        });
         Ok(OperatorValidator {
             num_locals: locals.len() as u32,
             locals,
-            operands: Vec::new(),
-            control: vec![Frame {
-                kind: FrameKind::Block,
-                block_type: TypeOrFuncType::FuncType(ty),
-                height: 0,
-                unreachable: false,
-            }],
+            let_locals,
+            operands,
+            control,
             features: *features,
+            limits: *limits,
         })
     }
 
+    /// Tears down this validator, returning its allocations for reuse.
+    pub fn into_allocations(self) -> OperatorValidatorAllocations {
+        OperatorValidatorAllocations {
+            locals: self.locals,
+            let_locals: self.let_locals,
+            operands: self.operands,
+            control: self.control,
+        }
+    }
+
     pub fn define_locals(&mut self, offset: usize, count: u32, ty: Type) -> Result<()> {
         self.features
             .check_value_type(ty)
-            .map_err(|e| BinaryReaderError::new(e, offset))?;
+            .map_err(|(code, e)| BinaryReaderError::new(code, e, offset))?;
         if count == 0 {
             return Ok(());
         }
         match self.num_locals.checked_add(count) {
             Some(n) => self.num_locals = n,
-            None => return Err(BinaryReaderError::new("locals overflow", offset)),
+            None => {
+                return Err(BinaryReaderError::new(
+                    ErrorCode::LimitExceeded,
+                    "locals overflow",
+                    offset,
+                ))
+            }
         }
-        if self.num_locals > (MAX_WASM_FUNCTION_LOCALS as u32) {
-            return Err(BinaryReaderError::new("locals exceed maximum", offset));
+        if self.num_locals > self.limits.max_function_locals {
+            return Err(BinaryReaderError::new(
+                ErrorCode::LimitExceeded,
+                self.too_many_locals(self.num_locals),
+                offset,
+            ));
         }
         self.locals.push((self.num_locals - 1, ty));
         Ok(())
     }
 
+    fn too_many_locals(&self, count: u32) -> String {
+        format!(
+            "locals exceed maximum: function has {} locals, but the limit is {}",
+            count, self.limits.max_function_locals
+        )
+    }
+
     /// Fetches the type for the local at `idx`, returning an error if it's out
     /// of bounds.
+    ///
+    /// The locals of `let` blocks are prepended to the locals of the
+    /// enclosing code, so the innermost `let` block's locals come first.
     fn local(&self, idx: u32) -> OperatorValidatorResult<Type> {
-        match self.locals.binary_search_by_key(&idx, |(idx, _)| *idx) {
-            // If this index would be inserted at the end of the list, then the
-            // index is out of bounds and we return an error.
-            Err(i) if i == self.locals.len() => {
-                bail_op_err!("unknown local {}: local index out of bounds", idx)
-            }
-            // If `Ok` is returned we found the index exactly, or if `Err` is
-            // returned the position is the one which is the least index
-            // greater thatn `idx`, which is still the type of `idx` according
-            // to our "compressed" representation. In both cases we access the
-            // list at index `i`.
-            Ok(i) | Err(i) => Ok(self.locals[i].1),
+        let mut rel = idx;
+        let mut end = self.let_locals.len();
+        for frame in self.control.iter().rev().filter(|f| f.let_locals > 0) {
+            let scope = &self.let_locals[end - frame.let_locals..end];
+            end -= frame.let_locals;
+            match compressed_local(scope, rel) {
+                Some(ty) => return Ok(ty),
+                None => rel -= scope[scope.len() - 1].0 + 1,
+            }
+        }
+        match compressed_local(&self.locals, rel) {
+            Some(ty) => Ok(ty),
+            None => bail_op_err!(
+                ErrorCode::UnknownLocal,
+                "unknown local {}: local index out of bounds",
+                idx
+            ),
         }
     }
 
@@ -187,7 +266,7 @@ impl OperatorValidator {
     fn push_operand(&mut self, ty: Type) -> OperatorValidatorResult<()> {
         self.features
             .check_value_type(ty)
-            .map_err(OperatorValidatorError::new)?;
+            .map_err(|(code, e)| OperatorValidatorError::new(code, e))?;
         self.operands.push(Some(ty));
         Ok(())
     }
@@ -220,7 +299,11 @@ impl OperatorValidator {
                     Some(ty) => ty_to_str(ty),
                     None => "a type",
                 };
-                bail_op_err!("type mismatch: expected {} but nothing on stack", desc)
+                bail_op_err!(
+                    ErrorCode::TypeMismatch,
+                    "type mismatch: expected {} but nothing on stack",
+                    desc
+                )
             }
         } else {
             self.operands.pop().unwrap()
@@ -235,6 +318,7 @@ impl OperatorValidator {
         };
         if actual_ty != expected_ty {
             bail_op_err!(
+                ErrorCode::TypeMismatch,
                 "type mismatch: expected {}, found {}",
                 ty_to_str(expected_ty),
                 ty_to_str(actual_ty)
@@ -264,6 +348,13 @@ impl OperatorValidator {
         ty: TypeOrFuncType,
         resources: &impl WasmModuleResources,
     ) -> OperatorValidatorResult<()> {
+        if self.control.len() >= self.limits.max_nesting_depth as usize {
+            bail_op_err!(
+                ErrorCode::LimitExceeded,
+                "control frames are nested more than {} deep",
+                self.limits.max_nesting_depth
+            );
+        }
         // Push a new frame which has a snapshot of the height of the current
         // operand stack.
         self.control.push(Frame {
@@ -271,6 +362,7 @@ impl OperatorValidator {
             block_type: ty,
             height: self.operands.len(),
             unreachable: false,
+            let_locals: 0,
         });
         // All of the parameters are now also available in this control frame,
         // so we push them here in order.
@@ -290,6 +382,12 @@ impl OperatorValidator {
         let frame = self.control.last().unwrap();
         let ty = frame.block_type;
         let height = frame.height;
+        let what = if self.control.len() == 1 {
+            "function"
+        } else {
+            "block"
+        };
+        self.check_result_arity(ty, what, true, resources)?;
 
         // Pop all the result types, in reverse order, from the operand stack.
         // These types will, possibly, be transferred to the next frame.
@@ -300,11 +398,63 @@ impl OperatorValidator {
         // Make sure that the operand stack has returned to is original
         // height...
         if self.operands.len() != height {
-            bail_op_err!("type mismatch: values remaining on stack at end of block");
+            bail_op_err!(
+                ErrorCode::TypeMismatch,
+                "type mismatch: values remaining on stack at end of block"
+            );
         }
 
-        // And then we can remove it!
-        Ok(self.control.pop().unwrap())
+        // And then we can remove it, along with any locals it declared.
+        let frame = self.control.pop().unwrap();
+        if frame.let_locals > 0 {
+            let start = self.let_locals.len() - frame.let_locals;
+            self.num_locals -= self.let_locals[self.let_locals.len() - 1].0 + 1;
+            self.let_locals.truncate(start);
+        }
+        Ok(frame)
+    }
+
+    /// Checks that the current frame has as many values on the stack as `ty`
+    /// has results, or at least as many unless `exact`, before they're
+    /// popped.
+    ///
+    /// This only reports types with multiple results, for which the error of
+    /// the first value that's missing or left over is rather confusing. `what`
+    /// describes what `ty` is the type of.
+    fn check_result_arity(
+        &self,
+        ty: TypeOrFuncType,
+        what: &str,
+        exact: bool,
+        resources: &impl WasmModuleResources,
+    ) -> OperatorValidatorResult<()> {
+        let control = self.control.last().unwrap();
+        let expected = results(ty, resources)?.collect::<Vec<_>>();
+        let actual = self.operands.len() - control.height;
+        if expected.len() < 2
+            || control.unreachable
+            || actual == expected.len()
+            || (!exact && actual > expected.len())
+        {
+            return Ok(());
+        }
+        bail_op_err!(
+            ErrorCode::TypeMismatch,
+            "type mismatch: {} returns {} values [{}] but {} {} on the stack",
+            what,
+            expected.len(),
+            expected
+                .iter()
+                .map(|ty| ty_to_str(*ty))
+                .collect::<Vec<_>>()
+                .join(" "),
+            actual,
+            if actual == 1 {
+                "value is"
+            } else {
+                "values are"
+            },
+        )
     }
 
     /// Validates a relative jump to the `depth` specified.
@@ -317,7 +467,10 @@ impl OperatorValidator {
                 let frame = &self.control[i];
                 Ok((frame.block_type, frame.kind))
             }
-            None => bail_op_err!("unknown label: branch depth too large"),
+            None => bail_op_err!(
+                ErrorCode::UnknownLabel,
+                "unknown label: branch depth too large"
+            ),
         }
     }
 
@@ -330,12 +483,13 @@ impl OperatorValidator {
     ) -> OperatorValidatorResult<Type> {
         if memory_index > 0 && !self.features.multi_memory {
             return Err(OperatorValidatorError::new(
+                ErrorCode::FeatureNotEnabled,
                 "multi-memory support is not enabled",
             ));
         }
         match resources.memory_at(memory_index) {
             Some(mem) => Ok(mem.index_type()),
-            None => bail_op_err!("unknown memory {}", memory_index),
+            None => bail_op_err!(ErrorCode::UnknownMemory, "unknown memory {}", memory_index),
         }
     }
 
@@ -351,6 +505,7 @@ impl OperatorValidator {
         let align = memarg.align;
         if align > max_align {
             return Err(OperatorValidatorError::new(
+                ErrorCode::InvalidAlignment,
                 "alignment must not be larger than natural",
             ));
         }
@@ -361,6 +516,7 @@ impl OperatorValidator {
     fn check_non_deterministic_enabled(&self) -> OperatorValidatorResult<()> {
         if !self.features.deterministic_only {
             return Err(OperatorValidatorError::new(
+                ErrorCode::FeatureNotEnabled,
                 "deterministic_only support is not enabled",
             ));
         }
@@ -376,6 +532,7 @@ impl OperatorValidator {
     fn check_threads_enabled(&self) -> OperatorValidatorResult<()> {
         if !self.features.threads {
             return Err(OperatorValidatorError::new(
+                ErrorCode::FeatureNotEnabled,
                 "threads support is not enabled",
             ));
         }
@@ -385,6 +542,7 @@ impl OperatorValidator {
     fn check_reference_types_enabled(&self) -> OperatorValidatorResult<()> {
         if !self.features.reference_types {
             return Err(OperatorValidatorError::new(
+                ErrorCode::FeatureNotEnabled,
                 "reference types support is not enabled",
             ));
         }
@@ -393,7 +551,10 @@ impl OperatorValidator {
 
     fn check_simd_enabled(&self) -> OperatorValidatorResult<()> {
         if !self.features.simd {
-            return Err(OperatorValidatorError::new("SIMD support is not enabled"));
+            return Err(OperatorValidatorError::new(
+                ErrorCode::FeatureNotEnabled,
+                "SIMD support is not enabled",
+            ));
         }
         Ok(())
     }
@@ -401,6 +562,7 @@ impl OperatorValidator {
     fn check_exceptions_enabled(&self) -> OperatorValidatorResult<()> {
         if !self.features.exceptions {
             return Err(OperatorValidatorError::new(
+                ErrorCode::FeatureNotEnabled,
                 "Exceptions support is not enabled",
             ));
         }
@@ -410,12 +572,33 @@ impl OperatorValidator {
     fn check_bulk_memory_enabled(&self) -> OperatorValidatorResult<()> {
         if !self.features.bulk_memory {
             return Err(OperatorValidatorError::new(
+                ErrorCode::FeatureNotEnabled,
                 "bulk memory support is not enabled",
             ));
         }
         Ok(())
     }
 
+    fn check_function_references_enabled(&self) -> OperatorValidatorResult<()> {
+        if !self.features.function_references {
+            return Err(OperatorValidatorError::new(
+                ErrorCode::FeatureNotEnabled,
+                "function references support is not enabled",
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_wide_arithmetic_enabled(&self) -> OperatorValidatorResult<()> {
+        if !self.features.wide_arithmetic {
+            return Err(OperatorValidatorError::new(
+                ErrorCode::FeatureNotEnabled,
+                "wide arithmetic support is not enabled",
+            ));
+        }
+        Ok(())
+    }
+
     fn check_shared_memarg_wo_align(
         &self,
         memarg: MemoryImmediate,
@@ -426,7 +609,10 @@ impl OperatorValidator {
 
     fn check_simd_lane_index(&self, index: SIMDLaneIndex, max: u8) -> OperatorValidatorResult<()> {
         if index >= max {
-            return Err(OperatorValidatorError::new("SIMD index out of bounds"));
+            return Err(OperatorValidatorError::new(
+                ErrorCode::InvalidLaneIndex,
+                "SIMD index out of bounds",
+            ));
         }
         Ok(())
     }
@@ -452,12 +638,14 @@ impl OperatorValidator {
                 if !self.features.multi_value {
                     if ty.len_outputs() > 1 {
                         return Err(OperatorValidatorError::new(
+                            ErrorCode::FeatureNotEnabled,
                             "blocks, loops, and ifs may only return at most one \
                              value when multi-value is not enabled",
                         ));
                     }
                     if ty.len_inputs() > 0 {
                         return Err(OperatorValidatorError::new(
+                            ErrorCode::FeatureNotEnabled,
                             "blocks, loops, and ifs accept no parameters \
                              when multi-value is not enabled",
                         ));
@@ -465,7 +653,10 @@ impl OperatorValidator {
                 }
                 Ok(())
             }
-            _ => Err(OperatorValidatorError::new("invalid block return type")),
+            _ => Err(OperatorValidatorError::new(
+                ErrorCode::InvalidType,
+                "invalid block return type",
+            )),
         }
     }
 
@@ -480,6 +671,7 @@ impl OperatorValidator {
             Some(i) => i,
             None => {
                 bail_op_err!(
+                    ErrorCode::UnknownFunction,
                     "unknown function {}: function index out of bounds",
                     function_index
                 );
@@ -504,12 +696,14 @@ impl OperatorValidator {
         match resources.table_at(table_index) {
             None => {
                 return Err(OperatorValidatorError::new(
+                    ErrorCode::UnknownTable,
                     "unknown table: table index out of bounds",
                 ));
             }
             Some(tab) => {
                 if tab.element_type != Type::FuncRef {
                     return Err(OperatorValidatorError::new(
+                        ErrorCode::InvalidType,
                         "indirect calls must go through a table of funcref",
                     ));
                 }
@@ -532,6 +726,7 @@ impl OperatorValidator {
         &mut self,
         resources: &impl WasmModuleResources,
     ) -> OperatorValidatorResult<()> {
+        self.check_result_arity(self.control[0].block_type, "function", false, resources)?;
         for ty in results(self.control[0].block_type, resources)?.rev() {
             self.pop_operand(Some(ty))?;
         }
@@ -545,7 +740,10 @@ impl OperatorValidator {
         resources: &impl WasmModuleResources,
     ) -> OperatorValidatorResult<()> {
         if self.control.len() == 0 {
-            bail_op_err!("operators remaining after end of function");
+            bail_op_err!(
+                ErrorCode::ControlMismatch,
+                "operators remaining after end of function"
+            );
         }
         match *operator {
             Operator::Nop => {}
@@ -592,9 +790,13 @@ impl OperatorValidator {
                             block_type: frame.block_type,
                             height: self.operands.len(),
                             unreachable: false,
+                            let_locals: 0,
                         });
                     }
-                    _ => bail_op_err!("else found outside of an `if` block"),
+                    _ => bail_op_err!(
+                        ErrorCode::ControlMismatch,
+                        "else found outside of an `if` block"
+                    ),
                 }
             }
             Operator::Try { ty } => {
@@ -605,11 +807,71 @@ impl OperatorValidator {
                 }
                 self.push_ctrl(FrameKind::Try, ty, resources)?;
             }
+            Operator::Let { ty, ref locals } => {
+                self.check_function_references_enabled()?;
+                self.check_block_type(ty, resources)?;
+                let mut scope = Vec::new();
+                let mut count = 0u32;
+                for local in locals.locals() {
+                    let (n, ty) = local.map_err(|mut e| {
+                        e.inner.offset = usize::MAX;
+                        OperatorValidatorError(e)
+                    })?;
+                    self.features
+                        .check_value_type(ty)
+                        .map_err(|(code, e)| OperatorValidatorError::new(code, e))?;
+                    if n == 0 {
+                        continue;
+                    }
+                    count = match count.checked_add(n) {
+                        Some(count) => count,
+                        None => {
+                            return Err(OperatorValidatorError::new(
+                                ErrorCode::LimitExceeded,
+                                "locals overflow",
+                            ))
+                        }
+                    };
+                    scope.push((count - 1, ty));
+                }
+                match self.num_locals.checked_add(count) {
+                    Some(n) if n <= self.limits.max_function_locals => self.num_locals = n,
+                    Some(n) => {
+                        return Err(OperatorValidatorError::new(
+                            ErrorCode::LimitExceeded,
+                            self.too_many_locals(n),
+                        ))
+                    }
+                    None => {
+                        return Err(OperatorValidatorError::new(
+                            ErrorCode::LimitExceeded,
+                            "locals overflow",
+                        ))
+                    }
+                }
+                // The locals are initialized with values from the operand
+                // stack, which are above the parameters of the block.
+                for i in (0..scope.len()).rev() {
+                    let first = if i == 0 { 0 } else { scope[i - 1].0 + 1 };
+                    for _ in first..=scope[i].0 {
+                        self.pop_operand(Some(scope[i].1))?;
+                    }
+                }
+                for ty in params(ty, resources)?.rev() {
+                    self.pop_operand(Some(ty))?;
+                }
+                self.push_ctrl(FrameKind::Let, ty, resources)?;
+                self.control.last_mut().unwrap().let_locals = scope.len();
+                self.let_locals.extend(scope);
+            }
             Operator::Catch { index } => {
                 self.check_exceptions_enabled()?;
                 let frame = self.pop_ctrl(resources)?;
                 if frame.kind != FrameKind::Try && frame.kind != FrameKind::Catch {
-                    bail_op_err!("catch found outside of an `try` block");
+                    bail_op_err!(
+                        ErrorCode::ControlMismatch,
+                        "catch found outside of an `try` block"
+                    );
                 }
                 // Start a new frame and push `exnref` value.
                 self.control.push(Frame {
@@ -617,6 +879,7 @@ impl OperatorValidator {
                     block_type: frame.block_type,
                     height: self.operands.len(),
                     unreachable: false,
+                    let_locals: 0,
                 });
                 // Push exception argument types.
                 let ty = event_at(&resources, index)?;
@@ -632,7 +895,10 @@ impl OperatorValidator {
                     self.pop_operand(Some(ty))?;
                 }
                 if ty.outputs().len() > 0 {
-                    bail_op_err!("result type expected to be empty for exception");
+                    bail_op_err!(
+                        ErrorCode::ControlMismatch,
+                        "result type expected to be empty for exception"
+                    );
                 }
                 self.unreachable();
             }
@@ -642,7 +908,10 @@ impl OperatorValidator {
                 // targets an actual `catch` to get the exception.
                 let (_, kind) = self.jump(relative_depth)?;
                 if kind != FrameKind::Catch && kind != FrameKind::CatchAll {
-                    bail_op_err!("rethrow target was not a `catch` block");
+                    bail_op_err!(
+                        ErrorCode::ControlMismatch,
+                        "rethrow target was not a `catch` block"
+                    );
                 }
                 self.unreachable();
             }
@@ -652,13 +921,17 @@ impl OperatorValidator {
                 // the result type is empty.
                 let frame = self.pop_ctrl(resources)?;
                 if frame.kind != FrameKind::Try {
-                    bail_op_err!("unwind found outside of an `try` block");
+                    bail_op_err!(
+                        ErrorCode::ControlMismatch,
+                        "unwind found outside of an `try` block"
+                    );
                 }
                 self.control.push(Frame {
                     kind: FrameKind::Unwind,
                     block_type: TypeOrFuncType::Type(Type::EmptyBlockType),
                     height: self.operands.len(),
                     unreachable: false,
+                    let_locals: 0,
                 });
             }
             Operator::End => {
@@ -674,7 +947,7 @@ impl OperatorValidator {
                         frame = self.pop_ctrl(resources)?;
                     }
                     FrameKind::Try => {
-                        bail_op_err!("expected catch block");
+                        bail_op_err!(ErrorCode::ControlMismatch, "expected catch block");
                     }
                     _ => (),
                 }
@@ -703,11 +976,18 @@ impl OperatorValidator {
             Operator::BrTable { ref table } => {
                 self.pop_operand(Some(Type::I32))?;
                 let mut label = None;
+                let mut prev_depth = None;
                 for element in table.targets() {
                     let (relative_depth, _is_default) = element.map_err(|mut e| {
                         e.inner.offset = usize::max_value();
                         OperatorValidatorError(e)
                     })?;
+                    // Runs of the same target are common in large tables and
+                    // were already checked.
+                    if prev_depth == Some(relative_depth) {
+                        continue;
+                    }
+                    prev_depth = Some(relative_depth);
                     let block = self.jump(relative_depth)?;
                     match label {
                         None => label = Some(block),
@@ -716,6 +996,7 @@ impl OperatorValidator {
                             let b = label_types(prev.0, resources, prev.1)?;
                             if a.ne(b) {
                                 bail_op_err!(
+                                    ErrorCode::TypeMismatch,
                                     "type mismatch: br_table target labels have different types"
                                 );
                             }
@@ -733,6 +1014,7 @@ impl OperatorValidator {
             Operator::ReturnCall { function_index } => {
                 if !self.features.tail_call {
                     return Err(OperatorValidatorError::new(
+                        ErrorCode::FeatureNotEnabled,
                         "tail calls support is not enabled",
                     ));
                 }
@@ -745,6 +1027,7 @@ impl OperatorValidator {
             Operator::ReturnCallIndirect { index, table_index } => {
                 if !self.features.tail_call {
                     return Err(OperatorValidatorError::new(
+                        ErrorCode::FeatureNotEnabled,
                         "tail calls support is not enabled",
                     ));
                 }
@@ -767,7 +1050,10 @@ impl OperatorValidator {
                         self.operands.push(ty)
                     }
                     None => self.operands.push(None),
-                    Some(_) => bail_op_err!("type mismatch: select only takes integral types"),
+                    Some(_) => bail_op_err!(
+                        ErrorCode::TypeMismatch,
+                        "type mismatch: select only takes integral types"
+                    ),
                 }
             }
             Operator::TypedSelect { ty } => {
@@ -794,6 +1080,7 @@ impl OperatorValidator {
                     self.push_operand(ty.content_type)?;
                 } else {
                     return Err(OperatorValidatorError::new(
+                        ErrorCode::UnknownGlobal,
                         "unknown global: global index out of bounds",
                     ));
                 };
@@ -802,12 +1089,14 @@ impl OperatorValidator {
                 if let Some(ty) = resources.global_at(global_index) {
                     if !ty.mutable {
                         return Err(OperatorValidatorError::new(
+                            ErrorCode::ImmutableGlobal,
                             "global is immutable: cannot modify it with `global.set`",
                         ));
                     }
                     self.pop_operand(Some(ty.content_type))?;
                 } else {
                     return Err(OperatorValidatorError::new(
+                        ErrorCode::UnknownGlobal,
                         "unknown global: global index out of bounds",
                     ));
                 };
@@ -908,14 +1197,20 @@ impl OperatorValidator {
             }
             Operator::MemorySize { mem, mem_byte } => {
                 if mem_byte != 0 && !self.features.multi_memory {
-                    return Err(OperatorValidatorError::new("multi-memory not enabled"));
+                    return Err(OperatorValidatorError::new(
+                        ErrorCode::FeatureNotEnabled,
+                        "multi-memory not enabled",
+                    ));
                 }
                 let index_ty = self.check_memory_index(mem, resources)?;
                 self.push_operand(index_ty)?;
             }
             Operator::MemoryGrow { mem, mem_byte } => {
                 if mem_byte != 0 && !self.features.multi_memory {
-                    return Err(OperatorValidatorError::new("multi-memory not enabled"));
+                    return Err(OperatorValidatorError::new(
+                        ErrorCode::FeatureNotEnabled,
+                        "multi-memory not enabled",
+                    ));
                 }
                 let index_ty = self.check_memory_index(mem, resources)?;
                 self.pop_operand(Some(index_ty))?;
@@ -1327,6 +1622,7 @@ impl OperatorValidator {
                 self.check_threads_enabled()?;
                 if *flags != 0 {
                     return Err(OperatorValidatorError::new(
+                        ErrorCode::InvalidEncoding,
                         "non-zero flags for fence not supported yet",
                     ));
                 }
@@ -1337,6 +1633,7 @@ impl OperatorValidator {
                     Type::FuncRef | Type::ExternRef => {}
                     _ => {
                         return Err(OperatorValidatorError::new(
+                            ErrorCode::InvalidType,
                             "invalid reference type in ref.null",
                         ))
                     }
@@ -1349,6 +1646,7 @@ impl OperatorValidator {
                     None | Some(Type::FuncRef) | Some(Type::ExternRef) => {}
                     _ => {
                         return Err(OperatorValidatorError::new(
+                            ErrorCode::TypeMismatch,
                             "type mismatch: invalid reference type in ref.is_null",
                         ))
                     }
@@ -1359,11 +1657,15 @@ impl OperatorValidator {
                 self.check_reference_types_enabled()?;
                 if resources.type_of_function(function_index).is_none() {
                     return Err(OperatorValidatorError::new(
+                        ErrorCode::UnknownFunction,
                         "unknown function: function index out of bounds",
                     ));
                 }
                 if !resources.is_function_referenced(function_index) {
-                    return Err(OperatorValidatorError::new("undeclared function reference"));
+                    return Err(OperatorValidatorError::new(
+                        ErrorCode::UndeclaredFunctionReference,
+                        "undeclared function reference",
+                    ));
                 }
                 self.push_operand(Type::FuncRef)?;
             }
@@ -1804,7 +2106,11 @@ impl OperatorValidator {
                 self.check_bulk_memory_enabled()?;
                 let ty = self.check_memory_index(mem, resources)?;
                 if segment >= resources.data_count() {
-                    bail_op_err!("unknown data segment {}", segment);
+                    bail_op_err!(
+                        ErrorCode::UnknownDataSegment,
+                        "unknown data segment {}",
+                        segment
+                    );
                 }
                 self.pop_operand(Some(Type::I32))?;
                 self.pop_operand(Some(Type::I32))?;
@@ -1813,7 +2119,11 @@ impl OperatorValidator {
             Operator::DataDrop { segment } => {
                 self.check_bulk_memory_enabled()?;
                 if segment >= resources.data_count() {
-                    bail_op_err!("unknown data segment {}", segment);
+                    bail_op_err!(
+                        ErrorCode::UnknownDataSegment,
+                        "unknown data segment {}",
+                        segment
+                    );
                 }
             }
             Operator::MemoryCopy { src, dst } => {
@@ -1841,17 +2151,25 @@ impl OperatorValidator {
                 }
                 let table = match resources.table_at(table) {
                     Some(table) => table,
-                    None => bail_op_err!("unknown table {}: table index out of bounds", table),
+                    None => bail_op_err!(
+                        ErrorCode::UnknownTable,
+                        "unknown table {}: table index out of bounds",
+                        table
+                    ),
                 };
                 let segment_ty = match resources.element_type_at(segment) {
                     Some(ty) => ty,
                     None => bail_op_err!(
+                        ErrorCode::UnknownElementSegment,
                         "unknown elem segment {}: segment index out of bounds",
                         segment
                     ),
                 };
                 if segment_ty != table.element_type {
-                    return Err(OperatorValidatorError::new("type mismatch"));
+                    return Err(OperatorValidatorError::new(
+                        ErrorCode::TypeMismatch,
+                        "type mismatch",
+                    ));
                 }
                 self.pop_operand(Some(Type::I32))?;
                 self.pop_operand(Some(Type::I32))?;
@@ -1861,6 +2179,7 @@ impl OperatorValidator {
                 self.check_bulk_memory_enabled()?;
                 if segment >= resources.element_count() {
                     bail_op_err!(
+                        ErrorCode::UnknownElementSegment,
                         "unknown elem segment {}: segment index out of bounds",
                         segment
                     );
@@ -1877,10 +2196,18 @@ impl OperatorValidator {
                 let (src, dst) =
                     match (resources.table_at(src_table), resources.table_at(dst_table)) {
                         (Some(a), Some(b)) => (a, b),
-                        _ => return Err(OperatorValidatorError::new("table index out of bounds")),
+                        _ => {
+                            return Err(OperatorValidatorError::new(
+                                ErrorCode::UnknownTable,
+                                "table index out of bounds",
+                            ))
+                        }
                     };
                 if src.element_type != dst.element_type {
-                    return Err(OperatorValidatorError::new("type mismatch"));
+                    return Err(OperatorValidatorError::new(
+                        ErrorCode::TypeMismatch,
+                        "type mismatch",
+                    ));
                 }
                 self.pop_operand(Some(Type::I32))?;
                 self.pop_operand(Some(Type::I32))?;
@@ -1890,7 +2217,12 @@ impl OperatorValidator {
                 self.check_reference_types_enabled()?;
                 let ty = match resources.table_at(table) {
                     Some(ty) => ty.element_type,
-                    None => return Err(OperatorValidatorError::new("table index out of bounds")),
+                    None => {
+                        return Err(OperatorValidatorError::new(
+                            ErrorCode::UnknownTable,
+                            "table index out of bounds",
+                        ))
+                    }
                 };
                 self.pop_operand(Some(Type::I32))?;
                 self.push_operand(ty)?;
@@ -1899,7 +2231,12 @@ impl OperatorValidator {
                 self.check_reference_types_enabled()?;
                 let ty = match resources.table_at(table) {
                     Some(ty) => ty.element_type,
-                    None => return Err(OperatorValidatorError::new("table index out of bounds")),
+                    None => {
+                        return Err(OperatorValidatorError::new(
+                            ErrorCode::UnknownTable,
+                            "table index out of bounds",
+                        ))
+                    }
                 };
                 self.pop_operand(Some(ty))?;
                 self.pop_operand(Some(Type::I32))?;
@@ -1908,7 +2245,12 @@ impl OperatorValidator {
                 self.check_reference_types_enabled()?;
                 let ty = match resources.table_at(table) {
                     Some(ty) => ty.element_type,
-                    None => return Err(OperatorValidatorError::new("table index out of bounds")),
+                    None => {
+                        return Err(OperatorValidatorError::new(
+                            ErrorCode::UnknownTable,
+                            "table index out of bounds",
+                        ))
+                    }
                 };
                 self.pop_operand(Some(Type::I32))?;
                 self.pop_operand(Some(ty))?;
@@ -1917,7 +2259,10 @@ impl OperatorValidator {
             Operator::TableSize { table } => {
                 self.check_reference_types_enabled()?;
                 if resources.table_at(table).is_none() {
-                    return Err(OperatorValidatorError::new("table index out of bounds"));
+                    return Err(OperatorValidatorError::new(
+                        ErrorCode::UnknownTable,
+                        "table index out of bounds",
+                    ));
                 }
                 self.push_operand(Type::I32)?;
             }
@@ -1925,19 +2270,42 @@ impl OperatorValidator {
                 self.check_bulk_memory_enabled()?;
                 let ty = match resources.table_at(table) {
                     Some(ty) => ty.element_type,
-                    None => return Err(OperatorValidatorError::new("table index out of bounds")),
+                    None => {
+                        return Err(OperatorValidatorError::new(
+                            ErrorCode::UnknownTable,
+                            "table index out of bounds",
+                        ))
+                    }
                 };
                 self.pop_operand(Some(Type::I32))?;
                 self.pop_operand(Some(ty))?;
                 self.pop_operand(Some(Type::I32))?;
             }
+            Operator::I64Add128 | Operator::I64Sub128 => {
+                self.check_wide_arithmetic_enabled()?;
+                for _ in 0..4 {
+                    self.pop_operand(Some(Type::I64))?;
+                }
+                self.push_operand(Type::I64)?;
+                self.push_operand(Type::I64)?;
+            }
+            Operator::I64MulWideS | Operator::I64MulWideU => {
+                self.check_wide_arithmetic_enabled()?;
+                self.pop_operand(Some(Type::I64))?;
+                self.pop_operand(Some(Type::I64))?;
+                self.push_operand(Type::I64)?;
+                self.push_operand(Type::I64)?;
+            }
         }
         Ok(())
     }
 
     pub fn finish(&mut self) -> OperatorValidatorResult<()> {
         if self.control.len() != 0 {
-            bail_op_err!("control frames remain at end of function");
+            bail_op_err!(
+                ErrorCode::ControlMismatch,
+                "control frames remain at end of function"
+            );
         }
         Ok(())
     }
@@ -1947,18 +2315,24 @@ fn func_type_at<T: WasmModuleResources>(
     resources: &T,
     at: u32,
 ) -> OperatorValidatorResult<&T::FuncType> {
-    resources
-        .func_type_at(at)
-        .ok_or_else(|| OperatorValidatorError::new("unknown type: type index out of bounds"))
+    resources.func_type_at(at).ok_or_else(|| {
+        OperatorValidatorError::new(
+            ErrorCode::UnknownType,
+            "unknown type: type index out of bounds",
+        )
+    })
 }
 
 fn event_at<T: WasmModuleResources>(
     resources: &T,
     at: u32,
 ) -> OperatorValidatorResult<&T::FuncType> {
-    resources
-        .event_at(at)
-        .ok_or_else(|| OperatorValidatorError::new("unknown event: event index out of bounds"))
+    resources.event_at(at).ok_or_else(|| {
+        OperatorValidatorError::new(
+            ErrorCode::UnknownEvent,
+            "unknown event: event index out of bounds",
+        )
+    })
 }
 
 enum Either<A, B> {
@@ -1992,6 +2366,22 @@ where
     }
 }
 
+/// Returns the type of the local at `idx` in the compressed list of `locals`,
+/// or `None` if it's out of bounds.
+fn compressed_local(locals: &[(u32, Type)], idx: u32) -> Option<Type> {
+    match locals.binary_search_by_key(&idx, |(idx, _)| *idx) {
+        // If this index would be inserted at the end of the list, then the
+        // index is out of bounds.
+        Err(i) if i == locals.len() => None,
+        // If `Ok` is returned we found the index exactly, or if `Err` is
+        // returned the position is the one which is the least index
+        // greater thatn `idx`, which is still the type of `idx` according
+        // to our "compressed" representation. In both cases we access the
+        // list at index `i`.
+        Ok(i) | Err(i) => Some(locals[i].1),
+    }
+}
+
 fn params<'a>(
     ty: TypeOrFuncType,
     resources: &'a impl WasmModuleResources,
@@ -2024,7 +2414,7 @@ fn label_types<'a>(
     })
 }
 
-fn ty_to_str(ty: Type) -> &'static str {
+pub(crate) fn ty_to_str(ty: Type) -> &'static str {
     match ty {
         Type::I32 => "i32",
         Type::I64 => "i64",
@@ -2038,3 +2428,14 @@ fn ty_to_str(ty: Type) -> &'static str {
         Type::EmptyBlockType => "nil",
     }
 }
+
+/// Formats `ty` like `[i32 i32] -> [i64]` for error messages.
+pub(crate) fn func_type_to_str(ty: &impl WasmFuncType) -> String {
+    let types =
+        |types: &mut dyn Iterator<Item = Type>| types.map(ty_to_str).collect::<Vec<_>>().join(" ");
+    format!(
+        "[{}] -> [{}]",
+        types(&mut ty.inputs()),
+        types(&mut ty.outputs())
+    )
+}
@@ -1,12 +1,14 @@
 use crate::EventSectionReader;
 use crate::{AliasSectionReader, InstanceSectionReader};
-use crate::{BinaryReader, BinaryReaderError, FunctionBody, Range, Result};
+use crate::{BinaryReader, BinaryReaderError, ErrorCode, FunctionBody, Range, Result};
 use crate::{DataSectionReader, ElementSectionReader, ExportSectionReader};
 use crate::{FunctionSectionReader, ImportSectionReader, TypeSectionReader};
 use crate::{GlobalSectionReader, MemorySectionReader, TableSectionReader};
-use std::convert::TryInto;
-use std::fmt;
-use std::iter;
+use alloc::format;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use core::fmt;
+use core::iter;
 
 /// An incremental parser of a binary WebAssembly module.
 ///
@@ -477,7 +479,11 @@ impl Parser {
                     .and_then(|s| s.checked_sub(len.into()))
                     .is_none();
                 if section_overflow {
-                    return Err(BinaryReaderError::new("section too large", len_pos));
+                    return Err(BinaryReaderError::new(
+                        ErrorCode::SizeMismatch,
+                        "section too large",
+                        len_pos,
+                    ));
                 }
 
                 match id {
@@ -584,6 +590,7 @@ impl Parser {
                 debug_assert!(len > 0);
                 let offset = reader.original_position();
                 Err(BinaryReaderError::new(
+                    ErrorCode::SizeMismatch,
                     "trailing bytes at end of section",
                     offset,
                 ))
@@ -648,6 +655,7 @@ impl Parser {
                     Some(i) => len = i,
                     None => {
                         return Err(BinaryReaderError::new(
+                            ErrorCode::UnexpectedEof,
                             "Unexpected EOF",
                             reader.original_position(),
                         ));
@@ -842,6 +850,7 @@ fn single_u32<'a>(reader: &mut BinaryReader<'a>, len: u32, desc: &str) -> Result
     let index = content.read_var_u32().map_err(clear_hint)?;
     if !content.eof() {
         return Err(BinaryReaderError::new(
+            ErrorCode::SizeMismatch,
             format!("Unexpected content in the {} section", desc),
             content.original_position(),
         ));
@@ -867,7 +876,13 @@ fn delimited<'a, T>(
         .and_then(|i| len.checked_sub(i))
     {
         Some(i) => i,
-        None => return Err(BinaryReaderError::new("Unexpected EOF", start)),
+        None => {
+            return Err(BinaryReaderError::new(
+                ErrorCode::UnexpectedEof,
+                "Unexpected EOF",
+                start,
+            ))
+        }
     };
     Ok(ret)
 }
@@ -13,9 +13,11 @@
  * limitations under the License.
  */
 
-use std::error::Error;
-use std::fmt;
-use std::result;
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::result;
 
 #[derive(Debug, Clone)]
 pub struct BinaryReaderError {
@@ -27,14 +29,28 @@ pub struct BinaryReaderError {
 
 #[derive(Debug, Clone)]
 pub(crate) struct BinaryReaderErrorInner {
+    pub(crate) code: ErrorCode,
     pub(crate) message: String,
     pub(crate) offset: usize,
     pub(crate) needed_hint: Option<usize>,
+    pub(crate) related: Vec<RelatedOffset>,
+}
+
+/// Another location in the Wasm binary which is relevant to an error, such
+/// as the first definition of a name which is defined twice.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RelatedOffset {
+    /// The offset within the Wasm binary.
+    pub offset: usize,
+    /// A description of what is at `offset`.
+    pub message: String,
 }
 
 pub type Result<T, E = BinaryReaderError> = result::Result<T, E>;
 
-impl Error for BinaryReaderError {}
+#[cfg(feature = "std")]
+impl std::error::Error for BinaryReaderError {}
 
 impl fmt::Display for BinaryReaderError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -47,13 +63,15 @@ impl fmt::Display for BinaryReaderError {
 }
 
 impl BinaryReaderError {
-    pub(crate) fn new(message: impl Into<String>, offset: usize) -> Self {
+    pub(crate) fn new(code: ErrorCode, message: impl Into<String>, offset: usize) -> Self {
         let message = message.into();
         BinaryReaderError {
             inner: Box::new(BinaryReaderErrorInner {
+                code,
                 message,
                 offset,
                 needed_hint: None,
+                related: Vec::new(),
             }),
         }
     }
@@ -61,13 +79,29 @@ impl BinaryReaderError {
     pub(crate) fn eof(offset: usize, needed_hint: usize) -> Self {
         BinaryReaderError {
             inner: Box::new(BinaryReaderErrorInner {
+                code: ErrorCode::UnexpectedEof,
                 message: "Unexpected EOF".to_string(),
                 offset,
                 needed_hint: Some(needed_hint),
+                related: Vec::new(),
             }),
         }
     }
 
+    pub(crate) fn with_related(mut self, offset: usize, message: impl Into<String>) -> Self {
+        self.inner.related.push(RelatedOffset {
+            offset,
+            message: message.into(),
+        });
+        self
+    }
+
+    /// Get this error's stable code, which identifies the kind of error
+    /// independently of its message.
+    pub fn code(&self) -> ErrorCode {
+        self.inner.code
+    }
+
     /// Get this error's message.
     pub fn message(&self) -> &str {
         &self.inner.message
@@ -77,9 +111,158 @@ impl BinaryReaderError {
     pub fn offset(&self) -> usize {
         self.inner.offset
     }
+
+    /// Get other locations in the Wasm binary which are relevant to this
+    /// error.
+    pub fn related(&self) -> &[RelatedOffset] {
+        &self.inner.related
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for BinaryReaderError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut s = serializer.serialize_struct("BinaryReaderError", 4)?;
+        s.serialize_field("code", &self.code())?;
+        s.serialize_field("message", self.message())?;
+        s.serialize_field("offset", &self.offset())?;
+        s.serialize_field("related", self.related())?;
+        s.end()
+    }
+}
+
+macro_rules! error_codes {
+    ($($(#[$doc:meta])* $name:ident = $number:expr,)*) => {
+        /// The kind of a `BinaryReaderError`.
+        ///
+        /// Every code has a stable number, written like `E0301`, which won't
+        /// change or be reused even if the wording of error messages changes.
+        /// Codes are grouped by what went wrong: `E00xx` for malformed
+        /// binaries, `E01xx` for invalid modules, `E02xx` for references to
+        /// items which don't exist and `E03xx` for invalid function bodies.
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        #[non_exhaustive]
+        pub enum ErrorCode {
+            $($(#[$doc])* $name = $number,)*
+        }
+
+        impl ErrorCode {
+            /// Returns the code with the given number, if there is one.
+            pub fn from_number(number: u16) -> Option<ErrorCode> {
+                match number {
+                    $($number => Some(ErrorCode::$name),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+error_codes! {
+    /// The binary ended before an item was complete.
+    UnexpectedEof = 1,
+    /// The magic number or version at the start of the binary is wrong.
+    InvalidHeader = 2,
+    /// A LEB128 integer is too long or out of range for its type.
+    InvalidInteger = 3,
+    /// A string isn't valid UTF-8.
+    InvalidUtf8 = 4,
+    /// An instruction's opcode isn't known.
+    UnknownOpcode = 5,
+    /// A section's id isn't known.
+    UnknownSection = 6,
+    /// An item's size doesn't match its contents, such as a section with
+    /// trailing bytes or a function body which extends past its section.
+    SizeMismatch = 7,
+    /// A byte which encodes a type, kind or set of flags isn't valid.
+    InvalidEncoding = 8,
+    /// A count or size is larger than this implementation supports.
+    LimitExceeded = 9,
+    /// A section appears after a section which it must precede.
+    SectionOrder = 101,
+    /// The binary uses a proposal which isn't enabled in `WasmFeatures`.
+    FeatureNotEnabled = 102,
+    /// The same name is defined twice.
+    DuplicateName = 103,
+    /// The limits of a memory or table aren't valid.
+    InvalidLimits = 104,
+    /// An initializer isn't a constant expression.
+    ConstantExpressionRequired = 105,
+    /// The counts of related sections, such as the function and code
+    /// sections, don't match.
+    CountMismatch = 106,
+    /// The start function's type isn't `[] -> []`.
+    InvalidStartFunction = 107,
+    /// An item can't be exported.
+    InvalidExport = 108,
+    /// An alias refers to something that doesn't exist or has another kind.
+    InvalidAlias = 109,
+    /// A type is used where it isn't allowed, such as a non-function type
+    /// for a function or a non-reference type for a table element.
+    InvalidType = 110,
+    /// A type index is out of bounds.
+    UnknownType = 201,
+    /// A function index is out of bounds.
+    UnknownFunction = 202,
+    /// A table index is out of bounds.
+    UnknownTable = 203,
+    /// A memory index is out of bounds.
+    UnknownMemory = 204,
+    /// A global index is out of bounds.
+    UnknownGlobal = 205,
+    /// An event index is out of bounds.
+    UnknownEvent = 206,
+    /// An element segment index is out of bounds.
+    UnknownElementSegment = 207,
+    /// A data segment index is out of bounds.
+    UnknownDataSegment = 208,
+    /// A local index is out of bounds.
+    UnknownLocal = 209,
+    /// A branch depth is out of bounds.
+    UnknownLabel = 210,
+    /// A module index is out of bounds.
+    UnknownModule = 211,
+    /// An instance index is out of bounds.
+    UnknownInstance = 212,
+    /// `ref.func` refers to a function which isn't declared by an element
+    /// segment, export or global.
+    UndeclaredFunctionReference = 213,
+    /// An operand or result doesn't have the expected type.
+    TypeMismatch = 301,
+    /// A memory access's alignment is larger than its natural alignment.
+    InvalidAlignment = 302,
+    /// A SIMD lane index is out of bounds.
+    InvalidLaneIndex = 303,
+    /// `global.set` modifies an immutable global.
+    ImmutableGlobal = 304,
+    /// Instructions which delimit blocks, such as `else`, `catch` or `end`,
+    /// don't match up.
+    ControlMismatch = 305,
+}
+
+impl ErrorCode {
+    /// Returns the stable number of this code, such as `301` for `E0301`.
+    pub fn number(self) -> u16 {
+        self as u16
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "E{:04}", self.number())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ErrorCode {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum CustomSectionKind {
     Unknown,
     Name,
@@ -93,6 +276,7 @@ pub enum CustomSectionKind {
 ///
 /// [here]: https://webassembly.github.io/spec/core/binary/modules.html#sections
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum SectionCode<'a> {
     Custom {
         name: &'a str,
@@ -121,6 +305,7 @@ pub enum SectionCode<'a> {
 ///
 /// [here]: https://webassembly.github.io/spec/core/syntax/types.html#types
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Type {
     I32,
     I64,
@@ -136,6 +321,7 @@ pub enum Type {
 
 /// Either a value type or a function type.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum TypeOrFuncType {
     /// A value type.
     ///
@@ -151,6 +337,7 @@ pub enum TypeOrFuncType {
 ///
 /// [here]: https://webassembly.github.io/spec/core/syntax/types.html#external-types
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ExternalKind {
     Function,
     Table,
@@ -163,6 +350,7 @@ pub enum ExternalKind {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum TypeDef<'a> {
     Func(FuncType),
     Instance(InstanceType<'a>),
@@ -170,47 +358,55 @@ pub enum TypeDef<'a> {
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FuncType {
     pub params: Box<[Type]>,
     pub returns: Box<[Type]>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct InstanceType<'a> {
     pub exports: Box<[ExportType<'a>]>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ModuleType<'a> {
     pub imports: Box<[crate::Import<'a>]>,
     pub exports: Box<[ExportType<'a>]>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ExportType<'a> {
     pub name: &'a str,
     pub ty: ImportSectionEntryType,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ResizableLimits {
     pub initial: u32,
     pub maximum: Option<u32>,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ResizableLimits64 {
     pub initial: u64,
     pub maximum: Option<u64>,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct TableType {
     pub element_type: Type,
     pub limits: ResizableLimits,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum MemoryType {
     M32 {
         limits: ResizableLimits,
@@ -223,6 +419,7 @@ pub enum MemoryType {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct EventType {
     pub type_index: u32,
 }
@@ -237,12 +434,14 @@ impl MemoryType {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct GlobalType {
     pub content_type: Type,
     pub mutable: bool,
 }
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ImportSectionEntryType {
     Function(u32),
     Table(TableType),
@@ -254,6 +453,7 @@ pub enum ImportSectionEntryType {
 }
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct MemoryImmediate {
     /// Alignment, stored as `n` where the actual alignment is `2^n`
     pub align: u8,
@@ -262,33 +462,75 @@ pub struct MemoryImmediate {
 }
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Naming<'a> {
     pub index: u32,
     pub name: &'a str,
 }
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum NameType {
     Module,
     Function,
     Local,
 }
 
-#[derive(Debug, Copy, Clone)]
-pub enum LinkingType {
-    StackPointer(u32),
-}
-
-#[derive(Debug, Copy, Clone)]
+/// The type of a relocation in a `reloc.*` custom section, see
+/// [`RelocSectionReader`](crate::RelocSectionReader).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum RelocType {
     FunctionIndexLEB,
     TableIndexSLEB,
     TableIndexI32,
-    GlobalAddrLEB,
-    GlobalAddrSLEB,
-    GlobalAddrI32,
+    MemoryAddrLEB,
+    MemoryAddrSLEB,
+    MemoryAddrI32,
     TypeIndexLEB,
     GlobalIndexLEB,
+    FunctionOffsetI32,
+    SectionOffsetI32,
+    EventIndexLEB,
+    MemoryAddrRelSLEB,
+    TableIndexRelSLEB,
+    GlobalIndexI32,
+    MemoryAddrLEB64,
+    MemoryAddrSLEB64,
+    MemoryAddrI64,
+    MemoryAddrRelSLEB64,
+    TableIndexSLEB64,
+    TableIndexI64,
+    TableNumberLEB,
+    MemoryAddrTlsSLEB,
+    FunctionOffsetI64,
+    MemoryAddrLocRelI32,
+    TableIndexRelSLEB64,
+    MemoryAddrTlsSLEB64,
+    FunctionIndexI32,
+}
+
+impl RelocType {
+    /// Returns whether relocations of this type have an addend.
+    pub fn has_addend(&self) -> bool {
+        matches!(
+            self,
+            RelocType::MemoryAddrLEB
+                | RelocType::MemoryAddrSLEB
+                | RelocType::MemoryAddrI32
+                | RelocType::FunctionOffsetI32
+                | RelocType::SectionOffsetI32
+                | RelocType::MemoryAddrRelSLEB
+                | RelocType::MemoryAddrLEB64
+                | RelocType::MemoryAddrSLEB64
+                | RelocType::MemoryAddrI64
+                | RelocType::MemoryAddrRelSLEB64
+                | RelocType::MemoryAddrTlsSLEB
+                | RelocType::FunctionOffsetI64
+                | RelocType::MemoryAddrLocRelI32
+                | RelocType::MemoryAddrTlsSLEB64
+        )
+    }
 }
 
 /// A br_table entries representation.
@@ -298,11 +540,20 @@ pub struct BrTable<'a> {
     pub(crate) cnt: usize,
 }
 
+/// The locals declared by a `let` instruction, in the same compressed form
+/// as the locals of a function body.
+#[derive(Clone)]
+pub struct LetLocals<'a> {
+    pub(crate) reader: crate::BinaryReader<'a>,
+    pub(crate) cnt: usize,
+}
+
 /// An IEEE binary32 immediate floating point value, represented as a u32
 /// containing the bitpattern.
 ///
 /// All bit patterns are allowed.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Ieee32(pub(crate) u32);
 
 impl Ieee32 {
@@ -316,6 +567,7 @@ impl Ieee32 {
 ///
 /// All bit patterns are allowed.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Ieee64(pub(crate) u64);
 
 impl Ieee64 {
@@ -325,6 +577,7 @@ impl Ieee64 {
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct V128(pub(crate) [u8; 16]);
 
 impl V128 {
@@ -335,865 +588,18 @@ impl V128 {
 
 pub type SIMDLaneIndex = u8;
 
-/// Instructions as defined [here].
-///
-/// [here]: https://webassembly.github.io/spec/core/binary/instructions.html
-#[derive(Debug, Clone)]
-pub enum Operator<'a> {
-    Unreachable,
-    Nop,
-    Block {
-        ty: TypeOrFuncType,
-    },
-    Loop {
-        ty: TypeOrFuncType,
-    },
-    If {
-        ty: TypeOrFuncType,
-    },
-    Else,
-    Try {
-        ty: TypeOrFuncType,
-    },
-    Catch {
-        index: u32,
-    },
-    Throw {
-        index: u32,
-    },
-    Rethrow {
-        relative_depth: u32,
-    },
-    Unwind,
-    End,
-    Br {
-        relative_depth: u32,
-    },
-    BrIf {
-        relative_depth: u32,
-    },
-    BrTable {
-        table: BrTable<'a>,
-    },
-    Return,
-    Call {
-        function_index: u32,
-    },
-    CallIndirect {
-        index: u32,
-        table_index: u32,
-    },
-    ReturnCall {
-        function_index: u32,
-    },
-    ReturnCallIndirect {
-        index: u32,
-        table_index: u32,
-    },
-    Drop,
-    Select,
-    TypedSelect {
-        ty: Type,
-    },
-    LocalGet {
-        local_index: u32,
-    },
-    LocalSet {
-        local_index: u32,
-    },
-    LocalTee {
-        local_index: u32,
-    },
-    GlobalGet {
-        global_index: u32,
-    },
-    GlobalSet {
-        global_index: u32,
-    },
-    I32Load {
-        memarg: MemoryImmediate,
-    },
-    I64Load {
-        memarg: MemoryImmediate,
-    },
-    F32Load {
-        memarg: MemoryImmediate,
-    },
-    F64Load {
-        memarg: MemoryImmediate,
-    },
-    I32Load8S {
-        memarg: MemoryImmediate,
-    },
-    I32Load8U {
-        memarg: MemoryImmediate,
-    },
-    I32Load16S {
-        memarg: MemoryImmediate,
-    },
-    I32Load16U {
-        memarg: MemoryImmediate,
-    },
-    I64Load8S {
-        memarg: MemoryImmediate,
-    },
-    I64Load8U {
-        memarg: MemoryImmediate,
-    },
-    I64Load16S {
-        memarg: MemoryImmediate,
-    },
-    I64Load16U {
-        memarg: MemoryImmediate,
-    },
-    I64Load32S {
-        memarg: MemoryImmediate,
-    },
-    I64Load32U {
-        memarg: MemoryImmediate,
-    },
-    I32Store {
-        memarg: MemoryImmediate,
-    },
-    I64Store {
-        memarg: MemoryImmediate,
-    },
-    F32Store {
-        memarg: MemoryImmediate,
-    },
-    F64Store {
-        memarg: MemoryImmediate,
-    },
-    I32Store8 {
-        memarg: MemoryImmediate,
-    },
-    I32Store16 {
-        memarg: MemoryImmediate,
-    },
-    I64Store8 {
-        memarg: MemoryImmediate,
-    },
-    I64Store16 {
-        memarg: MemoryImmediate,
-    },
-    I64Store32 {
-        memarg: MemoryImmediate,
-    },
-    MemorySize {
-        mem: u32,
-        mem_byte: u8,
-    },
-    MemoryGrow {
-        mem: u32,
-        mem_byte: u8,
-    },
-    I32Const {
-        value: i32,
-    },
-    I64Const {
-        value: i64,
-    },
-    F32Const {
-        value: Ieee32,
-    },
-    F64Const {
-        value: Ieee64,
-    },
-    RefNull {
-        ty: Type,
-    },
-    RefIsNull,
-    RefFunc {
-        function_index: u32,
-    },
-    I32Eqz,
-    I32Eq,
-    I32Ne,
-    I32LtS,
-    I32LtU,
-    I32GtS,
-    I32GtU,
-    I32LeS,
-    I32LeU,
-    I32GeS,
-    I32GeU,
-    I64Eqz,
-    I64Eq,
-    I64Ne,
-    I64LtS,
-    I64LtU,
-    I64GtS,
-    I64GtU,
-    I64LeS,
-    I64LeU,
-    I64GeS,
-    I64GeU,
-    F32Eq,
-    F32Ne,
-    F32Lt,
-    F32Gt,
-    F32Le,
-    F32Ge,
-    F64Eq,
-    F64Ne,
-    F64Lt,
-    F64Gt,
-    F64Le,
-    F64Ge,
-    I32Clz,
-    I32Ctz,
-    I32Popcnt,
-    I32Add,
-    I32Sub,
-    I32Mul,
-    I32DivS,
-    I32DivU,
-    I32RemS,
-    I32RemU,
-    I32And,
-    I32Or,
-    I32Xor,
-    I32Shl,
-    I32ShrS,
-    I32ShrU,
-    I32Rotl,
-    I32Rotr,
-    I64Clz,
-    I64Ctz,
-    I64Popcnt,
-    I64Add,
-    I64Sub,
-    I64Mul,
-    I64DivS,
-    I64DivU,
-    I64RemS,
-    I64RemU,
-    I64And,
-    I64Or,
-    I64Xor,
-    I64Shl,
-    I64ShrS,
-    I64ShrU,
-    I64Rotl,
-    I64Rotr,
-    F32Abs,
-    F32Neg,
-    F32Ceil,
-    F32Floor,
-    F32Trunc,
-    F32Nearest,
-    F32Sqrt,
-    F32Add,
-    F32Sub,
-    F32Mul,
-    F32Div,
-    F32Min,
-    F32Max,
-    F32Copysign,
-    F64Abs,
-    F64Neg,
-    F64Ceil,
-    F64Floor,
-    F64Trunc,
-    F64Nearest,
-    F64Sqrt,
-    F64Add,
-    F64Sub,
-    F64Mul,
-    F64Div,
-    F64Min,
-    F64Max,
-    F64Copysign,
-    I32WrapI64,
-    I32TruncF32S,
-    I32TruncF32U,
-    I32TruncF64S,
-    I32TruncF64U,
-    I64ExtendI32S,
-    I64ExtendI32U,
-    I64TruncF32S,
-    I64TruncF32U,
-    I64TruncF64S,
-    I64TruncF64U,
-    F32ConvertI32S,
-    F32ConvertI32U,
-    F32ConvertI64S,
-    F32ConvertI64U,
-    F32DemoteF64,
-    F64ConvertI32S,
-    F64ConvertI32U,
-    F64ConvertI64S,
-    F64ConvertI64U,
-    F64PromoteF32,
-    I32ReinterpretF32,
-    I64ReinterpretF64,
-    F32ReinterpretI32,
-    F64ReinterpretI64,
-    I32Extend8S,
-    I32Extend16S,
-    I64Extend8S,
-    I64Extend16S,
-    I64Extend32S,
-
-    // 0xFC operators
-    // Non-trapping Float-to-int Conversions
-    I32TruncSatF32S,
-    I32TruncSatF32U,
-    I32TruncSatF64S,
-    I32TruncSatF64U,
-    I64TruncSatF32S,
-    I64TruncSatF32U,
-    I64TruncSatF64S,
-    I64TruncSatF64U,
-
-    // 0xFC operators
-    // bulk memory https://github.com/WebAssembly/bulk-memory-operations/blob/master/proposals/bulk-memory-operations/Overview.md
-    MemoryInit {
-        segment: u32,
-        mem: u32,
-    },
-    DataDrop {
-        segment: u32,
-    },
-    MemoryCopy {
-        src: u32,
-        dst: u32,
-    },
-    MemoryFill {
-        mem: u32,
-    },
-    TableInit {
-        segment: u32,
-        table: u32,
-    },
-    ElemDrop {
-        segment: u32,
-    },
-    TableCopy {
-        dst_table: u32,
-        src_table: u32,
-    },
-    TableFill {
-        table: u32,
-    },
-    TableGet {
-        table: u32,
-    },
-    TableSet {
-        table: u32,
-    },
-    TableGrow {
-        table: u32,
-    },
-    TableSize {
-        table: u32,
-    },
-
-    // 0xFE operators
-    // https://github.com/WebAssembly/threads/blob/master/proposals/threads/Overview.md
-    MemoryAtomicNotify {
-        memarg: MemoryImmediate,
-    },
-    MemoryAtomicWait32 {
-        memarg: MemoryImmediate,
-    },
-    MemoryAtomicWait64 {
-        memarg: MemoryImmediate,
-    },
-    AtomicFence {
-        flags: u8,
-    },
-    I32AtomicLoad {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicLoad {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicLoad8U {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicLoad16U {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicLoad8U {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicLoad16U {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicLoad32U {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicStore {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicStore {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicStore8 {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicStore16 {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicStore8 {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicStore16 {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicStore32 {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicRmwAdd {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmwAdd {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicRmw8AddU {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicRmw16AddU {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmw8AddU {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmw16AddU {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmw32AddU {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicRmwSub {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmwSub {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicRmw8SubU {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicRmw16SubU {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmw8SubU {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmw16SubU {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmw32SubU {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicRmwAnd {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmwAnd {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicRmw8AndU {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicRmw16AndU {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmw8AndU {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmw16AndU {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmw32AndU {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicRmwOr {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmwOr {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicRmw8OrU {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicRmw16OrU {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmw8OrU {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmw16OrU {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmw32OrU {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicRmwXor {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmwXor {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicRmw8XorU {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicRmw16XorU {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmw8XorU {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmw16XorU {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmw32XorU {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicRmwXchg {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmwXchg {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicRmw8XchgU {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicRmw16XchgU {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmw8XchgU {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmw16XchgU {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmw32XchgU {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicRmwCmpxchg {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmwCmpxchg {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicRmw8CmpxchgU {
-        memarg: MemoryImmediate,
-    },
-    I32AtomicRmw16CmpxchgU {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmw8CmpxchgU {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmw16CmpxchgU {
-        memarg: MemoryImmediate,
-    },
-    I64AtomicRmw32CmpxchgU {
-        memarg: MemoryImmediate,
-    },
-
-    // 0xFD operators
-    // SIMD https://github.com/WebAssembly/simd/blob/master/proposals/simd/BinarySIMD.md
-    V128Load {
-        memarg: MemoryImmediate,
-    },
-    V128Store {
-        memarg: MemoryImmediate,
-    },
-    V128Const {
-        value: V128,
-    },
-    I8x16Splat,
-    I8x16ExtractLaneS {
-        lane: SIMDLaneIndex,
-    },
-    I8x16ExtractLaneU {
-        lane: SIMDLaneIndex,
-    },
-    I8x16ReplaceLane {
-        lane: SIMDLaneIndex,
-    },
-    I16x8Splat,
-    I16x8ExtractLaneS {
-        lane: SIMDLaneIndex,
-    },
-    I16x8ExtractLaneU {
-        lane: SIMDLaneIndex,
-    },
-    I16x8ReplaceLane {
-        lane: SIMDLaneIndex,
-    },
-    I32x4Splat,
-    I32x4ExtractLane {
-        lane: SIMDLaneIndex,
-    },
-    I32x4ReplaceLane {
-        lane: SIMDLaneIndex,
-    },
-    I64x2Splat,
-    I64x2ExtractLane {
-        lane: SIMDLaneIndex,
-    },
-    I64x2ReplaceLane {
-        lane: SIMDLaneIndex,
-    },
-    F32x4Splat,
-    F32x4ExtractLane {
-        lane: SIMDLaneIndex,
-    },
-    F32x4ReplaceLane {
-        lane: SIMDLaneIndex,
-    },
-    F64x2Splat,
-    F64x2ExtractLane {
-        lane: SIMDLaneIndex,
-    },
-    F64x2ReplaceLane {
-        lane: SIMDLaneIndex,
-    },
-    I8x16Eq,
-    I8x16Ne,
-    I8x16LtS,
-    I8x16LtU,
-    I8x16GtS,
-    I8x16GtU,
-    I8x16LeS,
-    I8x16LeU,
-    I8x16GeS,
-    I8x16GeU,
-    I16x8Eq,
-    I16x8Ne,
-    I16x8LtS,
-    I16x8LtU,
-    I16x8GtS,
-    I16x8GtU,
-    I16x8LeS,
-    I16x8LeU,
-    I16x8GeS,
-    I16x8GeU,
-    I32x4Eq,
-    I32x4Ne,
-    I32x4LtS,
-    I32x4LtU,
-    I32x4GtS,
-    I32x4GtU,
-    I32x4LeS,
-    I32x4LeU,
-    I32x4GeS,
-    I32x4GeU,
-    F32x4Eq,
-    F32x4Ne,
-    F32x4Lt,
-    F32x4Gt,
-    F32x4Le,
-    F32x4Ge,
-    F64x2Eq,
-    F64x2Ne,
-    F64x2Lt,
-    F64x2Gt,
-    F64x2Le,
-    F64x2Ge,
-    V128Not,
-    V128And,
-    V128AndNot,
-    V128Or,
-    V128Xor,
-    V128Bitselect,
-    V128AnyTrue,
-    I8x16Abs,
-    I8x16Neg,
-    I8x16AllTrue,
-    I8x16Bitmask,
-    I8x16Shl,
-    I8x16ShrS,
-    I8x16ShrU,
-    I8x16Add,
-    I8x16AddSatS,
-    I8x16AddSatU,
-    I8x16Sub,
-    I8x16SubSatS,
-    I8x16SubSatU,
-    I8x16MinS,
-    I8x16MinU,
-    I8x16MaxS,
-    I8x16MaxU,
-    I16x8Abs,
-    I16x8Neg,
-    I16x8AllTrue,
-    I16x8Bitmask,
-    I16x8Shl,
-    I16x8ShrS,
-    I16x8ShrU,
-    I16x8Add,
-    I16x8AddSatS,
-    I16x8AddSatU,
-    I16x8Sub,
-    I16x8SubSatS,
-    I16x8SubSatU,
-    I16x8Mul,
-    I16x8MinS,
-    I16x8MinU,
-    I16x8MaxS,
-    I16x8MaxU,
-    I32x4Abs,
-    I32x4Neg,
-    I32x4AllTrue,
-    I32x4Bitmask,
-    I32x4Shl,
-    I32x4ShrS,
-    I32x4ShrU,
-    I32x4Add,
-    I32x4Sub,
-    I32x4Mul,
-    I32x4MinS,
-    I32x4MinU,
-    I32x4MaxS,
-    I32x4MaxU,
-    I32x4DotI16x8S,
-    I64x2Neg,
-    I64x2Bitmask,
-    I64x2Shl,
-    I64x2ShrS,
-    I64x2ShrU,
-    I64x2Add,
-    I64x2Sub,
-    I64x2Mul,
-    F32x4Ceil,
-    F32x4Floor,
-    F32x4Trunc,
-    F32x4Nearest,
-    F64x2Ceil,
-    F64x2Floor,
-    F64x2Trunc,
-    F64x2Nearest,
-    F32x4Abs,
-    F32x4Neg,
-    F32x4Sqrt,
-    F32x4Add,
-    F32x4Sub,
-    F32x4Mul,
-    F32x4Div,
-    F32x4Min,
-    F32x4Max,
-    F32x4PMin,
-    F32x4PMax,
-    F64x2Abs,
-    F64x2Neg,
-    F64x2Sqrt,
-    F64x2Add,
-    F64x2Sub,
-    F64x2Mul,
-    F64x2Div,
-    F64x2Min,
-    F64x2Max,
-    F64x2PMin,
-    F64x2PMax,
-    I32x4TruncSatF32x4S,
-    I32x4TruncSatF32x4U,
-    F32x4ConvertI32x4S,
-    F32x4ConvertI32x4U,
-    I8x16Swizzle,
-    I8x16Shuffle {
-        lanes: [SIMDLaneIndex; 16],
-    },
-    V128Load8Splat {
-        memarg: MemoryImmediate,
-    },
-    V128Load16Splat {
-        memarg: MemoryImmediate,
-    },
-    V128Load32Splat {
-        memarg: MemoryImmediate,
-    },
-    V128Load32Zero {
-        memarg: MemoryImmediate,
-    },
-    V128Load64Splat {
-        memarg: MemoryImmediate,
-    },
-    V128Load64Zero {
-        memarg: MemoryImmediate,
-    },
-    I8x16NarrowI16x8S,
-    I8x16NarrowI16x8U,
-    I16x8NarrowI32x4S,
-    I16x8NarrowI32x4U,
-    I16x8WidenLowI8x16S,
-    I16x8WidenHighI8x16S,
-    I16x8WidenLowI8x16U,
-    I16x8WidenHighI8x16U,
-    I32x4WidenLowI16x8S,
-    I32x4WidenHighI16x8S,
-    I32x4WidenLowI16x8U,
-    I32x4WidenHighI16x8U,
-    I64x2WidenLowI32x4S,
-    I64x2WidenHighI32x4S,
-    I64x2WidenLowI32x4U,
-    I64x2WidenHighI32x4U,
-    I16x8ExtMulLowI8x16S,
-    I16x8ExtMulHighI8x16S,
-    I16x8ExtMulLowI8x16U,
-    I16x8ExtMulHighI8x16U,
-    I32x4ExtMulLowI16x8S,
-    I32x4ExtMulHighI16x8S,
-    I32x4ExtMulLowI16x8U,
-    I32x4ExtMulHighI16x8U,
-    I64x2ExtMulLowI32x4S,
-    I64x2ExtMulHighI32x4S,
-    I64x2ExtMulLowI32x4U,
-    I64x2ExtMulHighI32x4U,
-    V128Load8x8S {
-        memarg: MemoryImmediate,
-    },
-    V128Load8x8U {
-        memarg: MemoryImmediate,
-    },
-    V128Load16x4S {
-        memarg: MemoryImmediate,
-    },
-    V128Load16x4U {
-        memarg: MemoryImmediate,
-    },
-    V128Load32x2S {
-        memarg: MemoryImmediate,
-    },
-    V128Load32x2U {
-        memarg: MemoryImmediate,
-    },
-    V128Load8Lane {
-        memarg: MemoryImmediate,
-        lane: SIMDLaneIndex,
-    },
-    V128Load16Lane {
-        memarg: MemoryImmediate,
-        lane: SIMDLaneIndex,
-    },
-    V128Load32Lane {
-        memarg: MemoryImmediate,
-        lane: SIMDLaneIndex,
-    },
-    V128Load64Lane {
-        memarg: MemoryImmediate,
-        lane: SIMDLaneIndex,
-    },
-    V128Store8Lane {
-        memarg: MemoryImmediate,
-        lane: SIMDLaneIndex,
-    },
-    V128Store16Lane {
-        memarg: MemoryImmediate,
-        lane: SIMDLaneIndex,
-    },
-    V128Store32Lane {
-        memarg: MemoryImmediate,
-        lane: SIMDLaneIndex,
-    },
-    V128Store64Lane {
-        memarg: MemoryImmediate,
-        lane: SIMDLaneIndex,
-    },
-    I8x16RoundingAverageU,
-    I16x8RoundingAverageU,
-    I16x8Q15MulrSatS,
+macro_rules! define_operator {
+    ($($op:ident $({ $($payload:tt)* })? => $visit:ident)*) => {
+        /// Instructions as defined [here].
+        ///
+        /// [here]: https://webassembly.github.io/spec/core/binary/instructions.html
+        #[derive(Debug, Clone)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+        pub enum Operator<'a> {
+            $(
+                $op $({ $($payload)* })?,
+            )*
+        }
+    }
 }
+for_each_operator!(define_operator);
@@ -1,6 +1,6 @@
 use crate::{
-    BinaryReader, BinaryReaderError, ExternalKind, Range, Result, SectionIteratorLimited,
-    SectionReader, SectionWithLimitedItems,
+    BinaryReader, BinaryReaderError, ErrorCode, ExternalKind, Range, Result,
+    SectionIteratorLimited, SectionReader, SectionWithLimitedItems,
 };
 
 #[derive(Clone)]
@@ -10,6 +10,7 @@ pub struct AliasSectionReader<'a> {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Alias<'a> {
     OuterType {
         relative_depth: u32,
@@ -61,6 +62,7 @@ impl<'a> AliasSectionReader<'a> {
                     },
                     _ => {
                         return Err(BinaryReaderError::new(
+                            ErrorCode::InvalidEncoding,
                             "invalid external kind in alias",
                             self.original_position() - 1,
                         ))
@@ -69,6 +71,7 @@ impl<'a> AliasSectionReader<'a> {
             }
             _ => {
                 return Err(BinaryReaderError::new(
+                    ErrorCode::InvalidEncoding,
                     "invalid byte in alias",
                     self.original_position() - 1,
                 ))
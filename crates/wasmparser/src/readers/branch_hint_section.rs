@@ -0,0 +1,127 @@
+/* Copyright 2018 Mozilla Foundation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::linking_section::read_vec;
+use super::{
+    BinaryReader, BinaryReaderError, ErrorCode, Range, Result, SectionIteratorLimited,
+    SectionReader, SectionWithLimitedItems,
+};
+use alloc::boxed::Box;
+
+/// The hints of one function of a `metadata.code.branch_hint` custom section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct BranchHintFunction {
+    /// The index of the function, including imported functions.
+    pub func: u32,
+    /// The hints of the function, in increasing order of offset.
+    pub hints: Box<[BranchHint]>,
+}
+
+/// A hint of whether a `br_if` or `if` instruction is likely to branch, as
+/// described by the [branch hinting proposal].
+///
+/// [branch hinting proposal]: https://github.com/WebAssembly/branch-hinting
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct BranchHint {
+    /// The offset of the instruction, relative to the start of the function's
+    /// body, which is where its local declarations start.
+    pub func_offset: u32,
+    /// Whether the branch is likely to be taken.
+    pub taken: bool,
+}
+
+/// A reader for the `metadata.code.branch_hint` custom section.
+pub struct BranchHintSectionReader<'a> {
+    reader: BinaryReader<'a>,
+    count: u32,
+}
+
+impl<'a> BranchHintSectionReader<'a> {
+    pub fn new(data: &'a [u8], offset: usize) -> Result<BranchHintSectionReader<'a>> {
+        let mut reader = BinaryReader::new_with_offset(data, offset);
+        let count = reader.read_var_u32()?;
+        Ok(BranchHintSectionReader { reader, count })
+    }
+
+    pub fn get_count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn original_position(&self) -> usize {
+        self.reader.original_position()
+    }
+
+    pub fn read(&mut self) -> Result<BranchHintFunction> {
+        let func = self.reader.read_var_u32()?;
+        let hints = read_vec(&mut self.reader, |reader| {
+            let func_offset = reader.read_var_u32()?;
+            let offset = reader.original_position();
+            if reader.read_var_u32()? != 1 {
+                return Err(BinaryReaderError::new(
+                    ErrorCode::InvalidEncoding,
+                    "invalid branch hint size, expected 1",
+                    offset,
+                ));
+            }
+            let offset = reader.original_position();
+            let taken = match reader.read_u8()? {
+                0 => false,
+                1 => true,
+                _ => {
+                    return Err(BinaryReaderError::new(
+                        ErrorCode::InvalidEncoding,
+                        "invalid branch hint",
+                        offset,
+                    ))
+                }
+            };
+            Ok(BranchHint { func_offset, taken })
+        })?;
+        Ok(BranchHintFunction { func, hints })
+    }
+}
+
+impl<'a> SectionReader for BranchHintSectionReader<'a> {
+    type Item = BranchHintFunction;
+    fn read(&mut self) -> Result<Self::Item> {
+        BranchHintSectionReader::read(self)
+    }
+    fn eof(&self) -> bool {
+        self.reader.eof()
+    }
+    fn original_position(&self) -> usize {
+        BranchHintSectionReader::original_position(self)
+    }
+    fn range(&self) -> Range {
+        self.reader.range()
+    }
+}
+
+impl<'a> SectionWithLimitedItems for BranchHintSectionReader<'a> {
+    fn get_count(&self) -> u32 {
+        BranchHintSectionReader::get_count(self)
+    }
+}
+
+impl<'a> IntoIterator for BranchHintSectionReader<'a> {
+    type Item = Result<BranchHintFunction>;
+    type IntoIter = SectionIteratorLimited<BranchHintSectionReader<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        SectionIteratorLimited::new(self)
+    }
+}
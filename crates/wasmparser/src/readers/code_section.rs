@@ -14,11 +14,12 @@
  */
 
 use super::{
-    BinaryReader, BinaryReaderError, OperatorsReader, Range, Result, SectionIteratorLimited,
-    SectionReader, SectionWithLimitedItems, Type,
+    BinaryReader, BinaryReaderError, ErrorCode, OperatorsReader, Range, Result,
+    SectionIteratorLimited, SectionReader, SectionWithLimitedItems, Type,
 };
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FunctionBody<'a> {
     offset: usize,
     data: &'a [u8],
@@ -152,6 +153,7 @@ impl<'a> CodeSectionReader<'a> {
     fn verify_body_end(&self, end: usize) -> Result<()> {
         if self.reader.buffer.len() < end {
             return Err(BinaryReaderError::new(
+                ErrorCode::SizeMismatch,
                 "Function body extends past end of the code section",
                 self.reader.original_offset + self.reader.buffer.len(),
             ));
@@ -0,0 +1,135 @@
+/* Copyright 2018 Mozilla Foundation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::{
+    BinaryReader, BinaryReaderError, ErrorCode, Range, Result, SectionIteratorLimited,
+    SectionReader, SectionWithLimitedItems,
+};
+
+/// A comment of the text a module was assembled from, as recorded in a
+/// `metadata.comments` custom section.
+///
+/// The section is a vector of comments, each of which is a byte giving the
+/// kind of its anchor, the anchor's fields as unsigned LEB128 integers and the
+/// text of the comment as a string:
+///
+/// * `0x00`: before the module.
+/// * `0x01 section:u8 index:u32`: before an item.
+/// * `0x02 func:u32 offset:u32`: before an instruction.
+/// * `0x03`: at the end of the module.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Comment<'a> {
+    /// Where the comment was in the text.
+    pub anchor: CommentAnchor,
+    /// The text of the comment, including its `;;` or `(;` and `;)`
+    /// delimiters.
+    pub text: &'a str,
+}
+
+/// What a [`Comment`] preceded in the text.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum CommentAnchor {
+    /// The comment came before the module.
+    Module,
+    /// The comment came before the item `index` of the sections with the id
+    /// `section`, counting from the first section with that id.
+    Item { section: u8, index: u32 },
+    /// The comment came before an instruction of the function `func`, which
+    /// includes imported functions, at `offset` relative to the start of the
+    /// function's body, which is where its local declarations start.
+    Instruction { func: u32, offset: u32 },
+    /// The comment came after all the items of the module.
+    End,
+}
+
+/// A reader for the `metadata.comments` custom section.
+pub struct CommentSectionReader<'a> {
+    reader: BinaryReader<'a>,
+    count: u32,
+}
+
+impl<'a> CommentSectionReader<'a> {
+    pub fn new(data: &'a [u8], offset: usize) -> Result<CommentSectionReader<'a>> {
+        let mut reader = BinaryReader::new_with_offset(data, offset);
+        let count = reader.read_var_u32()?;
+        Ok(CommentSectionReader { reader, count })
+    }
+
+    pub fn get_count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn original_position(&self) -> usize {
+        self.reader.original_position()
+    }
+
+    pub fn read(&mut self) -> Result<Comment<'a>> {
+        let offset = self.reader.original_position();
+        let anchor = match self.reader.read_u8()? {
+            0x00 => CommentAnchor::Module,
+            0x01 => CommentAnchor::Item {
+                section: self.reader.read_u8()? as u8,
+                index: self.reader.read_var_u32()?,
+            },
+            0x02 => CommentAnchor::Instruction {
+                func: self.reader.read_var_u32()?,
+                offset: self.reader.read_var_u32()?,
+            },
+            0x03 => CommentAnchor::End,
+            _ => {
+                return Err(BinaryReaderError::new(
+                    ErrorCode::InvalidEncoding,
+                    "invalid comment anchor",
+                    offset,
+                ))
+            }
+        };
+        let text = self.reader.read_string()?;
+        Ok(Comment { anchor, text })
+    }
+}
+
+impl<'a> SectionReader for CommentSectionReader<'a> {
+    type Item = Comment<'a>;
+    fn read(&mut self) -> Result<Self::Item> {
+        CommentSectionReader::read(self)
+    }
+    fn eof(&self) -> bool {
+        self.reader.eof()
+    }
+    fn original_position(&self) -> usize {
+        CommentSectionReader::original_position(self)
+    }
+    fn range(&self) -> Range {
+        self.reader.range()
+    }
+}
+
+impl<'a> SectionWithLimitedItems for CommentSectionReader<'a> {
+    fn get_count(&self) -> u32 {
+        CommentSectionReader::get_count(self)
+    }
+}
+
+impl<'a> IntoIterator for CommentSectionReader<'a> {
+    type Item = Result<Comment<'a>>;
+    type IntoIter = SectionIteratorLimited<CommentSectionReader<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        SectionIteratorLimited::new(self)
+    }
+}
@@ -0,0 +1,228 @@
+/* Copyright 2018 Mozilla Foundation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::linking_section::read_vec;
+use super::{BinaryReader, BinaryReaderError, ErrorCode, Result};
+use crate::{Ieee32, Ieee64};
+use alloc::boxed::Box;
+use alloc::format;
+
+/// The name of the custom section describing the crashed process.
+pub const CORE_SECTION_NAME: &str = "core";
+/// The name of the custom section listing the modules of the crashed process.
+pub const CORE_MODULES_SECTION_NAME: &str = "coremodules";
+/// The name of the custom section listing the instances of the crashed
+/// process.
+pub const CORE_INSTANCES_SECTION_NAME: &str = "coreinstances";
+/// The name of the custom section holding the stack of a thread of the
+/// crashed process. A coredump has one of these per thread.
+pub const CORE_STACK_SECTION_NAME: &str = "corestack";
+
+/// The `core` custom section of a coredump, as described by the [tool
+/// conventions].
+///
+/// A coredump is itself a WebAssembly module. The memories and globals of the
+/// crashed instances are the coredump's own memories, whose contents are
+/// given by data segments, and globals. Its custom sections describe the
+/// process, the instances it was running and the stack of each thread.
+///
+/// [tool conventions]: https://github.com/WebAssembly/tool-conventions/blob/main/Coredump.md
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CoreDumpSection<'a> {
+    /// The name of the program which crashed.
+    pub executable_name: &'a str,
+}
+
+impl<'a> CoreDumpSection<'a> {
+    /// Reads the contents of a `core` custom section.
+    pub fn new(data: &'a [u8], offset: usize) -> Result<CoreDumpSection<'a>> {
+        let mut reader = BinaryReader::new_with_offset(data, offset);
+        expect_zero(&mut reader, "process-info")?;
+        let executable_name = reader.read_string()?;
+        expect_end(&reader)?;
+        Ok(CoreDumpSection { executable_name })
+    }
+}
+
+/// The `coremodules` custom section of a coredump.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CoreDumpModulesSection<'a> {
+    /// The names of the modules of the crashed process, which instances
+    /// refer to by index.
+    pub modules: Box<[&'a str]>,
+}
+
+impl<'a> CoreDumpModulesSection<'a> {
+    /// Reads the contents of a `coremodules` custom section.
+    pub fn new(data: &'a [u8], offset: usize) -> Result<CoreDumpModulesSection<'a>> {
+        let mut reader = BinaryReader::new_with_offset(data, offset);
+        let modules = read_vec(&mut reader, |reader| {
+            expect_zero(reader, "module")?;
+            reader.read_string()
+        })?;
+        expect_end(&reader)?;
+        Ok(CoreDumpModulesSection { modules })
+    }
+}
+
+/// The `coreinstances` custom section of a coredump.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CoreDumpInstancesSection {
+    /// The instances of the crashed process, which stack frames refer to by
+    /// index.
+    pub instances: Box<[CoreDumpInstance]>,
+}
+
+/// An instance of a module in a coredump.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CoreDumpInstance {
+    /// The index of the instance's module in the `coremodules` section.
+    pub module_index: u32,
+    /// For each memory of the instance, the index of the coredump's memory
+    /// holding its contents.
+    pub memories: Box<[u32]>,
+    /// For each global of the instance, the index of the coredump's global
+    /// holding its value.
+    pub globals: Box<[u32]>,
+}
+
+impl CoreDumpInstancesSection {
+    /// Reads the contents of a `coreinstances` custom section.
+    pub fn new(data: &[u8], offset: usize) -> Result<CoreDumpInstancesSection> {
+        let mut reader = BinaryReader::new_with_offset(data, offset);
+        let instances = read_vec(&mut reader, |reader| {
+            expect_zero(reader, "instance")?;
+            Ok(CoreDumpInstance {
+                module_index: reader.read_var_u32()?,
+                memories: read_vec(reader, |reader| reader.read_var_u32())?,
+                globals: read_vec(reader, |reader| reader.read_var_u32())?,
+            })
+        })?;
+        expect_end(&reader)?;
+        Ok(CoreDumpInstancesSection { instances })
+    }
+}
+
+/// The `corestack` custom section of a coredump, holding the stack of one
+/// thread.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CoreDumpStackSection<'a> {
+    /// The name of the thread.
+    pub name: &'a str,
+    /// The frames of the thread's stack, starting with the innermost frame.
+    pub frames: Box<[CoreDumpStackFrame]>,
+}
+
+/// A frame of a thread's stack in a coredump.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CoreDumpStackFrame {
+    /// The index of the instance running the frame's function in the
+    /// `coreinstances` section.
+    pub instance_index: u32,
+    /// The index of the function in its module's function index space.
+    pub func_index: u32,
+    /// The offset of the instruction being executed, relative to the start
+    /// of the function's body in the code section of its module.
+    pub code_offset: u32,
+    /// The values of the function's locals.
+    pub locals: Box<[CoreDumpValue]>,
+    /// The values on the function's operand stack, from the bottom up.
+    pub stack: Box<[CoreDumpValue]>,
+}
+
+/// A value of a local or on the operand stack in a coredump.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum CoreDumpValue {
+    /// A value which wasn't recorded, for example because it was optimized
+    /// away.
+    Missing,
+    /// An `i32` value.
+    I32(i32),
+    /// An `i64` value.
+    I64(i64),
+    /// An `f32` value.
+    F32(Ieee32),
+    /// An `f64` value.
+    F64(Ieee64),
+}
+
+impl<'a> CoreDumpStackSection<'a> {
+    /// Reads the contents of a `corestack` custom section.
+    pub fn new(data: &'a [u8], offset: usize) -> Result<CoreDumpStackSection<'a>> {
+        let mut reader = BinaryReader::new_with_offset(data, offset);
+        expect_zero(&mut reader, "thread-info")?;
+        let name = reader.read_string()?;
+        let frames = read_vec(&mut reader, |reader| {
+            expect_zero(reader, "frame")?;
+            Ok(CoreDumpStackFrame {
+                instance_index: reader.read_var_u32()?,
+                func_index: reader.read_var_u32()?,
+                code_offset: reader.read_var_u32()?,
+                locals: read_vec(reader, read_value)?,
+                stack: read_vec(reader, read_value)?,
+            })
+        })?;
+        expect_end(&reader)?;
+        Ok(CoreDumpStackSection { name, frames })
+    }
+}
+
+fn read_value(reader: &mut BinaryReader<'_>) -> Result<CoreDumpValue> {
+    let offset = reader.original_position();
+    Ok(match reader.read_u8()? {
+        0x01 => CoreDumpValue::Missing,
+        0x7f => CoreDumpValue::I32(reader.read_var_i32()?),
+        0x7e => CoreDumpValue::I64(reader.read_var_i64()?),
+        0x7d => CoreDumpValue::F32(reader.read_f32()?),
+        0x7c => CoreDumpValue::F64(reader.read_f64()?),
+        _ => {
+            return Err(BinaryReaderError::new(
+                ErrorCode::InvalidEncoding,
+                "invalid coredump value type",
+                offset,
+            ))
+        }
+    })
+}
+
+fn expect_zero(reader: &mut BinaryReader<'_>, what: &str) -> Result<()> {
+    let offset = reader.original_position();
+    if reader.read_u8()? != 0 {
+        return Err(BinaryReaderError::new(
+            ErrorCode::InvalidEncoding,
+            format!("invalid leading byte in coredump {}", what),
+            offset,
+        ));
+    }
+    Ok(())
+}
+
+fn expect_end(reader: &BinaryReader<'_>) -> Result<()> {
+    if !reader.eof() {
+        return Err(BinaryReaderError::new(
+            ErrorCode::SizeMismatch,
+            "unexpected data at the end of coredump section",
+            reader.original_position(),
+        ));
+    }
+    Ok(())
+}
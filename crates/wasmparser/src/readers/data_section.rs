@@ -14,17 +14,19 @@
  */
 
 use super::{
-    BinaryReader, BinaryReaderError, InitExpr, Range, Result, SectionIteratorLimited,
+    BinaryReader, BinaryReaderError, ErrorCode, InitExpr, Range, Result, SectionIteratorLimited,
     SectionReader, SectionWithLimitedItems,
 };
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Data<'a> {
     pub kind: DataKind<'a>,
     pub data: &'a [u8],
 }
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum DataKind<'a> {
     Passive,
     Active {
@@ -66,6 +68,7 @@ impl<'a> DataSectionReader<'a> {
     fn verify_data_end(&self, end: usize) -> Result<()> {
         if self.reader.buffer.len() < end {
             return Err(BinaryReaderError::new(
+                ErrorCode::SizeMismatch,
                 "Data segment extends past end of the data section",
                 self.reader.original_offset + self.reader.buffer.len(),
             ));
@@ -105,6 +108,7 @@ impl<'a> DataSectionReader<'a> {
                 2 => self.reader.read_var_u32()?,
                 _ => {
                     return Err(BinaryReaderError::new(
+                        ErrorCode::InvalidEncoding,
                         "invalid flags byte in data segment",
                         self.reader.original_position() - 1,
                     ));
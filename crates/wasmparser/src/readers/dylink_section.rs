@@ -0,0 +1,220 @@
+/* Copyright 2018 Mozilla Foundation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::linking_section::read_vec;
+use super::{
+    BinaryReader, BinaryReaderError, ErrorCode, Import, Range, Result, SectionIterator,
+    SectionReader,
+};
+use crate::ImportSectionEntryType;
+use alloc::boxed::Box;
+
+/// The name of the custom section describing a dynamic library.
+pub const DYLINK_SECTION_NAME: &str = "dylink.0";
+
+/// A subsection of the `dylink.0` custom section of dynamic libraries, as
+/// described by the [tool conventions].
+///
+/// [tool conventions]: https://github.com/WebAssembly/tool-conventions/blob/master/DynamicLinking.md
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Dylink<'a> {
+    /// The memory and table space the library needs.
+    MemInfo(MemInfo),
+    /// The names of the libraries this library depends on.
+    Needed(Box<[&'a str]>),
+    /// Extra information about exports.
+    ExportInfo(Box<[ExportInfo<'a>]>),
+    /// Extra information about imports.
+    ImportInfo(Box<[ImportInfo<'a>]>),
+    /// A subsection which isn't known to this crate.
+    Unknown {
+        /// The identifier of the subsection.
+        ty: u8,
+        /// The contents of the subsection.
+        data: &'a [u8],
+    },
+}
+
+/// The memory and table requirements of a dynamic library, see
+/// [`Dylink::MemInfo`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MemInfo {
+    /// The size of the library's static data, in bytes.
+    pub memory_size: u32,
+    /// The alignment of the library's static data, as a power of two.
+    pub memory_alignment: u32,
+    /// The number of table slots the library needs.
+    pub table_size: u32,
+    /// The alignment of the library's table slots, as a power of two.
+    pub table_alignment: u32,
+}
+
+/// Extra information about an export, see [`Dylink::ExportInfo`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ExportInfo<'a> {
+    /// The name of the export.
+    pub name: &'a str,
+    /// The symbol flags of the export, such as
+    /// [`SymbolInfo::BINDING_WEAK`](crate::SymbolInfo::BINDING_WEAK).
+    pub flags: u32,
+}
+
+/// Extra information about an import, see [`Dylink::ImportInfo`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ImportInfo<'a> {
+    /// The module name of the import.
+    pub module: &'a str,
+    /// The field name of the import.
+    pub field: &'a str,
+    /// The symbol flags of the import, such as
+    /// [`SymbolInfo::BINDING_WEAK`](crate::SymbolInfo::BINDING_WEAK).
+    pub flags: u32,
+}
+
+/// A reader for the `dylink.0` custom section, yielding its subsections.
+pub struct DylinkSectionReader<'a> {
+    reader: BinaryReader<'a>,
+}
+
+impl<'a> DylinkSectionReader<'a> {
+    pub fn new(data: &'a [u8], offset: usize) -> Result<DylinkSectionReader<'a>> {
+        let reader = BinaryReader::new_with_offset(data, offset);
+        Ok(DylinkSectionReader { reader })
+    }
+
+    pub fn eof(&self) -> bool {
+        self.reader.eof()
+    }
+
+    pub fn original_position(&self) -> usize {
+        self.reader.original_position()
+    }
+
+    pub fn read<'b>(&mut self) -> Result<Dylink<'b>>
+    where
+        'a: 'b,
+    {
+        let ty = self.reader.read_u8()? as u8;
+        let payload_len = self.reader.read_var_u32()? as usize;
+        let offset = self.reader.original_position();
+        let data = self.reader.read_bytes(payload_len)?;
+        let mut reader = BinaryReader::new_with_offset(data, offset);
+        let dylink = match ty {
+            1 => Dylink::MemInfo(MemInfo {
+                memory_size: reader.read_var_u32()?,
+                memory_alignment: reader.read_var_u32()?,
+                table_size: reader.read_var_u32()?,
+                table_alignment: reader.read_var_u32()?,
+            }),
+            2 => Dylink::Needed(read_vec(&mut reader, |reader| reader.read_string())?),
+            3 => Dylink::ExportInfo(read_vec(&mut reader, |reader| {
+                Ok(ExportInfo {
+                    name: reader.read_string()?,
+                    flags: reader.read_var_u32()?,
+                })
+            })?),
+            4 => Dylink::ImportInfo(read_vec(&mut reader, |reader| {
+                Ok(ImportInfo {
+                    module: reader.read_string()?,
+                    field: reader.read_string()?,
+                    flags: reader.read_var_u32()?,
+                })
+            })?),
+            _ => return Ok(Dylink::Unknown { ty, data }),
+        };
+        if !reader.eof() {
+            return Err(BinaryReaderError::new(
+                ErrorCode::SizeMismatch,
+                "unexpected data at the end of dylink subsection",
+                reader.original_position(),
+            ));
+        }
+        Ok(dylink)
+    }
+}
+
+impl<'a> SectionReader for DylinkSectionReader<'a> {
+    type Item = Dylink<'a>;
+    fn read(&mut self) -> Result<Self::Item> {
+        DylinkSectionReader::read(self)
+    }
+    fn eof(&self) -> bool {
+        DylinkSectionReader::eof(self)
+    }
+    fn original_position(&self) -> usize {
+        DylinkSectionReader::original_position(self)
+    }
+    fn range(&self) -> Range {
+        self.reader.range()
+    }
+}
+
+impl<'a> IntoIterator for DylinkSectionReader<'a> {
+    type Item = Result<Dylink<'a>>;
+    type IntoIter = SectionIterator<DylinkSectionReader<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        SectionIterator::new(self)
+    }
+}
+
+/// The kind of an entry of the global offset table, which dynamic libraries
+/// import as globals from the `GOT.mem` and `GOT.func` modules.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum GotKind {
+    /// A global holding the address of the data symbol named by the import.
+    Mem,
+    /// A global holding the table index of the function named by the import.
+    Func,
+}
+
+impl GotKind {
+    /// Returns the kind of GOT entries imported from `module`, if any.
+    pub fn from_module(module: &str) -> Option<GotKind> {
+        match module {
+            "GOT.mem" => Some(GotKind::Mem),
+            "GOT.func" => Some(GotKind::Func),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the module GOT entries of this kind are imported
+    /// from.
+    pub fn module(&self) -> &'static str {
+        match self {
+            GotKind::Mem => "GOT.mem",
+            GotKind::Func => "GOT.func",
+        }
+    }
+}
+
+impl<'a> Import<'a> {
+    /// Returns the kind of GOT entry this import is and the name of the
+    /// symbol it refers to, if this is a global imported from one of the
+    /// GOT modules.
+    pub fn got_entry(&self) -> Option<(GotKind, &'a str)> {
+        match (self.ty, self.field) {
+            (ImportSectionEntryType::Global(_), Some(field)) => {
+                GotKind::from_module(self.module).map(|kind| (kind, field))
+            }
+            _ => None,
+        }
+    }
+}
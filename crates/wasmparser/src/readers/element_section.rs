@@ -14,12 +14,13 @@
  */
 
 use super::{
-    BinaryReader, BinaryReaderError, InitExpr, Range, Result, SectionIteratorLimited,
+    BinaryReader, BinaryReaderError, ErrorCode, InitExpr, Range, Result, SectionIteratorLimited,
     SectionReader, SectionWithLimitedItems, Type,
 };
 use crate::{ExternalKind, Operator};
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Element<'a> {
     pub kind: ElementKind<'a>,
     pub items: ElementItems<'a>,
@@ -27,6 +28,7 @@ pub struct Element<'a> {
 }
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ElementKind<'a> {
     Passive,
     Active {
@@ -37,6 +39,7 @@ pub enum ElementKind<'a> {
 }
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ElementItems<'a> {
     exprs: bool,
     offset: usize,
@@ -44,6 +47,7 @@ pub struct ElementItems<'a> {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ElementItem {
     Null(Type),
     Func(u32),
@@ -93,11 +97,23 @@ impl<'a> ElementItemsReader<'a> {
             let ret = match self.reader.read_operator()? {
                 Operator::RefNull { ty } => ElementItem::Null(ty),
                 Operator::RefFunc { function_index } => ElementItem::Func(function_index),
-                _ => return Err(BinaryReaderError::new("invalid passive segment", offset)),
+                _ => {
+                    return Err(BinaryReaderError::new(
+                        ErrorCode::InvalidEncoding,
+                        "invalid passive segment",
+                        offset,
+                    ))
+                }
             };
             match self.reader.read_operator()? {
                 Operator::End => {}
-                _ => return Err(BinaryReaderError::new("invalid passive segment", offset)),
+                _ => {
+                    return Err(BinaryReaderError::new(
+                        ErrorCode::InvalidEncoding,
+                        "invalid passive segment",
+                        offset,
+                    ))
+                }
             }
             Ok(ret)
         } else {
@@ -192,6 +208,7 @@ impl<'a> ElementSectionReader<'a> {
         let flags = self.reader.read_var_u32()?;
         if (flags & !0b111) != 0 {
             return Err(BinaryReaderError::new(
+                ErrorCode::InvalidEncoding,
                 "invalid flags byte in element segment",
                 self.reader.original_position() - 1,
             ));
@@ -228,6 +245,7 @@ impl<'a> ElementSectionReader<'a> {
                     ExternalKind::Function => Type::FuncRef,
                     _ => {
                         return Err(BinaryReaderError::new(
+                            ErrorCode::InvalidEncoding,
                             "only the function external type is supported in elem segment",
                             self.reader.original_position() - 1,
                         ));
@@ -19,6 +19,7 @@ use super::{
 };
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Export<'a> {
     pub field: &'a str,
     pub kind: ExternalKind,
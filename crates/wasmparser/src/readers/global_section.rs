@@ -19,6 +19,7 @@ use super::{
 };
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Global<'a> {
     pub ty: GlobalType,
     pub init_expr: InitExpr<'a>,
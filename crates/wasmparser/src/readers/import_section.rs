@@ -19,6 +19,7 @@ use crate::{
 };
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Import<'a> {
     pub module: &'a str,
     pub field: Option<&'a str>,
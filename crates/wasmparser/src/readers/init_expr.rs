@@ -16,6 +16,7 @@
 use super::{BinaryReader, OperatorsReader};
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct InitExpr<'a> {
     offset: usize,
     data: &'a [u8],
@@ -1,6 +1,6 @@
 use crate::{
-    BinaryReader, BinaryReaderError, ExternalKind, Range, Result, SectionIteratorLimited,
-    SectionReader, SectionWithLimitedItems,
+    BinaryReader, BinaryReaderError, ErrorCode, ExternalKind, Range, Result,
+    SectionIteratorLimited, SectionReader, SectionWithLimitedItems,
 };
 
 #[derive(Clone)]
@@ -75,6 +75,7 @@ impl<'a> IntoIterator for InstanceSectionReader<'a> {
 }
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Instance<'a> {
     reader: BinaryReader<'a>,
     module: u32,
@@ -85,6 +86,7 @@ impl<'a> Instance<'a> {
         let mut reader = BinaryReader::new_with_offset(data, offset);
         if reader.read_u8()? != 0 {
             return Err(BinaryReaderError::new(
+                ErrorCode::InvalidEncoding,
                 "instantiate instruction not found",
                 offset,
             ));
@@ -120,6 +122,7 @@ pub struct InstanceArgsReader<'a> {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct InstanceArg<'a> {
     pub name: &'a str,
     pub kind: ExternalKind,
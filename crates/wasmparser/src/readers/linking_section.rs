@@ -14,45 +14,359 @@
  */
 
 use super::{
-    BinaryReader, LinkingType, Range, Result, SectionIteratorLimited, SectionReader,
-    SectionWithLimitedItems,
+    BinaryReader, BinaryReaderError, ErrorCode, Range, Result, SectionIterator, SectionReader,
 };
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::vec::Vec;
 
+/// The version of the `linking` section this crate reads.
+pub const LINKING_SECTION_VERSION: u32 = 2;
+
+/// A subsection of the `linking` custom section of relocatable object files,
+/// as described by the [tool conventions].
+///
+/// [tool conventions]: https://github.com/WebAssembly/tool-conventions/blob/master/Linking.md
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Linking<'a> {
+    /// Extra metadata about the data segments, in the order they're defined.
+    SegmentInfo(Box<[SegmentInfo<'a>]>),
+    /// Functions to call when the module is initialized.
+    InitFuncs(Box<[InitFunc]>),
+    /// Groups of symbols of which only one copy is kept when linking.
+    ComdatInfo(Box<[Comdat<'a>]>),
+    /// The symbols defined or used by the object file.
+    SymbolTable(Box<[SymbolInfo<'a>]>),
+    /// A subsection which isn't known to this crate.
+    Unknown {
+        /// The identifier of the subsection.
+        ty: u8,
+        /// The contents of the subsection.
+        data: &'a [u8],
+    },
+}
+
+/// Metadata of a data segment, see [`Linking::SegmentInfo`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SegmentInfo<'a> {
+    /// The name of the segment.
+    pub name: &'a str,
+    /// The alignment of the segment, as a power of two.
+    pub alignment: u32,
+    /// The `WASM_SEG_FLAG_*` flags of the segment.
+    pub flags: u32,
+}
+
+/// A function to call when the module is initialized, see
+/// [`Linking::InitFuncs`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct InitFunc {
+    /// Functions are called in increasing order of priority.
+    pub priority: u32,
+    /// The index of the function's symbol in the symbol table.
+    pub symbol_index: u32,
+}
+
+/// A group of symbols of which only one copy is kept when linking, see
+/// [`Linking::ComdatInfo`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Comdat<'a> {
+    /// The name of the group.
+    pub name: &'a str,
+    /// Flags of the group, currently always zero.
+    pub flags: u32,
+    /// The items which are part of the group.
+    pub symbols: Box<[ComdatSymbol]>,
+}
+
+/// An item of a [`Comdat`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ComdatSymbol {
+    /// The kind of the item.
+    pub kind: ComdatSymbolKind,
+    /// The index of the item in the index space of its kind.
+    pub index: u32,
+}
+
+/// The kinds of items that can be part of a [`Comdat`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ComdatSymbolKind {
+    Data,
+    Function,
+    Global,
+    Event,
+    Table,
+    Section,
+}
+
+/// A symbol of the symbol table, see [`Linking::SymbolTable`].
+///
+/// Symbols of imported items, which are undefined, only have a name if the
+/// `EXPLICIT_NAME` flag is set, otherwise the name of the import is used.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum SymbolInfo<'a> {
+    Func {
+        flags: u32,
+        index: u32,
+        name: Option<&'a str>,
+    },
+    Data {
+        flags: u32,
+        name: &'a str,
+        /// Where the data is defined, `None` for undefined symbols.
+        symbol: Option<DefinedDataSymbol>,
+    },
+    Global {
+        flags: u32,
+        index: u32,
+        name: Option<&'a str>,
+    },
+    Section {
+        flags: u32,
+        section: u32,
+    },
+    Event {
+        flags: u32,
+        index: u32,
+        name: Option<&'a str>,
+    },
+    Table {
+        flags: u32,
+        index: u32,
+        name: Option<&'a str>,
+    },
+}
+
+impl SymbolInfo<'_> {
+    /// The symbol is weak, and may be overridden by a strong symbol.
+    pub const BINDING_WEAK: u32 = 0x1;
+    /// The symbol is only visible within the object file.
+    pub const BINDING_LOCAL: u32 = 0x2;
+    /// The symbol isn't exported from the linked module.
+    pub const VISIBILITY_HIDDEN: u32 = 0x4;
+    /// The symbol refers to an imported item.
+    pub const UNDEFINED: u32 = 0x10;
+    /// The symbol should be exported from the linked module.
+    pub const EXPORTED: u32 = 0x20;
+    /// The symbol of an import has a name of its own.
+    pub const EXPLICIT_NAME: u32 = 0x40;
+    /// The symbol must be kept when linking, even if it isn't used.
+    pub const NO_STRIP: u32 = 0x80;
+
+    /// Returns the flags of this symbol, see the associated constants.
+    pub fn flags(&self) -> u32 {
+        match *self {
+            SymbolInfo::Func { flags, .. }
+            | SymbolInfo::Data { flags, .. }
+            | SymbolInfo::Global { flags, .. }
+            | SymbolInfo::Section { flags, .. }
+            | SymbolInfo::Event { flags, .. }
+            | SymbolInfo::Table { flags, .. } => flags,
+        }
+    }
+}
+
+/// The location of a defined data symbol, see [`SymbolInfo::Data`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DefinedDataSymbol {
+    /// The index of the data segment the symbol is in.
+    pub index: u32,
+    /// The offset of the symbol within its data segment.
+    pub offset: u32,
+    /// The size of the symbol in bytes.
+    pub size: u32,
+}
+
+/// A reader for the `linking` custom section, yielding its subsections.
 pub struct LinkingSectionReader<'a> {
     reader: BinaryReader<'a>,
-    count: u32,
+    version: u32,
 }
 
 impl<'a> LinkingSectionReader<'a> {
     pub fn new(data: &'a [u8], offset: usize) -> Result<LinkingSectionReader<'a>> {
         let mut reader = BinaryReader::new_with_offset(data, offset);
-        let count = reader.read_var_u32()?;
-        Ok(LinkingSectionReader { reader, count })
+        let version = reader.read_var_u32()?;
+        if version != LINKING_SECTION_VERSION {
+            return Err(BinaryReaderError::new(
+                ErrorCode::InvalidEncoding,
+                format!("unsupported linking section version: {}", version),
+                offset,
+            ));
+        }
+        Ok(LinkingSectionReader { reader, version })
     }
 
-    pub fn get_count(&self) -> u32 {
-        self.count
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn eof(&self) -> bool {
+        self.reader.eof()
     }
 
     pub fn original_position(&self) -> usize {
         self.reader.original_position()
     }
 
-    pub fn read<'b>(&mut self) -> Result<LinkingType>
+    pub fn read<'b>(&mut self) -> Result<Linking<'b>>
     where
         'a: 'b,
     {
-        Ok(self.reader.read_linking_type()?)
+        let ty = self.reader.read_u8()? as u8;
+        let payload_len = self.reader.read_var_u32()? as usize;
+        let offset = self.reader.original_position();
+        let data = self.reader.read_bytes(payload_len)?;
+        let mut reader = BinaryReader::new_with_offset(data, offset);
+        let linking = match ty {
+            5 => Linking::SegmentInfo(read_vec(&mut reader, |reader| {
+                Ok(SegmentInfo {
+                    name: reader.read_string()?,
+                    alignment: reader.read_var_u32()?,
+                    flags: reader.read_var_u32()?,
+                })
+            })?),
+            6 => Linking::InitFuncs(read_vec(&mut reader, |reader| {
+                Ok(InitFunc {
+                    priority: reader.read_var_u32()?,
+                    symbol_index: reader.read_var_u32()?,
+                })
+            })?),
+            7 => Linking::ComdatInfo(read_vec(&mut reader, |reader| {
+                Ok(Comdat {
+                    name: reader.read_string()?,
+                    flags: reader.read_var_u32()?,
+                    symbols: read_vec(reader, read_comdat_symbol)?,
+                })
+            })?),
+            8 => Linking::SymbolTable(read_vec(&mut reader, read_symbol_info)?),
+            _ => return Ok(Linking::Unknown { ty, data }),
+        };
+        if !reader.eof() {
+            return Err(BinaryReaderError::new(
+                ErrorCode::SizeMismatch,
+                "unexpected data at the end of linking subsection",
+                reader.original_position(),
+            ));
+        }
+        Ok(linking)
+    }
+}
+
+pub(super) fn read_vec<'a, T>(
+    reader: &mut BinaryReader<'a>,
+    mut read: impl FnMut(&mut BinaryReader<'a>) -> Result<T>,
+) -> Result<Box<[T]>> {
+    let count = reader.read_var_u32()?;
+    // Don't trust the count to preallocate, every item takes at least a byte.
+    let mut items = Vec::with_capacity((count as usize).min(reader.bytes_remaining()));
+    for _ in 0..count {
+        items.push(read(reader)?);
     }
+    Ok(items.into_boxed_slice())
+}
+
+fn read_comdat_symbol(reader: &mut BinaryReader<'_>) -> Result<ComdatSymbol> {
+    let offset = reader.original_position();
+    let kind = match reader.read_u8()? {
+        0 => ComdatSymbolKind::Data,
+        1 => ComdatSymbolKind::Function,
+        2 => ComdatSymbolKind::Global,
+        3 => ComdatSymbolKind::Event,
+        4 => ComdatSymbolKind::Table,
+        5 => ComdatSymbolKind::Section,
+        _ => {
+            return Err(BinaryReaderError::new(
+                ErrorCode::InvalidEncoding,
+                "invalid comdat symbol kind",
+                offset,
+            ))
+        }
+    };
+    Ok(ComdatSymbol {
+        kind,
+        index: reader.read_var_u32()?,
+    })
+}
+
+fn read_symbol_info<'a>(reader: &mut BinaryReader<'a>) -> Result<SymbolInfo<'a>> {
+    let offset = reader.original_position();
+    let kind = reader.read_u8()?;
+    let flags = reader.read_var_u32()?;
+    let defined = flags & SymbolInfo::UNDEFINED == 0;
+    let has_name = defined || flags & SymbolInfo::EXPLICIT_NAME != 0;
+    let index_and_name = |reader: &mut BinaryReader<'a>| -> Result<_> {
+        let index = reader.read_var_u32()?;
+        let name = if has_name {
+            Some(reader.read_string()?)
+        } else {
+            None
+        };
+        Ok((index, name))
+    };
+    Ok(match kind {
+        0 => {
+            let (index, name) = index_and_name(reader)?;
+            SymbolInfo::Func { flags, index, name }
+        }
+        1 => {
+            let name = reader.read_string()?;
+            let symbol = if defined {
+                Some(DefinedDataSymbol {
+                    index: reader.read_var_u32()?,
+                    offset: reader.read_var_u32()?,
+                    size: reader.read_var_u32()?,
+                })
+            } else {
+                None
+            };
+            SymbolInfo::Data {
+                flags,
+                name,
+                symbol,
+            }
+        }
+        2 => {
+            let (index, name) = index_and_name(reader)?;
+            SymbolInfo::Global { flags, index, name }
+        }
+        3 => SymbolInfo::Section {
+            flags,
+            section: reader.read_var_u32()?,
+        },
+        4 => {
+            let (index, name) = index_and_name(reader)?;
+            SymbolInfo::Event { flags, index, name }
+        }
+        5 => {
+            let (index, name) = index_and_name(reader)?;
+            SymbolInfo::Table { flags, index, name }
+        }
+        _ => {
+            return Err(BinaryReaderError::new(
+                ErrorCode::InvalidEncoding,
+                "invalid symbol kind",
+                offset,
+            ))
+        }
+    })
 }
 
 impl<'a> SectionReader for LinkingSectionReader<'a> {
-    type Item = LinkingType;
+    type Item = Linking<'a>;
     fn read(&mut self) -> Result<Self::Item> {
         LinkingSectionReader::read(self)
     }
     fn eof(&self) -> bool {
-        self.reader.eof()
+        LinkingSectionReader::eof(self)
     }
     fn original_position(&self) -> usize {
         LinkingSectionReader::original_position(self)
@@ -62,17 +376,11 @@ impl<'a> SectionReader for LinkingSectionReader<'a> {
     }
 }
 
-impl<'a> SectionWithLimitedItems for LinkingSectionReader<'a> {
-    fn get_count(&self) -> u32 {
-        LinkingSectionReader::get_count(self)
-    }
-}
-
 impl<'a> IntoIterator for LinkingSectionReader<'a> {
-    type Item = Result<LinkingType>;
-    type IntoIter = SectionIteratorLimited<LinkingSectionReader<'a>>;
+    type Item = Result<Linking<'a>>;
+    type IntoIter = SectionIterator<LinkingSectionReader<'a>>;
 
     fn into_iter(self) -> Self::IntoIter {
-        SectionIteratorLimited::new(self)
+        SectionIterator::new(self)
     }
 }
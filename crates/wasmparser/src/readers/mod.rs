@@ -14,13 +14,17 @@
  */
 
 use super::{
-    BinaryReader, BinaryReaderError, EventType, ExternalKind, GlobalType, LinkingType, MemoryType,
-    NameType, Naming, Operator, Range, RelocType, Result, SectionCode, TableType, Type,
+    BinaryReader, BinaryReaderError, ErrorCode, EventType, ExternalKind, GlobalType, MemoryType,
+    NameType, Naming, Operator, Range, RelocType, Result, TableType, Type,
 };
 
 pub use self::alias_section::*;
+pub use self::branch_hint_section::*;
 pub use self::code_section::*;
+pub use self::comment_section::*;
+pub use self::coredump_section::*;
 pub use self::data_section::*;
+pub use self::dylink_section::*;
 pub use self::element_section::*;
 pub use self::event_section::*;
 pub use self::export_section::*;
@@ -41,8 +45,12 @@ pub use self::table_section::*;
 pub use self::type_section::*;
 
 mod alias_section;
+mod branch_hint_section;
 mod code_section;
+mod comment_section;
+mod coredump_section;
 mod data_section;
+mod dylink_section;
 mod element_section;
 mod event_section;
 mod export_section;
@@ -1,5 +1,5 @@
 use crate::{
-    BinaryReader, BinaryReaderError, Range, Result, SectionIteratorLimited, SectionReader,
+    BinaryReader, BinaryReaderError, ErrorCode, Range, Result, SectionIteratorLimited, SectionReader,
     SectionWithLimitedItems,
 };
 
@@ -9,6 +9,7 @@ pub struct ModuleCodeSectionReader<'a> {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ModuleCode<'a> {
     reader: BinaryReader<'a>,
 }
@@ -31,7 +32,7 @@ impl<'a> ModuleCodeSectionReader<'a> {
     fn verify_module_end(&self, end: usize) -> Result<()> {
         if self.reader.buffer.len() < end {
             return Err(BinaryReaderError::new(
-                "module body extends past end of the module code section",
+                ErrorCode::SizeMismatch, "module body extends past end of the module code section",
                 self.reader.original_offset + self.reader.buffer.len(),
             ));
         }
@@ -1,6 +1,6 @@
 use crate::{
-    BinaryReader, BinaryReaderError, Range, Result, SectionIteratorLimited, SectionReader,
-    SectionWithLimitedItems,
+    BinaryReader, BinaryReaderError, ErrorCode, Range, Result, SectionIteratorLimited,
+    SectionReader, SectionWithLimitedItems,
 };
 
 pub struct ModuleSectionReader<'a> {
@@ -9,6 +9,7 @@ pub struct ModuleSectionReader<'a> {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct NestedModule<'a> {
     reader: BinaryReader<'a>,
 }
@@ -31,6 +32,7 @@ impl<'a> ModuleSectionReader<'a> {
     fn verify_module_end(&self, end: usize) -> Result<()> {
         if self.reader.buffer.len() < end {
             return Err(BinaryReaderError::new(
+                ErrorCode::SizeMismatch,
                 "module body extends past end of the module code section",
                 self.reader.original_offset + self.reader.buffer.len(),
             ));
@@ -14,11 +14,12 @@
  */
 
 use super::{
-    BinaryReader, BinaryReaderError, NameType, Naming, Range, Result, SectionIterator,
+    BinaryReader, BinaryReaderError, ErrorCode, NameType, Naming, Range, Result, SectionIterator,
     SectionReader,
 };
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ModuleName<'a> {
     data: &'a [u8],
     offset: usize,
@@ -78,6 +79,7 @@ impl<'a> NamingReader<'a> {
 }
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FunctionName<'a> {
     data: &'a [u8],
     offset: usize,
@@ -97,6 +99,7 @@ impl<'a> FunctionName<'a> {
 }
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FunctionLocalName<'a> {
     pub func_index: u32,
     data: &'a [u8],
@@ -153,6 +156,7 @@ impl<'a> FunctionLocalReader<'a> {
 }
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct LocalName<'a> {
     data: &'a [u8],
     offset: usize,
@@ -172,6 +176,7 @@ impl<'a> LocalName<'a> {
 }
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Name<'a> {
     Module(ModuleName<'a>),
     Function(FunctionName<'a>),
@@ -192,6 +197,7 @@ impl<'a> NameSectionReader<'a> {
     fn verify_section_end(&self, end: usize) -> Result<()> {
         if self.reader.buffer.len() < end {
             return Err(BinaryReaderError::new(
+                ErrorCode::SizeMismatch,
                 "Name entry extends past end of the code section",
                 self.reader.original_offset + self.reader.buffer.len(),
             ));
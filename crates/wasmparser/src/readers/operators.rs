@@ -13,7 +13,7 @@
  * limitations under the License.
  */
 
-use super::{BinaryReader, BinaryReaderError, Operator, Result};
+use super::{BinaryReader, BinaryReaderError, ErrorCode, Operator, Result};
 
 #[derive(Clone)]
 pub struct OperatorsReader<'a> {
@@ -43,6 +43,7 @@ impl<'a> OperatorsReader<'a> {
             return Ok(());
         }
         Err(BinaryReaderError::new(
+            ErrorCode::SizeMismatch,
             "Unexpected data at the end of operators",
             self.reader.original_position(),
         ))
@@ -72,6 +73,46 @@ impl<'a> OperatorsReader<'a> {
         let pos = self.reader.original_position();
         Ok((self.read()?, pos))
     }
+
+    /// Reads the next operator and dispatches it to the corresponding method
+    /// of `visitor`, returning what the visitor returns.
+    ///
+    /// # Examples
+    /// ```
+    /// use wasmparser::{CodeSectionReader, VisitOperator};
+    ///
+    /// // A visitor which returns the name of each operator it sees.
+    /// struct Names;
+    ///
+    /// macro_rules! define_visit {
+    ///     ($($op:ident $({ $($arg:ident: $argty:ty),* })? => $visit:ident)*) => {
+    ///         $(
+    ///             fn $visit(&mut self, _offset: usize $($(, $arg: $argty)*)?) -> &'static str {
+    ///                 stringify!($op)
+    ///             }
+    ///         )*
+    ///     };
+    /// }
+    ///
+    /// impl<'a> VisitOperator<'a> for Names {
+    ///     type Output = &'static str;
+    ///     wasmparser::for_each_operator!(define_visit);
+    /// }
+    ///
+    /// # let data: &[u8] = &[
+    /// #     0x01, 0x03, 0x00, 0x01, 0x0b];
+    /// let mut code_reader = CodeSectionReader::new(data, 0).unwrap();
+    /// let body = code_reader.read().expect("function body");
+    /// let mut op_reader = body.get_operators_reader().expect("op reader");
+    /// assert_eq!(op_reader.visit_operator(&mut Names).unwrap(), "Nop");
+    /// assert_eq!(op_reader.visit_operator(&mut Names).unwrap(), "End");
+    /// ```
+    pub fn visit_operator<T>(&mut self, visitor: &mut T) -> Result<T::Output>
+    where
+        T: VisitOperator<'a>,
+    {
+        self.reader.visit_operator(visitor)
+    }
 }
 
 impl<'a> IntoIterator for OperatorsReader<'a> {
@@ -159,3 +200,37 @@ impl<'a> Iterator for OperatorsIteratorWithOffsets<'a> {
         Some(result)
     }
 }
+
+macro_rules! define_visit_operator {
+    ($($op:ident $({ $($arg:ident: $argty:ty),* })? => $visit:ident)*) => {
+        /// A trait to handle each kind of [`Operator`] with its own method.
+        ///
+        /// This is an alternative to matching over the [`Operator`]
+        /// enumeration which is useful for interpreters and translators. The
+        /// [`for_each_operator`](crate::for_each_operator) macro can be used to
+        /// implement this trait for types which handle most operators the same
+        /// way.
+        ///
+        /// Each method receives the original offset of the operator in the
+        /// wasm binary followed by the operator's payload, if any.
+        pub trait VisitOperator<'a> {
+            /// The result type of the visitor methods.
+            type Output: 'a;
+
+            /// Dispatches `op` to the visitor method corresponding to its
+            /// kind.
+            fn visit_operator(&mut self, offset: usize, op: Operator<'a>) -> Self::Output {
+                match op {
+                    $(
+                        Operator::$op $({ $($arg),* })? => self.$visit(offset $($(, $arg)*)?),
+                    )*
+                }
+            }
+
+            $(
+                fn $visit(&mut self, offset: usize $($(, $arg: $argty)*)?) -> Self::Output;
+            )*
+        }
+    }
+}
+for_each_operator!(define_visit_operator);
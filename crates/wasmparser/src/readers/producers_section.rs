@@ -18,6 +18,7 @@ use super::{
 };
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ProducersFieldValue<'a> {
     pub name: &'a str,
     pub version: &'a str,
@@ -92,6 +93,7 @@ impl<'a> Iterator for ProducersFieldValuesIterator<'a> {
 }
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ProducersField<'a> {
     pub name: &'a str,
     values_count: u32,
@@ -14,36 +14,41 @@
  */
 
 use super::{
-    BinaryReader, Range, RelocType, Result, SectionCode, SectionIteratorLimited, SectionReader,
+    BinaryReader, Range, RelocType, Result, SectionIteratorLimited, SectionReader,
     SectionWithLimitedItems,
 };
 
-#[derive(Debug, Copy, Clone)]
+/// A relocation of a `reloc.*` custom section.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Reloc {
     pub ty: RelocType,
+    /// The offset of the value to relocate, relative to the start of the
+    /// contents of the section the relocations apply to.
     pub offset: u32,
+    /// The index of the symbol, or for `TypeIndexLEB` relocations of the
+    /// type, the relocation refers to.
     pub index: u32,
-    pub addend: Option<u32>,
+    /// The addend to add to the address, for the types which have one.
+    pub addend: Option<i64>,
 }
 
+/// A reader for `reloc.*` custom sections, which hold the relocations to apply
+/// to one of the other sections when linking.
 pub struct RelocSectionReader<'a> {
     reader: BinaryReader<'a>,
-    section_code: SectionCode<'a>,
+    section_index: u32,
     count: u32,
 }
 
 impl<'a> RelocSectionReader<'a> {
     pub fn new(data: &'a [u8], offset: usize) -> Result<RelocSectionReader<'a>> {
         let mut reader = BinaryReader::new_with_offset(data, offset);
-
-        let section_id_position = reader.position;
-        let section_id = reader.read_var_u7()?;
-        let section_code = reader.read_section_code(section_id, section_id_position)?;
-
+        let section_index = reader.read_var_u32()?;
         let count = reader.read_var_u32()?;
         Ok(RelocSectionReader {
             reader,
-            section_code,
+            section_index,
             count,
         })
     }
@@ -52,11 +57,10 @@ impl<'a> RelocSectionReader<'a> {
         self.count
     }
 
-    pub fn get_section_code<'b>(&self) -> SectionCode<'b>
-    where
-        'a: 'b,
-    {
-        self.section_code
+    /// Returns the index of the section these relocations apply to, counting
+    /// all sections of the module from zero, including custom sections.
+    pub fn get_section_index(&self) -> u32 {
+        self.section_index
     }
 
     pub fn original_position(&self) -> usize {
@@ -67,15 +71,10 @@ impl<'a> RelocSectionReader<'a> {
         let ty = self.reader.read_reloc_type()?;
         let offset = self.reader.read_var_u32()?;
         let index = self.reader.read_var_u32()?;
-        let addend = match ty {
-            RelocType::FunctionIndexLEB
-            | RelocType::TableIndexSLEB
-            | RelocType::TableIndexI32
-            | RelocType::TypeIndexLEB
-            | RelocType::GlobalIndexLEB => None,
-            RelocType::GlobalAddrLEB | RelocType::GlobalAddrSLEB | RelocType::GlobalAddrI32 => {
-                Some(self.reader.read_var_u32()?)
-            }
+        let addend = if ty.has_addend() {
+            Some(self.reader.read_var_i64()?)
+        } else {
+            None
         };
         Ok(Reloc {
             ty,
@@ -13,7 +13,7 @@
  * limitations under the License.
  */
 
-use super::{BinaryReaderError, Range, Result};
+use super::{BinaryReaderError, ErrorCode, Range, Result};
 
 pub trait SectionReader {
     type Item;
@@ -26,6 +26,7 @@ pub trait SectionReader {
             return Ok(());
         }
         Err(BinaryReaderError::new(
+            ErrorCode::SizeMismatch,
             "Unexpected data at the end of the section",
             self.original_position(),
         ))
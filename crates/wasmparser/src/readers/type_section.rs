@@ -14,8 +14,8 @@
  */
 
 use crate::{
-    BinaryReader, BinaryReaderError, Range, Result, SectionIteratorLimited, SectionReader,
-    SectionWithLimitedItems, TypeDef,
+    BinaryReader, BinaryReaderError, ErrorCode, Range, Result, SectionIteratorLimited,
+    SectionReader, SectionWithLimitedItems, TypeDef,
 };
 
 #[derive(Clone)]
@@ -58,6 +58,7 @@ impl<'a> TypeSectionReader<'a> {
             0x62 => TypeDef::Instance(self.reader.read_instance_type()?),
             _ => {
                 return Err(BinaryReaderError::new(
+                    ErrorCode::InvalidEncoding,
                     "invalid leading byte in type definition",
                     self.original_position() - 1,
                 ))
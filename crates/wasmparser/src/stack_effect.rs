@@ -0,0 +1,775 @@
+/* Copyright 2018 Mozilla Foundation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::{MemoryImmediate, Operator, Type, TypeOrFuncType, WasmFuncType, WasmModuleResources};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// The type of an operand popped or pushed by an operator, see
+/// [`Operator::stack_effect`].
+///
+/// Most operands have a type which is known from the operator and the module
+/// alone. The others are described symbolically, in terms of the context the
+/// operator appears in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum StackType {
+    /// A value of this type.
+    Val(Type),
+    /// A value of any type. All the `Any` operands of an operator have the
+    /// same type, as for the operands and result of `select`.
+    Any,
+    /// A value of any reference type.
+    AnyRef,
+    /// A value of the type of the local with this index.
+    Local(u32),
+    /// The values carried by a branch to the label with this relative depth:
+    /// the parameters of a `loop` and the results of any other block.
+    Label(u32),
+    /// The results of the function.
+    Return,
+    /// The parameters of the innermost enclosing block.
+    BlockParams,
+    /// The results of the innermost enclosing block.
+    BlockResults,
+}
+
+/// The operands popped and pushed by an operator, see
+/// [`Operator::stack_effect`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct StackEffect {
+    /// The operands popped by the operator, with the one at the top of the
+    /// stack last.
+    pub params: Vec<StackType>,
+    /// The operands pushed by the operator, with the one left at the top of
+    /// the stack last.
+    pub results: Vec<StackType>,
+    /// Whether the code following the operator is unreachable, in which case
+    /// the stack is polymorphic until the end of the block.
+    pub unreachable: bool,
+}
+
+impl StackEffect {
+    fn new(params: Vec<StackType>, results: Vec<StackType>) -> StackEffect {
+        StackEffect {
+            params,
+            results,
+            unreachable: false,
+        }
+    }
+
+    fn fixed(params: &[Type], results: &[Type]) -> StackEffect {
+        StackEffect::new(vals(params.iter().copied()), vals(results.iter().copied()))
+    }
+
+    fn diverging(params: Vec<StackType>) -> StackEffect {
+        StackEffect {
+            params,
+            results: Vec::new(),
+            unreachable: true,
+        }
+    }
+}
+
+fn vals(types: impl Iterator<Item = Type>) -> Vec<StackType> {
+    types.map(StackType::Val).collect()
+}
+
+const I32: Type = Type::I32;
+const I64: Type = Type::I64;
+const F32: Type = Type::F32;
+const F64: Type = Type::F64;
+const V128: Type = Type::V128;
+
+impl<'a> Operator<'a> {
+    /// Returns the operands this operator pops from and pushes to the operand
+    /// stack.
+    ///
+    /// Types which depend on the module, such as the signature of a called
+    /// function or whether a memory uses 64-bit addresses, are resolved with
+    /// `resources`, for example the [`ValidatorResources`] of a module or the
+    /// resources of a [`FuncValidator`]. Types which depend on where the
+    /// operator appears in a function are given symbolically, see
+    /// [`StackType`].
+    ///
+    /// Returns `None` if the operator refers to a function, type, table,
+    /// memory, global or event which doesn't exist in `resources`, or if the
+    /// targets of a `br_table` or the locals of a `let` are malformed. Only
+    /// the default target of a `br_table` is reported, as all its targets
+    /// carry the same values.
+    ///
+    /// ```
+    /// use wasmparser::{Operator, StackEffect, StackType, Type, WasmModuleResources};
+    ///
+    /// # fn effect(resources: &impl WasmModuleResources) {
+    /// let effect = Operator::I32Add.stack_effect(resources).unwrap();
+    /// assert_eq!(effect.params, [StackType::Val(Type::I32); 2]);
+    /// assert_eq!(effect.results, [StackType::Val(Type::I32)]);
+    ///
+    /// let effect = Operator::Br { relative_depth: 1 }.stack_effect(resources).unwrap();
+    /// assert_eq!(effect.params, [StackType::Label(1)]);
+    /// assert!(effect.unreachable);
+    /// # }
+    /// ```
+    ///
+    /// [`ValidatorResources`]: crate::ValidatorResources
+    /// [`FuncValidator`]: crate::FuncValidator
+    pub fn stack_effect(&self, resources: &impl WasmModuleResources) -> Option<StackEffect> {
+        let memory = |memarg: &MemoryImmediate| memory_index_type(resources, memarg.memory);
+        let load = |memarg: &MemoryImmediate, ty: Type| {
+            Some(StackEffect::fixed(&[memory(memarg)?], &[ty]))
+        };
+        let store = |memarg: &MemoryImmediate, ty: Type| {
+            Some(StackEffect::fixed(&[memory(memarg)?, ty], &[]))
+        };
+        let rmw = |memarg: &MemoryImmediate, ty: Type| {
+            Some(StackEffect::fixed(&[memory(memarg)?, ty], &[ty]))
+        };
+        let table = |index: u32| Some(resources.table_at(index)?.element_type);
+
+        Some(match *self {
+            Operator::Unreachable => StackEffect::diverging(Vec::new()),
+            Operator::Nop | Operator::AtomicFence { .. } => StackEffect::fixed(&[], &[]),
+            Operator::Block { ty } | Operator::Loop { ty } | Operator::Try { ty } => {
+                let params = block_params(resources, ty)?;
+                StackEffect::new(params.clone(), params)
+            }
+            Operator::If { ty } => {
+                let mut params = block_params(resources, ty)?;
+                let results = params.clone();
+                params.push(StackType::Val(I32));
+                StackEffect::new(params, results)
+            }
+            Operator::Let { ty, ref locals } => {
+                let results = block_params(resources, ty)?;
+                let mut params = results.clone();
+                for local in locals.locals() {
+                    let (count, ty) = local.ok()?;
+                    params.extend((0..count).map(|_| StackType::Val(ty)));
+                }
+                StackEffect::new(params, results)
+            }
+            Operator::Else => {
+                StackEffect::new(vec![StackType::BlockResults], vec![StackType::BlockParams])
+            }
+            Operator::Catch { index } => StackEffect::new(
+                vec![StackType::BlockResults],
+                vals(resources.event_at(index)?.inputs()),
+            ),
+            Operator::Unwind => StackEffect::new(vec![StackType::BlockResults], Vec::new()),
+            Operator::End => {
+                StackEffect::new(vec![StackType::BlockResults], vec![StackType::BlockResults])
+            }
+            Operator::Throw { index } => {
+                StackEffect::diverging(vals(resources.event_at(index)?.inputs()))
+            }
+            Operator::Rethrow { .. } => StackEffect::diverging(Vec::new()),
+            Operator::Br { relative_depth } => {
+                StackEffect::diverging(vec![StackType::Label(relative_depth)])
+            }
+            Operator::BrIf { relative_depth } => StackEffect::new(
+                vec![StackType::Label(relative_depth), StackType::Val(I32)],
+                vec![StackType::Label(relative_depth)],
+            ),
+            Operator::BrTable { ref table } => {
+                let (default, _) = table.targets().last()?.ok()?;
+                StackEffect::diverging(vec![StackType::Label(default), StackType::Val(I32)])
+            }
+            Operator::Return => StackEffect::diverging(vec![StackType::Return]),
+            Operator::Call { function_index } => {
+                let ty = resources.type_of_function(function_index)?;
+                StackEffect::new(vals(ty.inputs()), vals(ty.outputs()))
+            }
+            Operator::CallIndirect { index, table_index } => {
+                table(table_index)?;
+                let ty = resources.func_type_at(index)?;
+                let mut params = vals(ty.inputs());
+                params.push(StackType::Val(I32));
+                StackEffect::new(params, vals(ty.outputs()))
+            }
+            Operator::ReturnCall { function_index } => {
+                StackEffect::diverging(vals(resources.type_of_function(function_index)?.inputs()))
+            }
+            Operator::ReturnCallIndirect { index, table_index } => {
+                table(table_index)?;
+                let mut params = vals(resources.func_type_at(index)?.inputs());
+                params.push(StackType::Val(I32));
+                StackEffect::diverging(params)
+            }
+
+            Operator::Drop => StackEffect::new(vec![StackType::Any], Vec::new()),
+            Operator::Select => StackEffect::new(
+                vec![StackType::Any, StackType::Any, StackType::Val(I32)],
+                vec![StackType::Any],
+            ),
+            Operator::TypedSelect { ty } => StackEffect::fixed(&[ty, ty, I32], &[ty]),
+            Operator::LocalGet { local_index } => {
+                StackEffect::new(Vec::new(), vec![StackType::Local(local_index)])
+            }
+            Operator::LocalSet { local_index } => {
+                StackEffect::new(vec![StackType::Local(local_index)], Vec::new())
+            }
+            Operator::LocalTee { local_index } => StackEffect::new(
+                vec![StackType::Local(local_index)],
+                vec![StackType::Local(local_index)],
+            ),
+            Operator::GlobalGet { global_index } => {
+                StackEffect::fixed(&[], &[resources.global_at(global_index)?.content_type])
+            }
+            Operator::GlobalSet { global_index } => {
+                StackEffect::fixed(&[resources.global_at(global_index)?.content_type], &[])
+            }
+
+            Operator::I32Load { ref memarg }
+            | Operator::I32Load8S { ref memarg }
+            | Operator::I32Load8U { ref memarg }
+            | Operator::I32Load16S { ref memarg }
+            | Operator::I32Load16U { ref memarg }
+            | Operator::I32AtomicLoad { ref memarg }
+            | Operator::I32AtomicLoad8U { ref memarg }
+            | Operator::I32AtomicLoad16U { ref memarg } => load(memarg, I32)?,
+            Operator::I64Load { ref memarg }
+            | Operator::I64Load8S { ref memarg }
+            | Operator::I64Load8U { ref memarg }
+            | Operator::I64Load16S { ref memarg }
+            | Operator::I64Load16U { ref memarg }
+            | Operator::I64Load32S { ref memarg }
+            | Operator::I64Load32U { ref memarg }
+            | Operator::I64AtomicLoad { ref memarg }
+            | Operator::I64AtomicLoad8U { ref memarg }
+            | Operator::I64AtomicLoad16U { ref memarg }
+            | Operator::I64AtomicLoad32U { ref memarg } => load(memarg, I64)?,
+            Operator::F32Load { ref memarg } => load(memarg, F32)?,
+            Operator::F64Load { ref memarg } => load(memarg, F64)?,
+            Operator::V128Load { ref memarg }
+            | Operator::V128Load8x8S { ref memarg }
+            | Operator::V128Load8x8U { ref memarg }
+            | Operator::V128Load16x4S { ref memarg }
+            | Operator::V128Load16x4U { ref memarg }
+            | Operator::V128Load32x2S { ref memarg }
+            | Operator::V128Load32x2U { ref memarg }
+            | Operator::V128Load8Splat { ref memarg }
+            | Operator::V128Load16Splat { ref memarg }
+            | Operator::V128Load32Splat { ref memarg }
+            | Operator::V128Load64Splat { ref memarg }
+            | Operator::V128Load32Zero { ref memarg }
+            | Operator::V128Load64Zero { ref memarg } => load(memarg, V128)?,
+            Operator::V128Load8Lane { ref memarg, .. }
+            | Operator::V128Load16Lane { ref memarg, .. }
+            | Operator::V128Load32Lane { ref memarg, .. }
+            | Operator::V128Load64Lane { ref memarg, .. } => rmw(memarg, V128)?,
+            Operator::I32Store { ref memarg }
+            | Operator::I32Store8 { ref memarg }
+            | Operator::I32Store16 { ref memarg }
+            | Operator::I32AtomicStore { ref memarg }
+            | Operator::I32AtomicStore8 { ref memarg }
+            | Operator::I32AtomicStore16 { ref memarg } => store(memarg, I32)?,
+            Operator::I64Store { ref memarg }
+            | Operator::I64Store8 { ref memarg }
+            | Operator::I64Store16 { ref memarg }
+            | Operator::I64Store32 { ref memarg }
+            | Operator::I64AtomicStore { ref memarg }
+            | Operator::I64AtomicStore8 { ref memarg }
+            | Operator::I64AtomicStore16 { ref memarg }
+            | Operator::I64AtomicStore32 { ref memarg } => store(memarg, I64)?,
+            Operator::F32Store { ref memarg } => store(memarg, F32)?,
+            Operator::F64Store { ref memarg } => store(memarg, F64)?,
+            Operator::V128Store { ref memarg }
+            | Operator::V128Store8Lane { ref memarg, .. }
+            | Operator::V128Store16Lane { ref memarg, .. }
+            | Operator::V128Store32Lane { ref memarg, .. }
+            | Operator::V128Store64Lane { ref memarg, .. } => store(memarg, V128)?,
+            Operator::MemorySize { mem, .. } => {
+                StackEffect::fixed(&[], &[memory_index_type(resources, mem)?])
+            }
+            Operator::MemoryGrow { mem, .. } => {
+                let ty = memory_index_type(resources, mem)?;
+                StackEffect::fixed(&[ty], &[ty])
+            }
+
+            Operator::MemoryAtomicNotify { ref memarg } => {
+                StackEffect::fixed(&[memory(memarg)?, I32], &[I32])
+            }
+            Operator::MemoryAtomicWait32 { ref memarg } => {
+                StackEffect::fixed(&[memory(memarg)?, I32, I64], &[I32])
+            }
+            Operator::MemoryAtomicWait64 { ref memarg } => {
+                StackEffect::fixed(&[memory(memarg)?, I64, I64], &[I32])
+            }
+            Operator::I32AtomicRmwAdd { ref memarg }
+            | Operator::I32AtomicRmw8AddU { ref memarg }
+            | Operator::I32AtomicRmw16AddU { ref memarg }
+            | Operator::I32AtomicRmwSub { ref memarg }
+            | Operator::I32AtomicRmw8SubU { ref memarg }
+            | Operator::I32AtomicRmw16SubU { ref memarg }
+            | Operator::I32AtomicRmwAnd { ref memarg }
+            | Operator::I32AtomicRmw8AndU { ref memarg }
+            | Operator::I32AtomicRmw16AndU { ref memarg }
+            | Operator::I32AtomicRmwOr { ref memarg }
+            | Operator::I32AtomicRmw8OrU { ref memarg }
+            | Operator::I32AtomicRmw16OrU { ref memarg }
+            | Operator::I32AtomicRmwXor { ref memarg }
+            | Operator::I32AtomicRmw8XorU { ref memarg }
+            | Operator::I32AtomicRmw16XorU { ref memarg }
+            | Operator::I32AtomicRmwXchg { ref memarg }
+            | Operator::I32AtomicRmw8XchgU { ref memarg }
+            | Operator::I32AtomicRmw16XchgU { ref memarg } => rmw(memarg, I32)?,
+            Operator::I64AtomicRmwAdd { ref memarg }
+            | Operator::I64AtomicRmw8AddU { ref memarg }
+            | Operator::I64AtomicRmw16AddU { ref memarg }
+            | Operator::I64AtomicRmw32AddU { ref memarg }
+            | Operator::I64AtomicRmwSub { ref memarg }
+            | Operator::I64AtomicRmw8SubU { ref memarg }
+            | Operator::I64AtomicRmw16SubU { ref memarg }
+            | Operator::I64AtomicRmw32SubU { ref memarg }
+            | Operator::I64AtomicRmwAnd { ref memarg }
+            | Operator::I64AtomicRmw8AndU { ref memarg }
+            | Operator::I64AtomicRmw16AndU { ref memarg }
+            | Operator::I64AtomicRmw32AndU { ref memarg }
+            | Operator::I64AtomicRmwOr { ref memarg }
+            | Operator::I64AtomicRmw8OrU { ref memarg }
+            | Operator::I64AtomicRmw16OrU { ref memarg }
+            | Operator::I64AtomicRmw32OrU { ref memarg }
+            | Operator::I64AtomicRmwXor { ref memarg }
+            | Operator::I64AtomicRmw8XorU { ref memarg }
+            | Operator::I64AtomicRmw16XorU { ref memarg }
+            | Operator::I64AtomicRmw32XorU { ref memarg }
+            | Operator::I64AtomicRmwXchg { ref memarg }
+            | Operator::I64AtomicRmw8XchgU { ref memarg }
+            | Operator::I64AtomicRmw16XchgU { ref memarg }
+            | Operator::I64AtomicRmw32XchgU { ref memarg } => rmw(memarg, I64)?,
+            Operator::I32AtomicRmwCmpxchg { ref memarg }
+            | Operator::I32AtomicRmw8CmpxchgU { ref memarg }
+            | Operator::I32AtomicRmw16CmpxchgU { ref memarg } => {
+                StackEffect::fixed(&[memory(memarg)?, I32, I32], &[I32])
+            }
+            Operator::I64AtomicRmwCmpxchg { ref memarg }
+            | Operator::I64AtomicRmw8CmpxchgU { ref memarg }
+            | Operator::I64AtomicRmw16CmpxchgU { ref memarg }
+            | Operator::I64AtomicRmw32CmpxchgU { ref memarg } => {
+                StackEffect::fixed(&[memory(memarg)?, I64, I64], &[I64])
+            }
+
+            Operator::I32Const { .. } => StackEffect::fixed(&[], &[I32]),
+            Operator::I64Const { .. } => StackEffect::fixed(&[], &[I64]),
+            Operator::F32Const { .. } => StackEffect::fixed(&[], &[F32]),
+            Operator::F64Const { .. } => StackEffect::fixed(&[], &[F64]),
+            Operator::V128Const { .. } => StackEffect::fixed(&[], &[V128]),
+            Operator::RefNull { ty } => StackEffect::fixed(&[], &[ty]),
+            Operator::RefIsNull => {
+                StackEffect::new(vec![StackType::AnyRef], vec![StackType::Val(I32)])
+            }
+            Operator::RefFunc { function_index } => {
+                resources.type_of_function(function_index)?;
+                StackEffect::fixed(&[], &[Type::FuncRef])
+            }
+
+            Operator::I32Eqz
+            | Operator::I32Clz
+            | Operator::I32Ctz
+            | Operator::I32Popcnt
+            | Operator::I32Extend8S
+            | Operator::I32Extend16S => StackEffect::fixed(&[I32], &[I32]),
+            Operator::I32Eq
+            | Operator::I32Ne
+            | Operator::I32LtS
+            | Operator::I32LtU
+            | Operator::I32GtS
+            | Operator::I32GtU
+            | Operator::I32LeS
+            | Operator::I32LeU
+            | Operator::I32GeS
+            | Operator::I32GeU
+            | Operator::I32Add
+            | Operator::I32Sub
+            | Operator::I32Mul
+            | Operator::I32DivS
+            | Operator::I32DivU
+            | Operator::I32RemS
+            | Operator::I32RemU
+            | Operator::I32And
+            | Operator::I32Or
+            | Operator::I32Xor
+            | Operator::I32Shl
+            | Operator::I32ShrS
+            | Operator::I32ShrU
+            | Operator::I32Rotl
+            | Operator::I32Rotr => StackEffect::fixed(&[I32, I32], &[I32]),
+            Operator::I64Eqz | Operator::I32WrapI64 => StackEffect::fixed(&[I64], &[I32]),
+            Operator::I64Eq
+            | Operator::I64Ne
+            | Operator::I64LtS
+            | Operator::I64LtU
+            | Operator::I64GtS
+            | Operator::I64GtU
+            | Operator::I64LeS
+            | Operator::I64LeU
+            | Operator::I64GeS
+            | Operator::I64GeU => StackEffect::fixed(&[I64, I64], &[I32]),
+            Operator::I64Clz
+            | Operator::I64Ctz
+            | Operator::I64Popcnt
+            | Operator::I64Extend8S
+            | Operator::I64Extend16S
+            | Operator::I64Extend32S => StackEffect::fixed(&[I64], &[I64]),
+            Operator::I64Add
+            | Operator::I64Sub
+            | Operator::I64Mul
+            | Operator::I64DivS
+            | Operator::I64DivU
+            | Operator::I64RemS
+            | Operator::I64RemU
+            | Operator::I64And
+            | Operator::I64Or
+            | Operator::I64Xor
+            | Operator::I64Shl
+            | Operator::I64ShrS
+            | Operator::I64ShrU
+            | Operator::I64Rotl
+            | Operator::I64Rotr => StackEffect::fixed(&[I64, I64], &[I64]),
+            Operator::F32Eq
+            | Operator::F32Ne
+            | Operator::F32Lt
+            | Operator::F32Gt
+            | Operator::F32Le
+            | Operator::F32Ge => StackEffect::fixed(&[F32, F32], &[I32]),
+            Operator::F32Abs
+            | Operator::F32Neg
+            | Operator::F32Ceil
+            | Operator::F32Floor
+            | Operator::F32Trunc
+            | Operator::F32Nearest
+            | Operator::F32Sqrt => StackEffect::fixed(&[F32], &[F32]),
+            Operator::F32Add
+            | Operator::F32Sub
+            | Operator::F32Mul
+            | Operator::F32Div
+            | Operator::F32Min
+            | Operator::F32Max
+            | Operator::F32Copysign => StackEffect::fixed(&[F32, F32], &[F32]),
+            Operator::F64Eq
+            | Operator::F64Ne
+            | Operator::F64Lt
+            | Operator::F64Gt
+            | Operator::F64Le
+            | Operator::F64Ge => StackEffect::fixed(&[F64, F64], &[I32]),
+            Operator::F64Abs
+            | Operator::F64Neg
+            | Operator::F64Ceil
+            | Operator::F64Floor
+            | Operator::F64Trunc
+            | Operator::F64Nearest
+            | Operator::F64Sqrt => StackEffect::fixed(&[F64], &[F64]),
+            Operator::F64Add
+            | Operator::F64Sub
+            | Operator::F64Mul
+            | Operator::F64Div
+            | Operator::F64Min
+            | Operator::F64Max
+            | Operator::F64Copysign => StackEffect::fixed(&[F64, F64], &[F64]),
+
+            Operator::I32TruncF32S
+            | Operator::I32TruncF32U
+            | Operator::I32TruncSatF32S
+            | Operator::I32TruncSatF32U
+            | Operator::I32ReinterpretF32 => StackEffect::fixed(&[F32], &[I32]),
+            Operator::I32TruncF64S
+            | Operator::I32TruncF64U
+            | Operator::I32TruncSatF64S
+            | Operator::I32TruncSatF64U => StackEffect::fixed(&[F64], &[I32]),
+            Operator::I64ExtendI32S | Operator::I64ExtendI32U => StackEffect::fixed(&[I32], &[I64]),
+            Operator::I64TruncF32S
+            | Operator::I64TruncF32U
+            | Operator::I64TruncSatF32S
+            | Operator::I64TruncSatF32U => StackEffect::fixed(&[F32], &[I64]),
+            Operator::I64TruncF64S
+            | Operator::I64TruncF64U
+            | Operator::I64TruncSatF64S
+            | Operator::I64TruncSatF64U
+            | Operator::I64ReinterpretF64 => StackEffect::fixed(&[F64], &[I64]),
+            Operator::F32ConvertI32S | Operator::F32ConvertI32U | Operator::F32ReinterpretI32 => {
+                StackEffect::fixed(&[I32], &[F32])
+            }
+            Operator::F32ConvertI64S | Operator::F32ConvertI64U => {
+                StackEffect::fixed(&[I64], &[F32])
+            }
+            Operator::F32DemoteF64 => StackEffect::fixed(&[F64], &[F32]),
+            Operator::F64ConvertI32S | Operator::F64ConvertI32U => {
+                StackEffect::fixed(&[I32], &[F64])
+            }
+            Operator::F64ConvertI64S | Operator::F64ConvertI64U | Operator::F64ReinterpretI64 => {
+                StackEffect::fixed(&[I64], &[F64])
+            }
+            Operator::F64PromoteF32 => StackEffect::fixed(&[F32], &[F64]),
+
+            Operator::MemoryInit { mem, .. } => {
+                StackEffect::fixed(&[memory_index_type(resources, mem)?, I32, I32], &[])
+            }
+            Operator::DataDrop { .. } | Operator::ElemDrop { .. } => StackEffect::fixed(&[], &[]),
+            Operator::MemoryCopy { src, dst } => {
+                let src = memory_index_type(resources, src)?;
+                let dst = memory_index_type(resources, dst)?;
+                let len = if src == I32 { I32 } else { dst };
+                StackEffect::fixed(&[dst, src, len], &[])
+            }
+            Operator::MemoryFill { mem } => {
+                let ty = memory_index_type(resources, mem)?;
+                StackEffect::fixed(&[ty, I32, ty], &[])
+            }
+            Operator::TableInit { table: index, .. } => {
+                table(index)?;
+                StackEffect::fixed(&[I32, I32, I32], &[])
+            }
+            Operator::TableCopy {
+                dst_table,
+                src_table,
+            } => {
+                table(dst_table)?;
+                table(src_table)?;
+                StackEffect::fixed(&[I32, I32, I32], &[])
+            }
+            Operator::TableFill { table: index } => {
+                StackEffect::fixed(&[I32, table(index)?, I32], &[])
+            }
+            Operator::TableGet { table: index } => StackEffect::fixed(&[I32], &[table(index)?]),
+            Operator::TableSet { table: index } => StackEffect::fixed(&[I32, table(index)?], &[]),
+            Operator::TableGrow { table: index } => {
+                StackEffect::fixed(&[table(index)?, I32], &[I32])
+            }
+            Operator::TableSize { table: index } => {
+                table(index)?;
+                StackEffect::fixed(&[], &[I32])
+            }
+            Operator::I64Add128 | Operator::I64Sub128 => {
+                StackEffect::fixed(&[I64, I64, I64, I64], &[I64, I64])
+            }
+            Operator::I64MulWideS | Operator::I64MulWideU => {
+                StackEffect::fixed(&[I64, I64], &[I64, I64])
+            }
+
+            Operator::I8x16Splat | Operator::I16x8Splat | Operator::I32x4Splat => {
+                StackEffect::fixed(&[I32], &[V128])
+            }
+            Operator::I64x2Splat => StackEffect::fixed(&[I64], &[V128]),
+            Operator::F32x4Splat => StackEffect::fixed(&[F32], &[V128]),
+            Operator::F64x2Splat => StackEffect::fixed(&[F64], &[V128]),
+            Operator::I8x16ExtractLaneS { .. }
+            | Operator::I8x16ExtractLaneU { .. }
+            | Operator::I16x8ExtractLaneS { .. }
+            | Operator::I16x8ExtractLaneU { .. }
+            | Operator::I32x4ExtractLane { .. }
+            | Operator::V128AnyTrue
+            | Operator::I8x16AllTrue
+            | Operator::I8x16Bitmask
+            | Operator::I16x8AllTrue
+            | Operator::I16x8Bitmask
+            | Operator::I32x4AllTrue
+            | Operator::I32x4Bitmask
+            | Operator::I64x2Bitmask => StackEffect::fixed(&[V128], &[I32]),
+            Operator::I64x2ExtractLane { .. } => StackEffect::fixed(&[V128], &[I64]),
+            Operator::F32x4ExtractLane { .. } => StackEffect::fixed(&[V128], &[F32]),
+            Operator::F64x2ExtractLane { .. } => StackEffect::fixed(&[V128], &[F64]),
+            Operator::I8x16ReplaceLane { .. }
+            | Operator::I16x8ReplaceLane { .. }
+            | Operator::I32x4ReplaceLane { .. }
+            | Operator::I8x16Shl
+            | Operator::I8x16ShrS
+            | Operator::I8x16ShrU
+            | Operator::I16x8Shl
+            | Operator::I16x8ShrS
+            | Operator::I16x8ShrU
+            | Operator::I32x4Shl
+            | Operator::I32x4ShrS
+            | Operator::I32x4ShrU
+            | Operator::I64x2Shl
+            | Operator::I64x2ShrS
+            | Operator::I64x2ShrU => StackEffect::fixed(&[V128, I32], &[V128]),
+            Operator::I64x2ReplaceLane { .. } => StackEffect::fixed(&[V128, I64], &[V128]),
+            Operator::F32x4ReplaceLane { .. } => StackEffect::fixed(&[V128, F32], &[V128]),
+            Operator::F64x2ReplaceLane { .. } => StackEffect::fixed(&[V128, F64], &[V128]),
+            Operator::V128Not
+            | Operator::I8x16Abs
+            | Operator::I8x16Neg
+            | Operator::I16x8Abs
+            | Operator::I16x8Neg
+            | Operator::I32x4Abs
+            | Operator::I32x4Neg
+            | Operator::I64x2Neg
+            | Operator::F32x4Ceil
+            | Operator::F32x4Floor
+            | Operator::F32x4Trunc
+            | Operator::F32x4Nearest
+            | Operator::F64x2Ceil
+            | Operator::F64x2Floor
+            | Operator::F64x2Trunc
+            | Operator::F64x2Nearest
+            | Operator::F32x4Abs
+            | Operator::F32x4Neg
+            | Operator::F32x4Sqrt
+            | Operator::F64x2Abs
+            | Operator::F64x2Neg
+            | Operator::F64x2Sqrt
+            | Operator::I32x4TruncSatF32x4S
+            | Operator::I32x4TruncSatF32x4U
+            | Operator::F32x4ConvertI32x4S
+            | Operator::F32x4ConvertI32x4U
+            | Operator::I16x8WidenLowI8x16S
+            | Operator::I16x8WidenHighI8x16S
+            | Operator::I16x8WidenLowI8x16U
+            | Operator::I16x8WidenHighI8x16U
+            | Operator::I32x4WidenLowI16x8S
+            | Operator::I32x4WidenHighI16x8S
+            | Operator::I32x4WidenLowI16x8U
+            | Operator::I32x4WidenHighI16x8U
+            | Operator::I64x2WidenLowI32x4S
+            | Operator::I64x2WidenHighI32x4S
+            | Operator::I64x2WidenLowI32x4U
+            | Operator::I64x2WidenHighI32x4U => StackEffect::fixed(&[V128], &[V128]),
+            Operator::I8x16Eq
+            | Operator::I8x16Ne
+            | Operator::I8x16LtS
+            | Operator::I8x16LtU
+            | Operator::I8x16GtS
+            | Operator::I8x16GtU
+            | Operator::I8x16LeS
+            | Operator::I8x16LeU
+            | Operator::I8x16GeS
+            | Operator::I8x16GeU
+            | Operator::I16x8Eq
+            | Operator::I16x8Ne
+            | Operator::I16x8LtS
+            | Operator::I16x8LtU
+            | Operator::I16x8GtS
+            | Operator::I16x8GtU
+            | Operator::I16x8LeS
+            | Operator::I16x8LeU
+            | Operator::I16x8GeS
+            | Operator::I16x8GeU
+            | Operator::I32x4Eq
+            | Operator::I32x4Ne
+            | Operator::I32x4LtS
+            | Operator::I32x4LtU
+            | Operator::I32x4GtS
+            | Operator::I32x4GtU
+            | Operator::I32x4LeS
+            | Operator::I32x4LeU
+            | Operator::I32x4GeS
+            | Operator::I32x4GeU
+            | Operator::F32x4Eq
+            | Operator::F32x4Ne
+            | Operator::F32x4Lt
+            | Operator::F32x4Gt
+            | Operator::F32x4Le
+            | Operator::F32x4Ge
+            | Operator::F64x2Eq
+            | Operator::F64x2Ne
+            | Operator::F64x2Lt
+            | Operator::F64x2Gt
+            | Operator::F64x2Le
+            | Operator::F64x2Ge
+            | Operator::V128And
+            | Operator::V128AndNot
+            | Operator::V128Or
+            | Operator::V128Xor
+            | Operator::I8x16Add
+            | Operator::I8x16AddSatS
+            | Operator::I8x16AddSatU
+            | Operator::I8x16Sub
+            | Operator::I8x16SubSatS
+            | Operator::I8x16SubSatU
+            | Operator::I8x16MinS
+            | Operator::I8x16MinU
+            | Operator::I8x16MaxS
+            | Operator::I8x16MaxU
+            | Operator::I16x8Add
+            | Operator::I16x8AddSatS
+            | Operator::I16x8AddSatU
+            | Operator::I16x8Sub
+            | Operator::I16x8SubSatS
+            | Operator::I16x8SubSatU
+            | Operator::I16x8Mul
+            | Operator::I16x8MinS
+            | Operator::I16x8MinU
+            | Operator::I16x8MaxS
+            | Operator::I16x8MaxU
+            | Operator::I32x4Add
+            | Operator::I32x4Sub
+            | Operator::I32x4Mul
+            | Operator::I32x4MinS
+            | Operator::I32x4MinU
+            | Operator::I32x4MaxS
+            | Operator::I32x4MaxU
+            | Operator::I32x4DotI16x8S
+            | Operator::I64x2Add
+            | Operator::I64x2Sub
+            | Operator::I64x2Mul
+            | Operator::F32x4Add
+            | Operator::F32x4Sub
+            | Operator::F32x4Mul
+            | Operator::F32x4Div
+            | Operator::F32x4Min
+            | Operator::F32x4Max
+            | Operator::F32x4PMin
+            | Operator::F32x4PMax
+            | Operator::F64x2Add
+            | Operator::F64x2Sub
+            | Operator::F64x2Mul
+            | Operator::F64x2Div
+            | Operator::F64x2Min
+            | Operator::F64x2Max
+            | Operator::F64x2PMin
+            | Operator::F64x2PMax
+            | Operator::I8x16Swizzle
+            | Operator::I8x16Shuffle { .. }
+            | Operator::I8x16NarrowI16x8S
+            | Operator::I8x16NarrowI16x8U
+            | Operator::I16x8NarrowI32x4S
+            | Operator::I16x8NarrowI32x4U
+            | Operator::I16x8ExtMulLowI8x16S
+            | Operator::I16x8ExtMulHighI8x16S
+            | Operator::I16x8ExtMulLowI8x16U
+            | Operator::I16x8ExtMulHighI8x16U
+            | Operator::I32x4ExtMulLowI16x8S
+            | Operator::I32x4ExtMulHighI16x8S
+            | Operator::I32x4ExtMulLowI16x8U
+            | Operator::I32x4ExtMulHighI16x8U
+            | Operator::I64x2ExtMulLowI32x4S
+            | Operator::I64x2ExtMulHighI32x4S
+            | Operator::I64x2ExtMulLowI32x4U
+            | Operator::I64x2ExtMulHighI32x4U
+            | Operator::I8x16RoundingAverageU
+            | Operator::I16x8RoundingAverageU
+            | Operator::I16x8Q15MulrSatS => StackEffect::fixed(&[V128, V128], &[V128]),
+            Operator::V128Bitselect => StackEffect::fixed(&[V128, V128, V128], &[V128]),
+        })
+    }
+}
+
+/// Returns the address type of the memory `index`.
+fn memory_index_type(resources: &impl WasmModuleResources, index: u32) -> Option<Type> {
+    Some(resources.memory_at(index)?.index_type())
+}
+
+/// Returns the parameters of a block of type `ty`.
+fn block_params(
+    resources: &impl WasmModuleResources,
+    ty: TypeOrFuncType,
+) -> Option<Vec<StackType>> {
+    match ty {
+        TypeOrFuncType::Type(_) => Some(Vec::new()),
+        TypeOrFuncType::FuncType(index) => Some(vals(resources.func_type_at(index)?.inputs())),
+    }
+}
@@ -14,16 +14,28 @@
  */
 
 use crate::limits::*;
+use crate::operators_validator::func_type_to_str;
 use crate::ResizableLimits64;
 use crate::WasmModuleResources;
 use crate::{Alias, ExternalKind, Import, ImportSectionEntryType};
-use crate::{BinaryReaderError, EventType, GlobalType, MemoryType, Range, Result, TableType, Type};
+use crate::{
+    BinaryReaderError, ErrorCode, EventType, GlobalType, MemoryType, Range, Result, TableType, Type,
+};
 use crate::{DataKind, ElementItem, ElementKind, InitExpr, Instance, Operator};
 use crate::{FuncType, ResizableLimits, SectionReader, SectionWithLimitedItems};
 use crate::{FunctionBody, Parser, Payload};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::mem;
+#[cfg(feature = "std")]
 use std::collections::{HashMap, HashSet};
-use std::mem;
-use std::sync::Arc;
+
+// Without `std` there's no `HashMap`, so fall back to the ordered collections
+// in `alloc`, which provide all the operations used here.
+#[cfg(not(feature = "std"))]
+use alloc::collections::{BTreeMap as HashMap, BTreeSet as HashSet};
 
 /// Test whether the given buffer contains a valid WebAssembly module,
 /// analogous to [`WebAssembly.validate`][js] in the JS API.
@@ -47,7 +59,9 @@ fn test_validate() {
 }
 
 mod func;
-pub use func::FuncValidator;
+pub use func::{FuncToValidate, FuncValidator, FuncValidatorAllocations};
+mod streaming;
+pub use streaming::{StreamingValidator, ValidationStatus};
 
 /// Validator for a WebAssembly binary module.
 ///
@@ -91,6 +105,9 @@ pub struct Validator {
     /// isn't.
     features: WasmFeatures,
 
+    /// Implementation limits, such as the maximum number of locals.
+    limits: WasmLimits,
+
     /// The current byte-level offset in the wasm binary. This is updated to
     /// produce error messages in `create_error`.
     offset: usize,
@@ -155,6 +172,7 @@ struct ModuleState {
 
 /// Flags for features that are enabled for validation.
 #[derive(Hash, Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct WasmFeatures {
     /// The WebAssembly reference types proposal (enabled by default)
     pub reference_types: bool,
@@ -178,6 +196,10 @@ pub struct WasmFeatures {
     pub exceptions: bool,
     /// The WebAssembly memory64 proposal
     pub memory64: bool,
+    /// The WebAssembly wide-arithmetic proposal
+    pub wide_arithmetic: bool,
+    /// The WebAssembly function references proposal
+    pub function_references: bool,
 }
 
 impl Default for WasmFeatures {
@@ -191,6 +213,8 @@ impl Default for WasmFeatures {
             multi_memory: false,
             exceptions: false,
             memory64: false,
+            wide_arithmetic: false,
+            function_references: false,
             deterministic_only: cfg!(feature = "deterministic"),
 
             // on-by-default features
@@ -201,6 +225,49 @@ impl Default for WasmFeatures {
     }
 }
 
+/// Implementation limits which are enforced during validation.
+///
+/// The defaults are the limits which WebAssembly engines have agreed upon, so
+/// hosts whose engine enforces lower limits can reject modules the engine
+/// would reject when they're validated. The number of locals, parameters and
+/// results are also limited while reading binaries, so those can be lowered
+/// but not raised above their defaults.
+#[derive(Hash, Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct WasmLimits {
+    /// The maximum size of a binary in bytes (1 GiB by default). This is only
+    /// enforced by [`Validator::validate_all`] and [`StreamingValidator`],
+    /// which see the whole binary.
+    pub max_module_size: usize,
+    /// The maximum size of a function body in bytes, not including the
+    /// body's size prefix (7,654,321 by default).
+    pub max_function_size: usize,
+    /// The maximum number of locals of a function, including its parameters
+    /// and the locals of `let` blocks (50,000 by default).
+    pub max_function_locals: u32,
+    /// The maximum number of parameters of a function type (1,000 by
+    /// default).
+    pub max_function_params: u32,
+    /// The maximum number of results of a function type (1,000 by default).
+    pub max_function_results: u32,
+    /// The maximum number of nested control frames in a function body,
+    /// including the function body itself (unlimited by default).
+    pub max_nesting_depth: u32,
+}
+
+impl Default for WasmLimits {
+    fn default() -> WasmLimits {
+        WasmLimits {
+            max_module_size: MAX_WASM_MODULE_SIZE,
+            max_function_size: MAX_WASM_FUNCTION_BODY_SIZE,
+            max_function_locals: MAX_WASM_FUNCTION_LOCALS as u32,
+            max_function_params: MAX_WASM_FUNCTION_PARAMS as u32,
+            max_function_results: MAX_WASM_FUNCTION_RETURNS as u32,
+            max_nesting_depth: u32::MAX,
+        }
+    }
+}
+
 #[derive(Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Debug)]
 enum Order {
     Initial,
@@ -286,8 +353,11 @@ pub enum ValidPayload<'a> {
     /// This result indicates that the specified parser should be used instead
     /// of the currently-used parser until this returned one ends.
     Submodule(Parser),
-    /// A function was found to be validate.
-    Func(FuncValidator<ValidatorResources>, FunctionBody<'a>),
+    /// A function was found to be validated.
+    ///
+    /// The function can be validated at any later point, on any thread, by
+    /// converting the [`FuncToValidate`] into a [`FuncValidator`].
+    Func(FuncToValidate<ValidatorResources>, FunctionBody<'a>),
 }
 
 impl Validator {
@@ -306,6 +376,12 @@ impl Validator {
         self
     }
 
+    /// Configures the implementation limits enforced by this `Validator`.
+    pub fn wasm_limits(&mut self, limits: WasmLimits) -> &mut Validator {
+        self.limits = limits;
+        self
+    }
+
     /// Validates an entire in-memory module with this validator.
     ///
     /// This function will internally create a [`Parser`] to parse the `bytes`
@@ -314,6 +390,7 @@ impl Validator {
     /// `Err(_)`, and otherwise a successful validation means `Ok(())` is
     /// returned.
     pub fn validate_all(&mut self, bytes: &[u8]) -> Result<()> {
+        self.check_module_size(bytes.len())?;
         let mut functions_to_validate = Vec::new();
         for payload in Parser::new(0).parse_all(bytes) {
             if let ValidPayload::Func(a, b) = self.payload(&payload?)? {
@@ -321,8 +398,11 @@ impl Validator {
             }
         }
 
-        for (mut validator, body) in functions_to_validate {
+        let mut allocs = FuncValidatorAllocations::default();
+        for (func, body) in functions_to_validate {
+            let mut validator = func.into_validator(allocs);
             validator.validate(&body)?;
+            allocs = validator.into_allocations();
         }
         Ok(())
     }
@@ -363,8 +443,8 @@ impl Validator {
                 size: _,
             } => self.code_section_start(*count, range)?,
             CodeSectionEntry(body) => {
-                let func_validator = self.code_section_entry()?;
-                return Ok(ValidPayload::Func(func_validator, body.clone()));
+                let func = self.code_section_entry()?;
+                return Ok(ValidPayload::Func(func, body.clone()));
             }
             ModuleSectionStart {
                 count,
@@ -384,19 +464,31 @@ impl Validator {
         Ok(ValidPayload::Ok)
     }
 
-    fn create_error<T>(&self, msg: impl Into<String>) -> Result<T> {
-        Err(BinaryReaderError::new(msg.into(), self.offset))
+    fn check_module_size(&self, size: usize) -> Result<()> {
+        let max = self.limits.max_module_size;
+        if size > max {
+            return Err(BinaryReaderError::new(
+                ErrorCode::LimitExceeded,
+                format!("module is larger than the limit of {} bytes", max),
+                max,
+            ));
+        }
+        Ok(())
+    }
+
+    fn create_error<T>(&self, code: ErrorCode, msg: impl Into<String>) -> Result<T> {
+        Err(BinaryReaderError::new(code, msg.into(), self.offset))
     }
 
     /// Validates [`Payload::Version`](crate::Payload)
     pub fn version(&mut self, num: u32, range: &Range) -> Result<()> {
         self.offset = range.start;
         if self.cur.order != Order::Initial {
-            return self.create_error("wasm version header out of order");
+            return self.create_error(ErrorCode::SectionOrder, "wasm version header out of order");
         }
         self.cur.order = Order::AfterHeader;
         if num != 1 {
-            return self.create_error("bad wasm file version");
+            return self.create_error(ErrorCode::InvalidHeader, "bad wasm file version");
         }
         Ok(())
     }
@@ -413,7 +505,7 @@ impl Validator {
         if prev == self.cur.order && self.cur.order == Order::ModuleLinkingHeader {
             return Ok(());
         }
-        self.create_error("section out of order")
+        self.create_error(ErrorCode::SectionOrder, "section out of order")
     }
 
     fn header_order(&mut self, order: Order) -> Order {
@@ -427,55 +519,70 @@ impl Validator {
     fn get_type(&self, idx: u32) -> Result<&TypeDef> {
         match self.cur.state.types.get(idx as usize) {
             Some(t) => Ok(&self.types[*t]),
-            None => self.create_error("unknown type: type index out of bounds"),
+            None => self.create_error(
+                ErrorCode::UnknownType,
+                "unknown type: type index out of bounds",
+            ),
         }
     }
 
     fn get_table(&self, idx: u32) -> Result<&TableType> {
         match self.cur.state.tables.get(idx as usize) {
             Some(t) => Ok(t),
-            None => self.create_error("unknown table: table index out of bounds"),
+            None => self.create_error(
+                ErrorCode::UnknownTable,
+                "unknown table: table index out of bounds",
+            ),
         }
     }
 
     fn get_memory(&self, idx: u32) -> Result<&MemoryType> {
         match self.cur.state.memories.get(idx as usize) {
             Some(t) => Ok(t),
-            None => self.create_error(format!(
-                "unknown memory {}: memory index out of bounds",
-                idx,
-            )),
+            None => self.create_error(
+                ErrorCode::UnknownMemory,
+                format!("unknown memory {}: memory index out of bounds", idx,),
+            ),
         }
     }
 
     fn get_global(&self, idx: u32) -> Result<&GlobalType> {
         match self.cur.state.globals.get(idx as usize) {
             Some(t) => Ok(t),
-            None => self.create_error("unknown global: global index out of bounds"),
+            None => self.create_error(
+                ErrorCode::UnknownGlobal,
+                "unknown global: global index out of bounds",
+            ),
         }
     }
 
     fn get_func_type(&self, func_idx: u32) -> Result<&FuncType> {
         match self.cur.state.func_types.get(func_idx as usize) {
             Some(t) => Ok(self.types[*t].unwrap_func()),
-            None => self.create_error(format!(
-                "unknown function {}: func index out of bounds",
-                func_idx,
-            )),
+            None => self.create_error(
+                ErrorCode::UnknownFunction,
+                format!("unknown function {}: func index out of bounds", func_idx,),
+            ),
         }
     }
 
     fn get_module_type(&self, module_idx: u32) -> Result<&ModuleType> {
         match self.cur.state.submodules.get(module_idx as usize) {
             Some(t) => Ok(self.types[*t].unwrap_module()),
-            None => self.create_error("unknown module: module index out of bounds"),
+            None => self.create_error(
+                ErrorCode::UnknownModule,
+                "unknown module: module index out of bounds",
+            ),
         }
     }
 
     fn get_instance_type(&self, instance_idx: u32) -> Result<&InstanceType> {
         match self.cur.state.instances.get(instance_idx as usize) {
             Some(t) => Ok(self.types[*t].unwrap_instance()),
-            None => self.create_error("unknown instance: instance index out of bounds"),
+            None => self.create_error(
+                ErrorCode::UnknownInstance,
+                "unknown instance: instance index out of bounds",
+            ),
         }
     }
 
@@ -483,29 +590,35 @@ impl Validator {
         let def = self.get_type(type_index)?;
         match def {
             TypeDef::Func(item) => Ok(item),
-            _ => self.create_error("type index is not a function"),
+            _ => self.create_error(ErrorCode::InvalidType, "type index is not a function"),
         }
     }
 
     fn module_type_at(&self, type_index: u32) -> Result<&ModuleType> {
         if !self.features.module_linking {
-            return self.create_error("module linking proposal not enabled");
+            return self.create_error(
+                ErrorCode::FeatureNotEnabled,
+                "module linking proposal not enabled",
+            );
         }
         let ty = self.get_type(type_index)?;
         match ty {
             TypeDef::Module(item) => Ok(item),
-            _ => self.create_error("type index is not a module"),
+            _ => self.create_error(ErrorCode::InvalidType, "type index is not a module"),
         }
     }
 
     fn instance_type_at(&self, type_index: u32) -> Result<&InstanceType> {
         if !self.features.module_linking {
-            return self.create_error("module linking proposal not enabled");
+            return self.create_error(
+                ErrorCode::FeatureNotEnabled,
+                "module linking proposal not enabled",
+            );
         }
         let ty = self.get_type(type_index)?;
         match ty {
             TypeDef::Instance(item) => Ok(item),
-            _ => self.create_error("type index is not an instance"),
+            _ => self.create_error(ErrorCode::InvalidType, "type index is not an instance"),
         }
     }
 
@@ -516,9 +629,12 @@ impl Validator {
             .is_none();
         if overflow {
             return if max == 1 {
-                self.create_error(format!("multiple {}", desc))
+                self.create_error(ErrorCode::FeatureNotEnabled, format!("multiple {}", desc))
             } else {
-                self.create_error(format!("{} count is out of bounds", desc))
+                self.create_error(
+                    ErrorCode::LimitExceeded,
+                    format!("{} count is out of bounds", desc),
+                )
             };
         }
         Ok(())
@@ -565,15 +681,41 @@ impl Validator {
                 for ty in t.params.iter().chain(t.returns.iter()) {
                     self.value_type(*ty)?;
                 }
+                if t.params.len() > self.limits.max_function_params as usize {
+                    return self.create_error(
+                        ErrorCode::LimitExceeded,
+                        format!(
+                            "function type has {} parameters, but the limit is {}",
+                            t.params.len(),
+                            self.limits.max_function_params
+                        ),
+                    );
+                }
+                if t.returns.len() > self.limits.max_function_results as usize {
+                    return self.create_error(
+                        ErrorCode::LimitExceeded,
+                        format!(
+                            "function type has {} results, but the limit is {}",
+                            t.returns.len(),
+                            self.limits.max_function_results
+                        ),
+                    );
+                }
                 if t.returns.len() > 1 && !self.features.multi_value {
-                    return self
-                        .create_error("invalid result arity: func type returns multiple values");
+                    return self.create_error(
+                        ErrorCode::FeatureNotEnabled,
+                        "invalid result arity: func type returns multiple values, \
+                         which requires the multi-value feature to be enabled",
+                    );
                 }
                 TypeDef::Func(t)
             }
             crate::TypeDef::Module(t) => {
                 if !self.features.module_linking {
-                    return self.create_error("module linking proposal not enabled");
+                    return self.create_error(
+                        ErrorCode::FeatureNotEnabled,
+                        "module linking proposal not enabled",
+                    );
                 }
                 let mut imports = NameSet::default();
                 for i in t.imports.iter() {
@@ -600,7 +742,10 @@ impl Validator {
             }
             crate::TypeDef::Instance(t) => {
                 if !self.features.module_linking {
-                    return self.create_error("module linking proposal not enabled");
+                    return self.create_error(
+                        ErrorCode::FeatureNotEnabled,
+                        "module linking proposal not enabled",
+                    );
                 }
                 let mut exports = NameSet::default();
                 for e in t.exports.iter() {
@@ -620,7 +765,7 @@ impl Validator {
     fn value_type(&self, ty: Type) -> Result<()> {
         match self.features.check_value_type(ty) {
             Ok(()) => Ok(()),
-            Err(e) => self.create_error(e),
+            Err((code, e)) => self.create_error(code, e),
         }
     }
 
@@ -668,14 +813,17 @@ impl Validator {
             Type::FuncRef => {}
             Type::ExternRef => {
                 if !self.features.reference_types {
-                    return self.create_error("element is not anyfunc");
+                    return self.create_error(ErrorCode::InvalidType, "element is not anyfunc");
                 }
             }
-            _ => return self.create_error("element is not reference type"),
+            _ => return self.create_error(ErrorCode::InvalidType, "element is not reference type"),
         }
         self.limits(&ty.limits)?;
         if ty.limits.initial > MAX_WASM_TABLE_ENTRIES as u32 {
-            return self.create_error("minimum table size is out of bounds");
+            return self.create_error(
+                ErrorCode::LimitExceeded,
+                "minimum table size is out of bounds",
+            );
         }
         Ok(())
     }
@@ -686,42 +834,67 @@ impl Validator {
                 self.limits(limits)?;
                 let initial = limits.initial;
                 if initial as usize > MAX_WASM_MEMORY_PAGES {
-                    return self.create_error("memory size must be at most 65536 pages (4GiB)");
+                    return self.create_error(
+                        ErrorCode::LimitExceeded,
+                        "memory size must be at most 65536 pages (4GiB)",
+                    );
                 }
                 if let Some(maximum) = limits.maximum {
                     if maximum as usize > MAX_WASM_MEMORY_PAGES {
-                        return self.create_error("memory size must be at most 65536 pages (4GiB)");
+                        return self.create_error(
+                            ErrorCode::LimitExceeded,
+                            "memory size must be at most 65536 pages (4GiB)",
+                        );
                     }
                 }
                 if *shared {
                     if !self.features.threads {
-                        return self.create_error("threads must be enabled for shared memories");
+                        return self.create_error(
+                            ErrorCode::FeatureNotEnabled,
+                            "threads must be enabled for shared memories",
+                        );
                     }
                     if limits.maximum.is_none() {
-                        return self.create_error("shared memory must have maximum size");
+                        return self.create_error(
+                            ErrorCode::InvalidLimits,
+                            "shared memory must have maximum size",
+                        );
                     }
                 }
             }
             MemoryType::M64 { limits, shared } => {
                 if !self.features.memory64 {
-                    return self.create_error("memory64 must be enabled for 64-bit memories");
+                    return self.create_error(
+                        ErrorCode::FeatureNotEnabled,
+                        "memory64 must be enabled for 64-bit memories",
+                    );
                 }
                 self.limits64(&limits)?;
                 let initial = limits.initial;
                 if initial > MAX_WASM_MEMORY64_PAGES {
-                    return self.create_error("memory initial size too large");
+                    return self
+                        .create_error(ErrorCode::LimitExceeded, "memory initial size too large");
                 }
                 if let Some(maximum) = limits.maximum {
                     if maximum > MAX_WASM_MEMORY64_PAGES {
-                        return self.create_error("memory initial size too large");
+                        return self.create_error(
+                            ErrorCode::LimitExceeded,
+                            "memory initial size too large",
+                        );
                     }
                 }
                 if *shared {
                     if !self.features.threads {
-                        return self.create_error("threads must be enabled for shared memories");
+                        return self.create_error(
+                            ErrorCode::FeatureNotEnabled,
+                            "threads must be enabled for shared memories",
+                        );
                     }
                     if limits.maximum.is_none() {
-                        return self.create_error("shared memory must have maximum size");
+                        return self.create_error(
+                            ErrorCode::InvalidLimits,
+                            "shared memory must have maximum size",
+                        );
                     }
                 }
             }
@@ -732,7 +905,10 @@ impl Validator {
     fn event_type(&self, ty: &EventType) -> Result<()> {
         let ty = self.func_type_at(ty.type_index)?;
         if ty.returns.len() > 0 {
-            return self.create_error("invalid result arity for exception type");
+            return self.create_error(
+                ErrorCode::InvalidType,
+                "invalid result arity for exception type",
+            );
         }
         Ok(())
     }
@@ -744,7 +920,10 @@ impl Validator {
     fn limits(&self, limits: &ResizableLimits) -> Result<()> {
         if let Some(max) = limits.maximum {
             if limits.initial > max {
-                return self.create_error("size minimum must not be greater than maximum");
+                return self.create_error(
+                    ErrorCode::InvalidLimits,
+                    "size minimum must not be greater than maximum",
+                );
             }
         }
         Ok(())
@@ -753,7 +932,10 @@ impl Validator {
     fn limits64(&self, limits: &ResizableLimits64) -> Result<()> {
         if let Some(max) = limits.maximum {
             if limits.initial > max {
-                return self.create_error("size minimum must not be greater than maximum");
+                return self.create_error(
+                    ErrorCode::InvalidLimits,
+                    "size minimum must not be greater than maximum",
+                );
             }
         }
         Ok(())
@@ -767,13 +949,16 @@ impl Validator {
         // Clear the list of implicit imports after the import section is
         // finished since later import sections cannot append further to the
         // pseudo-instances defined in this import section.
-        self.cur.state.assert_mut().imports.implicit.drain();
+        self.cur.state.assert_mut().imports.implicit.clear();
         Ok(())
     }
 
     fn import(&mut self, entry: Import<'_>) -> Result<()> {
         if !self.features.module_linking && entry.field.is_none() {
-            return self.create_error("module linking proposal is not enabled");
+            return self.create_error(
+                ErrorCode::FeatureNotEnabled,
+                "module linking proposal is not enabled",
+            );
         }
         let ty = self.import_entry_type(&entry.ty)?;
         let state = self.cur.state.assert_mut();
@@ -843,7 +1028,10 @@ impl Validator {
     pub fn module_section_start(&mut self, count: u32, range: &Range) -> Result<()> {
         drop(count);
         if !self.features.module_linking {
-            return self.create_error("module linking proposal not enabled");
+            return self.create_error(
+                ErrorCode::FeatureNotEnabled,
+                "module linking proposal not enabled",
+            );
         }
         self.offset = range.start;
         self.update_order(Order::ModuleLinkingHeader)?;
@@ -872,7 +1060,10 @@ impl Validator {
     /// Validates [`Payload::AliasSection`](crate::Payload)
     pub fn alias_section(&mut self, section: &crate::AliasSectionReader<'_>) -> Result<()> {
         if !self.features.module_linking {
-            return self.create_error("module linking proposal not enabled");
+            return self.create_error(
+                ErrorCode::FeatureNotEnabled,
+                "module linking proposal not enabled",
+            );
         }
         self.section(Order::ModuleLinkingHeader, section, |me, a| me.alias(a))
     }
@@ -888,10 +1079,10 @@ impl Validator {
                 let export = match ty.exports.get(export) {
                     Some(e) => e,
                     None => {
-                        return self.create_error(format!(
-                            "aliased name `{}` does not exist in instance",
-                            export
-                        ));
+                        return self.create_error(
+                            ErrorCode::InvalidAlias,
+                            format!("aliased name `{}` does not exist in instance", export),
+                        );
                     }
                 };
                 match (export, kind) {
@@ -923,7 +1114,12 @@ impl Validator {
                         let ty = *ty;
                         self.cur.state.assert_mut().submodules.push(ty);
                     }
-                    _ => return self.create_error("alias kind mismatch with export kind"),
+                    _ => {
+                        return self.create_error(
+                            ErrorCode::InvalidAlias,
+                            "alias kind mismatch with export kind",
+                        )
+                    }
                 }
             }
             Alias::OuterType {
@@ -936,11 +1132,20 @@ impl Validator {
                     .checked_sub(relative_depth as usize)
                     .and_then(|i| i.checked_sub(1))
                     .ok_or_else(|| {
-                        BinaryReaderError::new("relative depth too large", self.offset)
+                        BinaryReaderError::new(
+                            ErrorCode::InvalidAlias,
+                            "relative depth too large",
+                            self.offset,
+                        )
                     })?;
                 let ty = match self.parents[i].state.types.get(index as usize) {
                     Some(m) => *m,
-                    None => return self.create_error("alias to type not defined in parent yet"),
+                    None => {
+                        return self.create_error(
+                            ErrorCode::InvalidAlias,
+                            "alias to type not defined in parent yet",
+                        )
+                    }
                 };
                 self.cur.state.assert_mut().types.push(ty);
             }
@@ -954,11 +1159,20 @@ impl Validator {
                     .checked_sub(relative_depth as usize)
                     .and_then(|i| i.checked_sub(1))
                     .ok_or_else(|| {
-                        BinaryReaderError::new("relative depth too large", self.offset)
+                        BinaryReaderError::new(
+                            ErrorCode::InvalidAlias,
+                            "relative depth too large",
+                            self.offset,
+                        )
                     })?;
                 let module = match self.parents[i].state.submodules.get(index as usize) {
                     Some(m) => *m,
-                    None => return self.create_error("alias to module not defined in parent yet"),
+                    None => {
+                        return self.create_error(
+                            ErrorCode::InvalidAlias,
+                            "alias to module not defined in parent yet",
+                        )
+                    }
                 };
                 self.cur.state.assert_mut().submodules.push(module);
             }
@@ -970,7 +1184,10 @@ impl Validator {
     /// Validates [`Payload::InstanceSection`](crate::Payload)
     pub fn instance_section(&mut self, section: &crate::InstanceSectionReader<'_>) -> Result<()> {
         if !self.features.module_linking {
-            return self.create_error("module linking proposal not enabled");
+            return self.create_error(
+                ErrorCode::FeatureNotEnabled,
+                "module linking proposal not enabled",
+            );
         }
         self.check_max(
             self.cur.state.instances.len(),
@@ -1031,73 +1248,73 @@ impl Validator {
             EntityType::Global(a) => {
                 let b = match b {
                     EntityType::Global(b) => b,
-                    _ => return self.create_error("item type mismatch"),
+                    _ => return self.create_error(ErrorCode::TypeMismatch, "item type mismatch"),
                 };
                 if a == b {
                     Ok(())
                 } else {
-                    self.create_error("global type mismatch")
+                    self.create_error(ErrorCode::TypeMismatch, "global type mismatch")
                 }
             }
             EntityType::Table(a) => {
                 let b = match b {
                     EntityType::Table(b) => b,
-                    _ => return self.create_error("item type mismatch"),
+                    _ => return self.create_error(ErrorCode::TypeMismatch, "item type mismatch"),
                 };
                 if a.element_type == b.element_type && limits_match!(&a.limits, &b.limits) {
                     Ok(())
                 } else {
-                    self.create_error("table type mismatch")
+                    self.create_error(ErrorCode::TypeMismatch, "table type mismatch")
                 }
             }
             EntityType::Func(a) => {
                 let b = match b {
                     EntityType::Func(b) => b,
-                    _ => return self.create_error("item type mismatch"),
+                    _ => return self.create_error(ErrorCode::TypeMismatch, "item type mismatch"),
                 };
                 if self.types[*a].unwrap_func() == self.types[*b].unwrap_func() {
                     Ok(())
                 } else {
-                    self.create_error("func type mismatch")
+                    self.create_error(ErrorCode::TypeMismatch, "func type mismatch")
                 }
             }
             EntityType::Event(a) => {
                 let b = match b {
                     EntityType::Event(b) => b,
-                    _ => return self.create_error("item type mismatch"),
+                    _ => return self.create_error(ErrorCode::TypeMismatch, "item type mismatch"),
                 };
                 if self.types[*a].unwrap_func() == self.types[*b].unwrap_func() {
                     Ok(())
                 } else {
-                    self.create_error("event type mismatch")
+                    self.create_error(ErrorCode::TypeMismatch, "event type mismatch")
                 }
             }
             EntityType::Memory(MemoryType::M32 { limits, shared }) => {
                 let (b_limits, b_shared) = match b {
                     EntityType::Memory(MemoryType::M32 { limits, shared }) => (limits, shared),
-                    _ => return self.create_error("item type mismatch"),
+                    _ => return self.create_error(ErrorCode::TypeMismatch, "item type mismatch"),
                 };
                 if limits_match!(limits, b_limits) && shared == b_shared {
                     Ok(())
                 } else {
-                    self.create_error("memory type mismatch")
+                    self.create_error(ErrorCode::TypeMismatch, "memory type mismatch")
                 }
             }
             EntityType::Memory(MemoryType::M64 { limits, shared }) => {
                 let (b_limits, b_shared) = match b {
                     EntityType::Memory(MemoryType::M64 { limits, shared }) => (limits, shared),
-                    _ => return self.create_error("item type mismatch"),
+                    _ => return self.create_error(ErrorCode::TypeMismatch, "item type mismatch"),
                 };
                 if limits_match!(limits, b_limits) && shared == b_shared {
                     Ok(())
                 } else {
-                    self.create_error("memory type mismatch")
+                    self.create_error(ErrorCode::TypeMismatch, "memory type mismatch")
                 }
             }
             EntityType::Instance(a) => {
                 let b = match b {
                     EntityType::Instance(b) => b,
-                    _ => return self.create_error("item type mismatch"),
+                    _ => return self.create_error(ErrorCode::TypeMismatch, "item type mismatch"),
                 };
                 let a = self.types[*a].unwrap_instance();
                 let b = self.types[*b].unwrap_instance();
@@ -1107,7 +1324,7 @@ impl Validator {
             EntityType::Module(a) => {
                 let b = match b {
                     EntityType::Module(b) => b,
-                    _ => return self.create_error("item type mismatch"),
+                    _ => return self.create_error(ErrorCode::TypeMismatch, "item type mismatch"),
                 };
                 let a = self.types[*a].unwrap_module();
                 let b = self.types[*b].unwrap_module();
@@ -1129,7 +1346,12 @@ impl Validator {
         for (name, b) in b {
             match a.get(name) {
                 Some(a) => self.check_subtypes(a, b)?,
-                None => return self.create_error(&format!("no {} named `{}`", desc, name)),
+                None => {
+                    return self.create_error(
+                        ErrorCode::InvalidAlias,
+                        &format!("no {} named `{}`", desc, name),
+                    )
+                }
             }
         }
         Ok(())
@@ -1236,7 +1458,10 @@ impl Validator {
         let (op, offset) = match ops.next() {
             Some(Err(e)) => return Err(e),
             Some(Ok(pair)) => pair,
-            None => return self.create_error("type mismatch: init_expr is empty"),
+            None => {
+                return self
+                    .create_error(ErrorCode::TypeMismatch, "type mismatch: init_expr is empty")
+            }
         };
         self.offset = offset;
         let ty = match op {
@@ -1256,15 +1481,23 @@ impl Validator {
                     .insert(function_index);
                 Type::FuncRef
             }
-            Operator::End => return self.create_error("type mismatch: init_expr is empty"),
-            _ => {
+            Operator::End => {
                 return self
-                    .create_error("constant expression required: invalid init_expr operator")
+                    .create_error(ErrorCode::TypeMismatch, "type mismatch: init_expr is empty")
+            }
+            _ => {
+                return self.create_error(
+                    ErrorCode::ConstantExpressionRequired,
+                    "constant expression required: invalid init_expr operator",
+                )
             }
         };
         if ty != expected_ty {
             if !allow32 || ty != Type::I32 {
-                return self.create_error("type mismatch: invalid init_expr type");
+                return self.create_error(
+                    ErrorCode::TypeMismatch,
+                    "type mismatch: invalid init_expr type",
+                );
             }
         }
 
@@ -1274,32 +1507,39 @@ impl Validator {
             Some(Ok((Operator::End, _))) => {}
             Some(Ok(_)) => {
                 return self
-                    .create_error("constant expression required: type mismatch: only one init_expr operator is expected")
+                    .create_error(ErrorCode::ConstantExpressionRequired, "constant expression required: type mismatch: only one init_expr operator is expected")
             }
-            None => return self.create_error("type mismatch: init_expr is not terminated"),
+            None => return self.create_error(ErrorCode::TypeMismatch, "type mismatch: init_expr is not terminated"),
         }
 
         // ... and verify we're done after that
         match ops.next() {
             Some(Err(e)) => Err(e),
-            Some(Ok(_)) => {
-                self.create_error("constant expression required: invalid init_expr operator")
-            }
+            Some(Ok(_)) => self.create_error(
+                ErrorCode::ConstantExpressionRequired,
+                "constant expression required: invalid init_expr operator",
+            ),
             None => Ok(()),
         }
     }
 
     /// Validates [`Payload::ExportSection`](crate::Payload)
     pub fn export_section(&mut self, section: &crate::ExportSectionReader<'_>) -> Result<()> {
+        // Only used to point duplicate export errors at the first export.
+        let mut offsets = HashMap::new();
         self.section(Order::Export, section, |me, e| {
             if let ExternalKind::Type = e.kind {
-                return me.create_error("cannot export types");
+                return me.create_error(ErrorCode::InvalidExport, "cannot export types");
             }
             let ty = me.check_external_kind("exported", e.kind, e.index)?;
             let state = me.cur.state.assert_mut();
             if state.exports.insert(e.field.to_string(), ty).is_some() {
-                return me.create_error("duplicate export name");
+                let first = offsets[e.field];
+                return me
+                    .create_error(ErrorCode::DuplicateName, "duplicate export name")
+                    .map_err(|e| e.with_related(first, "first exported here"));
             }
+            offsets.insert(e.field, me.offset);
             Ok(())
         })
     }
@@ -1310,21 +1550,28 @@ impl Validator {
         kind: ExternalKind,
         index: u32,
     ) -> Result<EntityType> {
-        let check = |ty: &str, total: usize| {
+        let check = |ty: &str, code: ErrorCode, total: usize| {
             if index as usize >= total {
-                self.create_error(&format!(
-                    "unknown {ty} {index}: {desc} {ty} index out of bounds",
-                    desc = desc,
-                    index = index,
-                    ty = ty,
-                ))
+                self.create_error(
+                    code,
+                    &format!(
+                        "unknown {ty} {index}: {desc} {ty} index out of bounds",
+                        desc = desc,
+                        index = index,
+                        ty = ty,
+                    ),
+                )
             } else {
                 Ok(())
             }
         };
         Ok(match kind {
             ExternalKind::Function => {
-                check("function", self.cur.state.func_types.len())?;
+                check(
+                    "function",
+                    ErrorCode::UnknownFunction,
+                    self.cur.state.func_types.len(),
+                )?;
                 self.cur
                     .state
                     .assert_mut()
@@ -1333,30 +1580,56 @@ impl Validator {
                 EntityType::Func(self.cur.state.func_types[index as usize])
             }
             ExternalKind::Table => {
-                check("table", self.cur.state.tables.len())?;
+                check(
+                    "table",
+                    ErrorCode::UnknownTable,
+                    self.cur.state.tables.len(),
+                )?;
                 EntityType::Table(self.cur.state.tables[index as usize].clone())
             }
             ExternalKind::Memory => {
-                check("memory", self.cur.state.memories.len())?;
+                check(
+                    "memory",
+                    ErrorCode::UnknownMemory,
+                    self.cur.state.memories.len(),
+                )?;
                 EntityType::Memory(self.cur.state.memories[index as usize].clone())
             }
             ExternalKind::Global => {
-                check("global", self.cur.state.globals.len())?;
+                check(
+                    "global",
+                    ErrorCode::UnknownGlobal,
+                    self.cur.state.globals.len(),
+                )?;
                 EntityType::Global(self.cur.state.globals[index as usize].clone())
             }
             ExternalKind::Event => {
-                check("event", self.cur.state.events.len())?;
+                check(
+                    "event",
+                    ErrorCode::UnknownEvent,
+                    self.cur.state.events.len(),
+                )?;
                 EntityType::Event(self.cur.state.events[index as usize])
             }
             ExternalKind::Module => {
-                check("module", self.cur.state.submodules.len())?;
+                check(
+                    "module",
+                    ErrorCode::UnknownModule,
+                    self.cur.state.submodules.len(),
+                )?;
                 EntityType::Module(self.cur.state.submodules[index as usize])
             }
             ExternalKind::Instance => {
-                check("instance", self.cur.state.instances.len())?;
+                check(
+                    "instance",
+                    ErrorCode::UnknownInstance,
+                    self.cur.state.instances.len(),
+                )?;
                 EntityType::Instance(self.cur.state.instances[index as usize])
             }
-            ExternalKind::Type => return self.create_error("cannot export types"),
+            ExternalKind::Type => {
+                return self.create_error(ErrorCode::InvalidExport, "cannot export types")
+            }
         })
     }
 
@@ -1366,7 +1639,15 @@ impl Validator {
         self.update_order(Order::Start)?;
         let ty = self.get_func_type(func)?;
         if !ty.params.is_empty() || !ty.returns.is_empty() {
-            return self.create_error("invalid start function type");
+            return self.create_error(
+                ErrorCode::InvalidStartFunction,
+                format!(
+                    "invalid start function type: function {} has type {}, but the \
+                 start function must take no parameters and return no results",
+                    func,
+                    func_type_to_str(ty),
+                ),
+            );
         }
         Ok(())
     }
@@ -1378,10 +1659,12 @@ impl Validator {
                 Type::FuncRef => {}
                 Type::ExternRef if me.features.reference_types => {}
                 Type::ExternRef => {
-                    return me
-                        .create_error("reference types must be enabled for anyref elem segment");
+                    return me.create_error(
+                        ErrorCode::FeatureNotEnabled,
+                        "reference types must be enabled for anyref elem segment",
+                    );
                 }
-                _ => return me.create_error("invalid reference type"),
+                _ => return me.create_error(ErrorCode::InvalidType, "invalid reference type"),
             }
             match e.kind {
                 ElementKind::Active {
@@ -1390,19 +1673,23 @@ impl Validator {
                 } => {
                     let table = me.get_table(table_index)?;
                     if e.ty != table.element_type {
-                        return me.create_error("element_type != table type");
+                        return me
+                            .create_error(ErrorCode::InvalidType, "element_type != table type");
                     }
                     me.init_expr(&init_expr, Type::I32, false)?;
                 }
                 ElementKind::Passive | ElementKind::Declared => {
                     if !me.features.bulk_memory {
-                        return me.create_error("reference types must be enabled");
+                        return me.create_error(
+                            ErrorCode::FeatureNotEnabled,
+                            "reference types must be enabled",
+                        );
                     }
                 }
             }
             let mut items = e.items.get_items_reader()?;
             if items.get_count() > MAX_WASM_TABLE_ENTRIES as u32 {
-                return me.create_error("num_elements is out of bounds");
+                return me.create_error(ErrorCode::LimitExceeded, "num_elements is out of bounds");
             }
             for _ in 0..items.get_count() {
                 me.offset = items.original_position();
@@ -1410,14 +1697,17 @@ impl Validator {
                     ElementItem::Null(ty) => {
                         if ty != e.ty {
                             return me.create_error(
+                                ErrorCode::TypeMismatch,
                                 "type mismatch: null type doesn't match element type",
                             );
                         }
                     }
                     ElementItem::Func(f) => {
                         if e.ty != Type::FuncRef {
-                            return me
-                                .create_error("type mismatch: segment does not have funcref type");
+                            return me.create_error(
+                                ErrorCode::TypeMismatch,
+                                "type mismatch: segment does not have funcref type",
+                            );
                         }
                         me.get_func_type(f)?;
                         me.cur.state.assert_mut().function_references.insert(f);
@@ -1436,7 +1726,10 @@ impl Validator {
         self.update_order(Order::DataCount)?;
         self.cur.state.assert_mut().data_count = Some(count);
         if count > MAX_WASM_DATA_SEGMENTS as u32 {
-            return self.create_error("data count section specifies too many data segments");
+            return self.create_error(
+                ErrorCode::CountMismatch,
+                "data count section specifies too many data segments",
+            );
         }
         Ok(())
     }
@@ -1448,12 +1741,20 @@ impl Validator {
         match self.cur.expected_code_bodies.take() {
             Some(n) if n == count => {}
             Some(_) => {
-                return self.create_error("function and code section have inconsistent lengths");
+                return self.create_error(
+                    ErrorCode::CountMismatch,
+                    "function and code section have inconsistent lengths",
+                );
             }
             // empty code sections are allowed even if the function section is
             // missing
             None if count == 0 => {}
-            None => return self.create_error("code section without function section"),
+            None => {
+                return self.create_error(
+                    ErrorCode::CountMismatch,
+                    "code section without function section",
+                )
+            }
         }
 
         // Prepare our module's view into the global `types` array. This enables
@@ -1469,22 +1770,23 @@ impl Validator {
 
     /// Validates [`Payload::CodeSectionEntry`](crate::Payload).
     ///
-    /// This function will prepare a [`FuncValidator`] which can be used to
-    /// validate the function. The function body provided will be parsed only
-    /// enough to create the function validation context. After this the
-    /// [`OperatorsReader`](crate::readers::OperatorsReader) returned can be used to read the
-    /// opcodes of the function as well as feed information into the validator.
+    /// This function will prepare a [`FuncToValidate`] which can be used to
+    /// validate the function. The function body isn't looked at, the returned
+    /// value is turned into a [`FuncValidator`] which is then fed the locals
+    /// and operators of the function body.
     ///
-    /// Note that the returned [`FuncValidator`] is "connected" to this
+    /// Note that the returned [`FuncToValidate`] is "connected" to this
     /// [`Validator`] in that it uses the internal context of this validator for
-    /// validating the function. The [`FuncValidator`] can be sent to
+    /// validating the function. The [`FuncToValidate`] can be sent to
     /// another thread, for example, to offload actual processing of functions
     /// elsewhere.
-    pub fn code_section_entry(&mut self) -> Result<FuncValidator<ValidatorResources>> {
+    pub fn code_section_entry(&mut self) -> Result<FuncToValidate<ValidatorResources>> {
         let ty = self.cur.state.code_type_indexes[self.cur.code_section_index];
         self.cur.code_section_index += 1;
         let resources = ValidatorResources(self.cur.state.arc().clone());
-        Ok(FuncValidator::new(ty, 0, resources, &self.features).unwrap())
+        let mut func = FuncToValidate::new(ty, resources, &self.features);
+        func.limits = self.limits;
+        Ok(func)
     }
 
     /// Validates [`Payload::DataSection`](crate::Payload).
@@ -1514,7 +1816,10 @@ impl Validator {
     /// Currently always returns an error.
     pub fn unknown_section(&mut self, id: u8, range: &Range) -> Result<()> {
         self.offset = range.start;
-        self.create_error(format!("invalid section code: {}", id))
+        self.create_error(
+            ErrorCode::UnknownSection,
+            format!("invalid section code: {}", id),
+        )
     }
 
     /// Validates [`Payload::End`](crate::Payload).
@@ -1522,14 +1827,20 @@ impl Validator {
         // Ensure that the data count section, if any, was correct.
         if let Some(data_count) = self.cur.state.data_count {
             if data_count != self.cur.data_found {
-                return self.create_error("data count section and passive data mismatch");
+                return self.create_error(
+                    ErrorCode::CountMismatch,
+                    "data count section and passive data mismatch",
+                );
             }
         }
         // Ensure that the function section, if nonzero, was paired with a code
         // section with the appropriate length.
         if let Some(n) = self.cur.expected_code_bodies.take() {
             if n > 0 {
-                return self.create_error("function and code sections have inconsistent lengths");
+                return self.create_error(
+                    ErrorCode::CountMismatch,
+                    "function and code sections have inconsistent lengths",
+                );
             }
         }
         // If we have a parent then we're going to exit this module's context
@@ -1550,31 +1861,37 @@ impl Validator {
 }
 
 impl WasmFeatures {
-    pub(crate) fn check_value_type(&self, ty: Type) -> Result<(), &'static str> {
+    pub(crate) fn check_value_type(&self, ty: Type) -> Result<(), (ErrorCode, &'static str)> {
         match ty {
             Type::I32 | Type::I64 | Type::F32 | Type::F64 => Ok(()),
             Type::FuncRef | Type::ExternRef => {
                 if self.reference_types {
                     Ok(())
                 } else {
-                    Err("reference types support is not enabled")
+                    Err((
+                        ErrorCode::FeatureNotEnabled,
+                        "reference types support is not enabled",
+                    ))
                 }
             }
             Type::ExnRef => {
                 if self.exceptions {
                     Ok(())
                 } else {
-                    Err("exceptions support is not enabled")
+                    Err((
+                        ErrorCode::FeatureNotEnabled,
+                        "exceptions support is not enabled",
+                    ))
                 }
             }
             Type::V128 => {
                 if self.simd {
                     Ok(())
                 } else {
-                    Err("SIMD support is not enabled")
+                    Err((ErrorCode::FeatureNotEnabled, "SIMD support is not enabled"))
                 }
             }
-            _ => Err("invalid value type"),
+            _ => Err((ErrorCode::InvalidEncoding, "invalid value type")),
         }
     }
 }
@@ -1642,8 +1959,8 @@ impl WasmModuleResources for ValidatorResources {
 }
 
 mod arc {
-    use std::ops::Deref;
-    use std::sync::Arc;
+    use alloc::sync::Arc;
+    use core::ops::Deref;
 
     pub struct MaybeOwned<T> {
         owned: bool,
@@ -1733,6 +2050,7 @@ impl NameSet {
                 let prev = self.set.insert(module.to_string(), ty);
                 return if prev.is_some() {
                     Err(BinaryReaderError::new(
+                        ErrorCode::DuplicateName,
                         format!("duplicate {} name `{}` already defined", desc, module),
                         offset,
                     ))
@@ -1759,6 +2077,7 @@ impl NameSet {
                 let prev = instance.exports.insert(name.to_string(), ty);
                 if prev.is_some() {
                     return Err(BinaryReaderError::new(
+                        ErrorCode::DuplicateName,
                         format!(
                             "duplicate {} name `{}::{}` already defined",
                             desc, module, name
@@ -1775,6 +2094,7 @@ impl NameSet {
             // error.
             Some(_) => {
                 return Err(BinaryReaderError::new(
+                    ErrorCode::DuplicateName,
                     format!("cannot define the {} `{}` twice", desc, module),
                     offset,
                 ))
@@ -1897,7 +2217,7 @@ impl<T> SnapshotList<T> {
     }
 }
 
-impl<T> std::ops::Index<usize> for SnapshotList<T> {
+impl<T> core::ops::Index<usize> for SnapshotList<T> {
     type Output = T;
 
     fn index(&self, index: usize) -> &T {
@@ -1905,7 +2225,7 @@ impl<T> std::ops::Index<usize> for SnapshotList<T> {
     }
 }
 
-impl<T> std::ops::IndexMut<usize> for SnapshotList<T> {
+impl<T> core::ops::IndexMut<usize> for SnapshotList<T> {
     fn index_mut(&mut self, index: usize) -> &mut T {
         self.get_mut(index).unwrap()
     }
@@ -1,26 +1,100 @@
-use crate::operators_validator::OperatorValidator;
-use crate::{BinaryReader, Result, Type};
-use crate::{FunctionBody, Operator, WasmFeatures, WasmModuleResources};
+use crate::operators_validator::{OperatorValidator, OperatorValidatorAllocations};
+use crate::{BinaryReader, BinaryReaderError, Result, Type};
+use crate::{ErrorCode, FunctionBody, Operator, WasmFeatures, WasmLimits, WasmModuleResources};
+use alloc::format;
 
-/// Validation context for a WebAssembly function.
+/// A function which has yet to be validated.
 ///
 /// This structure is created by
 /// [`Validator::code_section_entry`](crate::Validator::code_section_entry)
-/// and is created per-function in a WebAssembly module. This structure is
-/// suitable for sending to other threads while the original
-/// [`Validator`](crate::Validator) continues processing other functions.
+/// and is created per-function in a WebAssembly module. It's cheap to create
+/// and suitable for sending to other threads, such as the workers of a thread
+/// pool, while the original [`Validator`](crate::Validator) continues
+/// processing other functions. Once on the thread that will validate the
+/// function it's turned into a [`FuncValidator`] with
+/// [`FuncToValidate::into_validator`].
+pub struct FuncToValidate<T> {
+    /// The module resources the function may refer to.
+    pub resources: T,
+    /// The index of the function's type.
+    pub ty: u32,
+    /// The WebAssembly features enabled for this function.
+    pub features: WasmFeatures,
+    /// The implementation limits enforced for this function.
+    pub limits: WasmLimits,
+}
+
+impl<T: WasmModuleResources> FuncToValidate<T> {
+    /// Creates a new function to validate which will have the type `ty`.
+    ///
+    /// The `resources` indicate what the containing module has for the
+    /// function to use, and the `features` configure what WebAssembly
+    /// proposals are enabled for this function. The function is validated
+    /// with the default [`WasmLimits`].
+    pub fn new(ty: u32, resources: T, features: &WasmFeatures) -> FuncToValidate<T> {
+        FuncToValidate {
+            resources,
+            ty,
+            features: *features,
+            limits: WasmLimits::default(),
+        }
+    }
+
+    /// Converts this [`FuncToValidate`] into a [`FuncValidator`] using the
+    /// `allocs` provided.
+    ///
+    /// The allocations can be recycled from a previous validator with
+    /// [`FuncValidator::into_allocations`], which avoids reallocating the
+    /// validator's internal stacks for every function. Threads validating many
+    /// functions can keep one [`FuncValidatorAllocations`] each.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ty` isn't a valid function type index in `resources`, which
+    /// can't happen for values created by a [`Validator`](crate::Validator).
+    pub fn into_validator(self, allocs: FuncValidatorAllocations) -> FuncValidator<T> {
+        let validator = OperatorValidator::new(
+            self.ty,
+            0,
+            &self.features,
+            &self.limits,
+            &self.resources,
+            allocs.0,
+        )
+        .expect("invalid function type index");
+        FuncValidator {
+            validator,
+            resources: self.resources,
+        }
+    }
+}
+
+/// Validation context for a WebAssembly function.
+///
+/// This structure is created from a [`FuncToValidate`] and is used to
+/// validate the body of one function. When validation is done its heap
+/// allocations can be reclaimed with [`FuncValidator::into_allocations`] and
+/// reused for the next function.
 pub struct FuncValidator<T> {
     validator: OperatorValidator,
     resources: T,
 }
 
+/// Heap allocations of a [`FuncValidator`] which can be reused across the
+/// validation of many functions.
+///
+/// Created with `Default` or with [`FuncValidator::into_allocations`].
+#[derive(Default)]
+pub struct FuncValidatorAllocations(OperatorValidatorAllocations);
+
 impl<T: WasmModuleResources> FuncValidator<T> {
     /// Creates a new `FuncValidator`.
     ///
     /// The returned `FuncValidator` can be used to validate a function with
     /// the type `ty` specified. The `resources` indicate what the containing
     /// module has for the function to use, and the `features` configure what
-    /// WebAssembly proposals are enabled for this function.
+    /// WebAssembly proposals are enabled for this function. The function is
+    /// validated with the default [`WasmLimits`].
     ///
     /// The returned validator can be used to then parse a [`FunctionBody`], for
     /// example, to read locals and validate operators.
@@ -30,8 +104,16 @@ impl<T: WasmModuleResources> FuncValidator<T> {
         resources: T,
         features: &WasmFeatures,
     ) -> Result<FuncValidator<T>> {
+        let allocs = OperatorValidatorAllocations::default();
         Ok(FuncValidator {
-            validator: OperatorValidator::new(ty, offset, features, &resources)?,
+            validator: OperatorValidator::new(
+                ty,
+                offset,
+                features,
+                &WasmLimits::default(),
+                &resources,
+                allocs,
+            )?,
             resources,
         })
     }
@@ -63,8 +145,34 @@ impl<T: WasmModuleResources> FuncValidator<T> {
     /// from a `FunctionBody`.
     ///
     /// This function will automatically advance the `BinaryReader` forward,
-    /// leaving reading operators up to the caller afterwards.
+    /// leaving reading operators up to the caller afterwards. The bytes left
+    /// in `reader` are taken to be the function body, and are checked against
+    /// [`WasmLimits::max_function_size`].
     pub fn read_locals(&mut self, reader: &mut BinaryReader<'_>) -> Result<()> {
+        let max = self.validator.limits.max_function_size;
+        if reader.bytes_remaining() > max {
+            return Err(BinaryReaderError::new(
+                ErrorCode::LimitExceeded,
+                format!(
+                    "function body is {} bytes, but the limit is {}",
+                    reader.bytes_remaining(),
+                    max
+                ),
+                reader.original_position(),
+            ));
+        }
+        // Parameters count as locals too, so there may be too many even if
+        // the function doesn't declare any locals.
+        if self.validator.num_locals > self.validator.limits.max_function_locals {
+            return Err(BinaryReaderError::new(
+                ErrorCode::LimitExceeded,
+                format!(
+                    "locals exceed maximum: function has {} parameters, but the limit is {} locals",
+                    self.validator.num_locals, self.validator.limits.max_function_locals
+                ),
+                reader.original_position(),
+            ));
+        }
         for _ in 0..reader.read_var_u32()? {
             let offset = reader.original_position();
             let cnt = reader.read_var_u32()?;
@@ -112,6 +220,12 @@ impl<T: WasmModuleResources> FuncValidator<T> {
     pub fn resources(&self) -> &T {
         &self.resources
     }
+
+    /// Consumes this validator, returning its heap allocations so they can be
+    /// reused for validating another function.
+    pub fn into_allocations(self) -> FuncValidatorAllocations {
+        FuncValidatorAllocations(self.validator.into_allocations())
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,160 @@
+use crate::{Chunk, FuncValidatorAllocations, Parser, Payload, Result};
+use crate::{ValidPayload, Validator, WasmFeatures, WasmLimits};
+use alloc::vec::Vec;
+use core::mem;
+
+/// A validator which is fed a WebAssembly binary in pieces as they arrive,
+/// such as from a network download, and which can be suspended whenever it
+/// runs out of bytes.
+///
+/// This combines a [`Parser`] and a [`Validator`], buffering only the part of
+/// the binary which hasn't been validated yet. Sections are buffered until
+/// they're complete, except for the code section which is validated one
+/// function at a time, so the buffer never needs to be much larger than the
+/// largest section or function body. Functions are validated as soon as
+/// they arrive, on the calling thread.
+///
+/// # Examples
+///
+/// ```
+/// use wasmparser::{StreamingValidator, ValidationStatus};
+///
+/// let wasm = wat::parse_str("(module (func (result i32) i32.const 1))")?;
+/// let mut validator = StreamingValidator::new();
+/// for chunk in wasm.chunks(3) {
+///     // Each call validates as much as it can, and the validator is
+///     // resumed when the next chunk arrives.
+///     let status = validator.feed(chunk, false)?;
+///     assert!(matches!(status, ValidationStatus::NeedMoreData(_)));
+/// }
+/// assert_eq!(validator.feed(&[], true)?, ValidationStatus::Done);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct StreamingValidator {
+    validator: Validator,
+    parser: Parser,
+    // The parsers of the modules which enclose the one being parsed, when
+    // validating nested modules of the module linking proposal.
+    parents: Vec<Parser>,
+    buf: Vec<u8>,
+    // The number of bytes received so far.
+    received: usize,
+    allocs: FuncValidatorAllocations,
+    done: bool,
+}
+
+/// The progress of a [`StreamingValidator`] after it was fed some bytes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ValidationStatus {
+    /// Everything received so far is valid, but more bytes are needed to
+    /// continue. The hint is how many more bytes are needed at least.
+    NeedMoreData(u64),
+    /// The whole module was received and is valid.
+    Done,
+}
+
+impl StreamingValidator {
+    /// Creates a new validator which is ready to receive the first bytes of a
+    /// WebAssembly binary.
+    pub fn new() -> StreamingValidator {
+        StreamingValidator {
+            validator: Validator::new(),
+            parser: Parser::new(0),
+            parents: Vec::new(),
+            buf: Vec::new(),
+            received: 0,
+            allocs: FuncValidatorAllocations::default(),
+            done: false,
+        }
+    }
+
+    /// Configures the enabled WebAssembly features, which must happen before
+    /// the first bytes are fed to the validator.
+    pub fn wasm_features(&mut self, features: WasmFeatures) -> &mut StreamingValidator {
+        self.validator.wasm_features(features);
+        self
+    }
+
+    /// Configures the implementation limits, which must happen before the
+    /// first bytes are fed to the validator.
+    pub fn wasm_limits(&mut self, limits: WasmLimits) -> &mut StreamingValidator {
+        self.validator.wasm_limits(limits);
+        self
+    }
+
+    /// Returns the underlying [`Validator`], for example to inspect the types
+    /// of what's been validated so far.
+    pub fn validator(&self) -> &Validator {
+        &self.validator
+    }
+
+    /// Validates `data`, the next bytes of the binary, along with any bytes
+    /// left over from previous calls which weren't enough to validate on their
+    /// own.
+    ///
+    /// `eof` indicates that `data` is the end of the binary. Until it's
+    /// `true` this only ever returns [`ValidationStatus::NeedMoreData`] or an
+    /// error, and once it's `true` this returns [`ValidationStatus::Done`] or
+    /// an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first parse or validation error in the binary. Errors are
+    /// reported as soon as the bytes containing them are received, and the
+    /// validator shouldn't be used again afterwards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called again after [`ValidationStatus::Done`] was returned.
+    pub fn feed(&mut self, data: &[u8], eof: bool) -> Result<ValidationStatus> {
+        assert!(!self.done, "fed bytes to a validator which is done");
+        self.received = self.received.saturating_add(data.len());
+        self.validator.check_module_size(self.received)?;
+        self.buf.extend_from_slice(data);
+        let mut consumed = 0;
+        let status = self.validate_buffered(&mut consumed, eof);
+        self.buf.drain(..consumed);
+        status
+    }
+
+    fn validate_buffered(&mut self, consumed: &mut usize, eof: bool) -> Result<ValidationStatus> {
+        loop {
+            let payload = match self.parser.parse(&self.buf[*consumed..], eof)? {
+                Chunk::NeedMoreData(hint) => return Ok(ValidationStatus::NeedMoreData(hint)),
+                Chunk::Parsed {
+                    consumed: n,
+                    payload,
+                } => {
+                    *consumed += n;
+                    payload
+                }
+            };
+            match self.validator.payload(&payload)? {
+                ValidPayload::Ok => {}
+                ValidPayload::Submodule(parser) => {
+                    self.parents.push(mem::replace(&mut self.parser, parser));
+                }
+                ValidPayload::Func(func, body) => {
+                    let mut validator = func.into_validator(mem::take(&mut self.allocs));
+                    validator.validate(&body)?;
+                    self.allocs = validator.into_allocations();
+                }
+            }
+            if let Payload::End = payload {
+                match self.parents.pop() {
+                    Some(parent) => self.parser = parent,
+                    None => {
+                        self.done = true;
+                        return Ok(ValidationStatus::Done);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Default for StreamingValidator {
+    fn default() -> StreamingValidator {
+        StreamingValidator::new()
+    }
+}
@@ -0,0 +1,65 @@
+use wasmparser::{BinaryReader, BrTable, Operator, Validator};
+
+fn read_br_table(bytes: &[u8]) -> wasmparser::Result<BrTable<'_>> {
+    match BinaryReader::new(bytes).read_operator()? {
+        Operator::BrTable { table } => Ok(table),
+        op => panic!("not a br_table: {:?}", op),
+    }
+}
+
+#[test]
+fn targets() {
+    // Single byte labels.
+    let table = read_br_table(&[0x0e, 0x03, 0x00, 0x01, 0x02, 0x03]).unwrap();
+    let targets = table.targets();
+    assert_eq!(targets.len(), 4);
+    let targets = targets.collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(targets, [(0, false), (1, false), (2, false), (3, true)]);
+
+    // Multi-byte labels, followed by another operator.
+    let bytes = [0x0e, 0x02, 0x80, 0x01, 0x05, 0xff, 0xff, 0x03, 0x0b];
+    let mut reader = BinaryReader::new(&bytes);
+    let table = match reader.read_operator().unwrap() {
+        Operator::BrTable { table } => table,
+        op => panic!("not a br_table: {:?}", op),
+    };
+    let targets = table.targets().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(targets, [(128, false), (5, false), (0xffff, true)]);
+    assert!(matches!(reader.read_operator().unwrap(), Operator::End));
+}
+
+#[test]
+fn truncated() {
+    assert!(read_br_table(&[0x0e, 0x03, 0x00, 0x01]).is_err());
+    assert!(read_br_table(&[0x0e, 0x01, 0x00, 0x80]).is_err());
+}
+
+#[test]
+fn large_table_validates() {
+    let mut wat = String::from("(module (func (param i32) (block (block local.get 0 br_table");
+    for i in 0..100_000 {
+        wat.push_str(if i % 3 == 0 { " 0" } else { " 1" });
+    }
+    wat.push_str(" 1))))");
+    let wasm = wat::parse_str(&wat).unwrap();
+    Validator::new().validate_all(&wasm).unwrap();
+
+    // Labels with different types are still caught after long runs of the
+    // same label.
+    let mut wat = String::from(
+        "(module (func (param i32) (result i32) (block (result i32) (block \
+         i32.const 0 local.get 0 br_table",
+    );
+    for _ in 0..100_000 {
+        wat.push_str(" 0");
+    }
+    wat.push_str(" 1) i32.const 0)))");
+    let wasm = wat::parse_str(&wat).unwrap();
+    let err = Validator::new().validate_all(&wasm).unwrap_err();
+    assert!(
+        err.message()
+            .contains("br_table target labels have different types"),
+        "{}",
+        err
+    );
+}
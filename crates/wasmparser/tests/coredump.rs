@@ -0,0 +1,106 @@
+use wasmparser::{
+    CoreDumpInstance, CoreDumpInstancesSection, CoreDumpModulesSection, CoreDumpSection,
+    CoreDumpStackFrame, CoreDumpStackSection, CoreDumpValue, ErrorCode, Parser, Payload,
+};
+
+#[test]
+fn coredump_sections() {
+    let core = CoreDumpSection::new(b"\0\x08app.wasm", 100).unwrap();
+    assert_eq!(core.executable_name, "app.wasm");
+
+    let modules = CoreDumpModulesSection::new(b"\x02\0\x01a\0\x01b", 100).unwrap();
+    assert_eq!(&modules.modules[..], ["a", "b"]);
+
+    #[rustfmt::skip]
+    let instances = CoreDumpInstancesSection::new(&[
+        1,
+        // Module 1, with memory 0 and globals 2 and 3.
+        0, 1, 1, 0, 2, 2, 3,
+    ], 100).unwrap();
+    assert_eq!(
+        &instances.instances[..],
+        [CoreDumpInstance {
+            module_index: 1,
+            memories: Box::new([0]),
+            globals: Box::new([2, 3]),
+        }]
+    );
+
+    #[rustfmt::skip]
+    let stack = CoreDumpStackSection::new(&[
+        0, 4, b'm', b'a', b'i', b'n',
+        2,
+        // Instance 0, function 3 at offset 17, with two locals and an empty
+        // stack.
+        0, 0, 3, 17, 2, 0x7f, 0x7f, 0x01, 0,
+        // Instance 0, function 1 at offset 2, with an `f32` on the stack.
+        0, 0, 1, 2, 0, 1, 0x7d, 0, 0, 0x80, 0x3f,
+    ], 100).unwrap();
+    assert_eq!(stack.name, "main");
+    assert_eq!(
+        stack.frames[0],
+        CoreDumpStackFrame {
+            instance_index: 0,
+            func_index: 3,
+            code_offset: 17,
+            locals: Box::new([CoreDumpValue::I32(-1), CoreDumpValue::Missing]),
+            stack: Box::new([]),
+        }
+    );
+    let frame = &stack.frames[1];
+    assert_eq!(
+        (frame.instance_index, frame.func_index, frame.code_offset),
+        (0, 1, 2)
+    );
+    assert!(frame.locals.is_empty());
+    match &frame.stack[..] {
+        [CoreDumpValue::F32(x)] => assert_eq!(x.bits(), 1.0f32.to_bits()),
+        other => panic!("unexpected stack {:?}", other),
+    }
+}
+
+#[test]
+fn coredump_errors() {
+    let err = CoreDumpSection::new(b"\x01\x08app.wasm", 100).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::InvalidEncoding);
+    assert_eq!(
+        err.message(),
+        "invalid leading byte in coredump process-info"
+    );
+    assert_eq!(err.offset(), 100);
+
+    let err = CoreDumpModulesSection::new(b"\x01\0\x01a\0", 100).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::SizeMismatch);
+    assert_eq!(err.offset(), 104);
+
+    let err = CoreDumpStackSection::new(&[0, 0, 1, 0, 0, 0, 0, 1, 0x7b, 0], 100).unwrap_err();
+    assert_eq!(err.message(), "invalid coredump value type");
+    assert_eq!(err.offset(), 108);
+}
+
+#[test]
+fn coredump_module() {
+    let wasm = wat::parse_str(
+        r#"
+            (module
+              (@custom "core" "\00\08app.wasm")
+              (@custom "corestack" "\00\04main\01\00\00\05\00\00\00")
+              (memory 1)
+              (data (i32.const 1024) "crashed here"))
+        "#,
+    )
+    .unwrap();
+    let mut stacks = Vec::new();
+    for payload in Parser::new(0).parse_all(&wasm) {
+        if let Payload::CustomSection {
+            name: "corestack",
+            data,
+            data_offset,
+        } = payload.unwrap()
+        {
+            stacks.push(CoreDumpStackSection::new(data, data_offset).unwrap());
+        }
+    }
+    assert_eq!(stacks.len(), 1);
+    assert_eq!(stacks[0].frames[0].func_index, 5);
+}
@@ -0,0 +1,147 @@
+use wasmparser::{ErrorCode, RelatedOffset, Validator, WasmFeatures};
+
+fn validate(wat: &str, features: WasmFeatures) -> String {
+    let wasm = wat::parse_str(wat).unwrap();
+    let mut validator = Validator::new();
+    validator.wasm_features(features);
+    validator.validate_all(&wasm).unwrap_err().to_string()
+}
+
+#[test]
+fn start_function_type() {
+    let err = validate(
+        "(module (func $f (param i32) (result i64) i64.const 0) (start $f))",
+        WasmFeatures::default(),
+    );
+    assert!(
+        err.starts_with(
+            "invalid start function type: function 0 has type [i32] -> [i64], \
+             but the start function must take no parameters and return no results"
+        ),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn multi_value_arity() {
+    let err = validate(
+        "(module (func (result i32 i64) i32.const 0))",
+        WasmFeatures::default(),
+    );
+    assert!(
+        err.starts_with(
+            "type mismatch: function returns 2 values [i32 i64] but 1 value is on the stack"
+        ),
+        "{}",
+        err
+    );
+
+    let err = validate(
+        "(module (func (result i32 i64) (block (result i32 i64) i32.const 0 i32.const 0 i32.const 0)))",
+        WasmFeatures::default(),
+    );
+    assert!(
+        err.starts_with(
+            "type mismatch: block returns 2 values [i32 i64] but 3 values are on the stack"
+        ),
+        "{}",
+        err
+    );
+
+    // `return` may leave extra values behind, but needs enough of them.
+    let err = validate(
+        "(module (func (result i32 i64) i64.const 0 return))",
+        WasmFeatures::default(),
+    );
+    assert!(
+        err.starts_with(
+            "type mismatch: function returns 2 values [i32 i64] but 1 value is on the stack"
+        ),
+        "{}",
+        err
+    );
+    let wasm = wat::parse_str(
+        "(module (func (result i32 i64) f32.const 0 i32.const 0 i64.const 0 return))",
+    )
+    .unwrap();
+    Validator::new().validate_all(&wasm).unwrap();
+}
+
+#[test]
+fn multi_value_disabled() {
+    let err = validate(
+        "(module (func (result i32 i64) unreachable))",
+        WasmFeatures {
+            multi_value: false,
+            ..WasmFeatures::default()
+        },
+    );
+    assert!(
+        err.starts_with(
+            "invalid result arity: func type returns multiple values, \
+             which requires the multi-value feature to be enabled"
+        ),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn error_codes() {
+    let code = |wasm: &[u8]| Validator::new().validate_all(wasm).unwrap_err().code();
+    assert_eq!(code(b"\0asm"), ErrorCode::UnexpectedEof);
+    assert_eq!(code(b"\0asx\x01\0\0\0"), ErrorCode::InvalidHeader);
+    assert_eq!(code(b"\0asm\x01\0\0\0\x20\0"), ErrorCode::UnknownSection);
+
+    let code = |wat: &str| {
+        let wasm = wat::parse_str(wat).unwrap();
+        Validator::new().validate_all(&wasm).unwrap_err().code()
+    };
+    assert_eq!(
+        code("(module (func (result i32) i64.const 0))"),
+        ErrorCode::TypeMismatch
+    );
+    assert_eq!(code("(module (func call 1))"), ErrorCode::UnknownFunction);
+    assert_eq!(code("(module (func br 1))"), ErrorCode::UnknownLabel);
+    assert_eq!(
+        code("(module (memory 1) (func i32.const 0 i32.load align=8 drop))"),
+        ErrorCode::InvalidAlignment
+    );
+    assert_eq!(
+        code("(module (global i32 (i32.const 0)) (func i32.const 0 global.set 0))"),
+        ErrorCode::ImmutableGlobal
+    );
+    assert_eq!(
+        code("(module (func (param i32)) (start 0))"),
+        ErrorCode::InvalidStartFunction
+    );
+    assert_eq!(
+        code("(module (memory 1) (memory 1))"),
+        ErrorCode::FeatureNotEnabled
+    );
+
+    assert_eq!(ErrorCode::TypeMismatch.to_string(), "E0301");
+    assert_eq!(ErrorCode::from_number(301), Some(ErrorCode::TypeMismatch));
+    assert_eq!(ErrorCode::from_number(300), None);
+}
+
+#[test]
+fn related_offsets() {
+    let wasm = wat::parse_str(
+        r#"(module (func (export "f")) (func (export "g")) (func (export "f")))"#,
+    )
+    .unwrap();
+    let err = Validator::new().validate_all(&wasm).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::DuplicateName);
+    let first = wasm.windows(2).position(|w| w == b"\x01f").unwrap();
+    let second = wasm.windows(2).rposition(|w| w == b"\x01f").unwrap();
+    assert_eq!(err.offset(), second);
+    assert_eq!(
+        err.related(),
+        [RelatedOffset {
+            offset: first,
+            message: "first exported here".to_string(),
+        }]
+    );
+}
@@ -0,0 +1,100 @@
+use wasmparser::{
+    Dylink, DylinkSectionReader, ExportInfo, GotKind, ImportInfo, MemInfo, Parser, Payload,
+};
+
+#[rustfmt::skip]
+const DYLINK: &[u8] = &[
+    // Memory info: 16 bytes aligned to 2^2 and one table slot.
+    1, 4, 16, 2, 1, 0,
+    // Needed libraries: `a.so`.
+    2, 6, 1, 4, b'a', b'.', b's', b'o',
+    // Export info: `e` is weak.
+    3, 4, 1, 1, b'e', 1,
+    // Import info: `env::i` is weak.
+    4, 8, 1, 3, b'e', b'n', b'v', 1, b'i', 1,
+    // An unknown subsection.
+    42, 1, 7,
+];
+
+#[test]
+fn dylink_section() {
+    let reader = DylinkSectionReader::new(DYLINK, 100).unwrap();
+    let subsections = reader.into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+    match &subsections[..] {
+        [Dylink::MemInfo(info), Dylink::Needed(needed), Dylink::ExportInfo(exports), Dylink::ImportInfo(imports), Dylink::Unknown { ty: 42, data: &[7] }] =>
+        {
+            assert_eq!(
+                *info,
+                MemInfo {
+                    memory_size: 16,
+                    memory_alignment: 2,
+                    table_size: 1,
+                    table_alignment: 0,
+                }
+            );
+            assert_eq!(&needed[..], ["a.so"]);
+            assert_eq!(
+                &exports[..],
+                [ExportInfo {
+                    name: "e",
+                    flags: 1
+                }]
+            );
+            assert_eq!(
+                &imports[..],
+                [ImportInfo {
+                    module: "env",
+                    field: "i",
+                    flags: 1,
+                }]
+            );
+        }
+        other => panic!("unexpected subsections {:?}", other),
+    }
+}
+
+#[test]
+fn dylink_trailing_data() {
+    let mut reader = DylinkSectionReader::new(&[1, 5, 0, 0, 0, 0, 0], 100).unwrap();
+    let err = reader.read().unwrap_err();
+    assert_eq!(
+        err.message(),
+        "unexpected data at the end of dylink subsection"
+    );
+    assert_eq!(err.offset(), 106);
+}
+
+#[test]
+fn got_entries() {
+    let wasm = wat::parse_str(
+        r#"
+            (module
+              (import "GOT.mem" "data" (global (mut i32)))
+              (import "GOT.func" "func" (global (mut i32)))
+              (import "GOT.mem" "not_a_global" (func))
+              (import "env" "other" (global (mut i32))))
+        "#,
+    )
+    .unwrap();
+    let mut entries = Vec::new();
+    for payload in Parser::new(0).parse_all(&wasm) {
+        if let Payload::ImportSection(reader) = payload.unwrap() {
+            for import in reader {
+                entries.push(import.unwrap().got_entry());
+            }
+        }
+    }
+    assert_eq!(
+        entries,
+        [
+            Some((GotKind::Mem, "data")),
+            Some((GotKind::Func, "func")),
+            None,
+            None,
+        ]
+    );
+    assert_eq!(
+        GotKind::from_module(GotKind::Func.module()),
+        Some(GotKind::Func)
+    );
+}
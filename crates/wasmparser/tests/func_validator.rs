@@ -0,0 +1,87 @@
+use wasmparser::{
+    FuncToValidate, FuncValidatorAllocations, FunctionBody, Parser, ValidPayload, Validator,
+    ValidatorResources,
+};
+
+// The invalid functions fail with values, locals and blocks left on the
+// validator's stacks, which mustn't leak into the functions after them.
+const MODULE: &str = r#"
+    (module
+      (memory 1)
+      (func (param i32 i64) (result i32)
+        (local f32 f64)
+        block (result i32)
+          local.get 0
+          i32.const 1
+          i32.add
+        end)
+      (func (param i32) (result i32)
+        (local i64 i64 i64)
+        block
+          loop
+            local.get 0
+            local.get 1
+            i32.add
+            br 1
+          end
+        end
+        i32.const 0)
+      (func (result i32)
+        i32.const 1)
+      (func (param f64) (result f64)
+        block (result f64)
+          block
+            local.get 0
+            i64.const 2
+            f64.mul
+          end
+        end)
+      (func (result i64)
+        i64.const 3)
+      (func (param i32)
+        local.get 0
+        i32.load
+        drop))
+"#;
+
+type Func<'a> = (FuncToValidate<ValidatorResources>, FunctionBody<'a>);
+
+fn functions(wasm: &[u8]) -> Vec<Func<'_>> {
+    let mut validator = Validator::new();
+    let mut functions = Vec::new();
+    for payload in Parser::new(0).parse_all(wasm) {
+        if let ValidPayload::Func(func, body) = validator.payload(&payload.unwrap()).unwrap() {
+            functions.push((func, body));
+        }
+    }
+    functions
+}
+
+/// The outcome of validating a function, which is compared between fresh and
+/// reused allocations.
+fn outcome(result: wasmparser::Result<()>) -> Option<(String, usize)> {
+    result.err().map(|e| (e.message().to_string(), e.offset()))
+}
+
+#[test]
+fn reused_allocations_match_fresh_validators() {
+    let wasm = wat::parse_str(MODULE).unwrap();
+
+    let mut fresh = Vec::new();
+    for (func, body) in functions(&wasm) {
+        let mut validator = func.into_validator(FuncValidatorAllocations::default());
+        fresh.push(outcome(validator.validate(&body)));
+    }
+
+    let mut reused = Vec::new();
+    let mut allocs = FuncValidatorAllocations::default();
+    for (func, body) in functions(&wasm) {
+        let mut validator = func.into_validator(allocs);
+        reused.push(outcome(validator.validate(&body)));
+        allocs = validator.into_allocations();
+    }
+
+    assert_eq!(reused, fresh);
+    let valid = fresh.iter().map(Option::is_none).collect::<Vec<_>>();
+    assert_eq!(valid, [true, false, true, false, true, true]);
+}
@@ -0,0 +1,107 @@
+use wasmparser::{
+    BinaryReaderError, ErrorCode, StreamingValidator, ValidationStatus, Validator, WasmLimits,
+};
+
+fn validate(wat: &str, limits: WasmLimits) -> Result<(), BinaryReaderError> {
+    let wasm = wat::parse_str(wat).unwrap();
+    Validator::new().wasm_limits(limits).validate_all(&wasm)
+}
+
+fn assert_limit_exceeded(wat: &str, limits: WasmLimits, message: &str) {
+    let err = validate(wat, limits).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::LimitExceeded);
+    assert_eq!(err.message(), message);
+    // The same module is valid with the default limits.
+    validate(wat, WasmLimits::default()).unwrap();
+}
+
+#[test]
+fn function_locals() {
+    let limits = WasmLimits {
+        max_function_locals: 3,
+        ..WasmLimits::default()
+    };
+    validate("(module (func (param i32) (local i32 i64)))", limits).unwrap();
+    assert_limit_exceeded(
+        "(module (func (param i32) (local i32 i64 f32)))",
+        limits,
+        "locals exceed maximum: function has 4 locals, but the limit is 3",
+    );
+    assert_limit_exceeded(
+        "(module (func (param i32 i32 i32 i32)))",
+        limits,
+        "locals exceed maximum: function has 4 parameters, but the limit is 3 locals",
+    );
+}
+
+#[test]
+fn function_type() {
+    let limits = WasmLimits {
+        max_function_params: 2,
+        max_function_results: 1,
+        ..WasmLimits::default()
+    };
+    validate("(module (type (func (param i32 i32) (result i32))))", limits).unwrap();
+    assert_limit_exceeded(
+        "(module (type (func (param i32 i32 i32))))",
+        limits,
+        "function type has 3 parameters, but the limit is 2",
+    );
+    assert_limit_exceeded(
+        "(module (type (func (result i32 i32))))",
+        limits,
+        "function type has 2 results, but the limit is 1",
+    );
+}
+
+#[test]
+fn function_size() {
+    let limits = WasmLimits {
+        max_function_size: 5,
+        ..WasmLimits::default()
+    };
+    // One byte for the locals, then `i32.const 0; drop; end`.
+    validate("(module (func i32.const 0 drop))", limits).unwrap();
+    assert_limit_exceeded(
+        "(module (func i32.const 0 drop nop))",
+        limits,
+        "function body is 6 bytes, but the limit is 5",
+    );
+}
+
+#[test]
+fn nesting_depth() {
+    let limits = WasmLimits {
+        max_nesting_depth: 3,
+        ..WasmLimits::default()
+    };
+    validate("(module (func (block (loop))))", limits).unwrap();
+    assert_limit_exceeded(
+        "(module (func (block (loop (if (i32.const 0) (then))))))",
+        limits,
+        "control frames are nested more than 3 deep",
+    );
+}
+
+#[test]
+fn module_size() {
+    let wasm = wat::parse_str("(module (memory 1))").unwrap();
+    let limits = WasmLimits {
+        max_module_size: wasm.len() - 1,
+        ..WasmLimits::default()
+    };
+    let err = Validator::new()
+        .wasm_limits(limits)
+        .validate_all(&wasm)
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::LimitExceeded);
+    assert_eq!(err.offset(), wasm.len() - 1);
+
+    let mut validator = StreamingValidator::new();
+    validator.wasm_limits(limits);
+    let (first, last) = wasm.split_at(wasm.len() - 1);
+    let status = validator.feed(first, false).unwrap();
+    assert!(matches!(status, ValidationStatus::NeedMoreData(_)));
+    let err = validator.feed(last, true).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::LimitExceeded);
+}
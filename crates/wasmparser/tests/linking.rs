@@ -0,0 +1,175 @@
+use wasmparser::{
+    Comdat, ComdatSymbol, ComdatSymbolKind, DefinedDataSymbol, InitFunc, Linking,
+    LinkingSectionReader, Reloc, RelocSectionReader, RelocType, SegmentInfo, SymbolInfo,
+};
+
+#[rustfmt::skip]
+const LINKING: &[u8] = &[
+    // Version.
+    2,
+    // Segment info: one segment `.data` with an alignment of 2^2.
+    5, 9, 1, 5, b'.', b'd', b'a', b't', b'a', 2, 0,
+    // Init functions: symbol 0 with priority 65535.
+    6, 5, 1, 0xff, 0xff, 0x03, 0,
+    // Comdat info: `c` holding function 1.
+    7, 7, 1, 1, b'c', 0, 1, 1, 1,
+    // Symbol table.
+    8, 19, 4,
+    // Defined function 0 named `f`.
+    0, 0, 0, 1, b'f',
+    // Imported global 0 without an explicit name.
+    2, 0x10, 0,
+    // Defined data `d` at offset 4 of segment 0, 8 bytes long.
+    1, 0, 1, b'd', 0, 4, 8,
+    // Section 3.
+    3, 0x02, 3,
+    // An unknown subsection.
+    42, 2, 1, 2,
+];
+
+#[test]
+fn linking_section() {
+    let reader = LinkingSectionReader::new(LINKING, 100).unwrap();
+    assert_eq!(reader.version(), 2);
+    let subsections = reader.into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+    match &subsections[..] {
+        [Linking::SegmentInfo(segments), Linking::InitFuncs(funcs), Linking::ComdatInfo(comdats), Linking::SymbolTable(symbols), Linking::Unknown {
+            ty: 42,
+            data: &[1, 2],
+        }] => {
+            assert_eq!(
+                segments[..],
+                [SegmentInfo {
+                    name: ".data",
+                    alignment: 2,
+                    flags: 0
+                }]
+            );
+            assert_eq!(
+                funcs[..],
+                [InitFunc {
+                    priority: 65535,
+                    symbol_index: 0
+                }]
+            );
+            assert_eq!(
+                comdats[..],
+                [Comdat {
+                    name: "c",
+                    flags: 0,
+                    symbols: Box::new([ComdatSymbol {
+                        kind: ComdatSymbolKind::Function,
+                        index: 1
+                    }])
+                }]
+            );
+            assert_eq!(
+                symbols[..],
+                [
+                    SymbolInfo::Func {
+                        flags: 0,
+                        index: 0,
+                        name: Some("f")
+                    },
+                    SymbolInfo::Global {
+                        flags: SymbolInfo::UNDEFINED,
+                        index: 0,
+                        name: None
+                    },
+                    SymbolInfo::Data {
+                        flags: 0,
+                        name: "d",
+                        symbol: Some(DefinedDataSymbol {
+                            index: 0,
+                            offset: 4,
+                            size: 8
+                        })
+                    },
+                    SymbolInfo::Section {
+                        flags: SymbolInfo::BINDING_LOCAL,
+                        section: 3
+                    },
+                ]
+            );
+        }
+        other => panic!("unexpected subsections {:?}", other),
+    }
+}
+
+#[test]
+fn linking_section_errors() {
+    let err = LinkingSectionReader::new(&[1], 100).err().unwrap();
+    assert_eq!(err.message(), "unsupported linking section version: 1");
+
+    // A subsection with data after its contents.
+    let mut reader = LinkingSectionReader::new(&[2, 6, 2, 0, 0], 100).unwrap();
+    let err = reader.read().unwrap_err();
+    assert_eq!(
+        err.message(),
+        "unexpected data at the end of linking subsection"
+    );
+    assert_eq!(err.offset(), 104);
+}
+
+#[test]
+fn reloc_section() {
+    #[rustfmt::skip]
+    let data = [
+        // Section 5, two relocations.
+        5, 2,
+        // A function index at offset 4 referring to symbol 1.
+        0, 4, 1,
+        // A memory address at offset 0x80 referring to symbol 2, minus 8.
+        5, 0x80, 0x01, 2, 0x78,
+    ];
+    let reader = RelocSectionReader::new(&data, 0).unwrap();
+    assert_eq!(reader.get_section_index(), 5);
+    let relocs = reader.into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(
+        relocs,
+        [
+            Reloc {
+                ty: RelocType::FunctionIndexLEB,
+                offset: 4,
+                index: 1,
+                addend: None
+            },
+            Reloc {
+                ty: RelocType::MemoryAddrI32,
+                offset: 128,
+                index: 2,
+                addend: Some(-8)
+            },
+        ]
+    );
+}
+
+#[test]
+fn reloc_types() {
+    #[rustfmt::skip]
+    let data = [
+        // Section 3, five relocations of the types added most recently.
+        3, 5,
+        22, 0, 0, 1,
+        23, 1, 0, 2,
+        24, 2, 0,
+        25, 3, 0, 3,
+        26, 4, 0,
+    ];
+    let reader = RelocSectionReader::new(&data, 0).unwrap();
+    let relocs = reader
+        .into_iter()
+        .map(|reloc| reloc.map(|r| (r.ty, r.addend)))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(
+        relocs,
+        [
+            (RelocType::FunctionOffsetI64, Some(1)),
+            (RelocType::MemoryAddrLocRelI32, Some(2)),
+            (RelocType::TableIndexRelSLEB64, None),
+            (RelocType::MemoryAddrTlsSLEB64, Some(3)),
+            (RelocType::FunctionIndexI32, None),
+        ]
+    );
+}
@@ -0,0 +1,65 @@
+use wasmparser::ModuleView;
+
+#[test]
+fn sections() {
+    let wasm = wat::parse_str(
+        r#"
+            (module
+                (@custom "before" (before first) "a")
+                (type (func))
+                (import "m" "f" (func (type 0)))
+                (func (export "g") (type 0) unreachable)
+                (memory (export "mem") 1)
+                (start 0)
+                (@custom "after" (after last) "bc"))
+        "#,
+    )
+    .unwrap();
+    let view = ModuleView::new(&wasm).unwrap();
+    assert_eq!(view.version(), 1);
+    assert_eq!(
+        view.sections().iter().map(|s| s.id).collect::<Vec<_>>(),
+        [0, 1, 2, 3, 5, 7, 8, 10, 0]
+    );
+    for section in view.sections() {
+        assert_eq!(&wasm[section.range.start..section.range.end], section.data);
+    }
+
+    let exports = view
+        .export_section()
+        .unwrap()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.map(|e| (e.field, format!("{:?}", e.kind), e.index)))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(
+        exports,
+        [
+            ("g", "Function".to_string(), 1),
+            ("mem", "Memory".to_string(), 0)
+        ]
+    );
+    assert_eq!(view.import_section().unwrap().unwrap().get_count(), 1);
+    assert_eq!(view.code_section().unwrap().unwrap().get_count(), 1);
+    assert_eq!(view.start_func().unwrap(), Some(0));
+    assert!(view.data_section().unwrap().is_none());
+    assert!(view.data_count().unwrap().is_none());
+
+    let customs = view
+        .custom_sections()
+        .map(|c| c.map(|c| (c.name, c.data)))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(customs, [("before", &b"a"[..]), ("after", &b"bc"[..])]);
+    let after = view.custom_section("after").unwrap().unwrap();
+    assert_eq!(&wasm[after.data_offset..], b"bc");
+    assert!(view.custom_section("missing").unwrap().is_none());
+}
+
+#[test]
+fn truncated() {
+    let wasm = wat::parse_str("(module (func))").unwrap();
+    assert!(ModuleView::new(&wasm[..wasm.len() - 1]).is_err());
+    assert!(ModuleView::new(&wasm[4..]).is_err());
+}
@@ -0,0 +1,191 @@
+use wasmparser::{
+    FuncType, Operator, Parser, StackEffect, StackType, Type, ValidPayload, Validator,
+    WasmFeatures, WasmModuleResources,
+};
+
+/// Returns the stack effect of each operator of the last function of `wat`.
+fn effects(wat: &str) -> Vec<Option<StackEffect>> {
+    let wasm = wat::parse_str(wat).unwrap();
+    let mut validator = Validator::new();
+    validator.wasm_features(WasmFeatures {
+        memory64: true,
+        exceptions: true,
+        tail_call: true,
+        ..WasmFeatures::default()
+    });
+    let mut effects = Vec::new();
+    for payload in Parser::new(0).parse_all(&wasm) {
+        let payload = payload.unwrap();
+        if let ValidPayload::Func(func, body) = validator.payload(&payload).unwrap() {
+            let mut reader = body.get_operators_reader().unwrap();
+            effects.clear();
+            while !reader.eof() {
+                effects.push(reader.read().unwrap().stack_effect(&func.resources));
+            }
+        }
+    }
+    effects
+}
+
+fn vals(types: &[Type]) -> Vec<StackType> {
+    types.iter().copied().map(StackType::Val).collect()
+}
+
+#[test]
+fn resolves_module_types() {
+    let effects = effects(
+        r#"
+        (module
+          (type $t (func (param i32 f64) (result i64)))
+          (import "env" "f" (func $f (type $t)))
+          (memory i64 1)
+          (table 1 externref)
+          (global $g (mut f32) (f32.const 0))
+          (func (param i64)
+            i32.const 0
+            f64.const 0
+            call $f
+            drop
+            i32.const 0
+            f64.const 0
+            i32.const 0
+            call_indirect (type $t)
+            drop
+            local.get 0
+            i32.load
+            drop
+            memory.size
+            drop
+            global.get $g
+            global.set $g
+            i32.const 0
+            table.get 0
+            drop))
+        "#,
+    );
+    let effects = effects.into_iter().map(Option::unwrap).collect::<Vec<_>>();
+    assert_eq!(effects[2].params, vals(&[Type::I32, Type::F64]));
+    assert_eq!(effects[2].results, vals(&[Type::I64]));
+    assert_eq!(effects[3].params, [StackType::Any]);
+    assert_eq!(effects[7].params, vals(&[Type::I32, Type::F64, Type::I32]));
+    assert_eq!(effects[7].results, vals(&[Type::I64]));
+    assert_eq!(effects[9].results, [StackType::Local(0)]);
+    assert_eq!(effects[10].params, vals(&[Type::I64]));
+    assert_eq!(effects[10].results, vals(&[Type::I32]));
+    assert_eq!(effects[12].results, vals(&[Type::I64]));
+    assert_eq!(effects[14].results, vals(&[Type::F32]));
+    assert_eq!(effects[15].params, vals(&[Type::F32]));
+    assert_eq!(effects[17].params, vals(&[Type::I32]));
+    assert_eq!(effects[17].results, vals(&[Type::ExternRef]));
+    assert!(effects.iter().all(|e| !e.unreachable));
+}
+
+#[test]
+fn symbolic_control_flow() {
+    let effects = effects(
+        r#"
+        (module
+          (type $b (func (param i32) (result i64)))
+          (func (result i64)
+            i32.const 0
+            block (type $b)
+              i32.const 0
+              if (type $b)
+                i64.extend_i32_u
+              else
+                br 0
+              end
+              i32.const 0
+              br_table 0 1 0
+            end))
+        "#,
+    );
+    let effects = effects.into_iter().map(Option::unwrap).collect::<Vec<_>>();
+    assert_eq!(effects[1].params, vals(&[Type::I32]));
+    assert_eq!(effects[1].results, vals(&[Type::I32]));
+    assert_eq!(effects[3].params, vals(&[Type::I32, Type::I32]));
+    assert_eq!(effects[3].results, vals(&[Type::I32]));
+    assert_eq!(effects[5].params, [StackType::BlockResults]);
+    assert_eq!(effects[5].results, [StackType::BlockParams]);
+    assert_eq!(effects[6].params, [StackType::Label(0)]);
+    assert!(effects[6].unreachable);
+    assert_eq!(effects[7].results, [StackType::BlockResults]);
+    assert_eq!(
+        effects[9].params,
+        [StackType::Label(0), StackType::Val(Type::I32)]
+    );
+    assert!(effects[9].unreachable);
+}
+
+#[test]
+fn unknown_indices() {
+    struct Empty(Vec<FuncType>);
+    impl WasmModuleResources for Empty {
+        type FuncType = FuncType;
+        fn table_at(&self, _: u32) -> Option<wasmparser::TableType> {
+            None
+        }
+        fn memory_at(&self, _: u32) -> Option<wasmparser::MemoryType> {
+            None
+        }
+        fn event_at(&self, at: u32) -> Option<&FuncType> {
+            self.0.get(at as usize)
+        }
+        fn global_at(&self, _: u32) -> Option<wasmparser::GlobalType> {
+            None
+        }
+        fn func_type_at(&self, at: u32) -> Option<&FuncType> {
+            self.0.get(at as usize)
+        }
+        fn type_of_function(&self, _: u32) -> Option<&FuncType> {
+            None
+        }
+        fn element_type_at(&self, _: u32) -> Option<Type> {
+            None
+        }
+        fn element_count(&self) -> u32 {
+            0
+        }
+        fn data_count(&self) -> u32 {
+            0
+        }
+        fn is_function_referenced(&self, _: u32) -> bool {
+            false
+        }
+    }
+
+    let resources = Empty(vec![FuncType {
+        params: Box::new([Type::I32]),
+        returns: Box::new([]),
+    }]);
+    assert_eq!(
+        Operator::Throw { index: 0 }.stack_effect(&resources),
+        Some(StackEffect {
+            params: vals(&[Type::I32]),
+            results: Vec::new(),
+            unreachable: true,
+        })
+    );
+    assert_eq!(Operator::Throw { index: 1 }.stack_effect(&resources), None);
+    assert_eq!(
+        Operator::Call { function_index: 0 }.stack_effect(&resources),
+        None
+    );
+    assert_eq!(
+        Operator::GlobalGet { global_index: 0 }.stack_effect(&resources),
+        None
+    );
+    assert_eq!(
+        Operator::TableSize { table: 0 }.stack_effect(&resources),
+        None
+    );
+    assert_eq!(
+        Operator::MemorySize {
+            mem: 0,
+            mem_byte: 0
+        }
+        .stack_effect(&resources),
+        None
+    );
+    assert!(Operator::F32x4Add.stack_effect(&resources).is_some());
+}
@@ -0,0 +1,87 @@
+use wasmparser::{ErrorCode, StreamingValidator, ValidationStatus, WasmFeatures};
+
+const MODULE: &str = r#"
+    (module
+      (type (func (param i32) (result i32)))
+      (import "env" "f" (func (type 0)))
+      (memory 1)
+      (global (mut i32) (i32.const 0))
+      (func (export "g") (type 0)
+        local.get 0
+        call 0)
+      (func (result i64)
+        i64.const 1)
+      (data (i32.const 0) "hello"))
+"#;
+
+/// Feeds `wasm` to a new validator `chunk` bytes at a time.
+fn feed_in_chunks(
+    validator: &mut StreamingValidator,
+    wasm: &[u8],
+    chunk: usize,
+) -> wasmparser::Result<ValidationStatus> {
+    for bytes in wasm.chunks(chunk) {
+        match validator.feed(bytes, false)? {
+            ValidationStatus::NeedMoreData(hint) => assert!(hint > 0),
+            ValidationStatus::Done => panic!("done before the end of the input"),
+        }
+    }
+    validator.feed(&[], true)
+}
+
+#[test]
+fn any_chunk_size() {
+    let wasm = wat::parse_str(MODULE).unwrap();
+    for chunk in 1..=wasm.len() {
+        let status = feed_in_chunks(&mut StreamingValidator::new(), &wasm, chunk).unwrap();
+        assert_eq!(status, ValidationStatus::Done);
+    }
+
+    // All the input can be given at once too.
+    let status = StreamingValidator::new().feed(&wasm, true).unwrap();
+    assert_eq!(status, ValidationStatus::Done);
+}
+
+#[test]
+fn errors_are_reported_early() {
+    let wasm = wat::parse_str(
+        r#"(module (memory 1) (func (result i32) i64.const 0) (data (i32.const 0) "hello"))"#,
+    )
+    .unwrap();
+    let end_of_code = wasm.windows(5).position(|w| w == b"hello").unwrap();
+    let mut validator = StreamingValidator::new();
+    let err = validator.feed(&wasm[..end_of_code], false).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::TypeMismatch);
+
+    // Truncated modules are only invalid once the end of input is reached.
+    let wasm = wat::parse_str(MODULE).unwrap();
+    let mut validator = StreamingValidator::new();
+    let status = validator.feed(&wasm[..wasm.len() - 1], false).unwrap();
+    assert!(matches!(status, ValidationStatus::NeedMoreData(_)));
+    let err = validator.feed(&[], true).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::UnexpectedEof);
+}
+
+#[test]
+fn nested_modules() {
+    let wasm = wat::parse_str(
+        r#"
+            (module
+              (module $m
+                (func (export "f") (result i32) i32.const 0))
+              (instance (instantiate $m))
+              (func (result i32) i32.const 1))
+        "#,
+    )
+    .unwrap();
+    let features = WasmFeatures {
+        module_linking: true,
+        ..WasmFeatures::default()
+    };
+    for chunk in 1..=wasm.len() {
+        let mut validator = StreamingValidator::new();
+        validator.wasm_features(features);
+        let status = feed_in_chunks(&mut validator, &wasm, chunk).unwrap();
+        assert_eq!(status, ValidationStatus::Done);
+    }
+}
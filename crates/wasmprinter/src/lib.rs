@@ -9,13 +9,19 @@
 #![deny(missing_docs)]
 
 use anyhow::{bail, Context, Result};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Write;
+use std::io;
 use std::mem;
 use std::path::Path;
 use wasmparser::*;
 
 const MAX_LOCALS: u32 = 50000;
+const MAX_FOLDED_DEPTH: u32 = 1000;
+
+// Where printed text is written out to as printing goes, if anywhere, see
+// `Printer::flush`.
+type Sink<'a> = Option<&'a mut dyn FnMut(&str) -> Result<()>>;
 
 /// Reads a WebAssembly `file` from the filesystem and then prints it into an
 /// in-memory `String`.
@@ -38,12 +44,62 @@ pub fn print_bytes(wasm: impl AsRef<[u8]>) -> Result<String> {
 ///
 /// This is largely only required if you'd like to register custom printers for
 /// custom sections in a wasm binary.
-#[derive(Default)]
 pub struct Printer {
     printers: HashMap<String, Box<dyn FnMut(&mut Printer, usize, &[u8]) -> Result<()>>>,
     result: String,
     state: ModuleState,
     nesting: u32,
+    demangle: bool,
+    quoted_ids: bool,
+    float_style: FloatStyle,
+    fold_instructions: bool,
+    skeleton: bool,
+    item: Option<(ItemKind, String)>,
+    symbol_map: BTreeMap<u32, String>,
+}
+
+/// A kind of item of a module which can be printed on its own, see
+/// [`Printer::print_item`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ItemKind {
+    /// A function, either imported or defined.
+    Func,
+    /// A global, either imported or defined.
+    Global,
+    /// An element segment.
+    Elem,
+    /// A data segment.
+    Data,
+}
+
+impl ItemKind {
+    fn desc(self) -> &'static str {
+        match self {
+            ItemKind::Func => "function",
+            ItemKind::Global => "global",
+            ItemKind::Elem => "element segment",
+            ItemKind::Data => "data segment",
+        }
+    }
+}
+
+/// How the operands of `f32.const` and `f64.const` are printed, see
+/// [`Printer::float_style`].
+///
+/// Every style prints floats exactly, so that the output assembles back to
+/// the same bits, including the payloads of NaNs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum FloatStyle {
+    /// A hex float literal followed by a comment with its decimal value, such
+    /// as `0x1.8p+1 (;=3;)`. This is the default.
+    #[default]
+    Hex,
+    /// The shortest decimal literal which parses back to the same value, such
+    /// as `3.0` or `1e-7`.
+    Decimal,
+    /// A hex float literal followed by a comment with its bit pattern, such
+    /// as `0x1.8p+1 (;=0x40400000;)`.
+    Bits,
 }
 
 #[derive(Default)]
@@ -60,18 +116,113 @@ struct ModuleState {
     local_names: HashMap<u32, HashMap<u32, Naming>>,
     module_name: Option<Naming>,
     implicit_instances_seen: HashSet<String>,
+    branch_hints: HashMap<u32, Box<[BranchHint]>>,
+    // The number of locals declared by each block enclosing the instruction
+    // being printed, which is only non-zero for `let` blocks.
+    let_locals: Vec<u32>,
+    // Comments from the `metadata.comments` section which are yet to be
+    // printed, and the number of items of each section printed so far.
+    comments: HashMap<CommentAnchor, Vec<String>>,
+    items: HashMap<u8, u32>,
+    // The resources of each function of the code section, used to fold its
+    // instructions, if the module validated that far.
+    func_resources: Vec<ValidatorResources>,
+    // The only item to print, if not the whole module.
+    only: Option<(ItemKind, u32)>,
 }
 
 struct Naming {
     identifier: Option<String>,
     name: String,
+    // Whether `identifier` is the quoted form of `name`, rather than an
+    // alternate identifier which needs an `@name` annotation.
+    quoted: bool,
+}
+
+impl Default for Printer {
+    fn default() -> Printer {
+        Printer::new()
+    }
 }
 
 impl Printer {
     /// Creates a new `Printer` object that's ready to start printing wasm
     /// binaries to strings.
     pub fn new() -> Printer {
-        Printer::default()
+        Printer {
+            printers: HashMap::new(),
+            result: String::new(),
+            state: ModuleState::default(),
+            nesting: 0,
+            demangle: true,
+            quoted_ids: false,
+            float_style: FloatStyle::default(),
+            fold_instructions: false,
+            skeleton: false,
+            item: None,
+            symbol_map: BTreeMap::new(),
+        }
+    }
+
+    /// Configures whether Rust and C++ symbol names from the `name` section
+    /// are demangled.
+    ///
+    /// When enabled, which is the default, a comment containing the demangled
+    /// form of a function's name is printed after the function's identifier.
+    /// The identifier itself is left untouched so the output still assembles
+    /// to the same binary.
+    pub fn demangle(&mut self, demangle: bool) {
+        self.demangle = demangle;
+    }
+
+    /// Configures whether names from the `name` section which aren't valid
+    /// identifiers are printed as quoted identifiers like `$"foo bar"`, the
+    /// extended name syntax of the annotations proposal.
+    ///
+    /// When disabled, which is the default, such names are instead printed
+    /// as an identifier made of their valid characters followed by an
+    /// `(@name "foo bar")` annotation, which more tools understand.
+    pub fn quoted_ids(&mut self, quoted_ids: bool) {
+        self.quoted_ids = quoted_ids;
+    }
+
+    /// Configures how floating point constants are printed, which defaults
+    /// to [`FloatStyle::Hex`].
+    pub fn float_style(&mut self, style: FloatStyle) {
+        self.float_style = style;
+    }
+
+    /// Configures whether the instructions of functions are printed in the
+    /// folded s-expression form, such as `(i32.add (local.get 0) (i32.const
+    /// 1))`, rather than one per line.
+    ///
+    /// Instructions are only folded around the ones computing all of their
+    /// operands, so the output still assembles to the same binary. Functions
+    /// of modules which don't validate are printed unfolded. This is disabled
+    /// by default.
+    pub fn fold_instructions(&mut self, fold: bool) {
+        self.fold_instructions = fold;
+    }
+
+    /// Configures whether only the structure of modules is printed, leaving
+    /// out the locals and instructions of functions.
+    ///
+    /// Each function body is replaced with a comment giving its number of
+    /// instructions, like `(; 12 instructions ;)`, which makes the output of
+    /// large modules much quicker to look through. The output doesn't
+    /// assemble to the same binary. This is disabled by default.
+    pub fn skeleton(&mut self, skeleton: bool) {
+        self.skeleton = skeleton;
+    }
+
+    /// Configures names for the functions of modules whose name section
+    /// doesn't name any functions, such as those of a symbol map emitted by
+    /// the toolchain which produced a stripped module.
+    ///
+    /// `symbols` maps function indices to their names. The names are only
+    /// used for the outermost module, not for modules nested within it.
+    pub fn symbol_map(&mut self, symbols: impl IntoIterator<Item = (u32, String)>) {
+        self.symbol_map = symbols.into_iter().collect();
     }
 
     /// Registers a custom `printer` function to get invoked whenever a custom
@@ -108,16 +259,81 @@ impl Printer {
     /// the WebAssembly Text Format and return the result as a `String`.
     pub fn print(&mut self, mut wasm: &[u8]) -> Result<String> {
         self.start_group("module");
-        self.print_contents(Parser::new(0), &mut wasm, "")?;
+        self.print_contents(Parser::new(0), &mut wasm, "", &mut None)?;
         self.end_group();
         Ok(mem::take(&mut self.result))
     }
 
+    /// Prints a WebAssembly binary into `dst`, writing the text out as it's
+    /// printed.
+    ///
+    /// Unlike with [`Printer::print`] the text of the whole module is never
+    /// held in memory: it's written out after every section, function and
+    /// data segment, so printing a large module only takes as much memory as
+    /// its largest one of those.
+    pub fn print_to(&mut self, wasm: &[u8], mut dst: impl io::Write) -> Result<()> {
+        self.print_to_sink(wasm, &mut |text| Ok(dst.write_all(text.as_bytes())?))
+    }
+
+    /// Same as [`Printer::print_to`], but writes the text into a
+    /// [`fmt::Write`](std::fmt::Write) such as a `Formatter`.
+    pub fn print_to_fmt(&mut self, wasm: &[u8], mut dst: impl Write) -> Result<()> {
+        self.print_to_sink(wasm, &mut |text| Ok(dst.write_str(text)?))
+    }
+
+    fn print_to_sink(
+        &mut self,
+        mut wasm: &[u8],
+        sink: &mut dyn FnMut(&str) -> Result<()>,
+    ) -> Result<()> {
+        let mut sink = Some(sink);
+        self.start_group("module");
+        self.print_contents(Parser::new(0), &mut wasm, "", &mut sink)?;
+        self.end_group();
+        self.flush(&mut sink)
+    }
+
+    /// Writes out the text printed so far to `sink`, if there is one, so it
+    /// doesn't build up in memory.
+    ///
+    /// This is only done between items, as printing them may look back at
+    /// or rewrite the text of the current one.
+    fn flush(&mut self, sink: &mut Sink<'_>) -> Result<()> {
+        if let Some(sink) = sink {
+            sink(&self.result)?;
+            self.result.clear();
+        }
+        Ok(())
+    }
+
+    /// Prints a single item of the `wasm` binary into a `String`, without
+    /// printing the rest of the module.
+    ///
+    /// The item of kind `kind` is identified by `item`, which is either its
+    /// index or, for functions, its name from the `name` section like
+    /// `$main`. Items in nested modules can't be printed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the binary is malformed or if it has no such item.
+    pub fn print_item(&mut self, mut wasm: &[u8], kind: ItemKind, item: &str) -> Result<String> {
+        self.item = Some((kind, item.to_string()));
+        let result = self.print_contents(Parser::new(0), &mut wasm, "", &mut None);
+        self.item = None;
+        let text = mem::take(&mut self.result);
+        result?;
+        if text.is_empty() {
+            bail!("module has no {} `{}`", kind.desc(), item);
+        }
+        Ok(text.trim_start().to_string())
+    }
+
     fn print_contents(
         &mut self,
         mut parser: Parser,
         wasm: &mut &[u8],
         module_ty: &str,
+        sink: &mut Sink<'_>,
     ) -> Result<()> {
         // First up try to find the `name` subsection which we'll use to print
         // pretty names everywhere. Also look for the `code` section so we can
@@ -126,6 +342,29 @@ impl Printer {
         let mut pre_parser = parser.clone();
         let prev = mem::take(&mut self.state);
         let mut bytes = *wasm;
+        // Folding instructions needs the types of their operands, which are
+        // worked out by validating the module.
+        let mut validator = if self.fold_instructions {
+            let mut validator = Validator::new();
+            validator.wasm_features(WasmFeatures {
+                reference_types: true,
+                multi_value: true,
+                bulk_memory: true,
+                module_linking: true,
+                simd: true,
+                threads: true,
+                tail_call: true,
+                deterministic_only: false,
+                multi_memory: true,
+                exceptions: true,
+                memory64: true,
+                wide_arithmetic: true,
+                function_references: true,
+            });
+            Some(validator)
+        } else {
+            None
+        };
         loop {
             let payload = match pre_parser.parse(bytes, true)? {
                 Chunk::NeedMoreData(_) => unreachable!(),
@@ -134,6 +373,15 @@ impl Printer {
                     payload
                 }
             };
+            if let Some(v) = &mut validator {
+                match v.payload(&payload) {
+                    Ok(ValidPayload::Func(func, _)) => {
+                        self.state.func_resources.push(func.resources)
+                    }
+                    Ok(_) => {}
+                    Err(_) => validator = None,
+                }
+            }
             match payload {
                 Payload::CodeSectionEntry(f) => code.push(f),
                 Payload::ModuleSectionStart { size, .. } => {
@@ -149,17 +397,70 @@ impl Printer {
                     // Ignore any error associated with the name section.
                     drop(self.register_names(reader));
                 }
+                Payload::CustomSection {
+                    name: "metadata.code.branch_hint",
+                    data_offset,
+                    data,
+                } => {
+                    let reader = BranchHintSectionReader::new(data, data_offset)?;
+                    for func in reader {
+                        let func = func?;
+                        self.state.branch_hints.insert(func.func, func.hints);
+                    }
+                }
+                Payload::CustomSection {
+                    name: "metadata.comments",
+                    data_offset,
+                    data,
+                } => {
+                    let reader = CommentSectionReader::new(data, data_offset)?;
+                    for comment in reader {
+                        let comment = comment?;
+                        let comments = self.state.comments.entry(comment.anchor).or_default();
+                        comments.push(comment.text.to_string());
+                    }
+                }
                 Payload::End => break,
                 _ => {}
             }
         }
 
+        // Names from the symbol map are only for the outermost module, which
+        // is being printed when they haven't been taken yet.
+        let symbol_map = mem::take(&mut self.symbol_map);
+        if self.state.names.is_empty() {
+            let mut names = HashSet::new();
+            for (index, name) in symbol_map.iter() {
+                let naming = Naming::new(name, &mut names, self.quoted_ids);
+                self.state.names.insert(*index, naming);
+            }
+        }
+
+        // When printing a single item, the other items are only read for the
+        // indices and types they define.
+        if let Some((kind, item)) = self.item.take() {
+            self.state.only = Some((kind, self.resolve_item(kind, &item)?));
+        }
+        let filtered = self.state.only.is_some();
+
+        // Comments before the module are printed before the `(module` which
+        // was just printed, at the same indentation.
+        if let Some(comments) = self.state.comments.remove(&CommentAnchor::Module) {
+            if !filtered {
+                let group = self.result.split_off(self.result.len() - "(module".len());
+                self.print_comments_before(comments);
+                self.result.push_str(&group);
+            }
+        }
+
         // ... and here we go, time to print all the sections!
-        if let Some(name) = &self.state.module_name {
-            self.result.push_str(" ");
-            name.write(&mut self.result);
+        if !filtered {
+            if let Some(name) = &self.state.module_name {
+                self.result.push_str(" ");
+                name.write(&mut self.result);
+            }
+            self.result.push_str(module_ty);
         }
-        self.result.push_str(module_ty);
         loop {
             let payload = match parser.parse(*wasm, true)? {
                 Chunk::NeedMoreData(_) => unreachable!(),
@@ -168,6 +469,22 @@ impl Printer {
                     payload
                 }
             };
+            let start = self.result.len();
+            let has_items = match payload {
+                Payload::ImportSection(_)
+                | Payload::FunctionSection(_)
+                | Payload::GlobalSection(_)
+                | Payload::ElementSection(_)
+                | Payload::DataSection(_) => true,
+                Payload::ModuleSectionStart { count, size, .. } if filtered => {
+                    *wasm = &wasm[size as usize..];
+                    parser.skip_section();
+                    self.state.module += count;
+                    continue;
+                }
+                Payload::End if filtered => break,
+                _ => false,
+            };
             match payload {
                 Payload::CustomSection {
                     name,
@@ -186,7 +503,7 @@ impl Printer {
                     if reader.get_count() == 0 {
                         continue;
                     }
-                    self.print_code(&code, reader)?;
+                    self.print_code(&code, reader, sink)?;
                 }
                 Payload::TableSection(s) => self.print_tables(s)?,
                 Payload::MemorySection(s) => self.print_memories(s)?,
@@ -194,6 +511,7 @@ impl Printer {
                 Payload::GlobalSection(s) => self.print_globals(s)?,
                 Payload::ExportSection(s) => self.print_exports(s)?,
                 Payload::StartSection { func, .. } => {
+                    self.print_item_comments(8);
                     self.newline();
                     self.start_group("start ");
                     self.print_func_idx(func)?;
@@ -211,15 +529,17 @@ impl Printer {
                     unreachable!()
                 }
 
-                Payload::DataSection(s) => self.print_data(s)?,
+                Payload::DataSection(s) => self.print_data(s, sink)?,
                 Payload::AliasSection(s) => self.print_aliases(s)?,
                 Payload::InstanceSection(s) => self.print_instances(s)?,
 
                 Payload::ModuleSectionStart { .. } => {}
                 Payload::ModuleSectionEntry { parser, .. } => {
+                    self.print_item_comments(14);
                     self.newline();
                     self.start_group("module");
-                    self.print_contents(parser, wasm, &format!(" (;{};)", self.state.module))?;
+                    let ty = format!(" (;{};)", self.state.module);
+                    self.print_contents(parser, wasm, &ty, sink)?;
                     self.end_group();
                     self.state.module += 1;
                 }
@@ -227,15 +547,106 @@ impl Printer {
                 // not part of the text format
                 Payload::Version { .. } | Payload::DataCountSection { .. } => {}
 
-                Payload::End => break,
+                Payload::End => {
+                    // The closing paren goes on its own line so it isn't
+                    // part of a trailing line comment.
+                    if self.print_comments(CommentAnchor::End) {
+                        self.nesting -= 1;
+                        self.newline();
+                        self.nesting += 1;
+                    }
+                    break;
+                }
 
                 Payload::UnknownSection { id, .. } => bail!("found unknown section `{}`", id),
             }
+            if filtered && !has_items {
+                self.result.truncate(start);
+            }
+            self.flush(sink)?;
         }
         self.state = prev;
+        self.symbol_map = symbol_map;
         Ok(())
     }
 
+    /// Returns the index of the item of kind `kind` identified by `item`, see
+    /// [`Printer::print_item`].
+    fn resolve_item(&self, kind: ItemKind, item: &str) -> Result<u32> {
+        if let Ok(index) = item.parse() {
+            return Ok(index);
+        }
+        let name = item.strip_prefix('$').unwrap_or(item);
+        if kind == ItemKind::Func {
+            let names = &self.state.names;
+            let index = names
+                .iter()
+                .filter(|(_, n)| n.identifier() == name)
+                .chain(names.iter().filter(|(_, n)| n.name == name))
+                .map(|(i, _)| *i)
+                .min();
+            if let Some(index) = index {
+                return Ok(index);
+            }
+        }
+        bail!("no {} named `{}`", kind.desc(), item)
+    }
+
+    /// Returns whether only a single item is being printed and it isn't the
+    /// item `index` of `kind`, in which case the next item of the sections
+    /// with the id `section` is skipped over.
+    fn skip_item(&mut self, section: u8, kind: Option<ItemKind>, index: u32) -> bool {
+        match self.state.only {
+            Some(only) if Some(only) != kind.map(|k| (k, index)) => {
+                self.next_item_anchor(section);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Prints the comments recorded for the next item of the sections with
+    /// the id `section`, each on its own line.
+    fn print_item_comments(&mut self, section: u8) {
+        let anchor = self.next_item_anchor(section);
+        self.print_comments(anchor);
+    }
+
+    /// Returns the anchor of comments for the next item of the sections with
+    /// the id `section`.
+    fn next_item_anchor(&mut self, section: u8) -> CommentAnchor {
+        let index = self.state.items.entry(section).or_insert(0);
+        let anchor = CommentAnchor::Item {
+            section,
+            index: *index,
+        };
+        *index += 1;
+        anchor
+    }
+
+    /// Prints the comments recorded for `anchor` each on its own line,
+    /// returning whether there were any.
+    fn print_comments(&mut self, anchor: CommentAnchor) -> bool {
+        let comments = self.state.comments.remove(&anchor).unwrap_or_default();
+        for comment in comments.iter() {
+            self.newline();
+            self.result.push_str(comment);
+        }
+        !comments.is_empty()
+    }
+
+    /// Prints `comments` at the start of the current line, which only has its
+    /// indentation so far, each followed by a new line with the same
+    /// indentation.
+    fn print_comments_before(&mut self, comments: Vec<String>) {
+        let indent = self.result[self.result.rfind('\n').map_or(0, |i| i + 1)..].to_string();
+        for comment in comments {
+            self.result.push_str(&comment);
+            self.result.push('\n');
+            self.result.push_str(&indent);
+        }
+    }
+
     fn start_group(&mut self, name: &str) {
         self.result.push_str("(");
         self.result.push_str(name);
@@ -251,7 +662,7 @@ impl Printer {
         for section in names {
             match section? {
                 Name::Module(n) => {
-                    let name = Naming::new(n.get_name()?, &mut HashSet::new());
+                    let name = Naming::new(n.get_name()?, &mut HashSet::new(), self.quoted_ids);
                     self.state.module_name = Some(name);
                 }
                 Name::Function(n) => {
@@ -259,9 +670,10 @@ impl Printer {
                     let mut map = n.get_map()?;
                     for _ in 0..map.get_count() {
                         let name = map.read()?;
-                        self.state
-                            .names
-                            .insert(name.index, Naming::new(name.name, &mut names));
+                        self.state.names.insert(
+                            name.index,
+                            Naming::new(name.name, &mut names, self.quoted_ids),
+                        );
                     }
                 }
                 Name::Local(n) => {
@@ -273,7 +685,8 @@ impl Printer {
                         let mut local_map = HashMap::new();
                         for _ in 0..map.get_count() {
                             let name = map.read()?;
-                            local_map.insert(name.index, Naming::new(name.name, &mut names));
+                            let naming = Naming::new(name.name, &mut names, self.quoted_ids);
+                            local_map.insert(name.index, naming);
                         }
                         self.state
                             .local_names
@@ -287,6 +700,7 @@ impl Printer {
 
     fn print_types(&mut self, parser: TypeSectionReader<'_>) -> Result<()> {
         for ty in parser {
+            self.print_item_comments(1);
             self.newline();
             self.start_group("type");
             write!(self.result, " (;{};) ", self.state.types.len())?;
@@ -411,6 +825,15 @@ impl Printer {
     fn print_imports(&mut self, parser: ImportSectionReader<'_>) -> Result<()> {
         for import in parser {
             let import = import?;
+            let (kind, index) = match import.ty {
+                ImportSectionEntryType::Function(_) => (Some(ItemKind::Func), self.state.func),
+                ImportSectionEntryType::Global(_) => (Some(ItemKind::Global), self.state.global),
+                _ => (None, 0),
+            };
+            let skip = self.skip_item(2, kind, index);
+            if !skip {
+                self.print_item_comments(2);
+            }
 
             // Handle the module linking proposal here where the first time we
             // see the module-name of a two-level import that translates to an
@@ -424,7 +847,9 @@ impl Printer {
                     self.state.instance += 1;
                 }
             }
-            self.print_import(&import, true)?;
+            if !skip {
+                self.print_import(&import, true)?;
+            }
             match import.ty {
                 ImportSectionEntryType::Function(_) => self.state.func += 1,
                 ImportSectionEntryType::Module(_) => self.state.module += 1,
@@ -463,6 +888,7 @@ impl Printer {
                         Some(name) => name.write(&mut self.result),
                         None => write!(self.result, "(;{};)", self.state.func)?,
                     }
+                    self.print_demangled_name(self.state.func)?;
                 }
                 write!(self.result, " (type {})", f)?;
             }
@@ -560,6 +986,7 @@ impl Printer {
     fn print_tables(&mut self, parser: TableSectionReader<'_>) -> Result<()> {
         for table in parser {
             let table = table?;
+            self.print_item_comments(4);
             self.newline();
             self.print_table_type(&table, true)?;
             self.end_group();
@@ -571,6 +998,7 @@ impl Printer {
     fn print_memories(&mut self, parser: MemorySectionReader<'_>) -> Result<()> {
         for memory in parser {
             let memory = memory?;
+            self.print_item_comments(5);
             self.newline();
             self.print_memory_type(&memory, true)?;
             self.end_group();
@@ -582,6 +1010,7 @@ impl Printer {
     fn print_events(&mut self, parser: EventSectionReader<'_>) -> Result<()> {
         for exn in parser {
             let exn = exn?;
+            self.print_item_comments(13);
             self.newline();
             self.print_event_type(&exn, true)?;
             self.end_group();
@@ -593,6 +1022,11 @@ impl Printer {
     fn print_globals(&mut self, parser: GlobalSectionReader<'_>) -> Result<()> {
         for global in parser {
             let global = global?;
+            if self.skip_item(6, Some(ItemKind::Global), self.state.global) {
+                self.state.global += 1;
+                continue;
+            }
+            self.print_item_comments(6);
             self.newline();
             self.print_global_type(&global.ty, true)?;
             self.result.push_str(" ");
@@ -607,18 +1041,43 @@ impl Printer {
         &mut self,
         code: &[FunctionBody<'_>],
         mut funcs: FunctionSectionReader<'_>,
+        sink: &mut Sink<'_>,
     ) -> Result<()> {
-        for body in code {
+        let resources = mem::take(&mut self.state.func_resources);
+        for (i, body) in code.iter().enumerate() {
+            self.flush(sink)?;
             let ty = funcs.read()?;
+            if self.skip_item(3, Some(ItemKind::Func), self.state.func) {
+                self.state.func += 1;
+                continue;
+            }
+            self.print_item_comments(3);
             self.newline();
             self.start_group("func ");
             match self.state.names.get(&self.state.func) {
                 Some(name) => name.write(&mut self.result),
                 None => write!(self.result, "(;{};)", self.state.func)?,
             }
+            self.print_demangled_name(self.state.func)?;
             let params = self
                 .print_functype_idx(ty, true, Some(self.state.func))?
                 .unwrap_or(0);
+            if self.skeleton {
+                let mut reader = body.get_operators_reader()?;
+                let mut count = 0u32;
+                while !reader.eof() {
+                    reader.read()?;
+                    count += 1;
+                }
+                // The final `end` is implied in the text format.
+                match count.saturating_sub(1) {
+                    1 => self.result.push_str(" (; 1 instruction ;)"),
+                    n => write!(self.result, " (; {} instructions ;)", n)?,
+                }
+                self.end_group();
+                self.state.func += 1;
+                continue;
+            }
 
             let mut first = true;
             let mut local_idx = 0;
@@ -650,53 +1109,371 @@ impl Printer {
             }
             locals.finish(&mut self.result);
 
-            let nesting_start = self.nesting;
-            let mut reader = body.get_operators_reader()?;
-            while !reader.eof() {
-                let operator = reader.read()?;
-                match operator {
-                    // The final `end` in a reader is not printed, it's implied
-                    // in the text format.
-                    Operator::End if reader.eof() => break,
-
-                    // When we start a block we newline to the current
-                    // indentation, then we increase the indentation so further
-                    // instructions are tabbed over.
-                    Operator::If { .. }
-                    | Operator::Block { .. }
-                    | Operator::Loop { .. }
-                    | Operator::Try { .. } => {
+            let hints = self
+                .state
+                .branch_hints
+                .remove(&self.state.func)
+                .unwrap_or_default();
+            let folded = match resources.get(i) {
+                Some(resources) if self.fold_instructions => {
+                    self.fold_body(body, ty, resources, &hints)?
+                }
+                _ => None,
+            };
+            match folded {
+                Some(body) => {
+                    for instr in body.iter() {
                         self.newline();
-                        self.nesting += 1;
+                        self.print_folded(instr);
                     }
+                }
+                None => self.print_body(body, &hints)?,
+            }
+            self.end_group();
 
-                    // `else`/`catch` are special in that it's printed at
-                    // the previous indentation, but it doesn't actually change
-                    // our nesting level.
-                    Operator::Else | Operator::Catch { .. } | Operator::Unwind => {
-                        self.nesting -= 1;
-                        self.newline();
-                        self.nesting += 1;
+            self.state.func += 1;
+        }
+        Ok(())
+    }
+
+    fn print_body(&mut self, body: &FunctionBody<'_>, hints: &[BranchHint]) -> Result<()> {
+        let nesting_start = self.nesting;
+        self.state.let_locals.clear();
+        let mut reader = body.get_operators_reader()?;
+        let mut hints = hints.iter().peekable();
+        while !reader.eof() {
+            let offset = reader.original_position() - body.range().start;
+            let hint = match hints.peek() {
+                Some(hint) if hint.func_offset as usize == offset => hints.next(),
+                _ => None,
+            };
+            let operator = reader.read()?;
+            match operator {
+                // The final `end` in a reader is not printed, it's implied
+                // in the text format.
+                Operator::End if reader.eof() => break,
+
+                // When we start a block we newline to the current
+                // indentation, then we increase the indentation so further
+                // instructions are tabbed over.
+                Operator::If { .. }
+                | Operator::Block { .. }
+                | Operator::Loop { .. }
+                | Operator::Try { .. } => {
+                    self.newline();
+                    self.nesting += 1;
+                    self.state.let_locals.push(0);
+                }
+                Operator::Let { ref locals, .. } => {
+                    self.newline();
+                    self.nesting += 1;
+                    let mut count = 0u32;
+                    for local in locals.locals() {
+                        count = count.saturating_add(local?.0);
                     }
+                    self.state.let_locals.push(count);
+                }
 
-                    // Exiting a block prints `end` at the previous indentation
-                    // level.
-                    Operator::End if self.nesting > nesting_start => {
-                        self.nesting -= 1;
-                        self.newline();
+                // `else`/`catch` are special in that it's printed at
+                // the previous indentation, but it doesn't actually change
+                // our nesting level.
+                Operator::Else | Operator::Catch { .. } | Operator::Unwind => {
+                    self.nesting -= 1;
+                    self.newline();
+                    self.nesting += 1;
+                }
+
+                // Exiting a block prints `end` at the previous indentation
+                // level.
+                Operator::End if self.nesting > nesting_start => {
+                    self.nesting -= 1;
+                    self.newline();
+                    self.state.let_locals.pop();
+                }
+
+                // .. otherwise everything else just has a normal newline
+                // out in front.
+                _ => self.newline(),
+            }
+            let anchor = CommentAnchor::Instruction {
+                func: self.state.func,
+                offset: offset as u32,
+            };
+            if let Some(comments) = self.state.comments.remove(&anchor) {
+                self.print_comments_before(comments);
+            }
+            if let Some(hint) = hint {
+                let value = if hint.taken { "\\01" } else { "\\00" };
+                write!(self.result, "(@metadata.code.branch_hint \"{}\") ", value)?;
+            }
+            self.print_operator(&operator, nesting_start)?;
+        }
+        Ok(())
+    }
+
+    /// Folds the instructions of `body`, whose function has the type `ty`,
+    /// around the ones computing their operands.
+    ///
+    /// Returns `None` if some of the instructions can't be written in the
+    /// folded form, such as an `else` without any instructions, in which case
+    /// the function is printed unfolded.
+    fn fold_body(
+        &mut self,
+        body: &FunctionBody<'_>,
+        ty: u32,
+        resources: &ValidatorResources,
+        hints: &[BranchHint],
+    ) -> Result<Option<Vec<Folded>>> {
+        let start = self.result.len();
+        let nesting_start = self.nesting;
+        self.state.let_locals.clear();
+        let folded = self.fold_operators(body, ty, resources, hints, nesting_start);
+        self.result.truncate(start);
+        self.nesting = nesting_start;
+        let folded = match folded? {
+            Some(folded) => folded,
+            None => return Ok(None),
+        };
+        // The comments were printed with the instructions.
+        let func = self.state.func;
+        self.state.comments.retain(|anchor, _| match anchor {
+            CommentAnchor::Instruction { func: f, .. } => *f != func,
+            _ => true,
+        });
+        Ok(Some(folded))
+    }
+
+    fn fold_operators(
+        &mut self,
+        body: &FunctionBody<'_>,
+        ty: u32,
+        resources: &ValidatorResources,
+        hints: &[BranchHint],
+        nesting_start: u32,
+    ) -> Result<Option<Vec<Folded>>> {
+        let func_results = match resources.func_type_at(ty) {
+            Some(ty) => ty.len_outputs(),
+            None => return Ok(None),
+        };
+        let mut blocks = vec![FoldedBlock {
+            instr: Folded::new(String::new(), Vec::new(), None),
+            arms: vec![(String::new(), Vec::new())],
+            label_arity: func_results,
+            results: None,
+        }];
+        let mut reader = body.get_operators_reader()?;
+        let mut hints = hints.iter().peekable();
+        while !reader.eof() {
+            let offset = reader.original_position() - body.range().start;
+            let hint = match hints.peek() {
+                Some(hint) if hint.func_offset as usize == offset => hints.next(),
+                _ => None,
+            };
+            let operator = reader.read()?;
+            if let Operator::End = operator {
+                if reader.eof() {
+                    break;
+                }
+            }
+            let anchor = CommentAnchor::Instruction {
+                func: self.state.func,
+                offset: offset as u32,
+            };
+            let comments = self.state.comments.get(&anchor).cloned();
+            let effect = match operator.stack_effect(resources) {
+                Some(effect) => effect,
+                None => return Ok(None),
+            };
+            // The operands of `else`, `end` and the like are the results of
+            // their block, which aren't counted.
+            let params = stack_len(&effect.params, &blocks);
+            let block = blocks.last_mut().unwrap();
+            let (_, instrs) = block.arms.last_mut().unwrap();
+            match operator {
+                Operator::Block { ty }
+                | Operator::Loop { ty }
+                | Operator::If { ty }
+                | Operator::Try { ty }
+                | Operator::Let { ty, .. } => {
+                    let results = match ty {
+                        TypeOrFuncType::Type(Type::EmptyBlockType) => 0,
+                        TypeOrFuncType::Type(_) => 1,
+                        TypeOrFuncType::FuncType(idx) => match resources.func_type_at(idx) {
+                            Some(ty) => ty.len_outputs(),
+                            None => return Ok(None),
+                        },
+                    };
+                    let params = match params {
+                        Some(n) => n,
+                        None => return Ok(None),
+                    };
+                    // Only the condition of an `if` is folded into a block,
+                    // its other operands are the block's parameters.
+                    let operands = match operator {
+                        Operator::If { .. } if params == 1 => fold_operands(instrs, 1),
+                        _ => Vec::new(),
+                    };
+                    let self_contained = operands.len() == params;
+                    let (label_arity, arm) = match operator {
+                        Operator::Loop { .. } => (effect.results.len(), ""),
+                        Operator::If { .. } => (results, "then"),
+                        Operator::Try { .. } => (results, "do"),
+                        _ => (results, ""),
+                    };
+                    self.nesting += 1;
+                    let let_locals = match operator {
+                        Operator::Let { ref locals, .. } => {
+                            let mut count = 0u32;
+                            for local in locals.locals() {
+                                count = count.saturating_add(local?.0);
+                            }
+                            count
+                        }
+                        _ => 0,
+                    };
+                    self.state.let_locals.push(let_locals);
+                    let text = self.operator_text(&operator, nesting_start)?;
+                    let mut instr = Folded::new(text, operands, hint);
+                    instr.comments = comments;
+                    blocks.push(FoldedBlock {
+                        instr,
+                        arms: vec![(arm.to_string(), Vec::new())],
+                        label_arity,
+                        results: if self_contained { Some(results) } else { None },
+                    });
+                    if blocks.len() as u32 > MAX_FOLDED_DEPTH {
+                        return Ok(None);
                     }
+                }
+
+                Operator::Else | Operator::Catch { .. } | Operator::Unwind => {
+                    if comments.is_some() || hint.is_some() {
+                        return Ok(None);
+                    }
+                    let arms = block.arms.len();
+                    let valid = match (&operator, &block.arms[0].0[..]) {
+                        (Operator::Else, "then") => arms == 1,
+                        (Operator::Catch { .. }, "do") | (Operator::Unwind, "do") => true,
+                        _ => false,
+                    };
+                    if !valid {
+                        return Ok(None);
+                    }
+                    let text = self.operator_text(&operator, nesting_start)?;
+                    blocks.last_mut().unwrap().arms.push((text, Vec::new()));
+                }
 
-                    // .. otherwise everything else just has a normal newline
-                    // out in front.
-                    _ => self.newline(),
+                Operator::End => {
+                    if comments.is_some() || hint.is_some() || blocks.len() == 1 {
+                        return Ok(None);
+                    }
+                    let block = blocks.pop().unwrap();
+                    let complete = match &block.arms[0].0[..] {
+                        // An empty `(else)` isn't printed.
+                        "then" => block.arms.iter().skip(1).all(|(_, i)| !i.is_empty()),
+                        "do" => block.arms.len() > 1,
+                        _ => true,
+                    };
+                    if !complete {
+                        return Ok(None);
+                    }
+                    self.nesting -= 1;
+                    self.state.let_locals.pop();
+                    let mut instr = block.instr;
+                    instr.depth = block
+                        .arms
+                        .iter()
+                        .flat_map(|(_, instrs)| instrs.iter())
+                        .map(|i| i.depth + 1)
+                        .max()
+                        .unwrap_or(0)
+                        .max(instr.depth);
+                    instr.results = block.results;
+                    instr.arms = Some(block.arms);
+                    let block = blocks.last_mut().unwrap();
+                    block.arms.last_mut().unwrap().1.push(instr);
+                }
+
+                _ => {
+                    let (params, results) = match (params, stack_len(&effect.results, &blocks)) {
+                        (Some(params), Some(results)) => (params, results),
+                        _ => return Ok(None),
+                    };
+                    let block = blocks.last_mut().unwrap();
+                    let (_, instrs) = block.arms.last_mut().unwrap();
+                    let operands = fold_operands(instrs, params);
+                    let self_contained = operands.len() == params;
+                    let text = self.operator_text(&operator, nesting_start)?;
+                    let mut instr = Folded::new(text, operands, hint);
+                    instr.comments = comments;
+                    if self_contained {
+                        instr.results = Some(results);
+                    }
+                    let block = blocks.last_mut().unwrap();
+                    block.arms.last_mut().unwrap().1.push(instr);
                 }
-                self.print_operator(&operator, nesting_start)?;
             }
-            self.end_group();
+        }
+        if blocks.len() != 1 {
+            return Ok(None);
+        }
+        Ok(blocks.pop().unwrap().arms.pop().map(|(_, instrs)| instrs))
+    }
 
-            self.state.func += 1;
+    /// Returns the text of `op` as it's printed on its own.
+    fn operator_text(&mut self, op: &Operator<'_>, nesting_start: u32) -> Result<String> {
+        let start = self.result.len();
+        self.print_operator(op, nesting_start)?;
+        Ok(self.result.split_off(start))
+    }
+
+    fn print_folded(&mut self, instr: &Folded) {
+        if let Some(comments) = &instr.comments {
+            self.print_comments_before(comments.clone());
         }
-        Ok(())
+        if let Some(hint) = instr.hint {
+            let value = if hint { "\\01" } else { "\\00" };
+            self.result.push_str("(@metadata.code.branch_hint \"");
+            self.result.push_str(value);
+            self.result.push_str("\") ");
+        }
+        self.result.push('(');
+        self.result.push_str(&instr.text);
+        self.nesting += 1;
+        let mut empty = true;
+        for operand in instr.operands.iter() {
+            self.newline();
+            self.print_folded(operand);
+            empty = false;
+        }
+        for (arm, instrs) in instr.arms.iter().flatten() {
+            if arm.is_empty() {
+                for instr in instrs {
+                    self.newline();
+                    self.print_folded(instr);
+                    empty = false;
+                }
+                continue;
+            }
+            self.newline();
+            self.result.push('(');
+            self.result.push_str(arm);
+            self.nesting += 1;
+            for instr in instrs {
+                self.newline();
+                self.print_folded(instr);
+            }
+            self.nesting -= 1;
+            self.result.push(')');
+            empty = false;
+        }
+        self.nesting -= 1;
+        // The label of a block is in a line comment, so the closing paren of
+        // an empty block goes on its own line.
+        if empty && instr.arms.is_some() {
+            self.newline();
+        }
+        self.result.push(')');
     }
 
     fn newline(&mut self) {
@@ -732,6 +1509,22 @@ impl Printer {
                 write!(self.result, "  ;; label = @{}", cur_label)?;
             }
             Else => self.result.push_str("else"),
+            Let { ty, locals } => {
+                self.result.push_str("let");
+                self.print_blockty(ty)?;
+                if !locals.is_empty() {
+                    self.result.push_str(" (local");
+                    for local in locals.locals() {
+                        let (count, ty) = local?;
+                        for _ in 0..count {
+                            self.result.push(' ');
+                            self.print_valtype(ty)?;
+                        }
+                    }
+                    self.result.push(')');
+                }
+                write!(self.result, "  ;; label = @{}", cur_label)?;
+            }
             Try { ty } => {
                 self.result.push_str("try");
                 self.print_blockty(ty)?;
@@ -1060,6 +1853,11 @@ impl Printer {
             TableSize { table } => write!(self.result, "table.size {}", table)?,
             TableFill { table } => write!(self.result, "table.fill {}", table)?,
 
+            I64Add128 => self.result.push_str("i64.add128"),
+            I64Sub128 => self.result.push_str("i64.sub128"),
+            I64MulWideS => self.result.push_str("i64.mul_wide_s"),
+            I64MulWideU => self.result.push_str("i64.mul_wide_u"),
+
             MemoryAtomicNotify { memarg } => self.mem_instr("memory.atomic.notify", memarg, 4)?,
             MemoryAtomicWait32 { memarg } => self.mem_instr("memory.atomic.wait32", memarg, 4)?,
             MemoryAtomicWait64 { memarg } => self.mem_instr("memory.atomic.wait64", memarg, 8)?,
@@ -1483,6 +2281,7 @@ impl Printer {
     fn print_exports(&mut self, data: ExportSectionReader) -> Result<()> {
         for export in data {
             let export = export?;
+            self.print_item_comments(7);
             self.newline();
             self.start_group("export ");
             self.print_str(export.field)?;
@@ -1538,8 +2337,38 @@ impl Printer {
         Ok(())
     }
 
+    /// Prints the demangled form of the name of function `idx` as a comment,
+    /// if demangling is enabled and the name looks like a Rust or C++ symbol.
+    fn print_demangled_name(&mut self, idx: u32) -> Result<()> {
+        if !self.demangle {
+            return Ok(());
+        }
+        let demangled = match self.state.names.get(&idx).and_then(|n| demangle(&n.name)) {
+            Some(name) => name,
+            None => return Ok(()),
+        };
+        // Demangled names are printed inside of a block comment, so make sure
+        // they can't accidentally open or close one.
+        if demangled.contains("(;") || demangled.contains(";)") {
+            return Ok(());
+        }
+        write!(self.result, " (;{};)", demangled)?;
+        Ok(())
+    }
+
     fn print_local_idx(&mut self, func: u32, idx: u32) -> Result<()> {
-        match self.state.local_names.get(&func).and_then(|f| f.get(&idx)) {
+        // Locals declared by `let` blocks come before the function's own
+        // locals, and don't have names.
+        let shift = self
+            .state
+            .let_locals
+            .iter()
+            .fold(0u32, |sum, n| sum.saturating_add(*n));
+        let local_names = &self.state.local_names;
+        let name = idx
+            .checked_sub(shift)
+            .and_then(|idx| local_names.get(&func)?.get(&idx));
+        match name {
             Some(name) => write!(self.result, "${}", name.identifier())?,
             None => write!(self.result, "{}", idx)?,
         }
@@ -1549,6 +2378,10 @@ impl Printer {
     fn print_elems(&mut self, data: ElementSectionReader) -> Result<()> {
         for (i, elem) in data.into_iter().enumerate() {
             let mut elem = elem?;
+            if self.skip_item(9, Some(ItemKind::Elem), i as u32) {
+                continue;
+            }
+            self.print_item_comments(9);
             self.newline();
             self.start_group("elem");
             write!(self.result, " (;{};)", i)?;
@@ -1596,9 +2429,14 @@ impl Printer {
         Ok(())
     }
 
-    fn print_data(&mut self, data: DataSectionReader) -> Result<()> {
+    fn print_data(&mut self, data: DataSectionReader, sink: &mut Sink<'_>) -> Result<()> {
         for (i, data) in data.into_iter().enumerate() {
+            self.flush(sink)?;
             let data = data?;
+            if self.skip_item(11, Some(ItemKind::Data), i as u32) {
+                continue;
+            }
+            self.print_item_comments(11);
             self.newline();
             self.start_group("data");
             write!(self.result, " (;{};) ", i)?;
@@ -1624,6 +2462,7 @@ impl Printer {
     fn print_instances(&mut self, instances: InstanceSectionReader) -> Result<()> {
         for instance in instances.into_iter() {
             let instance = instance?;
+            self.print_item_comments(15);
             self.newline();
             self.start_group("instance");
             write!(self.result, " (;{};)", self.state.instance)?;
@@ -1647,6 +2486,7 @@ impl Printer {
     fn print_aliases(&mut self, aliases: AliasSectionReader) -> Result<()> {
         for alias in aliases {
             let alias = alias?;
+            self.print_item_comments(16);
             self.newline();
             self.start_group("alias ");
             match alias {
@@ -1851,6 +2691,15 @@ impl NamedLocalPrinter {
 macro_rules! print_float {
     ($name:ident $float:ident $uint:ident $sint:ident $exp_bits:tt) => {
         fn $name(&mut self, mut bits: $uint) -> Result<()> {
+            let f = $float::from_bits(bits);
+            if self.float_style == FloatStyle::Decimal && f.is_finite() {
+                // The `Debug` format is the shortest one which round-trips,
+                // and always looks like a float.
+                write!(self.result, "{:?}", f)?;
+                return Ok(());
+            }
+            let raw = bits;
+
             // Calculate a few constants
             let int_width = mem::size_of::<$uint>() * 8;
             let exp_width = $exp_bits;
@@ -1860,23 +2709,22 @@ macro_rules! print_float {
             let min_exp = -max_exp + 1;
 
             // Handle `NaN` and infinity specially
-            let f = $float::from_bits(bits);
             if bits >> (int_width - 1) != 0 {
                 bits ^= 1 << (int_width - 1);
                 self.result.push_str("-");
             }
             if f.is_infinite() {
-                write!(self.result, "inf (;={};)", f)?;
-                return Ok(());
+                self.result.push_str("inf");
+                return self.print_float_comment(f, raw);
             }
             if f.is_nan() {
                 let payload = bits & ((1 << mantissa_width) - 1);
                 if payload == 1 << (mantissa_width - 1) {
-                    write!(self.result, "nan (;={};)", f)?;
+                    self.result.push_str("nan");
                 } else {
-                    write!(self.result, "nan:{:#x} (;={};)", payload, f)?;
+                    write!(self.result, "nan:{:#x}", payload)?;
                 }
-                return Ok(());
+                return self.print_float_comment(f, raw);
             }
 
             // Figure out our exponent, but keep in mine that it's in an
@@ -1926,8 +2774,7 @@ macro_rules! print_float {
                 }
                 write!(self.result, "p{:+}", exponent)?;
             }
-            write!(self.result, " (;={};)", f)?;
-            Ok(())
+            self.print_float_comment(f, raw)
         }
     };
 }
@@ -1935,10 +2782,131 @@ macro_rules! print_float {
 impl Printer {
     print_float!(print_f32 f32 u32 i32 8);
     print_float!(print_f64 f64 u64 i64 11);
+
+    fn print_float_comment(
+        &mut self,
+        f: impl std::fmt::Display,
+        bits: impl std::fmt::LowerHex,
+    ) -> Result<()> {
+        match self.float_style {
+            FloatStyle::Hex => write!(self.result, " (;={};)", f)?,
+            FloatStyle::Bits => write!(self.result, " (;={:#x};)", bits)?,
+            FloatStyle::Decimal => {}
+        }
+        Ok(())
+    }
+}
+
+/// An instruction printed in the folded form, along with the instructions
+/// folded into it.
+struct Folded {
+    comments: Option<Vec<String>>,
+    hint: Option<bool>,
+    // The text of the instruction as it's printed on its own.
+    text: String,
+    // The instructions computing the operands of this one.
+    operands: Vec<Folded>,
+    // The keyword and instructions of each arm of a block, such as `then`
+    // and `else` for an `if`. Blocks with a single body have no keyword.
+    arms: Option<Vec<(String, Vec<Folded>)>>,
+    // The number of values pushed by this instruction if it doesn't pop any
+    // values pushed before it, which is when it can be an operand.
+    results: Option<usize>,
+    // How deeply instructions are folded into this one.
+    depth: u32,
+}
+
+impl Folded {
+    fn new(text: String, operands: Vec<Folded>, hint: Option<&BranchHint>) -> Folded {
+        Folded {
+            comments: None,
+            hint: hint.map(|h| h.taken),
+            text,
+            depth: operands.iter().map(|o| o.depth + 1).max().unwrap_or(0),
+            operands,
+            arms: None,
+            results: None,
+        }
+    }
+}
+
+/// A block whose instructions are being folded.
+struct FoldedBlock {
+    instr: Folded,
+    arms: Vec<(String, Vec<Folded>)>,
+    // The number of values carried by a branch to this block.
+    label_arity: usize,
+    results: Option<usize>,
+}
+
+/// Returns the number of values in `types`, which are operands of an
+/// instruction in the innermost of `blocks`.
+fn stack_len(types: &[StackType], blocks: &[FoldedBlock]) -> Option<usize> {
+    let mut len = 0;
+    for ty in types {
+        len += match *ty {
+            StackType::Label(depth) => {
+                let i = blocks.len().checked_sub(1 + depth as usize)?;
+                blocks[i].label_arity
+            }
+            StackType::Return => blocks[0].label_arity,
+            StackType::BlockParams | StackType::BlockResults => return None,
+            _ => 1,
+        };
+    }
+    Some(len)
+}
+
+/// Removes and returns the last `n` of `instrs` if they can be folded as the
+/// operands of the next instruction, which is when each of them pushes a
+/// single value without popping any.
+fn fold_operands(instrs: &mut Vec<Folded>, n: usize) -> Vec<Folded> {
+    let start = match instrs.len().checked_sub(n) {
+        Some(start) => start,
+        None => return Vec::new(),
+    };
+    let foldable = instrs[start..]
+        .iter()
+        .all(|i| i.results == Some(1) && i.depth < MAX_FOLDED_DEPTH);
+    if n == 0 || !foldable {
+        return Vec::new();
+    }
+    instrs.split_off(start)
+}
+
+/// Attempts to demangle `name` as a Rust symbol and then as a C++ symbol,
+/// returning `None` if it's neither.
+///
+/// This is how function names are demangled when [`Printer::demangle`] is
+/// enabled.
+pub fn demangle(name: &str) -> Option<String> {
+    if let Ok(sym) = rustc_demangle::try_demangle(name) {
+        // The alternate format omits the trailing hash of legacy symbols.
+        Some(format!("{:#}", sym))
+    } else if name.starts_with("_Z") {
+        let sym = cpp_demangle::Symbol::new(name).ok()?;
+        sym.demangle(&cpp_demangle::DemangleOptions::default()).ok()
+    } else {
+        None
+    }
 }
 
 impl Naming {
-    fn new(name: &str, used: &mut HashSet<String>) -> Naming {
+    fn new(name: &str, used: &mut HashSet<String>, quoted_ids: bool) -> Naming {
+        if quoted_ids
+            && !name.is_empty()
+            && !name.chars().all(is_idchar)
+            && used.insert(name.to_string())
+        {
+            let mut identifier = String::from("\"");
+            escape_str(name, &mut identifier);
+            identifier.push('"');
+            return Naming {
+                identifier: Some(identifier),
+                name: name.to_string(),
+                quoted: true,
+            };
+        }
         let identifier = if name.len() > 0 && name.chars().all(is_idchar) && !used.contains(name) {
             used.insert(name.to_string());
             None
@@ -1963,6 +2931,7 @@ impl Naming {
         return Naming {
             identifier,
             name: name.to_string(),
+            quoted: false,
         };
 
         // See https://webassembly.github.io/spec/core/text/values.html#text-id
@@ -2008,28 +2977,16 @@ impl Naming {
 
     fn write(&self, dst: &mut String) {
         match &self.identifier {
+            Some(quoted) if self.quoted => {
+                dst.push('$');
+                dst.push_str(quoted);
+            }
             Some(alternate) => {
                 assert!(*alternate != self.name);
                 dst.push_str("$");
                 dst.push_str(&alternate);
                 dst.push_str(" (@name \"");
-                // https://webassembly.github.io/spec/core/text/values.html#text-string
-                for c in self.name.chars() {
-                    match c {
-                        '\t' => dst.push_str("\\t"),
-                        '\n' => dst.push_str("\\n"),
-                        '\r' => dst.push_str("\\r"),
-                        '"' => dst.push_str("\\\""),
-                        '\'' => dst.push_str("\\'"),
-                        '\\' => dst.push_str("\\\\"),
-                        c if (c as u32) < 0x20 || c as u32 == 0x7f => {
-                            dst.push_str("\\u{");
-                            write!(dst, "{:x}", c as u32).unwrap();
-                            dst.push_str("}");
-                        }
-                        other => dst.push(other),
-                    }
-                }
+                escape_str(&self.name, dst);
                 dst.push_str("\")");
             }
             None => {
@@ -2039,3 +2996,24 @@ impl Naming {
         }
     }
 }
+
+/// Appends `name` to `dst` with the escapes needed in a string literal.
+fn escape_str(name: &str, dst: &mut String) {
+    // https://webassembly.github.io/spec/core/text/values.html#text-string
+    for c in name.chars() {
+        match c {
+            '\t' => dst.push_str("\\t"),
+            '\n' => dst.push_str("\\n"),
+            '\r' => dst.push_str("\\r"),
+            '"' => dst.push_str("\\\""),
+            '\'' => dst.push_str("\\'"),
+            '\\' => dst.push_str("\\\\"),
+            c if (c as u32) < 0x20 || c as u32 == 0x7f => {
+                dst.push_str("\\u{");
+                write!(dst, "{:x}", c as u32).unwrap();
+                dst.push_str("}");
+            }
+            other => dst.push(other),
+        }
+    }
+}
@@ -113,3 +113,556 @@ fn no_panic_dangling_else() {
     .unwrap();
     wasmprinter::print_bytes(&bytes).unwrap();
 }
+
+#[test]
+fn demangle_names() {
+    let bytes = wat::parse_str(
+        r#"
+            (module
+                (import "" "" (func $_ZN3foo3bar17h0123456789abcdefE))
+                (func $_Z3bazi (param i32))
+                (func $plain)
+            )
+        "#,
+    )
+    .unwrap();
+    let text = wasmprinter::print_bytes(&bytes).unwrap();
+    assert!(
+        text.contains("$_ZN3foo3bar17h0123456789abcdefE (;foo::bar;)"),
+        "{}",
+        text
+    );
+    assert!(text.contains("$_Z3bazi (;baz(int);)"), "{}", text);
+    assert!(text.contains("$plain (type"), "{}", text);
+
+    let mut printer = wasmprinter::Printer::new();
+    printer.demangle(false);
+    let text = printer.print(&bytes).unwrap();
+    assert!(!text.contains("foo::bar"), "{}", text);
+    assert!(!text.contains("baz(int)"), "{}", text);
+
+    // The default printer is the same as a new one.
+    let text = wasmprinter::Printer::default().print(&bytes).unwrap();
+    assert!(text.contains("$_Z3bazi (;baz(int);)"), "{}", text);
+
+    assert_eq!(
+        wasmprinter::demangle("_ZN3foo3bar17h0123456789abcdefE").as_deref(),
+        Some("foo::bar")
+    );
+    assert_eq!(wasmprinter::demangle("plain"), None);
+}
+
+#[test]
+fn quoted_ids() {
+    let bytes = wat::parse_str(
+        r#"
+            (module
+                (func $"hello world" (param $"\t" i32)
+                    call $"hello world")
+                (func $plain)
+            )
+        "#,
+    )
+    .unwrap();
+    let text = wasmprinter::print_bytes(&bytes).unwrap();
+    assert!(
+        text.contains(r#"$hello_world (@name "hello world")"#),
+        "{}",
+        text
+    );
+
+    let mut printer = wasmprinter::Printer::new();
+    printer.quoted_ids(true);
+    let text = printer.print(&bytes).unwrap();
+    assert!(text.contains(r#"(func $"hello world" (type"#), "{}", text);
+    assert!(text.contains(r#"(param $"\t" i32)"#), "{}", text);
+    assert!(text.contains(r#"call $"hello world""#), "{}", text);
+    assert!(text.contains("$plain (type"), "{}", text);
+    // The output assembles back to the same module.
+    assert_eq!(wat::parse_str(&text).unwrap(), bytes);
+}
+
+#[test]
+fn float_styles() {
+    let bytes = wat::parse_str(
+        r#"
+            (module
+                (func
+                    f32.const 3
+                    f32.const -0x0p+0
+                    f32.const 0.1
+                    f32.const 0x1.fffffep+127
+                    f32.const -inf
+                    f32.const nan:0x200001
+                    f64.const 1e-7
+                    f64.const 0x0.0000000000001p-1022
+                    f64.const -nan
+                    drop drop drop drop drop drop drop drop drop)
+            )
+        "#,
+    )
+    .unwrap();
+
+    let text = wasmprinter::print_bytes(&bytes).unwrap();
+    assert!(text.contains("f32.const 0x1.8p+1 (;=3;)"), "{}", text);
+
+    let mut printer = wasmprinter::Printer::new();
+    printer.float_style(wasmprinter::FloatStyle::Decimal);
+    let decimal = printer.print(&bytes).unwrap();
+    for expected in &[
+        "f32.const 3.0\n",
+        "f32.const -0.0\n",
+        "f32.const 0.1\n",
+        "f32.const 3.4028235e38\n",
+        "f32.const -inf\n",
+        "f32.const nan:0x200001\n",
+        "f64.const 1e-7\n",
+        "f64.const 5e-324\n",
+        "f64.const -nan\n",
+    ] {
+        assert!(decimal.contains(expected), "{}", decimal);
+    }
+
+    printer.float_style(wasmprinter::FloatStyle::Bits);
+    let bits = printer.print(&bytes).unwrap();
+    assert!(
+        bits.contains("f32.const 0x1.8p+1 (;=0x40400000;)"),
+        "{}",
+        bits
+    );
+    assert!(
+        bits.contains("f32.const -0x0p+0 (;=0x80000000;)"),
+        "{}",
+        bits
+    );
+    assert!(
+        bits.contains("f64.const -nan (;=0xfff8000000000000;)"),
+        "{}",
+        bits
+    );
+
+    // Every style assembles back to the same bits.
+    for text in &[text, decimal, bits] {
+        assert_eq!(wat::parse_str(text).unwrap(), bytes, "{}", text);
+    }
+}
+
+#[test]
+fn fold_instructions() {
+    let bytes = wat::parse_str(
+        r#"
+            (module
+                (func $fac (param i32) (result i32)
+                    local.get 0
+                    i32.eqz
+                    if (result i32)
+                        i32.const 1
+                    else
+                        local.get 0
+                        local.get 0
+                        i32.const 1
+                        i32.sub
+                        call $fac
+                        i32.mul
+                    end)
+                (func $g (param i32)
+                    block
+                        local.get 0
+                        br_if 0
+                        ;; The operands of `i32.add` aren't the last
+                        ;; instructions, so it's left unfolded.
+                        i32.const 1
+                        local.get 0
+                        call $g
+                        i32.const 3
+                        i32.add
+                        drop
+                    end
+                    loop
+                    end)
+            )
+        "#,
+    )
+    .unwrap();
+    let mut printer = wasmprinter::Printer::new();
+    printer.fold_instructions(true);
+    let text = printer.print(&bytes).unwrap();
+    for expected in &[
+        "    (if (result i32)  ;; label = @1
+      (i32.eqz
+        (local.get 0))
+      (then
+        (i32.const 1))
+      (else
+        (i32.mul
+          (local.get 0)
+          (call $fac
+            (i32.sub
+              (local.get 0)
+              (i32.const 1)))))))",
+        "      (br_if 0 (;@1;)
+        (local.get 0))
+      (i32.const 1)
+      (call $g
+        (local.get 0))
+      (i32.const 3)
+      (i32.add)
+      (drop))",
+        "    (loop  ;; label = @1
+    ))",
+    ] {
+        assert!(text.contains(expected), "{}", text);
+    }
+    assert_eq!(wat::parse_str(&text).unwrap(), bytes);
+}
+
+#[test]
+fn skeleton() {
+    let bytes = wat::parse_str(
+        r#"
+            (module
+                (import "env" "log" (func $log (param i32)))
+                (memory 1)
+                (func $run (export "run") (param i32) (result i32) (local i64)
+                    local.get 0
+                    call $log
+                    i32.const 1)
+                (func $nop nop)
+                (func $empty)
+            )
+        "#,
+    )
+    .unwrap();
+    let mut printer = wasmprinter::Printer::new();
+    printer.skeleton(true);
+    let text = printer.print(&bytes).unwrap();
+    for expected in &[
+        "(import \"env\" \"log\" (func $log (type 0)))",
+        "(func $run (type 1) (param i32) (result i32) (; 3 instructions ;))",
+        "(func $nop (type 2) (; 1 instruction ;))",
+        "(func $empty (type 2) (; 0 instructions ;))",
+        "(memory (;0;) 1)",
+        "(export \"run\" (func $run))",
+    ] {
+        assert!(text.contains(expected), "{}", text);
+    }
+    assert!(!text.contains("local"), "{}", text);
+}
+
+#[test]
+fn print_to() {
+    let bytes = wat::parse_str(
+        r#"
+            (module $m
+                (memory 1)
+                (func $f (result i32) i32.const 1)
+                (func $g (param i32) local.get 0 drop)
+                (module $inner
+                    (func $h))
+                (data (i32.const 0) "a")
+                (data (i32.const 8) "b")
+            )
+        "#,
+    )
+    .unwrap();
+    let expected = wasmprinter::print_bytes(&bytes).unwrap();
+
+    // The text is written out piece by piece rather than all at the end.
+    let mut writes = Vec::new();
+    struct Recorder<'a>(&'a mut Vec<String>);
+    impl std::io::Write for Recorder<'_> {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.push(String::from_utf8(buf.to_vec()).unwrap());
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    wasmprinter::Printer::new()
+        .print_to(&bytes, Recorder(&mut writes))
+        .unwrap();
+    assert!(writes.len() > 5, "{:?}", writes);
+    assert_eq!(writes.concat(), expected);
+
+    let mut text = String::new();
+    wasmprinter::Printer::new()
+        .print_to_fmt(&bytes, &mut text)
+        .unwrap();
+    assert_eq!(text, expected);
+}
+
+#[test]
+fn symbol_map() {
+    let symbols = vec![(0, "log".to_string()), (1, "main".to_string())];
+    let print = |wat: &str| {
+        let bytes = wat::parse_str(wat).unwrap();
+        let mut printer = wasmprinter::Printer::new();
+        printer.symbol_map(symbols.clone());
+        printer.print(&bytes).unwrap()
+    };
+
+    let text = print(
+        r#"
+            (module
+                (import "env" "f" (func (param i32)))
+                (func i32.const 1 call 0)
+                (module (func)))
+        "#,
+    );
+    assert!(text.contains("(func $log (type 0)"), "{}", text);
+    assert!(text.contains("(func $main (type 1)"), "{}", text);
+    assert!(text.contains("call $log"), "{}", text);
+    // Nested modules don't use the symbol map.
+    assert!(text.contains("(func (;0;) (type 0))"), "{}", text);
+
+    // Names from the name section take precedence.
+    let text = print("(module (func $f) (func))");
+    assert!(text.contains("(func $f (type 0))"), "{}", text);
+    assert!(text.contains("(func (;1;) (type 0))"), "{}", text);
+}
+
+#[test]
+fn print_item() {
+    use wasmprinter::ItemKind;
+
+    let bytes = wat::parse_str(
+        r#"
+            (module
+                (import "env" "log" (func $log (param i32)))
+                (import "env" "g" (global i32))
+                (global $counter (mut i32) (i32.const 0))
+                (memory 1)
+                (table 1 funcref)
+                (func $main (export "main")
+                    global.get $counter
+                    call $log)
+                (func $helper)
+                (elem (i32.const 0) $helper)
+                (data (i32.const 0) "a")
+                (data (i32.const 8) "b")
+            )
+        "#,
+    )
+    .unwrap();
+    let print = |kind, item| wasmprinter::Printer::new().print_item(&bytes, kind, item);
+
+    let main = "(func $main (type 1)\n  global.get 1\n  call $log)";
+    assert_eq!(print(ItemKind::Func, "$main").unwrap(), main);
+    assert_eq!(print(ItemKind::Func, "main").unwrap(), main);
+    assert_eq!(print(ItemKind::Func, "1").unwrap(), main);
+    assert_eq!(
+        print(ItemKind::Func, "0").unwrap(),
+        "(import \"env\" \"log\" (func $log (type 0)))"
+    );
+    assert_eq!(
+        print(ItemKind::Global, "1").unwrap(),
+        "(global (;1;) (mut i32) (i32.const 0))"
+    );
+    assert_eq!(
+        print(ItemKind::Elem, "0").unwrap(),
+        "(elem (;0;) (i32.const 0) func $helper)"
+    );
+    assert_eq!(
+        print(ItemKind::Data, "1").unwrap(),
+        "(data (;1;) (i32.const 8) \"b\")"
+    );
+
+    let err = print(ItemKind::Func, "$missing").unwrap_err();
+    assert_eq!(err.to_string(), "no function named `$missing`");
+    let err = print(ItemKind::Data, "2").unwrap_err();
+    assert_eq!(err.to_string(), "module has no data segment `2`");
+}
+
+#[test]
+fn branch_hints() {
+    let bytes = wat::parse_str(
+        r#"
+            (module
+                (import "" "" (func))
+                (func (param i32) (local i64)
+                    (block
+                        (@metadata.code.branch_hint "\01")
+                        (br_if 0 (local.get 0))
+                        local.get 0
+                        (@metadata.code.branch_hint "\00")
+                        if
+                            nop
+                        end
+                    )
+                )
+            )
+        "#,
+    )
+    .unwrap();
+    let mut found = false;
+    for payload in wasmparser::Parser::new(0).parse_all(&bytes) {
+        match payload.unwrap() {
+            wasmparser::Payload::CustomSection {
+                name: "metadata.code.branch_hint",
+                data,
+                data_offset,
+            } => {
+                let reader = wasmparser::BranchHintSectionReader::new(data, data_offset).unwrap();
+                let funcs = reader.into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+                assert_eq!(funcs.len(), 1);
+                assert_eq!(funcs[0].func, 1);
+                // The local declarations take 3 bytes, `block` 2 and
+                // `local.get` 2 more.
+                let hints = funcs[0]
+                    .hints
+                    .iter()
+                    .map(|h| (h.func_offset, h.taken))
+                    .collect::<Vec<_>>();
+                assert_eq!(hints, [(7, true), (11, false)]);
+                found = true;
+            }
+            wasmparser::Payload::CodeSectionStart { .. } => assert!(found),
+            _ => {}
+        }
+    }
+    assert!(found);
+
+    let text = wasmprinter::print_bytes(&bytes).unwrap();
+    assert!(
+        text.contains("(@metadata.code.branch_hint \"\\01\") br_if 0"),
+        "{}",
+        text
+    );
+    assert!(
+        text.contains("(@metadata.code.branch_hint \"\\00\") if"),
+        "{}",
+        text
+    );
+    assert_eq!(wat::parse_str(&text).unwrap(), bytes);
+}
+
+#[test]
+fn let_locals() {
+    let bytes = wat::parse_str(
+        r#"
+            (module
+                (func (param $p i32) (result i64) (local $x i64)
+                    local.get $p
+                    i64.const 3
+                    let (result i64) (local $a i32) (local $b i64)
+                        local.get $b
+                        local.get $x
+                        i64.add
+                        local.get $a
+                        local.get $p
+                        i32.add
+                        i64.extend_i32_u
+                        i64.add
+                    end
+                )
+            )
+        "#,
+    )
+    .unwrap();
+    let mut validator = wasmparser::Validator::new();
+    validator.wasm_features(wasmparser::WasmFeatures {
+        function_references: true,
+        ..wasmparser::WasmFeatures::default()
+    });
+    validator.validate_all(&bytes).unwrap();
+
+    // The locals of the `let` come first, so the function's own locals are
+    // shifted past them but keep their names.
+    let text = wasmprinter::print_bytes(&bytes).unwrap();
+    let body = text
+        .lines()
+        .skip_while(|line| !line.contains("let"))
+        .map(|line| line.trim().trim_end_matches(')'))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        body,
+        [
+            "let (result i64) (local i32 i64)  ;; label = @1",
+            "local.get 1",
+            "local.get $x",
+            "i64.add",
+            "local.get 0",
+            "local.get $p",
+            "i32.add",
+            "i64.extend_i32_u",
+            "i64.add",
+            "end",
+        ]
+    );
+    assert_eq!(wat::parse_str(&text).unwrap(), bytes);
+}
+
+#[test]
+fn comments() {
+    let wat = r#";; before
+(module
+  ;; imported
+  (import "" "" (func))
+  (func $f (param i32) (local i64)
+    ;; first
+    local.get 0
+    (; in a
+       block ;)
+    if
+      ;; nested
+      nop
+    end)
+  ;; last
+)
+"#;
+    let buf = wast::parser::ParseBuffer::new(wat).unwrap();
+    let mut module = wast::parser::parse::<wast::Wat>(&buf).unwrap().module;
+    let bytes = module.encode_with_comments(wat).unwrap();
+
+    let mut comments = Vec::new();
+    for payload in wasmparser::Parser::new(0).parse_all(&bytes) {
+        if let wasmparser::Payload::CustomSection {
+            name: "metadata.comments",
+            data,
+            data_offset,
+        } = payload.unwrap()
+        {
+            let reader = wasmparser::CommentSectionReader::new(data, data_offset).unwrap();
+            for comment in reader {
+                let comment = comment.unwrap();
+                comments.push((comment.anchor, comment.text));
+            }
+        }
+    }
+    use wasmparser::CommentAnchor::*;
+    assert_eq!(
+        comments,
+        [
+            (Module, ";; before"),
+            (
+                Item {
+                    section: 2,
+                    index: 0
+                },
+                ";; imported"
+            ),
+            // The local declarations take 3 bytes and `local.get` 2 more.
+            (Instruction { func: 1, offset: 3 }, ";; first"),
+            (
+                Instruction { func: 1, offset: 5 },
+                "(; in a\n       block ;)"
+            ),
+            (Instruction { func: 1, offset: 7 }, ";; nested"),
+            (End, ";; last"),
+        ]
+    );
+
+    let text = wasmprinter::print_bytes(&bytes).unwrap();
+    for expected in &[
+        ";; before\n(module",
+        "  ;; imported\n  (import",
+        "    ;; first\n    local.get 0",
+        "    (; in a\n       block ;)\n    if",
+        "      ;; nested\n      nop",
+        "  ;; last\n)",
+    ] {
+        assert!(text.contains(expected), "{} not in {}", expected, text);
+    }
+    assert_eq!(wat::parse_str(&text).unwrap(), wat::parse_str(wat).unwrap());
+}
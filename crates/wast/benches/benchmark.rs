@@ -0,0 +1,295 @@
+//! Benchmarks of each stage of turning text into a binary: lexing, parsing,
+//! resolving names and encoding.
+//!
+//! Each stage is measured on its own, with the stages before it done ahead of
+//! time, and reported as throughput in bytes of text so they can be compared
+//! with each other and across inputs.
+
+#[macro_use]
+extern crate criterion;
+
+use criterion::{Criterion, Throughput};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use wast::lexer::Lexer;
+use wast::parser::{self, ParseBuffer};
+use wast::{Module, Wast, WastDirective};
+
+/// Collects the text of every `.wat` and `.wast` file under `path` which
+/// parses, resolves and encodes successfully.
+fn collect_test_files(path: &Path, list: &mut Vec<String>) {
+    let entries = match path.read_dir() {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect_test_files(&path, list);
+            continue;
+        }
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("wat") | Some("wast") => {}
+            _ => continue,
+        }
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(_) => continue,
+        };
+        let encodes = |text: &str| -> Result<(), wast::Error> {
+            let buf = ParseBuffer::new(text)?;
+            for mut module in modules(parser::parse(&buf)?) {
+                module.encode()?;
+            }
+            Ok(())
+        };
+        if encodes(&text).is_ok() {
+            list.push(text);
+        }
+    }
+}
+
+/// Returns the modules defined at the top level of `wast`.
+fn modules(wast: Wast<'_>) -> Vec<Module<'_>> {
+    wast.directives
+        .into_iter()
+        .filter_map(|directive| match directive {
+            WastDirective::Module(module) => Some(module),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the text of a module resembling the output of Emscripten which
+/// was converted to the text format, with `n` functions which use the stack
+/// pointer, linear memory and calls.
+fn emscripten_module(n: usize) -> String {
+    let mut wat = String::from(
+        r#"(module
+  (type $t0 (func (param i32 i32) (result i32)))
+  (import "env" "memory" (memory $env.memory 256 256))
+  (import "env" "abort" (func $env.abort (param i32)))
+  (global $__stack_pointer (mut i32) (i32.const 5242880))
+  (table $T0 1024 funcref)
+"#,
+    );
+    for i in 0..n {
+        let callee = (i * 7 + 3) % n;
+        write!(
+            wat,
+            r#"  (func $f{i} (type $t0) (param $p0 i32) (param $p1 i32) (result i32)
+    (local $l2 i32) (local $l3 i32) (local $l4 i64)
+    global.get $__stack_pointer
+    i32.const 32
+    i32.sub
+    local.tee $l2
+    global.set $__stack_pointer
+    block $B0
+      loop $L1
+        local.get $p0
+        i32.load offset=8
+        local.tee $l3
+        i32.eqz
+        br_if $B0
+        local.get $l2
+        local.get $p1
+        i64.load offset=16 align=4
+        local.tee $l4
+        i64.const 4294967295
+        i64.and
+        i64.store offset=24
+        local.get $l3
+        local.get $p1
+        call $f{callee}
+        local.set $p1
+        local.get $p0
+        local.get $l3
+        i32.const -1
+        i32.add
+        i32.store offset=8
+        local.get $p1
+        i32.const 1
+        i32.and
+        br_table $L1 $B0
+      end
+    end
+    local.get $l2
+    i32.const 32
+    i32.add
+    global.set $__stack_pointer
+    local.get $p1)
+"#,
+            i = i,
+            callee = callee
+        )
+        .unwrap();
+    }
+    wat.push_str("  (elem $e0 (i32.const 1) func");
+    for i in 0..n.min(1000) {
+        write!(wat, " $f{}", i).unwrap();
+    }
+    wat.push_str(")\n  (export \"main\" (func $f0))\n");
+    wat.push_str("  (data $d0 (i32.const 1024) \"");
+    for i in 0..n {
+        write!(wat, "\\{:02x}", i % 256).unwrap();
+    }
+    wat.push_str("\"))\n");
+    wat
+}
+
+/// Returns the text of a module with `n` functions which mostly execute SIMD
+/// instructions.
+fn simd_module(n: usize) -> String {
+    let mut wat = String::from("(module\n  (memory 1)\n");
+    for i in 0..n {
+        write!(
+            wat,
+            r#"  (func $f{i} (param $p i32) (result i32)
+    (local $v v128)
+    local.get $p
+    v128.load offset=16
+    v128.const i32x4 0 1 2 {i}
+    i32x4.add
+    local.get $p
+    v128.load offset=32 align=8
+    i8x16.shuffle 0 17 2 19 4 21 6 23 8 25 10 27 12 29 14 31
+    local.set $v
+    local.get $v
+    f32x4.convert_i32x4_s
+    v128.const f32x4 1.5 -2.25 0x1p-3 inf
+    f32x4.mul
+    local.get $v
+    local.get $v
+    i16x8.mul
+    v128.bitselect
+    local.set $v
+    local.get $p
+    local.get $v
+    i32x4.splat
+    drop
+    local.get $v
+    v128.store offset=48
+    local.get $v
+    i16x8.extract_lane_s 3
+    local.get $v
+    i32x4.extract_lane 1
+    i32.add)
+"#,
+            i = i
+        )
+        .unwrap();
+    }
+    wat.push_str(")\n");
+    wat
+}
+
+fn stages_benchmark(c: &mut Criterion) {
+    let mut tests = Vec::new();
+    collect_test_files("../../tests".as_ref(), &mut tests);
+    collect_test_files("../../testsuite".as_ref(), &mut tests);
+    let inputs = [
+        ("tests", tests),
+        ("emscripten", vec![emscripten_module(2_000)]),
+        ("simd", vec![simd_module(2_000)]),
+    ];
+
+    for (name, texts) in inputs.iter() {
+        let texts = texts.iter().map(|t| t.as_str()).collect::<Vec<_>>();
+        let bytes = texts.iter().map(|t| t.len() as u64).sum();
+        let mut group = c.benchmark_group(*name);
+        group.throughput(Throughput::Bytes(bytes));
+        group.sample_size(10);
+
+        group.bench_function("lex", |b| {
+            b.iter(|| {
+                for text in texts.iter() {
+                    for token in Lexer::new(text) {
+                        token.unwrap();
+                    }
+                }
+            })
+        });
+        group.bench_function("parse", |b| {
+            b.iter_custom(|iters| {
+                time_stage(
+                    iters,
+                    || lex(&texts),
+                    |bufs| {
+                        let start = Instant::now();
+                        for buf in bufs.iter() {
+                            parser::parse::<Wast>(buf).unwrap();
+                        }
+                        start.elapsed()
+                    },
+                )
+            })
+        });
+        group.bench_function("resolve", |b| {
+            b.iter_custom(|iters| {
+                time_stage(
+                    iters,
+                    || lex(&texts),
+                    |bufs| {
+                        let mut modules = parse(bufs);
+                        let start = Instant::now();
+                        for module in modules.iter_mut() {
+                            module.resolve().unwrap();
+                        }
+                        start.elapsed()
+                    },
+                )
+            })
+        });
+        group.bench_function("encode", |b| {
+            b.iter_custom(|iters| {
+                time_stage(
+                    iters,
+                    || lex(&texts),
+                    |bufs| {
+                        let mut modules = parse(bufs);
+                        for module in modules.iter_mut() {
+                            module.resolve().unwrap();
+                        }
+                        // Encoding resolves names again, which finds nothing
+                        // left to do.
+                        let start = Instant::now();
+                        for module in modules.iter_mut() {
+                            module.encode().unwrap();
+                        }
+                        start.elapsed()
+                    },
+                )
+            })
+        });
+        group.finish();
+    }
+}
+
+fn lex<'a>(texts: &[&'a str]) -> Vec<ParseBuffer<'a>> {
+    texts.iter().map(|t| ParseBuffer::new(t).unwrap()).collect()
+}
+
+fn parse<'a>(bufs: &'a [ParseBuffer<'a>]) -> Vec<Module<'a>> {
+    bufs.iter()
+        .flat_map(|buf| modules(parser::parse(buf).unwrap()))
+        .collect()
+}
+
+/// Returns the total time measured by `iters` runs of `stage`, each given the
+/// output of `setup`, which isn't timed.
+fn time_stage<S>(
+    iters: u64,
+    setup: impl Fn() -> S,
+    mut stage: impl FnMut(&S) -> Duration,
+) -> Duration {
+    let mut total = Duration::from_secs(0);
+    for _ in 0..iters {
+        total += stage(&setup());
+    }
+    total
+}
+
+criterion_group!(benchmark, stages_benchmark);
+criterion_main!(benchmark);
@@ -3,6 +3,7 @@ use crate::parser::{Parse, Parser, Result};
 
 /// An `alias` statement used to juggle indices with nested modules.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Alias<'a> {
     /// Where this `alias` was defined.
     pub span: ast::Span,
@@ -16,6 +17,7 @@ pub struct Alias<'a> {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[allow(missing_docs)]
 pub enum AliasKind<'a> {
     InstanceExport {
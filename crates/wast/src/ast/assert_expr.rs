@@ -1,4 +1,4 @@
-use crate::ast::{kw, Float32, Float64, Index, HeapType};
+use crate::ast::{kw, Float32, Float64, HeapType, Index};
 use crate::parser::{Parse, Parser, Result};
 
 /// An expression that is valid inside an `assert_return` directive.
@@ -9,6 +9,7 @@ use crate::parser::{Parse, Parser, Result};
 /// - only constant values (e.g. `i32.const 4`) are used in the `assert_return` directive
 /// - the NaN patterns are allowed (they are not allowed in regular `Expression`s).
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[allow(missing_docs)]
 pub enum AssertExpression<'a> {
     I32(i32),
@@ -50,6 +51,7 @@ impl<'a> Parse<'a> for AssertExpression<'a> {
 
 /// Either a NaN pattern (`nan:canonical`, `nan:arithmetic`) or a value of type `T`.
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[allow(missing_docs)]
 pub enum NanPattern<T> {
     CanonicalNan,
@@ -75,11 +77,37 @@ where
     }
 }
 
+macro_rules! nan_pattern_matches {
+    ($($float:ident($int:ident))*) => ($(
+        impl NanPattern<$float> {
+            /// Returns whether a result, given as its bits, matches this
+            /// pattern.
+            ///
+            /// Values are compared by their bits, so a NaN value only matches
+            /// a NaN with the same sign and payload.
+            pub fn matches(&self, bits: $int) -> bool {
+                let result = $float { bits };
+                match self {
+                    NanPattern::CanonicalNan => result.is_canonical_nan(),
+                    NanPattern::ArithmeticNan => result.is_arithmetic_nan(),
+                    NanPattern::Value(expected) => *expected == result,
+                }
+            }
+        }
+    )*)
+}
+
+nan_pattern_matches! {
+    Float32(u32)
+    Float64(u64)
+}
+
 /// A version of `V128Const` that allows `NanPattern`s.
 ///
 /// This implementation is necessary because only float types can include NaN patterns; otherwise
 /// it is largely similar to the implementation of `V128Const`.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[allow(missing_docs)]
 pub enum V128Pattern {
     I8x16([i8; 16]),
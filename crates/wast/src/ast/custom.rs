@@ -3,6 +3,7 @@ use crate::parser::{Parse, Parser, Result};
 
 /// A wasm custom section within a module.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Custom<'a> {
     /// Where this `@custom` was defined.
     pub span: ast::Span,
@@ -19,6 +20,7 @@ pub struct Custom<'a> {
 
 /// Possible locations to place a custom section within a module.
 #[derive(Debug, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum CustomPlace {
     /// This custom section will appear before the first section in the module.
     BeforeFirst,
@@ -32,6 +34,7 @@ pub enum CustomPlace {
 
 /// Known sections that custom sections can be placed relative to.
 #[derive(Debug, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[allow(missing_docs)]
 pub enum CustomPlaceAnchor {
     Type,
@@ -3,6 +3,7 @@ use crate::parser::{Parse, Parser, Result};
 
 /// A WebAssembly event directive, part of the exception handling proposal.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Event<'a> {
     /// Where this event was defined
     pub span: ast::Span,
@@ -16,6 +17,7 @@ pub struct Event<'a> {
 
 /// Listing of various types of events that can be defined in a wasm module.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum EventType<'a> {
     /// An exception event, where the payload is the type signature of the event
     /// (constructor parameters, etc).
@@ -3,6 +3,7 @@ use crate::parser::{Cursor, Parse, Parser, Peek, Result};
 
 /// A entry in a WebAssembly module's export section.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Export<'a> {
     /// Where this export was defined.
     pub span: ast::Span,
@@ -15,6 +16,7 @@ pub struct Export<'a> {
 /// Different kinds of elements that can be exported from a WebAssembly module,
 /// contained in an [`Export`].
 #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[allow(missing_docs)]
 pub enum ExportKind {
     Func,
@@ -100,9 +102,11 @@ kw_conversions! {
 /// A listing of inline `(export "foo")` statements on a WebAssembly item in
 /// its textual format.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct InlineExport<'a> {
-    /// The extra names to export an item as, if any.
-    pub names: Vec<&'a str>,
+    /// The extra names to export an item as, if any, along with where each
+    /// `export` was written.
+    pub names: Vec<(ast::Span, &'a str)>,
 }
 
 impl<'a> Parse<'a> for InlineExport<'a> {
@@ -110,8 +114,8 @@ impl<'a> Parse<'a> for InlineExport<'a> {
         let mut names = Vec::new();
         while parser.peek::<Self>() {
             names.push(parser.parens(|p| {
-                p.parse::<kw::export>()?;
-                p.parse::<&str>()
+                let span = p.parse::<kw::export>()?.0;
+                Ok((span, p.parse::<&str>()?))
             })?);
         }
         Ok(InlineExport { names })
@@ -1,4 +1,4 @@
-use crate::ast::{self, kw, HeapType};
+use crate::ast::{self, annotation, kw, HeapType};
 use crate::parser::{Parse, Parser, Result};
 use std::mem;
 
@@ -8,17 +8,51 @@ use std::mem;
 /// list of instructions for emission later on. The implicit `end` instruction
 /// at the end of an expression is not included in the `instrs` field.
 #[derive(Debug)]
-#[allow(missing_docs)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Expression<'a> {
+    /// The flat list of instructions in this expression.
     pub instrs: Box<[Instruction<'a>]>,
+    /// Where each of `instrs` was defined, with the same length as `instrs`.
+    ///
+    /// Instructions are located at their keyword, and the `end` instructions
+    /// implied by folded instructions at their closing `)`.
+    pub instr_spans: Box<[ast::Span]>,
+    /// The `@metadata.code.branch_hint` annotations of `instrs`, in
+    /// increasing order of the instruction they apply to.
+    pub branch_hints: Vec<BranchHint>,
 }
 
 impl<'a> Parse<'a> for Expression<'a> {
     fn parse(parser: Parser<'a>) -> Result<Self> {
+        let _r = parser.register_annotation("metadata.code.branch_hint");
         ExpressionParser::default().parse(parser)
     }
 }
 
+/// A hint of whether an instruction is likely to branch, from an annotation
+/// such as `(@metadata.code.branch_hint "\01")` in front of it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct BranchHint {
+    /// The index of the instruction in [`Expression::instrs`].
+    pub instr_index: usize,
+    /// The value of the hint, `1` if the branch is likely to be taken and `0`
+    /// otherwise.
+    pub value: u32,
+}
+
+impl BranchHint {
+    /// Parses the contents of a `(@metadata.code.branch_hint "\01")`
+    /// annotation, returning its value.
+    fn parse_value(parser: Parser<'_>) -> Result<u32> {
+        parser.parse::<annotation::metadata_code_branch_hint>()?;
+        parser.step(|c| match c.string() {
+            Some((&[value], rest)) if value <= 1 => Ok((u32::from(value), rest)),
+            _ => Err(c.error("expected a branch hint of \"\\00\" or \"\\01\"")),
+        })
+    }
+}
+
 /// Helper struct used to parse an `Expression` with helper methods and such.
 ///
 /// The primary purpose of this is to avoid defining expression parsing as a
@@ -31,6 +65,17 @@ struct ExpressionParser<'a> {
     /// eventually become the final `Expression`.
     instrs: Vec<Instruction<'a>>,
 
+    /// The span of each of `instrs`.
+    spans: Vec<ast::Span>,
+
+    /// The values of the branch hints seen so far, and the span of the
+    /// instruction they apply to, which is resolved to its index once all
+    /// instructions are parsed.
+    branch_hints: Vec<(ast::Span, u32)>,
+
+    /// The value of a branch hint which applies to the next instruction.
+    pending_hint: Option<u32>,
+
     /// Descriptor of all our nested s-expr blocks. This only happens when
     /// instructions themselves are nested.
     stack: Vec<Level<'a>>,
@@ -46,7 +91,7 @@ enum Paren {
 enum Level<'a> {
     /// This is a normal `block` or `loop` or similar, where the instruction
     /// payload here is pushed when the block is exited.
-    EndWith(Instruction<'a>),
+    EndWith(Instruction<'a>, ast::Span),
 
     /// This is a pretty special variant which means that we're parsing an `if`
     /// statement, and the state of the `if` parsing is tracked internally in
@@ -70,9 +115,9 @@ enum Level<'a> {
 enum If<'a> {
     /// Only the `if` has been parsed, next thing to parse is the clause, if
     /// any, of the `if` instruction.
-    Clause(Instruction<'a>),
+    Clause(Instruction<'a>, ast::Span),
     /// Next thing to parse is the `then` block
-    Then(Instruction<'a>),
+    Then(Instruction<'a>, ast::Span),
     /// Next thing to parse is the `else` block
     Else,
     /// This `if` statement has finished parsing and if anything remains it's a
@@ -83,7 +128,7 @@ enum If<'a> {
 /// Possible state of "what should be parsed next?" in a `try` expression.
 enum Try<'a> {
     /// Next thing to parse is the `do` block.
-    Do(Instruction<'a>),
+    Do(Instruction<'a>, ast::Span),
     /// Next thing to parse is `catch`/`catch_all`, or `unwind`.
     CatchOrUnwind,
     /// Next thing to parse is a `catch` block or `catch_all`.
@@ -113,10 +158,25 @@ impl<'a> ExpressionParser<'a> {
                 }
             }
 
+            // Branch hints are annotations in front of the instruction they
+            // apply to, whether it's folded or not.
+            if parser.peek2::<annotation::metadata_code_branch_hint>() {
+                if self.pending_hint.is_some() {
+                    return Err(parser.error("multiple branch hints for one instruction"));
+                }
+                self.pending_hint = Some(parser.parens(BranchHint::parse_value)?);
+                continue;
+            }
+
             match self.paren(parser)? {
                 // No parenthesis seen? Then we just parse the next instruction
                 // and move on.
-                Paren::None => self.instrs.push(parser.parse()?),
+                Paren::None => {
+                    let span = parser.cur_span();
+                    self.take_hint(span);
+                    let instr = parser.parse()?;
+                    self.push(instr, span);
+                }
 
                 // If we see a left-parenthesis then things are a little
                 // special. We handle block-like instructions specially
@@ -138,6 +198,8 @@ impl<'a> ExpressionParser<'a> {
                     if self.handle_try_lparen(parser)? {
                         continue;
                     }
+                    let span = parser.cur_span();
+                    self.take_hint(span);
                     match parser.parse()? {
                         // If block/loop show up then we just need to be sure to
                         // push an `end` instruction whenever the `)` token is
@@ -145,27 +207,28 @@ impl<'a> ExpressionParser<'a> {
                         i @ Instruction::Block(_)
                         | i @ Instruction::Loop(_)
                         | i @ Instruction::Let(_) => {
-                            self.instrs.push(i);
-                            self.stack.push(Level::EndWith(Instruction::End(None)));
+                            self.push(i, span);
+                            self.stack
+                                .push(Level::EndWith(Instruction::End(None), span));
                         }
 
                         // Parsing an `if` instruction is super tricky, so we
                         // push an `If` scope and we let all our scope-based
                         // parsing handle the remaining items.
                         i @ Instruction::If(_) => {
-                            self.stack.push(Level::If(If::Clause(i)));
+                            self.stack.push(Level::If(If::Clause(i, span)));
                         }
 
                         // Parsing a `try` is easier than `if` but we also push
                         // a `Try` scope to handle the required nested blocks.
                         i @ Instruction::Try(_) => {
-                            self.stack.push(Level::Try(Try::Do(i)));
+                            self.stack.push(Level::Try(Try::Do(i, span)));
                         }
 
                         // Anything else means that we're parsing a nested form
                         // such as `(i32.add ...)` which means that the
                         // instruction we parsed will be coming at the end.
-                        other => self.stack.push(Level::EndWith(other)),
+                        other => self.stack.push(Level::EndWith(other, span)),
                     }
                 }
 
@@ -173,7 +236,12 @@ impl<'a> ExpressionParser<'a> {
                 // guaranteed there's an item in the `stack` stack for us to
                 // pop. We peel that off and take a look at what it says to do.
                 Paren::Right => match self.stack.pop().unwrap() {
-                    Level::EndWith(i) => self.instrs.push(i),
+                    // Implicit `end` instructions are located at the `)`,
+                    // everything else at its keyword.
+                    Level::EndWith(i @ Instruction::End(None), _) => {
+                        self.push(i, parser.prev_span())
+                    }
+                    Level::EndWith(i, span) => self.push(i, span),
                     Level::IfArm => {}
                     Level::TryArm => {}
 
@@ -181,20 +249,20 @@ impl<'a> ExpressionParser<'a> {
                     // block, then that's an error because there weren't enough
                     // items in the `if` statement. Otherwise we're just careful
                     // to terminate with an `end` instruction.
-                    Level::If(If::Clause(_)) => {
+                    Level::If(If::Clause(..)) => {
                         return Err(parser.error("previous `if` had no clause"));
                     }
-                    Level::If(If::Then(_)) => {
+                    Level::If(If::Then(..)) => {
                         return Err(parser.error("previous `if` had no `then`"));
                     }
                     Level::If(_) => {
-                        self.instrs.push(Instruction::End(None));
+                        self.push(Instruction::End(None), parser.prev_span());
                     }
 
                     // Both `do` and `catch` are required in a `try` statement, so
                     // we will signal those errors here. Otherwise, terminate with
                     // an `end` instruction.
-                    Level::Try(Try::Do(_)) => {
+                    Level::Try(Try::Do(..)) => {
                         return Err(parser.error("previous `try` had no `do`"));
                     }
                     Level::Try(Try::CatchOrUnwind) => {
@@ -203,17 +271,47 @@ impl<'a> ExpressionParser<'a> {
                         );
                     }
                     Level::Try(_) => {
-                        self.instrs.push(Instruction::End(None));
+                        self.push(Instruction::End(None), parser.prev_span());
                     }
                 },
             }
         }
 
+        if self.pending_hint.is_some() {
+            return Err(parser.error("branch hint isn't followed by an instruction"));
+        }
+        let spans = &self.spans;
+        let mut branch_hints = self
+            .branch_hints
+            .into_iter()
+            .filter_map(|(span, value)| {
+                Some(BranchHint {
+                    instr_index: spans.iter().position(|s| *s == span)?,
+                    value,
+                })
+            })
+            .collect::<Vec<_>>();
+        branch_hints.sort_by_key(|h| h.instr_index);
+
         Ok(Expression {
             instrs: self.instrs.into(),
+            instr_spans: self.spans.into(),
+            branch_hints,
         })
     }
 
+    /// Applies a pending branch hint to the instruction at `span`.
+    fn take_hint(&mut self, span: ast::Span) {
+        if let Some(value) = self.pending_hint.take() {
+            self.branch_hints.push((span, value));
+        }
+    }
+
+    fn push(&mut self, instr: Instruction<'a>, span: ast::Span) {
+        self.instrs.push(instr);
+        self.spans.push(span);
+    }
+
     /// Parses either `(`, `)`, or nothing.
     fn paren(&self, parser: Parser<'a>) -> Result<Paren> {
         parser.step(|cursor| {
@@ -255,13 +353,14 @@ impl<'a> ExpressionParser<'a> {
             Some(Level::If(i)) => i,
             _ => return Ok(false),
         };
+        let span = parser.cur_span();
 
         // The first thing parsed in an `if` statement is the clause. If the
         // clause starts with `then`, however, then we know to skip the clause
         // and fall through to below.
-        if let If::Clause(if_instr) = i {
+        if let If::Clause(if_instr, if_span) = i {
             let instr = mem::replace(if_instr, Instruction::End(None));
-            *i = If::Then(instr);
+            *i = If::Then(instr, *if_span);
             if !parser.peek::<kw::then>() {
                 return Ok(false);
             }
@@ -274,9 +373,10 @@ impl<'a> ExpressionParser<'a> {
         //
         // Note that when we see the `then`, that's when we actually add the
         // original `if` instruction to the stream.
-        if let If::Then(if_instr) = i {
+        if let If::Then(if_instr, if_span) = i {
             let instr = mem::replace(if_instr, Instruction::End(None));
             self.instrs.push(instr);
+            self.spans.push(*if_span);
             *i = If::Else;
             if parser.parse::<Option<kw::then>>()?.is_some() {
                 self.stack.push(Level::IfArm);
@@ -288,9 +388,11 @@ impl<'a> ExpressionParser<'a> {
         // effectively the same as the `then` parsing above
         if let If::Else = i {
             self.instrs.push(Instruction::Else(None));
+            self.spans.push(span);
             if parser.parse::<Option<kw::r#else>>()?.is_some() {
                 if parser.is_empty() {
                     self.instrs.pop();
+                    self.spans.pop();
                 }
                 self.stack.push(Level::IfArm);
                 return Ok(true);
@@ -324,11 +426,13 @@ impl<'a> ExpressionParser<'a> {
             Some(Level::Try(i)) => i,
             _ => return Ok(false),
         };
+        let span = parser.cur_span();
 
         // Try statements must start with a `do` block.
-        if let Try::Do(try_instr) = i {
+        if let Try::Do(try_instr, try_span) = i {
             let instr = mem::replace(try_instr, Instruction::End(None));
             self.instrs.push(instr);
+            self.spans.push(*try_span);
             if parser.parse::<Option<kw::r#do>>()?.is_some() {
                 // The state is advanced here only if the parse succeeds in
                 // order to strictly require the keyword.
@@ -348,6 +452,7 @@ impl<'a> ExpressionParser<'a> {
             if parser.parse::<Option<kw::catch>>()?.is_some() {
                 let evt = parser.parse::<ast::Index<'a>>()?;
                 self.instrs.push(Instruction::Catch(evt));
+                self.spans.push(span);
                 *i = Try::Catch;
                 self.stack.push(Level::TryArm);
                 return Ok(true);
@@ -355,6 +460,7 @@ impl<'a> ExpressionParser<'a> {
             // `catch_all` can only come at the end and has no argument.
             if parser.parse::<Option<kw::catch_all>>()?.is_some() {
                 self.instrs.push(Instruction::CatchAll);
+                self.spans.push(span);
                 *i = Try::End;
                 self.stack.push(Level::TryArm);
                 return Ok(true);
@@ -362,6 +468,7 @@ impl<'a> ExpressionParser<'a> {
             // `unwind` is similar to `catch_all`.
             if parser.parse::<Option<kw::unwind>>()?.is_some() {
                 self.instrs.push(Instruction::Unwind);
+                self.spans.push(span);
                 *i = Try::End;
                 self.stack.push(Level::TryArm);
                 return Ok(true);
@@ -373,12 +480,14 @@ impl<'a> ExpressionParser<'a> {
             if parser.parse::<Option<kw::catch>>()?.is_some() {
                 let evt = parser.parse::<ast::Index<'a>>()?;
                 self.instrs.push(Instruction::Catch(evt));
+                self.spans.push(span);
                 *i = Try::Catch;
                 self.stack.push(Level::TryArm);
                 return Ok(true);
             }
             if parser.parse::<Option<kw::catch_all>>()?.is_some() {
                 self.instrs.push(Instruction::CatchAll);
+                self.spans.push(span);
                 *i = Try::End;
                 self.stack.push(Level::TryArm);
                 return Ok(true);
@@ -395,16 +504,22 @@ macro_rules! instructions {
     (pub enum Instruction<'a> {
         $(
             $(#[$doc:meta])*
+            $(@feature($feature:literal))?
             $name:ident $(($($arg:tt)*))? : [$($binary:tt)*] : $instr:tt $( | $deprecated:tt )?,
         )*
     }) => (
         /// A listing of all WebAssembly instructions that can be in a module
         /// that this crate currently parses.
+        ///
+        /// Instructions marked with `@feature` are only available when the
+        /// Cargo feature of that name is enabled.
         #[derive(Debug)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize))]
         #[allow(missing_docs)]
         pub enum Instruction<'a> {
             $(
                 $(#[$doc])*
+                $(#[cfg(feature = $feature)])?
                 $name $(( instructions!(@ty $($arg)*) ))?,
             )*
         }
@@ -413,6 +528,7 @@ macro_rules! instructions {
         impl<'a> Parse<'a> for Instruction<'a> {
             fn parse(parser: Parser<'a>) -> Result<Self> {
                 $(
+                    $(#[cfg(feature = $feature)])?
                     fn $name<'a>(_parser: Parser<'a>) -> Result<Instruction<'a>> {
                         Ok(Instruction::$name $((
                             instructions!(@parse _parser $($arg)*)?
@@ -425,7 +541,10 @@ macro_rules! instructions {
                         None => return Err(c.error("expected an instruction")),
                     };
                     match kw {
-                        $($instr $( | $deprecated )?=> Ok(($name as fn(_) -> _, rest)),)*
+                        $(
+                            $(#[cfg(feature = $feature)])?
+                            $instr $( | $deprecated )?=> Ok(($name as fn(_) -> _, rest)),
+                        )*
                         _ => return Err(c.error("unknown operator or unexpected token")),
                     }
                 })?;
@@ -438,6 +557,7 @@ macro_rules! instructions {
             fn encode(&self, v: &mut Vec<u8>) {
                 match self {
                     $(
+                        $(#[cfg(feature = $feature)])?
                         Instruction::$name $((instructions!(@first $($arg)*)))? => {
                             fn encode<'a>($(arg: &instructions!(@ty $($arg)*),)? v: &mut Vec<u8>) {
                                 instructions!(@encode v $($binary)*);
@@ -457,6 +577,7 @@ macro_rules! instructions {
             pub fn memarg_mut(&mut self) -> Option<&mut MemArg<'a>> {
                 match self {
                     $(
+                        $(#[cfg(feature = $feature)])?
                         Instruction::$name $((instructions!(@memarg_binding a $($arg)*)))? => {
                             instructions!(@get_memarg a $($($arg)*)?)
                         }
@@ -464,8 +585,66 @@ macro_rules! instructions {
                 }
             }
         }
+
+        /// Returns whether `keyword` is the name of an instruction, including
+        /// deprecated names which are still accepted.
+        pub(crate) fn is_instruction(keyword: &str) -> bool {
+            match keyword {
+                $(
+                    $(#[cfg(feature = $feature)])?
+                    $instr $( | $deprecated )? => true,
+                )*
+                _ => false,
+            }
+        }
+
+        /// The variants of [`Instruction`] without their payloads, in the
+        /// same order as [`OPCODES`].
+        #[allow(non_camel_case_types)]
+        enum InstructionKind {
+            $(
+                $(#[cfg(feature = $feature)])?
+                $name,
+            )*
+        }
+
+        static OPCODES: &[Opcode] = &[
+            $(
+                $(#[cfg(feature = $feature)])?
+                Opcode {
+                    name: stringify!($name),
+                    mnemonic: $instr,
+                    deprecated: instructions!(@deprecated $($deprecated)?),
+                    binary: &[$($binary)*],
+                    immediates: instructions!(@immediates $($($arg)*)?),
+                    proposal: proposal(&[$($binary)*], $instr, instructions!(@feature $($feature)?)),
+                },
+            )*
+        ];
+
+        impl Instruction<'_> {
+            /// Returns the [`Opcode`] describing this instruction.
+            pub fn opcode(&self) -> &'static Opcode {
+                let kind = match self {
+                    $(
+                        $(#[cfg(feature = $feature)])?
+                        Instruction::$name { .. } => InstructionKind::$name,
+                    )*
+                };
+                &OPCODES[kind as usize]
+            }
+        }
     );
 
+    (@deprecated) => (None);
+    (@deprecated $name:tt) => (Some($name));
+
+    (@immediates) => (None);
+    (@immediates $($arg:tt)+) => (Some(stringify!($($arg)+)));
+
+    (@feature) => (None);
+    (@feature $feature:literal) => (Some($feature));
+
     (@ty MemArg<$amt:tt>) => (MemArg<'a>);
     (@ty LoadOrStoreLane<$amt:tt>) => (LoadOrStoreLane<'a>);
     (@ty $other:ty) => ($other);
@@ -769,6 +948,12 @@ instructions! {
         I64Extend16S : [0xc3] : "i64.extend16_s",
         I64Extend32S : [0xc4] : "i64.extend32_s",
 
+        // wide arithmetic proposal
+        I64Add128 : [0xfc, 0x13] : "i64.add128",
+        I64Sub128 : [0xfc, 0x14] : "i64.sub128",
+        I64MulWideS : [0xfc, 0x15] : "i64.mul_wide_s",
+        I64MulWideU : [0xfc, 0x16] : "i64.mul_wide_u",
+
         // atomics proposal
         MemoryAtomicNotify(MemArg<4>) : [0xfe, 0x00] : "memory.atomic.notify" | "atomic.notify",
         MemoryAtomicWait32(MemArg<4>) : [0xfe, 0x01] : "memory.atomic.wait32" | "i32.atomic.wait",
@@ -1087,14 +1272,182 @@ instructions! {
         Throw(ast::Index<'a>) : [0x08] : "throw",
         Rethrow(ast::Index<'a>) : [0x09] : "rethrow",
         Unwind : [0x0a] : "unwind",
+
+        // stack-switching proposal
+        @feature("stack-switching") ContNew(ast::Index<'a>) : [0xe0] : "cont.new",
+        @feature("stack-switching") ContBind(ContBind<'a>) : [0xe1] : "cont.bind",
+        @feature("stack-switching") Suspend(ast::Index<'a>) : [0xe2] : "suspend",
+        @feature("stack-switching") Resume(Resume<'a>) : [0xe3] : "resume",
+        @feature("stack-switching") ResumeThrow(ResumeThrow<'a>) : [0xe4] : "resume_throw",
+        @feature("stack-switching") Switch(Switch<'a>) : [0xe5] : "switch",
+
+        // shared-everything-threads proposal
+        @feature("shared-everything-threads") ThreadSpawn(ast::Index<'a>) : [0xfe, 0x7e] : "thread.spawn",
+        @feature("shared-everything-threads") ThreadHwConcurrency : [0xfe, 0x7f] : "thread.hw_concurrency",
     }
 }
 
+/// A description of an [`Instruction`]: its names in the text format, how
+/// its opcode is encoded in the binary format, and the proposal it belongs
+/// to.
+///
+/// The descriptions of all the instructions this crate parses are available
+/// through [`Opcode::all`], which is generated from the same table as
+/// [`Instruction`] and so always agrees with the parser and encoder.
+///
+/// ```
+/// use wast::Opcode;
+///
+/// let opcode = Opcode::from_mnemonic("i32x4.add").unwrap();
+/// assert_eq!(opcode.name, "I32x4Add");
+/// assert_eq!(opcode.binary, &[0xfd, 174]);
+/// assert_eq!(opcode.encoded(), [0xfd, 0xae, 0x01]);
+/// assert_eq!(opcode.proposal, Some("simd"));
+/// assert_eq!(Opcode::from_binary(&[0xfd, 0xae, 0x01]), Some(opcode));
+/// ```
+#[derive(Debug, PartialEq, Eq)]
+pub struct Opcode {
+    /// The name of the [`Instruction`] variant, such as `"I32Add"`.
+    pub name: &'static str,
+    /// The name of the instruction in the text format, such as `"i32.add"`.
+    pub mnemonic: &'static str,
+    /// A deprecated name of the instruction which is still accepted by the
+    /// parser, such as `"get_local"` for `local.get`.
+    pub deprecated: Option<&'static str>,
+    /// The opcode of the instruction, which is encoded before its
+    /// immediates.
+    ///
+    /// The first element is a single byte. Instructions with a prefix byte,
+    /// such as `0xfd` for SIMD, have more elements, which are each encoded
+    /// as an unsigned LEB128 integer. This is empty for `select`, whose
+    /// opcode depends on whether it has a type annotation.
+    pub binary: &'static [u32],
+    /// The type of the immediates of the instruction in [`Instruction`], such
+    /// as `"MemArg<4>"` for `i32.load`, where `4` is its default alignment.
+    /// `None` if the instruction has no immediates.
+    pub immediates: Option<&'static str>,
+    /// The proposal which added the instruction, using the names of the
+    /// proposals' repositories such as `"bulk-memory"`. `None` for
+    /// instructions of the MVP.
+    pub proposal: Option<&'static str>,
+}
+
+impl Opcode {
+    /// Returns the descriptions of all the instructions this crate parses,
+    /// in the order of the variants of [`Instruction`].
+    pub fn all() -> &'static [Opcode] {
+        OPCODES
+    }
+
+    /// Returns the instruction named `mnemonic` in the text format,
+    /// including deprecated names.
+    pub fn from_mnemonic(mnemonic: &str) -> Option<&'static Opcode> {
+        OPCODES
+            .iter()
+            .find(|op| op.mnemonic == mnemonic || op.deprecated == Some(mnemonic))
+    }
+
+    /// Returns the instruction whose encoded opcode is at the start of
+    /// `bytes`, which may be followed by its immediates.
+    ///
+    /// `else` and `catch_all` share an opcode, for which `else` is returned,
+    /// and `select` is never returned.
+    pub fn from_binary(bytes: &[u8]) -> Option<&'static Opcode> {
+        OPCODES.iter().find(|op| {
+            let mut bytes = bytes;
+            for (i, value) in op.binary.iter().enumerate() {
+                let decoded = if i == 0 {
+                    bytes.first().map(|b| (u32::from(*b), 1))
+                } else {
+                    read_u32(bytes)
+                };
+                match decoded {
+                    Some((decoded, len)) if decoded == *value => bytes = &bytes[len..],
+                    _ => return false,
+                }
+            }
+            !op.binary.is_empty()
+        })
+    }
+
+    /// Returns the bytes of the opcode of this instruction in the binary
+    /// format, see [`Opcode::binary`].
+    pub fn encoded(&self) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        for (i, value) in self.binary.iter().enumerate() {
+            if i == 0 {
+                encoded.push(*value as u8);
+            } else {
+                <u32 as crate::binary::Encode>::encode(value, &mut encoded);
+            }
+        }
+        encoded
+    }
+}
+
+/// Decodes an unsigned LEB128 integer from the start of `bytes`, returning it
+/// and its length.
+fn read_u32(bytes: &[u8]) -> Option<(u32, usize)> {
+    let mut value = 0u32;
+    for (i, byte) in bytes.iter().enumerate().take(5) {
+        value |= u32::from(byte & 0x7f) << (i * 7);
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+/// Returns the proposal which added the instruction `mnemonic` with the
+/// opcode `binary`, which is `feature` if it's only parsed with that Cargo
+/// feature.
+const fn proposal(
+    binary: &[u32],
+    mnemonic: &str,
+    feature: Option<&'static str>,
+) -> Option<&'static str> {
+    if feature.is_some() {
+        return feature;
+    }
+    Some(match binary {
+        // `catch_all` reuses the opcode of `else`.
+        [0x05] if str_eq(mnemonic, "catch_all") => "exceptions",
+        [0x06..=0x0a] => "exceptions",
+        [0x12] | [0x13] => "tail-call",
+        [0x14..=0x17] | [0xd3] | [0xd4] => "function-references",
+        [0x25] | [0x26] | [0xd0..=0xd2] | [0xfc, 0x0f..=0x11] => "reference-types",
+        [0xc0..=0xc4] => "sign-extension",
+        [0xd5] | [0xfb, ..] => "gc",
+        [0xfc, 0x00..=0x07] => "saturating-float-to-int",
+        [0xfc, 0x08..=0x0e] => "bulk-memory",
+        [0xfc, 0x13..=0x16] => "wide-arithmetic",
+        [0xfd, ..] => "simd",
+        [0xfe, ..] => "threads",
+        _ => return None,
+    })
+}
+
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
 /// Extra information associated with block-related instructions.
 ///
 /// This is used to label blocks and also annotate what types are expected for
 /// the block.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[allow(missing_docs)]
 pub struct BlockType<'a> {
     pub label: Option<ast::Id<'a>>,
@@ -1114,6 +1467,7 @@ impl<'a> Parse<'a> for BlockType<'a> {
 
 /// Extra information associated with the func.bind instruction.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[allow(missing_docs)]
 pub struct FuncBindType<'a> {
     pub ty: ast::TypeUse<'a, ast::FunctionType<'a>>,
@@ -1131,6 +1485,7 @@ impl<'a> Parse<'a> for FuncBindType<'a> {
 
 /// Extra information associated with the let instruction.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[allow(missing_docs)]
 pub struct LetType<'a> {
     pub block: BlockType<'a>,
@@ -1149,6 +1504,7 @@ impl<'a> Parse<'a> for LetType<'a> {
 /// Extra information associated with the `br_table` instruction.
 #[allow(missing_docs)]
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct BrTableIndices<'a> {
     pub labels: Vec<ast::Index<'a>>,
     pub default: ast::Index<'a>,
@@ -1168,6 +1524,7 @@ impl<'a> Parse<'a> for BrTableIndices<'a> {
 
 /// Payload for lane-related instructions. Unsigned with no + prefix.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct LaneArg {
     /// The lane argument.
     pub lane: u8,
@@ -1178,9 +1535,7 @@ impl<'a> Parse<'a> for LaneArg {
         let lane = parser.step(|c| {
             if let Some((i, rest)) = c.integer() {
                 if i.sign() == None {
-                    let (src, radix) = i.val();
-                    let val = u8::from_str_radix(src, radix)
-                        .map_err(|_| c.error("malformed lane index"))?;
+                    let val = i.as_u8().map_err(|_| c.error("malformed lane index"))?;
                     Ok((val, rest))
                 } else {
                     Err(c.error("unexpected token"))
@@ -1196,6 +1551,7 @@ impl<'a> Parse<'a> for LaneArg {
 /// Payload for memory-related instructions indicating offset/alignment of
 /// memory accesses.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct MemArg<'a> {
     /// The alignment of this access.
     ///
@@ -1268,24 +1624,26 @@ fn idx_zero<T>(span: ast::Span, mk_kind: fn(ast::Span) -> T) -> ast::ItemRef<'st
 
 /// Extra data associated with the `loadN_lane` and `storeN_lane` instructions.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct LoadOrStoreLane<'a> {
     /// The memory argument for this instruction.
     pub memarg: MemArg<'a>,
     /// The lane argument for this instruction.
-    pub lane: LaneArg
+    pub lane: LaneArg,
 }
 
 impl<'a> LoadOrStoreLane<'a> {
     fn parse(parser: Parser<'a>, default_align: u32) -> Result<Self> {
         Ok(LoadOrStoreLane {
             memarg: MemArg::parse(parser, default_align)?,
-            lane: LaneArg::parse(parser)?
+            lane: LaneArg::parse(parser)?,
         })
     }
 }
 
 /// Extra data associated with the `call_indirect` instruction.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct CallIndirect<'a> {
     /// The table that this call is going to be indexing.
     pub table: ast::ItemRef<'a, kw::table>,
@@ -1313,6 +1671,7 @@ impl<'a> Parse<'a> for CallIndirect<'a> {
 
 /// Extra data associated with the `table.init` instruction
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct TableInit<'a> {
     /// The index of the table we're copying into.
     pub table: ast::ItemRef<'a, kw::table>,
@@ -1336,6 +1695,7 @@ impl<'a> Parse<'a> for TableInit<'a> {
 
 /// Extra data associated with the `table.copy` instruction.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct TableCopy<'a> {
     /// The index of the destination table to copy into.
     pub dst: ast::ItemRef<'a, kw::table>,
@@ -1358,6 +1718,7 @@ impl<'a> Parse<'a> for TableCopy<'a> {
 
 /// Extra data associated with unary table instructions.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct TableArg<'a> {
     /// The index of the table argument.
     pub dst: ast::ItemRef<'a, kw::table>,
@@ -1376,6 +1737,7 @@ impl<'a> Parse<'a> for TableArg<'a> {
 
 /// Extra data associated with unary memory instructions.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct MemoryArg<'a> {
     /// The index of the memory space.
     pub mem: ast::ItemRef<'a, kw::memory>,
@@ -1394,6 +1756,7 @@ impl<'a> Parse<'a> for MemoryArg<'a> {
 
 /// Extra data associated with the `memory.init` instruction
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct MemoryInit<'a> {
     /// The index of the data segment we're copying into memory.
     pub data: ast::Index<'a>,
@@ -1414,6 +1777,7 @@ impl<'a> Parse<'a> for MemoryInit<'a> {
 
 /// Extra data associated with the `memory.copy` instruction
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct MemoryCopy<'a> {
     /// The index of the memory we're copying from.
     pub src: ast::ItemRef<'a, kw::memory>,
@@ -1436,6 +1800,7 @@ impl<'a> Parse<'a> for MemoryCopy<'a> {
 
 /// Extra data associated with the `struct.get/set` instructions
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct StructAccess<'a> {
     /// The index of the struct type we're accessing.
     pub r#struct: ast::Index<'a>,
@@ -1454,6 +1819,7 @@ impl<'a> Parse<'a> for StructAccess<'a> {
 
 /// Extra data associated with the `struct.narrow` instruction
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct StructNarrow<'a> {
     /// The type of the struct we're casting from
     pub from: ast::ValType<'a>,
@@ -1472,6 +1838,7 @@ impl<'a> Parse<'a> for StructNarrow<'a> {
 
 /// Different ways to specify a `v128.const` instruction
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[rustfmt::skip]
 #[allow(missing_docs)]
 pub enum V128Const {
@@ -1639,6 +2006,7 @@ impl<'a> Parse<'a> for V128Const {
 
 /// Lanes being shuffled in the `i8x16.shuffle` instruction
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct I8x16Shuffle {
     #[allow(missing_docs)]
     pub lanes: [u8; 16],
@@ -1671,6 +2039,7 @@ impl<'a> Parse<'a> for I8x16Shuffle {
 
 /// Payload of the `select` instructions
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct SelectTypes<'a> {
     #[allow(missing_docs)]
     pub tys: Option<Vec<ast::ValType<'a>>>,
@@ -1696,6 +2065,7 @@ impl<'a> Parse<'a> for SelectTypes<'a> {
 
 /// Payload of the `br_on_exn` instruction
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[allow(missing_docs)]
 pub struct BrOnExn<'a> {
     pub label: ast::Index<'a>,
@@ -1712,6 +2082,7 @@ impl<'a> Parse<'a> for BrOnExn<'a> {
 
 /// Payload of the `br_on_cast` instruction
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[allow(missing_docs)]
 pub struct BrOnCast<'a> {
     pub label: ast::Index<'a>,
@@ -1730,6 +2101,7 @@ impl<'a> Parse<'a> for BrOnCast<'a> {
 
 /// Payload of the `rtt.sub` instruction
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[allow(missing_docs)]
 pub struct RTTSub<'a> {
     pub depth: u32,
@@ -1752,6 +2124,7 @@ impl<'a> Parse<'a> for RTTSub<'a> {
 
 /// Payload of the `ref.test/cast` instruction
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[allow(missing_docs)]
 pub struct RefTest<'a> {
     pub val: HeapType<'a>,
@@ -1765,3 +2138,131 @@ impl<'a> Parse<'a> for RefTest<'a> {
         Ok(RefTest { val, rtt })
     }
 }
+
+/// Payload of the `cont.bind` instruction
+#[cfg(feature = "stack-switching")]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ContBind<'a> {
+    /// The continuation type of the continuation which is bound.
+    pub src: ast::Index<'a>,
+    /// The continuation type of the continuation which is produced.
+    pub dst: ast::Index<'a>,
+}
+
+#[cfg(feature = "stack-switching")]
+impl<'a> Parse<'a> for ContBind<'a> {
+    fn parse(parser: Parser<'a>) -> Result<Self> {
+        let src = parser.parse()?;
+        let dst = parser.parse()?;
+        Ok(ContBind { src, dst })
+    }
+}
+
+/// Payload of the `resume` instruction
+#[cfg(feature = "stack-switching")]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Resume<'a> {
+    /// The continuation type of the resumed continuation.
+    pub ty: ast::Index<'a>,
+    /// How the tags the continuation may suspend with are handled.
+    pub handlers: Vec<Handle<'a>>,
+}
+
+#[cfg(feature = "stack-switching")]
+impl<'a> Parse<'a> for Resume<'a> {
+    fn parse(parser: Parser<'a>) -> Result<Self> {
+        let ty = parser.parse()?;
+        let handlers = Handle::parse_all(parser)?;
+        Ok(Resume { ty, handlers })
+    }
+}
+
+/// Payload of the `resume_throw` instruction
+#[cfg(feature = "stack-switching")]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ResumeThrow<'a> {
+    /// The continuation type of the resumed continuation.
+    pub ty: ast::Index<'a>,
+    /// The tag of the exception thrown into the continuation.
+    pub tag: ast::Index<'a>,
+    /// How the tags the continuation may suspend with are handled.
+    pub handlers: Vec<Handle<'a>>,
+}
+
+#[cfg(feature = "stack-switching")]
+impl<'a> Parse<'a> for ResumeThrow<'a> {
+    fn parse(parser: Parser<'a>) -> Result<Self> {
+        let ty = parser.parse()?;
+        let tag = parser.parse()?;
+        let handlers = Handle::parse_all(parser)?;
+        Ok(ResumeThrow { ty, tag, handlers })
+    }
+}
+
+/// A handler clause of `resume` and `resume_throw`, such as
+/// `(on $tag $label)`.
+#[cfg(feature = "stack-switching")]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Handle<'a> {
+    /// Suspending with `tag` branches to `label`.
+    OnLabel {
+        /// The tag being handled.
+        tag: ast::Index<'a>,
+        /// The label branched to.
+        label: ast::Index<'a>,
+    },
+    /// Suspending with `tag` switches to another continuation, as with
+    /// `(on $tag switch)`.
+    OnSwitch {
+        /// The tag being handled.
+        tag: ast::Index<'a>,
+    },
+}
+
+#[cfg(feature = "stack-switching")]
+impl<'a> Handle<'a> {
+    fn parse_all(parser: Parser<'a>) -> Result<Vec<Handle<'a>>> {
+        let mut handlers = Vec::new();
+        while parser.peek2::<kw::on>() {
+            handlers.push(parser.parens(|p| p.parse())?);
+        }
+        Ok(handlers)
+    }
+}
+
+#[cfg(feature = "stack-switching")]
+impl<'a> Parse<'a> for Handle<'a> {
+    fn parse(parser: Parser<'a>) -> Result<Self> {
+        parser.parse::<kw::on>()?;
+        let tag = parser.parse()?;
+        if parser.parse::<Option<kw::switch>>()?.is_some() {
+            return Ok(Handle::OnSwitch { tag });
+        }
+        let label = parser.parse()?;
+        Ok(Handle::OnLabel { tag, label })
+    }
+}
+
+/// Payload of the `switch` instruction
+#[cfg(feature = "stack-switching")]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Switch<'a> {
+    /// The continuation type of the continuation switched to.
+    pub ty: ast::Index<'a>,
+    /// The tag handled by the `(on $tag switch)` clause to switch through.
+    pub tag: ast::Index<'a>,
+}
+
+#[cfg(feature = "stack-switching")]
+impl<'a> Parse<'a> for Switch<'a> {
+    fn parse(parser: Parser<'a>) -> Result<Self> {
+        let ty = parser.parse()?;
+        let tag = parser.parse()?;
+        Ok(Switch { ty, tag })
+    }
+}
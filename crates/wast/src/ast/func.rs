@@ -5,6 +5,7 @@ use crate::parser::{Parse, Parser, Result};
 ///
 /// This is a member of both the function and code sections.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Func<'a> {
     /// Where this `func` was defined.
     pub span: ast::Span,
@@ -25,6 +26,7 @@ pub struct Func<'a> {
 
 /// Possible ways to define a function in the text format.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum FuncKind<'a> {
     /// A function which is actually defined as an import, such as:
     ///
@@ -80,6 +82,7 @@ impl<'a> Parse<'a> for Func<'a> {
 /// Each local has an optional identifier for name resolution, an optional name
 /// for the custom `name` section, and a value type.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Local<'a> {
     /// An identifier that this local is resolved with (optionally) for name
     /// resolution.
@@ -105,7 +108,11 @@ impl<'a> Local<'a> {
                 let parse_more = id.is_none() && name.is_none();
                 locals.push(Local { id, name, ty });
                 while parse_more && !p.is_empty() {
-                    locals.push(Local { id: None, name: None, ty: p.parse()? });
+                    locals.push(Local {
+                        id: None,
+                        name: None,
+                        ty: p.parse()?,
+                    });
                 }
                 Ok(())
             })?;
@@ -3,6 +3,7 @@ use crate::parser::{Cursor, Parse, Parser, Peek, Result};
 
 /// An `import` statement and entry in a WebAssembly module.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Import<'a> {
     /// Where this `import` was defined
     pub span: ast::Span,
@@ -30,6 +31,7 @@ impl<'a> Parse<'a> for Import<'a> {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[allow(missing_docs)]
 pub struct ItemSig<'a> {
     /// Where this item is defined in the source.
@@ -45,6 +47,7 @@ pub struct ItemSig<'a> {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[allow(missing_docs)]
 pub enum ItemKind<'a> {
     Func(ast::TypeUse<'a, ast::FunctionType<'a>>),
@@ -128,8 +131,11 @@ impl<'a> Parse<'a> for ItemSig<'a> {
 /// so it's so far been a bit nicer to have the optionality handled through
 /// `Peek` rather than `Option<T>`.
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[allow(missing_docs)]
 pub struct InlineImport<'a> {
+    /// Where this `import` was written.
+    pub span: ast::Span,
     pub module: &'a str,
     pub field: Option<&'a str>,
 }
@@ -137,8 +143,9 @@ pub struct InlineImport<'a> {
 impl<'a> Parse<'a> for InlineImport<'a> {
     fn parse(parser: Parser<'a>) -> Result<Self> {
         parser.parens(|p| {
-            p.parse::<kw::import>()?;
+            let span = p.parse::<kw::import>()?.0;
             Ok(InlineImport {
+                span,
                 module: p.parse()?,
                 field: p.parse()?,
             })
@@ -3,6 +3,7 @@ use crate::parser::{Parse, Parser, Result};
 
 /// A nested WebAssembly instance to be created as part of a module.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Instance<'a> {
     /// Where this `instance` was defined.
     pub span: ast::Span,
@@ -18,6 +19,7 @@ pub struct Instance<'a> {
 
 /// Possible ways to define a instance in the text format.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum InstanceKind<'a> {
     /// An instance which is actually defined as an import, such as:
     Import {
@@ -38,6 +40,7 @@ pub enum InstanceKind<'a> {
 
 /// Arguments to the `instantiate` instruction
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[allow(missing_docs)]
 pub struct InstanceArg<'a> {
     pub name: &'a str,
@@ -3,6 +3,7 @@ use crate::parser::{Lookahead1, Parse, Parser, Peek, Result};
 
 /// A defined WebAssembly memory instance inside of a module.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Memory<'a> {
     /// Where this `memory` was defined
     pub span: ast::Span,
@@ -17,6 +18,7 @@ pub struct Memory<'a> {
 
 /// Different syntactical ways a memory can be defined in a module.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum MemoryKind<'a> {
     /// This memory is actually an inlined import definition.
     #[allow(missing_docs)]
@@ -87,6 +89,7 @@ impl<'a> Parse<'a> for Memory<'a> {
 
 /// A `data` directive in a WebAssembly module.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Data<'a> {
     /// Where this `data` was defined
     pub span: ast::Span,
@@ -99,11 +102,37 @@ pub struct Data<'a> {
 
     /// Bytes for this `Data` segment, viewed as the concatenation of all the
     /// contained slices.
+    ///
+    /// Each string or list of values written in the text format is kept as a
+    /// separate chunk, and strings borrow from the [`ParseBuffer`] they were
+    /// parsed from, so large segments aren't copied while parsing. See
+    /// [`Data::chunks`] to stream them out.
+    ///
+    /// [`ParseBuffer`]: crate::parser::ParseBuffer
     pub data: Vec<DataVal<'a>>,
 }
 
+impl<'a> Data<'a> {
+    /// Returns the length, in bytes, of this data segment.
+    pub fn len(&self) -> usize {
+        self.data.iter().map(|d| d.len()).sum()
+    }
+
+    /// Returns whether this data segment is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the chunks of bytes which, concatenated, make up this data
+    /// segment, without copying them.
+    pub fn chunks(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        self.data.iter().map(|d| d.as_bytes())
+    }
+}
+
 /// Different kinds of data segments, either passive or active.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum DataKind<'a> {
     /// A passive data segment which isn't associated with a memory and is
     /// referenced from various instructions.
@@ -171,6 +200,7 @@ impl<'a> Parse<'a> for Data<'a> {
 
 /// Differnet ways the value of a data segment can be defined.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[allow(missing_docs)]
 pub enum DataVal<'a> {
     String(&'a [u8]),
@@ -187,13 +217,18 @@ impl DataVal<'_> {
         }
     }
 
-    /// Pushes the value of this data value onto the provided list of bytes.
-    pub fn push_onto(&self, dst: &mut Vec<u8>) {
+    /// Returns the bytes of this data value.
+    pub fn as_bytes(&self) -> &[u8] {
         match self {
-            DataVal::String(s) => dst.extend_from_slice(s),
-            DataVal::Integral(s) => dst.extend_from_slice(s),
+            DataVal::String(s) => s,
+            DataVal::Integral(s) => s,
         }
     }
+
+    /// Pushes the value of this data value onto the provided list of bytes.
+    pub fn push_onto(&self, dst: &mut Vec<u8>) {
+        dst.extend_from_slice(self.as_bytes());
+    }
 }
 
 impl<'a> Parse<'a> for DataVal<'a> {
@@ -73,6 +73,10 @@ macro_rules! custom_keyword {
             fn display() -> &'static str {
                 concat!("`", $kw, "`")
             }
+
+            fn token_kind() -> $crate::parser::TokenKind {
+                $crate::parser::TokenKind::Keyword($kw)
+            }
         }
     };
 }
@@ -140,6 +144,10 @@ macro_rules! custom_reserved {
             fn display() -> &'static str {
                 concat!("`", $rsv, "`")
             }
+
+            fn token_kind() -> $crate::parser::TokenKind {
+                $crate::parser::TokenKind::Reserved($rsv)
+            }
         }
     };
 }
@@ -289,6 +297,10 @@ macro_rules! annotation {
             fn display() -> &'static str {
                 concat!("`@", $annotation, "`")
             }
+
+            fn token_kind() -> $crate::parser::TokenKind {
+                $crate::parser::TokenKind::Annotation($annotation)
+            }
         }
     };
 }
@@ -349,6 +361,7 @@ pub mod kw {
     custom_keyword!(catch);
     custom_keyword!(catch_all);
     custom_keyword!(code);
+    custom_keyword!(cont);
     custom_keyword!(data);
     custom_keyword!(declare);
     custom_keyword!(r#do = "do");
@@ -397,6 +410,7 @@ pub mod kw {
     custom_keyword!(null);
     custom_keyword!(nullref);
     custom_keyword!(offset);
+    custom_keyword!(on);
     custom_keyword!(outer);
     custom_keyword!(param);
     custom_keyword!(parent);
@@ -416,6 +430,7 @@ pub mod kw {
     custom_keyword!(shared);
     custom_keyword!(start);
     custom_keyword!(r#struct = "struct");
+    custom_keyword!(switch);
     custom_keyword!(table);
     custom_keyword!(then);
     custom_keyword!(r#try = "try");
@@ -427,4 +442,5 @@ pub mod kw {
 pub mod annotation {
     annotation!(custom);
     annotation!(name);
+    annotation!(metadata_code_branch_hint = "metadata.code.branch_hint");
 }
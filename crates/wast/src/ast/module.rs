@@ -8,6 +8,7 @@ pub use crate::resolve::Names;
 /// This is the top-level type which you'll frequently parse when working with
 /// this crate. A `*.wat` file is either one `module` s-expression or a sequence
 /// of s-expressions that are module fields.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Wat<'a> {
     #[allow(missing_docs)]
     pub module: Module<'a>,
@@ -36,6 +37,7 @@ impl<'a> Parse<'a> for Wat<'a> {
 }
 
 /// A parsed WebAssembly module.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Module<'a> {
     /// Where this `module` was defined
     pub span: ast::Span,
@@ -47,7 +49,22 @@ pub struct Module<'a> {
     pub kind: ModuleKind<'a>,
 }
 
+/// Where an instruction was encoded, as returned by
+/// [`Module::encode_with_offsets`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstrOffset {
+    /// The index of the function the instruction belongs to, counting imported
+    /// functions.
+    pub func: u32,
+    /// The byte offset of the instruction within the code section, relative to
+    /// the start of the section's contents after its size.
+    pub offset: usize,
+    /// Where the instruction was defined in the text.
+    pub span: ast::Span,
+}
+
 /// The different kinds of ways to define a module.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ModuleKind<'a> {
     /// A module defined in the textual s-expression format.
     Text(Vec<ModuleField<'a>>),
@@ -110,7 +127,84 @@ impl<'a> Module<'a> {
     /// expansion-related errors.
     pub fn encode(&mut self) -> std::result::Result<Vec<u8>, crate::Error> {
         self.resolve()?;
-        Ok(crate::binary::encode(self))
+        Ok(crate::binary::encode(self, None, None))
+    }
+
+    /// Same as [`Module::encode`], but also returns where each instruction of
+    /// the code section was encoded.
+    ///
+    /// The returned list has an entry for every instruction of every function
+    /// defined in this module, including the `end` terminating each function,
+    /// in the order they're encoded. This can be used by profilers or coverage
+    /// tools to map code offsets back to the text they came from.
+    ///
+    /// Modules defined with the `binary` directive have no offsets returned.
+    ///
+    /// # Errors
+    ///
+    /// This function can return the same errors as [`Module::encode`].
+    pub fn encode_with_offsets(
+        &mut self,
+    ) -> std::result::Result<(Vec<u8>, Vec<InstrOffset>), crate::Error> {
+        self.resolve()?;
+        let mut offsets = Vec::new();
+        let wasm = crate::binary::encode(self, Some(&mut offsets), None);
+        Ok((wasm, offsets))
+    }
+
+    /// Same as [`Module::encode`], but also records the comments of `wat`, the
+    /// text this module was parsed from, in a `metadata.comments` custom
+    /// section at the end of the binary.
+    ///
+    /// Each comment is recorded along with the item or instruction which
+    /// follows it in the text, such as a function or an `i32.add`, so that
+    /// printers can print it next to the same item or instruction again.
+    /// Comments before the module and after its last item are recorded as
+    /// such. Comments in nested modules are attached to the items of this
+    /// module.
+    ///
+    /// # Errors
+    ///
+    /// This function can return the same errors as [`Module::encode`].
+    pub fn encode_with_comments(
+        &mut self,
+        wat: &str,
+    ) -> std::result::Result<Vec<u8>, crate::Error> {
+        let mut wasm = self.encode()?;
+        wasm.extend(crate::binary::encode_comments(self, wat));
+        Ok(wasm)
+    }
+
+    /// Same as [`Module::encode`], but also returns a hexdump of the binary
+    /// describing what each of its bytes encodes.
+    ///
+    /// Every byte is dumped on a line starting with its offset, and grouped
+    /// with the bytes of the same item, such as a section header, an entry of
+    /// a section or an instruction, which are followed by a description of
+    /// that item:
+    ///
+    /// ```text
+    /// 0000000: 00 61 73 6d                                     ; magic
+    /// 0000004: 01 00 00 00                                     ; version
+    /// ; section "type" (1)
+    /// 0000008: 01                                              ; section code
+    /// 0000009: 04                                              ; section size
+    /// 000000a: 01                                              ; entry count
+    /// 000000b: 60 00 00                                        ; type 0
+    /// ```
+    ///
+    /// This is meant for learning about and debugging the binary format, and
+    /// the format of the dump may change between releases.
+    ///
+    /// # Errors
+    ///
+    /// This function can return the same errors as [`Module::encode`].
+    pub fn encode_annotated(&mut self) -> std::result::Result<(Vec<u8>, String), crate::Error> {
+        self.resolve()?;
+        let mut notes = Vec::new();
+        let wasm = crate::binary::encode(self, None, Some(&mut notes));
+        let dump = crate::binary::dump(&wasm, &notes);
+        Ok((wasm, dump))
     }
 
     fn validate(&self, parser: Parser<'_>) -> Result<()> {
@@ -158,6 +252,7 @@ impl<'a> Parse<'a> for Module<'a> {
 /// A listing of all possible fields that can make up a WebAssembly module.
 #[allow(missing_docs)]
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ModuleField<'a> {
     Type(ast::Type<'a>),
     Import(ast::Import<'a>),
@@ -174,13 +269,55 @@ pub enum ModuleField<'a> {
     Instance(ast::Instance<'a>),
     NestedModule(ast::NestedModule<'a>),
     Alias(ast::Alias<'a>),
+    /// A field which failed to parse, only produced when the parser recovers
+    /// from errors, see [`ParseBuffer::recover`](crate::parser::ParseBuffer::recover).
+    Error(ErrorField),
+}
+
+/// A module field which failed to parse and was skipped.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ErrorField {
+    /// Where the skipped field starts.
+    pub span: ast::Span,
+    /// Where the error was found, within the field.
+    pub error_span: ast::Span,
+    /// The error message.
+    pub message: String,
+}
+
+impl ErrorField {
+    /// Returns the error which made this field fail to parse.
+    ///
+    /// Note that you'll want to call [`Error::set_text`](crate::Error::set_text)
+    /// on the result to render it along with the text it points to.
+    pub fn error(&self) -> crate::Error {
+        crate::Error::new(self.error_span, self.message.clone())
+    }
 }
 
 impl<'a> ModuleField<'a> {
-    fn parse_remaining(parser: Parser<'a>) -> Result<Vec<ModuleField>> {
+    pub(crate) fn parse_remaining(parser: Parser<'a>) -> Result<Vec<ModuleField>> {
         let mut fields = Vec::new();
         while !parser.is_empty() {
-            fields.push(parser.parens(ModuleField::parse)?);
+            if !parser.recovering() {
+                fields.push(parser.parens(ModuleField::parse)?);
+                continue;
+            }
+            let span = parser.cur_span();
+            match parser.parens(ModuleField::parse) {
+                Ok(field) => fields.push(field),
+                Err(e) => {
+                    if parser.skip().is_err() {
+                        return Err(e);
+                    }
+                    fields.push(ModuleField::Error(ErrorField {
+                        span,
+                        error_span: e.span(),
+                        message: e.message(),
+                    }));
+                }
+            }
         }
         Ok(fields)
     }
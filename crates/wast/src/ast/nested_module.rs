@@ -3,6 +3,7 @@ use crate::parser::{Cursor, Parse, Parser, Peek, Result};
 
 /// A nested WebAssembly nested module to be created as part of a module.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct NestedModule<'a> {
     /// Where this `nested module` was defined.
     pub span: ast::Span,
@@ -20,6 +21,7 @@ pub struct NestedModule<'a> {
 
 /// Possible ways to define a nested module in the text format.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum NestedModuleKind<'a> {
     /// An nested module which is actually defined as an import, such as:
     Import {
@@ -62,10 +64,7 @@ impl<'a> Parse<'a> for NestedModule<'a> {
                 ty: parser.parse()?,
             }
         } else {
-            let mut fields = Vec::new();
-            while !parser.is_empty() {
-                fields.push(parser.parens(|p| p.parse())?);
-            }
+            let fields = ast::ModuleField::parse_remaining(parser)?;
             NestedModuleKind::Inline { fields }
         };
 
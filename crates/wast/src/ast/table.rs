@@ -3,6 +3,7 @@ use crate::parser::{Parse, Parser, Result};
 
 /// A WebAssembly `table` directive in a module.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Table<'a> {
     /// Where this table was defined.
     pub span: ast::Span,
@@ -17,6 +18,7 @@ pub struct Table<'a> {
 
 /// Different ways to textually define a table.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum TableKind<'a> {
     /// This table is actually an inlined import definition.
     #[allow(missing_docs)]
@@ -48,21 +50,24 @@ impl<'a> Parse<'a> for Table<'a> {
         //
         //  *   `elemtype (elem ...)`
         //  *   `(import "a" "b") limits`
-        //  *   `limits`
+        //  *   `limits`, optionally preceded by `shared`
         let mut l = parser.lookahead1();
         let kind = if l.peek::<ast::RefType>() {
-            let elem = parser.parse()?;
+            let elem: ast::RefType = parser.parse()?;
             let payload = parser.parens(|p| {
                 p.parse::<kw::elem>()?;
-                let ty = if parser.peek::<ast::LParen>() {
-                    Some(elem)
-                } else {
-                    None
-                };
-                ElemPayload::parse_tail(parser, ty)
+                // The payload always has the table's element type, even when
+                // it's empty, but a redundant `func` is accepted as it is for
+                // `elem` segments.
+                if let ast::HeapType::Func = elem.heap {
+                    p.parse::<Option<kw::func>>()?;
+                }
+                ElemPayload::parse_tail(parser, Some(elem))
             })?;
             TableKind::Inline { elem, payload }
-        } else if l.peek::<u32>() {
+        } else if l.peek::<u32>()
+            || (cfg!(feature = "shared-everything-threads") && l.peek::<kw::shared>())
+        {
             TableKind::Normal(parser.parse()?)
         } else if let Some(import) = parser.parse()? {
             TableKind::Import {
@@ -83,6 +88,7 @@ impl<'a> Parse<'a> for Table<'a> {
 
 /// An `elem` segment in a WebAssembly module.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Elem<'a> {
     /// Where this `elem` was defined.
     pub span: ast::Span,
@@ -96,6 +102,7 @@ pub struct Elem<'a> {
 
 /// Different ways to define an element segment in an mdoule.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ElemKind<'a> {
     /// A passive segment that isn't associated with a table and can be used in
     /// various bulk-memory instructions.
@@ -116,6 +123,7 @@ pub enum ElemKind<'a> {
 
 /// Different ways to define the element segment payload in a module.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ElemPayload<'a> {
     /// This element segment has a contiguous list of function indices
     Indices(Vec<ast::ItemRef<'a, kw::func>>),
@@ -1,12 +1,13 @@
 use crate::ast::{annotation, kw};
-use crate::lexer::FloatVal;
-use crate::parser::{Cursor, Parse, Parser, Peek, Result};
+use crate::lexer::{strtod, strtof, FloatVal};
+use crate::parser::{Cursor, Parse, Parser, Peek, Result, Symbol, TokenKind};
 use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::str;
 
 /// A position in the original source stream, used to render errors.
 #[derive(Copy, Clone, Debug, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Span {
     pub(crate) offset: usize,
 }
@@ -41,21 +42,35 @@ impl Span {
 ///
 /// An identifier is used to symbolically refer to items in a a wasm module,
 /// typically via the [`Index`] type.
+///
+/// Identifiers are compared and hashed by their names. Comparing two
+/// identifiers parsed from the same
+/// [`ParseBuffer`](crate::parser::ParseBuffer) only compares the symbols their
+/// names were interned as, though.
 #[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Id<'a> {
     name: &'a str,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    symbol: Symbol,
     gen: u32,
     span: Span,
 }
 
 impl<'a> Id<'a> {
-    fn new(name: &'a str, span: Span) -> Id<'a> {
-        Id { name, gen: 0, span }
+    fn new(name: &'a str, symbol: Symbol, span: Span) -> Id<'a> {
+        Id {
+            name,
+            symbol,
+            gen: 0,
+            span,
+        }
     }
 
     pub(crate) fn gensym(span: Span, gen: u32) -> Id<'a> {
         Id {
             name: "gensym",
+            symbol: Symbol::default(),
             gen,
             span,
         }
@@ -87,7 +102,11 @@ impl<'a> Hash for Id<'a> {
 
 impl<'a> PartialEq for Id<'a> {
     fn eq(&self, other: &Id<'a>) -> bool {
-        self.name == other.name && self.gen == other.gen
+        self.gen == other.gen
+            && self
+                .symbol
+                .same_name(other.symbol)
+                .unwrap_or_else(|| self.name == other.name)
     }
 }
 
@@ -96,8 +115,8 @@ impl<'a> Eq for Id<'a> {}
 impl<'a> Parse<'a> for Id<'a> {
     fn parse(parser: Parser<'a>) -> Result<Self> {
         parser.step(|c| {
-            if let Some((name, rest)) = c.id() {
-                return Ok((Id::new(name, c.cur_span()), rest));
+            if let Some((name, symbol, rest)) = c.id_symbol() {
+                return Ok((Id::new(name, symbol, c.cur_span()), rest));
             }
             Err(c.error("expected an identifier"))
         })
@@ -122,6 +141,10 @@ impl Peek for Id<'_> {
     fn display() -> &'static str {
         "an identifier"
     }
+
+    fn token_kind() -> TokenKind {
+        TokenKind::Id
+    }
 }
 
 /// A reference to another item in a wasm module.
@@ -133,6 +156,7 @@ impl Peek for Id<'_> {
 /// The emission phase of a module will ensure that `Index::Id` is never used
 /// and switch them all to `Index::Num`.
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Index<'a> {
     /// A numerical index that this references. The index space this is
     /// referencing is implicit based on where this [`Index`] is stored.
@@ -221,14 +245,19 @@ impl Hash for Index<'_> {
 /// Optionally includes export strings for module-linking sugar syntax for alias
 /// injection.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(bound = ""))]
 #[allow(missing_docs)]
 pub enum ItemRef<'a, K> {
     Outer {
+        // The keyword only carries a span, which is already implied by the
+        // position of this reference, so it's not serialized.
+        #[cfg_attr(feature = "serde", serde(skip))]
         kind: K,
         module: Index<'a>,
         idx: Index<'a>,
     },
     Item {
+        #[cfg_attr(feature = "serde", serde(skip))]
         kind: K,
         idx: Index<'a>,
         exports: Vec<&'a str>,
@@ -287,6 +316,7 @@ impl<'a, K: Peek> Peek for ItemRef<'a, K> {
 
 /// Convenience structure to parse `$f` or `(item $f)`.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(bound = ""))]
 pub struct IndexOrRef<'a, K>(pub ItemRef<'a, K>);
 
 impl<'a, K> Parse<'a> for IndexOrRef<'a, K>
@@ -318,6 +348,7 @@ impl<'a, K: Peek> Peek for IndexOrRef<'a, K> {
 
 /// An `@name` annotation in source, currently of the form `@name "foo"`
 #[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct NameAnnotation<'a> {
     /// The name specified for the item
     pub name: &'a str,
@@ -343,7 +374,7 @@ impl<'a> Parse<'a> for Option<NameAnnotation<'a>> {
 }
 
 macro_rules! integers {
-    ($($i:ident($u:ident))*) => ($(
+    ($($i:ident($as:ident))*) => ($(
         impl<'a> Parse<'a> for $i {
             fn parse(parser: Parser<'a>) -> Result<Self> {
                 Ok(parser.parse::<($i, Span)>()?.0)
@@ -354,12 +385,7 @@ macro_rules! integers {
             fn parse(parser: Parser<'a>) -> Result<Self> {
                 parser.step(|c| {
                     if let Some((i, rest)) = c.integer() {
-                        let (s, base) = i.val();
-                        let val = $i::from_str_radix(s, base)
-                            .or_else(|_| {
-                                $u::from_str_radix(s, base).map(|i| i as $i)
-                            });
-                        return match val {
+                        return match i.$as() {
                             Ok(n) => Ok(((n, c.cur_span()), rest)),
                             Err(_) => Err(c.error(concat!(
                                 "invalid ",
@@ -381,13 +407,17 @@ macro_rules! integers {
             fn display() -> &'static str {
                 stringify!($i)
             }
+
+            fn token_kind() -> TokenKind {
+                TokenKind::Integer
+            }
         }
     )*)
 }
 
 integers! {
-    u8(u8) u16(u16) u32(u32) u64(u64)
-    i8(u8) i16(u16) i32(u32) i64(u64)
+    u8(as_u8) u16(as_u16) u32(as_u32) u64(as_u64)
+    i8(as_i8) i16(as_i16) i32(as_i32) i64(as_i64)
 }
 
 impl<'a> Parse<'a> for &'a [u8] {
@@ -409,6 +439,10 @@ impl Peek for &'_ [u8] {
     fn display() -> &'static str {
         "string"
     }
+
+    fn token_kind() -> TokenKind {
+        TokenKind::String
+    }
 }
 
 impl<'a> Parse<'a> for &'a str {
@@ -431,22 +465,93 @@ impl Peek for &'_ str {
     fn display() -> &'static str {
         <&[u8]>::display()
     }
+
+    fn token_kind() -> TokenKind {
+        TokenKind::String
+    }
 }
 
 macro_rules! float {
     ($($name:ident => {
         bits: $int:ident,
-        float: $float:ident,
-        exponent_bits: $exp_bits:tt,
+        significand_bits: $signif_bits:tt,
         name: $parse:ident,
     })*) => ($(
         /// A parsed floating-point type
-        #[derive(Debug)]
+        ///
+        /// Floats are compared by their bits, so NaNs with the same sign and
+        /// payload are equal while `0` and `-0` aren't.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize))]
         pub struct $name {
             /// The raw bits that this floating point number represents.
             pub bits: $int,
         }
 
+        impl $name {
+            const SIGN: $int = !(!0 >> 1);
+            const SIGNIFICAND: $int = (1 << $signif_bits) - 1;
+            const EXPONENT: $int = !Self::SIGN & !Self::SIGNIFICAND;
+            const QUIET: $int = 1 << ($signif_bits - 1);
+
+            /// Returns the NaN with the given sign and payload, the
+            /// significand bits, as written with `nan:0x...`.
+            ///
+            /// Returns `None` if the payload is zero, which would be infinity,
+            /// or doesn't fit in the significand.
+            pub fn nan(negative: bool, payload: $int) -> Option<$name> {
+                if payload == 0 || payload & !Self::SIGNIFICAND != 0 {
+                    return None;
+                }
+                let sign = if negative { Self::SIGN } else { 0 };
+                Some($name { bits: sign | Self::EXPONENT | payload })
+            }
+
+            /// Returns the canonical NaN with the given sign, which is what
+            /// `nan` and `-nan` denote.
+            pub fn canonical_nan(negative: bool) -> $name {
+                $name::nan(negative, Self::QUIET).unwrap()
+            }
+
+            /// Returns whether this is a NaN, of either sign.
+            pub fn is_nan(&self) -> bool {
+                self.bits & Self::EXPONENT == Self::EXPONENT && self.nan_payload().is_some()
+            }
+
+            /// Returns whether the sign bit of this float is set, which is
+            /// preserved for NaNs too.
+            pub fn is_sign_negative(&self) -> bool {
+                self.bits & Self::SIGN != 0
+            }
+
+            /// Returns the payload of this float if it's a NaN.
+            pub fn nan_payload(&self) -> Option<$int> {
+                let payload = self.bits & Self::SIGNIFICAND;
+                if self.bits & Self::EXPONENT == Self::EXPONENT && payload != 0 {
+                    Some(payload)
+                } else {
+                    None
+                }
+            }
+
+            /// Returns whether this is a canonical NaN, of either sign, as
+            /// matched by the `nan:canonical` result pattern.
+            pub fn is_canonical_nan(&self) -> bool {
+                self.nan_payload() == Some(Self::QUIET)
+            }
+
+            /// Returns whether this is an arithmetic NaN, of either sign, as
+            /// matched by the `nan:arithmetic` result pattern. That's any NaN
+            /// whose most significant payload bit is set, which includes the
+            /// canonical NaNs.
+            pub fn is_arithmetic_nan(&self) -> bool {
+                match self.nan_payload() {
+                    Some(payload) => payload & Self::QUIET != 0,
+                    None => false,
+                }
+            }
+        }
+
         impl<'a> Parse<'a> for $name {
             fn parse(parser: Parser<'a>) -> Result<Self> {
                 parser.step(|c| {
@@ -473,233 +578,22 @@ macro_rules! float {
                 })
             }
         }
-
-        fn $parse(val: &FloatVal<'_>) -> Option<$int> {
-            // Compute a few well-known constants about the float representation
-            // given the parameters to the macro here.
-            let width = std::mem::size_of::<$int>() * 8;
-            let neg_offset = width - 1;
-            let exp_offset = neg_offset - $exp_bits;
-            let signif_bits = width - 1 - $exp_bits;
-            let signif_mask = (1 << exp_offset) - 1;
-            let bias = (1 << ($exp_bits - 1)) - 1;
-
-            let (hex, integral, decimal, exponent_str) = match val {
-                // Infinity is when the exponent bits are all set and
-                // the significand is zero.
-                FloatVal::Inf { negative } => {
-                    let exp_bits = (1 << $exp_bits) - 1;
-                    let neg_bit = *negative as $int;
-                    return Some(
-                        (neg_bit << neg_offset) |
-                        (exp_bits << exp_offset)
-                    );
-                }
-
-                // NaN is when the exponent bits are all set and
-                // the significand is nonzero. The default of NaN is
-                // when only the highest bit of the significand is set.
-                FloatVal::Nan { negative, val } => {
-                    let exp_bits = (1 << $exp_bits) - 1;
-                    let neg_bit = *negative as $int;
-                    let signif = val.unwrap_or(1 << (signif_bits - 1)) as $int;
-                    // If the significand is zero then this is actually infinity
-                    // so we fail to parse it.
-                    if signif & signif_mask == 0 {
-                        return None;
-                    }
-                    return Some(
-                        (neg_bit << neg_offset) |
-                        (exp_bits << exp_offset) |
-                        (signif & signif_mask)
-                    );
-                }
-
-                // This is trickier, handle this below
-                FloatVal::Val { hex, integral, decimal, exponent } => {
-                    (hex, integral, decimal, exponent)
-                }
-            };
-
-            // Rely on Rust's standard library to parse base 10 floats
-            // correctly.
-            if !*hex {
-                let mut s = integral.to_string();
-                if let Some(decimal) = decimal {
-                    s.push_str(".");
-                    s.push_str(&decimal);
-                }
-                if let Some(exponent) = exponent_str {
-                    s.push_str("e");
-                    s.push_str(&exponent);
-                }
-                let float = s.parse::<$float>().ok()?;
-                // looks like the `*.wat` format considers infinite overflow to
-                // be invalid.
-                if float.is_infinite() {
-                    return None;
-                }
-                return Some(float.to_bits());
-            }
-
-            // Parsing hex floats is... hard! I don't really know what most of
-            // this below does. It was copied from Gecko's implementation in
-            // `WasmTextToBinary.cpp`. Would love comments on this if you have
-            // them!
-            let decimal = decimal.as_ref().map(|s| &**s).unwrap_or("");
-            let negative = integral.starts_with('-');
-            let integral = integral.trim_start_matches('-').trim_start_matches('0');
-
-            // Do a bunch of work up front to locate the first non-zero digit
-            // to determine the initial exponent. There's a number of
-            // adjustments depending on where the digit was found, but the
-            // general idea here is that I'm not really sure why things are
-            // calculated the way they are but it should match Gecko.
-            let decimal_no_leading = decimal.trim_start_matches('0');
-            let decimal_iter = if integral.is_empty() {
-                decimal_no_leading.chars()
-            } else {
-                decimal.chars()
-            };
-            let mut digits = integral.chars()
-                .map(|c| (to_hex(c) as $int, false))
-                .chain(decimal_iter.map(|c| (to_hex(c) as $int, true)));
-            let lead_nonzero_digit = match digits.next() {
-                Some((c, _)) => c,
-                // No digits? Must be `+0` or `-0`, being careful to handle the
-                // sign encoding here.
-                None if negative => return Some(1 << (width - 1)),
-                None => return Some(0),
-            };
-            let mut significand = 0 as $int;
-            let mut exponent = if !integral.is_empty() {
-                1
-            } else {
-                -((decimal.len() - decimal_no_leading.len() + 1) as i32) + 1
-            };
-            let lz = (lead_nonzero_digit as u8).leading_zeros() as i32 - 4;
-            exponent = exponent.checked_mul(4)?.checked_sub(lz + 1)?;
-            let mut significand_pos = (width - (4 - (lz as usize))) as isize;
-            assert!(significand_pos >= 0);
-            significand |= lead_nonzero_digit << significand_pos;
-
-            // Now that we've got an anchor in the string we parse the remaining
-            // digits. Again, not entirely sure why everything is the way it is
-            // here! This is copied frmo gecko.
-            let mut discarded_extra_nonzero = false;
-            for (digit, decimal) in digits {
-                if !decimal {
-                    exponent += 4;
-                }
-                if significand_pos > -4 {
-                    significand_pos -= 4;
-                }
-
-                if significand_pos >= 0 {
-                    significand |= digit << significand_pos;
-                } else if significand_pos > -4 {
-                    significand |= digit >> (4 - significand_pos);
-                    discarded_extra_nonzero = (digit & !((!0) >> (4 - significand_pos))) != 0;
-                } else if digit != 0 {
-                    discarded_extra_nonzero = true;
-                }
-            }
-
-            exponent = exponent.checked_add(match exponent_str {
-                Some(s) => s.parse::<i32>().ok()?,
-                None => 0,
-            })?;
-            debug_assert!(significand != 0);
-
-            let (encoded_exponent, encoded_significand, discarded_significand) =
-                if exponent <= -bias {
-                    // Underflow to subnormal or zero.
-                    let shift = exp_offset as i32 + exponent + bias;
-                    if shift == 0 {
-                        (0, 0, significand)
-                    } else if shift < 0 || shift >= width as i32 {
-                        (0, 0, 0)
-                    } else {
-                        (
-                            0,
-                            significand >> (width as i32 - shift),
-                            significand << shift,
-                        )
-                    }
-                } else if exponent <= bias {
-                    // Normal (non-zero). The significand's leading 1 is encoded
-                    // implicitly.
-                    (
-                        ((exponent + bias) as $int) << exp_offset,
-                        (significand >> (width - exp_offset - 1)) & signif_mask,
-                        significand << (exp_offset + 1),
-                    )
-                } else {
-                    // Overflow to infinity.
-                    (
-                        ((1 << $exp_bits) - 1) << exp_offset,
-                        0,
-                        0,
-                    )
-                };
-
-            let bits = encoded_exponent | encoded_significand;
-
-            // Apply rounding. If this overflows the significand, it carries
-            // into the exponent bit according to the magic of the IEEE 754
-            // encoding.
-            //
-            // Or rather, the comment above is what Gecko says so it's copied
-            // here too.
-            let msb = 1 << (width - 1);
-            let bits = bits
-                + (((discarded_significand & msb != 0)
-                    && ((discarded_significand & !msb != 0) ||
-                         discarded_extra_nonzero ||
-                         // ties to even
-                         (encoded_significand & 1 != 0))) as $int);
-
-            // Just before we return the bits be sure to handle the sign bit we
-            // found at the beginning.
-            let bits = if negative {
-                bits | (1 << (width - 1))
-            } else {
-                bits
-            };
-            // looks like the `*.wat` format considers infinite overflow to
-            // be invalid.
-            if $float::from_bits(bits).is_infinite() {
-                return None;
-            }
-            Some(bits)
-        }
-
     )*)
 }
 
 float! {
     Float32 => {
         bits: u32,
-        float: f32,
-        exponent_bits: 8,
+        significand_bits: 23,
         name: strtof,
     }
     Float64 => {
         bits: u64,
-        float: f64,
-        exponent_bits: 11,
+        significand_bits: 52,
         name: strtod,
     }
 }
 
-fn to_hex(c: char) -> u8 {
-    match c {
-        'a'..='f' => c as u8 - b'a' + 10,
-        'A'..='F' => c as u8 - b'A' + 10,
-        _ => c as u8 - b'0',
-    }
-}
-
 /// A convenience type to use with [`Parser::peek`](crate::parser::Parser::peek)
 /// to see if the next token is an s-expression.
 pub struct LParen {
@@ -714,6 +608,10 @@ impl Peek for LParen {
     fn display() -> &'static str {
         "left paren"
     }
+
+    fn token_kind() -> TokenKind {
+        TokenKind::LParen
+    }
 }
 
 #[cfg(test)]
@@ -754,4 +652,80 @@ mod tests {
             Some(0x26800000)
         );
     }
+
+    #[test]
+    fn nan_classification() {
+        use super::{Float32, Float64};
+
+        let canonical = Float32::canonical_nan(true);
+        assert_eq!(canonical.bits, 0xffc0_0000);
+        assert!(canonical.is_nan() && canonical.is_sign_negative());
+        assert!(canonical.is_canonical_nan() && canonical.is_arithmetic_nan());
+        assert_eq!(canonical.nan_payload(), Some(0x40_0000));
+
+        let arithmetic = Float32::nan(false, 0x40_0001).unwrap();
+        assert_eq!(arithmetic.bits, 0x7fc0_0001);
+        assert!(!arithmetic.is_canonical_nan() && arithmetic.is_arithmetic_nan());
+
+        let signaling = Float64::nan(true, 1).unwrap();
+        assert_eq!(signaling.bits, 0xfff0_0000_0000_0001);
+        assert!(signaling.is_nan() && !signaling.is_arithmetic_nan());
+        assert_eq!(Float64::nan(false, 0), None);
+        assert_eq!(Float64::nan(false, 1 << 52), None);
+
+        let infinity = Float32 { bits: 0x7f80_0000 };
+        assert!(!infinity.is_nan() && infinity.nan_payload().is_none());
+        assert!(!Float64 { bits: 0 }.is_arithmetic_nan());
+        assert_ne!(Float64 { bits: 0 }, Float64 { bits: 1 << 63 });
+    }
+
+    #[test]
+    fn text_position() {
+        let input = "(module\r\n  (func)\n\n)\n;; end";
+        let buf = crate::parser::ParseBuffer::new(input).unwrap();
+        for offset in 0..=input.len() {
+            let span = super::Span::from_offset(offset);
+            assert_eq!(buf.text_position(offset), span.linecol_in(input));
+        }
+    }
+
+    #[test]
+    fn id_equality() {
+        use crate::parser::{self, ParseBuffer};
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        fn ids<'a>(buf: &'a ParseBuffer<'a>) -> Vec<super::Id<'a>> {
+            parser::parse::<Ids>(buf).unwrap().0
+        }
+        struct Ids<'a>(Vec<super::Id<'a>>);
+        impl<'a> parser::Parse<'a> for Ids<'a> {
+            fn parse(parser: parser::Parser<'a>) -> parser::Result<Self> {
+                let mut ids = Vec::new();
+                while !parser.is_empty() {
+                    ids.push(parser.parse()?);
+                }
+                Ok(Ids(ids))
+            }
+        }
+        fn hash(id: &super::Id<'_>) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            id.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let a = ParseBuffer::new("$foo $bar $\"foo\"").unwrap();
+        let b = ParseBuffer::new("$bar $foo").unwrap();
+        let a = ids(&a);
+        let b = ids(&b);
+        assert_eq!(a[0], a[2]);
+        assert_ne!(a[0], a[1]);
+        // Ids from different buffers, whose symbols are unrelated, are
+        // compared by name.
+        assert_eq!(a[0], b[1]);
+        assert_eq!(a[1], b[0]);
+        assert_ne!(a[0], b[0]);
+        assert_eq!(hash(&a[0]), hash(&b[1]));
+        assert_eq!(hash(&a[2]), hash(&b[1]));
+    }
 }
@@ -5,6 +5,7 @@ use std::mem;
 /// The value types for a wasm module.
 #[allow(missing_docs)]
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ValType<'a> {
     I32,
     I64,
@@ -69,6 +70,7 @@ impl<'a> Peek for ValType<'a> {
 /// A heap type for a reference type
 #[allow(missing_docs)]
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum HeapType<'a> {
     /// An untyped function reference: funcref. This is part of the reference
     /// types proposal.
@@ -140,6 +142,7 @@ impl<'a> Peek for HeapType<'a> {
 /// A reference type in a wasm module.
 #[allow(missing_docs)]
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RefType<'a> {
     pub nullable: bool,
     pub heap: HeapType<'a>,
@@ -264,6 +267,7 @@ impl<'a> Peek for RefType<'a> {
 /// The types of values that may be used in a struct or array.
 #[allow(missing_docs)]
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum StorageType<'a> {
     I8,
     I16,
@@ -289,27 +293,52 @@ impl<'a> Parse<'a> for StorageType<'a> {
 
 /// Type for a `global` in a wasm module
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct GlobalType<'a> {
     /// The element type of this `global`
     pub ty: ValType<'a>,
     /// Whether or not the global is mutable or not.
     pub mutable: bool,
+    /// Whether or not the global is shared between threads, as with
+    /// `(shared (mut i32))`.
+    ///
+    /// This is always `false` unless the `shared-everything-threads` feature
+    /// of this crate is enabled.
+    pub shared: bool,
 }
 
 impl<'a> Parse<'a> for GlobalType<'a> {
     fn parse(parser: Parser<'a>) -> Result<Self> {
+        if cfg!(feature = "shared-everything-threads") && parser.peek2::<kw::shared>() {
+            parser.parens(|p| {
+                p.parse::<kw::shared>()?;
+                Ok(GlobalType {
+                    shared: true,
+                    ..GlobalType::parse_unshared(p)?
+                })
+            })
+        } else {
+            GlobalType::parse_unshared(parser)
+        }
+    }
+}
+
+impl<'a> GlobalType<'a> {
+    fn parse_unshared(parser: Parser<'a>) -> Result<Self> {
         if parser.peek2::<kw::r#mut>() {
             parser.parens(|p| {
                 p.parse::<kw::r#mut>()?;
                 Ok(GlobalType {
                     ty: parser.parse()?,
                     mutable: true,
+                    shared: false,
                 })
             })
         } else {
             Ok(GlobalType {
                 ty: parser.parse()?,
                 mutable: false,
+                shared: false,
             })
         }
     }
@@ -317,6 +346,7 @@ impl<'a> Parse<'a> for GlobalType<'a> {
 
 /// Min/max limits used for tables/memories.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Limits {
     /// The minimum number of units for this type.
     pub min: u32,
@@ -338,6 +368,7 @@ impl<'a> Parse<'a> for Limits {
 
 /// Min/max limits used for 64-bit memories
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Limits64 {
     /// The minimum number of units for this type.
     pub min: u64,
@@ -359,24 +390,35 @@ impl<'a> Parse<'a> for Limits64 {
 
 /// Configuration for a table of a wasm mdoule
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct TableType<'a> {
     /// Limits on the element sizes of this table
     pub limits: Limits,
     /// The type of element stored in this table
     pub elem: RefType<'a>,
+    /// Whether or not the table is shared between threads, as with
+    /// `(table shared 1 funcref)`.
+    ///
+    /// This is always `false` unless the `shared-everything-threads` feature
+    /// of this crate is enabled.
+    pub shared: bool,
 }
 
 impl<'a> Parse<'a> for TableType<'a> {
     fn parse(parser: Parser<'a>) -> Result<Self> {
+        let shared = cfg!(feature = "shared-everything-threads")
+            && parser.parse::<Option<kw::shared>>()?.is_some();
         Ok(TableType {
             limits: parser.parse()?,
             elem: parser.parse()?,
+            shared,
         })
     }
 }
 
 /// Configuration for a memory of a wasm module
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum MemoryType {
     /// A 32-bit memory
     B32 {
@@ -412,6 +454,7 @@ impl<'a> Parse<'a> for MemoryType {
 
 /// A function type with parameters and results.
 #[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FunctionType<'a> {
     /// The parameters of a function, optionally each having an identifier for
     /// name resolution and a name for the custom `name` section.
@@ -502,6 +545,7 @@ impl<'a> Peek for FunctionType<'a> {
 
 /// A function type with parameters and results.
 #[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FunctionTypeNoNames<'a>(pub FunctionType<'a>);
 
 impl<'a> Parse<'a> for FunctionTypeNoNames<'a> {
@@ -533,6 +577,7 @@ impl<'a> From<FunctionTypeNoNames<'a>> for FunctionType<'a> {
 
 /// A struct type with fields.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct StructType<'a> {
     /// The fields of the struct
     pub fields: Vec<StructField<'a>>,
@@ -558,6 +603,7 @@ impl<'a> Parse<'a> for StructType<'a> {
 
 /// A field of a struct type.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct StructField<'a> {
     /// An optional identifier for name resolution.
     pub id: Option<ast::Id<'a>>,
@@ -585,6 +631,7 @@ impl<'a> StructField<'a> {
 
 /// An array type with fields.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ArrayType<'a> {
     /// Whether this field may be mutated or not.
     pub mutable: bool,
@@ -609,6 +656,7 @@ impl<'a> Parse<'a> for ArrayType<'a> {
 
 /// A type for a nested module
 #[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ModuleType<'a> {
     /// The imports that are expected for this module type.
     pub imports: Vec<ast::Import<'a>>,
@@ -652,6 +700,7 @@ impl<'a> Peek for ModuleType<'a> {
 
 /// A type for a nested instance
 #[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct InstanceType<'a> {
     /// The exported types from this instance
     pub exports: Vec<ExportType<'a>>,
@@ -686,6 +735,7 @@ impl<'a> Peek for InstanceType<'a> {
 
 /// The type of an exported item from a module or instance.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ExportType<'a> {
     /// Where this export was defined.
     pub span: ast::Span,
@@ -706,6 +756,7 @@ impl<'a> Parse<'a> for ExportType<'a> {
 
 /// A definition of a type.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum TypeDef<'a> {
     /// A function type definition.
     Func(FunctionType<'a>),
@@ -717,10 +768,33 @@ pub enum TypeDef<'a> {
     Module(ModuleType<'a>),
     /// An instance type definition.
     Instance(InstanceType<'a>),
+    /// A continuation type definition, from the stack-switching proposal.
+    #[cfg(feature = "stack-switching")]
+    Cont(ContType<'a>),
+}
+
+/// A continuation type, the type of continuations of functions of a function
+/// type.
+#[cfg(feature = "stack-switching")]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ContType<'a> {
+    /// The function type of the continuation.
+    pub idx: ast::Index<'a>,
+}
+
+#[cfg(feature = "stack-switching")]
+impl<'a> Parse<'a> for ContType<'a> {
+    fn parse(parser: Parser<'a>) -> Result<Self> {
+        Ok(ContType {
+            idx: parser.parse()?,
+        })
+    }
 }
 
 /// A type declaration in a module
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Type<'a> {
     /// Where this type was defined.
     pub span: ast::Span,
@@ -729,39 +803,69 @@ pub struct Type<'a> {
     pub id: Option<ast::Id<'a>>,
     /// The type that we're declaring.
     pub def: TypeDef<'a>,
+    /// Whether or not values of this type may be shared between threads, as
+    /// with `(type (shared (func)))`.
+    ///
+    /// This is always `false` unless the `shared-everything-threads` feature
+    /// of this crate is enabled.
+    pub shared: bool,
 }
 
 impl<'a> Parse<'a> for Type<'a> {
     fn parse(parser: Parser<'a>) -> Result<Self> {
         let span = parser.parse::<kw::r#type>()?.0;
         let id = parser.parse()?;
-        let def = parser.parens(|parser| {
-            let mut l = parser.lookahead1();
-            if l.peek::<kw::func>() {
-                parser.parse::<kw::func>()?;
-                Ok(TypeDef::Func(parser.parse()?))
-            } else if l.peek::<kw::r#struct>() {
-                parser.parse::<kw::r#struct>()?;
-                Ok(TypeDef::Struct(parser.parse()?))
-            } else if l.peek::<kw::array>() {
-                parser.parse::<kw::array>()?;
-                Ok(TypeDef::Array(parser.parse()?))
-            } else if l.peek::<kw::module>() {
-                parser.parse::<kw::module>()?;
-                Ok(TypeDef::Module(parser.parse()?))
-            } else if l.peek::<kw::instance>() {
-                parser.parse::<kw::instance>()?;
-                Ok(TypeDef::Instance(parser.parse()?))
+        let (shared, def) = parser.parens(|parser| {
+            if cfg!(feature = "shared-everything-threads") && parser.peek::<kw::shared>() {
+                parser.parse::<kw::shared>()?;
+                Ok((true, parser.parens(TypeDef::parse)?))
             } else {
-                Err(l.error())
+                Ok((false, TypeDef::parse(parser)?))
             }
         })?;
-        Ok(Type { span, id, def })
+        Ok(Type {
+            span,
+            id,
+            def,
+            shared,
+        })
+    }
+}
+
+impl<'a> Parse<'a> for TypeDef<'a> {
+    fn parse(parser: Parser<'a>) -> Result<Self> {
+        let mut l = parser.lookahead1();
+        if l.peek::<kw::func>() {
+            parser.parse::<kw::func>()?;
+            Ok(TypeDef::Func(parser.parse()?))
+        } else if l.peek::<kw::r#struct>() {
+            parser.parse::<kw::r#struct>()?;
+            Ok(TypeDef::Struct(parser.parse()?))
+        } else if l.peek::<kw::array>() {
+            parser.parse::<kw::array>()?;
+            Ok(TypeDef::Array(parser.parse()?))
+        } else if l.peek::<kw::module>() {
+            parser.parse::<kw::module>()?;
+            Ok(TypeDef::Module(parser.parse()?))
+        } else if l.peek::<kw::instance>() {
+            parser.parse::<kw::instance>()?;
+            Ok(TypeDef::Instance(parser.parse()?))
+        } else {
+            #[cfg(feature = "stack-switching")]
+            {
+                if l.peek::<kw::cont>() {
+                    parser.parse::<kw::cont>()?;
+                    return Ok(TypeDef::Cont(parser.parse()?));
+                }
+            }
+            Err(l.error())
+        }
     }
 }
 
 /// A reference to a type defined in this module.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct TypeUse<'a, T> {
     /// The type that we're referencing, if it was present.
     pub index: Option<ast::ItemRef<'a, kw::r#type>>,
@@ -7,6 +7,7 @@ use crate::{AssertExpression, NanPattern, V128Pattern};
 /// WAST files are not officially specified but are used in the official test
 /// suite to write official spec tests for wasm. This type represents a parsed
 /// `*.wast` file which parses a list of directives in a file.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Wast<'a> {
     #[allow(missing_docs)]
     pub directives: Vec<WastDirective<'a>>,
@@ -51,6 +52,7 @@ impl Peek for WastDirectiveToken {
 /// It's not entirely clear to me what all of these are per se, but they're only
 /// really interesting to test harnesses mostly.
 #[allow(missing_docs)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum WastDirective<'a> {
     Module(ast::Module<'a>),
     QuoteModule {
@@ -102,7 +104,7 @@ impl WastDirective<'_> {
             WastDirective::Module(m) => m.span,
             WastDirective::AssertMalformed { span, .. }
             | WastDirective::Register { span, .. }
-            | WastDirective::QuoteModule{ span, .. }
+            | WastDirective::QuoteModule { span, .. }
             | WastDirective::AssertTrap { span, .. }
             | WastDirective::AssertReturn { span, .. }
             | WastDirective::AssertExhaustion { span, .. }
@@ -254,6 +256,7 @@ impl<'a> Parse<'a> for WastDirective<'a> {
 }
 
 #[allow(missing_docs)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum WastExecute<'a> {
     Invoke(WastInvoke<'a>),
     Module(ast::Module<'a>),
@@ -283,6 +286,7 @@ impl<'a> Parse<'a> for WastExecute<'a> {
 }
 
 #[allow(missing_docs)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct WastInvoke<'a> {
     pub span: ast::Span,
     pub module: Option<ast::Id<'a>>,
@@ -309,6 +313,7 @@ impl<'a> Parse<'a> for WastInvoke<'a> {
 }
 
 #[allow(missing_docs)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum QuoteModule<'a> {
     Module(ast::Module<'a>),
     Quote(Vec<&'a [u8]>),
@@ -348,9 +353,50 @@ mod tests {
 
     #[test]
     fn assert_nan() {
-        assert_parses_to_directive!("assert_return_canonical_nan_f32x4 (invoke \"foo\" (f32.const 0))", WastDirective::AssertReturn { .. });
-        assert_parses_to_directive!("assert_return_canonical_nan_f64x2 (invoke \"foo\" (f32.const 0))", WastDirective::AssertReturn { .. });
-        assert_parses_to_directive!("assert_return_arithmetic_nan_f32x4 (invoke \"foo\" (f32.const 0))", WastDirective::AssertReturn { .. });
-        assert_parses_to_directive!("assert_return_arithmetic_nan_f64x2 (invoke \"foo\" (f32.const 0))", WastDirective::AssertReturn { .. });
+        assert_parses_to_directive!(
+            "assert_return_canonical_nan_f32x4 (invoke \"foo\" (f32.const 0))",
+            WastDirective::AssertReturn { .. }
+        );
+        assert_parses_to_directive!(
+            "assert_return_canonical_nan_f64x2 (invoke \"foo\" (f32.const 0))",
+            WastDirective::AssertReturn { .. }
+        );
+        assert_parses_to_directive!(
+            "assert_return_arithmetic_nan_f32x4 (invoke \"foo\" (f32.const 0))",
+            WastDirective::AssertReturn { .. }
+        );
+        assert_parses_to_directive!(
+            "assert_return_arithmetic_nan_f64x2 (invoke \"foo\" (f32.const 0))",
+            WastDirective::AssertReturn { .. }
+        );
+    }
+
+    #[test]
+    fn nan_patterns_match() {
+        use crate::ast::AssertExpression;
+
+        let buffer = ParseBuffer::new(
+            "assert_return (invoke \"foo\") \
+             (f32.const nan:canonical) (f64.const nan:arithmetic) (f32.const -nan:0x1)",
+        )
+        .unwrap();
+        let results = match parse::<WastDirective>(&buffer).unwrap() {
+            WastDirective::AssertReturn { results, .. } => results,
+            _ => panic!("not an assert_return"),
+        };
+        match &results[..] {
+            [AssertExpression::F32(canonical), AssertExpression::F64(arithmetic), AssertExpression::F32(value)] =>
+            {
+                assert!(canonical.matches(0x7fc0_0000));
+                assert!(canonical.matches(0xffc0_0000));
+                assert!(!canonical.matches(0x7fc0_0001));
+                assert!(arithmetic.matches(0xfff8_0000_0000_0001));
+                assert!(!arithmetic.matches(0x7ff0_0000_0000_0001));
+                assert!(!arithmetic.matches(0x7ff0_0000_0000_0000));
+                assert!(value.matches(0xff80_0001));
+                assert!(!value.matches(0x7f80_0001));
+            }
+            _ => panic!("unexpected results {:?}", results),
+        }
     }
 }
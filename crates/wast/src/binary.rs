@@ -1,9 +1,85 @@
 use crate::ast::*;
 
-pub fn encode(module: &Module<'_>) -> Vec<u8> {
+pub fn encode(
+    module: &Module<'_>,
+    offsets: Option<&mut Vec<InstrOffset>>,
+    notes: Option<&mut Vec<Note>>,
+) -> Vec<u8> {
     match &module.kind {
-        ModuleKind::Text(fields) => encode_fields(&module.id, &module.name, fields),
-        ModuleKind::Binary(bytes) => bytes.iter().flat_map(|b| b.iter().cloned()).collect(),
+        ModuleKind::Text(fields) => encode_fields(&module.id, &module.name, fields, offsets, notes),
+        ModuleKind::Binary(bytes) => {
+            let wasm = bytes
+                .iter()
+                .flat_map(|b| b.iter().cloned())
+                .collect::<Vec<_>>();
+            if let Some(notes) = notes {
+                notes.push(Note::new(0, wasm.len(), "binary module"));
+            }
+            wasm
+        }
+    }
+}
+
+/// A description of a range of the bytes written by the encoder.
+///
+/// Notes are written in order and don't overlap. A note with no bytes is a
+/// heading for the notes following it.
+pub(crate) struct Note {
+    offset: usize,
+    len: usize,
+    text: String,
+}
+
+impl Note {
+    fn new(offset: usize, len: usize, text: impl Into<String>) -> Note {
+        Note {
+            offset,
+            len,
+            text: text.into(),
+        }
+    }
+}
+
+/// Returns a hexdump of `wasm` with the bytes of each of `notes` on their own
+/// lines, followed by its description.
+pub(crate) fn dump(wasm: &[u8], notes: &[Note]) -> String {
+    let mut dump = String::new();
+    let mut offset = 0;
+    for note in notes {
+        if note.len == 0 {
+            dump.push_str(&format!("; {}\n", note.text));
+            continue;
+        }
+        // Bytes which aren't described by any note are still dumped.
+        dump_bytes(&mut dump, wasm, offset..note.offset, None);
+        offset = note.offset + note.len;
+        dump_bytes(&mut dump, wasm, note.offset..offset, Some(&note.text));
+    }
+    dump_bytes(&mut dump, wasm, offset..wasm.len(), None);
+    dump
+}
+
+/// Appends the bytes `range` of `wasm` to `dump`, 16 per line, with `text` at
+/// the end of the first line.
+fn dump_bytes(dump: &mut String, wasm: &[u8], range: std::ops::Range<usize>, text: Option<&str>) {
+    const BYTES_PER_LINE: usize = 16;
+    for start in range.clone().step_by(BYTES_PER_LINE) {
+        let bytes = &wasm[start..range.end.min(start + BYTES_PER_LINE)];
+        let hex = bytes
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<Vec<_>>()
+            .join(" ");
+        match text.filter(|_| start == range.start) {
+            Some(text) => dump.push_str(&format!(
+                "{:07x}: {:<width$} ; {}\n",
+                start,
+                hex,
+                text,
+                width = BYTES_PER_LINE * 3 - 1
+            )),
+            None => dump.push_str(&format!("{:07x}: {}\n", start, hex)),
+        }
     }
 }
 
@@ -11,6 +87,8 @@ fn encode_fields(
     module_id: &Option<Id<'_>>,
     module_name: &Option<NameAnnotation<'_>>,
     fields: &[ModuleField<'_>],
+    mut offsets: Option<&mut Vec<InstrOffset>>,
+    notes: Option<&mut Vec<Note>>,
 ) -> Vec<u8> {
     use crate::ast::CustomPlace::*;
     use crate::ast::CustomPlaceAnchor::*;
@@ -44,6 +122,7 @@ fn encode_fields(
             ModuleField::Data(i) => data.push(i),
             ModuleField::Event(i) => events.push(i),
             ModuleField::Custom(i) => customs.push(i),
+            ModuleField::Error(_) => {}
             ModuleField::Instance(i) => instances.push(i),
             ModuleField::NestedModule(i) => modules.push(i),
             ModuleField::Alias(a) => aliases.push(a),
@@ -54,9 +133,15 @@ fn encode_fields(
         wasm: Vec::new(),
         tmp: Vec::new(),
         customs: &customs,
+        notes: notes.as_ref().map(|_| Vec::new()),
+        tmp_notes: Vec::new(),
     };
     e.wasm.extend(b"\0asm");
     e.wasm.extend(b"\x01\0\0\0");
+    if let Some(notes) = &mut e.notes {
+        notes.push(Note::new(0, 4, "magic"));
+        notes.push(Note::new(4, 4, "version"));
+    }
 
     e.custom_sections(BeforeFirst);
 
@@ -118,15 +203,52 @@ fn encode_fields(
     if contains_bulk_memory(&funcs) {
         e.section(12, &data.len());
     }
-    e.section_list(10, Code, &funcs);
+    let first_func = imported_funcs(fields);
+    // The branch hint section has to precede the code section it refers to.
+    let branch_hints = find_branch_hints(first_func, &funcs);
+    if !branch_hints.is_empty() {
+        e.custom_section("metadata.code.branch_hint", &branch_hints);
+    }
+    if offsets.is_none() && e.notes.is_none() {
+        e.section_list(10, Code, &funcs);
+    } else {
+        e.custom_sections(Before(Code));
+        if !funcs.is_empty() {
+            e.tmp.truncate(0);
+            funcs.len().encode(&mut e.tmp);
+            e.note_tmp(0, || "function count".to_string());
+            let mut instrs = Vec::new();
+            for (i, func) in funcs.iter().enumerate() {
+                let start = e.tmp.len();
+                func.encode_body(&mut e.tmp, Some(&mut instrs));
+                let func_index = (first_func + i) as u32;
+                if e.notes.is_some() {
+                    e.note_body(func_index, func, start, &instrs);
+                }
+                if let Some(offsets) = &mut offsets {
+                    offsets.extend(instrs.iter().map(|&(offset, span)| InstrOffset {
+                        func: func_index,
+                        offset,
+                        span,
+                    }));
+                }
+                instrs.clear();
+            }
+            e.finish_section(10, "code");
+        }
+        e.custom_sections(After(Code));
+    }
     e.section_list(11, Data, &data);
 
     let names = find_names(module_id, module_name, fields);
     if !names.is_empty() {
-        e.section(0, &("name", names));
+        e.custom_section("name", &names);
     }
     e.custom_sections(AfterLast);
 
+    if let (Some(notes), Some(encoded)) = (notes, e.notes) {
+        notes.extend(encoded);
+    }
     return e.wasm;
 
     fn contains_bulk_memory(funcs: &[&crate::ast::Func<'_>]) -> bool {
@@ -144,24 +266,239 @@ fn encode_fields(
     }
 }
 
+/// Returns the number of functions which `fields` import or alias, which come
+/// before the functions they define.
+fn imported_funcs(fields: &[ModuleField<'_>]) -> usize {
+    fields
+        .iter()
+        .filter(|field| match field {
+            ModuleField::Import(i) => matches!(i.item.kind, ItemKind::Func(_)),
+            ModuleField::Alias(a) => matches!(
+                a.kind,
+                AliasKind::InstanceExport {
+                    kind: ExportKind::Func,
+                    ..
+                }
+            ),
+            _ => false,
+        })
+        .count()
+}
+
+/// What a comment of the text came before, see the `metadata.comments`
+/// section encoded by `encode_comments`.
+#[derive(Clone, Copy)]
+enum CommentAnchor {
+    Module,
+    Item(u8, u32),
+    Instruction(u32, usize),
+    End,
+}
+
+/// Returns a `metadata.comments` custom section recording the comments of
+/// `wat`, the text `module` was parsed from, or nothing if it has none.
+///
+/// Each comment is anchored to the item or instruction which follows it in
+/// the text, so it can be printed before the same item or instruction when
+/// printing the binary.
+pub(crate) fn encode_comments(module: &Module<'_>, wat: &str) -> Vec<u8> {
+    let fields = match &module.kind {
+        ModuleKind::Text(fields) => fields,
+        ModuleKind::Binary(_) => return Vec::new(),
+    };
+
+    // Find where each item and instruction starts in the text.
+    let mut anchors = Vec::new();
+    let mut items = [0u32; 17];
+    let mut item = |anchors: &mut Vec<_>, section: u8, span: Span| {
+        let index = items[section as usize];
+        items[section as usize] += 1;
+        anchors.push((span.offset, CommentAnchor::Item(section, index)));
+    };
+    let mut func = imported_funcs(fields) as u32;
+    for field in fields {
+        match field {
+            ModuleField::Type(i) => item(&mut anchors, 1, i.span),
+            ModuleField::Import(i) => item(&mut anchors, 2, i.span),
+            ModuleField::Func(i) => {
+                item(&mut anchors, 3, i.span);
+                let (expr, locals) = match &i.kind {
+                    FuncKind::Inline { expression, locals } => (expression, locals),
+                    _ => panic!("should only have inline functions in emission"),
+                };
+                let mut tmp = Vec::new();
+                locals.encode(&mut tmp);
+                for (instr, span) in expr.instrs.iter().zip(expr.instr_spans.iter()) {
+                    anchors.push((span.offset, CommentAnchor::Instruction(func, tmp.len())));
+                    instr.encode(&mut tmp);
+                }
+                func += 1;
+            }
+            ModuleField::Table(i) => item(&mut anchors, 4, i.span),
+            ModuleField::Memory(i) => item(&mut anchors, 5, i.span),
+            ModuleField::Global(i) => item(&mut anchors, 6, i.span),
+            ModuleField::Export(i) => item(&mut anchors, 7, i.span),
+            ModuleField::Start(i) => item(&mut anchors, 8, i.unwrap_index().span()),
+            ModuleField::Elem(i) => item(&mut anchors, 9, i.span),
+            ModuleField::Data(i) => item(&mut anchors, 11, i.span),
+            ModuleField::Event(i) => item(&mut anchors, 13, i.span),
+            ModuleField::NestedModule(i) => item(&mut anchors, 14, i.span),
+            ModuleField::Instance(i) => item(&mut anchors, 15, i.span),
+            ModuleField::Alias(i) => item(&mut anchors, 16, i.span),
+            ModuleField::Custom(_) | ModuleField::Error(_) => {}
+        }
+    }
+    anchors.sort_by_key(|(offset, _)| *offset);
+
+    let mut comments = Vec::new();
+    for token in crate::lexer::Lexer::new(wat) {
+        let text = match token {
+            Ok(crate::lexer::Token::LineComment(text))
+            | Ok(crate::lexer::Token::BlockComment(text)) => text,
+            Ok(_) => continue,
+            Err(_) => break,
+        };
+        let offset = text.as_ptr() as usize - wat.as_ptr() as usize;
+        let anchor = if offset < module.span.offset {
+            CommentAnchor::Module
+        } else {
+            let next = anchors.partition_point(|(start, _)| *start < offset);
+            match anchors.get(next) {
+                Some((_, anchor)) => *anchor,
+                None => CommentAnchor::End,
+            }
+        };
+        comments.push((anchor, text));
+    }
+    if comments.is_empty() {
+        return Vec::new();
+    }
+
+    let mut contents = Vec::new();
+    ("metadata.comments", comments).encode(&mut contents);
+    let mut section = vec![0];
+    contents.encode(&mut section);
+    section
+}
+
+impl Encode for CommentAnchor {
+    fn encode(&self, e: &mut Vec<u8>) {
+        match *self {
+            CommentAnchor::Module => e.push(0x00),
+            CommentAnchor::Item(section, index) => {
+                e.push(0x01);
+                e.push(section);
+                index.encode(e);
+            }
+            CommentAnchor::Instruction(func, offset) => {
+                e.push(0x02);
+                func.encode(e);
+                offset.encode(e);
+            }
+            CommentAnchor::End => e.push(0x03),
+        }
+    }
+}
+
 struct Encoder<'a> {
     wasm: Vec<u8>,
     tmp: Vec<u8>,
     customs: &'a [&'a Custom<'a>],
+    /// The notes describing `wasm`, if they're being recorded.
+    notes: Option<Vec<Note>>,
+    /// The notes describing `tmp`, relative to its start.
+    tmp_notes: Vec<Note>,
 }
 
 impl Encoder<'_> {
     fn section(&mut self, id: u8, section: &dyn Encode) {
         self.tmp.truncate(0);
         section.encode(&mut self.tmp);
+        self.note_tmp(0, || match id {
+            8 => "start function".to_string(),
+            12 => "data count".to_string(),
+            _ => "contents".to_string(),
+        });
+        self.finish_section(id, section_name(id));
+    }
+
+    fn custom_section(&mut self, name: &str, contents: &dyn Encode) {
+        self.tmp.truncate(0);
+        name.encode(&mut self.tmp);
+        self.note_tmp(0, || "name".to_string());
+        let start = self.tmp.len();
+        contents.encode(&mut self.tmp);
+        self.note_tmp(start, || "contents".to_string());
+        self.finish_section(0, name);
+    }
+
+    /// Writes the section `id` with the contents in `tmp`.
+    fn finish_section(&mut self, id: u8, name: &str) {
+        if let Some(notes) = &mut self.notes {
+            let heading = match id {
+                0 => format!("custom section {:?}", name),
+                _ => format!("section {:?} ({})", name, id),
+            };
+            notes.push(Note::new(self.wasm.len(), 0, heading));
+            notes.push(Note::new(self.wasm.len(), 1, "section code"));
+        }
         self.wasm.push(id);
-        self.tmp.encode(&mut self.wasm);
+        let size = self.wasm.len();
+        self.tmp.len().encode(&mut self.wasm);
+        if let Some(notes) = &mut self.notes {
+            let contents = self.wasm.len();
+            notes.push(Note::new(size, contents - size, "section size"));
+            notes.extend(self.tmp_notes.drain(..).map(|note| Note {
+                offset: contents + note.offset,
+                ..note
+            }));
+        }
+        self.wasm.extend_from_slice(&self.tmp);
+    }
+
+    /// Describes the bytes of `tmp` from `start` up to its end, if notes are
+    /// being recorded and there are any.
+    fn note_tmp(&mut self, start: usize, text: impl FnOnce() -> String) {
+        if self.notes.is_some() && self.tmp.len() > start {
+            let note = Note::new(start, self.tmp.len() - start, text());
+            self.tmp_notes.push(note);
+        }
+    }
+
+    /// Describes the body of `func`, which was encoded at `start` in `tmp`
+    /// with its instructions at `instrs`.
+    fn note_body(&mut self, index: u32, func: &Func<'_>, start: usize, instrs: &[(usize, Span)]) {
+        let expr = match &func.kind {
+            FuncKind::Inline { expression, .. } => expression,
+            _ => panic!("should only have inline functions in emission"),
+        };
+        let size = self.tmp[start..]
+            .iter()
+            .position(|b| b & 0x80 == 0)
+            .unwrap()
+            + 1;
+        let mut ranges = vec![
+            (start, format!("func {} body size", index)),
+            (start + size, "local decls".to_string()),
+        ];
+        for (i, (offset, _)) in instrs.iter().enumerate() {
+            let text = match expr.instrs.get(i) {
+                Some(instr) => instr.opcode().mnemonic,
+                None => "end",
+            };
+            ranges.push((*offset, text.to_string()));
+        }
+        for (i, (offset, text)) in ranges.iter().enumerate() {
+            let end = ranges.get(i + 1).map_or(self.tmp.len(), |r| r.0);
+            self.tmp_notes
+                .push(Note::new(*offset, end - offset, text.clone()));
+        }
     }
 
     fn custom_sections(&mut self, place: CustomPlace) {
         for entry in self.customs.iter() {
             if entry.place == place {
-                self.section(0, &(entry.name, entry));
+                self.custom_section(entry.name, entry);
             }
         }
     }
@@ -169,10 +506,49 @@ impl Encoder<'_> {
     fn section_list(&mut self, id: u8, anchor: CustomPlaceAnchor, list: &[impl Encode]) {
         self.custom_sections(CustomPlace::Before(anchor));
         if !list.is_empty() {
-            self.section(id, &list)
+            if self.notes.is_some() {
+                self.annotated_section_list(id, list);
+            } else {
+                self.section(id, &list)
+            }
         }
         self.custom_sections(CustomPlace::After(anchor));
     }
+
+    /// Same as `section`, but describes each item of `list` separately.
+    fn annotated_section_list(&mut self, id: u8, list: &[impl Encode]) {
+        self.tmp.truncate(0);
+        list.len().encode(&mut self.tmp);
+        self.note_tmp(0, || "entry count".to_string());
+        for (i, item) in list.iter().enumerate() {
+            let start = self.tmp.len();
+            item.encode(&mut self.tmp);
+            self.note_tmp(start, || format!("{} {}", section_name(id), i));
+        }
+        self.finish_section(id, section_name(id));
+    }
+}
+
+fn section_name(id: u8) -> &'static str {
+    match id {
+        1 => "type",
+        2 => "import",
+        3 => "function",
+        4 => "table",
+        5 => "memory",
+        6 => "global",
+        7 => "export",
+        8 => "start",
+        9 => "elem",
+        10 => "code",
+        11 => "data",
+        12 => "data count",
+        13 => "event",
+        14 => "module",
+        15 => "instance",
+        16 => "alias",
+        _ => "unknown",
+    }
 }
 
 pub(crate) trait Encode {
@@ -293,6 +669,9 @@ impl Encode for ExportType<'_> {
 
 impl Encode for Type<'_> {
     fn encode(&self, e: &mut Vec<u8>) {
+        if self.shared {
+            e.push(0x65);
+        }
         match &self.def {
             TypeDef::Func(func) => {
                 e.push(0x60);
@@ -314,6 +693,11 @@ impl Encode for Type<'_> {
                 e.push(0x62);
                 instance.encode(e)
             }
+            #[cfg(feature = "stack-switching")]
+            TypeDef::Cont(cont) => {
+                e.push(0x5d);
+                cont.idx.encode(e)
+            }
         }
     }
 }
@@ -516,7 +900,17 @@ impl<T> Encode for ItemRef<'_, T> {
 impl<'a> Encode for TableType<'a> {
     fn encode(&self, e: &mut Vec<u8>) {
         self.elem.encode(e);
-        self.limits.encode(e);
+        if self.shared {
+            // Shared tables use the same flag as shared memories.
+            let flag_max = self.limits.max.is_some() as u8;
+            e.push(flag_max | 0x02);
+            self.limits.min.encode(e);
+            if let Some(max) = self.limits.max {
+                max.encode(e);
+            }
+        } else {
+            self.limits.encode(e);
+        }
     }
 }
 
@@ -566,11 +960,9 @@ impl Encode for MemoryType {
 impl<'a> Encode for GlobalType<'a> {
     fn encode(&self, e: &mut Vec<u8>) {
         self.ty.encode(e);
-        if self.mutable {
-            e.push(0x01);
-        } else {
-            e.push(0x00);
-        }
+        let flag_mutable = self.mutable as u8;
+        let flag_shared = self.shared as u8;
+        e.push(flag_mutable | (flag_shared << 1));
     }
 }
 
@@ -768,15 +1160,23 @@ impl Encode for Data<'_> {
                 offset.encode(e);
             }
         }
-        self.data.iter().map(|l| l.len()).sum::<usize>().encode(e);
-        for val in self.data.iter() {
-            val.push_onto(e);
+        self.len().encode(e);
+        for chunk in self.chunks() {
+            e.extend_from_slice(chunk);
         }
     }
 }
 
 impl Encode for Func<'_> {
     fn encode(&self, e: &mut Vec<u8>) {
+        self.encode_body(e, None)
+    }
+}
+
+impl Func<'_> {
+    /// Encodes this function's body, and pushes the offset in `e` and the
+    /// span of each of its instructions onto `instrs` if given.
+    fn encode_body(&self, e: &mut Vec<u8>, instrs: Option<&mut Vec<(usize, Span)>>) {
         assert!(self.exports.names.is_empty());
         let mut tmp = Vec::new();
         let (expr, locals) = match &self.kind {
@@ -785,10 +1185,30 @@ impl Encode for Func<'_> {
         };
 
         locals.encode(&mut tmp);
-        expr.encode(&mut tmp);
+        let mut body_instrs = Vec::new();
+        match instrs {
+            Some(_) => {
+                for (instr, span) in expr.instrs.iter().zip(expr.instr_spans.iter()) {
+                    body_instrs.push((tmp.len(), *span));
+                    instr.encode(&mut tmp);
+                }
+                // The implicit final `end` is located at the function itself.
+                body_instrs.push((tmp.len(), self.span));
+                tmp.push(0x0b);
+            }
+            None => expr.encode(&mut tmp),
+        }
 
         tmp.len().encode(e);
+        let start = e.len();
         e.extend_from_slice(&tmp);
+        if let Some(instrs) = instrs {
+            instrs.extend(
+                body_instrs
+                    .into_iter()
+                    .map(|(offset, span)| (start + offset, span)),
+            );
+        }
     }
 }
 
@@ -936,6 +1356,9 @@ impl Encode for MemoryCopy<'_> {
 
 impl Encode for BrTableIndices<'_> {
     fn encode(&self, e: &mut Vec<u8>) {
+        // Most labels are encoded in a single byte, so reserve space for all
+        // of them up front as tables can have tens of thousands of entries.
+        e.reserve(self.labels.len() + 6);
         self.labels.encode(e);
         self.default.encode(e);
     }
@@ -953,6 +1376,53 @@ impl Encode for Float64 {
     }
 }
 
+/// A hint of a `metadata.code.branch_hint` section, at an offset relative to
+/// the start of its function's body.
+struct BranchHintEntry {
+    offset: usize,
+    value: u32,
+}
+
+fn find_branch_hints(first_func: usize, funcs: &[&Func<'_>]) -> Vec<(u32, Vec<BranchHintEntry>)> {
+    let mut ret = Vec::new();
+    for (i, func) in funcs.iter().enumerate() {
+        let (expr, locals) = match &func.kind {
+            FuncKind::Inline { expression, locals } => (expression, locals),
+            _ => panic!("should only have inline functions in emission"),
+        };
+        if expr.branch_hints.is_empty() {
+            continue;
+        }
+        // Encode the body up to the last hinted instruction to find the
+        // offsets of the hinted instructions.
+        let mut tmp = Vec::new();
+        locals.encode(&mut tmp);
+        let mut hints = Vec::new();
+        let mut next = 0;
+        for hint in expr.branch_hints.iter() {
+            for instr in &expr.instrs[next..hint.instr_index] {
+                instr.encode(&mut tmp);
+            }
+            next = next.max(hint.instr_index);
+            hints.push(BranchHintEntry {
+                offset: tmp.len(),
+                value: hint.value,
+            });
+        }
+        ret.push(((first_func + i) as u32, hints));
+    }
+    ret
+}
+
+impl Encode for BranchHintEntry {
+    fn encode(&self, e: &mut Vec<u8>) {
+        self.offset.encode(e);
+        // The size of the hint's value, which is always a single byte.
+        1u32.encode(e);
+        (self.value as u8).encode(e);
+    }
+}
+
 struct Names<'a> {
     module: Option<&'a str>,
     funcs: Vec<(u32, &'a str)>,
@@ -1143,6 +1613,56 @@ impl Encode for BrOnExn<'_> {
     }
 }
 
+#[cfg(feature = "stack-switching")]
+impl Encode for ContBind<'_> {
+    fn encode(&self, e: &mut Vec<u8>) {
+        self.src.encode(e);
+        self.dst.encode(e);
+    }
+}
+
+#[cfg(feature = "stack-switching")]
+impl Encode for Resume<'_> {
+    fn encode(&self, e: &mut Vec<u8>) {
+        self.ty.encode(e);
+        self.handlers.encode(e);
+    }
+}
+
+#[cfg(feature = "stack-switching")]
+impl Encode for ResumeThrow<'_> {
+    fn encode(&self, e: &mut Vec<u8>) {
+        self.ty.encode(e);
+        self.tag.encode(e);
+        self.handlers.encode(e);
+    }
+}
+
+#[cfg(feature = "stack-switching")]
+impl Encode for Handle<'_> {
+    fn encode(&self, e: &mut Vec<u8>) {
+        match self {
+            Handle::OnLabel { tag, label } => {
+                e.push(0x00);
+                tag.encode(e);
+                label.encode(e);
+            }
+            Handle::OnSwitch { tag } => {
+                e.push(0x01);
+                tag.encode(e);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "stack-switching")]
+impl Encode for Switch<'_> {
+    fn encode(&self, e: &mut Vec<u8>) {
+        self.ty.encode(e);
+        self.tag.encode(e);
+    }
+}
+
 impl Encode for BrOnCast<'_> {
     fn encode(&self, e: &mut Vec<u8>) {
         self.label.encode(e);
@@ -1187,7 +1707,7 @@ impl Encode for NestedModule<'_> {
             _ => panic!("should only have inline modules in emission"),
         };
 
-        encode_fields(&self.id, &self.name, fields).encode(e);
+        encode_fields(&self.id, &self.name, fields, None, None).encode(e);
     }
 }
 
@@ -0,0 +1,148 @@
+//! Extraction of documentation comments from the text of a module.
+//!
+//! A documentation comment is a run of line comments starting with a prefix,
+//! [`DEFAULT_PREFIX`] by default, on the lines just before the module or one of
+//! its fields:
+//!
+//! ```
+//! use wast::docs::{doc_comments, DEFAULT_PREFIX};
+//! use wast::parser::{self, ParseBuffer};
+//! use wast::Wat;
+//!
+//! # fn foo() -> Result<(), wast::Error> {
+//! let wat = r#"
+//!     ;;; Arithmetic helpers.
+//!     (module
+//!       ;; Not documentation.
+//!       (type (func))
+//!
+//!       ;;; Adds one to its argument.
+//!       ;;;
+//!       ;;; Wraps on overflow.
+//!       (func $inc (param i32) (result i32)
+//!         local.get 0
+//!         i32.const 1
+//!         i32.add))
+//! "#;
+//! let buf = ParseBuffer::new(wat)?;
+//! let module = parser::parse::<Wat>(&buf)?.module;
+//! let docs = doc_comments(&module, wat, DEFAULT_PREFIX);
+//! assert_eq!(docs[0].field, None);
+//! assert_eq!(docs[0].text(), "Arithmetic helpers.");
+//! assert_eq!(docs[1].field, Some(1));
+//! assert_eq!(docs[1].text(), "Adds one to its argument.\n\nWraps on overflow.");
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::ast::{Module, ModuleField, ModuleKind, Span};
+use crate::lexer::{Token, TokenStream};
+use std::collections::HashMap;
+
+/// The prefix of documentation comments unless another is given.
+pub const DEFAULT_PREFIX: &str = ";;;";
+
+/// The documentation comment of a module or one of its fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DocComment<'a> {
+    /// The index of the documented field in the fields of the module, or
+    /// `None` if the module itself is documented.
+    pub field: Option<usize>,
+    /// The span of the documented module or field.
+    pub span: Span,
+    /// The text of each line of the comment, without its prefix or the space
+    /// following the prefix, if there is one.
+    pub lines: Vec<&'a str>,
+}
+
+impl DocComment<'_> {
+    /// Returns the lines of this comment joined by newlines.
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+}
+
+/// Returns the documentation comments of `module` and its fields in `wat`, the
+/// text `module` was parsed from, in the order they appear.
+///
+/// Documentation comments are line comments starting with `prefix` which are
+/// followed by the documented item, with nothing but single newlines and
+/// indentation between them. Any other comment or a blank line ends a
+/// documentation comment, and comments after a token on the same line are
+/// never documentation.
+///
+/// Fields are numbered as they were parsed, so this should be called before
+/// the module is resolved or encoded, which adds and removes fields. Fields
+/// of nested modules, start fields and custom sections aren't documented.
+pub fn doc_comments<'a>(module: &Module<'a>, wat: &'a str, prefix: &str) -> Vec<DocComment<'a>> {
+    let mut items = HashMap::new();
+    items.insert(module.span.offset, None);
+    if let ModuleKind::Text(fields) = &module.kind {
+        for (i, field) in fields.iter().enumerate() {
+            if let Some(span) = field_span(field) {
+                items.insert(span.offset, Some(i));
+            }
+        }
+    }
+
+    let mut docs = Vec::new();
+    // The documentation comment before the last `(`, if any.
+    let mut pending = Vec::new();
+    for token in TokenStream::new(wat) {
+        let token = match token {
+            Ok(token) => token,
+            Err(_) => break,
+        };
+        let offset = token.token.src().as_ptr() as usize - wat.as_ptr() as usize;
+        if let Some(field) = items.get(&offset) {
+            if !pending.is_empty() {
+                docs.push(DocComment {
+                    field: *field,
+                    span: Span::from_offset(offset),
+                    lines: std::mem::take(&mut pending),
+                });
+            }
+        }
+        pending.clear();
+        if let Token::LParen(_) = token.token {
+            pending = doc_lines(&token.leading, prefix);
+        }
+    }
+    docs
+}
+
+/// Returns the lines of the documentation comment at the end of `trivia`.
+fn doc_lines<'a>(trivia: &[Token<'a>], prefix: &str) -> Vec<&'a str> {
+    let mut lines = Vec::new();
+    for token in trivia {
+        match token {
+            Token::LineComment(c) if c.starts_with(prefix) => {
+                let line = &c[prefix.len()..];
+                lines.push(line.strip_prefix(' ').unwrap_or(line).trim_end());
+            }
+            Token::Whitespace(ws) if ws.matches('\n').count() < 2 => {}
+            _ => lines.clear(),
+        }
+    }
+    lines
+}
+
+fn field_span(field: &ModuleField<'_>) -> Option<Span> {
+    Some(match field {
+        ModuleField::Type(i) => i.span,
+        ModuleField::Import(i) => i.span,
+        ModuleField::Func(i) => i.span,
+        ModuleField::Table(i) => i.span,
+        ModuleField::Memory(i) => i.span,
+        ModuleField::Global(i) => i.span,
+        ModuleField::Export(i) => i.span,
+        ModuleField::Elem(i) => i.span,
+        ModuleField::Data(i) => i.span,
+        ModuleField::Event(i) => i.span,
+        ModuleField::NestedModule(i) => i.span,
+        ModuleField::Instance(i) => i.span,
+        ModuleField::Alias(i) => i.span,
+        ModuleField::Start(_) | ModuleField::Custom(_) | ModuleField::Error(_) => return None,
+    })
+}
@@ -0,0 +1,92 @@
+//! Classification of the text of a WebAssembly text file for syntax
+//! highlighting.
+//!
+//! Unlike parsing, highlighting never fails: malformed text is classified as
+//! [`Category::Error`] and lexing resumes after it, which is what editors need
+//! while a file is being typed.
+//!
+//! ```
+//! use wast::highlight::{highlight, Category};
+//!
+//! let wat = "(func $f) ;; done";
+//! let categories = highlight(wat)
+//!     .into_iter()
+//!     .map(|h| (&wat[h.start..h.end], h.category))
+//!     .collect::<Vec<_>>();
+//! assert_eq!(
+//!     categories,
+//!     [
+//!         ("(", Category::Punctuation),
+//!         ("func", Category::Keyword),
+//!         ("$f", Category::Id),
+//!         (")", Category::Punctuation),
+//!         (";; done", Category::Comment),
+//!     ],
+//! );
+//! ```
+
+use crate::lexer::{Lexer, Token};
+
+/// The category of a range of source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Category {
+    /// A keyword such as `func` or `i32.add`.
+    Keyword,
+    /// An identifier such as `$foo`.
+    Id,
+    /// A string, integer or float literal.
+    Literal,
+    /// A line or block comment.
+    Comment,
+    /// A parenthesis.
+    Punctuation,
+    /// Text which isn't a valid token.
+    Error,
+}
+
+/// A classified range of source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Highlight {
+    /// The byte offset of the start of this range.
+    pub start: usize,
+    /// The byte offset of the end of this range, exclusive.
+    pub end: usize,
+    /// The category of the text in this range.
+    pub category: Category,
+}
+
+/// Classifies the source text `input`.
+///
+/// Returns the ranges of `input` in order. Every byte of `input` which isn't
+/// whitespace is covered by exactly one range.
+pub fn highlight(input: &str) -> Vec<Highlight> {
+    let mut lexer = Lexer::new(input);
+    let mut ret = Vec::new();
+    loop {
+        let start = lexer.pos();
+        let category = match lexer.parse() {
+            Ok(Some(token)) => match token {
+                Token::Whitespace(_) => continue,
+                Token::LineComment(_) | Token::BlockComment(_) => Category::Comment,
+                Token::LParen(_) | Token::RParen(_) => Category::Punctuation,
+                Token::String(_) | Token::Integer(_) | Token::Float(_) => Category::Literal,
+                Token::Id(_) => Category::Id,
+                Token::Keyword(_) | Token::Custom { .. } => Category::Keyword,
+                Token::Reserved(_) => Category::Error,
+            },
+            Ok(None) => break,
+            Err(e) => {
+                lexer.recover(start, &e);
+                Category::Error
+            }
+        };
+        ret.push(Highlight {
+            start,
+            end: lexer.pos(),
+            category,
+        });
+    }
+    ret
+}
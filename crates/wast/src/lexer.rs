@@ -19,16 +19,21 @@
 //! # }
 //! ```
 //!
+//! Tools which preserve the text they work on, like formatters, can use a
+//! [`TokenStream`][] instead to get each token with the whitespace and comments
+//! around it attached.
+//!
 //! Note that you'll typically not use this module but will rather use
 //! [`ParseBuffer`](crate::parser::ParseBuffer) instead.
 //!
 //! [`Lexer`]: crate::lexer::Lexer
 
-use crate::{Error, Span};
+use crate::{Error, LineIndex, Span};
 use std::borrow::Cow;
 use std::char;
 use std::fmt;
-use std::iter;
+use std::io;
+use std::mem;
 use std::str;
 
 /// A structure used to lex the s-expression syntax of WAT files.
@@ -38,8 +43,64 @@ use std::str;
 /// returned for any non-lexable text.
 #[derive(Clone)]
 pub struct Lexer<'a> {
-    it: iter::Peekable<str::CharIndices<'a>>,
+    pos: usize,
     input: &'a str,
+    limits: Limits,
+    // Number of tokens lexed so far and the current depth of nested parens,
+    // tracked to enforce `limits`.
+    tokens: usize,
+    depth: usize,
+    recognizers: Vec<(&'static str, Recognizer)>,
+    // where lines start in `input`, for the positions of errors.
+    lines: LineIndex,
+}
+
+/// A recognizer of custom tokens, registered with [`Lexer::recognize`].
+///
+/// It's given the rest of the input, starting where the token would start,
+/// and returns the length in bytes of the token found there, if any.
+pub type Recognizer = fn(&str) -> Option<usize>;
+
+/// Limits on the resources used to lex an input.
+///
+/// Services lexing or parsing untrusted input can use these to bound the
+/// memory and time spent on it. Each limit is unbounded by default, and
+/// exceeding one produces a [`LexError::LimitExceeded`] error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// The maximum nesting of block comments, where `(; ;)` has a nesting of
+    /// one.
+    pub block_comment_nesting: usize,
+    /// The maximum nesting of parentheses, where `()` has a depth of one.
+    pub paren_depth: usize,
+    /// The maximum number of tokens, including whitespace and comments.
+    pub tokens: usize,
+    /// The maximum length in bytes of the value of a string literal.
+    pub string_length: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            block_comment_nesting: usize::MAX,
+            paren_depth: usize::MAX,
+            tokens: usize::MAX,
+            string_length: usize::MAX,
+        }
+    }
+}
+
+/// A limit from [`Limits`], as reported by [`LexError::LimitExceeded`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    /// [`Limits::block_comment_nesting`]
+    BlockCommentNesting,
+    /// [`Limits::paren_depth`]
+    ParenDepth,
+    /// [`Limits::tokens`]
+    Tokens,
+    /// [`Limits::string_length`]
+    StringLength,
 }
 
 /// A fragment of source lex'd from an input string.
@@ -47,7 +108,8 @@ pub struct Lexer<'a> {
 /// This enumeration contains all kinds of fragments, including comments and
 /// whitespace. For most cases you'll probably ignore these and simply look at
 /// tokens.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Token<'a> {
     /// A line comment, preceded with `;;`
     LineComment(&'a str),
@@ -67,21 +129,30 @@ pub enum Token<'a> {
     /// A string literal, which is actually a list of bytes.
     String(WasmString<'a>),
 
-    /// An identifier (like `$foo`).
+    /// An identifier (like `$foo`), or a quoted identifier of the extended
+    /// name syntax (like `$"foo bar"`) which can contain any characters.
     ///
     /// All identifiers start with `$` and the payload here is the original
-    /// source text.
+    /// source text. Use [`id_name`] to get the name the identifier stands
+    /// for.
     Id(&'a str),
 
     /// A keyword, or something that starts with an alphabetic character.
-    ///
-    /// The payload here is the original source text.
-    Keyword(&'a str),
+    Keyword(Keyword<'a>),
 
     /// A reserved series of `idchar` symbols. Unknown what this is meant to be
     /// used for, you'll probably generate an error about an unexpected token.
     Reserved(&'a str),
 
+    /// A token matched by a [`Recognizer`] registered with
+    /// [`Lexer::recognize`] under the given name.
+    Custom {
+        /// The name the recognizer was registered under.
+        name: &'static str,
+        /// The source text of the token.
+        src: &'a str,
+    },
+
     /// An integer.
     Integer(Integer<'a>),
 
@@ -140,12 +211,26 @@ pub enum LexError {
     /// should always be preceded and succeeded with a digit of some form.
     LoneUnderscore,
 
+    /// The payload of a `nan:0x...` float was zero or didn't fit in the
+    /// significand of the target type.
+    InvalidNanPayload,
+
+    /// One of the [`Limits`] the lexer was configured with was exceeded.
+    LimitExceeded(Limit),
+
+    /// A quoted identifier like `$""` was empty.
+    EmptyId,
+
+    /// A quoted identifier's string isn't valid utf-8.
+    InvalidUtf8Id,
+
     #[doc(hidden)]
     __Nonexhaustive,
 }
 
 /// A sign token for an integer.
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum SignToken {
     /// Plus sign: "+",
     Plus,
@@ -153,13 +238,38 @@ pub enum SignToken {
     Minus,
 }
 
+/// A keyword.
+///
+/// Methods can be used to access the source text of the keyword and to
+/// classify it.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Keyword<'a>(&'a str);
+
+/// The kind of a [`Keyword`], as returned by [`Keyword::kind`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum KeywordKind {
+    /// The name of an instruction, like `i32.add` or `block`.
+    Instruction,
+    /// A keyword naming or qualifying a type, like `i32`, `funcref` or `mut`.
+    Type,
+    /// A keyword for the structure of a module or script, like `module`,
+    /// `func`, `param` or `assert_return`.
+    Structural,
+    /// Any other keyword.
+    Unknown,
+}
+
 /// A parsed integer, signed or unsigned.
 ///
 /// Methods can be use to access the value of the integer.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Integer<'a>(Box<IntegerInner<'a>>);
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 struct IntegerInner<'a> {
     sign: Option<SignToken>,
     src: &'a str,
@@ -170,27 +280,32 @@ struct IntegerInner<'a> {
 /// A parsed float.
 ///
 /// Methods can be use to access the value of the float.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Float<'a>(Box<FloatInner<'a>>);
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 struct FloatInner<'a> {
     src: &'a str,
     val: FloatVal<'a>,
 }
 
 /// A parsed string.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct WasmString<'a>(Box<WasmStringInner<'a>>);
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 struct WasmStringInner<'a> {
     src: &'a str,
     val: Cow<'a, [u8]>,
 }
 
 /// Possible parsed float values
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum FloatVal<'a> {
     /// A float `NaN` representation
     Nan {
@@ -222,12 +337,65 @@ pub enum FloatVal<'a> {
 impl<'a> Lexer<'a> {
     /// Creates a new lexer which will lex the `input` source string.
     pub fn new(input: &str) -> Lexer<'_> {
+        Lexer::new_with_limits(input, Limits::default())
+    }
+
+    /// Creates a new lexer which will lex the `input` source string, failing
+    /// if any of the `limits` are exceeded.
+    pub fn new_with_limits(input: &str, limits: Limits) -> Lexer<'_> {
         Lexer {
-            it: input.char_indices().peekable(),
+            pos: 0,
             input,
+            limits,
+            tokens: 0,
+            depth: 0,
+            recognizers: Vec::new(),
+            lines: LineIndex::default(),
         }
     }
 
+    /// Registers a recognizer of custom tokens for a dialect of the text
+    /// format, such as `%directive`s or `{{name}}` template placeholders.
+    ///
+    /// Recognizers are tried in the order they were registered wherever the
+    /// lexer would otherwise produce a [`Token::Reserved`] or fail on an
+    /// unexpected character. The first one to match produces a
+    /// [`Token::Custom`] with the given `name`.
+    ///
+    /// ```
+    /// # fn foo() -> Result<(), wast::Error> {
+    /// use wast::lexer::{Lexer, Token};
+    ///
+    /// fn placeholder(input: &str) -> Option<usize> {
+    ///     if !input.starts_with("{{") {
+    ///         return None;
+    ///     }
+    ///     input.find("}}").map(|end| end + 2)
+    /// }
+    ///
+    /// let mut lexer = Lexer::new("(i32.const {{value}})");
+    /// lexer.recognize("placeholder", placeholder);
+    /// let tokens = lexer.collect::<Result<Vec<_>, _>>()?;
+    /// assert_eq!(
+    ///     tokens[3],
+    ///     Token::Custom {
+    ///         name: "placeholder",
+    ///         src: "{{value}}",
+    ///     }
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Lexing panics if a recognizer returns a length which isn't a character
+    /// boundary of its input. Returning `Some(0)` is the same as `None`.
+    pub fn recognize(&mut self, name: &'static str, recognizer: Recognizer) -> &mut Self {
+        self.recognizers.push((name, recognizer));
+        self
+    }
+
     /// Returns the original source input that we're lexing.
     pub fn input(&self) -> &'a str {
         self.input
@@ -241,6 +409,32 @@ impl<'a> Lexer<'a> {
     ///
     /// Returns an error if the input is malformed.
     pub fn parse(&mut self) -> Result<Option<Token<'a>>, Error> {
+        let start = self.pos;
+        let token = match self.lex()? {
+            Some(token) => token,
+            None => return Ok(None),
+        };
+        self.tokens += 1;
+        if self.tokens > self.limits.tokens {
+            return Err(self.error(start, LexError::LimitExceeded(Limit::Tokens)));
+        }
+        match &token {
+            Token::LParen(_) => {
+                self.depth += 1;
+                if self.depth > self.limits.paren_depth {
+                    return Err(self.error(start, LexError::LimitExceeded(Limit::ParenDepth)));
+                }
+            }
+            Token::RParen(_) => self.depth = self.depth.saturating_sub(1),
+            Token::String(s) if s.val().len() > self.limits.string_length => {
+                return Err(self.error(start, LexError::LimitExceeded(Limit::StringLength)));
+            }
+            _ => {}
+        }
+        Ok(Some(token))
+    }
+
+    fn lex(&mut self) -> Result<Option<Token<'a>>, Error> {
         if let Some(ws) = self.ws() {
             return Ok(Some(Token::Whitespace(ws)));
         }
@@ -250,7 +444,7 @@ impl<'a> Lexer<'a> {
         if let Some(token) = self.token()? {
             return Ok(Some(token));
         }
-        match self.it.next() {
+        match self.next_char() {
             Some((i, ch)) => Err(self.error(i, LexError::Unexpected(ch))),
             None => Ok(None),
         }
@@ -265,6 +459,21 @@ impl<'a> Lexer<'a> {
             return Ok(Some(Token::RParen(&self.input[pos..pos + 1])));
         }
 
+        // Quoted identifiers are strings preceded by `$`, which otherwise
+        // would be a `$` on its own and a string.
+        if self.input[self.pos..].starts_with("$\"") {
+            let start = self.pos;
+            self.pos += 2;
+            let val = self.string()?;
+            if val.is_empty() {
+                return Err(self.error(start, LexError::EmptyId));
+            }
+            if str::from_utf8(&val).is_err() {
+                return Err(self.error(start, LexError::InvalidUtf8Id));
+            }
+            return Ok(Some(Token::Id(&self.input[start..self.cur()])));
+        }
+
         // Strings are also pretty easy, leading `"` is a dead giveaway
         if let Some(pos) = self.eat_char('"') {
             let val = self.string()?;
@@ -275,23 +484,27 @@ impl<'a> Lexer<'a> {
             })))));
         }
 
-        let (start, prefix) = match self.it.peek().cloned() {
+        let (start, prefix) = match self.peek_char() {
             Some((i, ch)) if is_idchar(ch) => (i, ch),
             Some((i, ch)) if is_reserved_extra(ch) => {
-                self.it.next();
+                if let Some(token) = self.custom(i) {
+                    return Ok(Some(token));
+                }
+                self.next_char();
                 return Ok(Some(Token::Reserved(&self.input[i..self.cur()])));
             }
-            Some((i, ch)) => return Err(self.error(i, LexError::Unexpected(ch))),
+            Some((i, ch)) => {
+                if let Some(token) = self.custom(i) {
+                    return Ok(Some(token));
+                }
+                return Err(self.error(i, LexError::Unexpected(ch)));
+            }
             None => return Ok(None),
         };
 
-        while let Some((_, ch)) = self.it.peek().cloned() {
-            if is_idchar(ch) {
-                self.it.next();
-            } else {
-                break;
-            }
-        }
+        // All idchars are ASCII, so the run of them can be found by looking
+        // at bytes rather than decoding characters.
+        self.skip_bytes_while(|b| is_idchar(b as char));
 
         let reserved = &self.input[start..self.cur()];
         if let Some(number) = self.number(reserved) {
@@ -299,12 +512,38 @@ impl<'a> Lexer<'a> {
         } else if prefix == '$' && reserved.len() > 1 {
             Ok(Some(Token::Id(reserved)))
         } else if 'a' <= prefix && prefix <= 'z' {
-            Ok(Some(Token::Keyword(reserved)))
+            Ok(Some(Token::Keyword(Keyword(reserved))))
+        } else if let Some(token) = self.custom(start) {
+            Ok(Some(token))
         } else {
             Ok(Some(Token::Reserved(reserved)))
         }
     }
 
+    /// Runs the registered recognizers on the input at `start`, consuming
+    /// the token of the first one which matches.
+    fn custom(&mut self, start: usize) -> Option<Token<'a>> {
+        let rest = &self.input[start..];
+        let (name, len) =
+            self.recognizers
+                .iter()
+                .find_map(|(name, recognizer)| match recognizer(rest) {
+                    Some(len) if len > 0 => Some((*name, len)),
+                    _ => None,
+                })?;
+        assert!(
+            rest.is_char_boundary(len),
+            "recognizer `{}` matched {} bytes, which isn't a character boundary",
+            name,
+            len
+        );
+        self.pos = start + len;
+        Some(Token::Custom {
+            name,
+            src: &rest[..len],
+        })
+    }
+
     fn number(&self, src: &'a str) -> Option<Token<'a>> {
         let (sign, num) = if src.starts_with('+') {
             (Some(SignToken::Plus), &src[1..])
@@ -336,7 +575,9 @@ impl<'a> Lexer<'a> {
             if it.next().is_some() {
                 return None;
             }
-            let n = u64::from_str_radix(&to_parse, 16).ok()?;
+            // Payloads too big for a `u64` are still NaNs, just ones which
+            // don't fit in any float.
+            let n = u64::from_str_radix(&to_parse, 16).unwrap_or(u64::MAX);
             return Some(Token::Float(Float(Box::new(FloatInner {
                 src,
                 val: FloatVal::Nan {
@@ -485,14 +726,10 @@ impl<'a> Lexer<'a> {
     /// if there's no whitespace to consume
     fn ws(&mut self) -> Option<&'a str> {
         let start = self.cur();
-        loop {
-            match self.it.peek() {
-                Some((_, ' ')) | Some((_, '\n')) | Some((_, '\r')) | Some((_, '\t')) => {
-                    drop(self.it.next())
-                }
-                _ => break,
-            }
-        }
+        self.skip_bytes_while(|b| match b {
+            b' ' | b'\n' | b'\r' | b'\t' => true,
+            _ => false,
+        });
         let end = self.cur();
         if start != end {
             Some(&self.input[start..end])
@@ -504,20 +741,34 @@ impl<'a> Lexer<'a> {
     /// Attempts to read a comment from the input stream
     fn comment(&mut self) -> Result<Option<Token<'a>>, Error> {
         if let Some(start) = self.eat_str(";;") {
-            loop {
-                match self.it.peek() {
-                    None | Some((_, '\n')) => break,
-                    _ => drop(self.it.next()),
-                }
-            }
+            // Note that `str::find` with a `char` uses `memchr` internally.
+            self.pos = match self.input[self.pos..].find('\n') {
+                Some(i) => self.pos + i,
+                None => self.input.len(),
+            };
             let end = self.cur();
             return Ok(Some(Token::LineComment(&self.input[start..end])));
         }
         if let Some(start) = self.eat_str("(;") {
             let mut level = 1;
-            while let Some((_, ch)) = self.it.next() {
+            if level > self.limits.block_comment_nesting {
+                let kind = LexError::LimitExceeded(Limit::BlockCommentNesting);
+                return Err(self.error(start, kind));
+            }
+            loop {
+                // Only `(` and `;` are interesting here and both are ASCII, so
+                // skip everything else bytewise.
+                self.skip_bytes_while(|b| b != b'(' && b != b';');
+                let (i, ch) = match self.next_char() {
+                    Some(pair) => pair,
+                    None => break,
+                };
                 if ch == '(' && self.eat_char(';').is_some() {
                     level += 1;
+                    if level > self.limits.block_comment_nesting {
+                        let kind = LexError::LimitExceeded(Limit::BlockCommentNesting);
+                        return Err(self.error(i, kind));
+                    }
                 }
                 if ch == ';' && self.eat_char(')').is_some() {
                     level -= 1;
@@ -542,7 +793,13 @@ impl<'a> Lexer<'a> {
         }
         let mut state = State::Start(self.cur());
         loop {
-            match self.it.next() {
+            // Fast path for runs of characters which are copied verbatim,
+            // before any escape has been seen. Everything that needs special
+            // handling is ASCII so this can be done bytewise.
+            if let State::Start(_) = state {
+                self.skip_bytes_while(|b| b >= 0x20 && b != 0x7f && b != b'"' && b != b'\\');
+            }
+            match self.next_char() {
                 Some((i, '\\')) => {
                     match state {
                         State::String(_) => {}
@@ -554,7 +811,7 @@ impl<'a> Lexer<'a> {
                         State::String(b) => b,
                         State::Start(_) => unreachable!(),
                     };
-                    match self.it.next() {
+                    match self.next_char() {
                         Some((_, '"')) => buf.push(b'"'),
                         Some((_, '\'')) => buf.push(b'\''),
                         Some((_, 't')) => buf.push(b'\t'),
@@ -602,9 +859,9 @@ impl<'a> Lexer<'a> {
         let (_, n) = self.hexdigit()?;
         let mut last_underscore = false;
         let mut n = n as u32;
-        while let Some((i, c)) = self.it.peek().cloned() {
-            if c == '_' {
-                self.it.next();
+        while let Some((i, c)) = self.peek_char() {
+            if c == '_' && !last_underscore {
+                self.next_char();
                 last_underscore = true;
                 continue;
             }
@@ -612,7 +869,7 @@ impl<'a> Lexer<'a> {
                 break;
             }
             last_underscore = false;
-            self.it.next();
+            self.next_char();
             n = n
                 .checked_mul(16)
                 .and_then(|n| n.checked_add(to_hex(c) as u32))
@@ -643,19 +900,16 @@ impl<'a> Lexer<'a> {
             return None;
         }
         let ret = self.cur();
-        for _ in s.chars() {
-            self.it.next();
-        }
+        self.pos += s.len();
         Some(ret)
     }
 
     /// Returns where the match happened, if any
     fn eat_char(&mut self, needle: char) -> Option<usize> {
-        match self.it.peek() {
-            Some((i, c)) if *c == needle => {
-                let ret = *i;
-                self.it.next();
-                Some(ret)
+        match self.peek_char() {
+            Some((i, c)) if c == needle => {
+                self.next_char();
+                Some(i)
             }
             _ => None,
         }
@@ -664,8 +918,7 @@ impl<'a> Lexer<'a> {
     /// Reads the next character from the input string and where it's located,
     /// returning an error if the input stream is empty.
     fn must_char(&mut self) -> Result<(usize, char), Error> {
-        self.it
-            .next()
+        self.next_char()
             .ok_or_else(|| self.error(self.input.len(), LexError::UnexpectedEof))
     }
 
@@ -680,18 +933,90 @@ impl<'a> Lexer<'a> {
     }
 
     /// Returns the current position of our iterator through the input string
-    fn cur(&mut self) -> usize {
-        self.it.peek().map(|p| p.0).unwrap_or(self.input.len())
+    fn cur(&self) -> usize {
+        self.pos
     }
 
     /// Returns the remaining string that we have left to parse
-    fn cur_str(&mut self) -> &'a str {
-        &self.input[self.cur()..]
+    fn cur_str(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    /// Returns the next character in the input and where it's located, without
+    /// consuming it.
+    fn peek_char(&self) -> Option<(usize, char)> {
+        self.cur_str().chars().next().map(|c| (self.pos, c))
+    }
+
+    /// Consumes the next character in the input, returning it and where it's
+    /// located.
+    fn next_char(&mut self) -> Option<(usize, char)> {
+        let ret = self.peek_char()?;
+        self.pos += ret.1.len_utf8();
+        Some(ret)
+    }
+
+    /// Advances past all bytes for which `f` returns `true`.
+    ///
+    /// This avoids decoding characters for the common case of scanning ASCII.
+    /// Scanning must stop on a character boundary, which is guaranteed if `f`
+    /// either only accepts ASCII bytes or only rejects ASCII bytes.
+    #[inline]
+    fn skip_bytes_while(&mut self, f: impl Fn(u8) -> bool) {
+        let bytes = &self.input.as_bytes()[self.pos..];
+        let n = bytes.iter().position(|b| !f(*b)).unwrap_or(bytes.len());
+        debug_assert!(self.input.is_char_boundary(self.pos + n));
+        self.pos += n;
+    }
+
+    /// Returns the byte offset of the next token in the input.
+    pub(crate) fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// Skips over malformed input after [`Lexer::parse`] failed with `err` at
+    /// a token starting at `start`, so that lexing can resume afterwards.
+    ///
+    /// Unterminated block comments run until the end of the input and
+    /// malformed strings until their closing quote or the end of the line.
+    /// Anything else is skipped up to the next whitespace, parenthesis, or
+    /// string.
+    pub(crate) fn recover(&mut self, start: usize, err: &Error) {
+        self.pos = start;
+        if let Some(LexError::DanglingBlockComment) = err.lex_error() {
+            self.pos = self.input.len();
+            return;
+        }
+        if self.eat_char('"').is_some() {
+            let mut escaped = false;
+            while let Some((_, c)) = self.peek_char() {
+                if c == '\n' {
+                    break;
+                }
+                self.next_char();
+                match c {
+                    '"' if !escaped => break,
+                    '\\' => escaped = !escaped,
+                    _ => escaped = false,
+                }
+            }
+            return;
+        }
+        self.next_char();
+        while let Some((_, c)) = self.peek_char() {
+            match c {
+                ' ' | '\n' | '\r' | '\t' | '(' | ')' | '"' => break,
+                _ => {
+                    self.next_char();
+                }
+            }
+        }
     }
 
     /// Creates an error at `pos` with the specified `kind`
     fn error(&self, pos: usize, kind: LexError) -> Error {
-        Error::lex(Span { offset: pos }, self.input, kind)
+        let (line, col) = self.lines.position(self.input, pos);
+        Error::lex(Span { offset: pos }, self.input, line, col, kind)
     }
 }
 
@@ -703,6 +1028,360 @@ impl<'a> Iterator for Lexer<'a> {
     }
 }
 
+/// An iterator over the tokens of a WebAssembly text file other than
+/// whitespace and comments, which are instead attached to the tokens next to
+/// them as trivia.
+///
+/// Trivia following a token on the same line, up to and including the end of
+/// that line, is the token's trailing trivia. All other trivia is leading
+/// trivia of the token after it, except for that at the end of the input,
+/// which is available from [`TokenStream::end_trivia`]. Whitespace spanning
+/// lines is split after its first newline, so every byte of the input is in
+/// exactly one token.
+///
+/// ```
+/// # fn foo() -> Result<(), wast::Error> {
+/// use wast::lexer::{Token, TokenStream};
+///
+/// fn src<'a>(tokens: &[Token<'a>]) -> Vec<&'a str> {
+///     tokens.iter().map(|t| t.src()).collect()
+/// }
+///
+/// let wat = "(func ;; empty\n  ;; really\n)";
+/// let tokens = TokenStream::new(wat).collect::<Result<Vec<_>, _>>()?;
+/// assert_eq!(tokens[1].token.src(), "func");
+/// assert_eq!(src(&tokens[1].trailing), [" ", ";; empty", "\n"]);
+/// assert_eq!(src(&tokens[2].leading), ["  ", ";; really", "\n"]);
+/// assert_eq!(tokens[2].token.src(), ")");
+/// assert!(tokens[2].line_comment_before);
+/// assert!(!tokens[2].blank_line_before);
+/// # Ok(())
+/// # }
+/// ```
+pub struct TokenStream<'a> {
+    lexer: Lexer<'a>,
+    // Leading trivia of the next token, and the token itself if it was lexed
+    // while looking for the end of the previous token's trailing trivia.
+    leading: Vec<Token<'a>>,
+    next: Option<Token<'a>>,
+}
+
+/// A token yielded by a [`TokenStream`] along with its trivia.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TokenWithTrivia<'a> {
+    /// The whitespace and comments before this token which aren't trailing
+    /// trivia of the token before it.
+    pub leading: Vec<Token<'a>>,
+    /// The token, which is never whitespace or a comment.
+    pub token: Token<'a>,
+    /// The whitespace and comments after this token on the same line.
+    pub trailing: Vec<Token<'a>>,
+    /// Whether there's a blank line in the leading trivia, such as one
+    /// grouping this token apart from the token before it.
+    pub blank_line_before: bool,
+    /// Whether a line comment is on the line just before this token, with
+    /// only whitespace between them, as is the case for a comment
+    /// documenting the token.
+    pub line_comment_before: bool,
+}
+
+impl<'a> TokenStream<'a> {
+    /// Creates a new stream of the tokens of `input`.
+    pub fn new(input: &str) -> TokenStream<'_> {
+        TokenStream::new_with_limits(input, Limits::default())
+    }
+
+    /// Creates a new stream of the tokens of `input`, failing if any of the
+    /// `limits` are exceeded.
+    pub fn new_with_limits(input: &str, limits: Limits) -> TokenStream<'_> {
+        TokenStream {
+            lexer: Lexer::new_with_limits(input, limits),
+            leading: Vec::new(),
+            next: None,
+        }
+    }
+
+    /// Returns the trivia after the trailing trivia of the last token, which
+    /// is only complete once this stream has returned `None`.
+    pub fn end_trivia(&self) -> &[Token<'a>] {
+        &self.leading
+    }
+
+    /// Lexes the next token and its trivia.
+    ///
+    /// Returns `Some` if a token is found or `None` if we're at EOF.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input is malformed.
+    pub fn parse(&mut self) -> Result<Option<TokenWithTrivia<'a>>, Error> {
+        let mut leading = mem::take(&mut self.leading);
+        let token = match self.next.take() {
+            Some(token) => token,
+            None => loop {
+                match self.lexer.parse()? {
+                    Some(token) if token.is_trivia() => leading.push(token),
+                    Some(token) => break token,
+                    None => {
+                        self.leading = leading;
+                        return Ok(None);
+                    }
+                }
+            },
+        };
+        let mut trailing = Vec::new();
+        loop {
+            match self.lexer.parse()? {
+                Some(Token::Whitespace(ws)) => match ws.find('\n') {
+                    Some(i) => {
+                        trailing.push(Token::Whitespace(&ws[..i + 1]));
+                        if i + 1 < ws.len() {
+                            self.leading.push(Token::Whitespace(&ws[i + 1..]));
+                        }
+                        break;
+                    }
+                    None => trailing.push(Token::Whitespace(ws)),
+                },
+                Some(token) if token.is_trivia() => trailing.push(token),
+                Some(token) => {
+                    self.next = Some(token);
+                    break;
+                }
+                None => break,
+            }
+        }
+        let (blank_line_before, line_comment_before) = trivia_flags(&leading);
+        Ok(Some(TokenWithTrivia {
+            leading,
+            token,
+            trailing,
+            blank_line_before,
+            line_comment_before,
+        }))
+    }
+}
+
+/// Returns whether the leading trivia `leading` contains a blank line, and
+/// whether it ends with a line comment followed by only whitespace.
+fn trivia_flags(leading: &[Token<'_>]) -> (bool, bool) {
+    let mut blank_line = false;
+    let mut line_comment = false;
+    // Leading trivia always starts at the beginning of a line, so track
+    // whether the current line has had anything but whitespace on it.
+    let mut empty_line = true;
+    for token in leading {
+        match token {
+            Token::Whitespace(ws) => {
+                for _ in ws.matches('\n') {
+                    if empty_line {
+                        blank_line = true;
+                        line_comment = false;
+                    }
+                    empty_line = true;
+                }
+            }
+            Token::LineComment(_) => {
+                line_comment = true;
+                empty_line = false;
+            }
+            _ => {
+                line_comment = false;
+                empty_line = false;
+            }
+        }
+    }
+    (blank_line, line_comment)
+}
+
+impl<'a> Iterator for TokenStream<'a> {
+    type Item = Result<TokenWithTrivia<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.parse().transpose()
+    }
+}
+
+/// A lexer which reads its input incrementally from an [`io::Read`].
+///
+/// Unlike [`Lexer`] this doesn't require the whole input to be in memory up
+/// front. Input is read in chunks as needed and only the text of the current
+/// token (plus at most one chunk of lookahead) is buffered at any one time.
+///
+/// Tokens returned borrow from the internal buffer, so this isn't an
+/// [`Iterator`]; use [`StreamLexer::parse`] in a loop instead:
+///
+/// ```
+/// # fn foo() -> Result<(), wast::Error> {
+/// use wast::lexer::StreamLexer;
+///
+/// let wat: &[u8] = b"(module (func $foo))";
+/// let mut lexer = StreamLexer::new(wat);
+/// while let Some(token) = lexer.parse()? {
+///     println!("{:?}", token);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Errors produced by this lexer carry the byte offset of the error in the
+/// whole input, but no source snippet since the input is no longer available.
+pub struct StreamLexer<R> {
+    reader: R,
+    // The input read so far which has been validated as utf-8.
+    buf: String,
+    // Bytes read after `buf` which aren't yet known to be valid utf-8: the
+    // start of a character split across reads, or malformed utf-8.
+    rest: Vec<u8>,
+    // Index into `buf` where the next token starts.
+    start: usize,
+    // Length of the token most recently returned, consumed on the next call.
+    pending: usize,
+    // Offset in the whole input of `buf[0]`.
+    buf_offset: usize,
+    eof: bool,
+    // Whether `rest` starts with malformed utf-8, in which case no more text
+    // is ever added to `buf`.
+    malformed: bool,
+}
+
+const STREAM_CHUNK_SIZE: usize = 8 * 1024;
+
+impl<R: io::Read> StreamLexer<R> {
+    /// Creates a new lexer which will lex the input read from `reader`.
+    pub fn new(reader: R) -> StreamLexer<R> {
+        StreamLexer {
+            reader,
+            buf: String::new(),
+            rest: Vec::new(),
+            start: 0,
+            pending: 0,
+            buf_offset: 0,
+            eof: false,
+            malformed: false,
+        }
+    }
+
+    /// Returns the byte offset in the whole input of the start of the next
+    /// token.
+    pub fn offset(&self) -> usize {
+        self.buf_offset + self.start + self.pending
+    }
+
+    /// Lexes the next token in the input, reading more input as necessary.
+    ///
+    /// Returns `Some` if a token is found or `None` if we're at EOF.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input is malformed, isn't valid utf-8, or
+    /// reading from the underlying reader fails.
+    pub fn parse(&mut self) -> Result<Option<Token<'_>>, Error> {
+        self.start += self.pending;
+        self.pending = 0;
+
+        // Keep reading more input until the text we have contains a complete
+        // token. A token is known to be complete if it ends before the end of
+        // the buffered text, since every token's end is determined by the
+        // character following it (or a closing delimiter). Otherwise, if there
+        // may be more input, the token might continue (e.g. `(` might become
+        // `(;`) or an error may be due to input having been cut short.
+        let mut at_end;
+        loop {
+            let text = &self.buf[self.start..];
+            at_end = match Lexer::new(text).parse() {
+                Ok(Some(token)) => token.src().len() == text.len(),
+                Ok(None) => true,
+                Err(e) => match e.lex_error() {
+                    Some(LexError::UnexpectedEof) | Some(LexError::DanglingBlockComment) => true,
+                    _ => false,
+                },
+            };
+            if !at_end || self.eof || self.malformed || !self.fill()? {
+                break;
+            }
+        }
+
+        let offset = self.offset();
+        let result = Lexer::new(&self.buf[self.start..]).parse();
+        match result {
+            // Malformed utf-8 can't continue a token, but it's an error once
+            // there's nothing left before it.
+            Ok(None) | Err(_) if at_end && self.malformed => Err(Error::new(
+                Span {
+                    offset: self.buf_offset + self.buf.len(),
+                },
+                "malformed UTF-8 encoding".to_string(),
+            )),
+            Ok(Some(token)) => {
+                self.pending = token.src().len();
+                Ok(Some(token))
+            }
+            Ok(None) => Ok(None),
+            Err(mut e) => {
+                e.shift_span(offset);
+                Err(e)
+            }
+        }
+    }
+
+    /// Reads another chunk of input into the buffer, returning whether any
+    /// bytes were read.
+    ///
+    /// Only the bytes read are validated as utf-8, so the text of earlier
+    /// tokens is never validated again.
+    fn fill(&mut self) -> Result<bool, Error> {
+        // Discard the text of tokens already returned so only the current
+        // token stays in memory.
+        if self.start > 0 {
+            self.buf.drain(..self.start);
+            self.buf_offset += self.start;
+            self.start = 0;
+        }
+        // Read at least as much as is already buffered so that re-lexing a
+        // very long token as it grows takes amortized linear time.
+        let len = self.rest.len();
+        let size = self.buf.len().max(STREAM_CHUNK_SIZE);
+        self.rest.resize(len + size, 0);
+        let result = loop {
+            match self.reader.read(&mut self.rest[len..]) {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                other => break other,
+            }
+        };
+        let n = match result {
+            Ok(n) => n,
+            Err(e) => {
+                self.rest.truncate(len);
+                let span = Span {
+                    offset: self.buf_offset + self.buf.len() + len,
+                };
+                return Err(Error::new(span, format!("failed to read input: {}", e)));
+            }
+        };
+        self.rest.truncate(len + n);
+        if n == 0 {
+            self.eof = true;
+        }
+
+        // Move the valid utf-8 read onto the end of `buf`, leaving the start
+        // of a character which may be completed by the next read.
+        let valid = match str::from_utf8(&self.rest) {
+            Ok(s) => s.len(),
+            Err(e) => {
+                if e.error_len().is_some() || self.eof {
+                    self.malformed = true;
+                }
+                e.valid_up_to()
+            }
+        };
+        self.buf
+            .push_str(str::from_utf8(&self.rest[..valid]).unwrap());
+        self.rest.drain(..valid);
+        Ok(n > 0)
+    }
+}
+
 impl<'a> Token<'a> {
     /// Returns the original source text for this token.
     pub fn src(&self) -> &'a str {
@@ -714,12 +1393,156 @@ impl<'a> Token<'a> {
             Token::RParen(s) => s,
             Token::String(s) => s.src(),
             Token::Id(s) => s,
-            Token::Keyword(s) => s,
+            Token::Keyword(k) => k.src(),
             Token::Reserved(s) => s,
+            Token::Custom { src, .. } => src,
             Token::Integer(i) => i.src(),
             Token::Float(f) => f.src(),
         }
     }
+
+    /// Returns whether this token is whitespace or a comment.
+    pub fn is_trivia(&self) -> bool {
+        matches!(
+            self,
+            Token::Whitespace(_) | Token::LineComment(_) | Token::BlockComment(_)
+        )
+    }
+}
+
+/// Returns the name of the identifier whose source text is `src`, as found in
+/// a [`Token::Id`].
+///
+/// This is the text after the `$`, except that for quoted identifiers it's
+/// the value of the string, so `$foo` and `$"foo"` have the same name.
+///
+/// ```
+/// use wast::lexer::id_name;
+///
+/// assert_eq!(id_name("$foo"), "foo");
+/// assert_eq!(id_name(r#"$"foo""#), "foo");
+/// assert_eq!(id_name(r#"$"a \"b\"""#), r#"a "b""#);
+/// ```
+pub fn id_name(src: &str) -> Cow<'_, str> {
+    let quoted = match src.strip_prefix('$') {
+        Some(quoted) if quoted.starts_with('"') => quoted,
+        Some(name) => return name.into(),
+        None => return src.into(),
+    };
+    let string = match Lexer::new(quoted).parse() {
+        Ok(Some(Token::String(s))) => s.0.val,
+        _ => return quoted.into(),
+    };
+    match string {
+        Cow::Borrowed(bytes) => str::from_utf8(bytes).unwrap_or(quoted).into(),
+        Cow::Owned(bytes) => match String::from_utf8(bytes) {
+            Ok(name) => name.into(),
+            Err(_) => quoted.into(),
+        },
+    }
+}
+
+impl<'a> Keyword<'a> {
+    /// Returns the original source text for this keyword.
+    pub fn src(&self) -> &'a str {
+        self.0
+    }
+
+    /// Classifies this keyword based on the WebAssembly text format.
+    ///
+    /// Keywords which are both an instruction and something else, like
+    /// `block`, are classified as instructions. Instructions are only known
+    /// with the `wasm-module` feature enabled, without it they're
+    /// [`KeywordKind::Unknown`].
+    pub fn kind(&self) -> KeywordKind {
+        if is_instruction(self.0) {
+            return KeywordKind::Instruction;
+        }
+        match self.0 {
+            "i8" | "i16" | "i32" | "i64" | "f32" | "f64" | "v128" | "i8x16" | "i16x8" | "i32x4"
+            | "i64x2" | "f32x4" | "f64x2" | "funcref" | "anyfunc" | "externref" | "anyref"
+            | "exnref" | "eqref" | "i31ref" | "nullref" | "ref" | "null" | "rtt" | "extern"
+            | "any" | "eq" | "i31" | "exn" | "mut" => KeywordKind::Type,
+            "module"
+            | "func"
+            | "param"
+            | "result"
+            | "local"
+            | "type"
+            | "import"
+            | "export"
+            | "table"
+            | "memory"
+            | "global"
+            | "elem"
+            | "data"
+            | "start"
+            | "offset"
+            | "item"
+            | "declare"
+            | "event"
+            | "instance"
+            | "alias"
+            | "instantiate"
+            | "modulecode"
+            | "outer"
+            | "parent"
+            | "arg"
+            | "then"
+            | "do"
+            | "struct"
+            | "array"
+            | "field"
+            | "shared"
+            | "passive"
+            | "code"
+            | "before"
+            | "after"
+            | "first"
+            | "last"
+            | "binary"
+            | "quote"
+            | "register"
+            | "invoke"
+            | "get"
+            | "assert_malformed"
+            | "assert_invalid"
+            | "assert_unlinkable"
+            | "assert_return"
+            | "assert_trap"
+            | "assert_exhaustion"
+            | "assert_return_canonical_nan"
+            | "assert_return_arithmetic_nan"
+            | "assert_return_func"
+            | "assert_return_canonical_nan_f32x4"
+            | "assert_return_canonical_nan_f64x2"
+            | "assert_return_arithmetic_nan_f32x4"
+            | "assert_return_arithmetic_nan_f64x2" => KeywordKind::Structural,
+            _ => KeywordKind::Unknown,
+        }
+    }
+}
+
+#[cfg(feature = "wasm-module")]
+fn is_instruction(keyword: &str) -> bool {
+    crate::ast::is_instruction(keyword)
+}
+
+#[cfg(not(feature = "wasm-module"))]
+fn is_instruction(_keyword: &str) -> bool {
+    false
+}
+
+macro_rules! integer_conversions {
+    ($($(#[$attr:meta])* $vis:vis fn $name:ident -> $i:ident($u:ident);)*) => ($(
+        $(#[$attr])*
+        $vis fn $name(&self) -> Result<$i, LexError> {
+            let (s, base) = self.val();
+            $i::from_str_radix(s, base)
+                .or_else(|_| $u::from_str_radix(s, base).map(|i| i as $i))
+                .map_err(|_| LexError::NumberTooBig)
+        }
+    )*)
 }
 
 impl<'a> Integer<'a> {
@@ -738,6 +1561,40 @@ impl<'a> Integer<'a> {
     pub fn val(&self) -> (&str, u32) {
         (&self.0.val, if self.0.hex { 16 } else { 10 })
     }
+
+    integer_conversions! {
+        /// Returns the value of this integer as an `i32`.
+        ///
+        /// As in the text format's `i32` literals, the value may be written
+        /// in either the signed or the unsigned range, and unsigned values
+        /// wrap, so `0xffffffff` and `4294967295` are both `-1`. Returns
+        /// [`LexError::NumberTooBig`] if the value is outside of both ranges.
+        pub fn as_i32 -> i32(u32);
+
+        /// Returns the value of this integer as a `u32`.
+        ///
+        /// Returns [`LexError::NumberTooBig`] if the value is negative or
+        /// doesn't fit in a `u32`.
+        pub fn as_u32 -> u32(u32);
+
+        /// Returns the value of this integer as an `i64`.
+        ///
+        /// Like [`Integer::as_i32`] the value may be written in either the
+        /// signed or the unsigned range, and unsigned values wrap. Returns
+        /// [`LexError::NumberTooBig`] if the value is outside of both ranges.
+        pub fn as_i64 -> i64(u64);
+
+        /// Returns the value of this integer as a `u64`.
+        ///
+        /// Returns [`LexError::NumberTooBig`] if the value is negative or
+        /// doesn't fit in a `u64`.
+        pub fn as_u64 -> u64(u64);
+
+        pub(crate) fn as_i8 -> i8(u8);
+        pub(crate) fn as_u8 -> u8(u8);
+        pub(crate) fn as_i16 -> i16(u16);
+        pub(crate) fn as_u16 -> u16(u16);
+    }
 }
 
 impl<'a> Float<'a> {
@@ -751,6 +1608,245 @@ impl<'a> Float<'a> {
     pub fn val(&self) -> &FloatVal<'a> {
         &self.0.val
     }
+
+    /// Returns the bits of the `f32` this float denotes.
+    ///
+    /// Decimal and hexadecimal values are rounded to the nearest `f32`, with
+    /// ties to even, and values too small to represent round to zero. Returns
+    /// [`LexError::NumberTooBig`] if the value instead rounds to infinity, as
+    /// the text format doesn't allow that, and [`LexError::InvalidNanPayload`]
+    /// if a `nan:0x...` payload is zero or doesn't fit in the significand.
+    pub fn to_f32_bits(&self) -> Result<u32, LexError> {
+        strtof(&self.0.val).ok_or_else(|| self.range_error())
+    }
+
+    /// Returns the bits of the `f64` this float denotes.
+    ///
+    /// See [`Float::to_f32_bits`] for how the value is rounded and which
+    /// values are errors.
+    pub fn to_f64_bits(&self) -> Result<u64, LexError> {
+        strtod(&self.0.val).ok_or_else(|| self.range_error())
+    }
+
+    fn range_error(&self) -> LexError {
+        match self.0.val {
+            FloatVal::Nan { .. } => LexError::InvalidNanPayload,
+            _ => LexError::NumberTooBig,
+        }
+    }
+}
+
+macro_rules! float_to_bits {
+    ($($name:ident -> $int:ident($float:ident, $exp_bits:tt);)*) => ($(
+    pub(crate) fn $name(val: &FloatVal<'_>) -> Option<$int> {
+        // Compute a few well-known constants about the float representation
+        // given the parameters to the macro here.
+        let width = std::mem::size_of::<$int>() * 8;
+        let neg_offset = width - 1;
+        let exp_offset = neg_offset - $exp_bits;
+        let signif_bits = width - 1 - $exp_bits;
+        let signif_mask = (1 << exp_offset) - 1;
+        let bias = (1 << ($exp_bits - 1)) - 1;
+
+        let (hex, integral, decimal, exponent_str) = match val {
+            // Infinity is when the exponent bits are all set and
+            // the significand is zero.
+            FloatVal::Inf { negative } => {
+                let exp_bits = (1 << $exp_bits) - 1;
+                let neg_bit = *negative as $int;
+                return Some(
+                    (neg_bit << neg_offset) |
+                    (exp_bits << exp_offset)
+                );
+            }
+
+            // NaN is when the exponent bits are all set and
+            // the significand is nonzero. The default of NaN is
+            // when only the highest bit of the significand is set.
+            FloatVal::Nan { negative, val } => {
+                let exp_bits = (1 << $exp_bits) - 1;
+                let neg_bit = *negative as $int;
+                let signif = val.unwrap_or(1 << (signif_bits - 1));
+                // The payload has to fit in the significand, and if it's
+                // zero then this is actually infinity so we fail to parse it.
+                if signif == 0 || signif >> exp_offset != 0 {
+                    return None;
+                }
+                return Some(
+                    (neg_bit << neg_offset) |
+                    (exp_bits << exp_offset) |
+                    signif as $int
+                );
+            }
+
+            // This is trickier, handle this below
+            FloatVal::Val { hex, integral, decimal, exponent } => {
+                (hex, integral, decimal, exponent)
+            }
+        };
+
+        // Rely on Rust's standard library to parse base 10 floats
+        // correctly.
+        if !*hex {
+            let mut s = integral.to_string();
+            if let Some(decimal) = decimal {
+                s.push_str(".");
+                s.push_str(&decimal);
+            }
+            if let Some(exponent) = exponent_str {
+                s.push_str("e");
+                s.push_str(&exponent);
+            }
+            let float = s.parse::<$float>().ok()?;
+            // looks like the `*.wat` format considers infinite overflow to
+            // be invalid.
+            if float.is_infinite() {
+                return None;
+            }
+            return Some(float.to_bits());
+        }
+
+        // Parsing hex floats is... hard! I don't really know what most of
+        // this below does. It was copied from Gecko's implementation in
+        // `WasmTextToBinary.cpp`. Would love comments on this if you have
+        // them!
+        let decimal = decimal.as_ref().map(|s| &**s).unwrap_or("");
+        let negative = integral.starts_with('-');
+        let integral = integral.trim_start_matches('-').trim_start_matches('0');
+
+        // Do a bunch of work up front to locate the first non-zero digit
+        // to determine the initial exponent. There's a number of
+        // adjustments depending on where the digit was found, but the
+        // general idea here is that I'm not really sure why things are
+        // calculated the way they are but it should match Gecko.
+        let decimal_no_leading = decimal.trim_start_matches('0');
+        let decimal_iter = if integral.is_empty() {
+            decimal_no_leading.chars()
+        } else {
+            decimal.chars()
+        };
+        let mut digits = integral.chars()
+            .map(|c| (to_hex(c) as $int, false))
+            .chain(decimal_iter.map(|c| (to_hex(c) as $int, true)));
+        let lead_nonzero_digit = match digits.next() {
+            Some((c, _)) => c,
+            // No digits? Must be `+0` or `-0`, being careful to handle the
+            // sign encoding here.
+            None if negative => return Some(1 << (width - 1)),
+            None => return Some(0),
+        };
+        let mut significand = 0 as $int;
+        let mut exponent = if !integral.is_empty() {
+            1
+        } else {
+            -((decimal.len() - decimal_no_leading.len() + 1) as i32) + 1
+        };
+        let lz = (lead_nonzero_digit as u8).leading_zeros() as i32 - 4;
+        exponent = exponent.checked_mul(4)?.checked_sub(lz + 1)?;
+        let mut significand_pos = (width - (4 - (lz as usize))) as isize;
+        assert!(significand_pos >= 0);
+        significand |= lead_nonzero_digit << significand_pos;
+
+        // Now that we've got an anchor in the string we parse the remaining
+        // digits. Again, not entirely sure why everything is the way it is
+        // here! This is copied frmo gecko.
+        let mut discarded_extra_nonzero = false;
+        for (digit, decimal) in digits {
+            if !decimal {
+                exponent += 4;
+            }
+            if significand_pos > -4 {
+                significand_pos -= 4;
+            }
+
+            if significand_pos >= 0 {
+                significand |= digit << significand_pos;
+            } else if significand_pos > -4 {
+                significand |= digit >> (4 - significand_pos);
+                discarded_extra_nonzero = (digit & !((!0) >> (4 - significand_pos))) != 0;
+            } else if digit != 0 {
+                discarded_extra_nonzero = true;
+            }
+        }
+
+        // Exponents too large to parse would overflow to infinity, or
+        // underflow to zero if they're negative.
+        exponent = match exponent_str.as_ref().map(|s| s.parse::<i32>()) {
+            Some(Ok(e)) => exponent.saturating_add(e),
+            Some(Err(_)) if exponent_str.as_ref().unwrap().starts_with('-') => i32::MIN,
+            Some(Err(_)) => return None,
+            None => exponent,
+        };
+        debug_assert!(significand != 0);
+
+        let (encoded_exponent, encoded_significand, discarded_significand) =
+            if exponent <= -bias {
+                // Underflow to subnormal or zero.
+                let shift = exp_offset as i32 + exponent + bias;
+                if shift == 0 {
+                    (0, 0, significand)
+                } else if shift < 0 || shift >= width as i32 {
+                    (0, 0, 0)
+                } else {
+                    (
+                        0,
+                        significand >> (width as i32 - shift),
+                        significand << shift,
+                    )
+                }
+            } else if exponent <= bias {
+                // Normal (non-zero). The significand's leading 1 is encoded
+                // implicitly.
+                (
+                    ((exponent + bias) as $int) << exp_offset,
+                    (significand >> (width - exp_offset - 1)) & signif_mask,
+                    significand << (exp_offset + 1),
+                )
+            } else {
+                // Overflow to infinity.
+                (
+                    ((1 << $exp_bits) - 1) << exp_offset,
+                    0,
+                    0,
+                )
+            };
+
+        let bits = encoded_exponent | encoded_significand;
+
+        // Apply rounding. If this overflows the significand, it carries
+        // into the exponent bit according to the magic of the IEEE 754
+        // encoding.
+        //
+        // Or rather, the comment above is what Gecko says so it's copied
+        // here too.
+        let msb = 1 << (width - 1);
+        let bits = bits
+            + (((discarded_significand & msb != 0)
+                && ((discarded_significand & !msb != 0) ||
+                     discarded_extra_nonzero ||
+                     // ties to even
+                     (encoded_significand & 1 != 0))) as $int);
+
+        // Just before we return the bits be sure to handle the sign bit we
+        // found at the beginning.
+        let bits = if negative {
+            bits | (1 << (width - 1))
+        } else {
+            bits
+        };
+        // looks like the `*.wat` format considers infinite overflow to
+        // be invalid.
+        if $float::from_bits(bits).is_infinite() {
+            return None;
+        }
+        Some(bits)
+    }
+    )*)
+}
+
+float_to_bits! {
+    strtof -> u32(f32, 8);
+    strtod -> u64(f64, 11);
 }
 
 impl<'a> WasmString<'a> {
@@ -763,6 +1859,100 @@ impl<'a> WasmString<'a> {
     pub fn val(&self) -> &[u8] {
         &self.0.val
     }
+
+    /// Returns the parsed value of this string if it's valid utf-8, as is
+    /// required of names such as those of imports and exports.
+    pub fn as_str(&self) -> Option<&str> {
+        str::from_utf8(self.val()).ok()
+    }
+
+    /// Returns an iterator over the parsed value of this string which decodes
+    /// escapes from the source text as it goes, without allocating.
+    ///
+    /// ```
+    /// use wast::lexer::{Lexer, Token};
+    ///
+    /// let mut lexer = Lexer::new(r#""a\62\u{63}""#);
+    /// match lexer.parse().unwrap() {
+    ///     Some(Token::String(s)) => assert!(s.bytes().eq(b"abc".iter().copied())),
+    ///     _ => unreachable!(),
+    /// }
+    /// ```
+    pub fn bytes(&self) -> StringBytes<'a> {
+        let src = self.0.src;
+        StringBytes {
+            src: &src.as_bytes()[1..src.len() - 1],
+            pending: [0; 4],
+            pending_len: 0,
+            pending_pos: 0,
+        }
+    }
+}
+
+/// An iterator over the parsed value of a [`WasmString`], see
+/// [`WasmString::bytes`].
+#[derive(Clone, Debug)]
+pub struct StringBytes<'a> {
+    // The source text left to decode, without the surrounding quotes.
+    src: &'a [u8],
+    // The remaining bytes of a `\u{...}` escape which encodes to more than
+    // one byte.
+    pending: [u8; 4],
+    pending_len: u8,
+    pending_pos: u8,
+}
+
+impl Iterator for StringBytes<'_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.pending_pos < self.pending_len {
+            self.pending_pos += 1;
+            return Some(self.pending[usize::from(self.pending_pos - 1)]);
+        }
+        // The string was validated when it was lexed, so escapes here are
+        // known to be well-formed.
+        let (&b, rest) = self.src.split_first()?;
+        if b != b'\\' {
+            self.src = rest;
+            return Some(b);
+        }
+        let (&c, rest) = rest.split_first()?;
+        self.src = rest;
+        Some(match c {
+            b't' => b'\t',
+            b'n' => b'\n',
+            b'r' => b'\r',
+            b'u' => {
+                let end = self.src.iter().position(|b| *b == b'}')?;
+                let n = self.src[1..end]
+                    .iter()
+                    .filter(|b| **b != b'_')
+                    .fold(0, |n, b| n * 16 + u32::from(to_hex(char::from(*b))));
+                self.src = &self.src[end + 1..];
+                let c = char::from_u32(n)?;
+                let len = c.encode_utf8(&mut self.pending).len();
+                self.pending_len = len as u8;
+                self.pending_pos = 1;
+                self.pending[0]
+            }
+            c if c.is_ascii_hexdigit() => {
+                let (&c2, rest) = self.src.split_first()?;
+                self.src = rest;
+                to_hex(char::from(c)) * 16 + to_hex(char::from(c2))
+            }
+            c => c,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let pending = usize::from(self.pending_len - self.pending_pos);
+        // Every escape is at least as long as what it encodes to.
+        (
+            pending + usize::from(!self.src.is_empty()),
+            Some(pending + self.src.len()),
+        )
+    }
 }
 
 fn to_hex(c: char) -> u8 {
@@ -827,6 +2017,15 @@ impl fmt::Display for LexError {
             NumberTooBig => f.write_str("number is too big to parse")?,
             InvalidUnicodeValue(c) => write!(f, "invalid unicode scalar value 0x{:x}", c)?,
             LoneUnderscore => write!(f, "bare underscore in numeric literal")?,
+            InvalidNanPayload => f.write_str("invalid NaN payload")?,
+            LimitExceeded(Limit::BlockCommentNesting) => {
+                f.write_str("block comments are nested too deeply")?
+            }
+            LimitExceeded(Limit::ParenDepth) => f.write_str("parentheses are nested too deeply")?,
+            LimitExceeded(Limit::Tokens) => f.write_str("too many tokens")?,
+            LimitExceeded(Limit::StringLength) => f.write_str("string literal is too long")?,
+            EmptyId => f.write_str("empty identifier")?,
+            InvalidUtf8Id => f.write_str("malformed UTF-8 encoding in identifier")?,
             __Nonexhaustive => unreachable!(),
         }
         Ok(())
@@ -837,6 +2036,62 @@ impl fmt::Display for LexError {
 mod tests {
     use super::*;
 
+    #[test]
+    fn stream_lexer() {
+        // A reader which hands out one byte at a time to exercise tokens (and
+        // characters) being split across reads.
+        struct Trickle<'a>(&'a [u8]);
+
+        impl std::io::Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                match self.0.split_first() {
+                    Some((b, rest)) if !buf.is_empty() => {
+                        buf[0] = *b;
+                        self.0 = rest;
+                        Ok(1)
+                    }
+                    _ => Ok(0),
+                }
+            }
+        }
+
+        let input =
+            "(module ;; comment\n (; (; nested ;) ;) (func $f \"a\\\"\u{1f600}\" 0x1_f 1.5e3));";
+        let expected = Lexer::new(input).collect::<Result<Vec<_>, _>>().unwrap();
+        let mut lexer = StreamLexer::new(Trickle(input.as_bytes()));
+        let mut actual = Vec::new();
+        while let Some(token) = lexer.parse().unwrap() {
+            actual.push(token.src().to_string());
+        }
+        let expected = expected.iter().map(|t| t.src()).collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+
+        let mut lexer = StreamLexer::new(Trickle(b"(module\n \"abc"));
+        let err = loop {
+            match lexer.parse() {
+                Ok(Some(_)) => {}
+                Ok(None) => panic!("expected an error"),
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(err.lex_error(), Some(&LexError::UnexpectedEof));
+        assert_eq!(err.span().offset, 13);
+
+        // Tokens before malformed utf-8 are still returned.
+        let mut lexer = StreamLexer::new(Trickle(b"(module \xff)"));
+        let mut tokens = Vec::new();
+        let err = loop {
+            match lexer.parse() {
+                Ok(Some(token)) => tokens.push(token.src().to_string()),
+                Ok(None) => panic!("expected an error"),
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(tokens, ["(", "module", " "]);
+        assert_eq!(err.message(), "malformed UTF-8 encoding");
+        assert_eq!(err.span().offset, 8);
+    }
+
     #[test]
     fn ws_smoke() {
         fn get_whitespace(input: &str) -> &str {
@@ -880,6 +2135,65 @@ mod tests {
         assert_eq!(get_block_comment("(; (;;) ;)"), "(; (;;) ;)");
     }
 
+    #[test]
+    fn token_stream() {
+        fn src<'a>(tokens: &[Token<'a>]) -> Vec<&'a str> {
+            tokens.iter().map(|t| t.src()).collect()
+        }
+
+        let wat = ";; header\n\n(module (; a ;) $m\r\n  (func)) ;; last\n\n;; end\n";
+        let mut stream = TokenStream::new(wat);
+        let tokens = stream.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+        let text = tokens
+            .iter()
+            .flat_map(|t| {
+                src(&t.leading)
+                    .into_iter()
+                    .chain(Some(t.token.src()))
+                    .chain(src(&t.trailing))
+            })
+            .chain(src(stream.end_trivia()))
+            .collect::<String>();
+        assert_eq!(text, wat);
+
+        assert_eq!(src(&tokens[0].leading), [";; header", "\n\n"]);
+        assert!(tokens[0].blank_line_before);
+        assert!(!tokens[0].line_comment_before);
+        assert_eq!(tokens[1].token.src(), "module");
+        assert_eq!(src(&tokens[1].trailing), [" ", "(; a ;)", " "]);
+        assert_eq!(src(&tokens[2].trailing), ["\r\n"]);
+        assert_eq!(src(&tokens[3].leading), ["  "]);
+        assert!(!tokens[3].blank_line_before);
+        assert_eq!(tokens[6].token.src(), ")");
+        assert_eq!(src(&tokens[6].trailing), [" ", ";; last", "\n"]);
+        assert_eq!(src(stream.end_trivia()), ["\n", ";; end", "\n"]);
+
+        let mut stream = TokenStream::new(" ;; nothing");
+        assert!(stream.next().is_none());
+        assert_eq!(src(stream.end_trivia()), [" ", ";; nothing"]);
+
+        let flags = |wat| {
+            TokenStream::new(wat)
+                .map(|t| {
+                    let t = t.unwrap();
+                    (t.blank_line_before, t.line_comment_before)
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            flags("a\n b\n\n c \n \t\r\n d"),
+            [(false, false), (false, false), (true, false), (true, false)]
+        );
+        assert_eq!(
+            flags("\n;; x\na\n  ;; y\n  ;; z\n  b ;; c\nc"),
+            [(true, true), (false, true), (false, false)]
+        );
+        assert_eq!(
+            flags("a\n;; x\n\nb\n;; y\n(; z ;)\nc\n(; z ;) ;; y\nd"),
+            [(false, false), (true, false), (false, false), (false, true)]
+        );
+    }
+
     fn get_token(input: &str) -> Token<'_> {
         Lexer::new(input)
             .parse()
@@ -927,11 +2241,36 @@ mod tests {
             &*get_string("\"\\u{0_f_3}\""),
             '\u{0f3}'.encode_utf8(&mut [0; 4]).as_bytes()
         );
+        let err = Lexer::new("\"\\u{0__f3}\"").parse().unwrap_err();
+        assert_eq!(err.lex_error(), Some(&LexError::LoneUnderscore));
 
         for i in 0..=255i32 {
             let s = format!("\"\\{:02x}\"", i);
             assert_eq!(&*get_string(&s), &[i as u8]);
         }
+
+        // Decoding the source text lazily gives the same bytes.
+        for input in &[
+            "\"\"",
+            "\"a b c d\"",
+            "\"\\\"\\'\\n\\t\\r\\\\\"",
+            "\"x\\01\\fFy\"",
+            "\"\\u{1}\\u{0_f_3}\\u{1f600}z\"",
+            "\"\u{1f600}\"",
+        ] {
+            match get_token(input) {
+                Token::String(s) => {
+                    let bytes = s.bytes().collect::<Vec<_>>();
+                    assert_eq!(bytes, s.val(), "{}", input);
+                    assert_eq!(s.as_str(), str::from_utf8(s.val()).ok());
+                }
+                other => panic!("not string {:?}", other),
+            }
+        }
+        match get_token("\"\\ff\"") {
+            Token::String(s) => assert_eq!(s.as_str(), None),
+            other => panic!("not string {:?}", other),
+        }
     }
 
     #[test]
@@ -954,7 +2293,7 @@ mod tests {
     fn keyword() {
         fn get_keyword(input: &str) -> &str {
             match get_token(input) {
-                Token::Keyword(s) => s,
+                Token::Keyword(k) => k.src(),
                 other => panic!("not id {:?}", other),
             }
         }
@@ -965,6 +2304,77 @@ mod tests {
         assert_eq!(get_keyword("x_z "), "x_z");
     }
 
+    #[test]
+    fn limits() {
+        fn check(input: &str, limits: Limits) -> Option<LexError> {
+            Lexer::new_with_limits(input, limits)
+                .find_map(|t| t.err())
+                .map(|e| e.lex_error().unwrap().clone())
+        }
+        let nesting = Limits {
+            block_comment_nesting: 2,
+            ..Limits::default()
+        };
+        assert_eq!(check("(; (; ;) ;)", nesting), None);
+        assert_eq!(
+            check("(; (; (; ;) ;) ;)", nesting),
+            Some(LexError::LimitExceeded(Limit::BlockCommentNesting))
+        );
+        let none = Limits {
+            block_comment_nesting: 0,
+            ..Limits::default()
+        };
+        assert_eq!(
+            check("(; ;)", none),
+            Some(LexError::LimitExceeded(Limit::BlockCommentNesting))
+        );
+        let depth = Limits {
+            paren_depth: 2,
+            ..Limits::default()
+        };
+        assert_eq!(check("(()) (())", depth), None);
+        assert_eq!(
+            check("((()))", depth),
+            Some(LexError::LimitExceeded(Limit::ParenDepth))
+        );
+        let tokens = Limits {
+            tokens: 3,
+            ..Limits::default()
+        };
+        assert_eq!(check("a b", tokens), None);
+        assert_eq!(
+            check("a b ", tokens),
+            Some(LexError::LimitExceeded(Limit::Tokens))
+        );
+        let length = Limits {
+            string_length: 2,
+            ..Limits::default()
+        };
+        assert_eq!(check("\"ab\" \"\\41\\42\"", length), None);
+        assert_eq!(
+            check("\"abc\"", length),
+            Some(LexError::LimitExceeded(Limit::StringLength))
+        );
+    }
+
+    #[test]
+    fn keyword_kind() {
+        fn get_kind(input: &str) -> KeywordKind {
+            match get_token(input) {
+                Token::Keyword(k) => k.kind(),
+                other => panic!("not keyword {:?}", other),
+            }
+        }
+        assert_eq!(get_kind("i32.add"), KeywordKind::Instruction);
+        assert_eq!(get_kind("get_local"), KeywordKind::Instruction);
+        assert_eq!(get_kind("block"), KeywordKind::Instruction);
+        assert_eq!(get_kind("i32"), KeywordKind::Type);
+        assert_eq!(get_kind("funcref"), KeywordKind::Type);
+        assert_eq!(get_kind("func"), KeywordKind::Structural);
+        assert_eq!(get_kind("assert_return"), KeywordKind::Structural);
+        assert_eq!(get_kind("x_z"), KeywordKind::Unknown);
+    }
+
     #[test]
     fn reserved() {
         fn get_reserved(input: &str) -> &str {
@@ -977,6 +2387,59 @@ mod tests {
         assert_eq!(get_reserved("^_x "), "^_x");
     }
 
+    #[test]
+    fn custom() {
+        fn directive(input: &str) -> Option<usize> {
+            let name = input.strip_prefix('%')?;
+            match name.find(|c: char| !c.is_ascii_lowercase()) {
+                Some(0) => None,
+                Some(len) => Some(len + 1),
+                None => Some(input.len()),
+            }
+        }
+        fn placeholder(input: &str) -> Option<usize> {
+            if !input.starts_with("{{") {
+                return None;
+            }
+            input.find("}}").map(|end| end + 2)
+        }
+        fn lex(input: &str) -> Vec<Token<'_>> {
+            let mut lexer = Lexer::new(input);
+            lexer
+                .recognize("directive", directive)
+                .recognize("placeholder", placeholder);
+            lexer
+                .filter(|t| !matches!(t, Ok(Token::Whitespace(_))))
+                .collect::<Result<_, _>>()
+                .unwrap()
+        }
+        let custom = |name, src| Token::Custom { name, src };
+
+        assert_eq!(
+            lex("%include {{file name}} ${{x}}"),
+            [
+                custom("directive", "%include"),
+                custom("placeholder", "{{file name}}"),
+                Token::Reserved("$"),
+                custom("placeholder", "{{x}}"),
+            ]
+        );
+        // Recognizers don't take precedence over other tokens, and tokens
+        // they don't match are still reserved.
+        assert_eq!(
+            lex("%0 {x} $id"),
+            [
+                Token::Reserved("%0"),
+                Token::Reserved("{"),
+                Token::Keyword(Keyword("x")),
+                Token::Reserved("}"),
+                Token::Id("$id"),
+            ]
+        );
+        // Without recognizers the same input is reserved.
+        assert_eq!(get_token("{{x}}"), Token::Reserved("{"));
+    }
+
     #[test]
     fn integer() {
         fn get_integer(input: &str) -> String {
@@ -999,6 +2462,39 @@ mod tests {
         assert_eq!(get_integer("0x10"), "10");
     }
 
+    #[test]
+    fn integer_conversions() {
+        fn integer(input: &str) -> Integer<'_> {
+            match get_token(input) {
+                Token::Integer(i) => i,
+                other => panic!("not integer {:?}", other),
+            }
+        }
+        assert_eq!(integer("-1").as_i32(), Ok(-1));
+        assert_eq!(integer("0xffffffff").as_i32(), Ok(-1));
+        assert_eq!(integer("4294967295").as_i32(), Ok(-1));
+        assert_eq!(integer("-0x8000_0000").as_i32(), Ok(i32::MIN));
+        assert_eq!(integer("+0x7fff_ffff").as_i32(), Ok(i32::MAX));
+        assert_eq!(integer("4294967296").as_i32(), Err(LexError::NumberTooBig));
+        assert_eq!(integer("-2147483649").as_i32(), Err(LexError::NumberTooBig));
+
+        assert_eq!(integer("4294967295").as_u32(), Ok(u32::MAX));
+        assert_eq!(
+            integer("0x1_0000_0000").as_u32(),
+            Err(LexError::NumberTooBig)
+        );
+        assert_eq!(integer("-1").as_u32(), Err(LexError::NumberTooBig));
+
+        assert_eq!(integer("0xffffffffffffffff").as_i64(), Ok(-1));
+        assert_eq!(integer("-9223372036854775808").as_i64(), Ok(i64::MIN));
+        assert_eq!(
+            integer("18446744073709551616").as_i64(),
+            Err(LexError::NumberTooBig)
+        );
+        assert_eq!(integer("4294967296").as_u64(), Ok(1 << 32));
+        assert_eq!(integer("-0x1").as_u64(), Err(LexError::NumberTooBig));
+    }
+
     #[test]
     fn float() {
         fn get_float(input: &str) -> FloatVal<'_> {
@@ -1122,4 +2618,69 @@ mod tests {
             },
         );
     }
+
+    #[test]
+    fn float_to_bits() {
+        fn f32_bits(input: &str) -> Result<u32, LexError> {
+            match get_token(input) {
+                Token::Float(f) => f.to_f32_bits(),
+                other => panic!("not float {:?}", other),
+            }
+        }
+        fn f64_bits(input: &str) -> Result<u64, LexError> {
+            match get_token(input) {
+                Token::Float(f) => f.to_f64_bits(),
+                other => panic!("not float {:?}", other),
+            }
+        }
+        assert_eq!(f32_bits("1.5"), Ok(1.5f32.to_bits()));
+        assert_eq!(f32_bits("-0x0p0"), Ok(0x8000_0000));
+        assert_eq!(f32_bits("inf"), Ok(0x7f80_0000));
+        assert_eq!(f32_bits("-nan"), Ok(0xffc0_0000));
+        assert_eq!(f32_bits("nan:0x1"), Ok(0x7f80_0001));
+        assert_eq!(
+            f64_bits("nan:0xf_ffff_ffff_ffff"),
+            Ok(0x7fff_ffff_ffff_ffff)
+        );
+
+        // Hex floats round to nearest, ties to even, down to subnormals.
+        assert_eq!(f32_bits("0x1.000001p0"), Ok(0x3f80_0000));
+        assert_eq!(f32_bits("0x1.000003p0"), Ok(0x3f80_0002));
+        assert_eq!(f32_bits("0x1p-149"), Ok(1));
+        assert_eq!(f32_bits("0x1.8p-149"), Ok(2));
+        assert_eq!(f32_bits("0x1p-150"), Ok(0));
+        assert_eq!(f32_bits("-0x1p-99999999999"), Ok(0x8000_0000));
+        assert_eq!(
+            f64_bits("0x1.fffffffffffffp1023"),
+            Ok(0x7fef_ffff_ffff_ffff)
+        );
+        assert_eq!(f64_bits("1e-400"), Ok(0));
+
+        // Values which round to infinity are out of range.
+        assert_eq!(f32_bits("1e39"), Err(LexError::NumberTooBig));
+        assert_eq!(f32_bits("0x1.ffffffp127"), Err(LexError::NumberTooBig));
+        assert_eq!(f64_bits("0x1p99999999999"), Err(LexError::NumberTooBig));
+        assert_eq!(f64_bits("1e309"), Err(LexError::NumberTooBig));
+
+        assert_eq!(f32_bits("nan:0x0"), Err(LexError::InvalidNanPayload));
+        assert_eq!(f32_bits("nan:0x80_0000"), Err(LexError::InvalidNanPayload));
+        assert_eq!(
+            f64_bits("nan:0x10_0000_0000_0000"),
+            Err(LexError::InvalidNanPayload)
+        );
+        assert_eq!(
+            f64_bits("nan:0x1_0000_0000_0000_0000"),
+            Err(LexError::InvalidNanPayload)
+        );
+    }
+
+    #[test]
+    fn error_position() {
+        let input = "(module\n  (func)\n  \"abc";
+        let err = Lexer::new(input).find_map(|t| t.err()).unwrap();
+        assert_eq!(err.span().offset, 23);
+        let rendered = err.to_string();
+        assert!(rendered.contains("--> <anon>:3:7"), "{}", rendered);
+        assert!(rendered.contains("   3 |   \"abc"), "{}", rendered);
+    }
 }
@@ -47,6 +47,7 @@
 
 #![deny(missing_docs, broken_intra_doc_links)]
 
+use std::cell::RefCell;
 use std::fmt;
 use std::path::{Path, PathBuf};
 
@@ -58,8 +59,13 @@ mod resolve;
 mod ast;
 pub use self::ast::*;
 
+#[cfg(feature = "wasm-module")]
+pub mod docs;
+pub mod highlight;
 pub mod lexer;
 pub mod parser;
+pub mod preprocess;
+pub mod source_map;
 
 /// A convenience error type to tie together all the detailed errors produced by
 /// this crate.
@@ -82,6 +88,8 @@ struct ErrorInner {
     file: Option<PathBuf>,
     span: Span,
     kind: ErrorKind,
+    expected: Vec<parser::TokenKindDescription>,
+    others: Vec<Error>,
 }
 
 #[derive(Debug)]
@@ -98,30 +106,30 @@ enum ErrorKind {
 }
 
 impl Error {
-    fn lex(span: Span, content: &str, kind: lexer::LexError) -> Error {
-        let mut ret = Error {
+    fn lex(span: Span, content: &str, line: usize, col: usize, kind: lexer::LexError) -> Error {
+        Error {
             inner: Box::new(ErrorInner {
-                text: None,
+                text: Some(Text::at(content, span, line, col)),
                 file: None,
                 span,
                 kind: ErrorKind::Lex(kind),
+                expected: Vec::new(),
+                others: Vec::new(),
             }),
-        };
-        ret.set_text(content);
-        return ret;
+        }
     }
 
-    fn parse(span: Span, content: &str, message: String) -> Error {
-        let mut ret = Error {
+    fn parse_at(span: Span, content: &str, line: usize, col: usize, message: String) -> Error {
+        Error {
             inner: Box::new(ErrorInner {
-                text: None,
+                text: Some(Text::at(content, span, line, col)),
                 file: None,
                 span,
                 kind: ErrorKind::Custom(message),
+                expected: Vec::new(),
+                others: Vec::new(),
             }),
-        };
-        ret.set_text(content);
-        return ret;
+        }
     }
 
     /// Creates a new error with the given `message` which is targeted at the
@@ -136,6 +144,8 @@ impl Error {
                 file: None,
                 span,
                 kind: ErrorKind::Custom(message),
+                expected: Vec::new(),
+                others: Vec::new(),
             }),
         }
     }
@@ -145,6 +155,24 @@ impl Error {
         self.inner.span
     }
 
+    /// Moves this error `offset` bytes further into the input, discarding any
+    /// source text already attached since it no longer lines up.
+    pub(crate) fn shift_span(&mut self, offset: usize) {
+        self.inner.span.offset += offset;
+        self.inner.text = None;
+        for other in self.inner.others.iter_mut() {
+            other.shift_span(offset);
+        }
+    }
+
+    /// Moves this error to `span` in the file at `path` with the given
+    /// `contents`, replacing any source text and path already attached.
+    pub(crate) fn relocate(&mut self, span: Span, path: &Path, contents: &str) {
+        self.inner.span = span;
+        self.inner.text = Some(Text::new(contents, span));
+        self.inner.file = Some(path.to_path_buf());
+    }
+
     /// To provide a more useful error this function can be used to extract
     /// relevant textual information about this error into the error itself.
     ///
@@ -152,6 +180,9 @@ impl Error {
     /// parsed, and this will extract a sub-slice as necessary to render in the
     /// `Display` implementation later on.
     pub fn set_text(&mut self, contents: &str) {
+        for other in self.inner.others.iter_mut() {
+            other.set_text(contents);
+        }
         if self.inner.text.is_some() {
             return;
         }
@@ -164,12 +195,46 @@ impl Error {
     /// The `path` here will be stored in this error and later rendered in the
     /// `Display` implementation.
     pub fn set_path(&mut self, path: &Path) {
+        for other in self.inner.others.iter_mut() {
+            other.set_path(path);
+        }
         if self.inner.file.is_some() {
             return;
         }
         self.inner.file = Some(path.to_path_buf());
     }
 
+    /// Returns the errors which were found along with this one, in the order
+    /// they appear in the input.
+    ///
+    /// This error is the first of them and these are the rest, which only
+    /// happens when resolving a module parsed with
+    /// [`ParseBuffer::recover`](parser::ParseBuffer::recover): the errors of
+    /// the fields which failed to parse are all reported, along with any
+    /// error resolving the rest of the module. They're also included when
+    /// this error is displayed.
+    pub fn others(&self) -> &[Error] {
+        &self.inner.others
+    }
+
+    /// Combines `errors` into one error, the first of them in the input with
+    /// the rest as its [`Error::others`], or returns `None` if there are none.
+    pub(crate) fn combine(mut errors: Vec<Error>) -> Option<Error> {
+        errors.sort_by_key(|e| e.span().offset);
+        let mut errors = errors.into_iter();
+        let mut ret = errors.next()?;
+        ret.inner.others.extend(errors);
+        Some(ret)
+    }
+
+    /// Applies `f` to this error and to each of its [`Error::others`].
+    pub(crate) fn map_all(mut self, f: &mut dyn FnMut(Error) -> Error) -> Error {
+        let others = std::mem::take(&mut self.inner.others);
+        let mut ret = f(self);
+        ret.inner.others = others.into_iter().map(|e| f(e)).collect();
+        ret
+    }
+
     /// Returns the underlying `LexError`, if any, that describes this error.
     pub fn lex_error(&self) -> Option<&lexer::LexError> {
         match &self.inner.kind {
@@ -178,6 +243,32 @@ impl Error {
         }
     }
 
+    /// Returns the tokens which were expected where this error happened, if
+    /// it's a parse error saying which tokens it expected.
+    ///
+    /// Editors can use these to offer completions at [`Error::span`]:
+    ///
+    /// ```
+    /// use wast::parser::{self, ParseBuffer, TokenKind};
+    /// use wast::Wat;
+    ///
+    /// let buf = ParseBuffer::new("(module (func (param $x i33)))").unwrap();
+    /// let err = match parser::parse::<Wat>(&buf) {
+    ///     Ok(_) => panic!("expected an error"),
+    ///     Err(err) => err,
+    /// };
+    /// let expected = err.expected();
+    /// assert!(expected.iter().any(|e| e.kind == TokenKind::Keyword("i32")));
+    /// assert!(expected.iter().any(|e| e.kind == TokenKind::Keyword("f64")));
+    /// ```
+    pub fn expected(&self) -> &[parser::TokenKindDescription] {
+        &self.inner.expected
+    }
+
+    pub(crate) fn set_expected(&mut self, expected: Vec<parser::TokenKindDescription>) {
+        self.inner.expected = expected;
+    }
+
     /// Returns the underlying message, if any, that describes this error.
     pub fn message(&self) -> String {
         match &self.inner.kind {
@@ -193,34 +284,37 @@ impl fmt::Display for Error {
             ErrorKind::Lex(e) => e as &dyn fmt::Display,
             ErrorKind::Custom(e) => e as &dyn fmt::Display,
         };
-        let text = match &self.inner.text {
-            Some(text) => text,
-            None => {
-                return write!(f, "{} at byte offset {}", err, self.inner.span.offset);
-            }
-        };
-        let file = self
-            .inner
-            .file
-            .as_ref()
-            .and_then(|p| p.to_str())
-            .unwrap_or("<anon>");
-        write!(
-            f,
-            "\
+        match &self.inner.text {
+            Some(text) => {
+                let file = self
+                    .inner
+                    .file
+                    .as_ref()
+                    .and_then(|p| p.to_str())
+                    .unwrap_or("<anon>");
+                write!(
+                    f,
+                    "\
 {err}
      --> {file}:{line}:{col}
       |
  {line:4} | {text}
       | {marker:>0$}",
-            text.col + 1,
-            file = file,
-            line = text.line + 1,
-            col = text.col + 1,
-            err = err,
-            text = text.snippet,
-            marker = "^",
-        )
+                    text.col + 1,
+                    file = file,
+                    line = text.line + 1,
+                    col = text.col + 1,
+                    err = err,
+                    text = text.snippet,
+                    marker = "^",
+                )?;
+            }
+            None => write!(f, "{} at byte offset {}", err, self.inner.span.offset)?,
+        }
+        for other in self.inner.others.iter() {
+            write!(f, "\n\n{}", other)?;
+        }
+        Ok(())
     }
 }
 
@@ -232,4 +326,48 @@ impl Text {
         let snippet = content.lines().nth(line).unwrap_or("").to_string();
         Text { line, col, snippet }
     }
+
+    /// Same as `new`, but with the `line` and `col` of `span` already known
+    /// so the input doesn't need to be scanned from the start.
+    fn at(content: &str, span: Span, line: usize, col: usize) -> Text {
+        let snippet = content[span.offset - col..]
+            .lines()
+            .next()
+            .unwrap_or("")
+            .to_string();
+        Text { line, col, snippet }
+    }
+}
+
+/// The byte offsets of the start of each line of a text, which are computed
+/// lazily the first time a line/column position is requested.
+#[derive(Clone, Default)]
+pub(crate) struct LineIndex {
+    starts: RefCell<Vec<usize>>,
+}
+
+impl LineIndex {
+    /// Returns the 0-indexed line and column of the byte `offset` in `text`,
+    /// which must be the same text every time this is called.
+    ///
+    /// This is the same as [`Span::linecol_in`] but the first call builds the
+    /// index, so subsequent calls only take time logarithmic in the number of
+    /// lines.
+    pub(crate) fn position(&self, text: &str, offset: usize) -> (usize, usize) {
+        let mut starts = self.starts.borrow_mut();
+        if starts.is_empty() {
+            starts.push(0);
+            starts.extend(
+                text.bytes()
+                    .enumerate()
+                    .filter(|(_, b)| *b == b'\n')
+                    .map(|(i, _)| i + 1),
+            );
+        }
+        let line = match starts.binary_search(&offset) {
+            Ok(i) => i,
+            Err(i) => i - 1,
+        };
+        (line, offset - starts[line])
+    }
 }
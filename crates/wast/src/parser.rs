@@ -63,11 +63,13 @@
 //! This module is heavily inspired by [`syn`](https://docs.rs/syn) so you can
 //! likely also draw inspiration from the excellent examples in the `syn` crate.
 
-use crate::lexer::{Float, Integer, Lexer, Token};
-use crate::{Error, Span};
+use crate::lexer::{self, Float, Integer, Lexer, Limits, Token};
+use crate::{Error, LineIndex, Span};
+use std::borrow::Cow;
 use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::fmt;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::usize;
 
 /// A top-level convenience parseing function that parss a `T` from `buf` and
@@ -264,6 +266,47 @@ pub trait Peek {
     /// Returns a human-readable name of this token to display when generating
     /// errors about this token missing.
     fn display() -> &'static str;
+
+    /// Returns what kind of token this is, for tools like editors which offer
+    /// completions for the tokens a parse error expected, see
+    /// [`Error::expected`].
+    ///
+    /// Defaults to [`TokenKind::Other`].
+    fn token_kind() -> TokenKind {
+        TokenKind::Other
+    }
+}
+
+/// A token which a parse error expected, as returned by [`Error::expected`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TokenKindDescription {
+    /// What kind of token was expected.
+    pub kind: TokenKind,
+    /// How the token is described in error messages, as returned by
+    /// [`Peek::display`].
+    pub display: &'static str,
+}
+
+/// The kind of a [`TokenKindDescription`], as returned by [`Peek::token_kind`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// The keyword with the given text, like `func`.
+    Keyword(&'static str),
+    /// The reserved symbol with the given text.
+    Reserved(&'static str),
+    /// The annotation with the given name, without its `@`, like `custom`.
+    Annotation(&'static str),
+    /// A left paren.
+    LParen,
+    /// An identifier, like `$foo`.
+    Id,
+    /// A string.
+    String,
+    /// An integer.
+    Integer,
+    /// Anything else, like a type or an index, which is only described by its
+    /// [`TokenKindDescription::display`].
+    Other,
 }
 
 /// A convenience type definition for `Result` where the error is hardwired to
@@ -277,15 +320,60 @@ pub type Result<T> = std::result::Result<T, Error>;
 /// [`parse`] function.
 pub struct ParseBuffer<'a> {
     // list of tokens from the tokenized source (including whitespace and
-    // comments), and the second element is how to skip this token, if it can be
-    // skipped.
-    tokens: Box<[(Token<'a>, Cell<NextTokenAt>)]>,
+    // comments), along with how to skip each token and its symbol.
+    tokens: Box<[TokenSlot<'a>]>,
+    // a number unique to this buffer which scopes the symbols of its tokens,
+    // or 0 if the numbers ran out.
+    id: u32,
     input: &'a str,
     cur: Cell<usize>,
     known_annotations: RefCell<HashMap<String, usize>>,
     depth: Cell<usize>,
+    // where lines start in `input`, for the positions of errors.
+    lines: LineIndex,
+    // the names of quoted identifiers which contain escapes, by the index of
+    // their token, since they can't be borrowed from `input`.
+    escaped_ids: HashMap<usize, Box<str>>,
+    // whether module fields which fail to parse are skipped, see
+    // `ParseBuffer::recover`.
+    recover: bool,
 }
 
+struct TokenSlot<'a> {
+    token: Token<'a>,
+    // where the next token is if this one can be skipped, as encoded by
+    // `NextTokenAt::encode`.
+    next: Cell<u32>,
+    // identifiers with the same name are given the same nonzero symbol,
+    // which `Id` compares instead of its name when both come from this
+    // buffer. All other tokens have the symbol 0. This doesn't make the slot
+    // any larger since it fits in the padding after `next`.
+    symbol: u32,
+}
+
+/// The symbol an identifier was interned as by the [`ParseBuffer`] it was
+/// parsed from, which identifies its name among those of the buffer.
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct Symbol {
+    // the `id` of the buffer, or 0 if the identifier wasn't parsed.
+    buffer: u32,
+    index: u32,
+}
+
+impl Symbol {
+    /// Returns whether `self` and `other` are symbols for the same name, if
+    /// that can be told from the symbols alone because they're of the same
+    /// buffer.
+    pub(crate) fn same_name(self, other: Symbol) -> Option<bool> {
+        if self.buffer == 0 || self.buffer != other.buffer {
+            return None;
+        }
+        Some(self.index == other.index)
+    }
+}
+
+static NEXT_BUFFER_ID: AtomicU32 = AtomicU32::new(1);
+
 #[derive(Copy, Clone, Debug)]
 enum NextTokenAt {
     /// Haven't computed where the next token is yet.
@@ -296,6 +384,27 @@ enum NextTokenAt {
     Eof,
 }
 
+impl NextTokenAt {
+    // The index of the next token is always greater than the index of the
+    // token it was computed for, so 0 can't be an index. Token indices also
+    // fit in a `u32` less than `u32::MAX`, which is checked when lexing.
+    fn encode(self) -> u32 {
+        match self {
+            NextTokenAt::Unknown => 0,
+            NextTokenAt::Index(i) => i as u32,
+            NextTokenAt::Eof => u32::MAX,
+        }
+    }
+
+    fn decode(next: u32) -> NextTokenAt {
+        match next {
+            0 => NextTokenAt::Unknown,
+            u32::MAX => NextTokenAt::Eof,
+            i => NextTokenAt::Index(i as usize),
+        }
+    }
+}
+
 /// An in-progress parser for the tokens of a WebAssembly text file.
 ///
 /// A `Parser` is argument to the [`Parse`] trait and is now the input stream is
@@ -315,7 +424,7 @@ pub struct Parser<'a> {
 /// For more information see the [`Parser::lookahead1`] method.
 pub struct Lookahead1<'a> {
     parser: Parser<'a>,
-    attempts: Vec<&'static str>,
+    attempts: Vec<TokenKindDescription>,
 }
 
 /// An immutable cursor into a list of tokens.
@@ -332,25 +441,151 @@ pub struct Cursor<'a> {
 impl ParseBuffer<'_> {
     /// Creates a new [`ParseBuffer`] by lexing the given `input` completely.
     ///
+    /// Identifiers are interned while lexing, so comparing the names of
+    /// identifiers parsed from the buffer compares integers rather than
+    /// strings.
+    ///
     /// # Errors
     ///
     /// Returns an error if `input` fails to lex.
     pub fn new(input: &str) -> Result<ParseBuffer<'_>> {
+        ParseBuffer::new_with_limits(input, Limits::default())
+    }
+
+    /// Creates a new [`ParseBuffer`] by lexing the given `input` completely,
+    /// bounding the resources used to do so with `limits`.
+    ///
+    /// Since the paren depth of `input` is bounded, so is the recursion of
+    /// parsers using the buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `input` fails to lex or exceeds any of the
+    /// `limits`, in which case [`Error::lex_error`] returns
+    /// [`LexError::LimitExceeded`](crate::lexer::LexError::LimitExceeded).
+    pub fn new_with_limits(input: &str, limits: Limits) -> Result<ParseBuffer<'_>> {
+        ParseBuffer::build(input, Lexer::new_with_limits(input, limits))
+    }
+
+    /// Creates a new [`ParseBuffer`] from `tokens` which have already been
+    /// lexed from `input`, for example by a preprocessing layer which filters
+    /// or expands them.
+    ///
+    /// The tokens can be any subset of those of `input`, in any order and
+    /// repeated any number of times, and should include whitespace and
+    /// comments where they're significant, like in annotations. Spans in
+    /// errors and in the parsed AST are offsets into `input`.
+    ///
+    /// ```
+    /// # fn foo() -> Result<(), wast::Error> {
+    /// use wast::lexer::Lexer;
+    /// use wast::parser::{self, ParseBuffer};
+    /// use wast::{ModuleKind, Wat};
+    ///
+    /// // Expand the module to contain three copies of the function.
+    /// let wat = "(module) (func)";
+    /// let tokens = Lexer::new(wat).collect::<Result<Vec<_>, _>>()?;
+    /// let mut expanded = tokens[..2].to_vec();
+    /// for _ in 0..3 {
+    ///     expanded.extend(tokens[3..].iter().cloned());
+    /// }
+    /// expanded.push(tokens[2].clone());
+    ///
+    /// let buf = ParseBuffer::from_tokens(wat, expanded)?;
+    /// let wat = parser::parse::<Wat>(&buf)?;
+    /// match wat.module.kind {
+    ///     ModuleKind::Text(fields) => assert_eq!(fields.len(), 3),
+    ///     ModuleKind::Binary(_) => unreachable!(),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an annotation in `tokens` isn't closed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the source text of a token isn't part of `input`.
+    pub fn from_tokens<'a>(input: &'a str, tokens: Vec<Token<'a>>) -> Result<ParseBuffer<'a>> {
+        let range = input.as_ptr() as usize..=input.as_ptr() as usize + input.len();
+        for token in tokens.iter() {
+            let src = token.src();
+            assert!(
+                range.contains(&(src.as_ptr() as usize))
+                    && range.contains(&(src.as_ptr() as usize + src.len())),
+                "token {:?} isn't part of the input",
+                src
+            );
+        }
+        ParseBuffer::build(input, tokens.into_iter().map(Ok))
+    }
+
+    fn build<'a>(
+        input: &'a str,
+        lexed: impl Iterator<Item = Result<Token<'a>>>,
+    ) -> Result<ParseBuffer<'a>> {
         let mut tokens = Vec::new();
-        for token in Lexer::new(input) {
-            tokens.push((token?, Cell::new(NextTokenAt::Unknown)));
+        let mut symbols = HashMap::new();
+        let mut escaped_ids = HashMap::new();
+        for token in lexed {
+            let token = token?;
+            if tokens.len() == u32::MAX as usize {
+                let offset = token.src().as_ptr() as usize - input.as_ptr() as usize;
+                return Err(Error::new(Span { offset }, "too many tokens".to_string()));
+            }
+            // Quoted identifiers are interned by their name so that `$"foo"`
+            // is the same identifier as `$foo`.
+            let symbol = match &token {
+                Token::Id(src) => {
+                    let name = lexer::id_name(src);
+                    if let Cow::Owned(name) = &name {
+                        escaped_ids.insert(tokens.len(), name.clone().into_boxed_str());
+                    }
+                    intern(&mut symbols, name)
+                }
+                _ => 0,
+            };
+            tokens.push(TokenSlot {
+                token,
+                next: Cell::new(NextTokenAt::Unknown.encode()),
+                symbol,
+            });
         }
         let ret = ParseBuffer {
             tokens: tokens.into_boxed_slice(),
+            id: NEXT_BUFFER_ID
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |id| id.checked_add(1))
+                .unwrap_or(0),
             cur: Cell::new(0),
             depth: Cell::new(0),
+            lines: LineIndex::default(),
             input,
             known_annotations: Default::default(),
+            escaped_ids,
+            recover: false,
         };
         ret.validate_annotations()?;
         Ok(ret)
     }
 
+    /// Sets whether parsing a module recovers from errors in its fields,
+    /// which is off by default.
+    ///
+    /// When recovering, a module field which fails to parse is skipped up to
+    /// its closing paren and recorded as a
+    /// [`ModuleField::Error`](crate::ModuleField::Error), and parsing
+    /// continues with the next field, so that all the well-formed fields of
+    /// a module can be analyzed. Errors outside of module fields, and errors
+    /// lexing the input, are still returned. Resolving or encoding a module
+    /// with skipped fields fails with all of their errors, see
+    /// [`Error::others`].
+    pub fn recover(&mut self, recover: bool) -> &mut Self {
+        self.recover = recover;
+        self
+    }
+
     fn parser(&self) -> Parser<'_> {
         Parser { buf: self }
     }
@@ -366,8 +601,8 @@ impl ParseBuffer<'_> {
             Annotation { depth: usize, span: Span },
         }
         let mut state = State::None;
-        for token in self.tokens.iter() {
-            state = match (&token.0, state) {
+        for slot in self.tokens.iter() {
+            state = match (&slot.token, state) {
                 // From nothing, a `(` starts the search for an annotation
                 (LParen(_), State::None) => State::LParen,
                 // ... otherwise in nothing we alwyas preserve that state.
@@ -410,6 +645,22 @@ impl ParseBuffer<'_> {
     fn input_pos(&self, src: &str) -> usize {
         src.as_ptr() as usize - self.input.as_ptr() as usize
     }
+
+    /// Returns the 0-indexed line and column of the byte `offset` in the input
+    /// of this buffer.
+    ///
+    /// This is the same as [`Span::linecol_in`] but the first call builds an
+    /// index of where lines start in the input, so subsequent calls only take
+    /// time logarithmic in the number of lines. This makes it suitable for
+    /// rendering many diagnostics for one file.
+    pub fn text_position(&self, offset: usize) -> (usize, usize) {
+        self.lines.position(self.input, offset)
+    }
+}
+
+fn intern<'a>(symbols: &mut HashMap<Cow<'a, str>, u32>, name: Cow<'a, str>) -> u32 {
+    let next = symbols.len() as u32 + 1;
+    *symbols.entry(name).or_insert(next)
 }
 
 impl<'a> Parser<'a> {
@@ -432,7 +683,7 @@ impl<'a> Parser<'a> {
     pub(crate) fn has_meaningful_tokens(self) -> bool {
         self.buf.tokens[self.cursor().cur..]
             .iter()
-            .any(|(t, _)| match t {
+            .any(|slot| match slot.token {
                 Token::Whitespace(_) | Token::LineComment(_) | Token::BlockComment(_) => false,
                 _ => true,
             })
@@ -680,6 +931,40 @@ impl<'a> Parser<'a> {
         return res;
     }
 
+    /// Returns whether this parser recovers from errors, see
+    /// [`ParseBuffer::recover`].
+    pub fn recovering(self) -> bool {
+        self.buf.recover
+    }
+
+    /// Skips the next token, or if it's a `(`, everything up to and including
+    /// its matching `)`.
+    ///
+    /// This is a low-level method for parsers which recover from errors. Does
+    /// nothing if this parser is empty. Returns an error, without skipping
+    /// anything, if the input ends before the matching `)`.
+    pub fn skip(self) -> Result<()> {
+        self.step(|mut cursor| {
+            let mut depth = 0usize;
+            loop {
+                let mut next = cursor;
+                match next.advance_token() {
+                    Some(Token::RParen(_)) if depth == 0 => break,
+                    Some(Token::RParen(_)) => depth -= 1,
+                    Some(Token::LParen(_)) => depth += 1,
+                    Some(_) => {}
+                    None if depth == 0 => break,
+                    None => return Err(cursor.error("expected `)`")),
+                }
+                cursor = next;
+                if depth == 0 {
+                    break;
+                }
+            }
+            Ok(((), cursor))
+        })
+    }
+
     /// Return the depth of nested parens we've parsed so far.
     ///
     /// This is a low-level method that is only useful for implementing
@@ -721,7 +1006,8 @@ impl<'a> Parser<'a> {
     }
 
     fn error_at(self, span: Span, msg: &dyn fmt::Display) -> Error {
-        Error::parse(span, self.buf.input, msg.to_string())
+        let (line, col) = self.buf.text_position(span.offset);
+        Error::parse_at(span, self.buf.input, line, col, msg.to_string())
     }
 
     /// Returns the span of the current token
@@ -902,9 +1188,9 @@ impl<'a> Cursor<'a> {
     ///
     /// Does not take into account whitespace or comments.
     pub(crate) fn prev_span(&self) -> Option<Span> {
-        let (token, _) = self.parser.buf.tokens.get(self.cur.checked_sub(1)?)?;
+        let slot = self.parser.buf.tokens.get(self.cur.checked_sub(1)?)?;
         Some(Span {
-            offset: self.parser.buf.input_pos(token.src()),
+            offset: self.parser.buf.input_pos(slot.token.src()),
         })
     }
 
@@ -953,7 +1239,22 @@ impl<'a> Cursor<'a> {
     /// unknown annotations.
     pub fn id(mut self) -> Option<(&'a str, Self)> {
         match self.advance_token()? {
-            Token::Id(id) => Some((&id[1..], self)),
+            Token::Id(id) => Some((self.id_name(id), self)),
+            _ => None,
+        }
+    }
+
+    /// Same as [`Cursor::id`], but also returns the symbol the identifier was
+    /// interned as.
+    pub(crate) fn id_symbol(mut self) -> Option<(&'a str, Symbol, Self)> {
+        match self.advance_token()? {
+            Token::Id(id) => {
+                let symbol = Symbol {
+                    buffer: self.parser.buf.id,
+                    index: self.parser.buf.tokens[self.cur - 1].symbol,
+                };
+                Some((self.id_name(id), symbol, self))
+            }
             _ => None,
         }
     }
@@ -969,7 +1270,7 @@ impl<'a> Cursor<'a> {
     /// unknown annotations.
     pub fn keyword(mut self) -> Option<(&'a str, Self)> {
         match self.advance_token()? {
-            Token::Keyword(id) => Some((id, self)),
+            Token::Keyword(k) => Some((k.src(), self)),
             _ => None,
         }
     }
@@ -1063,7 +1364,7 @@ impl<'a> Cursor<'a> {
         if !token.starts_with("@") || token.len() <= 1 {
             return None;
         }
-        match &self.parser.buf.tokens.get(self.cur.wrapping_sub(1))?.0 {
+        match &self.parser.buf.tokens.get(self.cur.wrapping_sub(1))?.token {
             Token::LParen(_) => Some((&token[1..], cursor)),
             _ => None,
         }
@@ -1076,7 +1377,7 @@ impl<'a> Cursor<'a> {
     /// This function will only skip whitespace, no other tokens.
     pub fn comment(mut self) -> Option<(&'a str, Self)> {
         let comment = loop {
-            match &self.parser.buf.tokens.get(self.cur)?.0 {
+            match &self.parser.buf.tokens.get(self.cur)?.token {
                 Token::LineComment(c) | Token::BlockComment(c) => {
                     self.cur += 1;
                     break c;
@@ -1090,6 +1391,17 @@ impl<'a> Cursor<'a> {
         Some((comment, self))
     }
 
+    /// Returns the name of the identifier `src` of the token just advanced
+    /// over.
+    fn id_name(&self, src: &'a str) -> &'a str {
+        let buf = self.parser.buf;
+        match buf.escaped_ids.get(&(self.cur - 1)) {
+            Some(name) => name,
+            None if src.starts_with("$\"") => &src[2..src.len() - 1],
+            None => &src[1..],
+        }
+    }
+
     fn advance_token(&mut self) -> Option<&'a Token<'a>> {
         let known_annotations = self.parser.buf.known_annotations.borrow();
         let is_known_annotation = |name: &str| match known_annotations.get(name) {
@@ -1098,7 +1410,7 @@ impl<'a> Cursor<'a> {
         };
 
         loop {
-            let (token, next) = self.parser.buf.tokens.get(self.cur)?;
+            let TokenSlot { token, next, .. } = self.parser.buf.tokens.get(self.cur)?;
 
             // If we're currently pointing at a token, and it's not the start
             // of an annotation, then we return that token and advance
@@ -1130,14 +1442,14 @@ impl<'a> Cursor<'a> {
             // dynamic and we can't cache which annotations are skipped. What we
             // can do though is cache the number of tokens in the annotation so
             // we know how to skip ahead of it.
-            match next.get() {
+            match NextTokenAt::decode(next.get()) {
                 NextTokenAt::Unknown => match self.find_next() {
                     Some(i) => {
-                        next.set(NextTokenAt::Index(i));
+                        next.set(NextTokenAt::Index(i).encode());
                         self.cur = i;
                     }
                     None => {
-                        next.set(NextTokenAt::Eof);
+                        next.set(NextTokenAt::Eof.encode());
                         return None;
                     }
                 },
@@ -1148,11 +1460,11 @@ impl<'a> Cursor<'a> {
     }
 
     fn annotation_start(&self) -> Option<&'a str> {
-        match self.parser.buf.tokens.get(self.cur).map(|p| &p.0) {
+        match self.parser.buf.tokens.get(self.cur).map(|p| &p.token) {
             Some(Token::LParen(_)) => {}
             _ => return None,
         }
-        let reserved = match self.parser.buf.tokens.get(self.cur + 1).map(|p| &p.0) {
+        let reserved = match self.parser.buf.tokens.get(self.cur + 1).map(|p| &p.token) {
             Some(Token::Reserved(n)) => n,
             _ => return None,
         };
@@ -1178,7 +1490,7 @@ impl<'a> Cursor<'a> {
             let mut depth = 1;
             self.cur += 1;
             while depth > 0 {
-                match &self.parser.buf.tokens.get(self.cur)?.0 {
+                match &self.parser.buf.tokens.get(self.cur)?.token {
                     Token::LParen(_) => depth += 1,
                     Token::RParen(_) => depth -= 1,
                     _ => {}
@@ -1191,7 +1503,7 @@ impl<'a> Cursor<'a> {
         // ... otherwise we're pointing at whitespace/comments, so we need to
         // figure out how many of them we can skip.
         loop {
-            let (token, _) = self.parser.buf.tokens.get(self.cur)?;
+            let token = &self.parser.buf.tokens.get(self.cur)?.token;
             // and otherwise we skip all comments/whitespace and otherwise
             // get real intersted once a normal `Token` pops up.
             match token {
@@ -1213,7 +1525,10 @@ impl Lookahead1<'_> {
         if self.parser.peek::<T>() {
             true
         } else {
-            self.attempts.push(T::display());
+            self.attempts.push(TokenKindDescription {
+                kind: T::token_kind(),
+                display: T::display(),
+            });
             false
         }
     }
@@ -1222,8 +1537,15 @@ impl Lookahead1<'_> {
     /// [`Lookahead1::peek`] method was expected.
     ///
     /// Before calling this method you should call [`Lookahead1::peek`] for all
-    /// possible tokens you'd like to parse.
+    /// possible tokens you'd like to parse. They're available from
+    /// [`Error::expected`] of the returned error.
     pub fn error(self) -> Error {
+        let mut err = self.message();
+        err.set_expected(self.attempts);
+        err
+    }
+
+    fn message(&self) -> Error {
         match self.attempts.len() {
             0 => {
                 if self.parser.is_empty() {
@@ -1233,18 +1555,23 @@ impl Lookahead1<'_> {
                 }
             }
             1 => {
-                let message = format!("unexpected token, expected {}", self.attempts[0]);
+                let message = format!("unexpected token, expected {}", self.attempts[0].display);
                 self.parser.error(&message)
             }
             2 => {
                 let message = format!(
                     "unexpected token, expected {} or {}",
-                    self.attempts[0], self.attempts[1]
+                    self.attempts[0].display, self.attempts[1].display
                 );
                 self.parser.error(&message)
             }
             _ => {
-                let join = self.attempts.join(", ");
+                let join = self
+                    .attempts
+                    .iter()
+                    .map(|a| a.display)
+                    .collect::<Vec<_>>()
+                    .join(", ");
                 let message = format!("unexpected token, expected one of: {}", join);
                 self.parser.error(&message)
             }
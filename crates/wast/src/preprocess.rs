@@ -0,0 +1,507 @@
+//! An optional preprocessing stage for hand-written text files.
+//!
+//! Large test suites written in the text format often repeat themselves, and
+//! are otherwise generated with external tools like m4. The [`Preprocessor`]
+//! supports a couple of directives for this instead, which are written like
+//! s-expressions whose keyword starts with `!`:
+//!
+//! * `(!include "file.wat")` is replaced with the contents of `file.wat`,
+//!   which is found relative to the directory of the file including it.
+//! * `(!define name ($a $b) ...)` defines a macro `name` with the parameters
+//!   `$a` and `$b`. The definition itself expands to nothing.
+//! * `(!name x (y z))` expands to the body of the macro `name`, with each of
+//!   its parameters replaced by the corresponding argument. An argument is a
+//!   single token or a parenthesized list of tokens.
+//!
+//! The result is plain text which can be parsed as usual, and a map from its
+//! offsets back to the original files so that errors point at the text the
+//! user actually wrote:
+//!
+//! ```
+//! # fn foo() -> Result<(), wast::Error> {
+//! use std::path::Path;
+//! use wast::parser::{self, ParseBuffer};
+//! use wast::preprocess::Preprocessor;
+//! use wast::Wat;
+//!
+//! let wat = r#"
+//!     (!define add ($a $b) (i32.add (i32.const $a) (i32.const $b)))
+//!     (module
+//!       (func (result i32) (!add 1 2))
+//!       (func (result i32) (!add 3 (!add 4 5))))
+//! "#;
+//! let preprocessed = Preprocessor::new().preprocess_str(Path::new("test.wat"), wat)?;
+//! let buf = ParseBuffer::new(preprocessed.output()).map_err(|e| preprocessed.map_error(e))?;
+//! parser::parse::<Wat>(&buf).map_err(|e| preprocessed.map_error(e))?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::lexer::{Lexer, Token};
+use crate::source_map::{FileId, SourceMap};
+use crate::{Error, Span};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::mem;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// How deeply macro expansions may be nested, which bounds the recursion of
+/// macros which (directly or not) expand to themselves.
+const MAX_EXPANSION_DEPTH: usize = 100;
+
+/// Reads the contents of an included file.
+type Loader<'a> = Box<dyn FnMut(&Path) -> io::Result<String> + 'a>;
+
+/// Expands the directives of text files, see the [module
+/// documentation](self) for details.
+pub struct Preprocessor<'a> {
+    loader: Loader<'a>,
+    files: SourceMap,
+    macros: HashMap<String, Macro>,
+    // The files currently being included, to detect cycles.
+    including: Vec<PathBuf>,
+}
+
+/// The output of a [`Preprocessor`].
+#[derive(Debug)]
+pub struct Preprocessed {
+    output: String,
+    files: SourceMap,
+    // Consecutive runs of the output copied from a single place in one of
+    // `files`, in order of their position in the output.
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    output: usize,
+    file: FileId,
+    offset: usize,
+    len: usize,
+}
+
+struct Macro {
+    params: Vec<String>,
+    body: Vec<Tok>,
+}
+
+/// A token of one of the preprocessor's files. The text of tokens isn't
+/// borrowed so that files can be added while tokens are alive.
+#[derive(Clone)]
+struct Tok {
+    file: FileId,
+    start: usize,
+    end: usize,
+    kind: Kind,
+}
+
+#[derive(Clone, PartialEq)]
+enum Kind {
+    LParen,
+    RParen,
+    Trivia,
+    Id,
+    Directive,
+    String(Vec<u8>),
+    Other,
+}
+
+impl<'a> Preprocessor<'a> {
+    /// Creates a new preprocessor which reads included files from the file
+    /// system.
+    pub fn new() -> Preprocessor<'a> {
+        Preprocessor {
+            loader: Box::new(|path| fs::read_to_string(path)),
+            files: SourceMap::new(),
+            macros: HashMap::new(),
+            including: Vec::new(),
+        }
+    }
+
+    /// Configures how included files are read, for example to read them from
+    /// memory instead of the file system.
+    pub fn loader(
+        &mut self,
+        loader: impl FnMut(&Path) -> io::Result<String> + 'a,
+    ) -> &mut Preprocessor<'a> {
+        self.loader = Box::new(loader);
+        self
+    }
+
+    /// Reads the file at `path` and expands its directives.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file can't be read or lexed, or a directive is
+    /// malformed. The error's location is in the file containing the problem.
+    pub fn preprocess_file(&mut self, path: &Path) -> Result<Preprocessed, Error> {
+        let contents = (self.loader)(path).map_err(|e| {
+            let mut err = Error::new(
+                Span { offset: 0 },
+                format!("failed to read `{}`: {}", path.display(), e),
+            );
+            err.set_path(path);
+            err
+        })?;
+        self.preprocess_str(path, &contents)
+    }
+
+    /// Expands the directives of `contents`, the text of the file at `path`.
+    /// The `path` is used for errors and to find included files.
+    ///
+    /// Macros defined by previous calls on this preprocessor remain defined,
+    /// so common definitions can be preprocessed once up front.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file can't be read or lexed, or a directive is
+    /// malformed. The error's location is in the file containing the problem.
+    pub fn preprocess_str(&mut self, path: &Path, contents: &str) -> Result<Preprocessed, Error> {
+        let mut output = Output::default();
+        self.files = SourceMap::new();
+        let toks = self.add_file(path, contents.to_string())?;
+        self.including.push(path.to_path_buf());
+        let result = self.expand(&toks, &HashMap::new(), &mut output, 0);
+        self.including.clear();
+        result?;
+        Ok(Preprocessed {
+            output: output.text,
+            files: mem::take(&mut self.files),
+            segments: output.segments,
+        })
+    }
+
+    fn add_file(&mut self, path: &Path, contents: String) -> Result<Vec<Tok>, Error> {
+        let file = self.files.add(path, contents);
+        let mut toks = Vec::new();
+        let mut start = 0;
+        for token in Lexer::new(self.files.contents(file)) {
+            let token = token.map_err(|e| self.files.locate(file, e))?;
+            let end = start + token.src().len();
+            let kind = match &token {
+                Token::LParen(_) => Kind::LParen,
+                Token::RParen(_) => Kind::RParen,
+                t if t.is_trivia() => Kind::Trivia,
+                Token::Id(_) => Kind::Id,
+                Token::Reserved(s) if s.starts_with('!') && s.len() > 1 => Kind::Directive,
+                Token::String(s) => Kind::String(s.val().to_vec()),
+                _ => Kind::Other,
+            };
+            toks.push(Tok {
+                file,
+                start,
+                end,
+                kind,
+            });
+            start = end;
+        }
+        Ok(toks)
+    }
+
+    fn text(&self, tok: &Tok) -> &str {
+        &self.files.contents(tok.file)[tok.start..tok.end]
+    }
+
+    fn error(&self, tok: &Tok, message: String) -> Error {
+        let err = Error::new(Span { offset: tok.start }, message);
+        self.files.locate(tok.file, err)
+    }
+
+    /// Appends `toks` to `output`, expanding directives and replacing the
+    /// macro parameters in `env` with their arguments.
+    fn expand(
+        &mut self,
+        toks: &[Tok],
+        env: &HashMap<String, Vec<Tok>>,
+        output: &mut Output,
+        depth: usize,
+    ) -> Result<(), Error> {
+        let mut i = 0;
+        while i < toks.len() {
+            let tok = &toks[i];
+            if tok.kind == Kind::LParen
+                && toks.get(i + 1).map(|t| &t.kind) == Some(&Kind::Directive)
+            {
+                let end = match matching_paren(toks, i) {
+                    Some(end) => end,
+                    None => return Err(self.error(tok, "unterminated directive".to_string())),
+                };
+                self.directive(&toks[i + 1], &toks[i + 2..end], env, output, depth)?;
+                i = end + 1;
+                continue;
+            }
+            match env.get(self.text(tok)) {
+                Some(arg) if tok.kind == Kind::Id => {
+                    self.expand(arg, &HashMap::new(), output, depth)?
+                }
+                _ => {
+                    output.push(tok.file, tok.start, self.text(tok));
+                }
+            }
+            i += 1;
+        }
+        Ok(())
+    }
+
+    fn directive(
+        &mut self,
+        directive: &Tok,
+        args: &[Tok],
+        env: &HashMap<String, Vec<Tok>>,
+        output: &mut Output,
+        depth: usize,
+    ) -> Result<(), Error> {
+        let ranges = items(args);
+        let items = ranges
+            .iter()
+            .map(|range| &args[range.clone()])
+            .collect::<Vec<_>>();
+        match &self.text(directive)[1..] {
+            "include" => {
+                let path = match &items[..] {
+                    [[Tok {
+                        kind: Kind::String(path),
+                        ..
+                    }]] => String::from_utf8_lossy(path).into_owned(),
+                    _ => {
+                        let msg = "expected the path of the file to include".to_string();
+                        return Err(self.error(directive, msg));
+                    }
+                };
+                let includer = self.files.path(directive.file);
+                let path = includer.parent().unwrap_or(Path::new("")).join(path);
+                if self.including.contains(&path) {
+                    let msg = format!("`{}` includes itself", path.display());
+                    return Err(self.error(directive, msg));
+                }
+                let contents = match (self.loader)(&path) {
+                    Ok(contents) => contents,
+                    Err(e) => {
+                        let msg = format!("failed to read `{}`: {}", path.display(), e);
+                        return Err(self.error(&items[0][0], msg));
+                    }
+                };
+                let toks = self.add_file(&path, contents)?;
+                self.including.push(path);
+                self.expand(&toks, &HashMap::new(), output, depth)?;
+                self.including.pop();
+                Ok(())
+            }
+            "define" => {
+                let name = match items.first() {
+                    Some([name]) if name.kind == Kind::Other => self.text(name).to_string(),
+                    _ => return Err(self.error(directive, "expected a macro name".to_string())),
+                };
+                let params = match items.get(1) {
+                    Some([open, params @ .., _]) if open.kind == Kind::LParen => params
+                        .iter()
+                        .filter(|t| t.kind != Kind::Trivia)
+                        .map(|t| match t.kind {
+                            Kind::Id => Some(self.text(t).to_string()),
+                            _ => None,
+                        })
+                        .collect::<Option<Vec<_>>>(),
+                    _ => None,
+                };
+                let params = match params {
+                    Some(params) => params,
+                    None => {
+                        let msg = "expected a list of macro parameters".to_string();
+                        return Err(self.error(directive, msg));
+                    }
+                };
+                let body = match ranges.get(2) {
+                    Some(first) => trim(&args[first.start..]),
+                    None => &[],
+                };
+                let body = self.substitute(body, env);
+                self.macros.insert(name, Macro { params, body });
+                Ok(())
+            }
+            name => {
+                let (params, body) = match self.macros.get(name) {
+                    Some(m) => (m.params.clone(), m.body.clone()),
+                    None => {
+                        let msg = format!("unknown macro `!{}`", name);
+                        return Err(self.error(directive, msg));
+                    }
+                };
+                if items.len() != params.len() {
+                    let msg = format!(
+                        "macro `!{}` expects {} arguments but was given {}",
+                        name,
+                        params.len(),
+                        items.len()
+                    );
+                    return Err(self.error(directive, msg));
+                }
+                if depth == MAX_EXPANSION_DEPTH {
+                    let msg = format!("macro expansions are nested more than {} deep", depth);
+                    return Err(self.error(directive, msg));
+                }
+                let env = params
+                    .into_iter()
+                    .zip(items.iter().map(|arg| self.substitute(arg, env)))
+                    .collect();
+                self.expand(&body, &env, output, depth + 1)
+            }
+        }
+    }
+
+    /// Replaces the parameters of `env` in `toks` with their arguments, for
+    /// macros which are defined or invoked within the body of another macro.
+    fn substitute(&self, toks: &[Tok], env: &HashMap<String, Vec<Tok>>) -> Vec<Tok> {
+        let mut ret = Vec::new();
+        for tok in toks {
+            match env.get(self.text(tok)) {
+                Some(arg) if tok.kind == Kind::Id => ret.extend(arg.iter().cloned()),
+                _ => ret.push(tok.clone()),
+            }
+        }
+        ret
+    }
+}
+
+impl Default for Preprocessor<'_> {
+    fn default() -> Self {
+        Preprocessor::new()
+    }
+}
+
+/// Returns the index of the `)` matching the `(` at `start`.
+fn matching_paren(toks: &[Tok], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, tok) in toks.iter().enumerate().skip(start) {
+        match tok.kind {
+            Kind::LParen => depth += 1,
+            Kind::RParen => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Splits the arguments of a directive into single tokens and parenthesized
+/// lists, skipping trivia between them.
+fn items(toks: &[Tok]) -> Vec<Range<usize>> {
+    let mut items = Vec::new();
+    let mut i = 0;
+    while i < toks.len() {
+        let end = match toks[i].kind {
+            Kind::Trivia => {
+                i += 1;
+                continue;
+            }
+            // Directives are only parsed once their parens are known to
+            // balance.
+            Kind::LParen => matching_paren(toks, i).unwrap(),
+            _ => i,
+        };
+        items.push(i..end + 1);
+        i = end + 1;
+    }
+    items
+}
+
+fn trim(toks: &[Tok]) -> &[Tok] {
+    let start = toks.iter().position(|t| t.kind != Kind::Trivia);
+    let end = toks.iter().rposition(|t| t.kind != Kind::Trivia);
+    match (start, end) {
+        (Some(start), Some(end)) => &toks[start..end + 1],
+        _ => &[],
+    }
+}
+
+#[derive(Default)]
+struct Output {
+    text: String,
+    segments: Vec<Segment>,
+}
+
+impl Output {
+    fn push(&mut self, file: FileId, offset: usize, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        if let Some(last) = self.segments.last_mut() {
+            if last.file == file && last.offset + last.len == offset {
+                last.len += text.len();
+                self.text.push_str(text);
+                return;
+            }
+        }
+        self.segments.push(Segment {
+            output: self.text.len(),
+            file,
+            offset,
+            len: text.len(),
+        });
+        self.text.push_str(text);
+    }
+}
+
+impl Preprocessed {
+    /// Returns the text with all directives expanded.
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    /// Returns the file and the position in it which the text at `span` of
+    /// the output was copied from.
+    ///
+    /// Text substituted for a macro parameter comes from the argument of the
+    /// macro invocation, and the rest of a macro's expansion from its
+    /// definition.
+    pub fn locate(&self, span: Span) -> Option<(&Path, Span)> {
+        let (file, span) = self.find(span)?;
+        Some((self.files.path(file), span))
+    }
+
+    /// Returns the files which the output was copied from, that is the file
+    /// which was preprocessed and the files it included.
+    pub fn source_map(&self) -> &SourceMap {
+        &self.files
+    }
+
+    /// Moves an error found in the [`Preprocessed::output`] to where its text
+    /// came from, see [`Preprocessed::locate`].
+    ///
+    /// Errors outside of the output are returned unchanged.
+    pub fn map_error(&self, err: Error) -> Error {
+        err.map_all(&mut |mut err| {
+            if let Some((file, span)) = self.find(err.span()) {
+                err.relocate(span, self.files.path(file), self.files.contents(file));
+            }
+            err
+        })
+    }
+
+    fn find(&self, span: Span) -> Option<(FileId, Span)> {
+        let i = match self
+            .segments
+            .binary_search_by_key(&span.offset, |s| s.output)
+        {
+            Ok(i) => i,
+            Err(0) => return None,
+            Err(i) => i - 1,
+        };
+        // The end of a segment is only part of it at the end of the output,
+        // which is where errors about an unexpected end-of-file point.
+        let segment = &self.segments[i];
+        let offset = span.offset - segment.output;
+        if offset > segment.len {
+            return None;
+        }
+        let span = Span {
+            offset: segment.offset + offset,
+        };
+        Some((segment.file, span))
+    }
+}
@@ -151,6 +151,7 @@ impl<'a> Expander<'a> {
             },
 
             ModuleField::Custom(_)
+            | ModuleField::Error(_)
             | ModuleField::Memory(_)
             | ModuleField::Table(_)
             | ModuleField::Type(_) => {}
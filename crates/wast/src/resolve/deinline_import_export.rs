@@ -9,13 +9,13 @@ pub fn run(fields: &mut Vec<ModuleField>) {
         let item = &mut fields[cur];
         match item {
             ModuleField::Func(f) => {
-                for name in f.exports.names.drain(..) {
-                    to_append.push(export(f.span, name, ExportKind::Func, &mut f.id));
+                for (span, name) in f.exports.names.drain(..) {
+                    to_append.push(export(span, f.span, name, ExportKind::Func, &mut f.id));
                 }
                 match f.kind {
                     FuncKind::Import(import) => {
                         *item = ModuleField::Import(Import {
-                            span: f.span,
+                            span: import.span,
                             module: import.module,
                             field: import.field,
                             item: ItemSig {
@@ -31,13 +31,13 @@ pub fn run(fields: &mut Vec<ModuleField>) {
             }
 
             ModuleField::Memory(m) => {
-                for name in m.exports.names.drain(..) {
-                    to_append.push(export(m.span, name, ExportKind::Memory, &mut m.id));
+                for (span, name) in m.exports.names.drain(..) {
+                    to_append.push(export(span, m.span, name, ExportKind::Memory, &mut m.id));
                 }
                 match m.kind {
                     MemoryKind::Import { import, ty } => {
                         *item = ModuleField::Import(Import {
-                            span: m.span,
+                            span: import.span,
                             module: import.module,
                             field: import.field,
                             item: ItemSig {
@@ -82,6 +82,8 @@ pub fn run(fields: &mut Vec<ModuleField>) {
                                 memory: item_ref(kw::memory(m.span), id),
                                 offset: Expression {
                                     instrs: Box::new([Instruction::I32Const(0)]),
+                                    instr_spans: Box::new([m.span]),
+                                    branch_hints: Vec::new(),
                                 },
                             },
                             data,
@@ -93,13 +95,13 @@ pub fn run(fields: &mut Vec<ModuleField>) {
             }
 
             ModuleField::Table(t) => {
-                for name in t.exports.names.drain(..) {
-                    to_append.push(export(t.span, name, ExportKind::Table, &mut t.id));
+                for (span, name) in t.exports.names.drain(..) {
+                    to_append.push(export(span, t.span, name, ExportKind::Table, &mut t.id));
                 }
                 match &mut t.kind {
                     TableKind::Import { import, ty } => {
                         *item = ModuleField::Import(Import {
-                            span: t.span,
+                            span: import.span,
                             module: import.module,
                             field: import.field,
                             item: ItemSig {
@@ -123,6 +125,7 @@ pub fn run(fields: &mut Vec<ModuleField>) {
                                 max: Some(len as u32),
                             },
                             elem: *elem,
+                            shared: false,
                         });
                         let payload = match mem::replace(&mut t.kind, kind) {
                             TableKind::Inline { payload, .. } => payload,
@@ -136,6 +139,8 @@ pub fn run(fields: &mut Vec<ModuleField>) {
                                 table: item_ref(kw::table(t.span), id),
                                 offset: Expression {
                                     instrs: Box::new([Instruction::I32Const(0)]),
+                                    instr_spans: Box::new([t.span]),
+                                    branch_hints: Vec::new(),
                                 },
                             },
                             payload,
@@ -147,13 +152,13 @@ pub fn run(fields: &mut Vec<ModuleField>) {
             }
 
             ModuleField::Global(g) => {
-                for name in g.exports.names.drain(..) {
-                    to_append.push(export(g.span, name, ExportKind::Global, &mut g.id));
+                for (span, name) in g.exports.names.drain(..) {
+                    to_append.push(export(span, g.span, name, ExportKind::Global, &mut g.id));
                 }
                 match g.kind {
                     GlobalKind::Import(import) => {
                         *item = ModuleField::Import(Import {
-                            span: g.span,
+                            span: import.span,
                             module: import.module,
                             field: import.field,
                             item: ItemSig {
@@ -169,19 +174,19 @@ pub fn run(fields: &mut Vec<ModuleField>) {
             }
 
             ModuleField::Event(e) => {
-                for name in e.exports.names.drain(..) {
-                    to_append.push(export(e.span, name, ExportKind::Event, &mut e.id));
+                for (span, name) in e.exports.names.drain(..) {
+                    to_append.push(export(span, e.span, name, ExportKind::Event, &mut e.id));
                 }
             }
 
             ModuleField::Instance(i) => {
-                for name in i.exports.names.drain(..) {
-                    to_append.push(export(i.span, name, ExportKind::Instance, &mut i.id));
+                for (span, name) in i.exports.names.drain(..) {
+                    to_append.push(export(span, i.span, name, ExportKind::Instance, &mut i.id));
                 }
                 match &mut i.kind {
                     InstanceKind::Import { import, ty } => {
                         *item = ModuleField::Import(Import {
-                            span: i.span,
+                            span: import.span,
                             module: import.module,
                             field: import.field,
                             item: ItemSig {
@@ -200,13 +205,13 @@ pub fn run(fields: &mut Vec<ModuleField>) {
             }
 
             ModuleField::NestedModule(m) => {
-                for name in m.exports.names.drain(..) {
-                    to_append.push(export(m.span, name, ExportKind::Module, &mut m.id));
+                for (span, name) in m.exports.names.drain(..) {
+                    to_append.push(export(span, m.span, name, ExportKind::Module, &mut m.id));
                 }
                 match &mut m.kind {
                     NestedModuleKind::Import { import, ty } => {
                         *item = ModuleField::Import(Import {
-                            span: m.span,
+                            span: import.span,
                             module: import.module,
                             field: import.field,
                             item: ItemSig {
@@ -233,7 +238,8 @@ pub fn run(fields: &mut Vec<ModuleField>) {
             | ModuleField::Start(_)
             | ModuleField::Elem(_)
             | ModuleField::Data(_)
-            | ModuleField::Custom(_) => {}
+            | ModuleField::Custom(_)
+            | ModuleField::Error(_) => {}
         }
 
         fields.splice(cur..cur, to_append.drain(..));
@@ -249,11 +255,12 @@ pub fn run(fields: &mut Vec<ModuleField>) {
 
 fn export<'a>(
     span: Span,
+    item_span: Span,
     name: &'a str,
     kind: ExportKind,
     id: &mut Option<Id<'a>>,
 ) -> ModuleField<'a> {
-    let id = gensym::fill(span, id);
+    let id = gensym::fill(item_span, id);
     ModuleField::Export(Export {
         span,
         name,
@@ -34,10 +34,40 @@ impl Ns {
     }
 }
 
+fn field_errors(fields: &[ModuleField<'_>], errors: &mut Vec<Error>) {
+    for field in fields {
+        match field {
+            ModuleField::Error(e) => errors.push(e.error()),
+            ModuleField::NestedModule(NestedModule {
+                kind: NestedModuleKind::Inline { fields },
+                ..
+            }) => field_errors(fields, errors),
+            _ => {}
+        }
+    }
+}
+
 pub fn resolve<'a>(module: &mut Module<'a>) -> Result<Names<'a>, Error> {
+    // Fields which failed to parse, when the parser recovered from errors,
+    // are skipped by resolution, and their errors are reported along with any
+    // error resolving the rest of the module.
+    let mut errors = Vec::new();
+    match &module.kind {
+        ModuleKind::Text(fields) => field_errors(fields, &mut errors),
+        _ => return Ok(Default::default()),
+    }
+    match resolve_fields(module) {
+        Ok(names) if errors.is_empty() => return Ok(names),
+        Ok(_) => {}
+        Err(e) => errors.push(e),
+    }
+    Err(Error::combine(errors).unwrap())
+}
+
+fn resolve_fields<'a>(module: &mut Module<'a>) -> Result<Names<'a>, Error> {
     let fields = match &mut module.kind {
         ModuleKind::Text(fields) => fields,
-        _ => return Ok(Default::default()),
+        _ => unreachable!(),
     };
 
     // Ensure that each resolution of a module is deterministic in the names
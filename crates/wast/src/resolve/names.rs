@@ -107,6 +107,8 @@ impl<'a> Resolver<'a> {
                     | TypeDef::Array(_)
                     | TypeDef::Func(_)
                     | TypeDef::Module(_) => {}
+                    #[cfg(feature = "stack-switching")]
+                    TypeDef::Cont(_) => {}
                 }
 
                 // Record function signatures as we see them to so we can
@@ -141,9 +143,10 @@ impl<'a> Resolver<'a> {
             },
 
             // These fields don't define any items in any index space.
-            ModuleField::Export(_) | ModuleField::Start(_) | ModuleField::Custom(_) => {
-                return Ok(())
-            }
+            ModuleField::Export(_)
+            | ModuleField::Start(_)
+            | ModuleField::Custom(_)
+            | ModuleField::Error(_) => return Ok(()),
         };
 
         Ok(())
@@ -171,6 +174,10 @@ impl<'a> Resolver<'a> {
                     TypeDef::Array(array) => self.resolve_storagetype(&mut array.ty)?,
                     TypeDef::Module(m) => m.resolve(self)?,
                     TypeDef::Instance(i) => i.resolve(self)?,
+                    #[cfg(feature = "stack-switching")]
+                    TypeDef::Cont(c) => {
+                        self.resolve(&mut c.idx, Ns::Type)?;
+                    }
                 }
                 Ok(())
             }
@@ -336,7 +343,7 @@ impl<'a> Resolver<'a> {
                 Ok(())
             }
 
-            ModuleField::Memory(_) | ModuleField::Custom(_) => Ok(()),
+            ModuleField::Memory(_) | ModuleField::Custom(_) | ModuleField::Error(_) => Ok(()),
         }
     }
 
@@ -673,6 +680,11 @@ impl<'a, 'b> ExprResolver<'a, 'b> {
                 // Resolve a local by iterating over scopes from most recent
                 // to less recent. This allows locals added by `let` blocks to
                 // shadow less recent locals.
+                //
+                // The locals of a `let` block are prepended to the locals of
+                // the enclosing code, so a local found in a less recent scope
+                // is shifted by the number of locals in the more recent ones.
+                let mut shift = 0;
                 for (depth, scope) in self.scopes.iter().enumerate().rev() {
                     if let Err(e) = scope.resolve(i, "local") {
                         if depth == 0 {
@@ -680,7 +692,11 @@ impl<'a, 'b> ExprResolver<'a, 'b> {
                             // the result
                             return Err(e);
                         }
+                        shift += scope.count;
                     } else {
+                        if let Index::Num(n, _) = i {
+                            *n += shift;
+                        }
                         break;
                     }
                 }
@@ -833,11 +849,62 @@ impl<'a, 'b> ExprResolver<'a, 'b> {
 
             RefNull(ty) => self.resolver.resolve_heaptype(ty)?,
 
+            #[cfg(feature = "shared-everything-threads")]
+            ThreadSpawn(i) => {
+                self.resolver.resolve(i, Ns::Type)?;
+            }
+
+            #[cfg(feature = "stack-switching")]
+            ContNew(i) => {
+                self.resolver.resolve(i, Ns::Type)?;
+            }
+            #[cfg(feature = "stack-switching")]
+            ContBind(b) => {
+                self.resolver.resolve(&mut b.src, Ns::Type)?;
+                self.resolver.resolve(&mut b.dst, Ns::Type)?;
+            }
+            #[cfg(feature = "stack-switching")]
+            Suspend(i) => {
+                self.resolver.resolve(i, Ns::Event)?;
+            }
+            #[cfg(feature = "stack-switching")]
+            Resume(r) => {
+                self.resolver.resolve(&mut r.ty, Ns::Type)?;
+                self.resolve_handlers(&mut r.handlers)?;
+            }
+            #[cfg(feature = "stack-switching")]
+            ResumeThrow(r) => {
+                self.resolver.resolve(&mut r.ty, Ns::Type)?;
+                self.resolver.resolve(&mut r.tag, Ns::Event)?;
+                self.resolve_handlers(&mut r.handlers)?;
+            }
+            #[cfg(feature = "stack-switching")]
+            Switch(s) => {
+                self.resolver.resolve(&mut s.ty, Ns::Type)?;
+                self.resolver.resolve(&mut s.tag, Ns::Event)?;
+            }
+
             _ => {}
         }
         Ok(())
     }
 
+    #[cfg(feature = "stack-switching")]
+    fn resolve_handlers(&self, handlers: &mut [Handle<'a>]) -> Result<(), Error> {
+        for handler in handlers {
+            match handler {
+                Handle::OnLabel { tag, label } => {
+                    self.resolver.resolve(tag, Ns::Event)?;
+                    self.resolve_label(label)?;
+                }
+                Handle::OnSwitch { tag } => {
+                    self.resolver.resolve(tag, Ns::Event)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn resolve_label(&self, label: &mut Index<'a>) -> Result<(), Error> {
         let id = match label {
             Index::Num(..) => return Ok(()),
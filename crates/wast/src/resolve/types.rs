@@ -72,9 +72,12 @@ impl<'a> Expander<'a> {
             ModuleField::Type(ty) => {
                 let id = gensym::fill(ty.span, &mut ty.id);
                 match &mut ty.def {
-                    TypeDef::Func(f) => {
+                    // Inline type uses never refer to shared types, so those
+                    // aren't interned.
+                    TypeDef::Func(f) if !ty.shared => {
                         f.key().insert(self, Index::Id(id));
                     }
+                    TypeDef::Func(_) => {}
                     TypeDef::Instance(i) => {
                         i.expand(self);
                         i.key().insert(self, Index::Id(id));
@@ -84,6 +87,8 @@ impl<'a> Expander<'a> {
                         m.key().insert(self, Index::Id(id));
                     }
                     TypeDef::Array(_) | TypeDef::Struct(_) => {}
+                    #[cfg(feature = "stack-switching")]
+                    TypeDef::Cont(_) => {}
                 }
             }
             ModuleField::Import(i) if self.process_imports_early => {
@@ -105,29 +110,29 @@ impl<'a> Expander<'a> {
                 }
             }
             ModuleField::Func(f) => {
-                self.expand_type_use(&mut f.ty);
+                self.expand_type_use(f.span, &mut f.ty);
                 if let FuncKind::Inline { expression, .. } = &mut f.kind {
-                    self.expand_expression(expression);
+                    self.expand_expression(f.span, expression);
                 }
             }
             ModuleField::Global(g) => {
                 if let GlobalKind::Inline(expr) = &mut g.kind {
-                    self.expand_expression(expr);
+                    self.expand_expression(g.span, expr);
                 }
             }
             ModuleField::Data(d) => {
                 if let DataKind::Active { offset, .. } = &mut d.kind {
-                    self.expand_expression(offset);
+                    self.expand_expression(d.span, offset);
                 }
             }
             ModuleField::Elem(e) => {
                 if let ElemKind::Active { offset, .. } = &mut e.kind {
-                    self.expand_expression(offset);
+                    self.expand_expression(e.span, offset);
                 }
             }
             ModuleField::Event(e) => match &mut e.ty {
                 EventType::Exception(ty) => {
-                    self.expand_type_use(ty);
+                    self.expand_type_use(e.span, ty);
                 }
             },
             ModuleField::NestedModule(m) => {
@@ -142,34 +147,38 @@ impl<'a> Expander<'a> {
             | ModuleField::Memory(_)
             | ModuleField::Start(_)
             | ModuleField::Export(_)
-            | ModuleField::Custom(_) => {}
+            | ModuleField::Custom(_)
+            | ModuleField::Error(_) => {}
         }
     }
 
     fn expand_item_sig(&mut self, item: &mut ItemSig<'a>) {
         match &mut item.kind {
             ItemKind::Func(t) | ItemKind::Event(EventType::Exception(t)) => {
-                self.expand_type_use(t);
+                self.expand_type_use(item.span, t);
             }
             ItemKind::Instance(t) => {
-                self.expand_type_use(t);
+                self.expand_type_use(item.span, t);
                 t.inline.take();
             }
             ItemKind::Module(m) => {
-                self.expand_type_use(m);
+                self.expand_type_use(item.span, m);
                 m.inline.take();
             }
             ItemKind::Global(_) | ItemKind::Table(_) | ItemKind::Memory(_) => {}
         }
     }
 
-    fn expand_expression(&mut self, expr: &mut Expression<'a>) {
+    /// Expands the type uses of the instructions in `expr`, where `span` is
+    /// the span of the item the expression belongs to since instructions
+    /// don't have spans of their own.
+    fn expand_expression(&mut self, span: Span, expr: &mut Expression<'a>) {
         for instr in expr.instrs.iter_mut() {
-            self.expand_instr(instr);
+            self.expand_instr(span, instr);
         }
     }
 
-    fn expand_instr(&mut self, instr: &mut Instruction<'a>) {
+    fn expand_instr(&mut self, span: Span, instr: &mut Instruction<'a>) {
         match instr {
             Instruction::Block(bt)
             | Instruction::If(bt)
@@ -206,19 +215,22 @@ impl<'a> Expander<'a> {
                         return;
                     }
                 }
-                self.expand_type_use(&mut bt.ty);
+                self.expand_type_use(span, &mut bt.ty);
             }
             Instruction::FuncBind(b) => {
-                self.expand_type_use(&mut b.ty);
+                self.expand_type_use(span, &mut b.ty);
             }
             Instruction::CallIndirect(c) | Instruction::ReturnCallIndirect(c) => {
-                self.expand_type_use(&mut c.ty);
+                self.expand_type_use(span, &mut c.ty);
             }
             _ => {}
         }
     }
 
-    fn expand_type_use<T>(&mut self, item: &mut TypeUse<'a, T>) -> Index<'a>
+    /// Resolves `item` to a type index, inserting a new type for an inline
+    /// type if there isn't an identical one already. Inserted types use
+    /// `span`, the span of the item with the type use.
+    fn expand_type_use<T>(&mut self, span: Span, item: &mut TypeUse<'a, T>) -> Index<'a>
     where
         T: TypeReference<'a>,
     {
@@ -238,7 +250,6 @@ impl<'a> Expander<'a> {
             }
             None => T::default().key(),
         };
-        let span = Span::from_offset(0); // FIXME: don't manufacture
         let idx = self.key_to_idx(span, key);
         item.index = Some(ItemRef::Item {
             idx,
@@ -261,6 +272,7 @@ impl<'a> Expander<'a> {
             span,
             id: Some(id),
             def: key.to_def(span),
+            shared: false,
         }));
         let idx = Index::Id(id);
         key.insert(self, idx);
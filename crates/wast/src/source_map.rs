@@ -0,0 +1,163 @@
+//! Parsing sessions spanning several files.
+//!
+//! A [`Span`] is only a byte offset, so on its own it can't say which file it
+//! points into. A [`SourceMap`] owns the text of every file involved in an
+//! operation, such as files which include or are composed with each other,
+//! and gives each file its own range of offsets. Spans of a file can be
+//! converted to spans of the whole map with [`SourceMap::span`], which then
+//! identify the file as well, and errors for them render with the right file
+//! name and source line.
+//!
+//! ```
+//! # fn foo() -> Result<(), wast::Error> {
+//! use wast::parser;
+//! use wast::source_map::SourceMap;
+//! use wast::Wat;
+//!
+//! let mut sources = SourceMap::new();
+//! sources.add("a.wat", "(module (func $f))");
+//! sources.add("b.wat", "(module (func $f i32.bad))");
+//!
+//! let mut errors = Vec::new();
+//! for file in sources.files() {
+//!     let buf = sources.parse_buffer(file)?;
+//!     if let Err(e) = parser::parse::<Wat>(&buf) {
+//!         errors.push(sources.locate(file, e));
+//!     }
+//! }
+//! assert_eq!(errors.len(), 1);
+//! assert!(errors[0].to_string().contains("--> b.wat:1:18"));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::parser::ParseBuffer;
+use crate::{Error, Span};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A collection of source files, see the [module documentation](self).
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+#[derive(Debug)]
+struct SourceFile {
+    path: PathBuf,
+    contents: String,
+    // The offset of the start of this file in the spans of the whole map.
+    start: usize,
+}
+
+/// A file of a [`SourceMap`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FileId(pub(crate) usize);
+
+impl SourceMap {
+    /// Creates a new, empty, source map.
+    pub fn new() -> SourceMap {
+        SourceMap::default()
+    }
+
+    /// Adds a file with the given `path` and `contents` to this map.
+    ///
+    /// The `path` is only used to render errors, and doesn't need to exist.
+    pub fn add(&mut self, path: impl Into<PathBuf>, contents: impl Into<String>) -> FileId {
+        // Files are separated by one offset so that the end of a file, where
+        // errors about an unexpected end-of-file point, is part of it.
+        let start = match self.files.last() {
+            Some(last) => last.start + last.contents.len() + 1,
+            None => 0,
+        };
+        self.files.push(SourceFile {
+            path: path.into(),
+            contents: contents.into(),
+            start,
+        });
+        FileId(self.files.len() - 1)
+    }
+
+    /// Reads the file at `path` and adds it to this map.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read or isn't valid utf-8.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> io::Result<FileId> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+        Ok(self.add(path, contents))
+    }
+
+    /// Returns the ids of all the files of this map, in the order they were
+    /// added.
+    pub fn files(&self) -> impl ExactSizeIterator<Item = FileId> {
+        (0..self.files.len()).map(FileId)
+    }
+
+    /// Returns the path of `file`.
+    pub fn path(&self, file: FileId) -> &Path {
+        &self.files[file.0].path
+    }
+
+    /// Returns the contents of `file`.
+    pub fn contents(&self, file: FileId) -> &str {
+        &self.files[file.0].contents
+    }
+
+    /// Creates a new [`ParseBuffer`] of the contents of `file`.
+    ///
+    /// The spans of the buffer are relative to the start of the file, and
+    /// [`SourceMap::locate`] attributes errors from parsing it to the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, already located in `file`, if its contents fail to
+    /// lex.
+    pub fn parse_buffer(&self, file: FileId) -> Result<ParseBuffer<'_>, Error> {
+        ParseBuffer::new(self.contents(file)).map_err(|e| self.locate(file, e))
+    }
+
+    /// Attaches the path and source text of `file` to `err`, whose span is
+    /// relative to the start of the file.
+    pub fn locate(&self, file: FileId, err: Error) -> Error {
+        let file = &self.files[file.0];
+        err.map_all(&mut |mut err| {
+            err.relocate(err.span(), &file.path, &file.contents);
+            err
+        })
+    }
+
+    /// Converts `span`, relative to the start of `file`, into a span of the
+    /// whole map, which also identifies the file.
+    pub fn span(&self, file: FileId, span: Span) -> Span {
+        Span {
+            offset: self.files[file.0].start + span.offset,
+        }
+    }
+
+    /// Returns the file which `span`, a span of the whole map, points into
+    /// along with the span relative to the start of that file.
+    pub fn lookup(&self, span: Span) -> Option<(FileId, Span)> {
+        let i = match self.files.binary_search_by_key(&span.offset, |f| f.start) {
+            Ok(i) => i,
+            Err(0) => return None,
+            Err(i) => i - 1,
+        };
+        let offset = span.offset - self.files[i].start;
+        if offset > self.files[i].contents.len() {
+            return None;
+        }
+        Some((FileId(i), Span { offset }))
+    }
+
+    /// Creates an error with the given `message` at `span`, a span of the
+    /// whole map, which renders with the file and source line it points at.
+    pub fn error(&self, span: Span, message: String) -> Error {
+        match self.lookup(span) {
+            Some((file, span)) => self.locate(file, Error::new(span, message)),
+            None => Error::new(span, message),
+        }
+    }
+}
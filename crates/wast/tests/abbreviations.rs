@@ -0,0 +1,172 @@
+use wast::parser::{self, ParseBuffer};
+use wast::{ElemKind, ElemPayload, ModuleField, ModuleKind, Span, TableKind, Wat};
+
+const WAT: &str = r#"(module
+  (memory (import "a" "b") 1)
+  (func $f (export "f") (export "g") (param i32))
+  (global (export "h") i32 (i32.const 0))
+  (func (param i64) (result i64) local.get 0))
+"#;
+
+/// Returns the line and column of `span` in `WAT`, both starting at 1.
+fn linecol(span: Span) -> (usize, usize) {
+    let (line, col) = span.linecol_in(WAT);
+    (line + 1, col + 1)
+}
+
+#[test]
+fn expanded_items_point_at_sugar() {
+    let buf = ParseBuffer::new(WAT).unwrap();
+    let mut wat = parser::parse::<Wat>(&buf).unwrap();
+    wat.module.resolve().unwrap();
+    let fields = match &wat.module.kind {
+        ModuleKind::Text(fields) => fields,
+        ModuleKind::Binary(_) => panic!("not a text module"),
+    };
+
+    let mut exports = Vec::new();
+    let mut imports = Vec::new();
+    let mut types = Vec::new();
+    for field in fields {
+        match field {
+            ModuleField::Export(e) => exports.push((e.name, linecol(e.span))),
+            ModuleField::Import(i) => imports.push((i.module, linecol(i.span))),
+            ModuleField::Type(t) => types.push(linecol(t.span)),
+            _ => {}
+        }
+    }
+    assert_eq!(exports, [("f", (3, 13)), ("g", (3, 26)), ("h", (4, 12))]);
+    assert_eq!(imports, [("a", (2, 12))]);
+    // Types inserted for inline type uses point at the item using them.
+    assert_eq!(types, [(3, 4), (5, 4)]);
+}
+
+#[test]
+fn expanded_module_encodes() {
+    let buf = ParseBuffer::new(WAT).unwrap();
+    let mut wat = parser::parse::<Wat>(&buf).unwrap();
+    let wasm = wat.module.encode().unwrap();
+    let mut validator = wasmparser::Validator::new();
+    validator.validate_all(&wasm).unwrap();
+}
+
+#[test]
+fn table_elem_expands_to_canonical_form() {
+    let wat = r#"(module
+      (func $f)
+      (table $t funcref (elem $f $f))
+      (table externref (elem))
+      (elem declare func $f))
+    "#;
+    let buf = ParseBuffer::new(wat).unwrap();
+    let mut module = parser::parse::<Wat>(&buf).unwrap().module;
+    module.resolve().unwrap();
+    let fields = match &module.kind {
+        ModuleKind::Text(fields) => fields,
+        ModuleKind::Binary(_) => panic!("not a text module"),
+    };
+
+    let mut tables = Vec::new();
+    let mut elems = Vec::new();
+    for field in fields {
+        match field {
+            ModuleField::Table(t) => match &t.kind {
+                TableKind::Normal(ty) => tables.push((ty.limits.min, ty.limits.max)),
+                _ => panic!("table wasn't expanded"),
+            },
+            ModuleField::Elem(e) => elems.push(match (&e.kind, &e.payload) {
+                (ElemKind::Active { .. }, ElemPayload::Indices(funcs)) => {
+                    format!("active funcs {}", funcs.len())
+                }
+                (ElemKind::Active { .. }, ElemPayload::Exprs { ty, exprs }) => {
+                    format!("active {:?} {}", ty.heap, exprs.len())
+                }
+                (ElemKind::Declared, _) => "declared".to_string(),
+                _ => panic!("unexpected elem segment"),
+            }),
+            _ => {}
+        }
+    }
+    assert_eq!(tables, [(2, Some(2)), (0, Some(0))]);
+    assert_eq!(elems, ["active funcs 2", "active Extern 0", "declared"]);
+
+    let wasm = module.encode().unwrap();
+    let mut validator = wasmparser::Validator::new();
+    validator.wasm_features(wasmparser::WasmFeatures {
+        reference_types: true,
+        ..wasmparser::WasmFeatures::default()
+    });
+    validator.validate_all(&wasm).unwrap();
+}
+
+#[test]
+fn block_types_reuse_type_entries() {
+    let wat = r#"(module
+      (type $pair (func (param i32) (result i32 i32)))
+      (func (param i32) (result i64)
+        local.get 0
+        block (param i32) (result i32 i32)
+          local.get 0
+        end
+        i32.add
+        loop (type 1) (param i32) (result i64)
+          i64.extend_i32_u
+        end
+        i64.const 1
+        i32.const 1
+        if (param i64 i64) (result i64)
+          i64.add
+        else
+          i64.sub
+        end
+        block (result i64)
+          i64.const 2
+        end
+        drop))
+    "#;
+    let wasm = wat::parse_str(wat).unwrap();
+    wasmparser::Validator::new().validate_all(&wasm).unwrap();
+
+    let mut types = 0;
+    let mut block_types = Vec::new();
+    for payload in wasmparser::Parser::new(0).parse_all(&wasm) {
+        match payload.unwrap() {
+            wasmparser::Payload::TypeSection(s) => types = s.get_count(),
+            wasmparser::Payload::CodeSectionEntry(body) => {
+                let mut reader = body.get_operators_reader().unwrap();
+                while !reader.eof() {
+                    match reader.read().unwrap() {
+                        wasmparser::Operator::Block { ty }
+                        | wasmparser::Operator::Loop { ty }
+                        | wasmparser::Operator::If { ty } => block_types.push(ty),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    // Block types with parameters or several results share entries with
+    // identical function types, and new ones are appended after the
+    // function's own type.
+    assert_eq!(types, 3);
+    assert_eq!(
+        block_types,
+        [
+            wasmparser::TypeOrFuncType::FuncType(0),
+            wasmparser::TypeOrFuncType::FuncType(1),
+            wasmparser::TypeOrFuncType::FuncType(2),
+            wasmparser::TypeOrFuncType::Type(wasmparser::Type::I64),
+        ]
+    );
+
+    let err = wat::parse_str(
+        r#"(module
+          (type (func (param i32) (result i32)))
+          (func block (type 0) (param i64) (result i32) end))"#,
+    )
+    .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("inline function type doesn't match type reference"));
+}
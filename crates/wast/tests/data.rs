@@ -0,0 +1,53 @@
+use wast::parser::{self, ParseBuffer};
+use wast::{Data, ModuleField, ModuleKind, Wat};
+
+fn data_segments<'a, 'b>(wat: &'a Wat<'b>) -> Vec<&'a Data<'b>> {
+    match &wat.module.kind {
+        ModuleKind::Text(fields) => fields
+            .iter()
+            .filter_map(|field| match field {
+                ModuleField::Data(data) => Some(data),
+                _ => None,
+            })
+            .collect(),
+        ModuleKind::Binary(_) => panic!("not a text module"),
+    }
+}
+
+#[test]
+fn chunks() {
+    let wat = r#"
+        (module
+          (memory 1)
+          (data (i32.const 0) "hello, " "world\0a" (i16 1 2) "")
+          (data ""))
+    "#;
+    let buf = ParseBuffer::new(wat).unwrap();
+    let module = parser::parse::<Wat>(&buf).unwrap();
+    let segments = data_segments(&module);
+
+    let chunks = segments[0].chunks().collect::<Vec<_>>();
+    assert_eq!(
+        chunks,
+        [
+            &b"hello, "[..],
+            &b"world\n"[..],
+            &[1, 0, 2, 0][..],
+            &b""[..]
+        ]
+    );
+    assert_eq!(segments[0].len(), 17);
+    assert!(segments[1].is_empty());
+
+    // Strings without escapes are borrowed from the source text.
+    let source = wat.as_bytes().as_ptr_range();
+    assert!(source.contains(&chunks[0].as_ptr()));
+}
+
+#[test]
+fn encodes_chunks() {
+    let wat = r#"(module (memory 1) (data (i32.const 0) "ab" (i8 99) "d"))"#;
+    let buf = ParseBuffer::new(wat).unwrap();
+    let wasm = parser::parse::<Wat>(&buf).unwrap().module.encode().unwrap();
+    assert!(wasm.ends_with(&[4, b'a', b'b', 99, b'd']));
+}
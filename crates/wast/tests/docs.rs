@@ -0,0 +1,58 @@
+use wast::docs::{doc_comments, DEFAULT_PREFIX};
+use wast::parser::{self, ParseBuffer};
+use wast::Wat;
+
+fn docs(wat: &str, prefix: &str) -> Vec<(Option<usize>, String)> {
+    let buf = ParseBuffer::new(wat).unwrap();
+    let module = parser::parse::<Wat>(&buf).unwrap().module;
+    doc_comments(&module, wat, prefix)
+        .into_iter()
+        .map(|doc| (doc.field, doc.text()))
+        .collect()
+}
+
+#[test]
+fn fields() {
+    let wat = r#"
+;;; The module.
+(module ;;; Not documentation, it follows `module`.
+  ;;; The type.
+  (type (func))
+
+  ;;; Separated by a blank line, so not documentation.
+
+  (import "" "f" (func))
+  ;;;Not documentation either,
+  ;; since a plain comment ends it.
+  (memory 1)
+  ;;; The first line.
+    ;;;    Indented.
+  ;;;
+  ;;; The last line.
+  (func $f (export "f") (param i32)
+    ;;; Not a field.
+    local.get 0
+    drop)
+  (start $f) ;;; Not documentation for the table.
+  (table 1 funcref))
+;;; Nothing follows.
+"#;
+    assert_eq!(
+        docs(wat, DEFAULT_PREFIX),
+        [
+            (None, "The module.".to_string()),
+            (Some(0), "The type.".to_string()),
+            (
+                Some(3),
+                "The first line.\n   Indented.\n\nThe last line.".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn custom_prefix() {
+    let wat =
+        "(module\n  ;; @doc first\n  ;;; not\n  ;; @doc second\n  (global i32 (i32.const 0)))";
+    assert_eq!(docs(wat, ";; @doc"), [(Some(0), "second".to_string())]);
+}
@@ -0,0 +1,67 @@
+use wast::parser::{self, ParseBuffer, TokenKind, TokenKindDescription};
+use wast::{Error, Span, Wat};
+
+fn parse_error(wat: &str) -> Error {
+    let buf = match ParseBuffer::new(wat) {
+        Ok(buf) => buf,
+        Err(e) => return e,
+    };
+    match parser::parse::<Wat>(&buf) {
+        Ok(_) => panic!("expected an error"),
+        Err(e) => e,
+    }
+}
+
+#[test]
+fn indices() {
+    let wat = "(module (func local.get 1.5))";
+    let err = parse_error(wat);
+    assert_eq!(err.span(), Span::from_offset(wat.find("1.5").unwrap()));
+    assert_eq!(
+        err.expected(),
+        [
+            TokenKindDescription {
+                kind: TokenKind::Id,
+                display: "an identifier",
+            },
+            TokenKindDescription {
+                kind: TokenKind::Integer,
+                display: "u32",
+            },
+        ]
+    );
+    assert_eq!(
+        err.message(),
+        "unexpected token, expected an identifier or u32"
+    );
+}
+
+#[test]
+fn keywords() {
+    let err = parse_error("(module (global (mut i32) (i32.const 0)) (export \"g\" (glob 0)))");
+    let keywords = err
+        .expected()
+        .iter()
+        .filter_map(|e| match e.kind {
+            TokenKind::Keyword(kw) => {
+                assert_eq!(e.display, format!("`{}`", kw));
+                Some(kw)
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    for kw in ["func", "table", "memory", "global"].iter() {
+        assert!(keywords.contains(kw), "{:?}", keywords);
+    }
+}
+
+#[test]
+fn no_expected_tokens() {
+    // Errors which don't come from a lookahead, and lexer errors, don't list
+    // any tokens.
+    assert_eq!(
+        parse_error("(module (func (result i32) i32.const oops))").expected(),
+        []
+    );
+    assert_eq!(parse_error("(module \"oops)").expected(), []);
+}
@@ -0,0 +1,54 @@
+use wast::lexer::{Lexer, Token};
+use wast::parser::{self, ParseBuffer};
+use wast::{ModuleField, ModuleKind, Span, Wat};
+
+fn lex(wat: &str) -> Vec<Token<'_>> {
+    Lexer::new(wat).collect::<Result<Vec<_>, _>>().unwrap()
+}
+
+#[test]
+fn filtered_tokens() {
+    // Drop the `(func $skipped)` field, which is tokens 3 to 7.
+    let wat = "(module (func $skipped) (func $kept (result i32) i32.const 1))";
+    let mut tokens = lex(wat);
+    tokens.drain(3..8);
+    let buf = ParseBuffer::from_tokens(wat, tokens).unwrap();
+    let wat_ast = parser::parse::<Wat>(&buf).unwrap();
+    let fields = match &wat_ast.module.kind {
+        ModuleKind::Text(fields) => fields,
+        ModuleKind::Binary(_) => panic!("expected a text module"),
+    };
+    assert_eq!(fields.len(), 1);
+    match &fields[0] {
+        ModuleField::Func(f) => {
+            assert_eq!(f.id.unwrap().name(), "kept");
+            assert_eq!(f.span, Span::from_offset(wat.find("func $kept").unwrap()));
+        }
+        _ => panic!("expected a function"),
+    }
+}
+
+#[test]
+fn errors_point_into_the_input() {
+    let wat = "(module)\n(func (result i32) i32.const oops)";
+    let tokens = lex(wat);
+    let buf = ParseBuffer::from_tokens(wat, tokens[3..].to_vec()).unwrap();
+    let err = match parser::parse::<Wat>(&buf) {
+        Ok(_) => panic!("expected an error"),
+        Err(e) => e,
+    };
+    assert_eq!(err.span(), Span::from_offset(wat.find("oops").unwrap()));
+    assert!(err.to_string().contains(":2:30"), "{}", err);
+
+    // Annotations are still checked for being closed.
+    let wat = "(module (@name \"m\"))";
+    let tokens = lex(wat);
+    assert!(ParseBuffer::from_tokens(wat, tokens[..7].to_vec()).is_err());
+}
+
+#[test]
+#[should_panic(expected = "isn't part of the input")]
+fn tokens_from_elsewhere() {
+    let other = "(module)".to_string();
+    let _ = ParseBuffer::from_tokens("(module)", lex(&other));
+}
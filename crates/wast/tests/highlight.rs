@@ -0,0 +1,63 @@
+use wast::highlight::{highlight, Category};
+
+fn categories(wat: &str) -> Vec<(&str, Category)> {
+    highlight(wat)
+        .into_iter()
+        .map(|h| (&wat[h.start..h.end], h.category))
+        .collect()
+}
+
+#[test]
+fn tokens() {
+    assert_eq!(
+        categories("(i32.const 1) (; c ;) \"s\" 1.5 $x ="),
+        [
+            ("(", Category::Punctuation),
+            ("i32.const", Category::Keyword),
+            ("1", Category::Literal),
+            (")", Category::Punctuation),
+            ("(; c ;)", Category::Comment),
+            ("\"s\"", Category::Literal),
+            ("1.5", Category::Literal),
+            ("$x", Category::Id),
+            ("=", Category::Error),
+        ]
+    );
+}
+
+#[test]
+fn recovers_from_errors() {
+    assert_eq!(
+        categories("(func \"a\\q\" $f)"),
+        [
+            ("(", Category::Punctuation),
+            ("func", Category::Keyword),
+            ("\"a\\q\"", Category::Error),
+            ("$f", Category::Id),
+            (")", Category::Punctuation),
+        ]
+    );
+    assert_eq!(
+        categories("nop \"unterminated\nnop"),
+        [
+            ("nop", Category::Keyword),
+            ("\"unterminated", Category::Error),
+            ("nop", Category::Keyword),
+        ]
+    );
+    assert_eq!(
+        categories("nop (; never closed"),
+        [
+            ("nop", Category::Keyword),
+            ("(; never closed", Category::Error),
+        ]
+    );
+    assert_eq!(
+        categories("a\u{1f600}b c"),
+        [
+            ("a", Category::Keyword),
+            ("\u{1f600}b", Category::Error),
+            ("c", Category::Keyword),
+        ]
+    );
+}
@@ -0,0 +1,25 @@
+use wast::lexer::{LexError, Limit, Limits};
+use wast::parser::{self, ParseBuffer};
+use wast::Wat;
+
+#[test]
+fn parse_buffer_limits() {
+    let limits = Limits {
+        paren_depth: 3,
+        ..Limits::default()
+    };
+    let wat = "(module (func (nop)))";
+    let buf = ParseBuffer::new_with_limits(wat, limits).unwrap();
+    parser::parse::<Wat>(&buf).unwrap();
+
+    let wat = "(module (func (block (nop))))";
+    let err = match ParseBuffer::new_with_limits(wat, limits) {
+        Ok(_) => panic!("expected an error"),
+        Err(e) => e,
+    };
+    assert_eq!(
+        err.lex_error(),
+        Some(&LexError::LimitExceeded(Limit::ParenDepth))
+    );
+    assert_eq!(err.message(), "parentheses are nested too deeply");
+}
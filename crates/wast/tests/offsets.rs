@@ -0,0 +1,90 @@
+use wasmparser::{Operator, Parser, Payload};
+use wast::parser::{self, ParseBuffer};
+use wast::Wat;
+
+const WAT: &str = r#"(module
+  (import "m" "f" (func))
+  (func)
+  (func (param i32) (result i32)
+    (if (result i32) (local.get 0)
+      (then (i32.const 1))
+      (else
+        i32.const 2))))
+"#;
+
+#[test]
+fn instructions_map_to_source() {
+    let buf = ParseBuffer::new(WAT).unwrap();
+    let mut wat = parser::parse::<Wat>(&buf).unwrap();
+    let (wasm, offsets) = wat.module.encode_with_offsets().unwrap();
+    assert_eq!(wasm, wat.module.encode().unwrap());
+
+    // Decode the instruction at each offset to check it's where it's said to
+    // be.
+    let mut code = None;
+    for payload in Parser::new(0).parse_all(&wasm) {
+        if let Payload::CodeSectionStart { range, .. } = payload.unwrap() {
+            code = Some(&wasm[range.start..range.end]);
+        }
+    }
+    let code = code.unwrap();
+    let actual = offsets
+        .iter()
+        .map(|o| {
+            let mut reader = wasmparser::BinaryReader::new(&code[o.offset..]);
+            let op = match reader.read_operator().unwrap() {
+                Operator::If { .. } => "if".to_string(),
+                op => format!("{:?}", op),
+            };
+            let (line, col) = o.span.linecol_in(WAT);
+            (o.func, op, line + 1, col + 1)
+        })
+        .collect::<Vec<_>>();
+    let expected = [
+        (1, "End", 3, 4),
+        (2, "LocalGet { local_index: 0 }", 5, 23),
+        (2, "if", 5, 6),
+        (2, "I32Const { value: 1 }", 6, 14),
+        (2, "Else", 7, 8),
+        (2, "I32Const { value: 2 }", 8, 9),
+        (2, "End", 8, 21),
+        (2, "End", 4, 4),
+    ];
+    let expected = expected
+        .iter()
+        .map(|(f, op, l, c)| (*f, op.to_string(), *l, *c))
+        .collect::<Vec<_>>();
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn annotated_dump_covers_every_byte() {
+    let buf = ParseBuffer::new(WAT).unwrap();
+    let mut wat = parser::parse::<Wat>(&buf).unwrap();
+    let (wasm, dump) = wat.module.encode_annotated().unwrap();
+    assert_eq!(wasm, wat.module.encode().unwrap());
+
+    // Reassemble the binary from the dumped bytes.
+    let mut dumped = Vec::new();
+    for line in dump.lines().filter(|l| !l.starts_with(';')) {
+        let (offset, rest) = line.split_at(line.find(": ").unwrap());
+        assert_eq!(usize::from_str_radix(offset, 16).unwrap(), dumped.len());
+        let hex = rest[2..].split(" ; ").next().unwrap();
+        for byte in hex.split_whitespace() {
+            dumped.push(u8::from_str_radix(byte, 16).unwrap());
+        }
+    }
+    assert_eq!(dumped, wasm);
+
+    for expected in &[
+        "0000000: 00 61 73 6d",
+        "; section \"code\" (10)",
+        "; func 2 body size",
+        "20 00                                           ; local.get",
+        "04 7f                                           ; if",
+        "; else",
+        "0b                                              ; end",
+    ] {
+        assert!(dump.contains(expected), "{} not in {}", expected, dump);
+    }
+}
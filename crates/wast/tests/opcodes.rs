@@ -0,0 +1,103 @@
+use wast::parser::{self, ParseBuffer};
+use wast::{FuncKind, ModuleField, ModuleKind, Opcode, Wat};
+
+#[test]
+fn lookups_agree() {
+    for opcode in Opcode::all() {
+        assert_eq!(Opcode::from_mnemonic(opcode.mnemonic), Some(opcode));
+        if let Some(deprecated) = opcode.deprecated {
+            assert_eq!(Opcode::from_mnemonic(deprecated), Some(opcode));
+        }
+        match opcode.mnemonic {
+            "select" => assert!(opcode.binary.is_empty()),
+            "catch_all" => assert_eq!(
+                Opcode::from_binary(&opcode.encoded()).unwrap().mnemonic,
+                "else"
+            ),
+            _ => assert_eq!(Opcode::from_binary(&opcode.encoded()), Some(opcode)),
+        }
+    }
+    assert_eq!(Opcode::from_mnemonic("i32.bogus"), None);
+    assert_eq!(Opcode::from_binary(&[]), None);
+    assert_eq!(Opcode::from_binary(&[0xfd, 0x80]), None);
+}
+
+#[test]
+fn describes_instructions() {
+    let local_get = Opcode::from_mnemonic("get_local").unwrap();
+    assert_eq!(local_get.mnemonic, "local.get");
+    assert_eq!(local_get.deprecated, Some("get_local"));
+    assert_eq!(local_get.proposal, None);
+
+    let load = Opcode::from_binary(&[0x28, 0x02, 0x00]).unwrap();
+    assert_eq!(load.mnemonic, "i32.load");
+    assert_eq!(load.immediates, Some("MemArg<4>"));
+
+    let fence = Opcode::from_mnemonic("atomic.fence").unwrap();
+    assert_eq!(fence.encoded(), [0xfe, 0x03, 0x00]);
+    assert_eq!(fence.proposal, Some("threads"));
+
+    let proposals = [
+        ("catch_all", "exceptions"),
+        ("return_call", "tail-call"),
+        ("let", "function-references"),
+        ("table.grow", "reference-types"),
+        ("memory.copy", "bulk-memory"),
+        ("i64.extend32_s", "sign-extension"),
+        ("i32.trunc_sat_f32_s", "saturating-float-to-int"),
+        ("i64.add128", "wide-arithmetic"),
+        ("ref.eq", "gc"),
+    ];
+    for (mnemonic, proposal) in proposals.iter() {
+        let opcode = Opcode::from_mnemonic(mnemonic).unwrap();
+        assert_eq!(opcode.proposal, Some(*proposal), "{}", mnemonic);
+    }
+}
+
+#[test]
+fn instructions_know_their_opcode() {
+    let wat = "(module (func (param i32) (result i32)
+        local.get 0
+        i32.const 1
+        i32.add
+        i32x4.splat
+        i32x4.extract_lane 3
+        memory.size
+        drop))";
+    let buf = ParseBuffer::new(wat).unwrap();
+    let mut wat = parser::parse::<Wat>(&buf).unwrap();
+    let mnemonics = match &wat.module.kind {
+        ModuleKind::Text(fields) => match &fields[0] {
+            ModuleField::Func(func) => match &func.kind {
+                FuncKind::Inline { expression, .. } => expression
+                    .instrs
+                    .iter()
+                    .map(|i| i.opcode().mnemonic)
+                    .collect::<Vec<_>>(),
+                _ => panic!("not an inline function"),
+            },
+            _ => panic!("not a function"),
+        },
+        ModuleKind::Binary(_) => panic!("not a text module"),
+    };
+    assert_eq!(
+        mnemonics,
+        [
+            "local.get",
+            "i32.const",
+            "i32.add",
+            "i32x4.splat",
+            "i32x4.extract_lane",
+            "memory.size",
+            "drop"
+        ]
+    );
+
+    let wasm = wat.module.encode().unwrap();
+    let encoded = Opcode::from_mnemonic("i32x4.extract_lane")
+        .unwrap()
+        .encoded();
+    assert!(wasm
+        .windows(encoded.len() + 1)
+        .any(|w| w == [&encoded[..], &[3]].concat()));
+}
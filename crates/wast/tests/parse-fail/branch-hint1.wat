@@ -0,0 +1 @@
+(func (@metadata.code.branch_hint "\02") br_if 0)
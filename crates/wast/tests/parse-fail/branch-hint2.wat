@@ -0,0 +1 @@
+(func nop (@metadata.code.branch_hint "\01"))
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use wast::parser::{self, ParseBuffer};
+use wast::preprocess::{Preprocessed, Preprocessor};
+use wast::{Span, Wat};
+
+/// Preprocesses `main.wat` of the in-memory `files`.
+fn preprocess(files: &[(&str, &str)]) -> Result<Preprocessed, wast::Error> {
+    let files = files
+        .iter()
+        .map(|(path, contents)| (PathBuf::from(path), contents.to_string()))
+        .collect::<HashMap<_, _>>();
+    let mut preprocessor = Preprocessor::new();
+    preprocessor.loader(|path| {
+        files
+            .get(path)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not found"))
+    });
+    preprocessor.preprocess_file(Path::new("main.wat"))
+}
+
+fn parse(preprocessed: &Preprocessed) -> Result<(), wast::Error> {
+    let buf = ParseBuffer::new(preprocessed.output()).map_err(|e| preprocessed.map_error(e))?;
+    parser::parse::<Wat>(&buf).map_err(|e| preprocessed.map_error(e))?;
+    Ok(())
+}
+
+#[test]
+fn include() {
+    let preprocessed = preprocess(&[
+        ("main.wat", "(module (!include \"lib/funcs.wat\"))"),
+        ("lib/funcs.wat", "(func $f) (!include \"more.wat\")"),
+        ("lib/more.wat", "(func $g)"),
+    ])
+    .unwrap();
+    assert_eq!(preprocessed.output(), "(module (func $f) (func $g))");
+    parse(&preprocessed).unwrap();
+
+    let (path, span) = preprocessed.locate(Span::from_offset(24)).unwrap();
+    assert_eq!(path, Path::new("lib/more.wat"));
+    assert_eq!(span, Span::from_offset(6));
+}
+
+#[test]
+fn macros() {
+    let preprocessed = preprocess(&[(
+        "main.wat",
+        r#"(!define add ($a $b) (i32.add $a $b))
+(!define inc ($x) (!add $x (i32.const 1)))
+(module (func (result i32) (!add (i32.const 2) (!inc (i32.const 3)))))"#,
+    )])
+    .unwrap();
+    assert_eq!(
+        preprocessed.output(),
+        "\n\n(module (func (result i32) \
+         (i32.add (i32.const 2) (i32.add (i32.const 3) (i32.const 1)))))"
+    );
+    parse(&preprocessed).unwrap();
+}
+
+#[test]
+fn errors_point_at_original_files() {
+    // A mistake in an included file.
+    let preprocessed = preprocess(&[
+        ("main.wat", "(module\n  (!include \"bad.wat\"))"),
+        ("bad.wat", "(func\n  i32.bad)"),
+    ])
+    .unwrap();
+    let err = parse(&preprocessed).unwrap_err();
+    let rendered = err.to_string();
+    assert!(rendered.contains("--> bad.wat:2:3"), "{}", rendered);
+    assert!(rendered.contains("i32.bad"), "{}", rendered);
+
+    // A mistake in the argument of a macro is in the invocation, not the
+    // definition.
+    let preprocessed = preprocess(&[(
+        "main.wat",
+        "(!define f ($body) (func $body))\n(module (!f (i32.bad)))",
+    )])
+    .unwrap();
+    let err = parse(&preprocessed).unwrap_err();
+    let rendered = err.to_string();
+    assert!(rendered.contains("--> main.wat:2:14"), "{}", rendered);
+}
+
+#[test]
+fn malformed_directives() {
+    fn message(files: &[(&str, &str)]) -> String {
+        preprocess(files).unwrap_err().message()
+    }
+    assert_eq!(
+        message(&[("main.wat", "(!include \"missing.wat\")")]),
+        "failed to read `missing.wat`: not found"
+    );
+    assert_eq!(
+        message(&[("main.wat", "(!include \"main.wat\")")]),
+        "`main.wat` includes itself"
+    );
+    assert_eq!(message(&[("main.wat", "(!f)")]), "unknown macro `!f`");
+    assert_eq!(
+        message(&[("main.wat", "(!define f ($x) $x) (!f)")]),
+        "macro `!f` expects 1 arguments but was given 0"
+    );
+    assert_eq!(
+        message(&[("main.wat", "(!define f () (!f))(!f)")]),
+        "macro expansions are nested more than 100 deep"
+    );
+    assert_eq!(
+        message(&[("main.wat", "(!define ($x) $x)")]),
+        "expected a macro name"
+    );
+    assert_eq!(
+        message(&[("main.wat", "(module (!include \"a.wat\"")]),
+        "unterminated directive"
+    );
+}
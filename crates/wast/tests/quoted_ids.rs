@@ -0,0 +1,68 @@
+use wast::lexer::{id_name, LexError, Lexer, Token};
+use wast::parser::{self, ParseBuffer};
+use wast::Wat;
+
+fn encode(wat: &str) -> Vec<u8> {
+    let buf = ParseBuffer::new(wat).unwrap();
+    let mut module = parser::parse::<Wat>(&buf).unwrap();
+    module.module.encode().unwrap()
+}
+
+#[test]
+fn lex() {
+    let tokens = Lexer::new(r#"$"a b" $"" $"\ff""#)
+        .map(|t| t.map_err(|e| e.lex_error().cloned()))
+        .collect::<Vec<_>>();
+    assert_eq!(tokens[0], Ok(Token::Id(r#"$"a b""#)));
+    assert_eq!(id_name(r#"$"a b""#), "a b");
+    assert_eq!(tokens[2], Err(Some(LexError::EmptyId)));
+
+    let err = Lexer::new(r#"$"\ff""#).next().unwrap().unwrap_err();
+    assert_eq!(err.lex_error(), Some(&LexError::InvalidUtf8Id));
+}
+
+#[test]
+fn same_as_plain_ids() {
+    // Quoted identifiers name the same things as plain ones with the same
+    // name, including once escapes are resolved.
+    let quoted = encode(
+        r#"
+            (module
+              (func $"f" (param $"x" i32)
+                local.get $x
+                call $f)
+              (func $"g\68" call $gh))
+        "#,
+    );
+    let plain = encode(
+        r#"
+            (module
+              (func $f (param $x i32)
+                local.get $x
+                call $f)
+              (func $gh call $gh))
+        "#,
+    );
+    assert_eq!(quoted, plain);
+}
+
+#[test]
+fn arbitrary_names() {
+    let wasm = encode(
+        r#"
+            (module
+              (func $"hello world" (export "f")
+                call $"hello world")
+              (func $"\u{1F600}" call $"😀"))
+        "#,
+    );
+    // The names are kept in the `name` section as they are.
+    let contains = |name: &str| wasm.windows(name.len()).any(|w| w == name.as_bytes());
+    assert!(contains("hello world"));
+    assert!(contains("😀"));
+
+    let buf = ParseBuffer::new(r#"(module (func call $"not defined"))"#).unwrap();
+    let mut module = parser::parse::<Wat>(&buf).unwrap();
+    let err = module.module.encode().unwrap_err();
+    assert!(err.message().contains("not defined"), "{}", err.message());
+}
@@ -0,0 +1,125 @@
+use wast::parser::{self, ParseBuffer};
+use wast::{ModuleField, ModuleKind, NestedModuleKind, Span, Wat};
+
+fn fields<'a>(wat: &'a Wat<'a>) -> &'a [ModuleField<'a>] {
+    match &wat.module.kind {
+        ModuleKind::Text(fields) => fields,
+        ModuleKind::Binary(_) => panic!("expected a text module"),
+    }
+}
+
+#[test]
+fn skips_bad_fields() {
+    let text = r#"
+        (module
+          (func $a (result i32) i32.const 1)
+          (func $b (result i32) i32.const oops)
+          (global (mut i32) (i32.const 0))
+          (memory 1 unexpected (nested (parens)))
+          (bogus)
+          (func $c (result i32) call $a))
+    "#;
+    let mut buf = ParseBuffer::new(text).unwrap();
+    assert!(parser::parse::<Wat>(&buf).is_err());
+
+    buf.recover(true);
+    let wat = parser::parse::<Wat>(&buf).unwrap();
+    let fields = fields(&wat);
+    assert_eq!(fields.len(), 6);
+    assert!(matches!(fields[0], ModuleField::Func(_)));
+    assert!(matches!(fields[2], ModuleField::Global(_)));
+    assert!(matches!(fields[5], ModuleField::Func(_)));
+
+    let errors = fields
+        .iter()
+        .filter_map(|f| match f {
+            ModuleField::Error(e) => Some(e),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(errors.len(), 3);
+    assert_eq!(errors[0].message, "expected a i32");
+    let at = |s: &str| Span::from_offset(text.find(s).unwrap());
+    assert_eq!(errors[0].span, at("(func $b"));
+    assert_eq!(errors[0].error_span, at("oops"));
+    assert_eq!(errors[1].span, at("(memory"));
+    assert_eq!(errors[2].message, "expected valid module field");
+
+    let mut error = errors[0].error();
+    error.set_text(text);
+    assert!(error.to_string().contains("i32.const oops"), "{}", error);
+}
+
+#[test]
+fn encoding_reports_the_first_error() {
+    let text = "(func) (func (param i32 i32) garbage) (func (result))";
+    let mut buf = ParseBuffer::new(text).unwrap();
+    buf.recover(true);
+    let mut wat = parser::parse::<Wat>(&buf).unwrap();
+    assert_eq!(fields(&wat).len(), 3);
+    let err = wat.module.encode().unwrap_err();
+    assert_eq!(err.span(), Span::from_offset(text.find("garbage").unwrap()));
+    assert!(err.others().is_empty());
+}
+
+#[test]
+fn resolution_reports_all_errors() {
+    let text = r#"
+        (module
+          (func $a call $missing)
+          (func oops)
+          (global i32 bad)
+          (module (memory x))
+          (func $b call $a))
+    "#;
+    let mut buf = ParseBuffer::new(text).unwrap();
+    buf.recover(true);
+    let mut wat = parser::parse::<Wat>(&buf).unwrap();
+    let mut err = wat.module.resolve().err().unwrap();
+    let at = |s: &str| Span::from_offset(text.find(s).unwrap());
+    assert_eq!(err.span(), at("$missing"));
+    assert_eq!(err.message(), "failed to find func named `$missing`");
+    let others = err.others().iter().map(|e| e.span()).collect::<Vec<_>>();
+    assert_eq!(others, [at("oops"), at("bad"), at("x))")]);
+
+    err.set_text(text);
+    let rendered = err.to_string();
+    assert!(rendered.contains("call $missing"), "{}", rendered);
+    assert!(rendered.contains("(global i32 bad)"), "{}", rendered);
+}
+
+#[test]
+fn nested_modules() {
+    let text = r#"
+        (module
+          (module $inner
+            (func bad)
+            (func))
+          (func))
+    "#;
+    let mut buf = ParseBuffer::new(text).unwrap();
+    buf.recover(true);
+    let mut wat = parser::parse::<Wat>(&buf).unwrap();
+    let inner = match &fields(&wat)[0] {
+        ModuleField::NestedModule(m) => match &m.kind {
+            NestedModuleKind::Inline { fields } => fields,
+            _ => panic!("expected an inline module"),
+        },
+        _ => panic!("expected a nested module"),
+    };
+    assert!(matches!(inner[0], ModuleField::Error(_)));
+    assert!(matches!(inner[1], ModuleField::Func(_)));
+    assert!(wat.module.encode().is_err());
+}
+
+#[test]
+fn unrecoverable_errors() {
+    // Errors outside of module fields, or which leave parens unbalanced, are
+    // still returned.
+    let mut buf = ParseBuffer::new("(module $m (func)) oops").unwrap();
+    buf.recover(true);
+    assert!(parser::parse::<Wat>(&buf).is_err());
+    let mut buf = ParseBuffer::new("(func (func)").unwrap();
+    buf.recover(true);
+    assert!(parser::parse::<Wat>(&buf).is_err());
+}
@@ -0,0 +1,57 @@
+#![cfg(feature = "shared-everything-threads")]
+
+use wast::parser::{self, ParseBuffer};
+use wast::Wat;
+
+fn encode(wat: &str) -> Vec<u8> {
+    let buf = ParseBuffer::new(wat).unwrap();
+    let mut wat = parser::parse::<Wat>(&buf).unwrap();
+    wat.module.encode().unwrap()
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+#[test]
+fn encodes_shared_types() {
+    let wasm = encode(
+        r#"
+        (module
+          (type $shared (shared (func (param i32))))
+          (import "" "g" (global (shared i64)))
+          (table $t shared 1 2 funcref)
+          (table 1 funcref)
+          (global (shared (mut i32)) (i32.const 0))
+          (global (mut f32) (f32.const 0))
+          (func (param i32)))
+        "#,
+    );
+    // The shared type isn't reused for the function's inline type.
+    assert!(contains(
+        &wasm,
+        &[0x01, 0x0a, 0x02, 0x65, 0x60, 0x01, 0x7f, 0x00, 0x60, 0x01, 0x7f, 0x00]
+    ));
+    assert!(contains(&wasm, &[0x03, 0x7e, 0x02]));
+    assert!(contains(
+        &wasm,
+        &[0x04, 0x08, 0x02, 0x70, 0x03, 0x01, 0x02, 0x70, 0x00, 0x01]
+    ));
+    assert!(contains(&wasm, &[0x7f, 0x03, 0x41, 0x00, 0x0b]));
+    assert!(contains(&wasm, &[0x7d, 0x01, 0x43]));
+}
+
+#[test]
+fn encodes_instructions() {
+    let wasm = encode(
+        r#"
+        (module
+          (type $ft (shared (func)))
+          (func
+            thread.spawn $ft
+            thread.hw_concurrency
+            drop))
+        "#,
+    );
+    assert!(contains(&wasm, &[0xfe, 0x7e, 0x00, 0xfe, 0x7f, 0x1a, 0x0b]));
+}
@@ -0,0 +1,53 @@
+use std::path::Path;
+use wast::parser;
+use wast::source_map::SourceMap;
+use wast::{Span, Wat};
+
+#[test]
+fn spans_identify_files() {
+    let mut sources = SourceMap::new();
+    let a = sources.add("a.wat", "(module)");
+    let b = sources.add("b.wat", "(module (func))");
+    assert_eq!(sources.files().collect::<Vec<_>>(), [a, b]);
+    assert_eq!(sources.path(b), Path::new("b.wat"));
+    assert_eq!(sources.contents(a), "(module)");
+
+    for (file, offset) in [(a, 0), (a, 8), (b, 0), (b, 15)].iter().copied() {
+        let span = sources.span(file, Span::from_offset(offset));
+        assert_eq!(
+            sources.lookup(span),
+            Some((file, Span::from_offset(offset)))
+        );
+    }
+    // Past the end of the last file.
+    let end = sources.span(b, Span::from_offset(16));
+    assert_eq!(sources.lookup(end), None);
+}
+
+#[test]
+fn errors_render_with_their_file() {
+    let mut sources = SourceMap::new();
+    sources.add("a.wat", "(module\n  (func $f))");
+    let b = sources.add("b.wat", "(module\n  (func $f\n    i32.bad))");
+
+    let buf = sources.parse_buffer(b).unwrap();
+    let err = match parser::parse::<Wat>(&buf) {
+        Ok(_) => panic!("expected an error"),
+        Err(e) => sources.locate(b, e),
+    };
+    assert!(err.to_string().contains("--> b.wat:3:5"), "{}", err);
+
+    // Errors of composed files can point into any of them.
+    let span = sources.span(b, Span::from_offset(10));
+    let err = sources.error(span, "duplicate function `$f`".to_string());
+    assert_eq!(err.span(), Span::from_offset(10));
+    assert!(err.to_string().contains("--> b.wat:2:3"), "{}", err);
+
+    // Lex errors are located by `parse_buffer` itself.
+    let c = sources.add("c.wat", "(module \"unterminated)");
+    let err = match sources.parse_buffer(c) {
+        Ok(_) => panic!("expected an error"),
+        Err(e) => e,
+    };
+    assert!(err.to_string().contains("--> c.wat:1:"), "{}", err);
+}
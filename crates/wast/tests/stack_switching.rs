@@ -0,0 +1,72 @@
+#![cfg(feature = "stack-switching")]
+
+use wast::parser::{self, ParseBuffer};
+use wast::Wat;
+
+fn encode(wat: &str) -> Vec<u8> {
+    let buf = ParseBuffer::new(wat).unwrap();
+    let mut wat = parser::parse::<Wat>(&buf).unwrap();
+    wat.module.encode().unwrap()
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+#[test]
+fn encodes_types_and_instructions() {
+    let wasm = encode(
+        r#"
+        (module
+          (type $ft (func))
+          (type $ct (cont $ft))
+          (event $e)
+          (func $f)
+          (func
+            block $l
+              ref.func $f
+              cont.new $ct
+              cont.bind $ct $ct
+              resume $ct (on $e $l) (on $e switch)
+              suspend $e
+              resume_throw $ct $e
+              switch $ct $e
+            end))
+        "#,
+    );
+    // The type section defines `$ft` and then `$ct` referring to it.
+    assert!(contains(
+        &wasm,
+        &[0x01, 0x06, 0x02, 0x60, 0x00, 0x00, 0x5d, 0x00]
+    ));
+    assert!(contains(
+        &wasm,
+        &[
+            0xe0, 0x01, // cont.new $ct
+            0xe1, 0x01, 0x01, // cont.bind $ct $ct
+            0xe3, 0x01, 0x02, 0x00, 0x00, 0x00, 0x01, 0x00, // resume
+            0xe2, 0x00, // suspend $e
+            0xe4, 0x01, 0x00, 0x00, // resume_throw $ct $e
+            0xe5, 0x01, 0x00, // switch $ct $e
+            0x0b, // end
+        ]
+    ));
+}
+
+#[test]
+fn unknown_handler_label() {
+    let buf = ParseBuffer::new(
+        r#"
+        (module
+          (type $ft (func))
+          (type $ct (cont $ft))
+          (event $e)
+          (func
+            resume $ct (on $e $missing)))
+        "#,
+    )
+    .unwrap();
+    let mut wat = parser::parse::<Wat>(&buf).unwrap();
+    let err = wat.module.encode().unwrap_err();
+    assert!(err.to_string().contains("$missing"), "{}", err);
+}
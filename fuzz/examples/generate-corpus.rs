@@ -0,0 +1,127 @@
+//! Generates seed corpora for the fuzz targets from the `*.wast` and `*.wat`
+//! tests of this repository, which includes the spec tests when the
+//! `tests/testsuite` submodule is checked out.
+//!
+//! Usage: `cargo run --example generate-corpus [test-dir...]`
+//!
+//! Text targets are seeded with the test files themselves and binary targets
+//! with every module the tests define. Seeds are written to `corpus/<target>`,
+//! where `cargo fuzz run` picks them up, alongside any existing corpus.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use wast::parser::{self, ParseBuffer};
+use wast::{Wast, WastDirective};
+
+const TEXT_TARGETS: &[&str] = &["text-lexer", "text-lexer-spec", "text-parser", "roundtrip"];
+const BINARY_TARGETS: &[&str] = &["binary-reader", "print"];
+
+fn main() -> Result<()> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut dirs = std::env::args()
+        .skip(1)
+        .map(PathBuf::from)
+        .collect::<Vec<_>>();
+    if dirs.is_empty() {
+        dirs.push(root.join("../tests/testsuite"));
+        dirs.push(root.join("../tests/local"));
+    }
+
+    let mut tests = Vec::new();
+    for dir in dirs.iter() {
+        find_tests(dir, &mut tests).with_context(|| format!("failed to read {:?}", dir))?;
+    }
+    tests.sort();
+
+    let corpus = root.join("corpus");
+    for target in TEXT_TARGETS
+        .iter()
+        .chain(BINARY_TARGETS)
+        .chain(&["validate"])
+    {
+        fs::create_dir_all(corpus.join(target))?;
+    }
+
+    let mut modules = 0;
+    for test in tests.iter() {
+        let contents =
+            fs::read_to_string(test).with_context(|| format!("failed to read {:?}", test))?;
+        let name = seed_name(test);
+        for target in TEXT_TARGETS {
+            fs::write(corpus.join(target).join(&name), &contents)?;
+        }
+
+        // Tests which don't parse, or contain modules which don't encode, are
+        // still useful as text seeds but contribute no binary ones.
+        for (i, wasm) in encode(test, &contents).into_iter().enumerate() {
+            let name = format!("{}-{}", name, i);
+            for target in BINARY_TARGETS {
+                fs::write(corpus.join(target).join(&name), &wasm)?;
+            }
+            // The `validate` target reads the enabled features from its first
+            // two bytes, so enable everything except `deterministic_only`.
+            let mut input = vec![0x7f, 0xff];
+            input.extend_from_slice(&wasm);
+            fs::write(corpus.join("validate").join(&name), input)?;
+            modules += 1;
+        }
+    }
+    println!(
+        "wrote seeds for {} tests and {} modules to {:?}",
+        tests.len(),
+        modules,
+        corpus
+    );
+    Ok(())
+}
+
+fn find_tests(dir: &Path, tests: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in dir.read_dir()? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_tests(&path, tests)?;
+            continue;
+        }
+        match path.extension().and_then(|s| s.to_str()) {
+            Some("wast") | Some("wat") => tests.push(path),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Returns a file name for the seeds of `test` which is unique among tests,
+/// derived from its last few path components.
+fn seed_name(test: &Path) -> String {
+    let components = test
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>();
+    let start = components.len().saturating_sub(3);
+    components[start..].join("-")
+}
+
+/// Returns the binary encoding of every module in the test `path`.
+fn encode(path: &Path, contents: &str) -> Vec<Vec<u8>> {
+    if path.extension().and_then(|s| s.to_str()) == Some("wat") {
+        return wat::parse_str(contents).into_iter().collect();
+    }
+    let buf = match ParseBuffer::new(contents) {
+        Ok(buf) => buf,
+        Err(_) => return Vec::new(),
+    };
+    let wast = match parser::parse::<Wast>(&buf) {
+        Ok(wast) => wast,
+        Err(_) => return Vec::new(),
+    };
+    let mut ret = Vec::new();
+    for directive in wast.directives {
+        if let WastDirective::Module(mut module) = directive {
+            if let Ok(wasm) = module.encode() {
+                ret.push(wasm);
+            }
+        }
+    }
+    ret
+}
@@ -0,0 +1,80 @@
+#![no_main]
+
+use libfuzzer_sys::*;
+use wasmparser::*;
+
+// Reads every item of every section, without validating anything, to exercise
+// the readers for parts of modules which the validator may never reach.
+fuzz_target!(|data: &[u8]| {
+    drop(read_all(data));
+});
+
+fn read_all(data: &[u8]) -> Result<()> {
+    for payload in Parser::new(0).parse_all(data) {
+        match payload? {
+            Payload::TypeSection(s) => read(s)?,
+            Payload::ImportSection(s) => read(s)?,
+            Payload::AliasSection(s) => read(s)?,
+            Payload::InstanceSection(s) => {
+                for instance in s {
+                    read(instance?.args()?)?;
+                }
+            }
+            Payload::FunctionSection(s) => read(s)?,
+            Payload::TableSection(s) => read(s)?,
+            Payload::MemorySection(s) => read(s)?,
+            Payload::EventSection(s) => read(s)?,
+            Payload::GlobalSection(s) => {
+                for global in s {
+                    read_operators(global?.init_expr.get_operators_reader())?;
+                }
+            }
+            Payload::ExportSection(s) => read(s)?,
+            Payload::ElementSection(s) => {
+                for element in s {
+                    let element = element?;
+                    if let ElementKind::Active { init_expr, .. } = &element.kind {
+                        read_operators(init_expr.get_operators_reader())?;
+                    }
+                    let mut items = element.items.get_items_reader()?;
+                    for _ in 0..items.get_count() {
+                        items.read()?;
+                    }
+                }
+            }
+            Payload::DataSection(s) => {
+                for data in s {
+                    if let DataKind::Active { init_expr, .. } = &data?.kind {
+                        read_operators(init_expr.get_operators_reader())?;
+                    }
+                }
+            }
+            Payload::CodeSectionEntry(body) => {
+                let mut locals = body.get_locals_reader()?;
+                for _ in 0..locals.get_count() {
+                    locals.read()?;
+                }
+                read_operators(body.get_operators_reader()?)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn read<T, I>(section: I) -> Result<()>
+where
+    I: IntoIterator<Item = Result<T>>,
+{
+    for item in section {
+        item?;
+    }
+    Ok(())
+}
+
+fn read_operators(mut reader: OperatorsReader<'_>) -> Result<()> {
+    while !reader.eof() {
+        reader.read()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,434 @@
+#![no_main]
+
+//! Cross-checks the lexer against a reference implementation of the lexical
+//! grammar of the text format, written down as directly from the spec as
+//! possible rather than for speed.
+//!
+//! Both must agree on whether the input lexes and on the kind and boundaries
+//! of every token before the first error. Whitespace and comments are all
+//! whitespace in the spec, so runs of them are compared as a single token.
+
+use libfuzzer_sys::*;
+use std::ops::Range;
+use std::str;
+use wast::lexer::{Lexer, Token};
+
+fuzz_target!(|data: &[u8]| {
+    let s = match str::from_utf8(data) {
+        Ok(s) => s,
+        Err(_) => return,
+    };
+    let expected = spec::lex(s);
+    let actual = lex(s);
+    assert_eq!(actual, expected, "input: {:?}", s);
+});
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Kind {
+    Whitespace,
+    LParen,
+    RParen,
+    String,
+    Id,
+    Keyword,
+    Integer,
+    Float,
+    Reserved,
+}
+
+/// The tokens of an input, and where the token which failed to lex starts if
+/// lexing failed.
+#[derive(Debug, PartialEq)]
+struct Lexed {
+    tokens: Vec<(Kind, Range<usize>)>,
+    error: Option<usize>,
+}
+
+impl Lexed {
+    fn push(&mut self, kind: Kind, range: Range<usize>) {
+        match self.tokens.last_mut() {
+            Some((Kind::Whitespace, prev)) if kind == Kind::Whitespace => prev.end = range.end,
+            _ => self.tokens.push((kind, range)),
+        }
+    }
+}
+
+fn lex(s: &str) -> Lexed {
+    let mut lexed = Lexed {
+        tokens: Vec::new(),
+        error: None,
+    };
+    let mut lexer = Lexer::new(s);
+    let mut pos = 0;
+    loop {
+        let token = match lexer.parse() {
+            Ok(Some(token)) => token,
+            Ok(None) => break,
+            Err(_) => {
+                lexed.error = Some(pos);
+                break;
+            }
+        };
+        let kind = match token {
+            Token::Whitespace(_) | Token::LineComment(_) | Token::BlockComment(_) => {
+                Kind::Whitespace
+            }
+            Token::LParen(_) => Kind::LParen,
+            Token::RParen(_) => Kind::RParen,
+            Token::String(_) => Kind::String,
+            Token::Id(_) => Kind::Id,
+            Token::Keyword(_) => Kind::Keyword,
+            Token::Integer(_) => Kind::Integer,
+            Token::Float(_) => Kind::Float,
+            Token::Reserved(_) => Kind::Reserved,
+            Token::Custom { .. } => unreachable!("no recognizers are registered"),
+        };
+        let len = token.src().len();
+        lexed.push(kind, pos..pos + len);
+        pos += len;
+    }
+    lexed
+}
+
+/// The lexical grammar of the text format.
+///
+/// Each production is a function returning the length of the longest prefix
+/// of its input which it matches. Tokens are found by longest match, with
+/// ties going to the earlier production in `TOKENS`.
+///
+/// Where the lexer intentionally departs from the grammar it's mirrored here,
+/// with a comment saying so.
+mod spec {
+    use super::{Kind, Lexed};
+    use std::str;
+
+    type Production = fn(&[u8]) -> Option<usize>;
+
+    /// Productions of `token`, in order of priority. Numbers come first since
+    /// `inf` and `nan` are keywords too.
+    const TOKENS: &[(Kind, Production)] = &[
+        (Kind::LParen, lparen),
+        (Kind::RParen, rparen),
+        (Kind::Integer, integer),
+        (Kind::Float, float),
+        (Kind::String, string),
+        (Kind::Id, id),
+        (Kind::Keyword, keyword),
+        (Kind::Reserved, reserved),
+    ];
+
+    pub(super) fn lex(s: &str) -> Lexed {
+        let s = s.as_bytes();
+        let mut lexed = Lexed {
+            tokens: Vec::new(),
+            error: None,
+        };
+        let mut pos = 0;
+        while pos < s.len() {
+            let rest = &s[pos..];
+            let len = match whitespace(rest) {
+                _ if malformed(rest) => None,
+                Some(Some(len)) => Some((Kind::Whitespace, len)),
+                Some(None) => None,
+                None => TOKENS
+                    .iter()
+                    .filter_map(|(kind, production)| Some((*kind, production(rest)?)))
+                    .filter(|(_, len)| *len > 0)
+                    .fold(None, |best, (kind, len)| match best {
+                        Some((_, best_len)) if best_len >= len => best,
+                        _ => Some((kind, len)),
+                    }),
+            };
+            let (kind, len) = match len {
+                Some(pair) => pair,
+                None => {
+                    lexed.error = Some(pos);
+                    break;
+                }
+            };
+            lexed.push(kind, pos..pos + len);
+            pos += len;
+        }
+        lexed
+    }
+
+    /// Whether `s` starts with something the lexer rejects outright rather
+    /// than leaving it to the parser.
+    fn malformed(s: &[u8]) -> bool {
+        // The spec makes `$` followed by anything other than a valid,
+        // non-empty name a reserved token, but the lexer takes `$"` to always
+        // start an identifier and reports an error for it.
+        if s.starts_with(b"$\"") {
+            return match name(&s[1..]) {
+                Some(name) => name.is_empty() || String::from_utf8(name).is_err(),
+                None => true,
+            };
+        }
+        false
+    }
+
+    /// `whitespace ::= (' ' | format | comment)+`, returning `Some(None)`
+    /// for a block comment which isn't closed.
+    fn whitespace(s: &[u8]) -> Option<Option<usize>> {
+        let mut i = 0;
+        loop {
+            let rest = &s[i..];
+            let len = match rest.first() {
+                Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') => 1,
+                _ if rest.starts_with(b";;") => line_comment(rest),
+                _ if rest.starts_with(b"(;") => match block_comment(rest) {
+                    Some(len) => len,
+                    None if i > 0 => break,
+                    None => return Some(None),
+                },
+                _ => break,
+            };
+            i += len;
+        }
+        if i == 0 {
+            None
+        } else {
+            Some(Some(i))
+        }
+    }
+
+    /// `linecomment ::= ';;' linechar* (U+0A | eof)`, leaving the newline to
+    /// be whitespace of its own.
+    fn line_comment(s: &[u8]) -> usize {
+        s.iter().position(|b| *b == b'\n').unwrap_or(s.len())
+    }
+
+    /// `blockcomment ::= '(;' blockchar* ';)'`, where blockchars are
+    /// anything but the start of `;)` or of a nested block comment.
+    fn block_comment(s: &[u8]) -> Option<usize> {
+        let mut i = 2;
+        while i < s.len() {
+            if s[i..].starts_with(b";)") {
+                return Some(i + 2);
+            }
+            if s[i..].starts_with(b"(;") {
+                i += block_comment(&s[i..])?;
+            } else {
+                i += 1;
+            }
+        }
+        None
+    }
+
+    fn lparen(s: &[u8]) -> Option<usize> {
+        if s.starts_with(b"(") {
+            Some(1)
+        } else {
+            None
+        }
+    }
+
+    fn rparen(s: &[u8]) -> Option<usize> {
+        if s.starts_with(b")") {
+            Some(1)
+        } else {
+            None
+        }
+    }
+
+    fn is_idchar(b: u8) -> bool {
+        match b {
+            b'0'..=b'9' | b'a'..=b'z' | b'A'..=b'Z' => true,
+            _ => b"!#$%&'*+-./:<=>?@\\^_`|~".contains(&b),
+        }
+    }
+
+    fn idchars(s: &[u8]) -> usize {
+        s.iter().position(|b| !is_idchar(*b)).unwrap_or(s.len())
+    }
+
+    /// `reserved ::= idchar+`
+    ///
+    /// Later versions of the spec also allow `,`, `;`, `[`, `]`, `{` and `}`
+    /// in reserved tokens, which the lexer accepts as tokens of their own.
+    fn reserved(s: &[u8]) -> Option<usize> {
+        match s.first() {
+            Some(b',') | Some(b';') | Some(b'[') | Some(b']') | Some(b'{') | Some(b'}') => Some(1),
+            _ => Some(idchars(s)),
+        }
+    }
+
+    /// `keyword ::= ('a' | ... | 'z') idchar*`
+    fn keyword(s: &[u8]) -> Option<usize> {
+        match s.first() {
+            Some(b'a'..=b'z') => Some(idchars(s)),
+            _ => None,
+        }
+    }
+
+    /// `id ::= '$' idchar+ | '$' name`
+    fn id(s: &[u8]) -> Option<usize> {
+        if !s.starts_with(b"$") {
+            return None;
+        }
+        match idchars(&s[1..]) {
+            0 => Some(1 + string(&s[1..])?),
+            n => Some(1 + n),
+        }
+    }
+
+    /// `string ::= '"' stringelem* '"'`
+    fn string(s: &[u8]) -> Option<usize> {
+        let (len, _) = string_value(s)?;
+        Some(len)
+    }
+
+    /// The value of the string at the start of `s`, if there is one.
+    fn name(s: &[u8]) -> Option<Vec<u8>> {
+        let (_, value) = string_value(s)?;
+        Some(value)
+    }
+
+    fn string_value(s: &[u8]) -> Option<(usize, Vec<u8>)> {
+        if !s.starts_with(b"\"") {
+            return None;
+        }
+        let mut value = Vec::new();
+        let mut i = 1;
+        loop {
+            match *s.get(i)? {
+                b'"' => return Some((i + 1, value)),
+                b'\\' => {
+                    i += 1;
+                    match *s.get(i)? {
+                        b't' => value.push(b'\t'),
+                        b'n' => value.push(b'\n'),
+                        b'r' => value.push(b'\r'),
+                        b'"' => value.push(b'"'),
+                        b'\'' => value.push(b'\''),
+                        b'\\' => value.push(b'\\'),
+                        b'u' => {
+                            // `'\u{' n:hexnum '}'` where `n` is a unicode
+                            // scalar value.
+                            if s.get(i + 1) != Some(&b'{') {
+                                return None;
+                            }
+                            let len = hexnum(&s[i + 2..]);
+                            let digits = str::from_utf8(&s[i + 2..i + 2 + len]).ok()?;
+                            let n = u32::from_str_radix(&digits.replace('_', ""), 16).ok()?;
+                            let c = std::char::from_u32(n)?;
+                            value.extend(c.to_string().as_bytes());
+                            i += 2 + len;
+                            if s.get(i) != Some(&b'}') {
+                                return None;
+                            }
+                        }
+                        hi if hi.is_ascii_hexdigit() => {
+                            let lo = *s.get(i + 1)?;
+                            if !lo.is_ascii_hexdigit() {
+                                return None;
+                            }
+                            let byte = str::from_utf8(&[hi, lo]).ok()?.to_string();
+                            value.push(u8::from_str_radix(&byte, 16).ok()?);
+                            i += 1;
+                        }
+                        _ => return None,
+                    }
+                    i += 1;
+                }
+                // Any character other than the ASCII control characters, so
+                // all bytes of multi-byte characters are fine.
+                b if b < 0x20 || b == 0x7f => return None,
+                b => {
+                    value.push(b);
+                    i += 1;
+                }
+            }
+        }
+    }
+
+    /// `digit ('_'? digit)*` for the given kind of digit, returning 0 if
+    /// there's no match.
+    fn digits(s: &[u8], digit: fn(&u8) -> bool) -> usize {
+        if !matches!(s.first(), Some(b) if digit(b)) {
+            return 0;
+        }
+        let mut i = 1;
+        loop {
+            match s.get(i) {
+                Some(b) if digit(b) => i += 1,
+                Some(b'_') if matches!(s.get(i + 1), Some(b) if digit(b)) => i += 2,
+                _ => return i,
+            }
+        }
+    }
+
+    /// `num ::= digit ('_'? digit)*`
+    fn num(s: &[u8]) -> usize {
+        digits(s, u8::is_ascii_digit)
+    }
+
+    /// `hexnum ::= hexdigit ('_'? hexdigit)*`
+    fn hexnum(s: &[u8]) -> usize {
+        digits(s, u8::is_ascii_hexdigit)
+    }
+
+    fn sign(s: &[u8]) -> usize {
+        match s.first() {
+            Some(b'+') | Some(b'-') => 1,
+            _ => 0,
+        }
+    }
+
+    fn nonzero(len: usize) -> Option<usize> {
+        if len == 0 {
+            None
+        } else {
+            Some(len)
+        }
+    }
+
+    /// `sN ::= ('+' | '-')? uN` and `uN ::= num | '0x' hexnum`, which is the
+    /// same as far as lexing is concerned.
+    fn integer(s: &[u8]) -> Option<usize> {
+        let i = sign(s);
+        if s[i..].starts_with(b"0x") {
+            return Some(i + 2 + nonzero(hexnum(&s[i + 2..]))?);
+        }
+        Some(i + nonzero(num(&s[i..]))?)
+    }
+
+    /// `fN ::= ('+' | '-')? fNmag` where
+    ///
+    /// ```text
+    /// fNmag ::= float | hexfloat | 'inf' | 'nan' | 'nan:0x' hexnum
+    /// float ::= num '.'? | num '.' frac | num '.'? ('E' | 'e') sign num
+    ///         | num '.' frac ('E' | 'e') sign num
+    /// hexfloat ::= '0x' hexnum '.'? | '0x' hexnum '.' hexfrac
+    ///            | '0x' hexnum '.'? ('P' | 'p') sign num
+    ///            | '0x' hexnum '.' hexfrac ('P' | 'p') sign num
+    /// ```
+    ///
+    /// with `frac` and `hexfrac` being the same as `num` and `hexnum`.
+    fn float(s: &[u8]) -> Option<usize> {
+        let i = sign(s);
+        let s = &s[i..];
+        if s.starts_with(b"nan:0x") {
+            return Some(i + 6 + nonzero(hexnum(&s[6..]))?);
+        }
+        if s.starts_with(b"inf") || s.starts_with(b"nan") {
+            return Some(i + 3);
+        }
+        let hex = s.starts_with(b"0x");
+        let digits = if hex { hexnum } else { num };
+        let exponent: &[u8] = if hex { b"Pp" } else { b"Ee" };
+        let mut j = if hex { 2 } else { 0 };
+        j += nonzero(digits(&s[j..]))?;
+        if s.get(j) == Some(&b'.') {
+            j += 1;
+            j += digits(&s[j..]);
+        }
+        if matches!(s.get(j), Some(b) if exponent.contains(b)) {
+            let k = j + 1 + sign(&s[j + 1..]);
+            if let Some(len) = nonzero(num(&s[k..])) {
+                j = k + len;
+            }
+        }
+        Some(i + j)
+    }
+}
@@ -4,6 +4,13 @@ use libfuzzer_sys::*;
 use std::str;
 
 fuzz_target!(|data: &[u8]| {
+    // The streaming lexer accepts arbitrary bytes, reporting malformed utf-8
+    // as an error.
+    let mut l = wast::lexer::StreamLexer::new(data);
+    while let Ok(Some(_)) = l.parse() {
+        // ...
+    }
+
     let s = match str::from_utf8(data) {
         Ok(s) => s,
         Err(_) => return,
@@ -12,4 +19,14 @@ fuzz_target!(|data: &[u8]| {
     while let Ok(Some(_)) = l.parse() {
         // ...
     }
+
+    // Highlighting recovers from errors, so every non-whitespace byte of the
+    // input must be covered by exactly one span.
+    let mut end = 0;
+    for h in wast::highlight::highlight(s) {
+        assert!(end <= h.start && h.start < h.end);
+        assert!(s[end..h.start].chars().all(char::is_whitespace));
+        end = h.end;
+    }
+    assert!(s[end..].chars().all(char::is_whitespace));
 });
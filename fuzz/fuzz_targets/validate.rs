@@ -25,6 +25,8 @@ fuzz_target!(|data: &[u8]| {
         multi_memory: (byte2 & 0b0000_0001) != 0,
         memory64: (byte2 & 0b0000_0010) != 0,
         exceptions: (byte2 & 0b0000_0100) != 0,
+        wide_arithmetic: (byte2 & 0b0000_1000) != 0,
+        function_references: (byte2 & 0b0001_0000) != 0,
     });
 
     drop(validator.validate_all(&data[2..]));
@@ -0,0 +1,370 @@
+//! Call graph construction and reachability analysis.
+//!
+//! Direct calls are known exactly, while `call_indirect` is over-approximated:
+//! it may call any function which can be in the table it uses and has the
+//! same signature as the call. A function can be in a table if it's in an
+//! element segment which initializes that table or is passive (and so can be
+//! copied into any table), or if its reference escapes with `ref.func` or a
+//! declarative element segment. Tables which are imported or exported can also
+//! be filled by the host with any exported function.
+//!
+//! ```
+//! use wasm_tools::analysis::CallGraph;
+//!
+//! let wasm = wat::parse_str(r#"
+//!     (module
+//!       (type $t (func))
+//!       (table funcref (elem $a $b))
+//!       (func $a)
+//!       (func $b (param i32))
+//!       (func $c)
+//!       (func (export "main") (param i32)
+//!         local.get 0
+//!         call_indirect (type $t)))
+//! "#)?;
+//! let graph = CallGraph::new(&wasm)?;
+//! // Only `$a` has the right signature, and `$c` isn't in the table.
+//! assert_eq!(graph.indirect_callees(3).iter().copied().collect::<Vec<_>>(), [0]);
+//! assert_eq!(graph.reachable().into_iter().collect::<Vec<_>>(), [0, 3]);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::{bail, Result};
+use std::collections::BTreeSet;
+use wasmparser::{
+    ElementItem, ElementKind, ExternalKind, FuncType, ImportSectionEntryType, Operator,
+    OperatorsReader, Parser, Payload, TypeDef,
+};
+
+/// The call graph of a module, see the [module docs](self).
+///
+/// Functions are identified by their index in the function index space,
+/// which includes imported functions. Imported functions don't call
+/// anything.
+#[derive(Debug, Clone)]
+pub struct CallGraph {
+    imported_functions: u32,
+    direct: Vec<BTreeSet<u32>>,
+    indirect: Vec<BTreeSet<u32>>,
+    roots: BTreeSet<u32>,
+}
+
+/// The facts gathered about a module before resolving indirect calls.
+#[derive(Default)]
+struct Module<'a> {
+    types: Vec<Option<FuncType>>,
+    func_types: Vec<u32>,
+    imported_functions: u32,
+    /// Whether each table is imported or exported.
+    tables_shared: Vec<bool>,
+    /// The functions which can be in each table.
+    table_funcs: Vec<BTreeSet<u32>>,
+    /// The functions which can be in any table.
+    escaping: BTreeSet<u32>,
+    exported: BTreeSet<u32>,
+    start: Option<u32>,
+    direct: Vec<BTreeSet<u32>>,
+    /// The `(table, type)` pairs of the indirect calls of each function.
+    indirect: Vec<BTreeSet<(u32, u32)>>,
+    bodies: Vec<OperatorsReader<'a>>,
+}
+
+impl CallGraph {
+    /// Builds the call graph of the module `wasm`.
+    ///
+    /// Returns an error if the module is malformed or refers to functions,
+    /// types or tables which don't exist. Modules using the module linking
+    /// proposal aren't supported.
+    pub fn new(wasm: &[u8]) -> Result<CallGraph> {
+        let mut module = Module::default();
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload? {
+                Payload::TypeSection(s) => {
+                    for ty in s {
+                        module.types.push(match ty? {
+                            TypeDef::Func(ty) => Some(ty),
+                            _ => None,
+                        });
+                    }
+                }
+                Payload::ImportSection(s) => {
+                    for import in s {
+                        match import?.ty {
+                            ImportSectionEntryType::Function(ty) => {
+                                module.imported_functions += 1;
+                                module.func_types.push(ty);
+                            }
+                            ImportSectionEntryType::Table(_) => {
+                                module.tables_shared.push(true);
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                Payload::FunctionSection(s) => {
+                    for ty in s {
+                        module.func_types.push(ty?);
+                    }
+                }
+                Payload::TableSection(s) => {
+                    for table in s {
+                        table?;
+                        module.tables_shared.push(false);
+                    }
+                }
+                Payload::GlobalSection(s) => {
+                    for global in s {
+                        module.refs(global?.init_expr.get_operators_reader())?;
+                    }
+                }
+                Payload::ExportSection(s) => {
+                    for export in s {
+                        let export = export?;
+                        match export.kind {
+                            ExternalKind::Function => {
+                                module.exported.insert(export.index);
+                            }
+                            ExternalKind::Table => {
+                                match module.tables_shared.get_mut(export.index as usize) {
+                                    Some(shared) => *shared = true,
+                                    None => bail!("table index {} out of bounds", export.index),
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                Payload::StartSection { func, .. } => module.start = Some(func),
+                Payload::ElementSection(s) => {
+                    module
+                        .table_funcs
+                        .resize(module.tables_shared.len(), BTreeSet::new());
+                    for element in s {
+                        let element = element?;
+                        let mut funcs = BTreeSet::new();
+                        for item in element.items.get_items_reader()? {
+                            if let ElementItem::Func(f) = item? {
+                                funcs.insert(f);
+                            }
+                        }
+                        match element.kind {
+                            ElementKind::Active { table_index, .. } => {
+                                match module.table_funcs.get_mut(table_index as usize) {
+                                    Some(table) => table.extend(funcs),
+                                    None => bail!("table index {} out of bounds", table_index),
+                                }
+                            }
+                            ElementKind::Passive | ElementKind::Declared => {
+                                module.escaping.extend(funcs)
+                            }
+                        }
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    module.bodies.push(body.get_operators_reader()?);
+                }
+                Payload::AliasSection(_)
+                | Payload::InstanceSection(_)
+                | Payload::ModuleSectionStart { .. } => {
+                    bail!("modules using the module linking proposal are not supported")
+                }
+                _ => {}
+            }
+        }
+        module
+            .table_funcs
+            .resize(module.tables_shared.len(), BTreeSet::new());
+        module.calls()?;
+        module.into_graph()
+    }
+
+    /// Returns the number of functions, including imported ones.
+    pub fn functions(&self) -> u32 {
+        self.direct.len() as u32
+    }
+
+    /// Returns the number of imported functions, which come first in the
+    /// function index space.
+    pub fn imported_functions(&self) -> u32 {
+        self.imported_functions
+    }
+
+    /// Returns the functions which `f` calls directly, with `call` or
+    /// `return_call`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` is out of bounds.
+    pub fn direct_callees(&self, f: u32) -> &BTreeSet<u32> {
+        &self.direct[f as usize]
+    }
+
+    /// Returns the functions which `f` may call indirectly, with
+    /// `call_indirect` or `return_call_indirect`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` is out of bounds.
+    pub fn indirect_callees(&self, f: u32) -> &BTreeSet<u32> {
+        &self.indirect[f as usize]
+    }
+
+    /// Returns the functions which `f` may call, directly or indirectly.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` is out of bounds.
+    pub fn callees(&self, f: u32) -> BTreeSet<u32> {
+        self.direct[f as usize]
+            .union(&self.indirect[f as usize])
+            .copied()
+            .collect()
+    }
+
+    /// Returns the functions which may call `f`.
+    pub fn callers(&self, f: u32) -> BTreeSet<u32> {
+        (0..self.functions())
+            .filter(|g| {
+                self.direct[*g as usize].contains(&f) || self.indirect[*g as usize].contains(&f)
+            })
+            .collect()
+    }
+
+    /// Returns the entry points of the module: its exported functions and its
+    /// start function.
+    pub fn roots(&self) -> &BTreeSet<u32> {
+        &self.roots
+    }
+
+    /// Returns the functions which may be called, transitively, from the
+    /// entry points of the module, including the entry points themselves.
+    pub fn reachable(&self) -> BTreeSet<u32> {
+        self.reachable_from(self.roots.iter().copied())
+    }
+
+    /// Returns the functions which may be called, transitively, from
+    /// `roots`, including `roots` themselves.
+    ///
+    /// Roots which are out of bounds are ignored.
+    pub fn reachable_from(&self, roots: impl IntoIterator<Item = u32>) -> BTreeSet<u32> {
+        let mut reachable = BTreeSet::new();
+        let mut worklist = Vec::new();
+        for f in roots {
+            if f < self.functions() && reachable.insert(f) {
+                worklist.push(f);
+            }
+        }
+        while let Some(f) = worklist.pop() {
+            let callees = self.direct[f as usize]
+                .iter()
+                .chain(&self.indirect[f as usize]);
+            for callee in callees {
+                if reachable.insert(*callee) {
+                    worklist.push(*callee);
+                }
+            }
+        }
+        reachable
+    }
+
+    /// Returns whether `from` may call `to`, transitively.
+    pub fn can_reach(&self, from: u32, to: u32) -> bool {
+        self.reachable_from(Some(from)).contains(&to)
+    }
+}
+
+impl<'a> Module<'a> {
+    /// Records the functions whose reference is taken in `reader`.
+    fn refs(&mut self, mut reader: OperatorsReader<'_>) -> Result<()> {
+        while !reader.eof() {
+            if let Operator::RefFunc { function_index } = reader.read()? {
+                self.escaping.insert(function_index);
+            }
+        }
+        Ok(())
+    }
+
+    /// Records the calls of each function body.
+    fn calls(&mut self) -> Result<()> {
+        for mut reader in std::mem::take(&mut self.bodies) {
+            let mut direct = BTreeSet::new();
+            let mut indirect = BTreeSet::new();
+            while !reader.eof() {
+                match reader.read()? {
+                    Operator::Call { function_index } | Operator::ReturnCall { function_index } => {
+                        direct.insert(function_index);
+                    }
+                    Operator::CallIndirect { index, table_index }
+                    | Operator::ReturnCallIndirect { index, table_index } => {
+                        indirect.insert((table_index, index));
+                    }
+                    Operator::RefFunc { function_index } => {
+                        self.escaping.insert(function_index);
+                    }
+                    _ => {}
+                }
+            }
+            self.direct.push(direct);
+            self.indirect.push(indirect);
+        }
+        Ok(())
+    }
+
+    fn into_graph(self) -> Result<CallGraph> {
+        let functions = self.func_types.len();
+        if self.imported_functions as usize + self.direct.len() != functions {
+            bail!("function and code section have inconsistent lengths");
+        }
+        let referenced = self
+            .escaping
+            .iter()
+            .chain(self.exported.iter())
+            .chain(self.start.iter())
+            .chain(self.table_funcs.iter().flatten())
+            .chain(self.direct.iter().flatten());
+        for f in referenced {
+            if *f as usize >= functions {
+                bail!("function index {} out of bounds", f);
+            }
+        }
+        let ty = |index: u32| match self.types.get(index as usize) {
+            Some(Some(ty)) => Ok(ty),
+            _ => bail!("type index {} out of bounds", index),
+        };
+
+        let mut direct = vec![BTreeSet::new(); self.imported_functions as usize];
+        let mut indirect = direct.clone();
+        for (calls, indirect_calls) in self.direct.iter().zip(self.indirect.iter()) {
+            direct.push(calls.clone());
+            let mut callees = BTreeSet::new();
+            for (table, index) in indirect_calls.iter() {
+                let shared = match self.tables_shared.get(*table as usize) {
+                    Some(shared) => *shared,
+                    None => bail!("table index {} out of bounds", table),
+                };
+                let expected = ty(*index)?;
+                let candidates = self.table_funcs[*table as usize]
+                    .iter()
+                    .chain(self.escaping.iter())
+                    .chain(self.exported.iter().filter(|_| shared));
+                for f in candidates {
+                    if ty(self.func_types[*f as usize])? == expected {
+                        callees.insert(*f);
+                    }
+                }
+            }
+            indirect.push(callees);
+        }
+        Ok(CallGraph {
+            imported_functions: self.imported_functions,
+            direct,
+            indirect,
+            roots: self
+                .exported
+                .iter()
+                .chain(self.start.iter())
+                .copied()
+                .collect(),
+        })
+    }
+}
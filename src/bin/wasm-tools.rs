@@ -0,0 +1,826 @@
+//! A single command line tool which bundles up the functionality of the crates
+//! in this repository behind subcommands.
+//!
+//! All subcommands read their input from the file given on the command line,
+//! or from stdin if no file (or `-`) is given. Inputs which are expected to be
+//! WebAssembly modules may be in either the text or the binary format.
+
+use anyhow::{bail, Context, Result};
+use arbitrary::{Arbitrary, Unstructured};
+use std::env;
+use std::io::Write;
+use wasm_tools::signature::{Ed25519Signer, Ed25519Verifier};
+use wasm_tools::{output_writer, parse_input, read_input, write_output};
+use wasmparser::{BinaryReader, Validator};
+use wasmprinter::ItemKind;
+
+const SUBCOMMANDS: &[(&str, &str, fn(&str, &[String]) -> Result<()>)] = &[
+    ("parse", "convert a module to the binary format", parse),
+    ("print", "convert a module to the text format", print),
+    ("validate", "validate a module", validate),
+    ("strip", "remove custom sections from a module", strip),
+    (
+        "canonicalize",
+        "rewrite a module into its canonical binary encoding",
+        canonicalize,
+    ),
+    ("gc", "remove unused items from a module", gc),
+    (
+        "split",
+        "move functions of a module into modules loaded later",
+        split,
+    ),
+    (
+        "merge",
+        "merge modules which import from each other into one",
+        merge,
+    ),
+    (
+        "names",
+        "add or change the debug names of a module's functions",
+        names,
+    ),
+    (
+        "optimize",
+        "fold constants and remove dead code in a module's functions",
+        optimize,
+    ),
+    (
+        "instrument",
+        "insert calls to host functions into a module",
+        instrument,
+    ),
+    (
+        "lower",
+        "rewrite instructions of newer proposals into MVP instructions",
+        lower,
+    ),
+    (
+        "objdump",
+        "print a summary of the sections in a module",
+        objdump,
+    ),
+    (
+        "usage",
+        "count the operators and proposals a module uses",
+        usage,
+    ),
+    (
+        "liveness",
+        "report the liveness of locals and stack depth of each function",
+        liveness,
+    ),
+    (
+        "capabilities",
+        "report what a module can do outside of its sandbox as JSON",
+        capabilities,
+    ),
+    (
+        "hash",
+        "print, embed or verify the hash of a module's contents",
+        hash,
+    ),
+    ("sign", "sign a module with an Ed25519 key", sign),
+    ("verify", "verify the signature of a module", verify),
+    ("smith", "generate a valid module from a random seed", smith),
+];
+
+fn main() -> Result<()> {
+    env_logger::init();
+
+    let program = env::args().nth(0).unwrap();
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let subcommand = match args.first() {
+        Some(name) => name,
+        None => {
+            print_subcommands(&program);
+            std::process::exit(1);
+        }
+    };
+    if subcommand == "-h" || subcommand == "--help" || subcommand == "help" {
+        print_subcommands(&program);
+        return Ok(());
+    }
+    match SUBCOMMANDS.iter().find(|(name, _, _)| name == subcommand) {
+        Some((name, _, run)) => run(&format!("{} {}", program, name), &args[1..]),
+        None => bail!("unknown subcommand `{}`", subcommand),
+    }
+}
+
+fn parse(program: &str, args: &[String]) -> Result<()> {
+    let mut opts = getopts::Options::new();
+    opts.optopt("o", "", "set output file name", "NAME");
+    let (matches, input) = match parse_args(program, opts, args)? {
+        Some(m) => m,
+        None => return Ok(()),
+    };
+    let input = read_input(input.as_deref())?;
+    let wasm = parse_input(&input)?;
+    write_output(matches.opt_str("o").as_deref(), &wasm)
+}
+
+fn print(program: &str, args: &[String]) -> Result<()> {
+    let mut opts = getopts::Options::new();
+    opts.optopt("o", "", "set output file name", "NAME");
+    opts.optflag(
+        "",
+        "no-demangle",
+        "don't demangle Rust and C++ symbol names",
+    );
+    opts.optflag(
+        "f",
+        "fold-instructions",
+        "print instructions in the folded s-expression form",
+    );
+    opts.optflag(
+        "",
+        "skeleton",
+        "only print the structure of the module, without function bodies",
+    );
+    opts.optopt(
+        "",
+        "symbols",
+        "name functions from the symbol map FILE if the module has no \
+         function names",
+        "FILE",
+    );
+    opts.optopt(
+        "",
+        "func",
+        "only print the function with this index or name",
+        "FUNC",
+    );
+    opts.optopt(
+        "",
+        "global",
+        "only print the global with this index",
+        "GLOBAL",
+    );
+    opts.optopt(
+        "",
+        "elem",
+        "only print the element segment with this index",
+        "ELEM",
+    );
+    opts.optopt(
+        "",
+        "data",
+        "only print the data segment with this index",
+        "DATA",
+    );
+    let (matches, input) = match parse_args(program, opts, args)? {
+        Some(m) => m,
+        None => return Ok(()),
+    };
+    let input = read_input(input.as_deref())?;
+    let wasm = parse_input(&input)?;
+    let mut printer = wasmprinter::Printer::new();
+    printer.demangle(!matches.opt_present("no-demangle"));
+    printer.fold_instructions(matches.opt_present("f"));
+    printer.skeleton(matches.opt_present("skeleton"));
+    if let Some(path) = matches.opt_str("symbols") {
+        printer.symbol_map(wasm_tools::names::read_symbol_map(&path)?);
+    }
+    let items = [
+        ("func", ItemKind::Func),
+        ("global", ItemKind::Global),
+        ("elem", ItemKind::Elem),
+        ("data", ItemKind::Data),
+    ];
+    let item = items
+        .iter()
+        .find_map(|(name, kind)| Some((*kind, matches.opt_str(name)?)));
+    let mut output = output_writer(matches.opt_str("o").as_deref())?;
+    match item {
+        Some((kind, item)) => {
+            let text = printer.print_item(&wasm, kind, &item)?;
+            output.write_all(text.as_bytes())?;
+        }
+        None => printer.print_to(&wasm, &mut output)?,
+    }
+    output.write_all(b"\n")?;
+    output.flush().context("failed to write output")
+}
+
+fn validate(program: &str, args: &[String]) -> Result<()> {
+    let mut opts = getopts::Options::new();
+    opts.optflag(
+        "",
+        "json",
+        "print validation errors as JSON, with their error codes",
+    );
+    let (matches, input) = match parse_args(program, opts, args)? {
+        Some(m) => m,
+        None => return Ok(()),
+    };
+    let input = read_input(input.as_deref())?;
+    let wasm = parse_input(&input)?;
+    match Validator::new().validate_all(&wasm) {
+        Ok(()) => Ok(()),
+        Err(e) if matches.opt_present("json") => {
+            println!("{}", serde_json::to_string(&e)?);
+            std::process::exit(1);
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn strip(program: &str, args: &[String]) -> Result<()> {
+    let mut opts = getopts::Options::new();
+    opts.optopt("o", "", "set output file name", "NAME");
+    opts.optmulti("", "keep", "don't remove the custom section NAME", "NAME");
+    let (matches, input) = match parse_args(program, opts, args)? {
+        Some(m) => m,
+        None => return Ok(()),
+    };
+    let keep = matches.opt_strs("keep");
+    let input = read_input(input.as_deref())?;
+    let wasm = parse_input(&input)?;
+
+    // Walk over the top-level sections of the module, copying everything except
+    // custom sections verbatim into the output.
+    let mut reader = BinaryReader::new(&wasm);
+    let header = reader.read_bytes(8)?;
+    let mut output = header.to_vec();
+    while !reader.eof() {
+        let start = reader.current_position();
+        let id = reader.read_u8()?;
+        let len = reader.read_var_u32()?;
+        let contents = reader.read_bytes(len as usize)?;
+        if id == 0 {
+            let name = BinaryReader::new(contents).read_string()?;
+            if !keep.iter().any(|k| k == name) {
+                continue;
+            }
+        }
+        output.extend_from_slice(&wasm[start..reader.current_position()]);
+    }
+    write_output(matches.opt_str("o").as_deref(), &output)
+}
+
+fn canonicalize(program: &str, args: &[String]) -> Result<()> {
+    let mut opts = getopts::Options::new();
+    opts.optopt("o", "", "set output file name", "NAME");
+    let (matches, input) = match parse_args(program, opts, args)? {
+        Some(m) => m,
+        None => return Ok(()),
+    };
+    let input = read_input(input.as_deref())?;
+    let wasm = parse_input(&input)?;
+    let wasm = wasm_tools::canonicalize::canonicalize(&wasm)?;
+    write_output(matches.opt_str("o").as_deref(), &wasm)
+}
+
+fn gc(program: &str, args: &[String]) -> Result<()> {
+    let mut opts = getopts::Options::new();
+    opts.optopt("o", "", "set output file name", "NAME");
+    let (matches, input) = match parse_args(program, opts, args)? {
+        Some(m) => m,
+        None => return Ok(()),
+    };
+    let input = read_input(input.as_deref())?;
+    let wasm = parse_input(&input)?;
+    let wasm = wasm_tools::gc::gc(&wasm)?;
+    write_output(matches.opt_str("o").as_deref(), &wasm)
+}
+
+fn split(program: &str, args: &[String]) -> Result<()> {
+    let mut opts = getopts::Options::new();
+    opts.reqopt(
+        "o",
+        "",
+        "set output file name, secondary modules go to NAME.1, NAME.2...",
+        "NAME",
+    );
+    opts.optmulti(
+        "",
+        "secondary",
+        "move the functions reachable from the comma-separated function \
+         indices ROOTS into a secondary module",
+        "ROOTS",
+    );
+    opts.optopt(
+        "",
+        "primary-module",
+        "import from the primary module as MODULE (default: primary)",
+        "MODULE",
+    );
+    let (matches, input) = match parse_args(program, opts, args)? {
+        Some(m) => m,
+        None => return Ok(()),
+    };
+    let mut splitter = wasm_tools::split::Splitter::new();
+    if let Some(module) = matches.opt_str("primary-module") {
+        splitter.primary_module(&module);
+    }
+    for roots in matches.opt_strs("secondary") {
+        let roots = roots
+            .split(',')
+            .map(|root| {
+                root.parse::<u32>()
+                    .with_context(|| format!("invalid function index `{}`", root))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        splitter.secondary(roots);
+    }
+    let input = read_input(input.as_deref())?;
+    let wasm = parse_input(&input)?;
+    let split = splitter.split(&wasm)?;
+    let output = matches.opt_str("o").unwrap();
+    write_output(Some(&output), &split.primary)?;
+    for (i, secondary) in split.secondaries.iter().enumerate() {
+        write_output(Some(&format!("{}.{}", output, i + 1)), secondary)?;
+    }
+    Ok(())
+}
+
+fn merge(program: &str, args: &[String]) -> Result<()> {
+    let mut opts = getopts::Options::new();
+    opts.optopt("o", "", "set output file name", "NAME");
+    opts.optmulti(
+        "m",
+        "module",
+        "merge the module in FILE, which the other modules import from as NAME",
+        "NAME=FILE",
+    );
+    let (matches, input) = match parse_args(program, opts, args)? {
+        Some(m) => m,
+        None => return Ok(()),
+    };
+    if input.is_some() {
+        bail!("the modules to merge are given with `--module`");
+    }
+    let mut modules = Vec::new();
+    for module in matches.opt_strs("module") {
+        let (name, path) = match module.find('=') {
+            Some(i) => (&module[..i], &module[i + 1..]),
+            None => bail!("expected `NAME=FILE`, found `{}`", module),
+        };
+        let input = read_input(Some(path))?;
+        modules.push((name.to_string(), parse_input(&input)?.into_owned()));
+    }
+    let modules = modules
+        .iter()
+        .map(|(name, wasm)| (name.as_str(), &wasm[..]))
+        .collect::<Vec<_>>();
+    let wasm = wasm_tools::merge::merge(&modules)?;
+    write_output(matches.opt_str("o").as_deref(), &wasm)
+}
+
+fn names(program: &str, args: &[String]) -> Result<()> {
+    let mut opts = getopts::Options::new();
+    opts.optopt("o", "", "set output file name", "NAME");
+    opts.optmulti(
+        "",
+        "symbol-map",
+        "name the unnamed functions listed in the symbol map FILE",
+        "FILE",
+    );
+    opts.optmulti(
+        "",
+        "func",
+        "name the function INDEX, replacing any previous name",
+        "INDEX=NAME",
+    );
+    let (matches, input) = match parse_args(program, opts, args)? {
+        Some(m) => m,
+        None => return Ok(()),
+    };
+    let input = read_input(input.as_deref())?;
+    let wasm = parse_input(&input)?;
+    let mut names = wasm_tools::names::Names::read(&wasm)?;
+    for func in matches.opt_strs("func") {
+        let mut parts = func.splitn(2, '=');
+        let index = parts.next().unwrap().parse::<u32>();
+        match (index, parts.next()) {
+            (Ok(index), Some(name)) => names.set_function(index, name),
+            _ => bail!("invalid function name `{}`, expected INDEX=NAME", func),
+        };
+    }
+    for path in matches.opt_strs("symbol-map") {
+        let map = std::fs::read_to_string(&path).context(format!("failed to read `{}`", path))?;
+        names
+            .add_symbol_map(&map)
+            .context(format!("failed to parse `{}`", path))?;
+    }
+    let wasm = names.apply(&wasm)?;
+    write_output(matches.opt_str("o").as_deref(), &wasm)
+}
+
+fn optimize(program: &str, args: &[String]) -> Result<()> {
+    let mut opts = getopts::Options::new();
+    opts.optopt("o", "", "set output file name", "NAME");
+    opts.optmulti(
+        "",
+        "skip",
+        "don't run PASS, one of fold-constants, tee-locals or remove-dead-code",
+        "PASS",
+    );
+    opts.optflag(
+        "",
+        "stats",
+        "print how many times each pass rewrote the code to stderr",
+    );
+    let (matches, input) = match parse_args(program, opts, args)? {
+        Some(m) => m,
+        None => return Ok(()),
+    };
+    let mut optimize = wasm_tools::optimize::Optimize::new();
+    for pass in matches.opt_strs("skip") {
+        match pass.as_str() {
+            "fold-constants" => optimize.fold_constants(false),
+            "tee-locals" => optimize.tee_locals(false),
+            "remove-dead-code" => optimize.remove_dead_code(false),
+            _ => bail!("unknown pass `{}`", pass),
+        };
+    }
+    let input = read_input(input.as_deref())?;
+    let wasm = parse_input(&input)?;
+    let (wasm, stats) = optimize.apply_with_stats(&wasm)?;
+    if matches.opt_present("stats") {
+        eprint!("{}", stats);
+    }
+    write_output(matches.opt_str("o").as_deref(), &wasm)
+}
+
+fn instrument(program: &str, args: &[String]) -> Result<()> {
+    let mut opts = getopts::Options::new();
+    opts.optopt("o", "", "set output file name", "NAME");
+    opts.optflag("", "entry", "call `enter` at the start of every function");
+    opts.optflag("", "exit", "call `exit` whenever a function returns");
+    opts.optopt(
+        "",
+        "fuel",
+        "call `fuel` with N after every N instructions",
+        "N",
+    );
+    opts.optopt(
+        "",
+        "module",
+        "import the hooks from MODULE (default: instrument)",
+        "MODULE",
+    );
+    let (matches, input) = match parse_args(program, opts, args)? {
+        Some(m) => m,
+        None => return Ok(()),
+    };
+    let mut instrument = wasm_tools::instrument::Instrument::new();
+    instrument
+        .entry(matches.opt_present("entry"))
+        .exit(matches.opt_present("exit"))
+        .fuel(matches.opt_str("fuel").map(|n| n.parse()).transpose()?);
+    if let Some(module) = matches.opt_str("module") {
+        instrument.module(&module);
+    }
+    let input = read_input(input.as_deref())?;
+    let wasm = parse_input(&input)?;
+    let wasm = instrument.apply(&wasm)?;
+    write_output(matches.opt_str("o").as_deref(), &wasm)
+}
+
+fn lower(program: &str, args: &[String]) -> Result<()> {
+    let mut opts = getopts::Options::new();
+    opts.optopt("o", "", "set output file name", "NAME");
+    opts.optmulti(
+        "",
+        "keep",
+        "don't lower the instructions of PROPOSAL, one of sign-extension, \
+         saturating-float-to-int or bulk-memory",
+        "PROPOSAL",
+    );
+    let (matches, input) = match parse_args(program, opts, args)? {
+        Some(m) => m,
+        None => return Ok(()),
+    };
+    let mut lower = wasm_tools::lower::Lower::new();
+    for proposal in matches.opt_strs("keep") {
+        match proposal.as_str() {
+            "sign-extension" => lower.sign_extension(false),
+            "saturating-float-to-int" => lower.saturating_float_to_int(false),
+            "bulk-memory" => lower.bulk_memory(false),
+            _ => bail!("unknown proposal `{}`", proposal),
+        };
+    }
+    let input = read_input(input.as_deref())?;
+    let wasm = parse_input(&input)?;
+    let wasm = lower.apply(&wasm)?;
+    let usage = wasm_tools::usage::usage(&wasm)?;
+    let mut remaining = usage
+        .proposals
+        .iter()
+        .filter(|(_, count)| **count > 0)
+        .map(|(proposal, _)| *proposal)
+        .chain(usage.required.iter().cloned())
+        .collect::<Vec<_>>();
+    remaining.sort_unstable();
+    remaining.dedup();
+    if !remaining.is_empty() {
+        eprintln!(
+            "warning: the module still uses these proposals: {}",
+            remaining.join(", ")
+        );
+    }
+    write_output(matches.opt_str("o").as_deref(), &wasm)
+}
+
+fn objdump(program: &str, args: &[String]) -> Result<()> {
+    let mut opts = getopts::Options::new();
+    opts.optflag(
+        "",
+        "json",
+        "print a JSON summary of the module's sections, imports, exports, \
+         memories, tables and required proposals instead",
+    );
+    opts.optopt(
+        "",
+        "symbols",
+        "list the functions of the module, named from the symbol map FILE \
+         if the module has no function names",
+        "FILE",
+    );
+    opts.optflag(
+        "",
+        "no-demangle",
+        "don't demangle Rust and C++ symbol names listed with --symbols",
+    );
+    let (matches, input) = match parse_args(program, opts, args)? {
+        Some(m) => m,
+        None => return Ok(()),
+    };
+    let input = read_input(input.as_deref())?;
+    let wasm = parse_input(&input)?;
+    if matches.opt_present("json") {
+        let summary = wasm_tools::summary::summarize(&wasm)?;
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+        return Ok(());
+    }
+    match matches.opt_str("symbols") {
+        Some(path) => {
+            let symbols = wasm_tools::names::read_symbol_map(&path)?;
+            wasm_tools::objdump::objdump_with_symbols(
+                &wasm,
+                &symbols,
+                !matches.opt_present("no-demangle"),
+            )
+        }
+        // Function names are only listed with `--symbols`, so there's
+        // nothing to demangle without it.
+        None if matches.opt_present("no-demangle") => {
+            bail!("`--no-demangle` requires `--symbols`")
+        }
+        None => wasm_tools::objdump::objdump(&wasm),
+    }
+}
+
+fn usage(program: &str, args: &[String]) -> Result<()> {
+    let mut opts = getopts::Options::new();
+    opts.optflag("", "json", "print the counts as JSON instead");
+    let (matches, input) = match parse_args(program, opts, args)? {
+        Some(m) => m,
+        None => return Ok(()),
+    };
+    let input = read_input(input.as_deref())?;
+    let wasm = parse_input(&input)?;
+    let usage = wasm_tools::usage::usage(&wasm)?;
+    if matches.opt_present("json") {
+        println!("{}", serde_json::to_string_pretty(&usage)?);
+    } else {
+        print!("{}", usage);
+    }
+    Ok(())
+}
+
+fn liveness(program: &str, args: &[String]) -> Result<()> {
+    let mut opts = getopts::Options::new();
+    opts.optflag("", "json", "print the report as JSON instead");
+    let (matches, input) = match parse_args(program, opts, args)? {
+        Some(m) => m,
+        None => return Ok(()),
+    };
+    let input = read_input(input.as_deref())?;
+    let wasm = parse_input(&input)?;
+    let functions = wasm_tools::liveness::liveness(&wasm)?;
+    if matches.opt_present("json") {
+        println!("{}", serde_json::to_string_pretty(&functions)?);
+    } else {
+        for function in functions {
+            print!("{}", function);
+        }
+    }
+    Ok(())
+}
+
+fn hash(program: &str, args: &[String]) -> Result<()> {
+    let mut opts = getopts::Options::new();
+    opts.optopt("o", "", "set output file name for --embed", "NAME");
+    opts.optflag("", "embed", "write the module with its hash embedded in it");
+    opts.optflag(
+        "",
+        "verify",
+        "check that the module matches the hash embedded in it",
+    );
+    let (matches, input) = match parse_args(program, opts, args)? {
+        Some(m) => m,
+        None => return Ok(()),
+    };
+    let input = read_input(input.as_deref())?;
+    let wasm = parse_input(&input)?;
+    if matches.opt_present("embed") {
+        let wasm = wasm_tools::integrity::embed(&wasm)?;
+        return write_output(matches.opt_str("o").as_deref(), &wasm);
+    }
+    if matches.opt_present("verify") {
+        return wasm_tools::integrity::verify(&wasm);
+    }
+    let hash = wasm_tools::integrity::hash(&wasm)?;
+    println!("{}", wasm_tools::integrity::hex(&hash));
+    Ok(())
+}
+
+fn sign(program: &str, args: &[String]) -> Result<()> {
+    let mut opts = getopts::Options::new();
+    opts.optopt("o", "", "set output file name", "NAME");
+    opts.optopt(
+        "k",
+        "key",
+        "sign with the 32-byte Ed25519 seed in FILE",
+        "FILE",
+    );
+    opts.optopt("", "key-id", "record ID as the id of the key", "ID");
+    opts.optopt(
+        "",
+        "detached",
+        "write a detached signature to FILE instead of embedding it",
+        "FILE",
+    );
+    opts.optopt(
+        "",
+        "public-key",
+        "also write the public key of the key to FILE",
+        "FILE",
+    );
+    let (matches, input) = match parse_args(program, opts, args)? {
+        Some(m) => m,
+        None => return Ok(()),
+    };
+    let key = match matches.opt_str("key") {
+        Some(key) => key,
+        None => bail!("a key must be given with --key"),
+    };
+    let signer = Ed25519Signer::from_seed(
+        read_key(&key)?,
+        matches.opt_str("key-id").unwrap_or_default().as_bytes(),
+    );
+    if let Some(path) = matches.opt_str("public-key") {
+        write_output(Some(&path), &signer.public_key())?;
+    }
+    let input = read_input(input.as_deref())?;
+    let wasm = parse_input(&input)?;
+    match matches.opt_str("detached") {
+        Some(path) => {
+            let signature = wasm_tools::signature::sign_detached(&wasm, &signer)?;
+            write_output(Some(&path), &signature)
+        }
+        None => {
+            let wasm = wasm_tools::signature::sign(&wasm, &signer)?;
+            write_output(matches.opt_str("o").as_deref(), &wasm)
+        }
+    }
+}
+
+fn verify(program: &str, args: &[String]) -> Result<()> {
+    let mut opts = getopts::Options::new();
+    opts.optopt(
+        "k",
+        "public-key",
+        "verify with the 32-byte Ed25519 public key in FILE",
+        "FILE",
+    );
+    opts.optopt(
+        "",
+        "key-id",
+        "only check signatures recorded with the key id ID",
+        "ID",
+    );
+    opts.optopt(
+        "",
+        "signature",
+        "verify the detached signature in FILE instead",
+        "FILE",
+    );
+    let (matches, input) = match parse_args(program, opts, args)? {
+        Some(m) => m,
+        None => return Ok(()),
+    };
+    let key = match matches.opt_str("public-key") {
+        Some(key) => key,
+        None => bail!("a public key must be given with --public-key"),
+    };
+    let verifier = Ed25519Verifier::new(
+        read_key(&key)?,
+        matches.opt_str("key-id").unwrap_or_default().as_bytes(),
+    );
+    let input = read_input(input.as_deref())?;
+    let wasm = parse_input(&input)?;
+    match matches.opt_str("signature") {
+        Some(path) => {
+            let signature = read_input(Some(&path))?;
+            wasm_tools::signature::verify_detached(&wasm, &signature, &verifier)
+        }
+        None => wasm_tools::signature::verify(&wasm, &verifier),
+    }
+}
+
+/// Reads a 32-byte Ed25519 key from the file `path`.
+fn read_key(path: &str) -> Result<[u8; 32]> {
+    let bytes = read_input(Some(path))?;
+    let mut key = [0; 32];
+    if bytes.len() != key.len() {
+        bail!("`{}` doesn't contain a 32-byte key", path);
+    }
+    key.copy_from_slice(&bytes);
+    Ok(key)
+}
+
+fn capabilities(program: &str, args: &[String]) -> Result<()> {
+    let opts = getopts::Options::new();
+    let (_, input) = match parse_args(program, opts, args)? {
+        Some(m) => m,
+        None => return Ok(()),
+    };
+    let input = read_input(input.as_deref())?;
+    let wasm = parse_input(&input)?;
+    let report = wasm_tools::capabilities::capabilities(&wasm)?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
+
+fn smith(program: &str, args: &[String]) -> Result<()> {
+    let mut opts = getopts::Options::new();
+    opts.optopt("o", "", "set output file name", "NAME");
+    opts.optflag(
+        "t",
+        "ensure-termination",
+        "ensure execution of the generated module always terminates",
+    );
+    opts.optopt(
+        "f",
+        "fuel",
+        "the amount of fuel used with --ensure-termination (default: 100)",
+        "FUEL",
+    );
+    let (matches, input) = match parse_args(program, opts, args)? {
+        Some(m) => m,
+        None => return Ok(()),
+    };
+    let fuel = match matches.opt_str("fuel") {
+        Some(fuel) => fuel.parse()?,
+        None => 100,
+    };
+    let seed = read_input(input.as_deref())?;
+    let mut u = Unstructured::new(&seed);
+    let mut module = match wasm_smith::Module::arbitrary(&mut u) {
+        Ok(module) => module,
+        Err(e) => bail!("failed to generate module: {}", e),
+    };
+    if matches.opt_present("ensure-termination") {
+        module.ensure_termination(fuel);
+    }
+    write_output(matches.opt_str("o").as_deref(), &module.to_bytes())
+}
+
+/// Parses the arguments of a subcommand, which all take at most one input.
+///
+/// Returns `None` if help was requested and has already been printed.
+fn parse_args(
+    program: &str,
+    mut opts: getopts::Options,
+    args: &[String],
+) -> Result<Option<(getopts::Matches, Option<String>)>> {
+    opts.optflag("h", "help", "print this help menu");
+    let matches = opts.parse(args)?;
+    if matches.opt_present("h") {
+        print_usage(program, opts);
+        return Ok(None);
+    }
+    let input = match matches.free.len() {
+        0 => None,
+        1 => Some(matches.free[0].clone()),
+        _ => bail!("more than one input file specified on command line"),
+    };
+    Ok(Some((matches, input)))
+}
+
+fn print_usage(program: &str, opts: getopts::Options) {
+    let brief = format!("Usage: {} [FILE] [options]", program);
+    print!("{}", opts.usage(&brief));
+}
+
+fn print_subcommands(program: &str) {
+    println!("Usage: {} SUBCOMMAND [options]", program);
+    println!();
+    println!("Subcommands:");
+    for (name, desc, _) in SUBCOMMANDS {
+        println!("    {:12}{}", name, desc);
+    }
+}
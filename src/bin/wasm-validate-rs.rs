@@ -5,10 +5,13 @@
 //! validation of all functions after parsing.
 
 use anyhow::{Context, Result};
+#[cfg(not(target_arch = "wasm32"))]
 use rayon::prelude::*;
 use std::env;
+use std::mem;
 use std::time::Instant;
-use wasmparser::{Parser, ValidPayload, Validator, WasmFeatures};
+use wasmparser::{BinaryReaderError, FuncToValidate, FuncValidatorAllocations, FunctionBody};
+use wasmparser::{Parser, ValidPayload, Validator, ValidatorResources, WasmFeatures};
 
 const FEATURES: &[(&str, &str, fn(&mut WasmFeatures) -> &mut bool)] = &[
     ("reference-types", "wasm reference types feature", |f| {
@@ -30,6 +33,14 @@ const FEATURES: &[(&str, &str, fn(&mut WasmFeatures) -> &mut bool)] = &[
         &mut f.multi_memory
     }),
     ("memory64", "wasm memory64 feature", |f| &mut f.memory64),
+    ("wide-arithmetic", "wasm wide-arithmetic feature", |f| {
+        &mut f.wide_arithmetic
+    }),
+    (
+        "function-references",
+        "wasm function-references feature",
+        |f| &mut f.function_references,
+    ),
 ];
 
 fn main() -> Result<()> {
@@ -99,17 +110,49 @@ fn main() -> Result<()> {
     }
     log::info!("module structure validated in {:?}", start.elapsed());
 
-    // After we've validate the entire wasm module we'll use `rayon` to iterate
-    // over all functions in parallel and perform parallel validation of the
-    // input wasm module.
     let start = Instant::now();
-    functions_to_validate
-        .into_par_iter()
-        .try_for_each(|(mut validator, body)| validator.validate(&body))?;
+    validate_functions(functions_to_validate)?;
     log::info!("functions validated in {:?}", start.elapsed());
     Ok(())
 }
 
+type Func<'a> = (FuncToValidate<ValidatorResources>, FunctionBody<'a>);
+
+/// After we've validate the entire wasm module we'll use `rayon` to iterate
+/// over all functions in parallel and perform parallel validation of the
+/// input wasm module. Each worker thread reuses the allocations of its
+/// function validators between functions.
+#[cfg(not(target_arch = "wasm32"))]
+fn validate_functions(functions: Vec<Func<'_>>) -> Result<(), BinaryReaderError> {
+    functions
+        .into_par_iter()
+        .try_for_each_init(FuncValidatorAllocations::default, |allocs, (func, body)| {
+            validate_function(allocs, func, body)
+        })
+}
+
+/// When compiled to WebAssembly there are no threads to use, so functions are
+/// validated one after another instead.
+#[cfg(target_arch = "wasm32")]
+fn validate_functions(functions: Vec<Func<'_>>) -> Result<(), BinaryReaderError> {
+    let mut allocs = FuncValidatorAllocations::default();
+    for (func, body) in functions {
+        validate_function(&mut allocs, func, body)?;
+    }
+    Ok(())
+}
+
+fn validate_function(
+    allocs: &mut FuncValidatorAllocations,
+    func: FuncToValidate<ValidatorResources>,
+    body: FunctionBody<'_>,
+) -> Result<(), BinaryReaderError> {
+    let mut validator = func.into_validator(mem::take(allocs));
+    validator.validate(&body)?;
+    *allocs = validator.into_allocations();
+    Ok(())
+}
+
 fn print_usage(program: &str, opts: getopts::Options) {
     let brief = format!("Usage: {} FILE [options]", program);
     print!("{}", opts.usage(&brief));
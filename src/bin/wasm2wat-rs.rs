@@ -1,10 +1,58 @@
 use anyhow::Context;
 use getopts::Options;
 use std::env;
+use std::io::Write;
+use wasmprinter::ItemKind;
 
 fn main() -> anyhow::Result<()> {
     let mut opts = Options::new();
     opts.optopt("o", "", "set output file name", "NAME");
+    opts.optflag(
+        "",
+        "no-demangle",
+        "don't demangle Rust and C++ symbol names",
+    );
+    opts.optflag(
+        "f",
+        "fold-instructions",
+        "print instructions in the folded s-expression form",
+    );
+    opts.optflag(
+        "",
+        "skeleton",
+        "only print the structure of the module, without function bodies",
+    );
+    opts.optopt(
+        "",
+        "symbols",
+        "name functions from the symbol map FILE if the module has no \
+         function names",
+        "FILE",
+    );
+    opts.optopt(
+        "",
+        "func",
+        "only print the function with this index or name",
+        "FUNC",
+    );
+    opts.optopt(
+        "",
+        "global",
+        "only print the global with this index",
+        "GLOBAL",
+    );
+    opts.optopt(
+        "",
+        "elem",
+        "only print the element segment with this index",
+        "ELEM",
+    );
+    opts.optopt(
+        "",
+        "data",
+        "only print the data segment with this index",
+        "DATA",
+    );
     opts.optflag("h", "help", "print this help menu");
     let matches = opts.parse(env::args_os().skip(1))?;
     if matches.opt_present("h") {
@@ -18,14 +66,37 @@ fn main() -> anyhow::Result<()> {
         std::process::exit(1);
     };
 
-    let wit = wasmprinter::print_file(&input)?;
-    if let Some(output) = matches.opt_str("o") {
-        std::fs::write(&output, wit).context(format!("failed to write `{}`", output))?;
-    } else {
-        println!("{}", wit);
+    let wasm = std::fs::read(&input).context(format!("failed to read `{}`", input))?;
+    let mut printer = wasmprinter::Printer::new();
+    printer.demangle(!matches.opt_present("no-demangle"));
+    printer.fold_instructions(matches.opt_present("f"));
+    printer.skeleton(matches.opt_present("skeleton"));
+    if let Some(path) = matches.opt_str("symbols") {
+        printer.symbol_map(wasm_tools::names::read_symbol_map(&path)?);
     }
-
-    Ok(())
+    let items = [
+        ("func", ItemKind::Func),
+        ("global", ItemKind::Global),
+        ("elem", ItemKind::Elem),
+        ("data", ItemKind::Data),
+    ];
+    let item = items
+        .iter()
+        .find_map(|(name, kind)| Some((*kind, matches.opt_str(name)?)));
+    let output = matches.opt_str("o");
+    let mut dst = wasm_tools::output_writer(output.as_deref())?;
+    match item {
+        Some((kind, item)) => {
+            let text = printer.print_item(&wasm, kind, &item)?;
+            dst.write_all(text.as_bytes())?;
+        }
+        None => printer.print_to(&wasm, &mut dst)?,
+    }
+    // Only stdout gets a trailing newline.
+    if output.is_none() {
+        dst.write_all(b"\n")?;
+    }
+    dst.flush().context("failed to write output")
 }
 
 fn print_usage(opts: Options) {
@@ -6,6 +6,12 @@ fn main() -> anyhow::Result<()> {
     let program = env::args().nth(0).unwrap();
     let mut opts = getopts::Options::new();
     opts.optopt("o", "", "set output file name", "NAME");
+    opts.optflag("v", "verbose", "print an annotated hexdump of the output");
+    opts.optflag(
+        "",
+        "comments",
+        "record comments in a custom section so they're printed again",
+    );
     opts.optflag("h", "help", "print this help menu");
     let matches = opts.parse(env::args_os().skip(1))?;
     if matches.opt_present("h") {
@@ -21,7 +27,16 @@ fn main() -> anyhow::Result<()> {
     };
 
     // Using `wast`, parse this input file into a wasm binary...
-    let binary = wat::parse_file(&input)?;
+    let binary = match (matches.opt_present("v"), matches.opt_present("comments")) {
+        (true, true) => anyhow::bail!("`-v` and `--comments` can't be used together"),
+        (true, false) => {
+            let (binary, dump) = parse_with(input, |module, _| module.encode_annotated())?;
+            print!("{}", dump);
+            binary
+        }
+        (false, true) => parse_with(input, |module, text| module.encode_with_comments(text))?,
+        (false, false) => wat::parse_file(&input)?,
+    };
 
     // ... and if requested, write out that file!
     if let Some(output) = matches.opt_str("o") {
@@ -30,6 +45,25 @@ fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Same as `wat::parse_file`, but encodes the parsed module with `encode`,
+/// which is also given the text of the module.
+fn parse_with<T>(
+    input: &str,
+    encode: impl FnOnce(&mut wast::Module<'_>, &str) -> Result<T, wast::Error>,
+) -> anyhow::Result<T> {
+    let contents = std::fs::read_to_string(input).context(format!("failed to read: {}", input))?;
+    let parse = || -> Result<_, wast::Error> {
+        let buf = wast::parser::ParseBuffer::new(&contents)?;
+        let mut wat = wast::parser::parse::<wast::Wat>(&buf)?;
+        encode(&mut wat.module, &contents)
+    };
+    parse().map_err(|mut e| {
+        e.set_text(&contents);
+        e.set_path(input.as_ref());
+        e.into()
+    })
+}
+
 fn print_usage(program: &str, opts: getopts::Options) {
     let brief = format!("Usage: {} FILE [options]", program);
     print!("{}", opts.usage(&brief));
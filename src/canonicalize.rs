@@ -0,0 +1,209 @@
+//! Rewriting of WebAssembly modules into a canonical binary encoding.
+//!
+//! The binary format leaves producers a fair amount of freedom which doesn't
+//! change the meaning of a module: LEB128 integers may be padded with extra
+//! bytes, custom sections may appear anywhere, and so on. Two modules which
+//! only differ in these ways have the same canonical encoding, so comparing
+//! canonical encodings compares modules semantically.
+
+use crate::sections::{sections, write_str, Section};
+use anyhow::{bail, Context, Result};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use wasmparser::leb128::write_u32;
+use wasmparser::{
+    BinaryReader, CodeSectionReader, CommentAnchor, CommentSectionReader, ImportSectionEntryType,
+    ImportSectionReader,
+};
+
+/// Returns the canonical encoding of the WebAssembly module `wasm`.
+///
+/// The canonical encoding is produced as follows:
+///
+/// * Every LEB128 integer uses the fewest possible bytes.
+/// * Non-custom sections are encoded the same way the `wat` crate encodes
+///   them, which for example merges runs of locals with the same type.
+/// * Custom sections are moved to the end of the module and sorted by their
+///   name and then their contents.
+/// * The `name` section is regenerated from the names that `wasmprinter`
+///   understands, dropping any which it doesn't, such as invalid subsections.
+/// * The `metadata.code.branch_hint` section is regenerated from the hints
+///   that `wasmprinter` prints, and the instructions which the comments of the
+///   `metadata.comments` section refer to are updated for the new encoding of
+///   the code. Every other custom section is copied as is.
+///
+/// Custom sections of modules nested within the module, from the module
+/// linking proposal, are removed.
+///
+/// Canonicalizing a module which is already canonical returns it unchanged.
+///
+/// # Errors
+///
+/// Object files and modules with DWARF debug information can't be
+/// canonicalized, since their `dylink.0`, `linking`, `reloc.*` and `.debug_*`
+/// custom sections depend on where they appear in the module or refer to
+/// offsets within the code, which change when it's encoded again. An error is
+/// returned for modules with any of these sections.
+pub fn canonicalize(wasm: &[u8]) -> Result<Vec<u8>> {
+    for section in sections(wasm)? {
+        if let Some(name) = section.custom_name()? {
+            if depends_on_layout(name) {
+                bail!(
+                    "cannot canonicalize a module with a `{}` custom section",
+                    name
+                );
+            }
+        }
+    }
+
+    // Round tripping through the text format normalizes the encoding of
+    // everything except the custom sections which `wasmprinter` doesn't print.
+    let mut printer = wasmprinter::Printer::new();
+    printer.demangle(false);
+    let text = printer
+        .print(wasm)
+        .context("failed to print module to the text format")?;
+    let encoded = wat::parse_str(&text).context("failed to parse the printed module")?;
+
+    let mut customs = Vec::new();
+    for section in sections(wasm)? {
+        let name = match section.custom_name()? {
+            Some(name) => name,
+            None => continue,
+        };
+        let data = custom_data(&section)?;
+        match name {
+            // These are regenerated by the round trip.
+            "name" | "metadata.code.branch_hint" => {}
+            "metadata.comments" => {
+                let data = remap_comments(data, section.offset, wasm, &encoded)?;
+                customs.push((name, Cow::Owned(data)));
+            }
+            _ => customs.push((name, Cow::Borrowed(data))),
+        }
+    }
+
+    let mut output = encoded[..8].to_vec();
+    for section in sections(&encoded)? {
+        match section.custom_name()? {
+            Some(name) => customs.push((name, Cow::Borrowed(custom_data(&section)?))),
+            None => section.encode(&mut output),
+        }
+    }
+
+    customs.sort();
+    for (name, data) in customs {
+        let mut contents = Vec::new();
+        write_str(&mut contents, name);
+        contents.extend_from_slice(&data);
+        Section {
+            id: 0,
+            contents: &contents,
+            offset: 0,
+        }
+        .encode(&mut output);
+    }
+    Ok(output)
+}
+
+/// Returns the contents of the custom section `section` after its name.
+fn custom_data<'a>(section: &Section<'a>) -> Result<&'a [u8]> {
+    let mut reader = BinaryReader::new(section.contents);
+    reader.read_string()?;
+    Ok(&section.contents[reader.current_position()..])
+}
+
+/// Rewrites the `metadata.comments` section `data` of `original`, found at
+/// `offset`, for `encoded`, the re-encoded module.
+///
+/// Comments before instructions are anchored to the instruction's offset
+/// within its function, which changes when the function is encoded again, so
+/// they're moved to the offset of the same instruction in `encoded`. Comments
+/// which aren't anchored to the start of an instruction are dropped.
+fn remap_comments(data: &[u8], offset: usize, original: &[u8], encoded: &[u8]) -> Result<Vec<u8>> {
+    let old = instruction_offsets(original)?;
+    let new = instruction_offsets(encoded)?;
+    let mut comments = Vec::new();
+    for comment in CommentSectionReader::new(data, offset)? {
+        let comment = comment?;
+        let anchor = match comment.anchor {
+            CommentAnchor::Instruction { func, offset } => {
+                let index = old
+                    .get(&func)
+                    .and_then(|offsets| offsets.binary_search(&offset).ok());
+                match index.and_then(|i| new.get(&func)?.get(i)) {
+                    Some(offset) => CommentAnchor::Instruction {
+                        func,
+                        offset: *offset,
+                    },
+                    None => continue,
+                }
+            }
+            anchor => anchor,
+        };
+        comments.push((anchor, comment.text));
+    }
+
+    let mut output = Vec::new();
+    write_u32(&mut output, comments.len() as u32);
+    for (anchor, text) in comments {
+        match anchor {
+            CommentAnchor::Module => output.push(0x00),
+            CommentAnchor::Item { section, index } => {
+                output.push(0x01);
+                output.push(section);
+                write_u32(&mut output, index);
+            }
+            CommentAnchor::Instruction { func, offset } => {
+                output.push(0x02);
+                write_u32(&mut output, func);
+                write_u32(&mut output, offset);
+            }
+            CommentAnchor::End => output.push(0x03),
+        }
+        write_str(&mut output, text);
+    }
+    Ok(output)
+}
+
+/// Returns the offsets of the instructions of each function defined by
+/// `wasm`, relative to the start of the function's body, by function index.
+fn instruction_offsets(wasm: &[u8]) -> Result<HashMap<u32, Vec<u32>>> {
+    let mut func = 0;
+    let mut ret = HashMap::new();
+    for section in sections(wasm)? {
+        match section.id {
+            2 => {
+                for import in ImportSectionReader::new(section.contents, section.offset)? {
+                    if let ImportSectionEntryType::Function(_) = import?.ty {
+                        func += 1;
+                    }
+                }
+            }
+            10 => {
+                for body in CodeSectionReader::new(section.contents, section.offset)? {
+                    let body = body?;
+                    let mut reader = body.get_operators_reader()?;
+                    let mut offsets = Vec::new();
+                    while !reader.eof() {
+                        offsets.push((reader.original_position() - body.range().start) as u32);
+                        reader.read()?;
+                    }
+                    ret.insert(func, offsets);
+                    func += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(ret)
+}
+
+/// Returns whether the custom section `name` depends on its position in the
+/// module or on the offsets of other sections.
+fn depends_on_layout(name: &str) -> bool {
+    name == "dylink.0"
+        || name == "linking"
+        || name.starts_with("reloc.")
+        || name.starts_with(".debug_")
+}
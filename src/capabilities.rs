@@ -0,0 +1,235 @@
+//! A report of everything a module can do outside of its sandbox, intended
+//! for reviewing modules before running them and for policy engines which
+//! decide whether a module may be run.
+//!
+//! A module can only affect the outside world through its imports, and the
+//! outside world can only observe it through its exports, shared memories and
+//! whatever its start function does when it's instantiated. The report lists
+//! all of these, along with which imported functions the module never calls
+//! and so don't need to be provided.
+//!
+//! ```
+//! use wasm_tools::capabilities::capabilities;
+//!
+//! let wasm = wat::parse_str(r#"
+//!     (module
+//!       (import "wasi_snapshot_preview1" "fd_write"
+//!         (func $fd_write (param i32 i32 i32 i32) (result i32)))
+//!       (import "wasi_snapshot_preview1" "proc_exit" (func (param i32)))
+//!       (global (export "counter") (mut i32) (i32.const 0))
+//!       (func (export "main") (result i32)
+//!         i32.const 1 i32.const 0 i32.const 1 i32.const 8
+//!         call $fd_write))
+//! "#)?;
+//! let report = capabilities(&wasm)?;
+//! let wasi = &report.imports["wasi_snapshot_preview1"];
+//! assert_eq!(wasi[0].called, Some(true));
+//! assert_eq!(wasi[1].called, Some(false));
+//! assert_eq!(report.exported_mutable_globals[0].name, "counter");
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::analysis::CallGraph;
+use crate::summary::import_kind;
+use anyhow::Result;
+#[cfg(feature = "json")]
+use serde::Serialize;
+use std::collections::BTreeMap;
+use wasmparser::{ExternalKind, ImportSectionEntryType, MemoryType, Parser, Payload::*};
+
+/// The capabilities of the outermost module of a WebAssembly binary.
+#[derive(Default, Debug)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct Capabilities {
+    /// Every import of the module, grouped by module name.
+    pub imports: BTreeMap<String, Vec<Import>>,
+    /// Exported globals which the module and the host can both change.
+    pub exported_mutable_globals: Vec<ExportedGlobal>,
+    /// Imported globals which the host can change while the module runs.
+    pub imported_mutable_globals: Vec<String>,
+    /// Memories which may be accessed by other threads at the same time as
+    /// the module.
+    pub shared_memories: Vec<SharedMemory>,
+    /// The names of exported memories, which the host can read and write.
+    pub exported_memories: Vec<String>,
+    /// The names of exported tables, which the host can read and write.
+    pub exported_tables: Vec<String>,
+    /// The function which runs when the module is instantiated, if any.
+    pub start: Option<Start>,
+}
+
+/// An import of a module.
+#[derive(Debug)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct Import {
+    /// The field name of this import, absent for module linking's
+    /// single-level imports.
+    pub field: Option<String>,
+    /// The kind of item imported, such as `"func"` or `"memory"`.
+    pub kind: &'static str,
+    /// For imported functions, whether the module may call the function,
+    /// directly or indirectly, from an export or its start function. Imported
+    /// functions which are never called can be left out of the host's
+    /// implementation, or removed from the module.
+    #[cfg_attr(feature = "json", serde(skip_serializing_if = "Option::is_none"))]
+    pub called: Option<bool>,
+}
+
+/// An exported mutable global.
+#[derive(Debug)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct ExportedGlobal {
+    /// The name of the export.
+    pub name: String,
+    /// The index of the global in the global index space.
+    pub index: u32,
+}
+
+/// A shared memory.
+#[derive(Debug)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct SharedMemory {
+    /// The index of the memory in the memory index space.
+    pub index: u32,
+    /// The name of the import of the memory, such as `env.memory`, if it's
+    /// imported.
+    pub import: Option<String>,
+    /// The names the memory is exported as.
+    pub exports: Vec<String>,
+}
+
+/// The start function of a module.
+#[derive(Debug)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct Start {
+    /// The index of the function in the function index space.
+    pub index: u32,
+    /// Whether the start function is imported, in which case instantiating
+    /// the module calls into the host before any export is called.
+    pub imported: bool,
+}
+
+/// Reports the capabilities of the WebAssembly binary `wasm`.
+///
+/// Returns an error if the binary is malformed or refers to items which
+/// don't exist. Modules using the module linking proposal aren't supported.
+pub fn capabilities(wasm: &[u8]) -> Result<Capabilities> {
+    let reachable = CallGraph::new(wasm)?.reachable();
+    let mut report = Capabilities::default();
+    let mut imported_functions = 0;
+    let mut global_mutability = Vec::new();
+    let mut memories = Vec::new();
+    let mut exported_memories = BTreeMap::new();
+    let mut depth = 0;
+    for payload in Parser::new(0).parse_all(wasm) {
+        let payload = payload?;
+        match &payload {
+            Version { .. } => depth += 1,
+            End => depth -= 1,
+            _ => {}
+        }
+        if depth != 1 {
+            continue;
+        }
+        match payload {
+            ImportSection(s) => {
+                for import in s {
+                    let import = import?;
+                    let mut called = None;
+                    match import.ty {
+                        ImportSectionEntryType::Function(_) => {
+                            called = Some(reachable.contains(&imported_functions));
+                            imported_functions += 1;
+                        }
+                        ImportSectionEntryType::Global(ty) => {
+                            global_mutability.push(ty.mutable);
+                            if ty.mutable {
+                                report
+                                    .imported_mutable_globals
+                                    .push(import_name(import.module, import.field));
+                            }
+                        }
+                        ImportSectionEntryType::Memory(ty) => memories
+                            .push((shared(ty), Some(import_name(import.module, import.field)))),
+                        _ => {}
+                    }
+                    report
+                        .imports
+                        .entry(import.module.to_string())
+                        .or_default()
+                        .push(Import {
+                            field: import.field.map(|s| s.to_string()),
+                            kind: import_kind(&import.ty),
+                            called,
+                        });
+                }
+            }
+            GlobalSection(s) => {
+                for global in s {
+                    global_mutability.push(global?.ty.mutable);
+                }
+            }
+            MemorySection(s) => {
+                for ty in s {
+                    memories.push((shared(ty?), None));
+                }
+            }
+            ExportSection(s) => {
+                for export in s {
+                    let export = export?;
+                    let name = export.field.to_string();
+                    match export.kind {
+                        ExternalKind::Global
+                            if global_mutability.get(export.index as usize) == Some(&true) =>
+                        {
+                            report.exported_mutable_globals.push(ExportedGlobal {
+                                name,
+                                index: export.index,
+                            })
+                        }
+                        ExternalKind::Memory => {
+                            exported_memories
+                                .entry(export.index)
+                                .or_insert_with(Vec::new)
+                                .push(name.clone());
+                            report.exported_memories.push(name);
+                        }
+                        ExternalKind::Table => report.exported_tables.push(name),
+                        _ => {}
+                    }
+                }
+            }
+            StartSection { func, .. } => {
+                report.start = Some(Start {
+                    index: func,
+                    imported: func < imported_functions,
+                })
+            }
+            _ => {}
+        }
+    }
+    for (index, (shared, import)) in memories.into_iter().enumerate() {
+        let index = index as u32;
+        if shared {
+            report.shared_memories.push(SharedMemory {
+                index,
+                import,
+                exports: exported_memories.remove(&index).unwrap_or_default(),
+            });
+        }
+    }
+    Ok(report)
+}
+
+fn shared(ty: MemoryType) -> bool {
+    match ty {
+        MemoryType::M32 { shared, .. } | MemoryType::M64 { shared, .. } => shared,
+    }
+}
+
+fn import_name(module: &str, field: Option<&str>) -> String {
+    match field {
+        Some(field) => format!("{}.{}", module, field),
+        None => module.to_string(),
+    }
+}
@@ -0,0 +1,326 @@
+//! Control-flow graphs of function bodies.
+//!
+//! WebAssembly's control flow is structured, but most analyses want basic
+//! blocks and edges instead of nested `block`s and relative branch depths.
+//! [`Cfg::new`] derives them from the operators of a function body.
+//!
+//! ```
+//! use wasm_tools::cfg::Cfg;
+//! use wasm_tools::functions::extract;
+//!
+//! let wasm = wat::parse_str(r#"
+//!     (module
+//!       (func (param i32) (result i32)
+//!         local.get 0
+//!         if (result i32)
+//!           i32.const 1
+//!         else
+//!           i32.const 2
+//!         end))
+//! "#)?;
+//! let func = extract(&wasm, 0)?;
+//! let cfg = Cfg::new(&wasmparser::FunctionBody::new(func.offset, func.body))?;
+//! // The entry, both arms of the `if`, and the block after it.
+//! assert_eq!(cfg.blocks().len(), 4);
+//! assert_eq!(cfg.blocks()[0].successors, [1, 2]);
+//! assert!(cfg.blocks()[3].exits);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::{bail, Result};
+use wasmparser::{FunctionBody, Operator};
+
+/// The control-flow graph of a function body.
+///
+/// Blocks are ordered by their offset and the first one is the entry of the
+/// function. Code which is unreachable because it follows an unconditional
+/// branch is in blocks without predecessors.
+#[derive(Debug, Clone)]
+pub struct Cfg {
+    blocks: Vec<BasicBlock>,
+}
+
+/// A maximal sequence of instructions which execute one after the other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    /// The offset of the first instruction of the block in the module.
+    pub start: usize,
+    /// The offset just past the last instruction of the block.
+    pub end: usize,
+    /// The indices of the blocks which control may continue to.
+    pub successors: Vec<usize>,
+    /// The indices of the blocks which may continue to this block.
+    pub predecessors: Vec<usize>,
+    /// Whether the function may return at the end of this block, with
+    /// `return`, a tail call, a branch to the function's label or its final
+    /// `end`.
+    pub exits: bool,
+}
+
+impl Cfg {
+    /// Builds the control-flow graph of `body`.
+    ///
+    /// Blocks ending with `unreachable`, `throw` or `rethrow` have no
+    /// successors. Exceptions are approximated: the handlers of a `try` are
+    /// successors of the block in which the `try` starts.
+    ///
+    /// Returns an error if the body is malformed or if a branch targets a
+    /// label which doesn't exist; the body is otherwise assumed to be valid.
+    pub fn new(body: &FunctionBody<'_>) -> Result<Cfg> {
+        let mut builder = Builder {
+            blocks: Vec::new(),
+            frames: Vec::new(),
+            current: None,
+        };
+        let mut reader = body.get_operators_reader()?;
+        let entry = builder.new_block(Some(reader.original_position()));
+        builder.current = Some(entry);
+        builder.frames.push(Frame {
+            kind: FrameKind::Function,
+            end: None,
+            pending: None,
+        });
+
+        while !builder.frames.is_empty() {
+            let pos = reader.original_position();
+            let op = reader.read()?;
+            let next = reader.original_position();
+            let structural = matches!(
+                op,
+                Operator::End | Operator::Else | Operator::Catch { .. } | Operator::Unwind
+            );
+            if builder.current.is_none() && !structural {
+                let block = builder.new_block(Some(pos));
+                builder.current = Some(block);
+            }
+            match op {
+                Operator::Block { .. } | Operator::Let { .. } => {
+                    builder.push(FrameKind::Block, None)
+                }
+                Operator::Loop { .. } => {
+                    let header = builder.new_block(Some(next));
+                    builder.goto(header, next);
+                    builder.push(FrameKind::Loop(header), None);
+                }
+                Operator::If { .. } => {
+                    let from = builder.current;
+                    let then = builder.new_block(Some(next));
+                    builder.edge_from(from, then);
+                    builder.finish(next);
+                    builder.current = Some(then);
+                    builder.push(FrameKind::If, from);
+                }
+                Operator::Try { .. } => {
+                    let from = builder.current;
+                    builder.push(FrameKind::Try(from), None);
+                }
+                Operator::Else | Operator::Catch { .. } | Operator::Unwind => {
+                    builder.branch(0)?;
+                    builder.finish(next);
+                    let frame = builder.frames.last_mut().unwrap();
+                    let from = match frame.kind {
+                        FrameKind::Try(from) => from,
+                        _ => frame.pending.take(),
+                    };
+                    let handler = builder.new_block(Some(next));
+                    builder.edge_from(from, handler);
+                    builder.current = Some(handler);
+                }
+                Operator::End => {
+                    let frame = builder.frames.pop().unwrap();
+                    if let FrameKind::Function = frame.kind {
+                        if let Some(current) = builder.current {
+                            builder.blocks[current].exits = true;
+                        }
+                        builder.finish(next);
+                        continue;
+                    }
+                    if frame.end.is_none() && frame.pending.is_none() {
+                        // Nothing branches past this `end`, so the block
+                        // simply continues.
+                        continue;
+                    }
+                    let end = match frame.end {
+                        Some(end) => end,
+                        None => builder.new_block(None),
+                    };
+                    builder.blocks[end].start = next;
+                    builder.edge_from(frame.pending, end);
+                    builder.goto(end, next);
+                }
+                Operator::Br { relative_depth } => {
+                    builder.branch(relative_depth)?;
+                    builder.finish(next);
+                }
+                Operator::BrIf { relative_depth } => {
+                    builder.branch(relative_depth)?;
+                    let fallthrough = builder.new_block(Some(next));
+                    builder.goto(fallthrough, next);
+                }
+                Operator::BrTable { table } => {
+                    for target in table.targets() {
+                        builder.branch(target?.0)?;
+                    }
+                    builder.finish(next);
+                }
+                Operator::Return
+                | Operator::ReturnCall { .. }
+                | Operator::ReturnCallIndirect { .. } => {
+                    if let Some(current) = builder.current {
+                        builder.blocks[current].exits = true;
+                    }
+                    builder.finish(next);
+                }
+                Operator::Unreachable | Operator::Throw { .. } | Operator::Rethrow { .. } => {
+                    builder.finish(next)
+                }
+                _ => {}
+            }
+        }
+        if !reader.eof() {
+            bail!("function body has operators after its final `end`");
+        }
+        Ok(builder.into_cfg())
+    }
+
+    /// Returns the basic blocks of the function.
+    pub fn blocks(&self) -> &[BasicBlock] {
+        &self.blocks
+    }
+
+    /// Returns the index of the block containing the instruction at
+    /// `offset`, if any.
+    pub fn block_at(&self, offset: usize) -> Option<usize> {
+        let i = self.blocks.partition_point(|b| b.start <= offset);
+        let i = i.checked_sub(1)?;
+        if offset < self.blocks[i].end {
+            Some(i)
+        } else {
+            None
+        }
+    }
+}
+
+struct Frame {
+    kind: FrameKind,
+    /// The block following the frame's `end`, once something branches there.
+    end: Option<usize>,
+    /// The block ending with an `if` which has no `else` yet.
+    pending: Option<usize>,
+}
+
+enum FrameKind {
+    Function,
+    Block,
+    /// A loop, whose label is its header block.
+    Loop(usize),
+    If,
+    /// A `try`, and the block it starts in, which its handlers follow.
+    Try(Option<usize>),
+}
+
+struct Builder {
+    blocks: Vec<BasicBlock>,
+    frames: Vec<Frame>,
+    /// The block being built, or `None` in unreachable code.
+    current: Option<usize>,
+}
+
+impl Builder {
+    fn new_block(&mut self, start: Option<usize>) -> usize {
+        self.blocks.push(BasicBlock {
+            start: start.unwrap_or(0),
+            end: 0,
+            successors: Vec::new(),
+            predecessors: Vec::new(),
+            exits: false,
+        });
+        self.blocks.len() - 1
+    }
+
+    fn push(&mut self, kind: FrameKind, pending: Option<usize>) {
+        self.frames.push(Frame {
+            kind,
+            end: None,
+            pending,
+        });
+    }
+
+    fn edge_from(&mut self, from: Option<usize>, to: usize) {
+        if let Some(from) = from {
+            if !self.blocks[from].successors.contains(&to) {
+                self.blocks[from].successors.push(to);
+            }
+        }
+    }
+
+    /// Ends the current block at `end`, after which code is unreachable.
+    fn finish(&mut self, end: usize) {
+        if let Some(current) = self.current.take() {
+            self.blocks[current].end = end;
+        }
+    }
+
+    /// Ends the current block at `end`, falling through to `block`.
+    fn goto(&mut self, block: usize, end: usize) {
+        self.edge_from(self.current, block);
+        self.finish(end);
+        self.current = Some(block);
+    }
+
+    /// Adds an edge from the current block to the label `depth`.
+    fn branch(&mut self, depth: u32) -> Result<()> {
+        let current = match self.current {
+            Some(current) => current,
+            None => return Ok(()),
+        };
+        let index = match (self.frames.len() as u32).checked_sub(depth + 1) {
+            Some(index) => index as usize,
+            None => bail!("branch to label {} which doesn't exist", depth),
+        };
+        let target = match self.frames[index].kind {
+            FrameKind::Function => {
+                self.blocks[current].exits = true;
+                return Ok(());
+            }
+            FrameKind::Loop(header) => header,
+            _ => match self.frames[index].end {
+                Some(end) => end,
+                None => {
+                    let end = self.new_block(None);
+                    self.frames[index].end = Some(end);
+                    end
+                }
+            },
+        };
+        self.edge_from(Some(current), target);
+        Ok(())
+    }
+
+    /// Sorts the blocks by offset and fills in their predecessors.
+    fn into_cfg(self) -> Cfg {
+        let mut order = (0..self.blocks.len()).collect::<Vec<_>>();
+        order.sort_by_key(|i| self.blocks[*i].start);
+        let mut renumber = vec![0; self.blocks.len()];
+        for (new, old) in order.iter().enumerate() {
+            renumber[*old] = new;
+        }
+        let mut blocks = order
+            .iter()
+            .map(|i| {
+                let mut block = self.blocks[*i].clone();
+                for s in block.successors.iter_mut() {
+                    *s = renumber[*s];
+                }
+                block.successors.sort_unstable();
+                block
+            })
+            .collect::<Vec<_>>();
+        for i in 0..blocks.len() {
+            for s in blocks[i].successors.clone() {
+                blocks[s].predecessors.push(i);
+            }
+        }
+        Cfg { blocks }
+    }
+}
@@ -0,0 +1,325 @@
+//! Extraction and replacement of individual function bodies in a binary
+//! module.
+//!
+//! Re-encoding a whole module to change one function is wasteful when the
+//! rest of the module is left untouched, so these functions work directly on
+//! the binary: every section other than the code section is copied verbatim,
+//! and within the code section only the replaced body and the sizes enclosing
+//! it change.
+
+use crate::sections::{sections, Section};
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeSet;
+use wasmparser::leb128::write_u32;
+use wasmparser::{
+    CodeSectionReader, FuncType, FunctionSectionReader, ImportSectionEntryType,
+    ImportSectionReader, OperatorsReader, SectionReader, TypeDef, TypeOrFuncType,
+    TypeSectionReader, VisitOperator,
+};
+
+/// A function body extracted from a module with [`extract`].
+#[derive(Debug)]
+pub struct Function<'a> {
+    /// The index of the function in the function index space, which includes
+    /// imported functions.
+    pub index: u32,
+    /// The index of the function's type in the type section.
+    pub type_index: u32,
+    /// The function's type.
+    pub ty: FuncType,
+    /// The encoded body of the function: its locals followed by its
+    /// instructions, without the leading size.
+    pub body: &'a [u8],
+    /// The offset of `body` within the module.
+    pub offset: usize,
+    /// The items of the module which the body refers to.
+    pub references: References,
+}
+
+/// The indices of the items of a module which a function body refers to.
+#[derive(Debug, Default)]
+pub struct References {
+    /// Functions which are called or referenced with `ref.func`.
+    pub functions: BTreeSet<u32>,
+    /// Types used by `call_indirect` and by blocks.
+    pub types: BTreeSet<u32>,
+    /// Tables which are accessed.
+    pub tables: BTreeSet<u32>,
+    /// Memories which are accessed.
+    pub memories: BTreeSet<u32>,
+    /// Globals which are read or written.
+    pub globals: BTreeSet<u32>,
+    /// Events which are thrown or caught.
+    pub events: BTreeSet<u32>,
+    /// Element segments used by `table.init` and `elem.drop`.
+    pub elements: BTreeSet<u32>,
+    /// Data segments used by `memory.init` and `data.drop`.
+    pub data: BTreeSet<u32>,
+}
+
+/// Extracts the body of the function `index` from the module `wasm`.
+///
+/// Returns an error if `index` is out of bounds or refers to an imported
+/// function. Modules using the module linking proposal aren't supported.
+pub fn extract(wasm: &[u8], index: u32) -> Result<Function<'_>> {
+    let module = Module::parse(wasm)?;
+    let defined = module.defined_index(index)?;
+    let type_index = module.functions[defined as usize];
+    let ty = match module.types.get(type_index as usize) {
+        Some(TypeDef::Func(ty)) => ty.clone(),
+        _ => bail!("function {} has an invalid type {}", index, type_index),
+    };
+
+    let code = module.code.context("module has no code section")?;
+    let mut reader = CodeSectionReader::new(code.contents, code.offset)?;
+    let mut body = None;
+    for _ in 0..=defined {
+        body = Some(reader.read()?);
+    }
+    let body = body.unwrap();
+    let range = body.range();
+
+    let mut references = References::default();
+    references.add(body.get_operators_reader()?)?;
+
+    Ok(Function {
+        index,
+        type_index,
+        ty,
+        body: &wasm[range.start..range.end],
+        offset: range.start,
+        references,
+    })
+}
+
+/// Returns a copy of the module `wasm` in which the body of the function
+/// `index` is replaced with `body`.
+///
+/// The `body` is the encoding of the function's locals followed by its
+/// instructions, in the same form as [`Function::body`]. It isn't validated,
+/// so callers should validate the result if `body` isn't trusted.
+///
+/// Offsets recorded in custom sections, such as DWARF or relocations, are not
+/// updated and will be stale for everything after the replaced body.
+pub fn replace_body(wasm: &[u8], index: u32, body: &[u8]) -> Result<Vec<u8>> {
+    let module = Module::parse(wasm)?;
+    let defined = module.defined_index(index)?;
+    let code = module.code.context("module has no code section")?;
+
+    let mut reader = CodeSectionReader::new(code.contents, code.offset)?;
+    let mut contents = Vec::new();
+    write_u32(&mut contents, reader.get_count());
+    for i in 0..reader.get_count() {
+        let range = reader.read()?.range();
+        let body = if i == defined {
+            body
+        } else {
+            &wasm[range.start..range.end]
+        };
+        write_u32(&mut contents, body.len() as u32);
+        contents.extend_from_slice(body);
+    }
+    if !reader.eof() {
+        bail!("code section has trailing bytes");
+    }
+
+    let mut output = wasm[..8].to_vec();
+    for section in module.sections {
+        if section.id == CODE {
+            Section {
+                id: CODE,
+                contents: &contents,
+                offset: 0,
+            }
+            .encode(&mut output);
+        } else {
+            section.encode(&mut output);
+        }
+    }
+    Ok(output)
+}
+
+const TYPE: u8 = 1;
+const IMPORT: u8 = 2;
+const FUNCTION: u8 = 3;
+const CODE: u8 = 10;
+const MODULE: u8 = 14;
+const INSTANCE: u8 = 15;
+const ALIAS: u8 = 16;
+const MODULE_CODE: u8 = 17;
+
+/// The parts of a module needed to locate function bodies.
+struct Module<'a> {
+    sections: Vec<Section<'a>>,
+    code: Option<Section<'a>>,
+    types: Vec<TypeDef<'a>>,
+    imported_functions: u32,
+    functions: Vec<u32>,
+}
+
+impl<'a> Module<'a> {
+    fn parse(wasm: &'a [u8]) -> Result<Module<'a>> {
+        let mut module = Module {
+            sections: sections(wasm)?,
+            code: None,
+            types: Vec::new(),
+            imported_functions: 0,
+            functions: Vec::new(),
+        };
+        for section in module.sections.iter() {
+            match section.id {
+                TYPE => {
+                    for ty in TypeSectionReader::new(section.contents, section.offset)? {
+                        module.types.push(ty?);
+                    }
+                }
+                IMPORT => {
+                    for import in ImportSectionReader::new(section.contents, section.offset)? {
+                        if let ImportSectionEntryType::Function(_) = import?.ty {
+                            module.imported_functions += 1;
+                        }
+                    }
+                }
+                FUNCTION => {
+                    for ty in FunctionSectionReader::new(section.contents, section.offset)? {
+                        module.functions.push(ty?);
+                    }
+                }
+                CODE => {
+                    module.code = Some(Section {
+                        id: CODE,
+                        contents: section.contents,
+                        offset: section.offset,
+                    })
+                }
+                MODULE | INSTANCE | ALIAS | MODULE_CODE => {
+                    bail!("modules using the module linking proposal are not supported")
+                }
+                _ => {}
+            }
+        }
+        Ok(module)
+    }
+
+    /// Returns the index of function `index` among the functions defined,
+    /// rather than imported, by this module.
+    fn defined_index(&self, index: u32) -> Result<u32> {
+        if index < self.imported_functions {
+            bail!("function {} is imported and has no body", index);
+        }
+        let defined = index - self.imported_functions;
+        if defined as usize >= self.functions.len() {
+            bail!("function index {} out of bounds", index);
+        }
+        Ok(defined)
+    }
+}
+
+// Records the items referred to by an operator's payload based on the names
+// of its fields, which are consistent across all operators except for the few
+// listed explicitly here.
+macro_rules! reference {
+    (BrTable, table, $r:ident, $v:ident) => {
+        let _ = $v;
+    };
+    (CallIndirect, index, $r:ident, $v:ident) => {
+        $r.types.insert($v);
+    };
+    (ReturnCallIndirect, index, $r:ident, $v:ident) => {
+        $r.types.insert($v);
+    };
+    (Catch, index, $r:ident, $v:ident) => {
+        $r.events.insert($v);
+    };
+    (Throw, index, $r:ident, $v:ident) => {
+        $r.events.insert($v);
+    };
+    (TableInit, segment, $r:ident, $v:ident) => {
+        $r.elements.insert($v);
+    };
+    (ElemDrop, segment, $r:ident, $v:ident) => {
+        $r.elements.insert($v);
+    };
+    ($op:ident, segment, $r:ident, $v:ident) => {
+        $r.data.insert($v);
+    };
+    ($op:ident, function_index, $r:ident, $v:ident) => {
+        $r.functions.insert($v);
+    };
+    ($op:ident, global_index, $r:ident, $v:ident) => {
+        $r.globals.insert($v);
+    };
+    ($op:ident, table, $r:ident, $v:ident) => {
+        $r.tables.insert($v);
+    };
+    ($op:ident, table_index, $r:ident, $v:ident) => {
+        $r.tables.insert($v);
+    };
+    ($op:ident, src_table, $r:ident, $v:ident) => {
+        $r.tables.insert($v);
+    };
+    ($op:ident, dst_table, $r:ident, $v:ident) => {
+        $r.tables.insert($v);
+    };
+    ($op:ident, mem, $r:ident, $v:ident) => {
+        $r.memories.insert($v);
+    };
+    (MemoryCopy, src, $r:ident, $v:ident) => {
+        $r.memories.insert($v);
+    };
+    (MemoryCopy, dst, $r:ident, $v:ident) => {
+        $r.memories.insert($v);
+    };
+    ($op:ident, memarg, $r:ident, $v:ident) => {
+        $r.memories.insert($v.memory);
+    };
+    (Block, ty, $r:ident, $v:ident) => {
+        $r.block_type($v);
+    };
+    (Loop, ty, $r:ident, $v:ident) => {
+        $r.block_type($v);
+    };
+    (If, ty, $r:ident, $v:ident) => {
+        $r.block_type($v);
+    };
+    (Try, ty, $r:ident, $v:ident) => {
+        $r.block_type($v);
+    };
+    (Let, ty, $r:ident, $v:ident) => {
+        $r.block_type($v);
+    };
+    ($op:ident, $arg:ident, $r:ident, $v:ident) => {
+        let _ = $v;
+    };
+}
+
+macro_rules! define_visit {
+    ($($op:ident $({ $($arg:ident: $argty:ty),* })? => $visit:ident)*) => {
+        $(
+            fn $visit(&mut self, _offset: usize $($(, $arg: $argty)*)?) {
+                $($(reference!($op, $arg, self, $arg);)*)?
+            }
+        )*
+    };
+}
+
+impl<'a> VisitOperator<'a> for References {
+    type Output = ();
+    wasmparser::for_each_operator!(define_visit);
+}
+
+impl References {
+    /// Records the items referred to by the operators in `reader`.
+    pub(crate) fn add(&mut self, mut reader: OperatorsReader<'_>) -> Result<()> {
+        while !reader.eof() {
+            reader.visit_operator(self)?;
+        }
+        Ok(())
+    }
+
+    fn block_type(&mut self, ty: TypeOrFuncType) {
+        if let TypeOrFuncType::FuncType(i) = ty {
+            self.types.insert(i);
+        }
+    }
+}
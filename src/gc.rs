@@ -0,0 +1,841 @@
+//! Removal of unused items from a binary module.
+//!
+//! This is similar to `wasm-gc` or `wasm-opt --dce`: everything reachable from
+//! the module's exports, its start function, and its active data and element
+//! segments is kept, and everything else is removed.
+//!
+//! ```
+//! let wasm = wat::parse_str(r#"
+//!     (module
+//!       (import "env" "unused" (func))
+//!       (func $used (result i32) i32.const 1)
+//!       (func $unused)
+//!       (func (export "main") (result i32) call $used))
+//! "#)?;
+//! let wasm = wasm_tools::gc::gc(&wasm)?;
+//! let text = wasmprinter::print_bytes(&wasm)?;
+//! assert!(!text.contains("unused"));
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::functions::References;
+use crate::sections::{sections, write_str, Section};
+use anyhow::{bail, Result};
+use wasmparser::leb128::{write_i64, write_u32};
+use wasmparser::{
+    BinaryReader, CodeSectionReader, DataKind, DataSectionReader, ElementItem, ElementKind,
+    ElementSectionReader, EventSectionReader, ExportSectionReader, ExternalKind, FunctionBody,
+    FunctionSectionReader, GlobalSectionReader, ImportSectionEntryType, ImportSectionReader,
+    InitExpr, Operator, OperatorsReader, Type, TypeOrFuncType, TypeSectionReader,
+};
+
+const CUSTOM: u8 = 0;
+const TYPE: u8 = 1;
+const IMPORT: u8 = 2;
+const FUNCTION: u8 = 3;
+const GLOBAL: u8 = 6;
+const EXPORT: u8 = 7;
+const START: u8 = 8;
+const ELEMENT: u8 = 9;
+const CODE: u8 = 10;
+const DATA: u8 = 11;
+const DATA_COUNT: u8 = 12;
+const EVENT: u8 = 13;
+const MODULE: u8 = 14;
+const INSTANCE: u8 = 15;
+const ALIAS: u8 = 16;
+const MODULE_CODE: u8 = 17;
+
+/// Removes the functions, types, globals and passive data segments of the
+/// module `wasm` which are unreachable, renumbering the items which remain.
+///
+/// Items are considered reachable if they are exported, are the start
+/// function, or are referred to by an active data segment, by any element
+/// segment, or by another reachable item. Imported functions and globals are
+/// removed too if they're unreachable. Tables, memories, events and element
+/// segments are always kept.
+///
+/// Function and local names in the `name` section are updated, but its other
+/// subsections are removed. All other custom sections are copied verbatim,
+/// so any indices or offsets they contain will be stale.
+///
+/// Modules using the module linking proposal aren't supported.
+pub fn gc(wasm: &[u8]) -> Result<Vec<u8>> {
+    let module = Module::parse(wasm)?;
+    let live = module.live()?;
+    let map = Map {
+        types: renumber(&live.types),
+        funcs: renumber(&live.funcs),
+        globals: renumber(&live.globals),
+        data: renumber(&live.data),
+        tables: None,
+        events: None,
+        elements: None,
+    };
+
+    let mut output = wasm[..8].to_vec();
+    for section in module.sections.iter() {
+        let mut contents = Vec::new();
+        match section.id {
+            TYPE => {
+                let mut reader = TypeSectionReader::new(section.contents, section.offset)?;
+                write_u32(&mut contents, map.types.count);
+                for i in 0..reader.get_count() {
+                    let start = reader.original_position();
+                    reader.read()?;
+                    if live.types[i as usize] {
+                        contents.extend_from_slice(&wasm[start..reader.original_position()]);
+                    }
+                }
+            }
+            IMPORT => {
+                let mut reader = ImportSectionReader::new(section.contents, section.offset)?;
+                let mut entries = Vec::new();
+                let mut count = 0;
+                let mut func = 0;
+                let mut global = 0;
+                for _ in 0..reader.get_count() {
+                    let start = reader.original_position();
+                    let import = reader.read()?;
+                    let entry = &wasm[start..reader.original_position()];
+                    let mut entry_reader = BinaryReader::new(entry);
+                    entry_reader.read_string()?;
+                    entry_reader.read_string()?;
+                    if import.field.is_none() {
+                        entry_reader.read_u8()?;
+                    }
+                    let names = &entry[..entry_reader.current_position()];
+                    match import.ty {
+                        ImportSectionEntryType::Function(ty) => {
+                            func += 1;
+                            if !live.funcs[func - 1] {
+                                continue;
+                            }
+                            entries.extend_from_slice(names);
+                            entries.push(0x00);
+                            write_u32(&mut entries, map.types.get(ty));
+                        }
+                        ImportSectionEntryType::Global(_) => {
+                            global += 1;
+                            if !live.globals[global - 1] {
+                                continue;
+                            }
+                            entries.extend_from_slice(entry);
+                        }
+                        ImportSectionEntryType::Event(ty) => {
+                            entries.extend_from_slice(names);
+                            entries.extend_from_slice(&[0x04, 0x00]);
+                            write_u32(&mut entries, map.types.get(ty.type_index));
+                        }
+                        _ => entries.extend_from_slice(entry),
+                    }
+                    count += 1;
+                }
+                write_u32(&mut contents, count);
+                contents.extend_from_slice(&entries);
+            }
+            FUNCTION => {
+                write_u32(&mut contents, live.defined_funcs(&module));
+                for i in module.imported_funcs as usize..module.func_types.len() {
+                    if live.funcs[i] {
+                        write_u32(&mut contents, map.types.get(module.func_types[i]));
+                    }
+                }
+            }
+            GLOBAL => {
+                let mut reader = GlobalSectionReader::new(section.contents, section.offset)?;
+                let imported = module.imported_globals as usize;
+                let count = live.globals[imported..].iter().filter(|l| **l).count();
+                write_u32(&mut contents, count as u32);
+                for i in 0..reader.get_count() as usize {
+                    let start = reader.original_position();
+                    let global = reader.read()?;
+                    if !live.globals[imported + i] {
+                        continue;
+                    }
+                    let expr = global.init_expr.get_operators_reader();
+                    contents.extend_from_slice(&wasm[start..expr.original_position()]);
+                    map.expr(wasm, expr, &mut contents)?;
+                }
+            }
+            EVENT => {
+                let mut reader = EventSectionReader::new(section.contents, section.offset)?;
+                write_u32(&mut contents, reader.get_count());
+                for _ in 0..reader.get_count() {
+                    contents.push(0x00);
+                    write_u32(&mut contents, map.types.get(reader.read()?.type_index));
+                }
+            }
+            EXPORT => {
+                let mut reader = ExportSectionReader::new(section.contents, section.offset)?;
+                write_u32(&mut contents, reader.get_count());
+                for _ in 0..reader.get_count() {
+                    map.export(wasm, &mut reader, &mut contents)?;
+                }
+            }
+            START => {
+                let func = BinaryReader::new(section.contents).read_var_u32()?;
+                write_u32(&mut contents, map.funcs.get(func));
+            }
+            ELEMENT => {
+                let mut reader = ElementSectionReader::new(section.contents, section.offset)?;
+                write_u32(&mut contents, reader.get_count());
+                for _ in 0..reader.get_count() {
+                    map.element(wasm, &mut reader, &mut contents)?;
+                }
+            }
+            DATA_COUNT => {
+                write_u32(&mut contents, map.data.count);
+            }
+            CODE => {
+                write_u32(&mut contents, live.defined_funcs(&module));
+                for (i, body) in module.bodies.iter().enumerate() {
+                    if !live.funcs[module.imported_funcs as usize + i] {
+                        continue;
+                    }
+                    let ops = body.get_operators_reader()?;
+                    let mut encoded = wasm[body.range().start..ops.original_position()].to_vec();
+                    map.expr(wasm, ops, &mut encoded)?;
+                    write_u32(&mut contents, encoded.len() as u32);
+                    contents.extend_from_slice(&encoded);
+                }
+            }
+            DATA => {
+                let mut reader = DataSectionReader::new(section.contents, section.offset)?;
+                write_u32(&mut contents, map.data.count);
+                for i in 0..reader.get_count() as usize {
+                    let data = reader.read()?;
+                    if !live.data[i] {
+                        continue;
+                    }
+                    match data.kind {
+                        DataKind::Passive => contents.push(0x01),
+                        DataKind::Active {
+                            memory_index,
+                            init_expr,
+                        } => {
+                            if memory_index == 0 {
+                                contents.push(0x00);
+                            } else {
+                                contents.push(0x02);
+                                write_u32(&mut contents, memory_index);
+                            }
+                            map.expr(wasm, init_expr.get_operators_reader(), &mut contents)?;
+                        }
+                    }
+                    write_u32(&mut contents, data.data.len() as u32);
+                    contents.extend_from_slice(data.data);
+                }
+            }
+            CUSTOM if section.custom_name()? == Some("name") => match map.names(section.contents) {
+                Ok(names) => contents = names,
+                Err(_) => continue,
+            },
+            _ => {
+                section.encode(&mut output);
+                continue;
+            }
+        }
+        Section {
+            id: section.id,
+            contents: &contents,
+            offset: 0,
+        }
+        .encode(&mut output);
+    }
+    Ok(output)
+}
+
+/// The parts of a module needed to determine which items are reachable.
+pub(crate) struct Module<'a> {
+    pub(crate) sections: Vec<Section<'a>>,
+    pub(crate) types: u32,
+    pub(crate) imported_funcs: u32,
+    pub(crate) imported_globals: u32,
+    /// The type of each function, imported ones first.
+    pub(crate) func_types: Vec<u32>,
+    /// The initializer of each defined global.
+    pub(crate) global_inits: Vec<InitExpr<'a>>,
+    pub(crate) bodies: Vec<FunctionBody<'a>>,
+    /// The types used by events, both imported and defined.
+    event_types: Vec<u32>,
+    exports: Vec<(ExternalKind, u32)>,
+    pub(crate) start: Option<u32>,
+    /// The functions and expressions of element segments.
+    element_funcs: Vec<u32>,
+    element_exprs: Vec<InitExpr<'a>>,
+    /// The offset of each active data segment, or `None` for passive ones.
+    pub(crate) data: Vec<Option<InitExpr<'a>>>,
+}
+
+impl<'a> Module<'a> {
+    pub(crate) fn parse(wasm: &'a [u8]) -> Result<Module<'a>> {
+        let mut module = Module {
+            sections: sections(wasm)?,
+            types: 0,
+            imported_funcs: 0,
+            imported_globals: 0,
+            func_types: Vec::new(),
+            global_inits: Vec::new(),
+            bodies: Vec::new(),
+            event_types: Vec::new(),
+            exports: Vec::new(),
+            start: None,
+            element_funcs: Vec::new(),
+            element_exprs: Vec::new(),
+            data: Vec::new(),
+        };
+        for section in module.sections.iter() {
+            match section.id {
+                TYPE => {
+                    module.types =
+                        TypeSectionReader::new(section.contents, section.offset)?.get_count();
+                }
+                IMPORT => {
+                    for import in ImportSectionReader::new(section.contents, section.offset)? {
+                        match import?.ty {
+                            ImportSectionEntryType::Function(ty) => {
+                                module.imported_funcs += 1;
+                                module.func_types.push(ty);
+                            }
+                            ImportSectionEntryType::Global(_) => module.imported_globals += 1,
+                            ImportSectionEntryType::Event(ty) => {
+                                module.event_types.push(ty.type_index)
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                FUNCTION => {
+                    for ty in FunctionSectionReader::new(section.contents, section.offset)? {
+                        module.func_types.push(ty?);
+                    }
+                }
+                GLOBAL => {
+                    for global in GlobalSectionReader::new(section.contents, section.offset)? {
+                        module.global_inits.push(global?.init_expr);
+                    }
+                }
+                EVENT => {
+                    for ty in EventSectionReader::new(section.contents, section.offset)? {
+                        module.event_types.push(ty?.type_index);
+                    }
+                }
+                EXPORT => {
+                    for export in ExportSectionReader::new(section.contents, section.offset)? {
+                        let export = export?;
+                        module.exports.push((export.kind, export.index));
+                    }
+                }
+                START => {
+                    module.start = Some(BinaryReader::new(section.contents).read_var_u32()?);
+                }
+                ELEMENT => {
+                    for element in ElementSectionReader::new(section.contents, section.offset)? {
+                        let element = element?;
+                        if let ElementKind::Active { init_expr, .. } = element.kind {
+                            module.element_exprs.push(init_expr);
+                        }
+                        for item in element.items.get_items_reader()? {
+                            if let ElementItem::Func(f) = item? {
+                                module.element_funcs.push(f);
+                            }
+                        }
+                    }
+                }
+                CODE => {
+                    for body in CodeSectionReader::new(section.contents, section.offset)? {
+                        module.bodies.push(body?);
+                    }
+                }
+                DATA => {
+                    for data in DataSectionReader::new(section.contents, section.offset)? {
+                        module.data.push(match data?.kind {
+                            DataKind::Passive => None,
+                            DataKind::Active { init_expr, .. } => Some(init_expr),
+                        });
+                    }
+                }
+                MODULE | INSTANCE | ALIAS | MODULE_CODE => {
+                    bail!("modules using the module linking proposal are not supported")
+                }
+                _ => {}
+            }
+        }
+        if module.bodies.len() != module.func_types.len() - module.imported_funcs as usize {
+            bail!("function and code section have inconsistent lengths");
+        }
+        Ok(module)
+    }
+
+    /// Determines which items of this module are reachable.
+    fn live(&self) -> Result<Live> {
+        let mut live = Live {
+            types: vec![false; self.types as usize],
+            funcs: vec![false; self.func_types.len()],
+            globals: vec![false; self.imported_globals as usize + self.global_inits.len()],
+            data: self.data.iter().map(|d| d.is_some()).collect(),
+            func_worklist: Vec::new(),
+            global_worklist: Vec::new(),
+        };
+
+        for (kind, index) in self.exports.iter() {
+            match kind {
+                ExternalKind::Function => live.func(*index)?,
+                ExternalKind::Global => live.global(*index)?,
+                _ => {}
+            }
+        }
+        if let Some(start) = self.start {
+            live.func(start)?;
+        }
+        for f in self.element_funcs.iter() {
+            live.func(*f)?;
+        }
+        for expr in self.element_exprs.iter() {
+            live.expr(expr.get_operators_reader())?;
+        }
+        for expr in self.data.iter().filter_map(|d| d.as_ref()) {
+            live.expr(expr.get_operators_reader())?;
+        }
+        for ty in self.event_types.iter() {
+            live.ty(*ty)?;
+        }
+
+        loop {
+            if let Some(f) = live.func_worklist.pop() {
+                live.ty(self.func_types[f as usize])?;
+                if let Some(i) = f.checked_sub(self.imported_funcs) {
+                    live.expr(self.bodies[i as usize].get_operators_reader()?)?;
+                }
+            } else if let Some(g) = live.global_worklist.pop() {
+                if let Some(i) = g.checked_sub(self.imported_globals) {
+                    live.expr(self.global_inits[i as usize].get_operators_reader())?;
+                }
+            } else {
+                break;
+            }
+        }
+        Ok(live)
+    }
+}
+
+/// Which items of a module are reachable.
+struct Live {
+    types: Vec<bool>,
+    funcs: Vec<bool>,
+    globals: Vec<bool>,
+    data: Vec<bool>,
+    func_worklist: Vec<u32>,
+    global_worklist: Vec<u32>,
+}
+
+impl Live {
+    fn func(&mut self, f: u32) -> Result<()> {
+        match self.funcs.get_mut(f as usize) {
+            Some(live) if !*live => {
+                *live = true;
+                self.func_worklist.push(f);
+            }
+            Some(_) => {}
+            None => bail!("function index {} out of bounds", f),
+        }
+        Ok(())
+    }
+
+    fn global(&mut self, g: u32) -> Result<()> {
+        match self.globals.get_mut(g as usize) {
+            Some(live) if !*live => {
+                *live = true;
+                self.global_worklist.push(g);
+            }
+            Some(_) => {}
+            None => bail!("global index {} out of bounds", g),
+        }
+        Ok(())
+    }
+
+    fn ty(&mut self, ty: u32) -> Result<()> {
+        match self.types.get_mut(ty as usize) {
+            Some(live) => *live = true,
+            None => bail!("type index {} out of bounds", ty),
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self, reader: OperatorsReader<'_>) -> Result<()> {
+        let mut references = References::default();
+        references.add(reader)?;
+        for f in references.functions {
+            self.func(f)?;
+        }
+        for g in references.globals {
+            self.global(g)?;
+        }
+        for ty in references.types {
+            self.ty(ty)?;
+        }
+        for d in references.data {
+            match self.data.get_mut(d as usize) {
+                Some(live) => *live = true,
+                None => bail!("data segment index {} out of bounds", d),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the number of functions defined by `module` which are kept.
+    fn defined_funcs(&self, module: &Module<'_>) -> u32 {
+        let defined = &self.funcs[module.imported_funcs as usize..];
+        defined.iter().filter(|l| **l).count() as u32
+    }
+}
+
+/// The new indices of the items which are kept.
+///
+/// Tables, events and element segments are only renumbered when merging
+/// modules, so their indices are left unchanged when they're `None`.
+pub(crate) struct Map {
+    pub(crate) types: Renumbering,
+    pub(crate) funcs: Renumbering,
+    pub(crate) globals: Renumbering,
+    pub(crate) data: Renumbering,
+    pub(crate) tables: Option<Renumbering>,
+    pub(crate) events: Option<Renumbering>,
+    pub(crate) elements: Option<Renumbering>,
+}
+
+pub(crate) struct Renumbering {
+    pub(crate) indices: Vec<Option<u32>>,
+    pub(crate) count: u32,
+}
+
+impl Renumbering {
+    pub(crate) fn get(&self, old: u32) -> u32 {
+        self.indices[old as usize].expect("reference to a removed item")
+    }
+}
+
+pub(crate) fn renumber(live: &[bool]) -> Renumbering {
+    let mut count = 0;
+    let indices = live
+        .iter()
+        .map(|live| {
+            if *live {
+                count += 1;
+                Some(count - 1)
+            } else {
+                None
+            }
+        })
+        .collect();
+    Renumbering { indices, count }
+}
+
+impl Map {
+    fn table(&self, old: u32) -> u32 {
+        self.tables.as_ref().map_or(old, |t| t.get(old))
+    }
+
+    fn event(&self, old: u32) -> u32 {
+        self.events.as_ref().map_or(old, |e| e.get(old))
+    }
+
+    fn element_segment(&self, old: u32) -> u32 {
+        self.elements.as_ref().map_or(old, |e| e.get(old))
+    }
+
+    /// Copies the operators of `reader` to `output`, renumbering the indices
+    /// which they refer to.
+    pub(crate) fn expr(
+        &self,
+        wasm: &[u8],
+        mut reader: OperatorsReader<'_>,
+        output: &mut Vec<u8>,
+    ) -> Result<()> {
+        while !reader.eof() {
+            let (op, start) = reader.read_with_offset()?;
+            self.operator(op, &wasm[start..reader.original_position()], output);
+        }
+        Ok(())
+    }
+
+    /// Writes the operator `op`, whose original encoding is `raw`, to
+    /// `output` with the indices it refers to renumbered.
+    pub(crate) fn operator(&self, op: Operator<'_>, raw: &[u8], output: &mut Vec<u8>) {
+        match op {
+            Operator::Call { function_index } => {
+                output.push(0x10);
+                write_u32(output, self.funcs.get(function_index));
+            }
+            Operator::ReturnCall { function_index } => {
+                output.push(0x12);
+                write_u32(output, self.funcs.get(function_index));
+            }
+            Operator::RefFunc { function_index } => {
+                output.push(0xd2);
+                write_u32(output, self.funcs.get(function_index));
+            }
+            Operator::CallIndirect { index, table_index } => {
+                output.push(0x11);
+                write_u32(output, self.types.get(index));
+                write_u32(output, self.table(table_index));
+            }
+            Operator::ReturnCallIndirect { index, table_index } => {
+                output.push(0x13);
+                write_u32(output, self.types.get(index));
+                write_u32(output, self.table(table_index));
+            }
+            Operator::TableGet { table } => {
+                output.push(0x25);
+                write_u32(output, self.table(table));
+            }
+            Operator::TableSet { table } => {
+                output.push(0x26);
+                write_u32(output, self.table(table));
+            }
+            Operator::TableInit { segment, table } => {
+                output.extend_from_slice(&[0xfc, 0x0c]);
+                write_u32(output, self.element_segment(segment));
+                write_u32(output, self.table(table));
+            }
+            Operator::ElemDrop { segment } => {
+                output.extend_from_slice(&[0xfc, 0x0d]);
+                write_u32(output, self.element_segment(segment));
+            }
+            Operator::TableCopy {
+                dst_table,
+                src_table,
+            } => {
+                output.extend_from_slice(&[0xfc, 0x0e]);
+                write_u32(output, self.table(dst_table));
+                write_u32(output, self.table(src_table));
+            }
+            Operator::TableGrow { table } => {
+                output.extend_from_slice(&[0xfc, 0x0f]);
+                write_u32(output, self.table(table));
+            }
+            Operator::TableSize { table } => {
+                output.extend_from_slice(&[0xfc, 0x10]);
+                write_u32(output, self.table(table));
+            }
+            Operator::TableFill { table } => {
+                output.extend_from_slice(&[0xfc, 0x11]);
+                write_u32(output, self.table(table));
+            }
+            Operator::Catch { index } => {
+                output.push(0x07);
+                write_u32(output, self.event(index));
+            }
+            Operator::Throw { index } => {
+                output.push(0x08);
+                write_u32(output, self.event(index));
+            }
+            Operator::GlobalGet { global_index } => {
+                output.push(0x23);
+                write_u32(output, self.globals.get(global_index));
+            }
+            Operator::GlobalSet { global_index } => {
+                output.push(0x24);
+                write_u32(output, self.globals.get(global_index));
+            }
+            Operator::Block {
+                ty: TypeOrFuncType::FuncType(ty),
+            }
+            | Operator::Loop {
+                ty: TypeOrFuncType::FuncType(ty),
+            }
+            | Operator::If {
+                ty: TypeOrFuncType::FuncType(ty),
+            }
+            | Operator::Try {
+                ty: TypeOrFuncType::FuncType(ty),
+            } => {
+                output.push(raw[0]);
+                write_i64(output, self.types.get(ty).into());
+            }
+            Operator::Let {
+                ty: TypeOrFuncType::FuncType(ty),
+                ..
+            } => {
+                // The block type is followed by the locals, which are copied.
+                let mut reader = BinaryReader::new(&raw[1..]);
+                let _ = reader.read_var_s33();
+                output.push(raw[0]);
+                write_i64(output, self.types.get(ty).into());
+                output.extend_from_slice(&raw[1 + reader.current_position()..]);
+            }
+            Operator::MemoryInit { segment, mem } => {
+                output.extend_from_slice(&[0xfc, 0x08]);
+                write_u32(output, self.data.get(segment));
+                write_u32(output, mem);
+            }
+            Operator::DataDrop { segment } => {
+                output.extend_from_slice(&[0xfc, 0x09]);
+                write_u32(output, self.data.get(segment));
+            }
+            _ => output.extend_from_slice(raw),
+        }
+    }
+
+    /// Reads an element segment from `reader` and writes it to `output` with
+    /// its table and functions renumbered.
+    pub(crate) fn element(
+        &self,
+        wasm: &[u8],
+        reader: &mut ElementSectionReader<'_>,
+        output: &mut Vec<u8>,
+    ) -> Result<()> {
+        let element = reader.read()?;
+        let mut items = element.items.get_items_reader()?;
+        let exprs = items.uses_exprs();
+        let mut flags = if exprs { 0b100 } else { 0 };
+        match &element.kind {
+            ElementKind::Passive => flags |= 0b001,
+            ElementKind::Declared => flags |= 0b011,
+            ElementKind::Active { table_index, .. } => {
+                if self.table(*table_index) != 0 || element.ty != Type::FuncRef {
+                    flags |= 0b010;
+                }
+            }
+        }
+        write_u32(output, flags);
+        if let ElementKind::Active {
+            table_index,
+            init_expr,
+        } = element.kind
+        {
+            if flags & 0b010 != 0 {
+                write_u32(output, self.table(table_index));
+            }
+            self.expr(wasm, init_expr.get_operators_reader(), output)?;
+        }
+        if flags & 0b011 != 0 {
+            if exprs {
+                output.push(ref_type(element.ty)?);
+            } else {
+                output.push(0x00);
+            }
+        }
+        write_u32(output, items.get_count());
+        for _ in 0..items.get_count() {
+            match items.read()? {
+                ElementItem::Func(f) if exprs => {
+                    output.push(0xd2);
+                    write_u32(output, self.funcs.get(f));
+                    output.push(0x0b);
+                }
+                ElementItem::Func(f) => {
+                    write_u32(output, self.funcs.get(f));
+                }
+                ElementItem::Null(ty) => {
+                    output.push(0xd0);
+                    output.push(ref_type(ty)?);
+                    output.push(0x0b);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Rewrites the contents of the `name` section, keeping only the module
+    /// name and the names of functions and locals which are kept.
+    pub(crate) fn names(&self, contents: &[u8]) -> Result<Vec<u8>> {
+        let mut reader = BinaryReader::new(contents);
+        let mut output = Vec::new();
+        write_str(&mut output, reader.read_string()?);
+        while !reader.eof() {
+            let id = reader.read_u8()?;
+            let len = reader.read_var_u32()?;
+            let subsection = reader.read_bytes(len as usize)?;
+            let mut names = BinaryReader::new(subsection);
+            let mut encoded = Vec::new();
+            match id {
+                0 => encoded.extend_from_slice(subsection),
+                1 => self.name_map(&mut names, &mut encoded)?,
+                2 => {
+                    let mut funcs = Vec::new();
+                    for _ in 0..names.read_var_u32()? {
+                        let f = names.read_var_u32()?;
+                        let mut locals = Vec::new();
+                        let n = names.read_var_u32()?;
+                        write_u32(&mut locals, n);
+                        for _ in 0..n {
+                            let local = names.read_var_u32()?;
+                            let name = names.read_string()?;
+                            write_u32(&mut locals, local);
+                            write_str(&mut locals, name);
+                        }
+                        if let Some(Some(f)) = self.funcs.indices.get(f as usize) {
+                            funcs.push((*f, locals));
+                        }
+                    }
+                    write_sorted(&mut encoded, funcs);
+                }
+                _ => continue,
+            }
+            output.push(id as u8);
+            write_u32(&mut output, encoded.len() as u32);
+            output.extend_from_slice(&encoded);
+        }
+        Ok(output)
+    }
+
+    fn name_map(&self, reader: &mut BinaryReader<'_>, output: &mut Vec<u8>) -> Result<()> {
+        let mut names = Vec::new();
+        for _ in 0..reader.read_var_u32()? {
+            let f = reader.read_var_u32()?;
+            let name = reader.read_string()?;
+            if let Some(Some(f)) = self.funcs.indices.get(f as usize) {
+                let mut encoded = Vec::new();
+                write_str(&mut encoded, name);
+                names.push((*f, encoded));
+            }
+        }
+        write_sorted(output, names);
+        Ok(())
+    }
+
+    /// Reads an export from `reader` and writes it to `output` with its index
+    /// renumbered, returning its name.
+    pub(crate) fn export<'a>(
+        &self,
+        wasm: &[u8],
+        reader: &mut ExportSectionReader<'a>,
+        output: &mut Vec<u8>,
+    ) -> Result<&'a str> {
+        let start = reader.original_position();
+        let export = reader.read()?;
+        let index = match export.kind {
+            ExternalKind::Function => self.funcs.get(export.index),
+            ExternalKind::Global => self.globals.get(export.index),
+            _ => export.index,
+        };
+        let entry = &wasm[start..reader.original_position()];
+        let mut entry_reader = BinaryReader::new(entry);
+        entry_reader.read_string()?;
+        output.extend_from_slice(&entry[..entry_reader.current_position() + 1]);
+        write_u32(output, index);
+        Ok(export.field)
+    }
+}
+
+/// Writes the entries of a name map, or of an indirect name map, to `output`
+/// in order of their indices, which may have been shuffled by renumbering.
+fn write_sorted(output: &mut Vec<u8>, mut entries: Vec<(u32, Vec<u8>)>) {
+    entries.sort_by_key(|(index, _)| *index);
+    write_u32(output, entries.len() as u32);
+    for (index, encoded) in entries {
+        write_u32(output, index);
+        output.extend_from_slice(&encoded);
+    }
+}
+
+pub(crate) fn ref_type(ty: Type) -> Result<u8> {
+    Ok(match ty {
+        Type::FuncRef => 0x70,
+        Type::ExternRef => 0x6f,
+        Type::ExnRef => 0x68,
+        _ => bail!("invalid reference type {:?}", ty),
+    })
+}
@@ -0,0 +1,328 @@
+//! Evaluation of the initial contents of a module's memories and tables.
+//!
+//! This computes the state a module is in right after instantiation, before
+//! its start function runs, without needing an engine: globals are
+//! initialized and the active data and element segments are copied into their
+//! memories and tables. Offsets and initializers may use the arithmetic
+//! instructions of the extended constant expressions proposal.
+//!
+//! ```
+//! use wasm_tools::init::{initialize, Imports};
+//! use wasm_tools::interp::Value;
+//!
+//! let wasm = wat::parse_str(r#"
+//!     (module
+//!       (import "env" "base" (global $base i32))
+//!       (memory 1)
+//!       (data (global.get $base) "hi"))
+//! "#)?;
+//! let state = initialize(&wasm, Imports::new().global("env", "base", Value::I32(8)))?;
+//! assert_eq!(&state.memories[0][8..10], b"hi");
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::interp::{trap, unsupported, Value};
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use wasmparser::{
+    DataKind, ElementItem, ElementKind, ImportSectionEntryType, MemoryType, Operator,
+    OperatorsReader, Parser, Payload, TableType,
+};
+
+/// The values given to the imports of a module, see [`initialize`].
+#[derive(Debug, Clone, Default)]
+pub struct Imports {
+    globals: HashMap<(String, String), Value>,
+    memories: HashMap<(String, String), Vec<u8>>,
+    tables: HashMap<(String, String), Vec<TableEntry>>,
+}
+
+impl Imports {
+    /// Creates an empty set of imports.
+    pub fn new() -> Imports {
+        Imports::default()
+    }
+
+    /// Sets the value of the imported global `module::field`.
+    pub fn global(&mut self, module: &str, field: &str, value: Value) -> &mut Imports {
+        self.globals.insert(key(module, field), value);
+        self
+    }
+
+    /// Sets the contents of the imported memory `module::field`, which are
+    /// zero-extended to its minimum size.
+    ///
+    /// Imported memories which aren't given are zero-filled.
+    pub fn memory(&mut self, module: &str, field: &str, contents: Vec<u8>) -> &mut Imports {
+        self.memories.insert(key(module, field), contents);
+        self
+    }
+
+    /// Sets the entries of the imported table `module::field`, which are
+    /// extended to its minimum size with null entries.
+    ///
+    /// Imported tables which aren't given are filled with null entries.
+    pub fn table(&mut self, module: &str, field: &str, entries: Vec<TableEntry>) -> &mut Imports {
+        self.tables.insert(key(module, field), entries);
+        self
+    }
+}
+
+fn key(module: &str, field: &str) -> (String, String) {
+    (module.to_string(), field.to_string())
+}
+
+/// An entry of a table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableEntry {
+    /// A null reference.
+    Null,
+    /// A reference to the function with this index.
+    Func(u32),
+}
+
+/// The state of a module right after instantiation, see [`initialize`].
+#[derive(Debug, Clone)]
+pub struct InitialState {
+    /// The value of each global, including imported ones, or `None` for
+    /// globals of reference types.
+    pub globals: Vec<Option<Value>>,
+    /// The contents of each memory, including imported ones.
+    pub memories: Vec<Vec<u8>>,
+    /// The entries of each table, including imported ones.
+    pub tables: Vec<Vec<TableEntry>>,
+}
+
+/// Computes the state of the module `wasm` right after it's instantiated with
+/// `imports`.
+///
+/// The module isn't validated, but malformed modules and invalid constant
+/// expressions are reported as errors. Returns an
+/// [`Unsupported`](crate::interp::Unsupported) error if an imported global has
+/// no value or if a memory is larger than 4 GiB, and a
+/// [`Trap`](crate::interp::Trap) error if a segment is out of bounds, as
+/// instantiation would.
+pub fn initialize(wasm: &[u8], imports: &Imports) -> Result<InitialState> {
+    let mut state = InitialState {
+        globals: Vec::new(),
+        memories: Vec::new(),
+        tables: Vec::new(),
+    };
+    for payload in Parser::new(0).parse_all(wasm) {
+        match payload? {
+            Payload::ImportSection(s) => {
+                for import in s {
+                    let import = import?;
+                    let key = key(import.module, import.field.unwrap_or(""));
+                    match import.ty {
+                        ImportSectionEntryType::Global(_) => match imports.globals.get(&key) {
+                            Some(value) => state.globals.push(Some(*value)),
+                            None => {
+                                return Err(unsupported(format!(
+                                    "imported global `{}::{}` without a value",
+                                    key.0, key.1
+                                )))
+                            }
+                        },
+                        ImportSectionEntryType::Memory(ty) => {
+                            let mut memory = new_memory(ty)?;
+                            if let Some(contents) = imports.memories.get(&key) {
+                                if contents.len() > memory.len() {
+                                    memory.resize(contents.len(), 0);
+                                }
+                                memory[..contents.len()].copy_from_slice(contents);
+                            }
+                            state.memories.push(memory);
+                        }
+                        ImportSectionEntryType::Table(ty) => {
+                            let mut table = new_table(ty);
+                            if let Some(entries) = imports.tables.get(&key) {
+                                if entries.len() > table.len() {
+                                    table.resize(entries.len(), TableEntry::Null);
+                                }
+                                table[..entries.len()].copy_from_slice(entries);
+                            }
+                            state.tables.push(table);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Payload::TableSection(s) => {
+                for table in s {
+                    state.tables.push(new_table(table?));
+                }
+            }
+            Payload::MemorySection(s) => {
+                for memory in s {
+                    state.memories.push(new_memory(memory?)?);
+                }
+            }
+            Payload::GlobalSection(s) => {
+                for global in s {
+                    let value = state.eval_const(global?.init_expr.get_operators_reader())?;
+                    state.globals.push(value);
+                }
+            }
+            Payload::ElementSection(s) => {
+                for element in s {
+                    let element = element?;
+                    let (table_index, init_expr) = match element.kind {
+                        ElementKind::Active {
+                            table_index,
+                            init_expr,
+                        } => (table_index, init_expr),
+                        _ => continue,
+                    };
+                    let offset = state.eval_offset(init_expr.get_operators_reader())?;
+                    let mut entries = Vec::new();
+                    for item in element.items.get_items_reader()? {
+                        entries.push(match item? {
+                            ElementItem::Null(_) => TableEntry::Null,
+                            ElementItem::Func(index) => TableEntry::Func(index),
+                        });
+                    }
+                    let table = match state.tables.get_mut(table_index as usize) {
+                        Some(table) => table,
+                        None => bail!("element segment for unknown table {}", table_index),
+                    };
+                    match range(offset, entries.len(), table.len()) {
+                        Some(range) => table[range].copy_from_slice(&entries),
+                        None => return Err(trap("out of bounds table access")),
+                    }
+                }
+            }
+            Payload::DataSection(s) => {
+                for data in s {
+                    let data = data?;
+                    let (memory_index, init_expr) = match data.kind {
+                        DataKind::Active {
+                            memory_index,
+                            init_expr,
+                        } => (memory_index, init_expr),
+                        DataKind::Passive => continue,
+                    };
+                    let offset = state.eval_offset(init_expr.get_operators_reader())?;
+                    let memory = match state.memories.get_mut(memory_index as usize) {
+                        Some(memory) => memory,
+                        None => bail!("data segment for unknown memory {}", memory_index),
+                    };
+                    match range(offset, data.data.len(), memory.len()) {
+                        Some(range) => memory[range].copy_from_slice(data.data),
+                        None => return Err(trap("out of bounds memory access")),
+                    }
+                }
+            }
+            Payload::ModuleSectionStart { .. } => {
+                return Err(unsupported("module linking"));
+            }
+            _ => {}
+        }
+    }
+    Ok(state)
+}
+
+fn new_memory(ty: MemoryType) -> Result<Vec<u8>> {
+    let pages = match ty {
+        MemoryType::M32 { limits, .. } => u64::from(limits.initial),
+        MemoryType::M64 { limits, .. } => limits.initial,
+    };
+    if pages > 65536 {
+        return Err(unsupported(format!("memories of {} pages", pages)));
+    }
+    Ok(vec![0; pages as usize * 65536])
+}
+
+fn new_table(ty: TableType) -> Vec<TableEntry> {
+    vec![TableEntry::Null; ty.limits.initial as usize]
+}
+
+/// Returns the range of `len` items at `offset` if it's within `size`.
+fn range(offset: u64, len: usize, size: usize) -> Option<std::ops::Range<usize>> {
+    let end = offset.checked_add(len as u64)?;
+    if end > size as u64 {
+        return None;
+    }
+    Some(offset as usize..end as usize)
+}
+
+impl InitialState {
+    fn eval_const(&self, mut reader: OperatorsReader<'_>) -> Result<Option<Value>> {
+        let mut stack = Vec::new();
+        loop {
+            let value = match reader.read()? {
+                Operator::I32Const { value } => Some(Value::I32(value)),
+                Operator::I64Const { value } => Some(Value::I64(value)),
+                Operator::F32Const { value } => Some(Value::F32(value.bits())),
+                Operator::F64Const { value } => Some(Value::F64(value.bits())),
+                Operator::RefNull { .. } | Operator::RefFunc { .. } => None,
+                Operator::GlobalGet { global_index } => {
+                    match self.globals.get(global_index as usize) {
+                        Some(value) => *value,
+                        None => bail!("constant expression uses unknown global {}", global_index),
+                    }
+                }
+                Operator::I32Add => binop(&mut stack, |a, b| {
+                    Some(Value::I32(i32(a)?.wrapping_add(i32(b)?)))
+                })?,
+                Operator::I32Sub => binop(&mut stack, |a, b| {
+                    Some(Value::I32(i32(a)?.wrapping_sub(i32(b)?)))
+                })?,
+                Operator::I32Mul => binop(&mut stack, |a, b| {
+                    Some(Value::I32(i32(a)?.wrapping_mul(i32(b)?)))
+                })?,
+                Operator::I64Add => binop(&mut stack, |a, b| {
+                    Some(Value::I64(i64(a)?.wrapping_add(i64(b)?)))
+                })?,
+                Operator::I64Sub => binop(&mut stack, |a, b| {
+                    Some(Value::I64(i64(a)?.wrapping_sub(i64(b)?)))
+                })?,
+                Operator::I64Mul => binop(&mut stack, |a, b| {
+                    Some(Value::I64(i64(a)?.wrapping_mul(i64(b)?)))
+                })?,
+                Operator::End => break,
+                op => bail!("invalid constant expression operator {:?}", op),
+            };
+            stack.push(value);
+        }
+        match (stack.pop(), stack.is_empty()) {
+            (Some(value), true) => Ok(value),
+            _ => bail!("constant expression doesn't produce a single value"),
+        }
+    }
+
+    fn eval_offset(&self, reader: OperatorsReader<'_>) -> Result<u64> {
+        Ok(match self.eval_const(reader)? {
+            Some(Value::I32(offset)) => u64::from(offset as u32),
+            Some(Value::I64(offset)) => offset as u64,
+            _ => bail!("segment offset is not an integer"),
+        })
+    }
+}
+
+/// Pops the operands of a binary operator from `stack`, returning its result.
+fn binop(
+    stack: &mut Vec<Option<Value>>,
+    f: impl FnOnce(Value, Value) -> Option<Value>,
+) -> Result<Option<Value>> {
+    let b = stack.pop().flatten();
+    let a = stack.pop().flatten();
+    match a.zip(b).and_then(|(a, b)| f(a, b)) {
+        Some(value) => Ok(Some(value)),
+        None => bail!("type mismatch in constant expression"),
+    }
+}
+
+fn i32(value: Value) -> Option<i32> {
+    match value {
+        Value::I32(value) => Some(value),
+        _ => None,
+    }
+}
+
+fn i64(value: Value) -> Option<i64> {
+    match value {
+        Value::I64(value) => Some(value),
+        _ => None,
+    }
+}
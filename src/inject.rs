@@ -0,0 +1,256 @@
+//! Support for transforms which add imports, globals and exports to a binary
+//! module while rewriting its function bodies.
+
+use crate::gc::{renumber, Map, Module, Renumbering};
+use crate::sections::{rank, write_str, Section};
+use anyhow::{bail, Result};
+use std::collections::HashSet;
+use wasmparser::leb128::write_u32;
+use wasmparser::{
+    BinaryReader, ElementSectionReader, ExportSectionReader, FunctionBody, GlobalSectionReader,
+};
+
+const CUSTOM: u8 = 0;
+const TYPE: u8 = 1;
+const IMPORT: u8 = 2;
+const GLOBAL: u8 = 6;
+const EXPORT: u8 = 7;
+const START: u8 = 8;
+const ELEMENT: u8 = 9;
+const CODE: u8 = 10;
+
+/// Items to add to a module, which are appended to its existing ones.
+///
+/// Functions are imported after the module's existing imports, so every
+/// function defined by the module is renumbered. Other index spaces are
+/// unchanged.
+pub(crate) struct Injector<'a> {
+    wasm: &'a [u8],
+    module: Module<'a>,
+    types: Entries,
+    imports: Entries,
+    globals: Entries,
+    exports: Entries,
+    export_names: Vec<String>,
+}
+
+/// The encoded entries to append to one section, and how many there are.
+#[derive(Default)]
+struct Entries {
+    count: u32,
+    encoded: Vec<u8>,
+}
+
+impl<'a> Injector<'a> {
+    pub(crate) fn new(wasm: &'a [u8]) -> Result<Injector<'a>> {
+        Ok(Injector {
+            wasm,
+            module: Module::parse(wasm)?,
+            types: Entries::default(),
+            imports: Entries::default(),
+            globals: Entries::default(),
+            exports: Entries::default(),
+            export_names: Vec::new(),
+        })
+    }
+
+    /// Adds a function type with the encoded value types `params` and
+    /// `results`, returning its index.
+    pub(crate) fn func_type(&mut self, params: &[u8], results: &[u8]) -> u32 {
+        let encoded = &mut self.types.encoded;
+        encoded.push(0x60);
+        write_u32(encoded, params.len() as u32);
+        encoded.extend_from_slice(params);
+        write_u32(encoded, results.len() as u32);
+        encoded.extend_from_slice(results);
+        self.types.count += 1;
+        self.module.types + self.types.count - 1
+    }
+
+    /// Imports `module`.`field` as a function of type `ty`, returning its
+    /// index in the new module.
+    pub(crate) fn import_func(&mut self, module: &str, field: &str, ty: u32) -> u32 {
+        let encoded = &mut self.imports.encoded;
+        write_str(encoded, module);
+        write_str(encoded, field);
+        encoded.push(0x00);
+        write_u32(encoded, ty);
+        self.imports.count += 1;
+        self.module.imported_funcs + self.imports.count - 1
+    }
+
+    /// Defines a global, given its encoded type and initializer, returning
+    /// its index.
+    pub(crate) fn global(&mut self, encoded: &[u8]) -> u32 {
+        self.globals.encoded.extend_from_slice(encoded);
+        self.globals.count += 1;
+        let existing = self.module.imported_globals + self.module.global_inits.len() as u32;
+        existing + self.globals.count - 1
+    }
+
+    /// Exports the global `index` as `name`.
+    pub(crate) fn export_global(&mut self, name: &str, index: u32) {
+        let encoded = &mut self.exports.encoded;
+        write_str(encoded, name);
+        encoded.push(0x03);
+        write_u32(encoded, index);
+        self.exports.count += 1;
+        self.export_names.push(name.to_string());
+    }
+
+    /// Writes the module with the added items.
+    ///
+    /// The body of each function defined by the module is written by `body`,
+    /// which is given the renumbering of the module's items, the function's
+    /// new index and its body. Its local declarations are copied as-is, and
+    /// `body` writes its operators.
+    ///
+    /// Function and local names in the `name` section are updated, but its
+    /// other subsections are removed. All other custom sections are copied
+    /// verbatim, so any indices or offsets they contain will be stale.
+    pub(crate) fn finish(
+        self,
+        mut body: impl FnMut(&Map, u32, &FunctionBody<'_>, &mut Vec<u8>) -> Result<()>,
+    ) -> Result<Vec<u8>> {
+        let wasm = self.wasm;
+        let module = &self.module;
+        let imported = module.imported_funcs;
+        let added = self.imports.count;
+        let identity = |count: u32| renumber(&vec![true; count as usize]);
+        let map = Map {
+            types: identity(module.types),
+            funcs: Renumbering {
+                indices: (0..module.func_types.len() as u32)
+                    .map(|f| Some(if f < imported { f } else { f + added }))
+                    .collect(),
+                count: module.func_types.len() as u32 + added,
+            },
+            globals: identity(module.imported_globals + module.global_inits.len() as u32),
+            data: identity(module.data.len() as u32),
+            tables: None,
+            events: None,
+            elements: None,
+        };
+
+        // Sections which the module doesn't have are added just before the
+        // first section which has to follow them.
+        let mut missing = [
+            (TYPE, &self.types),
+            (IMPORT, &self.imports),
+            (GLOBAL, &self.globals),
+            (EXPORT, &self.exports),
+        ]
+        .iter()
+        .filter(|(id, entries)| entries.count > 0 && !module.sections.iter().any(|s| s.id == *id))
+        .map(|(id, entries)| {
+            let mut contents = Vec::new();
+            write_u32(&mut contents, entries.count);
+            contents.extend_from_slice(&entries.encoded);
+            (*id, contents)
+        })
+        .collect::<Vec<_>>();
+        missing.reverse();
+
+        let mut output = wasm[..8].to_vec();
+        for section in module.sections.iter() {
+            if section.id != CUSTOM {
+                while let Some((id, contents)) = missing.last() {
+                    if rank(*id) > rank(section.id) {
+                        break;
+                    }
+                    encode_section(*id, contents, &mut output);
+                    missing.pop();
+                }
+            }
+
+            let mut contents = Vec::new();
+            match section.id {
+                TYPE => append(section.contents, &self.types, &mut contents)?,
+                IMPORT => append(section.contents, &self.imports, &mut contents)?,
+                GLOBAL => {
+                    let mut reader = GlobalSectionReader::new(section.contents, section.offset)?;
+                    write_u32(&mut contents, reader.get_count() + self.globals.count);
+                    for _ in 0..reader.get_count() {
+                        let start = reader.original_position();
+                        let expr = reader.read()?.init_expr.get_operators_reader();
+                        contents.extend_from_slice(&wasm[start..expr.original_position()]);
+                        map.expr(wasm, expr, &mut contents)?;
+                    }
+                    contents.extend_from_slice(&self.globals.encoded);
+                }
+                EXPORT => {
+                    let mut reader = ExportSectionReader::new(section.contents, section.offset)?;
+                    let mut names = HashSet::new();
+                    write_u32(&mut contents, reader.get_count() + self.exports.count);
+                    for _ in 0..reader.get_count() {
+                        names.insert(map.export(wasm, &mut reader, &mut contents)?);
+                    }
+                    for name in self.export_names.iter() {
+                        if names.contains(name.as_str()) {
+                            bail!("module already has an export named `{}`", name);
+                        }
+                    }
+                    contents.extend_from_slice(&self.exports.encoded);
+                }
+                START => {
+                    let func = BinaryReader::new(section.contents).read_var_u32()?;
+                    write_u32(&mut contents, map.funcs.get(func));
+                }
+                ELEMENT => {
+                    let mut reader = ElementSectionReader::new(section.contents, section.offset)?;
+                    write_u32(&mut contents, reader.get_count());
+                    for _ in 0..reader.get_count() {
+                        map.element(wasm, &mut reader, &mut contents)?;
+                    }
+                }
+                CODE => {
+                    write_u32(&mut contents, module.bodies.len() as u32);
+                    for (i, function) in module.bodies.iter().enumerate() {
+                        let func = map.funcs.get(imported + i as u32);
+                        let ops = function.get_operators_reader()?;
+                        let start = function.range().start;
+                        let mut encoded = wasm[start..ops.original_position()].to_vec();
+                        body(&map, func, function, &mut encoded)?;
+                        write_u32(&mut contents, encoded.len() as u32);
+                        contents.extend_from_slice(&encoded);
+                    }
+                }
+                CUSTOM if section.custom_name()? == Some("name") => {
+                    match map.names(section.contents) {
+                        Ok(names) => contents = names,
+                        Err(_) => continue,
+                    }
+                }
+                _ => {
+                    section.encode(&mut output);
+                    continue;
+                }
+            }
+            encode_section(section.id, &contents, &mut output);
+        }
+        while let Some((id, contents)) = missing.pop() {
+            encode_section(id, &contents, &mut output);
+        }
+        Ok(output)
+    }
+}
+
+/// Writes the contents of the vector section `contents` with `entries`
+/// appended to `output`.
+fn append(contents: &[u8], entries: &Entries, output: &mut Vec<u8>) -> Result<()> {
+    let mut reader = BinaryReader::new(contents);
+    let count = reader.read_var_u32()?;
+    write_u32(output, count + entries.count);
+    output.extend_from_slice(&contents[reader.current_position()..]);
+    output.extend_from_slice(&entries.encoded);
+    Ok(())
+}
+
+fn encode_section(id: u8, contents: &[u8], output: &mut Vec<u8>) {
+    Section {
+        id,
+        contents,
+        offset: 0,
+    }
+    .encode(output);
+}
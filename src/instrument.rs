@@ -0,0 +1,218 @@
+//! Instrumentation of a binary module with calls to host functions.
+//!
+//! Profilers, tracers and metering runtimes often want to observe a module
+//! without changing the toolchain which produced it. This transform imports
+//! hook functions from the host and inserts calls to them when functions are
+//! entered and exited, or after every N instructions.
+//!
+//! ```
+//! use wasm_tools::instrument::Instrument;
+//!
+//! let wasm = wat::parse_str(r#"
+//!     (module
+//!       (func (export "main") (result i32)
+//!         i32.const 1))
+//! "#)?;
+//! let wasm = Instrument::new().entry(true).exit(true).apply(&wasm)?;
+//! let text = wasmprinter::print_bytes(&wasm)?;
+//! assert!(text.contains(r#"(import "instrument" "enter" (func"#));
+//! assert!(text.contains(r#"(import "instrument" "exit" (func"#));
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::gc::Map;
+use crate::inject::Injector;
+use anyhow::{bail, Result};
+use wasmparser::leb128::{write_i64, write_u32};
+use wasmparser::{Operator, OperatorsReader};
+
+/// The hooks to insert into a module, and the module they're imported from.
+///
+/// Every hook is a function of type `[i32] -> []` imported from
+/// [`Instrument::module`]:
+///
+/// * `enter` is called with the index of a function when it's entered.
+/// * `exit` is called with the index of a function before it returns.
+/// * `fuel` is called with N after every N instructions of a function body.
+///
+/// Function indices are those of the instrumented module, which match the
+/// indices in its `name` section.
+#[derive(Clone, Debug)]
+pub struct Instrument {
+    module: String,
+    entry: bool,
+    exit: bool,
+    fuel: Option<u32>,
+}
+
+impl Default for Instrument {
+    fn default() -> Instrument {
+        Instrument {
+            module: "instrument".to_string(),
+            entry: false,
+            exit: false,
+            fuel: None,
+        }
+    }
+}
+
+impl Instrument {
+    /// Creates a new instrumentation which doesn't insert any hooks, and
+    /// imports them from the `instrument` module.
+    pub fn new() -> Instrument {
+        Instrument::default()
+    }
+
+    /// Sets the name of the module which hooks are imported from.
+    pub fn module(&mut self, module: &str) -> &mut Instrument {
+        self.module = module.to_string();
+        self
+    }
+
+    /// Whether to call the `enter` hook at the start of every function.
+    pub fn entry(&mut self, entry: bool) -> &mut Instrument {
+        self.entry = entry;
+        self
+    }
+
+    /// Whether to call the `exit` hook whenever a function returns.
+    ///
+    /// This covers `return`, tail calls, branches to the function's outermost
+    /// label with `br`, and falling off the end of the function. Exits through
+    /// `br_if` or `br_table`, traps and exceptions aren't reported.
+    pub fn exit(&mut self, exit: bool) -> &mut Instrument {
+        self.exit = exit;
+        self
+    }
+
+    /// Calls the `fuel` hook with `every` after every `every` instructions, or
+    /// never if `None`.
+    ///
+    /// Instructions are counted in the order they appear in a function body
+    /// rather than the order they execute in, so a loop whose body is shorter
+    /// than `every` instructions may run many times between calls.
+    pub fn fuel(&mut self, every: Option<u32>) -> &mut Instrument {
+        self.fuel = every;
+        self
+    }
+
+    /// Applies this instrumentation to the module `wasm`, returning the new
+    /// module.
+    ///
+    /// The hooks are imported after all of the module's existing imports, so
+    /// every function defined by the module is renumbered. Function and local
+    /// names in the `name` section are updated, but its other subsections are
+    /// removed. All other custom sections are copied verbatim, so any indices
+    /// or offsets they contain will be stale.
+    ///
+    /// Modules using the module linking proposal aren't supported.
+    pub fn apply(&self, wasm: &[u8]) -> Result<Vec<u8>> {
+        if self.fuel == Some(0) {
+            bail!("the fuel hook must be called every 1 or more instructions");
+        }
+        let hook_names = [
+            ("enter", self.entry),
+            ("exit", self.exit),
+            ("fuel", self.fuel.is_some()),
+        ];
+        let hook_names = hook_names
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>();
+        if hook_names.is_empty() {
+            return Ok(wasm.to_vec());
+        }
+
+        let mut injector = Injector::new(wasm)?;
+        let ty = injector.func_type(&[0x7f], &[]);
+        let mut hooks = Hooks {
+            enter: None,
+            exit: None,
+            fuel: None,
+        };
+        for name in hook_names {
+            let index = injector.import_func(&self.module, name, ty);
+            match name {
+                "enter" => hooks.enter = Some(index),
+                "exit" => hooks.exit = Some(index),
+                _ => hooks.fuel = self.fuel.map(|every| (index, every)),
+            }
+        }
+        injector.finish(|map, func, body, output| {
+            hooks.body(map, func, wasm, body.get_operators_reader()?, output)
+        })
+    }
+}
+
+/// The indices of the hooks which are inserted, and how often `fuel` is
+/// called.
+struct Hooks {
+    enter: Option<u32>,
+    exit: Option<u32>,
+    fuel: Option<(u32, u32)>,
+}
+
+impl Hooks {
+    /// Copies the operators of the body of function `func` to `output`,
+    /// renumbering them with `map` and inserting calls to the hooks.
+    fn body(
+        &self,
+        map: &Map,
+        func: u32,
+        wasm: &[u8],
+        mut reader: OperatorsReader<'_>,
+        output: &mut Vec<u8>,
+    ) -> Result<()> {
+        if let Some(enter) = self.enter {
+            call(output, enter, func);
+        }
+        // The number of blocks the current operator is nested in, not
+        // counting the function body itself.
+        let mut depth = 0;
+        let mut count = 0;
+        while !reader.eof() {
+            let (op, start) = reader.read_with_offset()?;
+            if let Some((fuel, every)) = self.fuel {
+                if count == every {
+                    call(output, fuel, every);
+                    count = 0;
+                }
+                count += 1;
+            }
+            let exits = match op {
+                Operator::Block { .. }
+                | Operator::Loop { .. }
+                | Operator::If { .. }
+                | Operator::Try { .. }
+                | Operator::Let { .. } => {
+                    depth += 1;
+                    false
+                }
+                Operator::End if depth == 0 => true,
+                Operator::End => {
+                    depth -= 1;
+                    false
+                }
+                Operator::Br { relative_depth } => relative_depth == depth,
+                Operator::Return
+                | Operator::ReturnCall { .. }
+                | Operator::ReturnCallIndirect { .. } => true,
+                _ => false,
+            };
+            if let (true, Some(exit)) = (exits, self.exit) {
+                call(output, exit, func);
+            }
+            map.operator(op, &wasm[start..reader.original_position()], output);
+        }
+        Ok(())
+    }
+}
+
+/// Appends `i32.const arg` followed by `call func` to `output`.
+fn call(output: &mut Vec<u8>, func: u32, arg: u32) {
+    output.push(0x41);
+    write_i64(output, (arg as i32).into());
+    output.push(0x10);
+    write_u32(output, func);
+}
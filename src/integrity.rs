@@ -0,0 +1,138 @@
+//! Content hashes embedded in modules for checking their integrity.
+//!
+//! [`embed`] stores a SHA-256 hash of the header and non-custom sections of a
+//! module in a custom section named [`SECTION_NAME`], and [`verify`] checks
+//! that a distributed module still matches the hash embedded in it. Custom
+//! sections aren't covered by the hash, so names and debug information can be
+//! added or stripped without invalidating it, but any change to the code, data
+//! or other items of the module is detected.
+//!
+//! ```
+//! use wasm_tools::integrity;
+//!
+//! let wasm = wat::parse_str(r#"(module (func (export "f")))"#)?;
+//! let wasm = integrity::embed(&wasm)?;
+//! integrity::verify(&wasm)?;
+//!
+//! // Rename the export from `f` to `g`.
+//! let mut tampered = wasm.clone();
+//! let f = tampered.iter().position(|b| *b == b'f').unwrap();
+//! tampered[f] = b'g';
+//! assert!(integrity::verify(&tampered).is_err());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! The custom section holds the name of the hash algorithm as a string,
+//! currently always `sha256`, followed by the hash itself.
+
+use crate::sections::{sections, write_str, Section};
+use anyhow::{bail, Result};
+use sha2::{Digest, Sha256};
+use wasmparser::leb128::write_u32;
+use wasmparser::BinaryReader;
+
+/// The name of the custom section holding the hash of a module.
+pub const SECTION_NAME: &str = "integrity";
+
+/// The name of the only hash algorithm supported.
+const ALGORITHM: &str = "sha256";
+
+/// Returns the SHA-256 hash of the header and non-custom sections of `wasm`.
+///
+/// Each section is hashed as its id, its size encoded as a minimal LEB128 and
+/// its contents, so encoding the sizes of sections differently doesn't change
+/// the hash, nor does adding, removing or moving custom sections.
+pub fn hash(wasm: &[u8]) -> Result<[u8; 32]> {
+    let sections = sections(wasm)?;
+    let mut hasher = Sha256::new();
+    hasher.update(&wasm[..8]);
+    let mut encoded = Vec::new();
+    for section in sections.iter().filter(|s| s.id != 0) {
+        encoded.clear();
+        encoded.push(section.id);
+        write_u32(&mut encoded, section.contents.len() as u32);
+        hasher.update(&encoded);
+        hasher.update(section.contents);
+    }
+    Ok(hasher.finalize().into())
+}
+
+/// Returns `wasm` with the hash of its header and non-custom sections embedded
+/// in a custom section at its end.
+///
+/// Any hash embedded in `wasm` before is removed.
+pub fn embed(wasm: &[u8]) -> Result<Vec<u8>> {
+    let hash = hash(wasm)?;
+    let mut output = wasm[..8].to_vec();
+    for section in sections(wasm)? {
+        if section.custom_name()? != Some(SECTION_NAME) {
+            section.encode(&mut output);
+        }
+    }
+    let mut contents = Vec::new();
+    write_str(&mut contents, SECTION_NAME);
+    write_str(&mut contents, ALGORITHM);
+    contents.extend_from_slice(&hash);
+    Section {
+        id: 0,
+        contents: &contents,
+        offset: 0,
+    }
+    .encode(&mut output);
+    Ok(output)
+}
+
+/// Returns the hash embedded in `wasm` by [`embed`], or `None` if it doesn't
+/// have one.
+///
+/// Returns an error if the hash section is malformed, uses an unknown
+/// algorithm or appears more than once.
+pub fn embedded(wasm: &[u8]) -> Result<Option<[u8; 32]>> {
+    let mut ret = None;
+    for section in sections(wasm)? {
+        if section.custom_name()? != Some(SECTION_NAME) {
+            continue;
+        }
+        if ret.is_some() {
+            bail!("module has more than one `{}` section", SECTION_NAME);
+        }
+        let mut reader = BinaryReader::new(section.contents);
+        reader.read_string()?;
+        let algorithm = reader.read_string()?;
+        if algorithm != ALGORITHM {
+            bail!("unknown hash algorithm `{}`", algorithm);
+        }
+        let mut hash = [0; 32];
+        if reader.bytes_remaining() != hash.len() {
+            bail!("malformed `{}` section", SECTION_NAME);
+        }
+        hash.copy_from_slice(reader.read_bytes(32)?);
+        ret = Some(hash);
+    }
+    Ok(ret)
+}
+
+/// Checks that the hash embedded in `wasm` matches its header and non-custom
+/// sections.
+///
+/// Returns an error if they don't match, or if `wasm` has no embedded hash.
+pub fn verify(wasm: &[u8]) -> Result<()> {
+    let expected = match embedded(wasm)? {
+        Some(hash) => hash,
+        None => bail!("module has no `{}` section", SECTION_NAME),
+    };
+    let actual = hash(wasm)?;
+    if actual != expected {
+        bail!(
+            "module hash {} doesn't match its embedded hash {}",
+            hex(&actual),
+            hex(&expected)
+        );
+    }
+    Ok(())
+}
+
+/// Returns `hash` formatted as lowercase hexadecimal.
+pub fn hex(hash: &[u8]) -> String {
+    hash.iter().map(|b| format!("{:02x}", b)).collect()
+}
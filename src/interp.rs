@@ -0,0 +1,1064 @@
+//! A small reference interpreter for WebAssembly.
+//!
+//! Only a subset of WebAssembly is supported: constant expressions, such as
+//! global initializers and segment offsets, and functions made of
+//! straight-line numeric code, which may use locals, globals and calls but no
+//! control flow, memories or tables. That's enough to run the `assert_return`
+//! directives of many `.wast` scripts without embedding a full engine, see
+//! [`run_wast`]. Anything else fails with an [`Unsupported`] error.
+//!
+//! ```
+//! use wasm_tools::interp::{Instance, Value};
+//!
+//! let wasm = wat::parse_str(r#"
+//!     (module
+//!       (global $g i32 (i32.const 40))
+//!       (func (export "add") (param i32) (result i32)
+//!         local.get 0
+//!         global.get $g
+//!         i32.add))
+//! "#)?;
+//! let mut instance = Instance::new(&wasm)?;
+//! assert_eq!(instance.invoke("add", &[Value::I32(2)])?, [Value::I32(42)]);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+use wasmparser::{
+    DataKind, ElementKind, ExternalKind, FuncType, FunctionBody, MemoryType, Operator,
+    OperatorsReader, Parser, Payload, Type, TypeDef, Validator, WasmFeatures,
+};
+use wast::parser::{self, ParseBuffer};
+use wast::{AssertExpression, Float32, Float64, Instruction, Wast, WastDirective, WastExecute};
+
+/// The maximum depth of nested calls before execution traps.
+const MAX_CALL_DEPTH: usize = 500;
+
+/// A value operated on by WebAssembly.
+///
+/// Floats are represented by their bits so that NaN payloads are preserved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Value {
+    /// An `i32` value.
+    I32(i32),
+    /// An `i64` value.
+    I64(i64),
+    /// The bits of an `f32` value.
+    F32(u32),
+    /// The bits of an `f64` value.
+    F64(u64),
+}
+
+impl Value {
+    fn ty(&self) -> Type {
+        match self {
+            Value::I32(_) => Type::I32,
+            Value::I64(_) => Type::I64,
+            Value::F32(_) => Type::F32,
+            Value::F64(_) => Type::F64,
+        }
+    }
+
+    fn zero(ty: Type) -> Result<Value> {
+        Ok(match ty {
+            Type::I32 => Value::I32(0),
+            Type::I64 => Value::I64(0),
+            Type::F32 => Value::F32(0),
+            Type::F64 => Value::F64(0),
+            _ => return Err(unsupported(format!("values of type {:?}", ty))),
+        })
+    }
+}
+
+/// An error for a trap which aborted execution, such as an integer division
+/// by zero.
+#[derive(Debug)]
+pub struct Trap {
+    message: &'static str,
+}
+
+impl Trap {
+    /// Returns the message the spec uses for this trap, such as `integer
+    /// divide by zero`.
+    pub fn message(&self) -> &str {
+        self.message
+    }
+}
+
+impl fmt::Display for Trap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "trap: {}", self.message)
+    }
+}
+
+impl std::error::Error for Trap {}
+
+/// An error for a part of WebAssembly which this interpreter doesn't support.
+#[derive(Debug)]
+pub struct Unsupported {
+    what: String,
+}
+
+impl fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unsupported by the interpreter: {}", self.what)
+    }
+}
+
+impl std::error::Error for Unsupported {}
+
+pub(crate) fn trap(message: &'static str) -> anyhow::Error {
+    Trap { message }.into()
+}
+
+pub(crate) fn unsupported(what: impl Into<String>) -> anyhow::Error {
+    Unsupported { what: what.into() }.into()
+}
+
+/// An instantiated module.
+pub struct Instance {
+    functions: Vec<Rc<Function>>,
+    globals: Vec<Value>,
+    exports: HashMap<String, (ExternalKind, u32)>,
+}
+
+struct Function {
+    ty: FuncType,
+    locals: Vec<Type>,
+    // The encoded body, starting with its locals, and its offset in the module.
+    body: Vec<u8>,
+    offset: usize,
+}
+
+impl Instance {
+    /// Validates and instantiates the module `wasm`, running its start
+    /// function if it has one.
+    ///
+    /// Returns an [`Unsupported`] error if the module has imports or uses
+    /// features, such as reference types, which this interpreter doesn't
+    /// support, and a [`Trap`] error if a segment is out of bounds or the
+    /// start function traps.
+    pub fn new(wasm: &[u8]) -> Result<Instance> {
+        let mut validator = Validator::new();
+        // `deterministic_only` must match whether wasmparser was built with
+        // its `deterministic` feature, so it comes from the defaults.
+        validator.wasm_features(WasmFeatures {
+            reference_types: true,
+            multi_value: true,
+            bulk_memory: true,
+            module_linking: true,
+            simd: true,
+            threads: true,
+            tail_call: true,
+            multi_memory: true,
+            exceptions: true,
+            memory64: true,
+            wide_arithmetic: true,
+            function_references: true,
+            ..WasmFeatures::default()
+        });
+        validator
+            .validate_all(wasm)
+            .context("failed to validate module")?;
+
+        let mut instance = Instance {
+            functions: Vec::new(),
+            globals: Vec::new(),
+            exports: HashMap::new(),
+        };
+        let mut types = Vec::new();
+        let mut functions = Vec::new();
+        let mut tables = Vec::new();
+        let mut memories = Vec::new();
+        let mut start = None;
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload? {
+                Payload::TypeSection(s) => {
+                    for ty in s {
+                        match ty? {
+                            TypeDef::Func(ty) => types.push(ty),
+                            _ => return Err(unsupported("module linking types")),
+                        }
+                    }
+                }
+                Payload::ImportSection(s) if s.get_count() > 0 => {
+                    return Err(unsupported("imports"));
+                }
+                Payload::FunctionSection(s) => {
+                    for ty in s {
+                        functions.push(ty?);
+                    }
+                }
+                Payload::TableSection(s) => {
+                    for table in s {
+                        tables.push(u64::from(table?.limits.initial));
+                    }
+                }
+                Payload::MemorySection(s) => {
+                    for memory in s {
+                        memories.push(match memory? {
+                            MemoryType::M32 { limits, .. } => u64::from(limits.initial),
+                            MemoryType::M64 { limits, .. } => limits.initial,
+                        });
+                    }
+                }
+                Payload::GlobalSection(s) => {
+                    for global in s {
+                        let value =
+                            instance.eval_const(global?.init_expr.get_operators_reader())?;
+                        instance.globals.push(value);
+                    }
+                }
+                Payload::ExportSection(s) => {
+                    for export in s {
+                        let export = export?;
+                        instance
+                            .exports
+                            .insert(export.field.to_string(), (export.kind, export.index));
+                    }
+                }
+                Payload::StartSection { func, .. } => start = Some(func),
+                Payload::ElementSection(s) => {
+                    for element in s {
+                        let element = element?;
+                        if let ElementKind::Active {
+                            table_index,
+                            init_expr,
+                        } = element.kind
+                        {
+                            let offset = instance.eval_offset(init_expr.get_operators_reader())?;
+                            let count = element.items.get_items_reader()?.get_count();
+                            if offset + u64::from(count) > tables[table_index as usize] {
+                                return Err(trap("out of bounds table access"));
+                            }
+                        }
+                    }
+                }
+                Payload::DataSection(s) => {
+                    for data in s {
+                        let data = data?;
+                        if let DataKind::Active {
+                            memory_index,
+                            init_expr,
+                        } = data.kind
+                        {
+                            let offset = instance.eval_offset(init_expr.get_operators_reader())?;
+                            let size = memories[memory_index as usize].saturating_mul(65536);
+                            if offset.saturating_add(data.data.len() as u64) > size {
+                                return Err(trap("out of bounds memory access"));
+                            }
+                        }
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    let index = instance.functions.len();
+                    let ty = types[functions[index] as usize].clone();
+                    let mut locals = Vec::new();
+                    let mut reader = body.get_locals_reader()?;
+                    for _ in 0..reader.get_count() {
+                        let (count, ty) = reader.read()?;
+                        locals.extend((0..count).map(|_| ty));
+                    }
+                    let range = body.range();
+                    instance.functions.push(Rc::new(Function {
+                        ty,
+                        locals,
+                        body: wasm[range.start..range.end].to_vec(),
+                        offset: range.start,
+                    }));
+                }
+                Payload::AliasSection(_)
+                | Payload::InstanceSection(_)
+                | Payload::ModuleSectionStart { .. } => {
+                    return Err(unsupported("module linking"));
+                }
+                Payload::EventSection(_) => return Err(unsupported("exception handling")),
+                _ => {}
+            }
+        }
+
+        if let Some(start) = start {
+            instance.call(start, Vec::new(), 0)?;
+        }
+        Ok(instance)
+    }
+
+    /// Calls the exported function `name` with `args`, returning its results.
+    ///
+    /// Returns a [`Trap`] error if the function traps and an [`Unsupported`]
+    /// error if it executes instructions which this interpreter doesn't
+    /// support.
+    pub fn invoke(&mut self, name: &str, args: &[Value]) -> Result<Vec<Value>> {
+        let index = match self.exports.get(name) {
+            Some((ExternalKind::Function, index)) => *index,
+            _ => bail!("no exported function named `{}`", name),
+        };
+        let params = &self.functions[index as usize].ty.params;
+        if args.len() != params.len() || args.iter().zip(params.iter()).any(|(a, p)| a.ty() != *p) {
+            bail!("wrong arguments for function `{}`: {:?}", name, args);
+        }
+        self.call(index, args.to_vec(), 0)
+    }
+
+    /// Returns the current value of the exported global `name`, if there is
+    /// one.
+    pub fn global(&self, name: &str) -> Option<Value> {
+        match self.exports.get(name) {
+            Some((ExternalKind::Global, index)) => Some(self.globals[*index as usize]),
+            _ => None,
+        }
+    }
+
+    fn eval_const(&self, mut reader: OperatorsReader<'_>) -> Result<Value> {
+        let mut stack = Stack(Vec::new());
+        loop {
+            match reader.read()? {
+                Operator::I32Const { value } => stack.push(Value::I32(value)),
+                Operator::I64Const { value } => stack.push(Value::I64(value)),
+                Operator::F32Const { value } => stack.push(Value::F32(value.bits())),
+                Operator::F64Const { value } => stack.push(Value::F64(value.bits())),
+                Operator::GlobalGet { global_index } => {
+                    stack.push(self.globals[global_index as usize])
+                }
+                Operator::End => break,
+                op => return Err(unsupported(format!("constant expression {:?}", op))),
+            }
+        }
+        Ok(stack.pop())
+    }
+
+    fn eval_offset(&self, reader: OperatorsReader<'_>) -> Result<u64> {
+        Ok(match self.eval_const(reader)? {
+            Value::I32(offset) => u64::from(offset as u32),
+            Value::I64(offset) => offset as u64,
+            _ => unreachable!("offsets are validated to be integers"),
+        })
+    }
+
+    fn call(&mut self, index: u32, mut locals: Vec<Value>, depth: usize) -> Result<Vec<Value>> {
+        if depth >= MAX_CALL_DEPTH {
+            return Err(trap("call stack exhausted"));
+        }
+        let func = self.functions[index as usize].clone();
+        for ty in func.locals.iter() {
+            locals.push(Value::zero(*ty)?);
+        }
+
+        use Value::*;
+        let mut s = Stack(Vec::new());
+        let mut reader = FunctionBody::new(func.offset, &func.body).get_operators_reader()?;
+        loop {
+            match reader.read()? {
+                // Without blocks the first `end` is the end of the function.
+                Operator::End | Operator::Return => break,
+                Operator::Nop => {}
+                Operator::Unreachable => return Err(trap("unreachable")),
+                Operator::Drop => drop(s.pop()),
+                Operator::Select | Operator::TypedSelect { .. } => {
+                    let c = s.i32();
+                    let b = s.pop();
+                    let a = s.pop();
+                    s.push(if c != 0 { a } else { b });
+                }
+                Operator::LocalGet { local_index } => s.push(locals[local_index as usize]),
+                Operator::LocalSet { local_index } => locals[local_index as usize] = s.pop(),
+                Operator::LocalTee { local_index } => {
+                    let v = s.pop();
+                    locals[local_index as usize] = v;
+                    s.push(v);
+                }
+                Operator::GlobalGet { global_index } => s.push(self.globals[global_index as usize]),
+                Operator::GlobalSet { global_index } => {
+                    self.globals[global_index as usize] = s.pop()
+                }
+                Operator::Call { function_index } => {
+                    let params = self.functions[function_index as usize].ty.params.len();
+                    let args = s.0.split_off(s.0.len() - params);
+                    let results = self.call(function_index, args, depth + 1)?;
+                    s.0.extend(results);
+                }
+
+                Operator::I32Const { value } => s.push(I32(value)),
+                Operator::I64Const { value } => s.push(I64(value)),
+                Operator::F32Const { value } => s.push(F32(value.bits())),
+                Operator::F64Const { value } => s.push(F64(value.bits())),
+
+                Operator::I32Eqz => {
+                    let a = s.i32();
+                    s.bool(a == 0)
+                }
+                Operator::I32Eq => s.i32_cmp(|a, b| a == b),
+                Operator::I32Ne => s.i32_cmp(|a, b| a != b),
+                Operator::I32LtS => s.i32_cmp(|a, b| a < b),
+                Operator::I32LtU => s.i32_cmp(|a, b| (a as u32) < (b as u32)),
+                Operator::I32GtS => s.i32_cmp(|a, b| a > b),
+                Operator::I32GtU => s.i32_cmp(|a, b| (a as u32) > (b as u32)),
+                Operator::I32LeS => s.i32_cmp(|a, b| a <= b),
+                Operator::I32LeU => s.i32_cmp(|a, b| (a as u32) <= (b as u32)),
+                Operator::I32GeS => s.i32_cmp(|a, b| a >= b),
+                Operator::I32GeU => s.i32_cmp(|a, b| (a as u32) >= (b as u32)),
+
+                Operator::I64Eqz => {
+                    let a = s.i64();
+                    s.bool(a == 0)
+                }
+                Operator::I64Eq => s.i64_cmp(|a, b| a == b),
+                Operator::I64Ne => s.i64_cmp(|a, b| a != b),
+                Operator::I64LtS => s.i64_cmp(|a, b| a < b),
+                Operator::I64LtU => s.i64_cmp(|a, b| (a as u64) < (b as u64)),
+                Operator::I64GtS => s.i64_cmp(|a, b| a > b),
+                Operator::I64GtU => s.i64_cmp(|a, b| (a as u64) > (b as u64)),
+                Operator::I64LeS => s.i64_cmp(|a, b| a <= b),
+                Operator::I64LeU => s.i64_cmp(|a, b| (a as u64) <= (b as u64)),
+                Operator::I64GeS => s.i64_cmp(|a, b| a >= b),
+                Operator::I64GeU => s.i64_cmp(|a, b| (a as u64) >= (b as u64)),
+
+                Operator::F32Eq => s.f32_cmp(|a, b| a == b),
+                Operator::F32Ne => s.f32_cmp(|a, b| a != b),
+                Operator::F32Lt => s.f32_cmp(|a, b| a < b),
+                Operator::F32Gt => s.f32_cmp(|a, b| a > b),
+                Operator::F32Le => s.f32_cmp(|a, b| a <= b),
+                Operator::F32Ge => s.f32_cmp(|a, b| a >= b),
+
+                Operator::F64Eq => s.f64_cmp(|a, b| a == b),
+                Operator::F64Ne => s.f64_cmp(|a, b| a != b),
+                Operator::F64Lt => s.f64_cmp(|a, b| a < b),
+                Operator::F64Gt => s.f64_cmp(|a, b| a > b),
+                Operator::F64Le => s.f64_cmp(|a, b| a <= b),
+                Operator::F64Ge => s.f64_cmp(|a, b| a >= b),
+
+                Operator::I32Clz => s.i32_unop(|a| a.leading_zeros() as i32),
+                Operator::I32Ctz => s.i32_unop(|a| a.trailing_zeros() as i32),
+                Operator::I32Popcnt => s.i32_unop(|a| a.count_ones() as i32),
+                Operator::I32Add => s.i32_binop(|a, b| a.wrapping_add(b)),
+                Operator::I32Sub => s.i32_binop(|a, b| a.wrapping_sub(b)),
+                Operator::I32Mul => s.i32_binop(|a, b| a.wrapping_mul(b)),
+                Operator::I32DivS => {
+                    let (a, b) = (s.i32(), s.i32());
+                    s.push(I32(div_s32(b, a)?));
+                }
+                Operator::I32DivU => {
+                    let (a, b) = (s.i32(), s.i32());
+                    s.push(I32((b as u32 / nonzero(a)? as u32) as i32));
+                }
+                Operator::I32RemS => {
+                    let (a, b) = (s.i32(), s.i32());
+                    s.push(I32(b.wrapping_rem(nonzero(a)?)));
+                }
+                Operator::I32RemU => {
+                    let (a, b) = (s.i32(), s.i32());
+                    s.push(I32((b as u32 % nonzero(a)? as u32) as i32));
+                }
+                Operator::I32And => s.i32_binop(|a, b| a & b),
+                Operator::I32Or => s.i32_binop(|a, b| a | b),
+                Operator::I32Xor => s.i32_binop(|a, b| a ^ b),
+                Operator::I32Shl => s.i32_binop(|a, b| a.wrapping_shl(b as u32)),
+                Operator::I32ShrS => s.i32_binop(|a, b| a.wrapping_shr(b as u32)),
+                Operator::I32ShrU => s.i32_binop(|a, b| (a as u32).wrapping_shr(b as u32) as i32),
+                Operator::I32Rotl => s.i32_binop(|a, b| a.rotate_left(b as u32)),
+                Operator::I32Rotr => s.i32_binop(|a, b| a.rotate_right(b as u32)),
+
+                Operator::I64Clz => s.i64_unop(|a| i64::from(a.leading_zeros())),
+                Operator::I64Ctz => s.i64_unop(|a| i64::from(a.trailing_zeros())),
+                Operator::I64Popcnt => s.i64_unop(|a| i64::from(a.count_ones())),
+                Operator::I64Add => s.i64_binop(|a, b| a.wrapping_add(b)),
+                Operator::I64Sub => s.i64_binop(|a, b| a.wrapping_sub(b)),
+                Operator::I64Mul => s.i64_binop(|a, b| a.wrapping_mul(b)),
+                Operator::I64DivS => {
+                    let (a, b) = (s.i64(), s.i64());
+                    s.push(I64(div_s64(b, a)?));
+                }
+                Operator::I64DivU => {
+                    let (a, b) = (s.i64(), s.i64());
+                    s.push(I64((b as u64 / nonzero(a)? as u64) as i64));
+                }
+                Operator::I64RemS => {
+                    let (a, b) = (s.i64(), s.i64());
+                    s.push(I64(b.wrapping_rem(nonzero(a)?)));
+                }
+                Operator::I64RemU => {
+                    let (a, b) = (s.i64(), s.i64());
+                    s.push(I64((b as u64 % nonzero(a)? as u64) as i64));
+                }
+                Operator::I64And => s.i64_binop(|a, b| a & b),
+                Operator::I64Or => s.i64_binop(|a, b| a | b),
+                Operator::I64Xor => s.i64_binop(|a, b| a ^ b),
+                Operator::I64Shl => s.i64_binop(|a, b| a.wrapping_shl(b as u32)),
+                Operator::I64ShrS => s.i64_binop(|a, b| a.wrapping_shr(b as u32)),
+                Operator::I64ShrU => s.i64_binop(|a, b| (a as u64).wrapping_shr(b as u32) as i64),
+                Operator::I64Rotl => s.i64_binop(|a, b| a.rotate_left(b as u32)),
+                Operator::I64Rotr => s.i64_binop(|a, b| a.rotate_right(b as u32)),
+
+                // Sign manipulation is done on the bits so that it applies to
+                // NaNs as well.
+                Operator::F32Abs => s.f32_bits_unop(|a| a & !F32_SIGN),
+                Operator::F32Neg => s.f32_bits_unop(|a| a ^ F32_SIGN),
+                Operator::F32Copysign => {
+                    let (b, a) = (s.f32_bits(), s.f32_bits());
+                    s.push(F32((a & !F32_SIGN) | (b & F32_SIGN)));
+                }
+                Operator::F32Ceil => s.f32_unop(f32::ceil),
+                Operator::F32Floor => s.f32_unop(f32::floor),
+                Operator::F32Trunc => s.f32_unop(f32::trunc),
+                Operator::F32Nearest => s.f32_unop(nearest32),
+                Operator::F32Sqrt => s.f32_unop(f32::sqrt),
+                Operator::F32Add => s.f32_binop(|a, b| a + b),
+                Operator::F32Sub => s.f32_binop(|a, b| a - b),
+                Operator::F32Mul => s.f32_binop(|a, b| a * b),
+                Operator::F32Div => s.f32_binop(|a, b| a / b),
+                Operator::F32Min => s.f32_binop(min32),
+                Operator::F32Max => s.f32_binop(max32),
+
+                Operator::F64Abs => s.f64_bits_unop(|a| a & !F64_SIGN),
+                Operator::F64Neg => s.f64_bits_unop(|a| a ^ F64_SIGN),
+                Operator::F64Copysign => {
+                    let (b, a) = (s.f64_bits(), s.f64_bits());
+                    s.push(F64((a & !F64_SIGN) | (b & F64_SIGN)));
+                }
+                Operator::F64Ceil => s.f64_unop(f64::ceil),
+                Operator::F64Floor => s.f64_unop(f64::floor),
+                Operator::F64Trunc => s.f64_unop(f64::trunc),
+                Operator::F64Nearest => s.f64_unop(nearest64),
+                Operator::F64Sqrt => s.f64_unop(f64::sqrt),
+                Operator::F64Add => s.f64_binop(|a, b| a + b),
+                Operator::F64Sub => s.f64_binop(|a, b| a - b),
+                Operator::F64Mul => s.f64_binop(|a, b| a * b),
+                Operator::F64Div => s.f64_binop(|a, b| a / b),
+                Operator::F64Min => s.f64_binop(min64),
+                Operator::F64Max => s.f64_binop(max64),
+
+                Operator::I32WrapI64 => {
+                    let a = s.i64();
+                    s.push(I32(a as i32));
+                }
+                Operator::I32TruncF32S => {
+                    let a = s.f32();
+                    s.push(I32(trunc(f64::from(a), -2147483648.0, 2147483648.0)? as i32));
+                }
+                Operator::I32TruncF32U => {
+                    let a = s.f32();
+                    s.push(I32(trunc(f64::from(a), 0.0, 4294967296.0)? as u32 as i32));
+                }
+                Operator::I32TruncF64S => {
+                    let a = s.f64();
+                    s.push(I32(trunc(a, -2147483648.0, 2147483648.0)? as i32));
+                }
+                Operator::I32TruncF64U => {
+                    let a = s.f64();
+                    s.push(I32(trunc(a, 0.0, 4294967296.0)? as u32 as i32));
+                }
+                Operator::I64ExtendI32S => {
+                    let a = s.i32();
+                    s.push(I64(i64::from(a)));
+                }
+                Operator::I64ExtendI32U => {
+                    let a = s.i32();
+                    s.push(I64(i64::from(a as u32)));
+                }
+                Operator::I64TruncF32S => {
+                    let a = s.f32();
+                    s.push(I64(trunc(f64::from(a), I64_MIN, I64_END)? as i64));
+                }
+                Operator::I64TruncF32U => {
+                    let a = s.f32();
+                    s.push(I64(trunc(f64::from(a), 0.0, U64_END)? as u64 as i64));
+                }
+                Operator::I64TruncF64S => {
+                    let a = s.f64();
+                    s.push(I64(trunc(a, I64_MIN, I64_END)? as i64));
+                }
+                Operator::I64TruncF64U => {
+                    let a = s.f64();
+                    s.push(I64(trunc(a, 0.0, U64_END)? as u64 as i64));
+                }
+                Operator::F32ConvertI32S => {
+                    let a = s.i32();
+                    s.push(F32((a as f32).to_bits()));
+                }
+                Operator::F32ConvertI32U => {
+                    let a = s.i32();
+                    s.push(F32((a as u32 as f32).to_bits()));
+                }
+                Operator::F32ConvertI64S => {
+                    let a = s.i64();
+                    s.push(F32((a as f32).to_bits()));
+                }
+                Operator::F32ConvertI64U => {
+                    let a = s.i64();
+                    s.push(F32((a as u64 as f32).to_bits()));
+                }
+                Operator::F32DemoteF64 => {
+                    let a = s.f64();
+                    s.push(F32((a as f32).to_bits()));
+                }
+                Operator::F64ConvertI32S => {
+                    let a = s.i32();
+                    s.push(F64(f64::from(a).to_bits()));
+                }
+                Operator::F64ConvertI32U => {
+                    let a = s.i32();
+                    s.push(F64(f64::from(a as u32).to_bits()));
+                }
+                Operator::F64ConvertI64S => {
+                    let a = s.i64();
+                    s.push(F64((a as f64).to_bits()));
+                }
+                Operator::F64ConvertI64U => {
+                    let a = s.i64();
+                    s.push(F64((a as u64 as f64).to_bits()));
+                }
+                Operator::F64PromoteF32 => {
+                    let a = s.f32();
+                    s.push(F64(f64::from(a).to_bits()));
+                }
+                Operator::I32ReinterpretF32 => {
+                    let a = s.f32_bits();
+                    s.push(I32(a as i32));
+                }
+                Operator::I64ReinterpretF64 => {
+                    let a = s.f64_bits();
+                    s.push(I64(a as i64));
+                }
+                Operator::F32ReinterpretI32 => {
+                    let a = s.i32();
+                    s.push(F32(a as u32));
+                }
+                Operator::F64ReinterpretI64 => {
+                    let a = s.i64();
+                    s.push(F64(a as u64));
+                }
+
+                Operator::I32Extend8S => s.i32_unop(|a| i32::from(a as i8)),
+                Operator::I32Extend16S => s.i32_unop(|a| i32::from(a as i16)),
+                Operator::I64Extend8S => s.i64_unop(|a| i64::from(a as i8)),
+                Operator::I64Extend16S => s.i64_unop(|a| i64::from(a as i16)),
+                Operator::I64Extend32S => s.i64_unop(|a| i64::from(a as i32)),
+
+                // Casts with `as` saturate and turn NaN into zero, exactly as
+                // these instructions are specified.
+                Operator::I32TruncSatF32S => {
+                    let a = s.f32();
+                    s.push(I32(a as i32));
+                }
+                Operator::I32TruncSatF32U => {
+                    let a = s.f32();
+                    s.push(I32(a as u32 as i32));
+                }
+                Operator::I32TruncSatF64S => {
+                    let a = s.f64();
+                    s.push(I32(a as i32));
+                }
+                Operator::I32TruncSatF64U => {
+                    let a = s.f64();
+                    s.push(I32(a as u32 as i32));
+                }
+                Operator::I64TruncSatF32S => {
+                    let a = s.f32();
+                    s.push(I64(a as i64));
+                }
+                Operator::I64TruncSatF32U => {
+                    let a = s.f32();
+                    s.push(I64(a as u64 as i64));
+                }
+                Operator::I64TruncSatF64S => {
+                    let a = s.f64();
+                    s.push(I64(a as i64));
+                }
+                Operator::I64TruncSatF64U => {
+                    let a = s.f64();
+                    s.push(I64(a as u64 as i64));
+                }
+
+                op => return Err(unsupported(format!("instruction {:?}", op))),
+            }
+        }
+
+        let results = func.ty.returns.len();
+        Ok(s.0.split_off(s.0.len() - results))
+    }
+}
+
+const F32_SIGN: u32 = 1 << 31;
+const F64_SIGN: u64 = 1 << 63;
+const I64_MIN: f64 = -9223372036854775808.0;
+const I64_END: f64 = 9223372036854775808.0;
+const U64_END: f64 = 18446744073709551616.0;
+
+/// The operand stack of a function, whose operands are always of the right
+/// types since functions are validated before they run.
+struct Stack(Vec<Value>);
+
+impl Stack {
+    fn push(&mut self, value: Value) {
+        self.0.push(value);
+    }
+
+    fn pop(&mut self) -> Value {
+        self.0.pop().expect("validated stack height")
+    }
+
+    fn bool(&mut self, b: bool) {
+        self.push(Value::I32(b as i32));
+    }
+
+    fn i32(&mut self) -> i32 {
+        match self.pop() {
+            Value::I32(v) => v,
+            _ => unreachable!("validated operand type"),
+        }
+    }
+
+    fn i64(&mut self) -> i64 {
+        match self.pop() {
+            Value::I64(v) => v,
+            _ => unreachable!("validated operand type"),
+        }
+    }
+
+    fn f32_bits(&mut self) -> u32 {
+        match self.pop() {
+            Value::F32(v) => v,
+            _ => unreachable!("validated operand type"),
+        }
+    }
+
+    fn f64_bits(&mut self) -> u64 {
+        match self.pop() {
+            Value::F64(v) => v,
+            _ => unreachable!("validated operand type"),
+        }
+    }
+
+    fn f32(&mut self) -> f32 {
+        f32::from_bits(self.f32_bits())
+    }
+
+    fn f64(&mut self) -> f64 {
+        f64::from_bits(self.f64_bits())
+    }
+
+    fn i32_unop(&mut self, f: impl FnOnce(i32) -> i32) {
+        let a = self.i32();
+        self.push(Value::I32(f(a)));
+    }
+
+    fn i32_binop(&mut self, f: impl FnOnce(i32, i32) -> i32) {
+        let b = self.i32();
+        let a = self.i32();
+        self.push(Value::I32(f(a, b)));
+    }
+
+    fn i32_cmp(&mut self, f: impl FnOnce(i32, i32) -> bool) {
+        let b = self.i32();
+        let a = self.i32();
+        self.bool(f(a, b));
+    }
+
+    fn i64_unop(&mut self, f: impl FnOnce(i64) -> i64) {
+        let a = self.i64();
+        self.push(Value::I64(f(a)));
+    }
+
+    fn i64_binop(&mut self, f: impl FnOnce(i64, i64) -> i64) {
+        let b = self.i64();
+        let a = self.i64();
+        self.push(Value::I64(f(a, b)));
+    }
+
+    fn i64_cmp(&mut self, f: impl FnOnce(i64, i64) -> bool) {
+        let b = self.i64();
+        let a = self.i64();
+        self.bool(f(a, b));
+    }
+
+    fn f32_bits_unop(&mut self, f: impl FnOnce(u32) -> u32) {
+        let a = self.f32_bits();
+        self.push(Value::F32(f(a)));
+    }
+
+    fn f32_unop(&mut self, f: impl FnOnce(f32) -> f32) {
+        let a = self.f32();
+        self.push(Value::F32(f(a).to_bits()));
+    }
+
+    fn f32_binop(&mut self, f: impl FnOnce(f32, f32) -> f32) {
+        let b = self.f32();
+        let a = self.f32();
+        self.push(Value::F32(f(a, b).to_bits()));
+    }
+
+    fn f32_cmp(&mut self, f: impl FnOnce(f32, f32) -> bool) {
+        let b = self.f32();
+        let a = self.f32();
+        self.bool(f(a, b));
+    }
+
+    fn f64_bits_unop(&mut self, f: impl FnOnce(u64) -> u64) {
+        let a = self.f64_bits();
+        self.push(Value::F64(f(a)));
+    }
+
+    fn f64_unop(&mut self, f: impl FnOnce(f64) -> f64) {
+        let a = self.f64();
+        self.push(Value::F64(f(a).to_bits()));
+    }
+
+    fn f64_binop(&mut self, f: impl FnOnce(f64, f64) -> f64) {
+        let b = self.f64();
+        let a = self.f64();
+        self.push(Value::F64(f(a, b).to_bits()));
+    }
+
+    fn f64_cmp(&mut self, f: impl FnOnce(f64, f64) -> bool) {
+        let b = self.f64();
+        let a = self.f64();
+        self.bool(f(a, b));
+    }
+}
+
+/// Returns the divisor `n`, trapping if it's zero.
+fn nonzero<T: Default + PartialEq>(n: T) -> Result<T> {
+    if n == T::default() {
+        return Err(trap("integer divide by zero"));
+    }
+    Ok(n)
+}
+
+fn div_s32(a: i32, b: i32) -> Result<i32> {
+    nonzero(b)?;
+    a.checked_div(b).ok_or_else(|| trap("integer overflow"))
+}
+
+fn div_s64(a: i64, b: i64) -> Result<i64> {
+    nonzero(b)?;
+    a.checked_div(b).ok_or_else(|| trap("integer overflow"))
+}
+
+/// Truncates `x` towards zero, trapping unless the result is within
+/// `min..end`.
+fn trunc(x: f64, min: f64, end: f64) -> Result<f64> {
+    if x.is_nan() {
+        return Err(trap("invalid conversion to integer"));
+    }
+    let x = x.trunc();
+    if x < min || x >= end {
+        return Err(trap("integer overflow"));
+    }
+    Ok(x)
+}
+
+macro_rules! float_ops {
+    ($($t:ident: $min:ident $max:ident $nearest:ident)*) => ($(
+        // Unlike `f32::min` and friends, NaN operands produce NaN and negative
+        // zero is less than positive zero.
+        fn $min(a: $t, b: $t) -> $t {
+            if a.is_nan() || b.is_nan() {
+                $t::NAN
+            } else if a == b {
+                if a.is_sign_negative() { a } else { b }
+            } else {
+                a.min(b)
+            }
+        }
+
+        fn $max(a: $t, b: $t) -> $t {
+            if a.is_nan() || b.is_nan() {
+                $t::NAN
+            } else if a == b {
+                if a.is_sign_positive() { a } else { b }
+            } else {
+                a.max(b)
+            }
+        }
+
+        // Rounds to the nearest integer, with ties to even.
+        fn $nearest(a: $t) -> $t {
+            let rounded = a.round();
+            if (rounded - a).abs() == 0.5 {
+                2.0 * (a / 2.0).round()
+            } else {
+                rounded
+            }
+        }
+    )*)
+}
+
+float_ops! {
+    f32: min32 max32 nearest32
+    f64: min64 max64 nearest64
+}
+
+/// The outcome of running a `.wast` script with [`run_wast`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WastStats {
+    /// The number of directives which ran and whose assertions held.
+    pub passed: usize,
+    /// The number of directives which were skipped because they need support
+    /// this interpreter doesn't have, such as imports, control flow or
+    /// assertions about malformed modules.
+    pub skipped: usize,
+}
+
+/// Runs the `.wast` script `wast`, executing its modules, `invoke`s and
+/// `assert_return`, `assert_trap` and `assert_exhaustion` directives.
+///
+/// Directives which can't be run are skipped, as are all directives using a
+/// module which can't be instantiated. Returns an error for the first
+/// directive which fails.
+pub fn run_wast(wast: &str) -> Result<WastStats> {
+    let adjust = |mut e: wast::Error| {
+        e.set_text(wast);
+        e
+    };
+    let buf = ParseBuffer::new(wast).map_err(adjust)?;
+    let script = parser::parse::<Wast>(&buf).map_err(adjust)?;
+    let mut runner = WastRunner::default();
+    for directive in script.directives {
+        let (line, col) = directive.span().linecol_in(wast);
+        match runner.run(directive) {
+            Ok(true) => runner.stats.passed += 1,
+            Ok(false) => runner.stats.skipped += 1,
+            Err(e) if e.is::<Unsupported>() => runner.stats.skipped += 1,
+            Err(e) => {
+                return Err(e.context(format!(
+                    "failed directive on line {} column {}",
+                    line + 1,
+                    col + 1
+                )))
+            }
+        }
+    }
+    Ok(runner.stats)
+}
+
+#[derive(Default)]
+struct WastRunner<'a> {
+    instances: Vec<Option<Instance>>,
+    names: HashMap<&'a str, usize>,
+    stats: WastStats,
+}
+
+impl<'a> WastRunner<'a> {
+    /// Runs `directive`, returning whether it ran or was skipped.
+    fn run(&mut self, directive: WastDirective<'a>) -> Result<bool> {
+        match directive {
+            WastDirective::Module(mut module) => {
+                let instance = match Instance::new(&module.encode()?) {
+                    Ok(instance) => Some(instance),
+                    Err(e) if e.is::<Unsupported>() => None,
+                    Err(e) => return Err(e),
+                };
+                let ran = instance.is_some();
+                if let Some(id) = module.id {
+                    self.names.insert(id.name(), self.instances.len());
+                }
+                self.instances.push(instance);
+                Ok(ran)
+            }
+            WastDirective::Invoke(invoke) => {
+                self.execute(WastExecute::Invoke(invoke))?;
+                Ok(true)
+            }
+            WastDirective::AssertReturn { exec, results, .. } => {
+                let values = self.execute(exec)?;
+                if values.len() != results.len() {
+                    bail!("expected {} results, got {:?}", results.len(), values);
+                }
+                for (value, expected) in values.iter().zip(results.iter()) {
+                    if !matches(*value, expected)? {
+                        bail!("expected {:?}, got {:?}", expected, value);
+                    }
+                }
+                Ok(true)
+            }
+            WastDirective::AssertTrap { exec, message, .. } => self.assert_trap(exec, message),
+            WastDirective::AssertExhaustion { call, message, .. } => {
+                self.assert_trap(WastExecute::Invoke(call), message)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn assert_trap(&mut self, exec: WastExecute<'a>, message: &str) -> Result<bool> {
+        match self.execute(exec) {
+            Ok(values) => bail!("expected trap `{}`, got {:?}", message, values),
+            Err(e) => match e.downcast_ref::<Trap>() {
+                Some(trap) if trap.message().contains(message) => Ok(true),
+                Some(trap) => bail!("expected trap `{}`, got `{}`", message, trap.message()),
+                None => Err(e),
+            },
+        }
+    }
+
+    fn execute(&mut self, exec: WastExecute<'a>) -> Result<Vec<Value>> {
+        match exec {
+            WastExecute::Invoke(invoke) => {
+                let args = invoke
+                    .args
+                    .iter()
+                    .map(|arg| match &arg.instrs[..] {
+                        [Instruction::I32Const(v)] => Ok(Value::I32(*v)),
+                        [Instruction::I64Const(v)] => Ok(Value::I64(*v)),
+                        [Instruction::F32Const(v)] => Ok(Value::F32(v.bits)),
+                        [Instruction::F64Const(v)] => Ok(Value::F64(v.bits)),
+                        _ => Err(unsupported("non-numeric arguments")),
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                self.instance(invoke.module)?.invoke(invoke.name, &args)
+            }
+            WastExecute::Get { module, global } => match self.instance(module)?.global(global) {
+                Some(value) => Ok(vec![value]),
+                None => bail!("no exported global named `{}`", global),
+            },
+            WastExecute::Module(mut module) => {
+                Instance::new(&module.encode()?)?;
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    fn instance(&mut self, id: Option<wast::Id<'a>>) -> Result<&mut Instance> {
+        let index = match id {
+            Some(id) => match self.names.get(id.name()) {
+                Some(index) => *index,
+                None => bail!("unknown module `{}`", id.name()),
+            },
+            None => match self.instances.len().checked_sub(1) {
+                Some(index) => index,
+                None => bail!("no module has been defined"),
+            },
+        };
+        self.instances[index]
+            .as_mut()
+            .ok_or_else(|| unsupported("a module which couldn't be instantiated"))
+    }
+}
+
+fn matches(value: Value, expected: &AssertExpression<'_>) -> Result<bool> {
+    Ok(match (value, expected) {
+        (Value::I32(v), AssertExpression::I32(e)) => v == *e,
+        (Value::I64(v), AssertExpression::I64(e)) => v == *e,
+        (Value::F32(v), AssertExpression::F32(e)) => e.matches(v),
+        (Value::F64(v), AssertExpression::F64(e)) => e.matches(v),
+        (Value::F32(bits), AssertExpression::LegacyCanonicalNaN) => {
+            Float32 { bits }.is_canonical_nan()
+        }
+        (Value::F64(bits), AssertExpression::LegacyCanonicalNaN) => {
+            Float64 { bits }.is_canonical_nan()
+        }
+        (Value::F32(bits), AssertExpression::LegacyArithmeticNaN) => {
+            Float32 { bits }.is_arithmetic_nan()
+        }
+        (Value::F64(bits), AssertExpression::LegacyArithmeticNaN) => {
+            Float64 { bits }.is_arithmetic_nan()
+        }
+        (_, AssertExpression::V128(_))
+        | (_, AssertExpression::RefNull(_))
+        | (_, AssertExpression::RefExtern(_))
+        | (_, AssertExpression::RefFunc(_)) => return Err(unsupported("non-numeric results")),
+        _ => false,
+    })
+}
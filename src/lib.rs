@@ -0,0 +1,101 @@
+//! Analyses and transformations of WebAssembly modules.
+//!
+//! This crate is the library behind the `wasm-tools` command line tool. Each
+//! of the tool's subcommands is implemented by one of the modules below,
+//! which can also be used on its own, and most of them work directly on
+//! binary modules.
+//!
+//! The parsing and printing of modules is left to the `wasmparser`,
+//! `wasmprinter` and `wat` crates, whose types appear in the APIs here.
+//!
+//! The public API of this crate follows semantic versioning like the other
+//! crates of this repository.
+//!
+//! Support for tests which round trip modules between the text and binary
+//! formats is in the `roundtrip` module, which is only available with the
+//! `test-support` feature.
+
+use anyhow::{Context, Result};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+
+pub mod analysis;
+pub mod canonicalize;
+pub mod capabilities;
+pub mod cfg;
+pub mod functions;
+pub mod gc;
+pub mod init;
+pub mod instrument;
+pub mod integrity;
+pub mod interp;
+pub mod link;
+pub mod liveness;
+pub mod lower;
+pub mod merge;
+pub mod metering;
+pub mod names;
+pub mod objdump;
+pub mod optimize;
+pub mod rename;
+#[cfg(feature = "test-support")]
+pub mod roundtrip;
+pub mod signature;
+pub mod snapshot;
+pub mod split;
+pub mod stub;
+pub mod summary;
+pub mod usage;
+
+mod inject;
+mod sections;
+
+/// Reads the contents of `input`, or of stdin if `input` is `None` or `-`.
+pub fn read_input(input: Option<&str>) -> Result<Vec<u8>> {
+    match input {
+        Some(path) if path != "-" => {
+            std::fs::read(path).context(format!("failed to read `{}`", path))
+        }
+        _ => {
+            let mut ret = Vec::new();
+            std::io::stdin()
+                .read_to_end(&mut ret)
+                .context("failed to read <stdin>")?;
+            Ok(ret)
+        }
+    }
+}
+
+/// Converts `input` to a WebAssembly binary.
+///
+/// The input is auto-detected as either the text format or the binary format.
+/// Binary inputs are returned as-is and text inputs are parsed and assembled.
+pub fn parse_input(input: &[u8]) -> Result<Cow<'_, [u8]>> {
+    Ok(wat::parse_bytes(input)?)
+}
+
+/// Opens the file `output`, or stdout if `output` is `None`, to write output
+/// which is produced bit by bit.
+pub fn output_writer(output: Option<&str>) -> Result<Box<dyn Write>> {
+    Ok(match output {
+        Some(path) => {
+            let file = File::create(path).context(format!("failed to create `{}`", path))?;
+            Box::new(BufWriter::new(file))
+        }
+        None => Box::new(BufWriter::new(io::stdout())),
+    })
+}
+
+/// Writes `bytes` to the file `output`, or to stdout if `output` is `None`.
+pub fn write_output(output: Option<&str>, bytes: &[u8]) -> Result<()> {
+    match output {
+        Some(path) => std::fs::write(path, bytes).context(format!("failed to write `{}`", path)),
+        None => {
+            use std::io::Write;
+            std::io::stdout()
+                .write_all(bytes)
+                .context("failed to write <stdout>")
+        }
+    }
+}
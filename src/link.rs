@@ -0,0 +1,1063 @@
+//! Static linking of relocatable object files.
+//!
+//! This is a small `wasm-ld`: it merges object files, such as those produced
+//! by `clang -c --target=wasm32`, into a single module. Symbols are resolved
+//! by name using the `linking` custom section of each object, data segments
+//! and the stack are laid out in linear memory, functions whose address is
+//! taken get a slot in the indirect function table, and the relocations of
+//! the `reloc.*` custom sections are applied to code and data.
+//!
+//! ```
+//! # fn object() -> Vec<u8> {
+//! #     use wasm_encoder::*;
+//! #     let mut module = Module::new();
+//! #     let mut types = TypeSection::new();
+//! #     types.function(vec![], vec![ValType::I32]);
+//! #     module.section(&types);
+//! #     let mut funcs = FunctionSection::new();
+//! #     funcs.function(0);
+//! #     module.section(&funcs);
+//! #     let mut code = CodeSection::new();
+//! #     let mut f = Function::new(vec![]);
+//! #     f.instruction(Instruction::I32Const(42));
+//! #     f.instruction(Instruction::End);
+//! #     code.function(&f);
+//! #     module.section(&code);
+//! #     let mut symbols = SymbolTable::new();
+//! #     symbols.function(0, 0, Some("answer"));
+//! #     let mut linking = LinkingSection::new();
+//! #     linking.symbol_table(&symbols);
+//! #     module.section(&linking);
+//! #     module.finish()
+//! # }
+//! use wasm_tools::link::Linker;
+//!
+//! // An object file defining the function `answer`.
+//! let object = object();
+//! let wasm = Linker::new().export("answer").link(&[&object])?;
+//! let text = wasmprinter::print_bytes(&wasm)?;
+//! assert!(text.contains(r#"(export "answer" (func 0))"#));
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::sections::{sections, write_str, Section};
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use wasmparser::leb128::{write_i32, write_u32};
+use wasmparser::{
+    BinaryReader, CodeSectionReader, DataKind, DataSectionReader, FunctionSectionReader,
+    GlobalSectionReader, ImportSectionEntryType, ImportSectionReader, Linking,
+    LinkingSectionReader, MemoryType, Reloc, RelocSectionReader, RelocType, SegmentInfo,
+    SymbolInfo, TypeSectionReader,
+};
+
+const CUSTOM: u8 = 0;
+const TYPE: u8 = 1;
+const IMPORT: u8 = 2;
+const FUNCTION: u8 = 3;
+const TABLE: u8 = 4;
+const MEMORY: u8 = 5;
+const GLOBAL: u8 = 6;
+const EXPORT: u8 = 7;
+const START: u8 = 8;
+const ELEMENT: u8 = 9;
+const CODE: u8 = 10;
+const DATA: u8 = 11;
+const DATA_COUNT: u8 = 12;
+
+/// The name of the synthesized function which calls the `init_funcs` of all
+/// objects.
+const CALL_CTORS: &str = "__wasm_call_ctors";
+
+/// The name of the synthesized global holding the stack pointer.
+const STACK_POINTER: &str = "__stack_pointer";
+
+/// A linker of relocatable object files, see the [module docs](self).
+///
+/// Only the simple cases are supported: objects must import their memory and
+/// table (if any) rather than define them, may not use the threads, shared
+/// memory or 64-bit memory proposals, and may not be position independent.
+/// COMDAT groups aren't used to discard duplicates, but the weak symbols
+/// they're made of resolve to a single definition. Custom sections of the
+/// objects, including debug information, are discarded.
+#[derive(Debug, Clone)]
+pub struct Linker {
+    exports: Vec<String>,
+    global_base: u32,
+    stack_size: u32,
+}
+
+impl Default for Linker {
+    fn default() -> Linker {
+        Linker {
+            exports: Vec::new(),
+            global_base: 1024,
+            stack_size: 64 * 1024,
+        }
+    }
+}
+
+impl Linker {
+    /// Creates a linker with the default memory layout and no exports.
+    pub fn new() -> Linker {
+        Linker::default()
+    }
+
+    /// Exports the function symbol `name` from the linked module under its
+    /// own name.
+    ///
+    /// Symbols marked as exported in their object are always exported.
+    pub fn export(&mut self, name: &str) -> &mut Linker {
+        self.exports.push(name.to_string());
+        self
+    }
+
+    /// Sets the address at which data segments start to be placed in memory,
+    /// which defaults to 1024.
+    pub fn global_base(&mut self, base: u32) -> &mut Linker {
+        self.global_base = base;
+        self
+    }
+
+    /// Sets the size of the stack, which defaults to 64 KiB.
+    ///
+    /// The stack is placed after the data segments and grows downwards from
+    /// its end, where `__stack_pointer` initially points.
+    pub fn stack_size(&mut self, size: u32) -> &mut Linker {
+        self.stack_size = size;
+        self
+    }
+
+    /// Links the object files `objects` into a module.
+    ///
+    /// Fails if an object is malformed or uses an unsupported feature, if a
+    /// symbol is defined by more than one object without being weak, or if a
+    /// data symbol isn't defined by any object. Undefined functions and
+    /// globals become imports of the module, with the import names of the
+    /// first object importing them.
+    pub fn link(&self, objects: &[&[u8]]) -> Result<Vec<u8>> {
+        let objects = objects
+            .iter()
+            .enumerate()
+            .map(|(i, wasm)| Object::parse(wasm).with_context(|| format!("in object {}", i)))
+            .collect::<Result<Vec<_>>>()?;
+        let mut state = State::new(self, &objects);
+        state.resolve()?;
+        state.layout()?;
+        state.emit()
+    }
+}
+
+/// A segment of an object's data section.
+struct Segment {
+    /// The range of the segment's bytes within the object.
+    start: usize,
+    end: usize,
+}
+
+/// A function or global imported by an object.
+struct Import<'a> {
+    module: &'a str,
+    field: &'a str,
+    /// The bytes of the import entry, starting at its kind.
+    desc: &'a [u8],
+    /// The object's type index of an imported function.
+    ty: u32,
+}
+
+/// The contents of an object file relevant to linking.
+struct Object<'a> {
+    wasm: &'a [u8],
+    /// The encoded entries of the type section.
+    types: Vec<&'a [u8]>,
+    func_imports: Vec<Import<'a>>,
+    global_imports: Vec<Import<'a>>,
+    imports_table: bool,
+    /// The type index of each defined function.
+    funcs: Vec<u32>,
+    /// The range of each defined function body within the object.
+    bodies: Vec<(usize, usize)>,
+    /// The encoded entries of the global section.
+    globals: Vec<&'a [u8]>,
+    segments: Vec<Segment>,
+    segment_info: Vec<SegmentInfo<'a>>,
+    symbols: Vec<SymbolInfo<'a>>,
+    init_funcs: Vec<(u32, u32)>,
+    /// Relocations of the code and data sections, keyed by their offset
+    /// within the object and sorted.
+    relocs: Vec<(usize, Reloc)>,
+}
+
+impl<'a> Object<'a> {
+    fn parse(wasm: &'a [u8]) -> Result<Object<'a>> {
+        let sections = sections(wasm)?;
+        let mut object = Object {
+            wasm,
+            types: Vec::new(),
+            func_imports: Vec::new(),
+            global_imports: Vec::new(),
+            imports_table: false,
+            funcs: Vec::new(),
+            bodies: Vec::new(),
+            globals: Vec::new(),
+            segments: Vec::new(),
+            segment_info: Vec::new(),
+            symbols: Vec::new(),
+            init_funcs: Vec::new(),
+            relocs: Vec::new(),
+        };
+        let mut has_linking = false;
+        for section in sections.iter() {
+            match section.id {
+                TYPE => {
+                    let mut reader = TypeSectionReader::new(section.contents, section.offset)?;
+                    for _ in 0..reader.get_count() {
+                        let start = reader.original_position();
+                        reader.read()?;
+                        object.types.push(&wasm[start..reader.original_position()]);
+                    }
+                }
+                IMPORT => object.parse_imports(section)?,
+                FUNCTION => {
+                    let reader = FunctionSectionReader::new(section.contents, section.offset)?;
+                    for ty in reader {
+                        object.funcs.push(ty?);
+                    }
+                }
+                TABLE | MEMORY => bail!("objects defining tables or memories are not supported"),
+                GLOBAL => {
+                    let mut reader = GlobalSectionReader::new(section.contents, section.offset)?;
+                    for _ in 0..reader.get_count() {
+                        let start = reader.original_position();
+                        reader.read()?;
+                        object
+                            .globals
+                            .push(&wasm[start..reader.original_position()]);
+                    }
+                }
+                EXPORT | DATA_COUNT => {}
+                START => bail!("objects with a start function are not supported"),
+                ELEMENT => bail!("objects with element segments are not supported"),
+                CODE => {
+                    let reader = CodeSectionReader::new(section.contents, section.offset)?;
+                    for body in reader {
+                        let range = body?.range();
+                        object.bodies.push((range.start, range.end));
+                    }
+                }
+                DATA => {
+                    let mut reader = DataSectionReader::new(section.contents, section.offset)?;
+                    for _ in 0..reader.get_count() {
+                        let data = reader.read()?;
+                        if let DataKind::Passive = data.kind {
+                            bail!("passive data segments are not supported");
+                        }
+                        let end = reader.original_position();
+                        object.segments.push(Segment {
+                            start: end - data.data.len(),
+                            end,
+                        });
+                    }
+                }
+                CUSTOM => match section.custom_name()? {
+                    Some("linking") => {
+                        has_linking = true;
+                        object.parse_linking(section)?;
+                    }
+                    Some(name) if name.starts_with("reloc.") => {
+                        object.parse_relocs(section, &sections)?;
+                    }
+                    _ => {}
+                },
+                id => bail!("unsupported section {} in object", id),
+            }
+        }
+        if !has_linking {
+            bail!("not a relocatable object file: no `linking` section");
+        }
+        if object.funcs.len() != object.bodies.len() {
+            bail!("function and code section have inconsistent lengths");
+        }
+        object.relocs.sort_by_key(|(offset, _)| *offset);
+        Ok(object)
+    }
+
+    fn parse_imports(&mut self, section: &Section<'a>) -> Result<()> {
+        let mut reader = ImportSectionReader::new(section.contents, section.offset)?;
+        for _ in 0..reader.get_count() {
+            let start = reader.original_position();
+            let import = reader.read()?;
+            let entry = &self.wasm[start..reader.original_position()];
+            let mut entry_reader = BinaryReader::new(entry);
+            entry_reader.read_string()?;
+            entry_reader.read_string()?;
+            let field = match import.field {
+                Some(field) => field,
+                None => bail!("module linking imports are not supported"),
+            };
+            let mut import_entry = Import {
+                module: import.module,
+                field,
+                desc: &entry[entry_reader.current_position()..],
+                ty: 0,
+            };
+            match import.ty {
+                ImportSectionEntryType::Function(ty) => {
+                    import_entry.ty = ty;
+                    self.func_imports.push(import_entry);
+                }
+                ImportSectionEntryType::Global(_) => self.global_imports.push(import_entry),
+                // The memory and the table are defined by the linked module.
+                ImportSectionEntryType::Memory(MemoryType::M32 { shared: false, .. }) => {}
+                ImportSectionEntryType::Memory(_) => {
+                    bail!("64-bit and shared memories are not supported")
+                }
+                ImportSectionEntryType::Table(_) => self.imports_table = true,
+                _ => bail!("unsupported import `{}::{}`", import.module, field),
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_linking(&mut self, section: &Section<'a>) -> Result<()> {
+        let mut reader = BinaryReader::new_with_offset(section.contents, section.offset);
+        reader.read_string()?;
+        let pos = reader.current_position();
+        let reader = LinkingSectionReader::new(&section.contents[pos..], section.offset + pos)?;
+        for subsection in reader {
+            match subsection? {
+                Linking::SegmentInfo(info) => self.segment_info = info.into_vec(),
+                Linking::InitFuncs(funcs) => {
+                    self.init_funcs = funcs.iter().map(|f| (f.priority, f.symbol_index)).collect();
+                }
+                Linking::SymbolTable(symbols) => self.symbols = symbols.into_vec(),
+                Linking::ComdatInfo(_) | Linking::Unknown { .. } => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_relocs(&mut self, section: &Section<'a>, sections: &[Section<'a>]) -> Result<()> {
+        let mut reader = BinaryReader::new_with_offset(section.contents, section.offset);
+        reader.read_string()?;
+        let pos = reader.current_position();
+        let mut reader = RelocSectionReader::new(&section.contents[pos..], section.offset + pos)?;
+        let target = match sections.get(reader.get_section_index() as usize) {
+            Some(target) => target,
+            None => bail!("relocations of out-of-bounds section"),
+        };
+        // Relocations of custom sections, such as debug information, are
+        // irrelevant since those sections are discarded.
+        if target.id != CODE && target.id != DATA {
+            return Ok(());
+        }
+        for _ in 0..reader.get_count() {
+            let reloc = reader.read()?;
+            self.relocs
+                .push((target.offset + reloc.offset as usize, reloc));
+        }
+        Ok(())
+    }
+
+    fn imported_funcs(&self) -> u32 {
+        self.func_imports.len() as u32
+    }
+
+    fn imported_globals(&self) -> u32 {
+        self.global_imports.len() as u32
+    }
+
+    /// Returns the name of the symbol `symbol`, which is that of its import
+    /// for undefined symbols without an explicit name.
+    fn symbol_name(&self, symbol: &SymbolInfo<'a>) -> Option<&'a str> {
+        match *symbol {
+            SymbolInfo::Func { index, name, .. } => {
+                name.or_else(|| self.func_imports.get(index as usize).map(|i| i.field))
+            }
+            SymbolInfo::Global { index, name, .. } => {
+                name.or_else(|| self.global_imports.get(index as usize).map(|i| i.field))
+            }
+            SymbolInfo::Data { name, .. } => Some(name),
+            SymbolInfo::Table { name, .. } | SymbolInfo::Event { name, .. } => name,
+            SymbolInfo::Section { .. } => None,
+        }
+    }
+}
+
+/// Whether a symbol is visible to other objects.
+fn is_global(flags: u32) -> bool {
+    flags & SymbolInfo::BINDING_LOCAL == 0
+}
+
+fn is_defined(flags: u32) -> bool {
+    flags & SymbolInfo::UNDEFINED == 0
+}
+
+fn is_weak(flags: u32) -> bool {
+    flags & SymbolInfo::BINDING_WEAK != 0
+}
+
+/// A definition of a symbol: the object defining it and its index in the
+/// object's symbol table.
+#[derive(Clone, Copy)]
+struct Definition {
+    object: usize,
+    symbol: usize,
+    weak: bool,
+}
+
+/// What a symbol of an object resolves to in the linked module.
+#[derive(Clone, Copy)]
+enum Resolved {
+    Func(u32),
+    Global(u32),
+    Data(u32),
+    Table(u32),
+    None,
+}
+
+struct State<'a, 'b> {
+    linker: &'b Linker,
+    objects: &'b [Object<'a>],
+    /// Definitions of the non-local symbols of each kind, by name.
+    funcs: HashMap<&'a str, Definition>,
+    globals: HashMap<&'a str, Definition>,
+    data: HashMap<&'a str, Definition>,
+    /// The deduplicated encoded types and the mapping of each object's types
+    /// to them.
+    types: Vec<&'a [u8]>,
+    type_map: Vec<Vec<u32>>,
+    /// The imported functions and globals, by symbol name.
+    func_imports: Vec<(&'a str, &'b Import<'a>, u32)>,
+    global_imports: Vec<(&'a str, &'b Import<'a>)>,
+    /// The index of the first defined function and global of each object.
+    func_bases: Vec<u32>,
+    global_bases: Vec<u32>,
+    /// The indices of the synthesized function and global, if needed.
+    call_ctors: Option<u32>,
+    stack_pointer: Option<u32>,
+    /// The address of each data segment of each object.
+    segment_addrs: Vec<Vec<u32>>,
+    stack_top: u32,
+    /// The functions in the table, starting at slot 1.
+    table: Vec<u32>,
+    uses_table: bool,
+    resolved: Vec<Vec<Resolved>>,
+}
+
+impl<'a, 'b> State<'a, 'b> {
+    fn new(linker: &'b Linker, objects: &'b [Object<'a>]) -> State<'a, 'b> {
+        let mut state = State {
+            linker,
+            objects,
+            funcs: HashMap::new(),
+            globals: HashMap::new(),
+            data: HashMap::new(),
+            types: Vec::new(),
+            type_map: Vec::new(),
+            func_imports: Vec::new(),
+            global_imports: Vec::new(),
+            func_bases: Vec::new(),
+            global_bases: Vec::new(),
+            call_ctors: None,
+            stack_pointer: None,
+            segment_addrs: Vec::new(),
+            stack_top: 0,
+            table: Vec::new(),
+            uses_table: false,
+            resolved: Vec::new(),
+        };
+        let mut type_indices = HashMap::new();
+        for object in objects {
+            let mut map = Vec::new();
+            for ty in object.types.iter() {
+                let types = &mut state.types;
+                let index = *type_indices.entry(*ty).or_insert_with(|| {
+                    types.push(*ty);
+                    types.len() as u32 - 1
+                });
+                map.push(index);
+            }
+            state.type_map.push(map);
+        }
+        state
+    }
+
+    /// Resolves the symbols of all objects and determines the index space of
+    /// functions and globals.
+    fn resolve(&mut self) -> Result<()> {
+        let objects = self.objects;
+        for (i, object) in objects.iter().enumerate() {
+            for (j, symbol) in object.symbols.iter().enumerate() {
+                let flags = symbol.flags();
+                if !is_global(flags) || !is_defined(flags) {
+                    continue;
+                }
+                let map = match symbol {
+                    SymbolInfo::Func { .. } => &mut self.funcs,
+                    SymbolInfo::Global { .. } => &mut self.globals,
+                    SymbolInfo::Data { .. } => &mut self.data,
+                    _ => continue,
+                };
+                let name = match object.symbol_name(symbol) {
+                    Some(name) => name,
+                    None => continue,
+                };
+                let definition = Definition {
+                    object: i,
+                    symbol: j,
+                    weak: is_weak(flags),
+                };
+                match map.get(name) {
+                    Some(prev) if !prev.weak && !definition.weak => {
+                        bail!(
+                            "duplicate symbol `{}` in objects {} and {}",
+                            name,
+                            prev.object,
+                            i
+                        )
+                    }
+                    Some(prev) if definition.weak || !prev.weak => {}
+                    _ => {
+                        map.insert(name, definition);
+                    }
+                }
+            }
+        }
+
+        // Undefined functions and globals without a definition are imported,
+        // unless the linker synthesizes them.
+        let mut needs_call_ctors = objects.iter().any(|o| !o.init_funcs.is_empty());
+        let mut needs_stack_pointer = false;
+        for (i, object) in objects.iter().enumerate() {
+            for symbol in object.symbols.iter() {
+                if is_defined(symbol.flags()) {
+                    continue;
+                }
+                match *symbol {
+                    SymbolInfo::Func { index, .. } => {
+                        let import = match object.func_imports.get(index as usize) {
+                            Some(import) => import,
+                            None => bail!(
+                                "object {} has an undefined symbol for function {}",
+                                i,
+                                index
+                            ),
+                        };
+                        let name = object.symbol_name(symbol).unwrap();
+                        if name == CALL_CTORS {
+                            needs_call_ctors = true;
+                        } else if !self.funcs.contains_key(name)
+                            && !self.func_imports.iter().any(|i| i.0 == name)
+                        {
+                            let ty = match self.type_map[i].get(import.ty as usize) {
+                                Some(ty) => *ty,
+                                None => bail!(
+                                    "object {} imports a function of an out-of-bounds type",
+                                    i
+                                ),
+                            };
+                            self.func_imports.push((name, import, ty));
+                        }
+                    }
+                    SymbolInfo::Global { index, .. } => {
+                        let import = match object.global_imports.get(index as usize) {
+                            Some(import) => import,
+                            None => {
+                                bail!("object {} has an undefined symbol for global {}", i, index)
+                            }
+                        };
+                        let name = object.symbol_name(symbol).unwrap();
+                        if name == STACK_POINTER {
+                            needs_stack_pointer = true;
+                        } else if !self.globals.contains_key(name)
+                            && !self.global_imports.iter().any(|i| i.0 == name)
+                        {
+                            self.global_imports.push((name, import));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut func = self.func_imports.len() as u32;
+        let mut global = self.global_imports.len() as u32;
+        for object in objects {
+            self.func_bases.push(func);
+            self.global_bases.push(global);
+            func += object.funcs.len() as u32;
+            global += object.globals.len() as u32;
+        }
+        if needs_call_ctors {
+            self.call_ctors = Some(func);
+        }
+        if needs_stack_pointer {
+            self.stack_pointer = Some(global);
+        }
+        Ok(())
+    }
+
+    /// Places data segments and the stack in memory and determines the
+    /// value of each symbol.
+    fn layout(&mut self) -> Result<()> {
+        let mut addr = self.linker.global_base;
+        for (i, object) in self.objects.iter().enumerate() {
+            let mut addrs = Vec::new();
+            for (j, segment) in object.segments.iter().enumerate() {
+                let alignment = object.segment_info.get(j).map_or(0, |s| s.alignment);
+                if alignment >= 32 {
+                    bail!("object {} has a segment with invalid alignment", i);
+                }
+                addr = align(addr, 1 << alignment)?;
+                addrs.push(addr);
+                addr = add(addr, (segment.end - segment.start) as u32)?;
+            }
+            self.segment_addrs.push(addrs);
+        }
+        self.stack_top = align(add(align(addr, 16)?, self.linker.stack_size)?, 16)?;
+
+        for (i, object) in self.objects.iter().enumerate() {
+            let mut resolved = Vec::new();
+            for symbol in object.symbols.iter() {
+                let value = self
+                    .resolve_symbol(i, symbol)
+                    .with_context(|| format!("in object {}", i))?;
+                resolved.push(value);
+            }
+            self.resolved.push(resolved);
+        }
+
+        // Assign table slots to the functions whose address is taken.
+        for (i, object) in self.objects.iter().enumerate() {
+            self.uses_table |= object.imports_table;
+            for (_, reloc) in object.relocs.iter() {
+                match reloc.ty {
+                    RelocType::TableIndexSLEB | RelocType::TableIndexI32 => {}
+                    RelocType::TableNumberLEB => {
+                        self.uses_table = true;
+                        continue;
+                    }
+                    _ => continue,
+                }
+                let func = match self.resolved[i].get(reloc.index as usize) {
+                    Some(Resolved::Func(func)) => *func,
+                    _ => bail!("object {} takes the address of a non-function", i),
+                };
+                if !self.table.contains(&func) {
+                    self.table.push(func);
+                }
+            }
+        }
+        self.uses_table |= !self.table.is_empty();
+        Ok(())
+    }
+
+    fn resolve_symbol(&self, object: usize, symbol: &SymbolInfo<'a>) -> Result<Resolved> {
+        let flags = symbol.flags();
+        let name = self.objects[object].symbol_name(symbol);
+        let definition = |map: &HashMap<&'a str, Definition>| {
+            if is_defined(flags) && !is_global(flags) {
+                return None;
+            }
+            name.and_then(|name| map.get(name)).copied()
+        };
+        Ok(match *symbol {
+            SymbolInfo::Func { index, .. } => {
+                if let Some(def) = definition(&self.funcs) {
+                    return Ok(Resolved::Func(self.defined_func(def)));
+                }
+                if is_defined(flags) {
+                    return Ok(Resolved::Func(self.object_func(object, index)));
+                }
+                let name = name.unwrap();
+                if name == CALL_CTORS {
+                    return Ok(Resolved::Func(self.call_ctors.unwrap()));
+                }
+                let import = self.func_imports.iter().position(|i| i.0 == name);
+                Resolved::Func(import.unwrap() as u32)
+            }
+            SymbolInfo::Global { index, .. } => {
+                if let Some(def) = definition(&self.globals) {
+                    return Ok(Resolved::Global(self.defined_global(def)));
+                }
+                if is_defined(flags) {
+                    return Ok(Resolved::Global(self.object_global(object, index)));
+                }
+                let name = name.unwrap();
+                if name == STACK_POINTER {
+                    return Ok(Resolved::Global(self.stack_pointer.unwrap()));
+                }
+                let import = self.global_imports.iter().position(|i| i.0 == name);
+                Resolved::Global(import.unwrap() as u32)
+            }
+            SymbolInfo::Data { name, symbol, .. } => {
+                if let Some(def) = definition(&self.data) {
+                    let (object, symbol) = match self.objects[def.object].symbols[def.symbol] {
+                        SymbolInfo::Data { symbol, .. } => (def.object, symbol),
+                        _ => unreachable!(),
+                    };
+                    return self.data_address(object, symbol.unwrap());
+                }
+                match symbol {
+                    Some(symbol) => return self.data_address(object, symbol),
+                    // Undefined weak data has the address 0.
+                    None if is_weak(flags) => Resolved::Data(0),
+                    None => bail!("undefined data symbol `{}`", name),
+                }
+            }
+            SymbolInfo::Table { .. } => Resolved::Table(0),
+            SymbolInfo::Section { .. } | SymbolInfo::Event { .. } => Resolved::None,
+        })
+    }
+
+    fn defined_func(&self, def: Definition) -> u32 {
+        match self.objects[def.object].symbols[def.symbol] {
+            SymbolInfo::Func { index, .. } => self.object_func(def.object, index),
+            _ => unreachable!(),
+        }
+    }
+
+    fn defined_global(&self, def: Definition) -> u32 {
+        match self.objects[def.object].symbols[def.symbol] {
+            SymbolInfo::Global { index, .. } => self.object_global(def.object, index),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the index of the function `index` defined by `object`.
+    fn object_func(&self, object: usize, index: u32) -> u32 {
+        self.func_bases[object] + index - self.objects[object].imported_funcs()
+    }
+
+    fn object_global(&self, object: usize, index: u32) -> u32 {
+        self.global_bases[object] + index - self.objects[object].imported_globals()
+    }
+
+    fn data_address(
+        &self,
+        object: usize,
+        symbol: wasmparser::DefinedDataSymbol,
+    ) -> Result<Resolved> {
+        let base = match self.segment_addrs[object].get(symbol.index as usize) {
+            Some(base) => *base,
+            None => bail!("data symbol in out-of-bounds segment {}", symbol.index),
+        };
+        Ok(Resolved::Data(add(base, symbol.offset)?))
+    }
+
+    /// Returns the bytes of `object` in `start..end` with its relocations
+    /// applied.
+    fn relocated(&self, object: usize, start: usize, end: usize) -> Result<Vec<u8>> {
+        let obj = &self.objects[object];
+        let mut bytes = obj.wasm[start..end].to_vec();
+        let first = obj.relocs.partition_point(|(offset, _)| *offset < start);
+        for (offset, reloc) in obj.relocs[first..].iter() {
+            if *offset >= end {
+                break;
+            }
+            let value = self
+                .reloc_value(object, reloc)
+                .with_context(|| format!("in object {}", object))?;
+            let pos = offset - start;
+            let patch = match reloc.ty {
+                RelocType::FunctionIndexLEB
+                | RelocType::TypeIndexLEB
+                | RelocType::GlobalIndexLEB
+                | RelocType::TableNumberLEB
+                | RelocType::MemoryAddrLEB => padded_u32(value).to_vec(),
+                RelocType::TableIndexSLEB | RelocType::MemoryAddrSLEB => {
+                    padded_i32(value as i32).to_vec()
+                }
+                _ => value.to_le_bytes().to_vec(),
+            };
+            let dst = match bytes.get_mut(pos..pos + patch.len()) {
+                Some(dst) => dst,
+                None => bail!("relocation at out-of-bounds offset {:#x}", offset),
+            };
+            dst.copy_from_slice(&patch);
+        }
+        Ok(bytes)
+    }
+
+    fn reloc_value(&self, object: usize, reloc: &Reloc) -> Result<u32> {
+        if reloc.ty == RelocType::TypeIndexLEB {
+            return match self.type_map[object].get(reloc.index as usize) {
+                Some(ty) => Ok(*ty),
+                None => bail!("relocation of out-of-bounds type {}", reloc.index),
+            };
+        }
+        let resolved = match self.resolved[object].get(reloc.index as usize) {
+            Some(resolved) => *resolved,
+            None => bail!("relocation of out-of-bounds symbol {}", reloc.index),
+        };
+        Ok(match (reloc.ty, resolved) {
+            (RelocType::FunctionIndexLEB, Resolved::Func(func)) => func,
+            (RelocType::TableIndexSLEB, Resolved::Func(func))
+            | (RelocType::TableIndexI32, Resolved::Func(func)) => {
+                self.table.iter().position(|f| *f == func).unwrap() as u32 + 1
+            }
+            (RelocType::MemoryAddrLEB, Resolved::Data(addr))
+            | (RelocType::MemoryAddrSLEB, Resolved::Data(addr))
+            | (RelocType::MemoryAddrI32, Resolved::Data(addr)) => {
+                addr.wrapping_add(reloc.addend.unwrap_or(0) as u32)
+            }
+            (RelocType::GlobalIndexLEB, Resolved::Global(global))
+            | (RelocType::GlobalIndexI32, Resolved::Global(global)) => global,
+            (RelocType::TableNumberLEB, Resolved::Table(table)) => table,
+            (
+                RelocType::FunctionIndexLEB
+                | RelocType::TableIndexSLEB
+                | RelocType::TableIndexI32
+                | RelocType::MemoryAddrLEB
+                | RelocType::MemoryAddrSLEB
+                | RelocType::MemoryAddrI32
+                | RelocType::GlobalIndexLEB
+                | RelocType::GlobalIndexI32
+                | RelocType::TableNumberLEB,
+                _,
+            ) => bail!("relocation {:?} of a symbol of the wrong kind", reloc.ty),
+            (ty, _) => bail!("unsupported relocation {:?}", ty),
+        })
+    }
+
+    fn emit(&self) -> Result<Vec<u8>> {
+        let mut output = b"\0asm\x01\0\0\0".to_vec();
+        let mut types = self.types.clone();
+        let empty_type: &[u8] = &[0x60, 0x00, 0x00];
+        let call_ctors_type = match types.iter().position(|t| *t == empty_type) {
+            Some(i) => i as u32,
+            None if self.call_ctors.is_some() => {
+                types.push(empty_type);
+                types.len() as u32 - 1
+            }
+            None => 0,
+        };
+        if !types.is_empty() {
+            let mut contents = Vec::new();
+            write_u32(&mut contents, types.len() as u32);
+            for ty in types.iter() {
+                contents.extend_from_slice(ty);
+            }
+            push_section(&mut output, TYPE, &contents);
+        }
+
+        let imports = self.func_imports.len() + self.global_imports.len();
+        if imports > 0 {
+            let mut contents = Vec::new();
+            write_u32(&mut contents, imports as u32);
+            for (_, import, ty) in self.func_imports.iter() {
+                write_str(&mut contents, import.module);
+                write_str(&mut contents, import.field);
+                contents.push(0x00);
+                write_u32(&mut contents, *ty);
+            }
+            for (_, import) in self.global_imports.iter() {
+                write_str(&mut contents, import.module);
+                write_str(&mut contents, import.field);
+                contents.extend_from_slice(import.desc);
+            }
+            push_section(&mut output, IMPORT, &contents);
+        }
+
+        let mut funcs = Vec::new();
+        for (i, object) in self.objects.iter().enumerate() {
+            for ty in object.funcs.iter() {
+                funcs.push(self.type_map[i][*ty as usize]);
+            }
+        }
+        if self.call_ctors.is_some() {
+            funcs.push(call_ctors_type);
+        }
+        if !funcs.is_empty() {
+            let mut contents = Vec::new();
+            write_u32(&mut contents, funcs.len() as u32);
+            for ty in funcs.iter() {
+                write_u32(&mut contents, *ty);
+            }
+            push_section(&mut output, FUNCTION, &contents);
+        }
+
+        if self.uses_table {
+            let size = self.table.len() as u32 + 1;
+            let mut contents = vec![0x01, 0x70, 0x01];
+            write_u32(&mut contents, size);
+            write_u32(&mut contents, size);
+            push_section(&mut output, TABLE, &contents);
+        }
+
+        let pages = u64::from(self.stack_top).div_ceil(0x10000);
+        let mut contents = vec![0x01, 0x00];
+        write_u32(&mut contents, pages as u32);
+        push_section(&mut output, MEMORY, &contents);
+
+        let mut globals = Vec::new();
+        for object in self.objects.iter() {
+            globals.extend(object.globals.iter().copied());
+        }
+        let mut stack_pointer = vec![0x7f, 0x01, 0x41];
+        write_i32(&mut stack_pointer, self.stack_top as i32);
+        stack_pointer.push(0x0b);
+        if self.stack_pointer.is_some() {
+            globals.push(&stack_pointer);
+        }
+        if !globals.is_empty() {
+            let mut contents = Vec::new();
+            write_u32(&mut contents, globals.len() as u32);
+            for global in globals.iter() {
+                contents.extend_from_slice(global);
+            }
+            push_section(&mut output, GLOBAL, &contents);
+        }
+
+        self.emit_exports(&mut output)?;
+
+        if !self.table.is_empty() {
+            let mut contents = vec![0x01, 0x00, 0x41, 0x01, 0x0b];
+            write_u32(&mut contents, self.table.len() as u32);
+            for func in self.table.iter() {
+                write_u32(&mut contents, *func);
+            }
+            push_section(&mut output, ELEMENT, &contents);
+        }
+
+        if !funcs.is_empty() {
+            let mut contents = Vec::new();
+            write_u32(&mut contents, funcs.len() as u32);
+            for (i, object) in self.objects.iter().enumerate() {
+                for (start, end) in object.bodies.iter() {
+                    let body = self.relocated(i, *start, *end)?;
+                    write_u32(&mut contents, body.len() as u32);
+                    contents.extend_from_slice(&body);
+                }
+            }
+            if self.call_ctors.is_some() {
+                let body = self.call_ctors_body();
+                write_u32(&mut contents, body.len() as u32);
+                contents.extend_from_slice(&body);
+            }
+            push_section(&mut output, CODE, &contents);
+        }
+
+        let segments = self.objects.iter().map(|o| o.segments.len()).sum::<usize>();
+        if segments > 0 {
+            let mut contents = Vec::new();
+            write_u32(&mut contents, segments as u32);
+            for (i, object) in self.objects.iter().enumerate() {
+                for (j, segment) in object.segments.iter().enumerate() {
+                    contents.extend_from_slice(&[0x00, 0x41]);
+                    write_i32(&mut contents, self.segment_addrs[i][j] as i32);
+                    contents.push(0x0b);
+                    let data = self.relocated(i, segment.start, segment.end)?;
+                    write_u32(&mut contents, data.len() as u32);
+                    contents.extend_from_slice(&data);
+                }
+            }
+            push_section(&mut output, DATA, &contents);
+        }
+        Ok(output)
+    }
+
+    fn emit_exports(&self, output: &mut Vec<u8>) -> Result<()> {
+        let mut exports: Vec<(&str, u32)> = Vec::new();
+        for (i, object) in self.objects.iter().enumerate() {
+            for (j, symbol) in object.symbols.iter().enumerate() {
+                let flags = symbol.flags();
+                if flags & SymbolInfo::EXPORTED == 0 || !is_defined(flags) {
+                    continue;
+                }
+                if let (Some(name), Resolved::Func(func)) =
+                    (object.symbol_name(symbol), self.resolved[i][j])
+                {
+                    if !exports.iter().any(|e| e.0 == name) {
+                        exports.push((name, func));
+                    }
+                }
+            }
+        }
+        for name in self.linker.exports.iter() {
+            let func = match (self.funcs.get(name.as_str()), self.call_ctors) {
+                (Some(def), _) => self.defined_func(*def),
+                (None, Some(func)) if name == CALL_CTORS => func,
+                _ => bail!("cannot export undefined function `{}`", name),
+            };
+            if !exports.iter().any(|e| e.0 == name) {
+                exports.push((name, func));
+            }
+        }
+
+        let mut contents = Vec::new();
+        write_u32(&mut contents, exports.len() as u32 + 1);
+        write_str(&mut contents, "memory");
+        contents.extend_from_slice(&[0x02, 0x00]);
+        for (name, func) in exports {
+            write_str(&mut contents, name);
+            contents.push(0x00);
+            write_u32(&mut contents, func);
+        }
+        push_section(output, EXPORT, &contents);
+        Ok(())
+    }
+
+    /// Returns the body of `__wasm_call_ctors`, which calls the init
+    /// functions of all objects ordered by priority, then by object.
+    fn call_ctors_body(&self) -> Vec<u8> {
+        let mut init_funcs = Vec::new();
+        for (i, object) in self.objects.iter().enumerate() {
+            for (priority, symbol) in object.init_funcs.iter() {
+                if let Some(Resolved::Func(func)) = self.resolved[i].get(*symbol as usize) {
+                    init_funcs.push((*priority, *func));
+                }
+            }
+        }
+        init_funcs.sort_by_key(|(priority, _)| *priority);
+        let mut body = vec![0x00];
+        for (_, func) in init_funcs {
+            body.push(0x10);
+            write_u32(&mut body, func);
+        }
+        body.push(0x0b);
+        body
+    }
+}
+
+fn align(addr: u32, alignment: u32) -> Result<u32> {
+    Ok(add(addr, alignment - 1)? & !(alignment - 1))
+}
+
+fn add(a: u32, b: u32) -> Result<u32> {
+    match a.checked_add(b) {
+        Some(sum) => Ok(sum),
+        None => bail!("linked module doesn't fit in a 32-bit memory"),
+    }
+}
+
+fn push_section(output: &mut Vec<u8>, id: u8, contents: &[u8]) {
+    Section {
+        id,
+        contents,
+        offset: 0,
+    }
+    .encode(output);
+}
+
+/// Encodes `n` as a ULEB128 padded to 5 bytes, the size relocated LEBs are
+/// reserved with.
+fn padded_u32(n: u32) -> [u8; 5] {
+    let mut bytes = [0; 5];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = ((n >> (i * 7)) & 0x7f) as u8 | 0x80;
+    }
+    bytes[4] &= 0x7f;
+    bytes
+}
+
+/// Encodes `n` as a SLEB128 padded to 5 bytes.
+fn padded_i32(n: i32) -> [u8; 5] {
+    let mut bytes = [0; 5];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = ((n >> (i * 7)) & 0x7f) as u8 | 0x80;
+    }
+    bytes[4] &= 0x7f;
+    bytes
+}
@@ -0,0 +1,372 @@
+//! Liveness of locals and operand stack depth of function bodies.
+//!
+//! An engine compiling a function keeps its locals and operands in
+//! registers where it can, so the number of values which are needed at the
+//! same time hints at how many registers the function wants. For every local
+//! of every defined function [`liveness`] reports how often it's accessed and
+//! the ranges of instructions during which it's live, that is, during which
+//! its current value may still be read. It also reports the maximum number
+//! of locals live at once and the maximum depth of the operand stack.
+//!
+//! ```
+//! use wasm_tools::liveness::liveness;
+//!
+//! let wasm = wat::parse_str(r#"
+//!     (module
+//!       (func (param i32) (result i32) (local i32 i32)
+//!         local.get 0
+//!         i32.const 1
+//!         i32.add
+//!         local.set 1
+//!         local.get 1
+//!         local.get 1
+//!         i32.mul))
+//! "#)?;
+//! let functions = liveness(&wasm)?;
+//! let f = &functions[0];
+//! assert_eq!(f.max_stack_depth, 2);
+//! assert_eq!(f.max_live, 1);
+//! assert_eq!((f.locals[1].gets, f.locals[1].sets), (2, 1));
+//! // The last local is never used.
+//! assert!(f.locals[2].live.is_empty());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::cfg::Cfg;
+use anyhow::{bail, Result};
+#[cfg(feature = "json")]
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::ops::Range;
+use wasmparser::{
+    FuncValidatorAllocations, ImportSectionEntryType, Operator, Parser, Payload, Type,
+    ValidPayload, Validator, WasmFeatures, WasmFuncType, WasmModuleResources,
+};
+
+/// The liveness report of a function, see the [module docs](self).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct Liveness {
+    /// The index of the function in the function index space, which
+    /// includes imported functions.
+    pub index: u32,
+    /// The number of parameters of the function, which are its first locals.
+    pub params: u32,
+    /// The parameters and locals of the function, in order.
+    pub locals: Vec<Local>,
+    /// The maximum number of locals live before any instruction.
+    pub max_live: u32,
+    /// The maximum number of operands on the operand stack after any
+    /// instruction.
+    pub max_stack_depth: u32,
+    /// The maximum number of live locals and operands on the operand stack
+    /// before any instruction, which approximates how many registers the
+    /// function needs.
+    pub max_pressure: u32,
+}
+
+/// How a local of a function is used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct Local {
+    /// The type of the local.
+    pub ty: Type,
+    /// The number of `local.get`s of the local.
+    pub gets: u32,
+    /// The number of `local.set`s of the local.
+    pub sets: u32,
+    /// The number of `local.tee`s of the local.
+    pub tees: u32,
+    /// The ranges of offsets in the module of the instructions before which
+    /// the local is live, in order.
+    ///
+    /// Parameters, and locals which are read before they are set, are live
+    /// from the start of the function.
+    pub live: Vec<Range<usize>>,
+}
+
+/// Computes the liveness of the locals of every function defined by the
+/// module `wasm`.
+///
+/// Liveness follows the control-flow graph of [`Cfg`], so a local which is
+/// read in a loop is live throughout it. Instructions in unreachable code
+/// use locals but don't make them live.
+///
+/// Returns an error if the module is malformed or fails to validate even
+/// with all proposals enabled. Modules using the module linking proposal
+/// aren't supported.
+pub fn liveness(wasm: &[u8]) -> Result<Vec<Liveness>> {
+    let mut validator = Validator::new();
+    validator.wasm_features(WasmFeatures {
+        reference_types: true,
+        multi_value: true,
+        bulk_memory: true,
+        module_linking: true,
+        simd: true,
+        threads: true,
+        tail_call: true,
+        multi_memory: true,
+        exceptions: true,
+        memory64: true,
+        wide_arithmetic: true,
+        function_references: true,
+        ..WasmFeatures::default()
+    });
+    let mut imported_functions = 0;
+    let mut functions = Vec::new();
+    let mut allocs = FuncValidatorAllocations::default();
+    for payload in Parser::new(0).parse_all(wasm) {
+        let payload = payload?;
+        match &payload {
+            Payload::ImportSection(s) => {
+                for import in s.clone() {
+                    if let ImportSectionEntryType::Function(_) = import?.ty {
+                        imported_functions += 1;
+                    }
+                }
+            }
+            Payload::AliasSection(_)
+            | Payload::InstanceSection(_)
+            | Payload::ModuleSectionStart { .. } => {
+                bail!("modules using the module linking proposal are not supported")
+            }
+            _ => {}
+        }
+        let (func, body) = match validator.payload(&payload)? {
+            ValidPayload::Func(func, body) => (func, body),
+            _ => continue,
+        };
+
+        let mut locals = Vec::new();
+        let ty = func.resources.func_type_at(func.ty).unwrap();
+        locals.extend(ty.inputs());
+        let params = locals.len() as u32;
+        let mut reader = body.get_locals_reader()?;
+        for _ in 0..reader.get_count() {
+            let (count, ty) = reader.read()?;
+            locals.extend((0..count).map(|_| ty));
+        }
+
+        // Validating the body tracks the height of the operand stack.
+        let mut validator = func.into_validator(std::mem::take(&mut allocs));
+        let mut reader = body.get_binary_reader();
+        validator.read_locals(&mut reader)?;
+        let mut instructions = Vec::new();
+        let mut max_stack_depth = 0;
+        while !reader.eof() {
+            let offset = reader.original_position();
+            let op = reader.read_operator()?;
+            let access = match op {
+                Operator::LocalGet { local_index } => Access::Get(local_index),
+                Operator::LocalSet { local_index } => Access::Set(local_index),
+                Operator::LocalTee { local_index } => Access::Tee(local_index),
+                _ => Access::None,
+            };
+            instructions.push(Instruction {
+                offset,
+                access,
+                stack_depth: validator.operand_stack_height(),
+            });
+            validator.op(offset, &op)?;
+            max_stack_depth = max_stack_depth.max(validator.operand_stack_height());
+        }
+        validator.finish(reader.original_position())?;
+        allocs = validator.into_allocations();
+
+        let cfg = Cfg::new(&body)?;
+        let mut function = analyze(&cfg, &instructions, &locals, body.range().end);
+        function.index = imported_functions + functions.len() as u32;
+        function.params = params;
+        function.max_stack_depth = max_stack_depth;
+        functions.push(function);
+    }
+    Ok(functions)
+}
+
+/// An instruction of a function body.
+struct Instruction {
+    offset: usize,
+    access: Access,
+    /// The height of the operand stack before the instruction.
+    stack_depth: u32,
+}
+
+enum Access {
+    None,
+    Get(u32),
+    Set(u32),
+    Tee(u32),
+}
+
+impl Access {
+    /// Updates `live`, the locals live after this access, to those live
+    /// before it.
+    fn transfer(&self, live: &mut BTreeSet<u32>) {
+        match *self {
+            Access::None => {}
+            Access::Get(local) => {
+                live.insert(local);
+            }
+            Access::Set(local) | Access::Tee(local) => {
+                live.remove(&local);
+            }
+        }
+    }
+}
+
+/// Computes the liveness of `locals` in the function with the control-flow
+/// graph `cfg` and the `instructions`, which end at offset `end`.
+fn analyze(cfg: &Cfg, instructions: &[Instruction], locals: &[Type], end: usize) -> Liveness {
+    let blocks = cfg.blocks();
+    // The instructions of each block, as a range of indices into
+    // `instructions`.
+    let ranges = blocks
+        .iter()
+        .map(|block| {
+            let start = instructions.partition_point(|i| i.offset < block.start);
+            let end = instructions.partition_point(|i| i.offset < block.end);
+            start..end.max(start)
+        })
+        .collect::<Vec<_>>();
+
+    // Iterate to a fixed point the locals live at the start of each block.
+    let mut live_in = vec![BTreeSet::new(); blocks.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for b in (0..blocks.len()).rev() {
+            let mut live = BTreeSet::new();
+            for s in blocks[b].successors.iter() {
+                live.extend(live_in[*s].iter().copied());
+            }
+            for instruction in instructions[ranges[b].clone()].iter().rev() {
+                instruction.access.transfer(&mut live);
+            }
+            if live != live_in[b] {
+                live_in[b] = live;
+                changed = true;
+            }
+        }
+    }
+
+    // Walk each block backwards again, now recording which locals are live
+    // before each instruction.
+    // The indices of the instructions before which each local is live.
+    let mut live_at = vec![Vec::new(); locals.len()];
+    let mut max_live = 0;
+    let mut max_pressure = 0;
+    for (b, block) in blocks.iter().enumerate() {
+        if b > 0 && block.predecessors.is_empty() {
+            continue;
+        }
+        let mut live = BTreeSet::new();
+        for s in block.successors.iter() {
+            live.extend(live_in[*s].iter().copied());
+        }
+        for i in ranges[b].clone().rev() {
+            instructions[i].access.transfer(&mut live);
+            for local in live.iter() {
+                live_at[*local as usize].push(i);
+            }
+            max_live = max_live.max(live.len() as u32);
+            max_pressure = max_pressure.max(live.len() as u32 + instructions[i].stack_depth);
+        }
+    }
+
+    let mut report = locals
+        .iter()
+        .map(|ty| Local {
+            ty: *ty,
+            gets: 0,
+            sets: 0,
+            tees: 0,
+            live: Vec::new(),
+        })
+        .collect::<Vec<_>>();
+    for instruction in instructions {
+        match instruction.access {
+            Access::None => {}
+            Access::Get(local) => report[local as usize].gets += 1,
+            Access::Set(local) => report[local as usize].sets += 1,
+            Access::Tee(local) => report[local as usize].tees += 1,
+        }
+    }
+    for (local, mut live_at) in report.iter_mut().zip(live_at) {
+        live_at.sort_unstable();
+        for i in live_at {
+            let start = instructions[i].offset;
+            let next = instructions.get(i + 1).map_or(end, |i| i.offset);
+            match local.live.last_mut() {
+                Some(range) if range.end == start => range.end = next,
+                _ => local.live.push(start..next),
+            }
+        }
+    }
+
+    Liveness {
+        index: 0,
+        params: 0,
+        locals: report,
+        max_live,
+        max_stack_depth: 0,
+        max_pressure,
+    }
+}
+
+impl fmt::Display for Liveness {
+    /// Formats the report as a line summarizing the function followed by a
+    /// line per local, such as
+    /// `  local 1 (i32): 2 gets, 1 sets, 0 tees, live at 0x1f..0x25`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "func[{}]: {} params, {} locals, max {} live, max stack depth {}, max pressure {}",
+            self.index,
+            self.params,
+            self.locals.len() as u32 - self.params,
+            self.max_live,
+            self.max_stack_depth,
+            self.max_pressure,
+        )?;
+        for (i, local) in self.locals.iter().enumerate() {
+            write!(
+                f,
+                "  local {} ({}): {} gets, {} sets, {} tees",
+                i,
+                type_name(local.ty),
+                local.gets,
+                local.sets,
+                local.tees,
+            )?;
+            if local.live.is_empty() {
+                writeln!(f, ", never live")?;
+                continue;
+            }
+            write!(f, ", live at ")?;
+            for (j, range) in local.live.iter().enumerate() {
+                if j > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{:#x}..{:#x}", range.start, range.end)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the name of `ty` in the text format.
+fn type_name(ty: Type) -> &'static str {
+    match ty {
+        Type::I32 => "i32",
+        Type::I64 => "i64",
+        Type::F32 => "f32",
+        Type::F64 => "f64",
+        Type::V128 => "v128",
+        Type::FuncRef => "funcref",
+        Type::ExternRef => "externref",
+        Type::ExnRef => "exnref",
+        Type::Func | Type::EmptyBlockType => "?",
+    }
+}
@@ -0,0 +1,548 @@
+//! Lowering of instructions from newer proposals into MVP instructions.
+//!
+//! Older engines reject modules using any instruction they don't know, even
+//! ones which a toolchain only emitted because it could. Many of these
+//! instructions can be replaced with a sequence of MVP instructions computing
+//! the same result, which this transform does for:
+//!
+//! * the sign-extension operators, such as `i32.extend8_s`, which become a
+//!   pair of shifts,
+//! * the saturating float-to-int conversions, such as
+//!   `i32.trunc_sat_f32_s`, which become explicit range checks around the
+//!   trapping conversions,
+//! * `memory.fill` and `memory.copy` from the bulk memory proposal, which
+//!   become loops over each byte.
+//!
+//! Whatever can't be lowered, such as `memory.init` or SIMD, is left as-is.
+//! [`usage`](crate::usage::usage) reports which proposals a module still uses
+//! afterwards.
+//!
+//! ```
+//! use wasm_tools::lower::Lower;
+//! use wasm_tools::usage::usage;
+//!
+//! let wasm = wat::parse_str(r#"
+//!     (module
+//!       (memory 1)
+//!       (func (param i32 f32) (result i32)
+//!         local.get 0
+//!         i32.extend8_s
+//!         local.get 1
+//!         i32.trunc_sat_f32_s
+//!         i32.add)
+//!       (func (param i32 i32)
+//!         local.get 0
+//!         i32.const 0
+//!         local.get 1
+//!         memory.fill))
+//! "#)?;
+//! assert_eq!(usage(&wasm)?.proposals["sign-extension"], 1);
+//! let wasm = Lower::new().apply(&wasm)?;
+//! let usage = usage(&wasm)?;
+//! assert_eq!(usage.proposals["sign-extension"], 0);
+//! assert_eq!(usage.proposals["saturating-float-to-int"], 0);
+//! assert_eq!(usage.proposals["bulk-memory"], 0);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::sections::{sections, Section};
+use anyhow::{bail, Result};
+use wasmparser::leb128::{write_i64, write_u32};
+use wasmparser::{
+    CodeSectionReader, FunctionBody, FunctionSectionReader, ImportSectionEntryType,
+    ImportSectionReader, MemorySectionReader, MemoryType, Operator, TypeDef, TypeSectionReader,
+};
+
+const TYPE: u8 = 1;
+const IMPORT: u8 = 2;
+const FUNCTION: u8 = 3;
+const MEMORY: u8 = 5;
+const CODE: u8 = 10;
+const DATA_COUNT: u8 = 12;
+
+const I32: u8 = 0x7f;
+const I64: u8 = 0x7e;
+const F32: u8 = 0x7d;
+const F64: u8 = 0x7c;
+
+/// Which proposals to lower the instructions of, see the [module
+/// documentation](self).
+#[derive(Clone, Debug)]
+pub struct Lower {
+    sign_extension: bool,
+    saturating_float_to_int: bool,
+    bulk_memory: bool,
+}
+
+impl Default for Lower {
+    fn default() -> Lower {
+        Lower {
+            sign_extension: true,
+            saturating_float_to_int: true,
+            bulk_memory: true,
+        }
+    }
+}
+
+impl Lower {
+    /// Creates a new configuration which lowers the instructions of every
+    /// proposal it can.
+    pub fn new() -> Lower {
+        Lower::default()
+    }
+
+    /// Sets whether the sign-extension operators are lowered.
+    pub fn sign_extension(&mut self, lower: bool) -> &mut Lower {
+        self.sign_extension = lower;
+        self
+    }
+
+    /// Sets whether the saturating float-to-int conversions are lowered.
+    pub fn saturating_float_to_int(&mut self, lower: bool) -> &mut Lower {
+        self.saturating_float_to_int = lower;
+        self
+    }
+
+    /// Sets whether `memory.fill` and `memory.copy` are lowered.
+    ///
+    /// They're only lowered when they access a memory with 32-bit addresses,
+    /// and only the first memory of a module. Unlike the original
+    /// instructions, the loops replacing them trap only once they reach the
+    /// first out-of-bounds byte, after writing the ones before it.
+    pub fn bulk_memory(&mut self, lower: bool) -> &mut Lower {
+        self.bulk_memory = lower;
+        self
+    }
+
+    /// Lowers the instructions of the module `wasm`, returning the new
+    /// module.
+    ///
+    /// Functions which need them get new locals, which are declared after
+    /// their existing ones. The data count section is removed if no
+    /// instructions using it are left. All other sections are copied
+    /// verbatim, so offsets recorded in custom sections, such as DWARF, will
+    /// be stale. Functions of modules nested within `wasm`, from the module
+    /// linking proposal, are left untouched.
+    pub fn apply(&self, wasm: &[u8]) -> Result<Vec<u8>> {
+        let module = Module::parse(wasm)?;
+        let mut code = None;
+        let mut needs_data_count = !self.bulk_memory;
+        if let Some(section) = module.sections.iter().find(|s| s.id == CODE) {
+            let mut reader = CodeSectionReader::new(section.contents, section.offset)?;
+            let mut contents = Vec::new();
+            write_u32(&mut contents, reader.get_count());
+            for i in 0..reader.get_count() {
+                let body = reader.read()?;
+                let params = match module.functions.get(i as usize) {
+                    Some(params) => *params,
+                    None => bail!("function and code section have inconsistent lengths"),
+                };
+                let mut encoded = Vec::new();
+                needs_data_count |= self.body(wasm, &module, params, &body, &mut encoded)?;
+                write_u32(&mut contents, encoded.len() as u32);
+                contents.extend_from_slice(&encoded);
+            }
+            code = Some(contents);
+        }
+
+        let mut output = wasm[..8].to_vec();
+        for section in module.sections.iter() {
+            match (section.id, &code) {
+                (CODE, Some(contents)) => Section {
+                    id: CODE,
+                    contents,
+                    offset: 0,
+                }
+                .encode(&mut output),
+                (DATA_COUNT, _) if !needs_data_count => {}
+                _ => section.encode(&mut output),
+            }
+        }
+        Ok(output)
+    }
+
+    /// Writes `body`, a function with `params` parameters, to `output` with
+    /// its instructions lowered, returning whether it uses the data count
+    /// section.
+    fn body(
+        &self,
+        wasm: &[u8],
+        module: &Module<'_>,
+        params: u32,
+        body: &FunctionBody<'_>,
+        output: &mut Vec<u8>,
+    ) -> Result<bool> {
+        // Find out what's lowered first, so the scratch locals which the
+        // lowered instructions need can be declared.
+        let mut lowered = false;
+        let mut scratch = Vec::new();
+        let mut uses_data_count = false;
+        let mut reader = body.get_operators_reader()?;
+        while !reader.eof() {
+            let op = reader.read()?;
+            let ty = match self.lowering(module, &op) {
+                Some(Lowering::SignExtend { .. }) => None,
+                Some(Lowering::TruncSat { from, .. }) => Some(from),
+                Some(Lowering::MemoryFill) | Some(Lowering::MemoryCopy) => Some(I32),
+                None => {
+                    if let Operator::MemoryInit { .. } | Operator::DataDrop { .. } = op {
+                        uses_data_count = true;
+                    }
+                    continue;
+                }
+            };
+            lowered = true;
+            if let Some(ty) = ty {
+                if !scratch.contains(&ty) {
+                    scratch.push(ty);
+                }
+            }
+        }
+        let range = body.range();
+        if !lowered {
+            output.extend_from_slice(&wasm[range.start..range.end]);
+            return Ok(uses_data_count);
+        }
+
+        // Existing locals are copied as-is, followed by the scratch locals.
+        let mut locals = body.get_locals_reader()?;
+        let groups_start = locals.original_position();
+        let mut declared = 0u32;
+        for _ in 0..locals.get_count() {
+            declared += locals.read()?.0;
+        }
+        let mut ops = body.get_operators_reader()?;
+        write_u32(output, locals.get_count() + scratch.len() as u32);
+        output.extend_from_slice(&wasm[groups_start..ops.original_position()]);
+        let mut next = params + declared;
+        let mut scratch_locals = Scratch::default();
+        for ty in scratch {
+            let count = match ty {
+                I32 => {
+                    scratch_locals.i32 = next;
+                    3
+                }
+                F32 => {
+                    scratch_locals.f32 = next;
+                    1
+                }
+                _ => {
+                    scratch_locals.f64 = next;
+                    1
+                }
+            };
+            write_u32(output, count);
+            output.push(ty);
+            next += count;
+        }
+
+        while !ops.eof() {
+            let (op, start) = ops.read_with_offset()?;
+            match self.lowering(module, &op) {
+                Some(lowering) => lowering.encode(&scratch_locals, output),
+                None => output.extend_from_slice(&wasm[start..ops.original_position()]),
+            }
+        }
+        Ok(uses_data_count)
+    }
+
+    /// Returns how `op` is lowered, or `None` if it's left as-is.
+    fn lowering(&self, module: &Module<'_>, op: &Operator<'_>) -> Option<Lowering> {
+        let sign_extend = |ty, bits| Some(Lowering::SignExtend { ty, bits });
+        let trunc_sat = |to, from, signed| Some(Lowering::TruncSat { to, from, signed });
+        match *op {
+            Operator::I32Extend8S if self.sign_extension => sign_extend(I32, 8),
+            Operator::I32Extend16S if self.sign_extension => sign_extend(I32, 16),
+            Operator::I64Extend8S if self.sign_extension => sign_extend(I64, 8),
+            Operator::I64Extend16S if self.sign_extension => sign_extend(I64, 16),
+            Operator::I64Extend32S if self.sign_extension => sign_extend(I64, 32),
+            Operator::I32TruncSatF32S if self.saturating_float_to_int => trunc_sat(I32, F32, true),
+            Operator::I32TruncSatF32U if self.saturating_float_to_int => trunc_sat(I32, F32, false),
+            Operator::I32TruncSatF64S if self.saturating_float_to_int => trunc_sat(I32, F64, true),
+            Operator::I32TruncSatF64U if self.saturating_float_to_int => trunc_sat(I32, F64, false),
+            Operator::I64TruncSatF32S if self.saturating_float_to_int => trunc_sat(I64, F32, true),
+            Operator::I64TruncSatF32U if self.saturating_float_to_int => trunc_sat(I64, F32, false),
+            Operator::I64TruncSatF64S if self.saturating_float_to_int => trunc_sat(I64, F64, true),
+            Operator::I64TruncSatF64U if self.saturating_float_to_int => trunc_sat(I64, F64, false),
+            Operator::MemoryFill { mem: 0 } if self.bulk_memory && module.memory32 => {
+                Some(Lowering::MemoryFill)
+            }
+            Operator::MemoryCopy { src: 0, dst: 0 } if self.bulk_memory && module.memory32 => {
+                Some(Lowering::MemoryCopy)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The first of the scratch locals of each type of a function. There are
+/// three `i32` locals and one of each float type.
+#[derive(Default)]
+struct Scratch {
+    i32: u32,
+    f32: u32,
+    f64: u32,
+}
+
+/// How an instruction is lowered.
+enum Lowering {
+    /// Sign-extends the low `bits` of a value of type `ty`.
+    SignExtend {
+        ty: u8,
+        bits: u32,
+    },
+    /// Converts a float of type `from` to an integer of type `to`,
+    /// saturating instead of trapping.
+    TruncSat {
+        to: u8,
+        from: u8,
+        signed: bool,
+    },
+    MemoryFill,
+    MemoryCopy,
+}
+
+impl Lowering {
+    fn encode(&self, scratch: &Scratch, output: &mut Vec<u8>) {
+        match *self {
+            Lowering::SignExtend { ty, bits } => {
+                // Shifting the value left and then arithmetically right
+                // copies its sign bit into the bits above it.
+                let (shl, shr_s, size) = if ty == I32 {
+                    (0x74, 0x75, 32)
+                } else {
+                    (0x86, 0x87, 64)
+                };
+                for op in [shl, shr_s].iter() {
+                    const_int(ty, (size - bits).into(), output);
+                    output.push(*op);
+                }
+            }
+            Lowering::TruncSat { to, from, signed } => trunc_sat(scratch, to, from, signed, output),
+            Lowering::MemoryFill => {
+                // (dst, val, n) -> store `val` to each byte from `dst` on.
+                let (dst, val, n) = (scratch.i32, scratch.i32 + 1, scratch.i32 + 2);
+                local(0x21, n, output);
+                local(0x21, val, output);
+                local(0x21, dst, output);
+                output.extend_from_slice(&[0x02, 0x40, 0x03, 0x40]);
+                local(0x20, n, output);
+                output.extend_from_slice(&[0x45, 0x0d, 0x01]);
+                local(0x20, dst, output);
+                local(0x20, val, output);
+                output.extend_from_slice(&[0x3a, 0x00, 0x00]);
+                increment(dst, 1, output);
+                increment(n, -1, output);
+                output.extend_from_slice(&[0x0c, 0x00, 0x0b, 0x0b]);
+            }
+            Lowering::MemoryCopy => {
+                // (dst, src, n) -> copy forwards when the destination comes
+                // first and backwards otherwise, so overlapping ranges are
+                // copied correctly.
+                let (dst, src, n) = (scratch.i32, scratch.i32 + 1, scratch.i32 + 2);
+                local(0x21, n, output);
+                local(0x21, src, output);
+                local(0x21, dst, output);
+                local(0x20, dst, output);
+                local(0x20, src, output);
+                output.extend_from_slice(&[0x4d, 0x04, 0x40]);
+                output.extend_from_slice(&[0x02, 0x40, 0x03, 0x40]);
+                local(0x20, n, output);
+                output.extend_from_slice(&[0x45, 0x0d, 0x01]);
+                local(0x20, dst, output);
+                local(0x20, src, output);
+                output.extend_from_slice(&[0x2d, 0x00, 0x00, 0x3a, 0x00, 0x00]);
+                increment(dst, 1, output);
+                increment(src, 1, output);
+                increment(n, -1, output);
+                output.extend_from_slice(&[0x0c, 0x00, 0x0b, 0x0b]);
+                output.push(0x05);
+                output.extend_from_slice(&[0x02, 0x40, 0x03, 0x40]);
+                local(0x20, n, output);
+                output.extend_from_slice(&[0x45, 0x0d, 0x01]);
+                increment(n, -1, output);
+                for addr in [dst, src].iter() {
+                    local(0x20, *addr, output);
+                    local(0x20, n, output);
+                    output.push(0x6a);
+                }
+                output.extend_from_slice(&[0x2d, 0x00, 0x00, 0x3a, 0x00, 0x00]);
+                output.extend_from_slice(&[0x0c, 0x00, 0x0b, 0x0b]);
+                output.push(0x0b);
+            }
+        }
+    }
+}
+
+/// Writes a saturating conversion of the float of type `from` on the stack
+/// to an integer of type `to`.
+///
+/// NaN converts to 0, and floats out of the integer's range to its minimum
+/// or maximum. Everything else is converted with the trapping conversion:
+///
+/// ```text
+/// local.set $f
+/// block (result to)
+///   to.const 0   local.get $f local.get $f from.ne br_if 0 drop
+///   to.const max local.get $f from.const hi  from.ge br_if 0 drop
+///   to.const min local.get $f from.const lo  from.le br_if 0 drop
+///   local.get $f to.trunc_from
+/// end
+/// ```
+fn trunc_sat(scratch: &Scratch, to: u8, from: u8, signed: bool, output: &mut Vec<u8>) {
+    // Floats strictly between `lo` and `hi` convert without trapping. `lo`
+    // is the greatest float of type `from` which truncates to less than the
+    // integer's minimum.
+    let (min, max, lo, hi, trunc) = match (to, from, signed) {
+        (I32, F32, true) => (
+            i32::MIN.into(),
+            i32::MAX.into(),
+            -2147483904.0,
+            2147483648.0,
+            0xa8,
+        ),
+        (I32, F32, false) => (0, -1, -1.0, 4294967296.0, 0xa9),
+        (I32, _, true) => (
+            i32::MIN.into(),
+            i32::MAX.into(),
+            -2147483649.0,
+            2147483648.0,
+            0xaa,
+        ),
+        (I32, _, false) => (0, -1, -1.0, 4294967296.0, 0xab),
+        (_, F32, true) => (
+            i64::MIN,
+            i64::MAX,
+            -9223373136366403584.0,
+            9223372036854775808.0,
+            0xae,
+        ),
+        (_, F32, false) => (0, -1, -1.0, 18446744073709551616.0, 0xaf),
+        (_, _, true) => (
+            i64::MIN,
+            i64::MAX,
+            -9223372036854777856.0,
+            9223372036854775808.0,
+            0xb0,
+        ),
+        (_, _, false) => (0, -1, -1.0, 18446744073709551616.0, 0xb1),
+    };
+    let (f, ne, le, ge) = if from == F32 {
+        (scratch.f32, 0x5c, 0x5f, 0x60)
+    } else {
+        (scratch.f64, 0x62, 0x65, 0x66)
+    };
+    local(0x21, f, output);
+    output.extend_from_slice(&[0x02, to]);
+    const_int(to, 0, output);
+    local(0x20, f, output);
+    local(0x20, f, output);
+    output.push(ne);
+    output.extend_from_slice(&[0x0d, 0x00, 0x1a]);
+    for (value, bound, cmp) in [(max, hi, ge), (min, lo, le)].iter() {
+        const_int(to, *value, output);
+        local(0x20, f, output);
+        const_float(from, *bound, output);
+        output.push(*cmp);
+        output.extend_from_slice(&[0x0d, 0x00, 0x1a]);
+    }
+    local(0x20, f, output);
+    output.push(trunc);
+    output.push(0x0b);
+}
+
+/// Writes the constant `value` of the integer type `ty`, truncated to its
+/// width.
+fn const_int(ty: u8, value: i64, output: &mut Vec<u8>) {
+    if ty == I32 {
+        output.push(0x41);
+        write_i64(output, (value as i32).into());
+    } else {
+        output.push(0x42);
+        write_i64(output, value);
+    }
+}
+
+/// Writes the constant `value` of the float type `ty`.
+fn const_float(ty: u8, value: f64, output: &mut Vec<u8>) {
+    if ty == F32 {
+        output.push(0x43);
+        output.extend_from_slice(&(value as f32).to_bits().to_le_bytes());
+    } else {
+        output.push(0x44);
+        output.extend_from_slice(&value.to_bits().to_le_bytes());
+    }
+}
+
+/// Writes the instruction `opcode`, such as `local.get`, of the local
+/// `index`.
+fn local(opcode: u8, index: u32, output: &mut Vec<u8>) {
+    output.push(opcode);
+    write_u32(output, index);
+}
+
+/// Writes instructions adding `delta` to the `i32` local `index`.
+fn increment(index: u32, delta: i32, output: &mut Vec<u8>) {
+    local(0x20, index, output);
+    const_int(I32, delta.into(), output);
+    output.push(0x6a);
+    local(0x21, index, output);
+}
+
+/// The parts of a module needed to lower its instructions.
+struct Module<'a> {
+    sections: Vec<Section<'a>>,
+    /// The number of parameters of each defined function.
+    functions: Vec<u32>,
+    /// Whether the module's first memory has 32-bit addresses.
+    memory32: bool,
+}
+
+impl<'a> Module<'a> {
+    fn parse(wasm: &'a [u8]) -> Result<Module<'a>> {
+        let sections = sections(wasm)?;
+        let mut types = Vec::new();
+        let mut functions = Vec::new();
+        let mut memories = Vec::new();
+        for section in sections.iter() {
+            match section.id {
+                TYPE => {
+                    for ty in TypeSectionReader::new(section.contents, section.offset)? {
+                        types.push(match ty? {
+                            TypeDef::Func(ty) => Some(ty.params.len() as u32),
+                            _ => None,
+                        });
+                    }
+                }
+                IMPORT => {
+                    for import in ImportSectionReader::new(section.contents, section.offset)? {
+                        if let ImportSectionEntryType::Memory(ty) = import?.ty {
+                            memories.push(ty);
+                        }
+                    }
+                }
+                FUNCTION => {
+                    for ty in FunctionSectionReader::new(section.contents, section.offset)? {
+                        let ty = ty?;
+                        match types.get(ty as usize) {
+                            Some(Some(params)) => functions.push(*params),
+                            _ => bail!("function has an invalid type index {}", ty),
+                        }
+                    }
+                }
+                MEMORY => {
+                    for ty in MemorySectionReader::new(section.contents, section.offset)? {
+                        memories.push(ty?);
+                    }
+                }
+                _ => {}
+            }
+        }
+        let memory32 = matches!(memories.first(), Some(MemoryType::M32 { .. }));
+        Ok(Module {
+            sections,
+            functions,
+            memory32,
+        })
+    }
+}
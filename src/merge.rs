@@ -0,0 +1,838 @@
+//! Merging of sibling modules into a single module.
+//!
+//! This is the inverse of [splitting](crate::split): modules which are
+//! instantiated side by side, and import from each other by name, become one
+//! module. Their function types are unified, their index spaces concatenated
+//! in the order the modules are given, and every import of an item which a
+//! sibling exports is resolved to that item, so calls across modules become
+//! direct calls. Identical imports from outside, such as the same memory
+//! imported by every module, are imported once.
+//!
+//! ```
+//! use wasm_tools::merge::merge;
+//!
+//! let app = wat::parse_str(r#"
+//!     (module
+//!       (import "lib" "double" (func $double (param i32) (result i32)))
+//!       (func (export "main") (result i32)
+//!         i32.const 21
+//!         call $double))
+//! "#)?;
+//! let lib = wat::parse_str(r#"
+//!     (module
+//!       (func (export "double") (param i32) (result i32)
+//!         local.get 0
+//!         i32.const 2
+//!         i32.mul))
+//! "#)?;
+//! let wasm = merge(&[("app", &app), ("lib", &lib)])?;
+//! let text = wasmprinter::print_bytes(&wasm)?;
+//! assert!(!text.contains("import"));
+//! assert!(text.contains("call 1"));
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::gc::{Map, Renumbering};
+use crate::names::Names;
+use crate::sections::{sections, write_str, Section};
+use anyhow::{anyhow, bail, Result};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use wasmparser::leb128::write_u32;
+use wasmparser::{
+    BinaryReader, CodeSectionReader, DataKind, DataSectionReader, ElementSectionReader,
+    EventSectionReader, FunctionBody, FunctionSectionReader, GlobalSectionReader, GlobalType,
+    ImportSectionEntryType, ImportSectionReader, InitExpr, MemorySectionReader, TableSectionReader,
+    Type, TypeSectionReader,
+};
+
+const TYPE: u8 = 1;
+const IMPORT: u8 = 2;
+const FUNCTION: u8 = 3;
+const TABLE: u8 = 4;
+const MEMORY: u8 = 5;
+const GLOBAL: u8 = 6;
+const EXPORT: u8 = 7;
+const START: u8 = 8;
+const ELEMENT: u8 = 9;
+const CODE: u8 = 10;
+const DATA: u8 = 11;
+const DATA_COUNT: u8 = 12;
+const EVENT: u8 = 13;
+const MODULE: u8 = 14;
+const MODULE_CODE: u8 = 17;
+
+// The kinds of imports and exports, which index the index spaces.
+const FUNC: u8 = 0;
+const TABLE_KIND: u8 = 1;
+const MEMORY_KIND: u8 = 2;
+const GLOBAL_KIND: u8 = 3;
+const EVENT_KIND: u8 = 4;
+const KINDS: [&str; 5] = ["function", "table", "memory", "global", "event"];
+
+/// Merges `modules`, each given with the name under which its siblings
+/// import from it, into a single module.
+///
+/// Imports from a module named like one of `modules` must name an export of
+/// it with the same kind and type. The merged module exports everything the
+/// modules export, and if several modules have a start function it gets a
+/// new one which calls them in order. Active data and element segments are
+/// applied in order too, but all of them before any start function runs.
+///
+/// Function, local and global names in the `name` section are kept, but
+/// other custom sections are dropped.
+///
+/// Returns an error if a module is malformed, if an import from a sibling
+/// can't be resolved, if two modules export different items under the same
+/// name, or if the memories of a module would have to be renumbered, that
+/// is, if more than one module defines or imports a distinct memory.
+/// Modules using the module linking proposal aren't supported.
+pub fn merge(modules: &[(&str, &[u8])]) -> Result<Vec<u8>> {
+    let mut inputs = Vec::new();
+    for (name, wasm) in modules {
+        if inputs.iter().any(|i: &Input<'_>| i.name == *name) {
+            bail!("more than one module is named `{}`", name);
+        }
+        inputs.push(Input::parse(name, wasm)?);
+    }
+
+    // Unify the types, in order of first appearance.
+    let mut types = Vec::new();
+    let mut type_indices = HashMap::new();
+    let mut type_maps = Vec::new();
+    for input in inputs.iter() {
+        let map = input
+            .types
+            .iter()
+            .map(|ty| {
+                *type_indices.entry(*ty).or_insert_with(|| {
+                    types.push(*ty);
+                    types.len() as u32 - 1
+                })
+            })
+            .collect::<Vec<_>>();
+        type_maps.push(map);
+    }
+    let mut merger = Merger {
+        inputs: &inputs,
+        type_maps: &type_maps,
+        externals: Vec::new(),
+        external_indices: HashMap::new(),
+        imported: [0; 5],
+    };
+
+    // Resolve the imports of every module, then lay out the index spaces:
+    // the remaining imports first, then the items each module defines.
+    let mut targets = Vec::new();
+    for (m, input) in inputs.iter().enumerate() {
+        let mut spaces: [Vec<Target>; 5] = Default::default();
+        for (i, import) in input.imports.iter().enumerate() {
+            let target = merger.resolve(m, i)?;
+            spaces[import.kind as usize].push(target);
+        }
+        targets.push(spaces);
+    }
+    let mut bases = Vec::new();
+    let mut next = merger.imported;
+    for (input, spaces) in inputs.iter().zip(targets.iter_mut()) {
+        bases.push(next);
+        for (kind, space) in spaces.iter_mut().enumerate() {
+            let defined = input.defined(kind as u8);
+            space.extend((0..defined).map(Target::Defined));
+            next[kind] += defined;
+        }
+    }
+    let index = |m: usize, kind: u8, target: &Target| match target {
+        Target::Defined(d) => bases[m][kind as usize] + d,
+        Target::Sibling(s, d) => bases[*s][kind as usize] + d,
+        Target::External(e) => merger.externals[*e].index,
+    };
+    let mut spaces = Vec::new();
+    for (m, input) in inputs.iter().enumerate() {
+        let space = |kind: u8| {
+            targets[m][kind as usize]
+                .iter()
+                .map(|t| index(m, kind, t))
+                .collect::<Vec<_>>()
+        };
+        let memories = space(MEMORY_KIND);
+        for (i, memory) in memories.iter().enumerate() {
+            if *memory != i as u32 {
+                bail!(
+                    "merging would renumber memory {} of module `{}`, which isn't supported",
+                    i,
+                    input.name
+                );
+            }
+        }
+        spaces.push([
+            space(FUNC),
+            space(TABLE_KIND),
+            space(GLOBAL_KIND),
+            space(EVENT_KIND),
+        ]);
+    }
+
+    // Several start functions are called by a new one, which goes last.
+    let mut starts = Vec::new();
+    for (m, input) in inputs.iter().enumerate() {
+        starts.extend(input.start.map(|f| spaces[m][0][f as usize]));
+    }
+    let start_type = if starts.len() > 1 {
+        const EMPTY: &[u8] = &[0x60, 0x00, 0x00];
+        Some(*type_indices.entry(EMPTY).or_insert_with(|| {
+            types.push(EMPTY);
+            types.len() as u32 - 1
+        }))
+    } else {
+        None
+    };
+    let funcs = next[FUNC as usize];
+    let start = match start_type {
+        Some(_) => Some(funcs),
+        None => starts.first().copied(),
+    };
+
+    let mut rewrites = Vec::new();
+    let mut elements = 0;
+    let mut data = 0;
+    let total_elements = inputs.iter().map(|i| i.elements).sum::<u32>();
+    let total_data = inputs.iter().map(|i| i.data).sum::<u32>();
+    for (m, input) in inputs.iter().enumerate() {
+        let [funcs, tables, globals, events] = &spaces[m];
+        let renumbering = |indices: &[u32], count: u32| Renumbering {
+            indices: indices.iter().map(|i| Some(*i)).collect(),
+            count,
+        };
+        let data_indices = (data..data + input.data).collect::<Vec<_>>();
+        let element_indices = (elements..elements + input.elements).collect::<Vec<_>>();
+        rewrites.push(Map {
+            types: renumbering(&type_maps[m], types.len() as u32),
+            funcs: renumbering(funcs, next[FUNC as usize]),
+            globals: renumbering(globals, next[GLOBAL_KIND as usize]),
+            data: renumbering(&data_indices, total_data),
+            tables: Some(renumbering(tables, next[TABLE_KIND as usize])),
+            events: Some(renumbering(events, next[EVENT_KIND as usize])),
+            elements: Some(renumbering(&element_indices, total_elements)),
+        });
+        elements += input.elements;
+        data += input.data;
+    }
+
+    let mut output = b"\0asm\x01\0\0\0".to_vec();
+    let mut contents = Vec::new();
+    write_u32(&mut contents, types.len() as u32);
+    for ty in types.iter() {
+        contents.extend_from_slice(ty);
+    }
+    encode_section(TYPE, &contents, &mut output);
+
+    let mut contents = Vec::new();
+    write_u32(&mut contents, merger.externals.len() as u32);
+    for external in merger.externals.iter() {
+        write_str(&mut contents, external.module);
+        write_str(&mut contents, external.field);
+        contents.push(external.kind);
+        contents.extend_from_slice(&external.ty);
+    }
+    encode_section(IMPORT, &contents, &mut output);
+
+    let mut contents = Vec::new();
+    write_u32(
+        &mut contents,
+        funcs - merger.imported[FUNC as usize] + start_type.is_some() as u32,
+    );
+    for (input, rewrite) in inputs.iter().zip(rewrites.iter()) {
+        for ty in input.func_types[input.imported(FUNC)..].iter() {
+            write_u32(&mut contents, rewrite.types.get(*ty));
+        }
+    }
+    if let Some(ty) = start_type {
+        write_u32(&mut contents, ty);
+    }
+    encode_section(FUNCTION, &contents, &mut output);
+
+    let tables = inputs.iter().flat_map(|i| i.tables.iter());
+    let memories = inputs.iter().flat_map(|i| i.memories.iter());
+    for (id, items) in [
+        (TABLE, tables.collect::<Vec<_>>()),
+        (MEMORY, memories.collect()),
+    ]
+    .iter()
+    {
+        let mut contents = Vec::new();
+        write_u32(&mut contents, items.len() as u32);
+        for item in items {
+            contents.extend_from_slice(item);
+        }
+        encode_section(*id, &contents, &mut output);
+    }
+
+    let mut contents = Vec::new();
+    write_u32(
+        &mut contents,
+        next[EVENT_KIND as usize] - merger.imported[EVENT_KIND as usize],
+    );
+    for (input, rewrite) in inputs.iter().zip(rewrites.iter()) {
+        for ty in input.event_types[input.imported(EVENT_KIND)..].iter() {
+            contents.push(0x00);
+            write_u32(&mut contents, rewrite.types.get(*ty));
+        }
+    }
+    encode_section(EVENT, &contents, &mut output);
+
+    let mut contents = Vec::new();
+    write_u32(
+        &mut contents,
+        next[GLOBAL_KIND as usize] - merger.imported[GLOBAL_KIND as usize],
+    );
+    for (input, rewrite) in inputs.iter().zip(rewrites.iter()) {
+        for (ty, init) in input.globals.iter() {
+            contents.extend_from_slice(ty);
+            rewrite.expr(input.wasm, init.get_operators_reader(), &mut contents)?;
+        }
+    }
+    encode_section(GLOBAL, &contents, &mut output);
+
+    let mut exports = Vec::new();
+    let mut exported = HashMap::<_, (usize, u8, u32)>::new();
+    for (m, input) in inputs.iter().enumerate() {
+        for (field, kind, i) in input.exports.iter() {
+            let index = index(m, *kind, &targets[m][*kind as usize][*i as usize]);
+            match exported.entry(*field) {
+                Entry::Occupied(entry) => {
+                    let (other, other_kind, other_index) = *entry.get();
+                    if (other_kind, other_index) != (*kind, index) {
+                        bail!(
+                            "modules `{}` and `{}` both export `{}`",
+                            inputs[other].name,
+                            input.name,
+                            field
+                        );
+                    }
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert((m, *kind, index));
+                    exports.push((*field, *kind, index));
+                }
+            }
+        }
+    }
+    let mut contents = Vec::new();
+    write_u32(&mut contents, exports.len() as u32);
+    for (field, kind, index) in exports {
+        write_str(&mut contents, field);
+        contents.push(kind);
+        write_u32(&mut contents, index);
+    }
+    encode_section(EXPORT, &contents, &mut output);
+
+    if let Some(start) = start {
+        let mut contents = Vec::new();
+        write_u32(&mut contents, start);
+        output.push(START);
+        write_u32(&mut output, contents.len() as u32);
+        output.extend_from_slice(&contents);
+    }
+
+    let mut contents = Vec::new();
+    write_u32(&mut contents, elements);
+    for (input, rewrite) in inputs.iter().zip(rewrites.iter()) {
+        if let Some(section) = input.section(ELEMENT) {
+            let mut reader = ElementSectionReader::new(section.contents, section.offset)?;
+            for _ in 0..reader.get_count() {
+                rewrite.element(input.wasm, &mut reader, &mut contents)?;
+            }
+        }
+    }
+    encode_section(ELEMENT, &contents, &mut output);
+
+    if inputs.iter().any(|i| i.section(DATA_COUNT).is_some()) {
+        let mut contents = Vec::new();
+        write_u32(&mut contents, total_data);
+        output.push(DATA_COUNT);
+        write_u32(&mut output, contents.len() as u32);
+        output.extend_from_slice(&contents);
+    }
+
+    let mut contents = Vec::new();
+    write_u32(
+        &mut contents,
+        funcs - merger.imported[FUNC as usize] + start_type.is_some() as u32,
+    );
+    for (input, rewrite) in inputs.iter().zip(rewrites.iter()) {
+        for body in input.bodies.iter() {
+            let ops = body.get_operators_reader()?;
+            let mut encoded = input.wasm[body.range().start..ops.original_position()].to_vec();
+            rewrite.expr(input.wasm, ops, &mut encoded)?;
+            write_u32(&mut contents, encoded.len() as u32);
+            contents.extend_from_slice(&encoded);
+        }
+    }
+    if start_type.is_some() {
+        let mut body = vec![0x00];
+        for f in starts {
+            body.push(0x10);
+            write_u32(&mut body, f);
+        }
+        body.push(0x0b);
+        write_u32(&mut contents, body.len() as u32);
+        contents.extend_from_slice(&body);
+    }
+    encode_section(CODE, &contents, &mut output);
+
+    let mut contents = Vec::new();
+    write_u32(&mut contents, total_data);
+    for (input, rewrite) in inputs.iter().zip(rewrites.iter()) {
+        if let Some(section) = input.section(DATA) {
+            for segment in DataSectionReader::new(section.contents, section.offset)? {
+                let segment = segment?;
+                match segment.kind {
+                    DataKind::Passive => contents.push(0x01),
+                    DataKind::Active {
+                        memory_index,
+                        init_expr,
+                    } => {
+                        if memory_index == 0 {
+                            contents.push(0x00);
+                        } else {
+                            contents.push(0x02);
+                            write_u32(&mut contents, memory_index);
+                        }
+                        rewrite.expr(
+                            input.wasm,
+                            init_expr.get_operators_reader(),
+                            &mut contents,
+                        )?;
+                    }
+                }
+                write_u32(&mut contents, segment.data.len() as u32);
+                contents.extend_from_slice(segment.data);
+            }
+        }
+    }
+    encode_section(DATA, &contents, &mut output);
+
+    // Items named in several modules keep the name given by the module
+    // defining them, or by the first module importing them.
+    let mut names = Names::new();
+    for (m, input) in inputs.iter().enumerate() {
+        let old = Names::read(input.wasm)?;
+        let [funcs, _, globals, _] = &spaces[m];
+        for (kind, indices) in [(FUNC, funcs), (GLOBAL_KIND, globals)].iter() {
+            for (i, new) in indices.iter().enumerate() {
+                let defined = match targets[m][*kind as usize][i] {
+                    Target::Defined(_) => true,
+                    Target::Sibling(..) => continue,
+                    Target::External(_) => false,
+                };
+                let (name, named) = if *kind == FUNC {
+                    (old.function(i as u32), names.function(*new).is_some())
+                } else {
+                    (old.global(i as u32), names.global(*new).is_some())
+                };
+                let name = match name {
+                    Some(name) if defined || !named => name,
+                    _ => continue,
+                };
+                if *kind == FUNC {
+                    names.set_function(*new, name);
+                    for (local, name) in old.locals(i as u32) {
+                        names.set_local(*new, local, name);
+                    }
+                } else {
+                    names.set_global(*new, name);
+                }
+            }
+        }
+    }
+    names.apply(&output)
+}
+
+/// A module to merge.
+struct Input<'a> {
+    name: &'a str,
+    wasm: &'a [u8],
+    sections: Vec<Section<'a>>,
+    /// The encoded types.
+    types: Vec<&'a [u8]>,
+    imports: Vec<Import<'a>>,
+    /// The types of all functions and events, and of all tables and globals,
+    /// imported ones first.
+    func_types: Vec<u32>,
+    event_types: Vec<u32>,
+    table_types: Vec<Type>,
+    global_types: Vec<GlobalType>,
+    /// The encoded types of the defined tables and memories.
+    tables: Vec<&'a [u8]>,
+    memories: Vec<&'a [u8]>,
+    /// The encoded type and the initializer of each defined global.
+    globals: Vec<(&'a [u8], InitExpr<'a>)>,
+    bodies: Vec<FunctionBody<'a>>,
+    exports: Vec<(&'a str, u8, u32)>,
+    start: Option<u32>,
+    /// The number of element and data segments.
+    elements: u32,
+    data: u32,
+}
+
+struct Import<'a> {
+    module: &'a str,
+    field: &'a str,
+    kind: u8,
+    /// The index of the import among the items of its kind.
+    index: u32,
+    /// The encoded type which follows the kind.
+    ty: &'a [u8],
+}
+
+impl<'a> Input<'a> {
+    fn parse(name: &'a str, wasm: &'a [u8]) -> Result<Input<'a>> {
+        let mut input = Input {
+            name,
+            wasm,
+            sections: sections(wasm)?,
+            types: Vec::new(),
+            imports: Vec::new(),
+            func_types: Vec::new(),
+            event_types: Vec::new(),
+            table_types: Vec::new(),
+            global_types: Vec::new(),
+            tables: Vec::new(),
+            memories: Vec::new(),
+            globals: Vec::new(),
+            bodies: Vec::new(),
+            exports: Vec::new(),
+            start: None,
+            elements: 0,
+            data: 0,
+        };
+        let mut counts = [0; 5];
+        for section in input.sections.iter() {
+            match section.id {
+                TYPE => {
+                    let mut reader = TypeSectionReader::new(section.contents, section.offset)?;
+                    for _ in 0..reader.get_count() {
+                        let start = reader.original_position();
+                        reader.read()?;
+                        input.types.push(&wasm[start..reader.original_position()]);
+                    }
+                }
+                IMPORT => {
+                    let mut reader = ImportSectionReader::new(section.contents, section.offset)?;
+                    for _ in 0..reader.get_count() {
+                        let start = reader.original_position();
+                        let import = reader.read()?;
+                        let field = match import.field {
+                            Some(field) => field,
+                            None => {
+                                bail!("modules using the module linking proposal are not supported")
+                            }
+                        };
+                        let kind = match import.ty {
+                            ImportSectionEntryType::Function(ty) => {
+                                input.func_types.push(ty);
+                                FUNC
+                            }
+                            ImportSectionEntryType::Table(ty) => {
+                                input.table_types.push(ty.element_type);
+                                TABLE_KIND
+                            }
+                            ImportSectionEntryType::Memory(_) => MEMORY_KIND,
+                            ImportSectionEntryType::Global(ty) => {
+                                input.global_types.push(ty);
+                                GLOBAL_KIND
+                            }
+                            ImportSectionEntryType::Event(ty) => {
+                                input.event_types.push(ty.type_index);
+                                EVENT_KIND
+                            }
+                            _ => {
+                                bail!("modules using the module linking proposal are not supported")
+                            }
+                        };
+                        let entry = &wasm[start..reader.original_position()];
+                        let mut entry_reader = BinaryReader::new(entry);
+                        entry_reader.read_string()?;
+                        entry_reader.read_string()?;
+                        entry_reader.read_u8()?;
+                        input.imports.push(Import {
+                            module: import.module,
+                            field,
+                            kind,
+                            index: counts[kind as usize],
+                            ty: &entry[entry_reader.current_position()..],
+                        });
+                        counts[kind as usize] += 1;
+                    }
+                }
+                FUNCTION => {
+                    for ty in FunctionSectionReader::new(section.contents, section.offset)? {
+                        input.func_types.push(ty?);
+                    }
+                }
+                TABLE => {
+                    let mut reader = TableSectionReader::new(section.contents, section.offset)?;
+                    for _ in 0..reader.get_count() {
+                        let start = reader.original_position();
+                        input.table_types.push(reader.read()?.element_type);
+                        input.tables.push(&wasm[start..reader.original_position()]);
+                    }
+                }
+                MEMORY => {
+                    let mut reader = MemorySectionReader::new(section.contents, section.offset)?;
+                    for _ in 0..reader.get_count() {
+                        let start = reader.original_position();
+                        reader.read()?;
+                        input
+                            .memories
+                            .push(&wasm[start..reader.original_position()]);
+                    }
+                }
+                GLOBAL => {
+                    let mut reader = GlobalSectionReader::new(section.contents, section.offset)?;
+                    for _ in 0..reader.get_count() {
+                        let start = reader.original_position();
+                        let global = reader.read()?;
+                        let init = global.init_expr.get_operators_reader();
+                        let ty = &wasm[start..init.original_position()];
+                        input.global_types.push(global.ty);
+                        input.globals.push((ty, global.init_expr));
+                    }
+                }
+                EVENT => {
+                    for ty in EventSectionReader::new(section.contents, section.offset)? {
+                        input.event_types.push(ty?.type_index);
+                    }
+                }
+                EXPORT => {
+                    let mut reader = BinaryReader::new(section.contents);
+                    for _ in 0..reader.read_var_u32()? {
+                        let field = reader.read_string()?;
+                        let kind = reader.read_u8()? as u8;
+                        let index = reader.read_var_u32()?;
+                        if kind > EVENT_KIND {
+                            bail!("modules using the module linking proposal are not supported");
+                        }
+                        input.exports.push((field, kind, index));
+                    }
+                }
+                START => input.start = Some(BinaryReader::new(section.contents).read_var_u32()?),
+                ELEMENT => {
+                    input.elements = BinaryReader::new(section.contents).read_var_u32()?;
+                }
+                CODE => {
+                    for body in CodeSectionReader::new(section.contents, section.offset)? {
+                        input.bodies.push(body?);
+                    }
+                }
+                DATA => input.data = BinaryReader::new(section.contents).read_var_u32()?,
+                MODULE..=MODULE_CODE => {
+                    bail!("modules using the module linking proposal are not supported")
+                }
+                _ => {}
+            }
+        }
+        if input.bodies.len() != input.func_types.len() - input.imported(FUNC) {
+            bail!("function and code section have inconsistent lengths");
+        }
+        Ok(input)
+    }
+
+    fn section(&self, id: u8) -> Option<&Section<'a>> {
+        self.sections.iter().find(|s| s.id == id)
+    }
+
+    /// Returns the number of imported items of `kind`.
+    fn imported(&self, kind: u8) -> usize {
+        self.imports.iter().filter(|i| i.kind == kind).count()
+    }
+
+    /// Returns the number of defined items of `kind`.
+    fn defined(&self, kind: u8) -> u32 {
+        (match kind {
+            FUNC => self.bodies.len(),
+            TABLE_KIND => self.tables.len(),
+            MEMORY_KIND => self.memories.len(),
+            GLOBAL_KIND => self.globals.len(),
+            _ => self.event_types.len() - self.imported(EVENT_KIND),
+        }) as u32
+    }
+
+    /// Returns the import of the item `index` of `kind`, or `None` if the
+    /// item is defined by this module.
+    fn import(&self, kind: u8, index: u32) -> Option<usize> {
+        self.imports
+            .iter()
+            .position(|i| i.kind == kind && i.index == index)
+    }
+}
+
+/// What an item of a module refers to in the merged module.
+enum Target {
+    /// The item `.0` of its kind defined by the module itself.
+    Defined(u32),
+    /// The item `.1` of its kind defined by the module `.0`.
+    Sibling(usize, u32),
+    /// The import `.0` of the merged module.
+    External(usize),
+}
+
+/// An import of the merged module.
+struct External<'a> {
+    module: &'a str,
+    field: &'a str,
+    kind: u8,
+    /// The encoded type, with type indices unified.
+    ty: Vec<u8>,
+    /// The index of the import among the items of its kind.
+    index: u32,
+}
+
+struct Merger<'a, 'b> {
+    inputs: &'b [Input<'a>],
+    /// The unified index of each type of each module.
+    type_maps: &'b [Vec<u32>],
+    externals: Vec<External<'a>>,
+    external_indices: HashMap<(&'a str, &'a str, u8, Vec<u8>), usize>,
+    /// The number of imported items of each kind.
+    imported: [u32; 5],
+}
+
+impl<'a, 'b> Merger<'a, 'b> {
+    /// Resolves the import `import` of the module `m`, following imports
+    /// from siblings until an item they define, or an import from outside.
+    fn resolve(&mut self, m: usize, import: usize) -> Result<Target> {
+        let inputs = self.inputs;
+        let (first, field) = (m, inputs[m].imports[import].field);
+        let (mut m, mut import) = (m, &inputs[m].imports[import]);
+        let kind = import.kind;
+        let from = import.module;
+        let limit = inputs.iter().map(|i| i.imports.len()).sum::<usize>();
+        for _ in 0..=limit {
+            let s = match inputs.iter().position(|i| i.name == import.module) {
+                Some(s) => s,
+                None => return Ok(Target::External(self.external(m, import))),
+            };
+            let sibling = &inputs[s];
+            let index = sibling
+                .exports
+                .iter()
+                .find(|(field, k, _)| *field == import.field && *k == kind)
+                .map(|(_, _, index)| *index)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "module `{}` has no {} export named `{}`",
+                        sibling.name,
+                        KINDS[kind as usize],
+                        import.field
+                    )
+                })?;
+            if !self.same_type(m, import.index, s, index, kind) {
+                bail!(
+                    "module `{}` imports {} `{}` from `{}` with the wrong type",
+                    inputs[m].name,
+                    KINDS[kind as usize],
+                    import.field,
+                    sibling.name
+                );
+            }
+            match sibling.import(kind, index) {
+                Some(i) => {
+                    m = s;
+                    import = &sibling.imports[i];
+                }
+                None => {
+                    let imported = sibling.imports.iter().filter(|i| i.kind == kind).count();
+                    return Ok(Target::Sibling(s, index - imported as u32));
+                }
+            }
+        }
+        bail!(
+            "module `{}` imports {} `{}` from `{}` in a cycle",
+            inputs[first].name,
+            KINDS[kind as usize],
+            field,
+            from
+        )
+    }
+
+    /// Returns whether the item `a` of the module `m` and the item `b` of
+    /// the module `n`, both of `kind`, have the same type.
+    fn same_type(&self, m: usize, a: u32, n: usize, b: u32, kind: u8) -> bool {
+        let (x, y) = (&self.inputs[m], &self.inputs[n]);
+        let ty =
+            |map: &[u32], types: &[u32], i: u32| types.get(i as usize).map(|t| map[*t as usize]);
+        match kind {
+            FUNC => {
+                ty(&self.type_maps[m], &x.func_types, a) == ty(&self.type_maps[n], &y.func_types, b)
+            }
+            EVENT_KIND => {
+                ty(&self.type_maps[m], &x.event_types, a)
+                    == ty(&self.type_maps[n], &y.event_types, b)
+            }
+            TABLE_KIND => x.table_types.get(a as usize) == y.table_types.get(b as usize),
+            GLOBAL_KIND => {
+                let (a, b) = (
+                    x.global_types.get(a as usize),
+                    y.global_types.get(b as usize),
+                );
+                match (a, b) {
+                    (Some(a), Some(b)) => {
+                        a.content_type == b.content_type && a.mutable == b.mutable
+                    }
+                    _ => false,
+                }
+            }
+            _ => true,
+        }
+    }
+
+    /// Returns the import of the merged module which the import `import` of
+    /// the module `m` becomes, adding it if it's new.
+    fn external(&mut self, m: usize, import: &Import<'a>) -> usize {
+        let ty = match import.kind {
+            FUNC => {
+                let mut ty = Vec::new();
+                let index = self.inputs[m].func_types[import.index as usize];
+                write_u32(&mut ty, self.type_maps[m][index as usize]);
+                ty
+            }
+            EVENT_KIND => {
+                let mut ty = vec![0x00];
+                let index = self.inputs[m].event_types[import.index as usize];
+                write_u32(&mut ty, self.type_maps[m][index as usize]);
+                ty
+            }
+            _ => import.ty.to_vec(),
+        };
+        let key = (import.module, import.field, import.kind, ty);
+        if let Some(e) = self.external_indices.get(&key) {
+            return *e;
+        }
+        let index = self.imported[import.kind as usize];
+        self.imported[import.kind as usize] += 1;
+        self.externals.push(External {
+            module: import.module,
+            field: import.field,
+            kind: import.kind,
+            ty: key.3.clone(),
+            index,
+        });
+        self.external_indices.insert(key, self.externals.len() - 1);
+        self.externals.len() - 1
+    }
+}
+
+/// Appends the section `id` to `output`, unless it's empty.
+fn encode_section(id: u8, contents: &[u8], output: &mut Vec<u8>) {
+    if contents == [0] {
+        return;
+    }
+    Section {
+        id,
+        contents,
+        offset: 0,
+    }
+    .encode(output);
+}
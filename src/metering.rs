@@ -0,0 +1,213 @@
+//! Injection of gas metering into a binary module.
+//!
+//! Embedders which run untrusted code, such as blockchains, need to bound how
+//! much work a module may do. This transform adds a mutable `i64` global
+//! holding the remaining fuel, and charges the cost of each basic block of
+//! every function when the block is entered. Once the fuel drops below zero
+//! the module either traps or calls an imported function.
+//!
+//! ```
+//! use wasm_tools::metering::Metering;
+//! use wasmparser::Operator;
+//!
+//! let wasm = wat::parse_str(r#"
+//!     (module
+//!       (func (export "main") (param i32) (result i32)
+//!         local.get 0
+//!         call 0))
+//! "#)?;
+//! let wasm = Metering::new()
+//!     .costs(|op| match op {
+//!         Operator::Call { .. } => 10,
+//!         _ => 1,
+//!     })
+//!     .apply(&wasm)?;
+//! let text = wasmprinter::print_bytes(&wasm)?;
+//! assert!(text.contains("(export \"fuel\" (global 0))"));
+//! assert!(text.contains("i64.const 12"));
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::cfg::Cfg;
+use crate::inject::Injector;
+use anyhow::Result;
+use std::fmt;
+use wasmparser::leb128::{write_i64, write_u32};
+use wasmparser::{FunctionBody, Operator};
+
+/// The configuration of gas metering, see the [module documentation](self).
+///
+/// The remaining fuel is held in a new mutable `i64` global which is exported
+/// so the embedder can set it before calling into the module and read it
+/// afterwards. It starts out as [`Metering::initial_fuel`].
+pub struct Metering {
+    costs: Box<dyn Fn(&Operator<'_>) -> u64>,
+    exhausted: Exhausted,
+    export: String,
+    initial_fuel: i64,
+}
+
+/// What to do when a module runs out of fuel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Exhausted {
+    /// Execute `unreachable`.
+    Trap,
+    /// Call the function `module`.`field` of type `[] -> []`, which is
+    /// imported into the module.
+    ///
+    /// The function may add fuel, by setting the exported global, and return
+    /// to continue execution. It's called again at the start of every basic
+    /// block for as long as the fuel remains negative.
+    Call { module: String, field: String },
+}
+
+impl Default for Metering {
+    fn default() -> Metering {
+        Metering {
+            costs: Box::new(|_| 1),
+            exhausted: Exhausted::Trap,
+            export: "fuel".to_string(),
+            initial_fuel: 0,
+        }
+    }
+}
+
+impl fmt::Debug for Metering {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metering")
+            .field("exhausted", &self.exhausted)
+            .field("export", &self.export)
+            .field("initial_fuel", &self.initial_fuel)
+            .finish()
+    }
+}
+
+impl Metering {
+    /// Creates a new metering configuration in which every operator costs 1,
+    /// the fuel global is exported as `fuel` and running out of fuel traps.
+    pub fn new() -> Metering {
+        Metering::default()
+    }
+
+    /// Sets the cost of each operator.
+    ///
+    /// The costs of the operators of a basic block are added up and charged
+    /// all at once when the block is entered, so an operator which traps is
+    /// charged in full along with the rest of its block.
+    pub fn costs(&mut self, costs: impl Fn(&Operator<'_>) -> u64 + 'static) -> &mut Metering {
+        self.costs = Box::new(costs);
+        self
+    }
+
+    /// Sets what to do when the module runs out of fuel.
+    pub fn exhausted(&mut self, exhausted: Exhausted) -> &mut Metering {
+        self.exhausted = exhausted;
+        self
+    }
+
+    /// Sets the name which the fuel global is exported as.
+    pub fn export(&mut self, name: &str) -> &mut Metering {
+        self.export = name.to_string();
+        self
+    }
+
+    /// Sets the initial value of the fuel global, which is 0 by default.
+    pub fn initial_fuel(&mut self, fuel: i64) -> &mut Metering {
+        self.initial_fuel = fuel;
+        self
+    }
+
+    /// Applies this metering to the module `wasm`, returning the new module.
+    ///
+    /// If running out of fuel calls a function, it's imported after all of
+    /// the module's existing imports, so every function defined by the module
+    /// is renumbered. Function and local names in the `name` section are
+    /// updated, but its other subsections are removed. All other custom
+    /// sections are copied verbatim, so any indices or offsets they contain
+    /// will be stale.
+    ///
+    /// Returns an error if the module already has an export with the name of
+    /// the fuel global. Modules using the module linking proposal aren't
+    /// supported.
+    pub fn apply(&self, wasm: &[u8]) -> Result<Vec<u8>> {
+        let mut injector = Injector::new(wasm)?;
+        let exhausted = match &self.exhausted {
+            Exhausted::Trap => None,
+            Exhausted::Call { module, field } => {
+                let ty = injector.func_type(&[], &[]);
+                Some(injector.import_func(module, field, ty))
+            }
+        };
+        let mut global = vec![0x7e, 0x01, 0x42];
+        write_i64(&mut global, self.initial_fuel);
+        global.push(0x0b);
+        let global = injector.global(&global);
+        injector.export_global(&self.export, global);
+
+        injector.finish(|map, _, body, output| {
+            let costs = self.block_costs(body)?;
+            let mut costs = costs.iter().peekable();
+            let mut reader = body.get_operators_reader()?;
+            while !reader.eof() {
+                let (op, start) = reader.read_with_offset()?;
+                while let Some((_, cost)) = costs.next_if(|(offset, _)| *offset <= start) {
+                    charge(output, global, *cost, exhausted);
+                }
+                map.operator(op, &wasm[start..reader.original_position()], output);
+            }
+            Ok(())
+        })
+    }
+
+    /// Returns the offset and total cost of each basic block of `body` which
+    /// costs anything, in order.
+    fn block_costs(&self, body: &FunctionBody<'_>) -> Result<Vec<(usize, u64)>> {
+        let cfg = Cfg::new(body)?;
+        let mut costs = cfg
+            .blocks()
+            .iter()
+            .map(|block| (block.start, block.end, 0u64))
+            .collect::<Vec<_>>();
+        let mut reader = body.get_operators_reader()?;
+        let mut block = 0;
+        while !reader.eof() {
+            let (op, start) = reader.read_with_offset()?;
+            while block < costs.len() && costs[block].1 <= start {
+                block += 1;
+            }
+            if let Some((block_start, _, cost)) = costs.get_mut(block) {
+                if *block_start <= start {
+                    *cost = cost.saturating_add((self.costs)(&op));
+                }
+            }
+        }
+        Ok(costs
+            .into_iter()
+            .filter(|(_, _, cost)| *cost > 0)
+            .map(|(start, _, cost)| (start, cost))
+            .collect())
+    }
+}
+
+/// Appends code subtracting `cost` from the fuel in `global`, and then either
+/// trapping or calling `exhausted` if it's negative, to `output`.
+fn charge(output: &mut Vec<u8>, global: u32, cost: u64, exhausted: Option<u32>) {
+    output.push(0x23);
+    write_u32(output, global);
+    output.push(0x42);
+    write_i64(output, cost.min(i64::MAX as u64) as i64);
+    output.push(0x7d);
+    output.push(0x24);
+    write_u32(output, global);
+    output.push(0x23);
+    write_u32(output, global);
+    output.extend_from_slice(&[0x42, 0x00, 0x53, 0x04, 0x40]);
+    match exhausted {
+        Some(func) => {
+            output.push(0x10);
+            write_u32(output, func);
+        }
+        None => output.push(0x00),
+    }
+    output.push(0x0b);
+}
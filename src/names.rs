@@ -0,0 +1,339 @@
+//! Reading and editing of the `name` custom section of a binary module.
+//!
+//! The name section gives debug names to a module, its functions, their
+//! locals and its globals, which show up in stack traces and disassembly.
+//! Linkers and optimizers often leave it incomplete, so after linking names
+//! are restored from elsewhere, such as the symbol map a toolchain emits
+//! alongside the module. Only the name section is rewritten, all other
+//! sections are copied verbatim.
+//!
+//! ```
+//! use wasm_tools::names::Names;
+//!
+//! let wasm = wat::parse_str("(module (func) (func $helper) (func))")?;
+//! let mut names = Names::read(&wasm)?;
+//! assert_eq!(names.function(1), Some("helper"));
+//!
+//! names.set_function(0, "main");
+//! // Functions which already have a name keep it.
+//! assert_eq!(names.add_symbol_map("1:other\n2:exit\n")?, 1);
+//! let wasm = names.apply(&wasm)?;
+//!
+//! let names = Names::read(&wasm)?;
+//! let functions = names.functions().collect::<Vec<_>>();
+//! assert_eq!(functions, [(0, "main"), (1, "helper"), (2, "exit")]);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::sections::{sections, write_str, Section};
+use anyhow::{bail, Context, Result};
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::path::Path;
+use wasmparser::leb128::write_u32;
+use wasmparser::BinaryReader;
+
+/// The name of the custom section holding names.
+pub const SECTION_NAME: &str = "name";
+
+// The ids of the subsections of the name section which are understood, from
+// the extended name section proposal.
+const MODULE: u8 = 0;
+const FUNCTION: u8 = 1;
+const LOCAL: u8 = 2;
+const GLOBAL: u8 = 7;
+
+/// The contents of the name section of a module.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Names {
+    module: Option<String>,
+    functions: BTreeMap<u32, String>,
+    locals: BTreeMap<u32, BTreeMap<u32, String>>,
+    globals: BTreeMap<u32, String>,
+    // Subsections which aren't understood, such as label or type names,
+    // which are written back as-is.
+    other: Vec<(u8, Vec<u8>)>,
+}
+
+impl Names {
+    /// Creates an empty set of names.
+    pub fn new() -> Names {
+        Names::default()
+    }
+
+    /// Reads the names of the module `wasm` from its name section, if it has
+    /// one.
+    ///
+    /// Returns an error if the name section is malformed. Names of modules
+    /// nested within `wasm`, from the module linking proposal, aren't read.
+    pub fn read(wasm: &[u8]) -> Result<Names> {
+        for section in sections(wasm)? {
+            if section.custom_name()? == Some(SECTION_NAME) {
+                return Names::parse(&section).context("invalid name section");
+            }
+        }
+        Ok(Names::new())
+    }
+
+    fn parse(section: &Section<'_>) -> Result<Names> {
+        let mut reader = BinaryReader::new(section.contents);
+        reader.read_string()?;
+        let mut names = Names::new();
+        while !reader.eof() {
+            let id = reader.read_u8()? as u8;
+            let len = reader.read_var_u32()?;
+            let contents = reader.read_bytes(len as usize)?;
+            let mut subsection = BinaryReader::new(contents);
+            match id {
+                MODULE => names.module = Some(subsection.read_string()?.to_string()),
+                FUNCTION => names.functions = read_name_map(&mut subsection)?,
+                LOCAL => {
+                    for _ in 0..subsection.read_var_u32()? {
+                        let func = subsection.read_var_u32()?;
+                        let locals = read_name_map(&mut subsection)?;
+                        names.locals.insert(func, locals);
+                    }
+                }
+                GLOBAL => names.globals = read_name_map(&mut subsection)?,
+                _ => {
+                    names.other.push((id, contents.to_vec()));
+                    continue;
+                }
+            }
+            if !subsection.eof() {
+                bail!("unexpected data at the end of subsection {}", id);
+            }
+        }
+        Ok(names)
+    }
+
+    /// Returns the name of the module.
+    pub fn module(&self) -> Option<&str> {
+        self.module.as_deref()
+    }
+
+    /// Names the module `name`.
+    pub fn set_module(&mut self, name: &str) -> &mut Names {
+        self.module = Some(name.to_string());
+        self
+    }
+
+    /// Returns the name of the function `index`.
+    pub fn function(&self, index: u32) -> Option<&str> {
+        self.functions.get(&index).map(|s| s.as_str())
+    }
+
+    /// Returns the indices and names of all named functions, in order.
+    pub fn functions(&self) -> impl Iterator<Item = (u32, &str)> {
+        self.functions.iter().map(|(i, name)| (*i, name.as_str()))
+    }
+
+    /// Names the function `index` `name`, replacing any previous name.
+    pub fn set_function(&mut self, index: u32, name: &str) -> &mut Names {
+        self.functions.insert(index, name.to_string());
+        self
+    }
+
+    /// Returns the name of the local `index` of the function `func`.
+    pub fn local(&self, func: u32, index: u32) -> Option<&str> {
+        self.locals.get(&func)?.get(&index).map(|s| s.as_str())
+    }
+
+    /// Returns the indices and names of all named locals of the function
+    /// `func`, in order.
+    pub fn locals(&self, func: u32) -> impl Iterator<Item = (u32, &str)> {
+        let locals = self.locals.get(&func).into_iter().flatten();
+        locals.map(|(i, name)| (*i, name.as_str()))
+    }
+
+    /// Names the local `index` of the function `func` `name`, replacing any
+    /// previous name.
+    pub fn set_local(&mut self, func: u32, index: u32, name: &str) -> &mut Names {
+        let locals = self.locals.entry(func).or_default();
+        locals.insert(index, name.to_string());
+        self
+    }
+
+    /// Returns the name of the global `index`.
+    pub fn global(&self, index: u32) -> Option<&str> {
+        self.globals.get(&index).map(|s| s.as_str())
+    }
+
+    /// Returns the indices and names of all named globals, in order.
+    pub fn globals(&self) -> impl Iterator<Item = (u32, &str)> {
+        self.globals.iter().map(|(i, name)| (*i, name.as_str()))
+    }
+
+    /// Names the global `index` `name`, replacing any previous name.
+    pub fn set_global(&mut self, index: u32, name: &str) -> &mut Names {
+        self.globals.insert(index, name.to_string());
+        self
+    }
+
+    /// Names the functions of the symbol map `map` which don't have a name
+    /// yet, returning how many were named.
+    ///
+    /// See [`parse_symbol_map`] for the formats of symbol maps.
+    pub fn add_symbol_map(&mut self, map: &str) -> Result<usize> {
+        let mut added = 0;
+        for (index, name) in parse_symbol_map(map)? {
+            if let Entry::Vacant(entry) = self.functions.entry(index) {
+                entry.insert(name);
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+
+    /// Replaces the name section of the module `wasm` with these names,
+    /// returning the new module.
+    ///
+    /// The new name section takes the place of the old one, or goes at the
+    /// end of the module if it had none. When there are no names at all the
+    /// module is left without a name section.
+    pub fn apply(&self, wasm: &[u8]) -> Result<Vec<u8>> {
+        let sections = sections(wasm)?;
+        let contents = self.encode();
+        let mut output = wasm[..8].to_vec();
+        let mut written = contents.is_none();
+        for section in sections {
+            if section.custom_name()? != Some(SECTION_NAME) {
+                section.encode(&mut output);
+                continue;
+            }
+            if let (Some(contents), false) = (&contents, written) {
+                custom_section(contents).encode(&mut output);
+                written = true;
+            }
+        }
+        if let (Some(contents), false) = (&contents, written) {
+            custom_section(contents).encode(&mut output);
+        }
+        Ok(output)
+    }
+
+    /// Returns the contents of the name section holding these names, or
+    /// `None` if there are no names.
+    fn encode(&self) -> Option<Vec<u8>> {
+        let mut subsections = self.other.clone();
+        if let Some(name) = &self.module {
+            let mut contents = Vec::new();
+            write_str(&mut contents, name);
+            subsections.push((MODULE, contents));
+        }
+        if !self.functions.is_empty() {
+            subsections.push((FUNCTION, encode_name_map(&self.functions)));
+        }
+        if !self.locals.is_empty() {
+            let mut contents = Vec::new();
+            write_u32(&mut contents, self.locals.len() as u32);
+            for (func, locals) in self.locals.iter() {
+                write_u32(&mut contents, *func);
+                contents.extend_from_slice(&encode_name_map(locals));
+            }
+            subsections.push((LOCAL, contents));
+        }
+        if !self.globals.is_empty() {
+            subsections.push((GLOBAL, encode_name_map(&self.globals)));
+        }
+        if subsections.is_empty() {
+            return None;
+        }
+
+        // Subsections must appear in order of their ids.
+        subsections.sort_by_key(|(id, _)| *id);
+        let mut contents = Vec::new();
+        write_str(&mut contents, SECTION_NAME);
+        for (id, subsection) in subsections {
+            contents.push(id);
+            write_u32(&mut contents, subsection.len() as u32);
+            contents.extend_from_slice(&subsection);
+        }
+        Some(contents)
+    }
+}
+
+/// Parses the symbol map `map`, which names functions of a module, into a map
+/// from function indices to names.
+///
+/// Symbol maps are either lines of a function index and its name separated
+/// by a colon, such as `12:main`, which is the format of the `.symbols` files
+/// emitted by Emscripten and Binaryen, or a JSON object mapping indices to
+/// names, such as `{"12": "main"}`, which requires the `json` feature. Empty
+/// lines are ignored.
+pub fn parse_symbol_map(map: &str) -> Result<BTreeMap<u32, String>> {
+    if map.trim_start().starts_with('{') {
+        return parse_json_symbol_map(map);
+    }
+
+    let mut symbols = BTreeMap::new();
+    for (i, line) in map.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = line.find(':').and_then(|colon| {
+            let index = line[..colon].trim().parse::<u32>().ok()?;
+            Some((index, &line[colon + 1..]))
+        });
+        match entry {
+            Some((index, name)) => symbols.insert(index, name.to_string()),
+            None => bail!("invalid symbol map entry on line {}: `{}`", i + 1, line),
+        };
+    }
+    Ok(symbols)
+}
+
+#[cfg(feature = "json")]
+fn parse_json_symbol_map(map: &str) -> Result<BTreeMap<u32, String>> {
+    let json: BTreeMap<String, String> =
+        serde_json::from_str(map).context("invalid JSON symbol map")?;
+    let mut symbols = BTreeMap::new();
+    for (index, name) in json {
+        match index.parse::<u32>() {
+            Ok(index) => symbols.insert(index, name),
+            Err(_) => bail!("invalid function index `{}` in symbol map", index),
+        };
+    }
+    Ok(symbols)
+}
+
+#[cfg(not(feature = "json"))]
+fn parse_json_symbol_map(_map: &str) -> Result<BTreeMap<u32, String>> {
+    bail!("JSON symbol maps require the `json` feature")
+}
+
+/// Reads the symbol map at `path`, see [`parse_symbol_map`].
+pub fn read_symbol_map(path: impl AsRef<Path>) -> Result<BTreeMap<u32, String>> {
+    let path = path.as_ref();
+    let map =
+        std::fs::read_to_string(path).context(format!("failed to read `{}`", path.display()))?;
+    parse_symbol_map(&map).context(format!("failed to parse `{}`", path.display()))
+}
+
+fn custom_section(contents: &[u8]) -> Section<'_> {
+    Section {
+        id: 0,
+        contents,
+        offset: 0,
+    }
+}
+
+fn read_name_map(reader: &mut BinaryReader<'_>) -> Result<BTreeMap<u32, String>> {
+    let mut map = BTreeMap::new();
+    for _ in 0..reader.read_var_u32()? {
+        let index = reader.read_var_u32()?;
+        let name = reader.read_string()?;
+        map.insert(index, name.to_string());
+    }
+    Ok(map)
+}
+
+fn encode_name_map(map: &BTreeMap<u32, String>) -> Vec<u8> {
+    let mut contents = Vec::new();
+    write_u32(&mut contents, map.len() as u32);
+    for (index, name) in map.iter() {
+        write_u32(&mut contents, *index);
+        write_str(&mut contents, name);
+    }
+    contents
+}
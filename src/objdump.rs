@@ -0,0 +1,189 @@
+//! Printing a summary of the sections in a WebAssembly binary, similar to
+//! `wasm-objdump -h`.
+
+use crate::names::Names;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use wasmparser::{ImportSectionEntryType, Parser, Payload::*};
+
+/// Prints the offset, size, and item count of every section in `input`,
+/// including those of nested modules, to stdout.
+pub fn objdump(input: &[u8]) -> Result<()> {
+    dump(input, None)
+}
+
+/// Same as [`objdump`], but also lists the function bodies of the code
+/// section of `input` with their names.
+///
+/// The names come from the name section of `input`, or from `symbols`, a map
+/// from function indices to names such as one read with
+/// [`read_symbol_map`](crate::names::read_symbol_map), if the name section
+/// doesn't name any functions.
+///
+/// If `demangle` is set then names which are Rust or C++ symbols are listed
+/// in their demangled form, like [`wasmprinter::Printer::demangle`].
+pub fn objdump_with_symbols(
+    input: &[u8],
+    symbols: &BTreeMap<u32, String>,
+    demangle: bool,
+) -> Result<()> {
+    let names = Names::read(input)?;
+    let mut functions = names
+        .functions()
+        .map(|(i, name)| (i, name.to_string()))
+        .collect::<BTreeMap<_, _>>();
+    if functions.is_empty() {
+        functions = symbols.clone();
+    }
+    if demangle {
+        for name in functions.values_mut() {
+            if let Some(demangled) = wasmprinter::demangle(name) {
+                *name = demangled;
+            }
+        }
+    }
+    dump(input, Some(functions))
+}
+
+fn dump(input: &[u8], functions: Option<BTreeMap<u32, String>>) -> Result<()> {
+    let mut printer = Printer {
+        functions,
+        ..Printer::default()
+    };
+    for payload in Parser::new(0).parse_all(input) {
+        match payload? {
+            Version { .. } => printer.start(),
+
+            TypeSection(s) => printer.section(s, "types"),
+            ImportSection(s) => {
+                if printer.module_code_counts.is_empty() {
+                    for import in s.clone() {
+                        if let ImportSectionEntryType::Function(_) = import?.ty {
+                            printer.func += 1;
+                        }
+                    }
+                }
+                printer.section(s, "imports")
+            }
+            AliasSection(s) => printer.section(s, "aliases"),
+            InstanceSection(s) => printer.section(s, "instances"),
+            FunctionSection(s) => printer.section(s, "functions"),
+            TableSection(s) => printer.section(s, "tables"),
+            MemorySection(s) => printer.section(s, "memories"),
+            EventSection(s) => printer.section(s, "events"),
+            GlobalSection(s) => printer.section(s, "globals"),
+            ExportSection(s) => printer.section(s, "exports"),
+            StartSection { range, .. } => printer.section_raw(range, 1, "start"),
+            ElementSection(s) => printer.section(s, "elements"),
+            DataCountSection { range, .. } => printer.section_raw(range, 1, "data count"),
+            DataSection(s) => printer.section(s, "data"),
+
+            CodeSectionStart { range, count, .. } => printer.section_raw(range, count, "code"),
+
+            ModuleSectionStart { range, count, .. } => {
+                printer.section_raw(range, count, "modules");
+                printer.module_code_counts.push((0, count));
+            }
+
+            CustomSection {
+                name,
+                data_offset,
+                data,
+            } => printer.section_raw(
+                wasmparser::Range {
+                    start: data_offset,
+                    end: data_offset + data.len(),
+                },
+                1,
+                &format!("custom {:?}", name),
+            ),
+
+            CodeSectionEntry(body) => printer.function(body.range()),
+            ModuleSectionEntry { .. } => {}
+            UnknownSection { .. } => {}
+
+            End => printer.end(),
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Default)]
+struct Printer {
+    module_code_counts: Vec<(u32, u32)>,
+    // The names of the functions of the outermost module, if its function
+    // bodies are listed, and the index of the next one.
+    functions: Option<BTreeMap<u32, String>>,
+    func: u32,
+}
+
+impl Printer {
+    fn start(&self) {
+        if let Some((a, b)) = self.module_code_counts.last() {
+            println!("{}------ start {}/{} ----------", self.header(), *a + 1, b);
+        }
+    }
+
+    fn end(&mut self) {
+        let header = self.header();
+        let pop = if let Some((a, b)) = self.module_code_counts.last_mut() {
+            println!("{}------   end {}/{} ----------", header, *a + 1, b);
+            *a += 1;
+            *a == *b
+        } else {
+            false
+        };
+        if pop {
+            self.module_code_counts.pop();
+        }
+    }
+
+    fn section<T>(&self, section: T, name: &str)
+    where
+        T: wasmparser::SectionWithLimitedItems + wasmparser::SectionReader,
+    {
+        self.section_raw(section.range(), section.get_count(), name)
+    }
+
+    fn section_raw(&self, range: wasmparser::Range, count: u32, name: &str) {
+        println!(
+            "{:40} | {:#10x} - {:#10x} | {:9} bytes | {} count",
+            format!("{}{}", self.header(), name),
+            range.start,
+            range.end,
+            range.end - range.start,
+            count,
+        );
+    }
+
+    fn function(&mut self, range: wasmparser::Range) {
+        if !self.module_code_counts.is_empty() {
+            return;
+        }
+        let functions = match &self.functions {
+            Some(functions) => functions,
+            None => return,
+        };
+        let name = match functions.get(&self.func) {
+            Some(name) => format!("  func[{}] <{}>", self.func, name),
+            None => format!("  func[{}]", self.func),
+        };
+        println!(
+            "{:40} | {:#10x} - {:#10x} | {:9} bytes |",
+            name,
+            range.start,
+            range.end,
+            range.end - range.start,
+        );
+        self.func += 1;
+    }
+
+    fn header(&self) -> String {
+        let mut s = String::new();
+        for _ in 0..self.module_code_counts.len() {
+            s.push_str("  ");
+        }
+        return s;
+    }
+}
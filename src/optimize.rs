@@ -0,0 +1,366 @@
+//! Peephole optimization of the code of a binary module.
+//!
+//! This is a small cleanup pipeline for modules which don't warrant a full
+//! optimizer such as Binaryen's `wasm-opt`. It rewrites short sequences of
+//! instructions in place:
+//!
+//! * integer arithmetic on constants, such as `i32.const 1 i32.const 2
+//!   i32.add`, is folded into a single constant,
+//! * a `local.set` directly followed by a `local.get` of the same local
+//!   becomes a `local.tee`,
+//! * `nop`s are removed, along with the unreachable instructions following an
+//!   `unreachable`, `br`, `br_table`, `return` or `throw` up to the end of
+//!   their block.
+//!
+//! The passes are guided by the opcode statistics of [`usage`]: a pass only
+//! runs if the module uses the instructions it rewrites, and a module with
+//! nothing to rewrite is returned unchanged.
+//!
+//! ```
+//! use wasm_tools::optimize::Optimize;
+//!
+//! let wasm = wat::parse_str(r#"
+//!     (module
+//!       (func (param i32) (result i32) (local i32)
+//!         nop
+//!         i32.const 2
+//!         i32.const 3
+//!         i32.mul
+//!         local.set 1
+//!         local.get 1
+//!         return
+//!         drop
+//!         i32.const 0))
+//! "#)?;
+//! let (wasm, stats) = Optimize::new().apply_with_stats(&wasm)?;
+//! assert_eq!(stats.folded, 1);
+//! assert_eq!(stats.tees, 1);
+//! assert_eq!(stats.nops, 1);
+//! assert_eq!(stats.dead, 2);
+//!
+//! let text = wasmprinter::print_bytes(&wasm)?;
+//! assert!(text.contains("i32.const 6\n    local.tee 1\n    return)"));
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::sections::{sections, Section};
+use crate::usage::usage;
+use anyhow::Result;
+use std::fmt;
+use wasmparser::leb128::{write_i64, write_u32};
+use wasmparser::{CodeSectionReader, FunctionBody, Operator};
+
+const CODE: u8 = 10;
+
+/// Which optimizations to run, see the [module documentation](self).
+#[derive(Clone, Debug)]
+pub struct Optimize {
+    fold_constants: bool,
+    tee_locals: bool,
+    remove_dead_code: bool,
+}
+
+/// How many times each optimization of [`Optimize`] rewrote the code of a
+/// module.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// The number of arithmetic instructions folded into a constant.
+    pub folded: u64,
+    /// The number of `local.set` and `local.get` pairs replaced by a
+    /// `local.tee`.
+    pub tees: u64,
+    /// The number of `nop`s removed.
+    pub nops: u64,
+    /// The number of unreachable instructions removed.
+    pub dead: u64,
+}
+
+impl Default for Optimize {
+    fn default() -> Optimize {
+        Optimize {
+            fold_constants: true,
+            tee_locals: true,
+            remove_dead_code: true,
+        }
+    }
+}
+
+impl Optimize {
+    /// Creates a new configuration which runs every optimization.
+    pub fn new() -> Optimize {
+        Optimize::default()
+    }
+
+    /// Sets whether integer arithmetic on constants is folded.
+    pub fn fold_constants(&mut self, fold: bool) -> &mut Optimize {
+        self.fold_constants = fold;
+        self
+    }
+
+    /// Sets whether `local.set` and `local.get` pairs become `local.tee`s.
+    pub fn tee_locals(&mut self, tee: bool) -> &mut Optimize {
+        self.tee_locals = tee;
+        self
+    }
+
+    /// Sets whether `nop`s and unreachable instructions are removed.
+    pub fn remove_dead_code(&mut self, remove: bool) -> &mut Optimize {
+        self.remove_dead_code = remove;
+        self
+    }
+
+    /// Optimizes the code of the module `wasm`, returning the new module.
+    ///
+    /// Only the code section is rewritten, all other sections are copied
+    /// verbatim, so offsets recorded in custom sections, such as DWARF, will
+    /// be stale. Functions of modules nested within `wasm`, from the module
+    /// linking proposal, are left untouched.
+    ///
+    /// Returns an error if `wasm` is malformed or fails to validate.
+    pub fn apply(&self, wasm: &[u8]) -> Result<Vec<u8>> {
+        Ok(self.apply_with_stats(wasm)?.0)
+    }
+
+    /// Like [`Optimize::apply`], but also returns how many times each
+    /// optimization rewrote the code.
+    pub fn apply_with_stats(&self, wasm: &[u8]) -> Result<(Vec<u8>, Stats)> {
+        let passes = self.passes(wasm)?;
+        let mut stats = Stats::default();
+        if !(passes.fold_constants || passes.tee_locals || passes.remove_dead_code) {
+            return Ok((wasm.to_vec(), stats));
+        }
+
+        let mut output = wasm[..8].to_vec();
+        for section in sections(wasm)? {
+            if section.id != CODE {
+                section.encode(&mut output);
+                continue;
+            }
+            let mut reader = CodeSectionReader::new(section.contents, section.offset)?;
+            let mut contents = Vec::new();
+            write_u32(&mut contents, reader.get_count());
+            for _ in 0..reader.get_count() {
+                let body = reader.read()?;
+                let mut encoded = Vec::new();
+                passes.body(wasm, &body, &mut encoded, &mut stats)?;
+                write_u32(&mut contents, encoded.len() as u32);
+                contents.extend_from_slice(&encoded);
+            }
+            Section {
+                id: CODE,
+                contents: &contents,
+                offset: 0,
+            }
+            .encode(&mut output);
+        }
+        Ok((output, stats))
+    }
+
+    /// Returns the optimizations which have something to rewrite in `wasm`,
+    /// according to the opcodes it uses.
+    fn passes(&self, wasm: &[u8]) -> Result<Optimize> {
+        let usage = usage(wasm)?;
+        let uses = |names: &[&str]| names.iter().any(|n| usage.opcodes.contains_key(n));
+        Ok(Optimize {
+            fold_constants: self.fold_constants && uses(&["I32Const", "I64Const"]),
+            tee_locals: self.tee_locals && uses(&["LocalSet"]) && uses(&["LocalGet"]),
+            remove_dead_code: self.remove_dead_code
+                && uses(&[
+                    "Nop",
+                    "Unreachable",
+                    "Br",
+                    "BrTable",
+                    "Return",
+                    "ReturnCall",
+                    "ReturnCallIndirect",
+                    "Throw",
+                    "Rethrow",
+                ]),
+        })
+    }
+
+    /// Writes `body` to `output` with its instructions optimized, adding the
+    /// rewrites to `stats`.
+    fn body(
+        &self,
+        wasm: &[u8],
+        body: &FunctionBody<'_>,
+        output: &mut Vec<u8>,
+        stats: &mut Stats,
+    ) -> Result<()> {
+        let mut locals = body.get_locals_reader()?;
+        for _ in 0..locals.get_count() {
+            locals.read()?;
+        }
+        let mut ops = body.get_operators_reader()?;
+        let code_start = ops.original_position();
+
+        let mut items = Vec::new();
+        let before = stats.clone();
+        // The nesting depth within unreachable code being removed, if any.
+        let mut dead = None;
+        while !ops.eof() {
+            let (op, start) = ops.read_with_offset()?;
+            let bytes = &wasm[start..ops.original_position()];
+            if let Some(depth) = dead {
+                dead = match op {
+                    Operator::Block { .. }
+                    | Operator::Loop { .. }
+                    | Operator::If { .. }
+                    | Operator::Try { .. } => Some(depth + 1),
+                    Operator::End if depth > 0 => Some(depth - 1),
+                    Operator::End | Operator::Else | Operator::Catch { .. } | Operator::Unwind
+                        if depth == 0 =>
+                    {
+                        None
+                    }
+                    _ => Some(depth),
+                };
+                if dead.is_some() {
+                    stats.dead += 1;
+                    continue;
+                }
+            }
+            match op {
+                Operator::Nop if self.remove_dead_code => stats.nops += 1,
+                Operator::Unreachable
+                | Operator::Br { .. }
+                | Operator::BrTable { .. }
+                | Operator::Return
+                | Operator::ReturnCall { .. }
+                | Operator::ReturnCallIndirect { .. }
+                | Operator::Throw { .. }
+                | Operator::Rethrow { .. }
+                    if self.remove_dead_code =>
+                {
+                    items.push(Item::Raw(bytes));
+                    dead = Some(0);
+                }
+                Operator::I32Const { value } => items.push(Item::I32(value)),
+                Operator::I64Const { value } => items.push(Item::I64(value)),
+                Operator::LocalSet { local_index } => items.push(Item::Set(local_index, bytes)),
+                Operator::LocalGet { local_index } if self.tee_locals => {
+                    if let Some(Item::Set(index, _)) = items.last() {
+                        if *index == local_index {
+                            items.pop();
+                            items.push(Item::Tee(local_index));
+                            stats.tees += 1;
+                            continue;
+                        }
+                    }
+                    items.push(Item::Raw(bytes));
+                }
+                _ => {
+                    if self.fold_constants {
+                        if let Some(folded) = fold(&op, &items) {
+                            items.truncate(items.len() - 2);
+                            items.push(folded);
+                            stats.folded += 1;
+                            continue;
+                        }
+                    }
+                    items.push(Item::Raw(bytes));
+                }
+            }
+        }
+
+        let range = body.range();
+        if *stats == before {
+            output.extend_from_slice(&wasm[range.start..range.end]);
+            return Ok(());
+        }
+        output.extend_from_slice(&wasm[range.start..code_start]);
+        for item in items {
+            item.encode(output);
+        }
+        Ok(())
+    }
+}
+
+/// An instruction of an optimized function body.
+enum Item<'a> {
+    /// An instruction which is copied as-is.
+    Raw(&'a [u8]),
+    I32(i32),
+    I64(i64),
+    /// A `local.set`, which is copied as-is unless it becomes a `local.tee`.
+    Set(u32, &'a [u8]),
+    Tee(u32),
+}
+
+impl Item<'_> {
+    fn encode(&self, output: &mut Vec<u8>) {
+        match *self {
+            Item::Raw(bytes) | Item::Set(_, bytes) => output.extend_from_slice(bytes),
+            Item::I32(value) => {
+                output.push(0x41);
+                write_i64(output, value.into());
+            }
+            Item::I64(value) => {
+                output.push(0x42);
+                write_i64(output, value);
+            }
+            Item::Tee(index) => {
+                output.push(0x22);
+                write_u32(output, index);
+            }
+        }
+    }
+}
+
+/// Returns the constant which `op` computes if it's integer arithmetic on the
+/// two constants at the end of `items`.
+///
+/// Division and remainder aren't folded, since they may trap.
+fn fold(op: &Operator<'_>, items: &[Item<'_>]) -> Option<Item<'static>> {
+    let (a, b) = match items {
+        [.., a, b] => (a, b),
+        _ => return None,
+    };
+    if let (Item::I32(a), Item::I32(b)) = (a, b) {
+        let (a, b) = (*a, *b);
+        let value = match op {
+            Operator::I32Add => a.wrapping_add(b),
+            Operator::I32Sub => a.wrapping_sub(b),
+            Operator::I32Mul => a.wrapping_mul(b),
+            Operator::I32And => a & b,
+            Operator::I32Or => a | b,
+            Operator::I32Xor => a ^ b,
+            Operator::I32Shl => a.wrapping_shl(b as u32),
+            Operator::I32ShrS => a.wrapping_shr(b as u32),
+            Operator::I32ShrU => (a as u32).wrapping_shr(b as u32) as i32,
+            Operator::I32Rotl => a.rotate_left(b as u32),
+            Operator::I32Rotr => a.rotate_right(b as u32),
+            _ => return None,
+        };
+        return Some(Item::I32(value));
+    }
+    if let (Item::I64(a), Item::I64(b)) = (a, b) {
+        let (a, b) = (*a, *b);
+        let value = match op {
+            Operator::I64Add => a.wrapping_add(b),
+            Operator::I64Sub => a.wrapping_sub(b),
+            Operator::I64Mul => a.wrapping_mul(b),
+            Operator::I64And => a & b,
+            Operator::I64Or => a | b,
+            Operator::I64Xor => a ^ b,
+            Operator::I64Shl => a.wrapping_shl(b as u32),
+            Operator::I64ShrS => a.wrapping_shr(b as u32),
+            Operator::I64ShrU => (a as u64).wrapping_shr(b as u32) as i64,
+            Operator::I64Rotl => a.rotate_left(b as u32),
+            Operator::I64Rotr => a.rotate_right(b as u32),
+            _ => return None,
+        };
+        return Some(Item::I64(value));
+    }
+    None
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "constants folded: {}", self.folded)?;
+        writeln!(f, "locals teed: {}", self.tees)?;
+        writeln!(f, "nops removed: {}", self.nops)?;
+        writeln!(f, "unreachable instructions removed: {}", self.dead)
+    }
+}
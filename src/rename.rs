@@ -0,0 +1,170 @@
+//! Renaming of the imports and exports of a binary module.
+//!
+//! Linkers and shims frequently need to retarget a module's imports, for
+//! example mapping `env.fd_write` to `wasi_snapshot_preview1.fd_write`, or to
+//! rename its exports. Only the import and export sections are rewritten, all
+//! other sections are copied verbatim.
+//!
+//! ```
+//! use wasm_tools::rename::Rename;
+//!
+//! let wasm = wat::parse_str(r#"
+//!     (module
+//!       (import "env" "fd_write" (func (param i32 i32 i32 i32) (result i32)))
+//!       (func (export "main")))
+//! "#)?;
+//! let wasm = Rename::new()
+//!     .import_module("env", "wasi_snapshot_preview1")
+//!     .export("main", "_start")
+//!     .apply(&wasm)?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::sections::{sections, write_str, Section};
+use anyhow::{bail, Result};
+use std::collections::{HashMap, HashSet};
+use wasmparser::leb128::write_u32;
+use wasmparser::{BinaryReader, ExportSectionReader, ImportSectionReader};
+
+const IMPORT: u8 = 2;
+const EXPORT: u8 = 7;
+
+/// A set of renamings to apply to the imports and exports of a module.
+#[derive(Clone, Debug, Default)]
+pub struct Rename {
+    imports: HashMap<(String, String), (String, String)>,
+    import_modules: HashMap<String, String>,
+    exports: HashMap<String, String>,
+}
+
+impl Rename {
+    /// Creates a new set of renamings which doesn't change anything.
+    pub fn new() -> Rename {
+        Rename::default()
+    }
+
+    /// Renames the import `module`.`field` to `new_module`.`new_field`.
+    ///
+    /// This takes precedence over renamings of the whole module configured
+    /// with [`Rename::import_module`].
+    pub fn import(
+        &mut self,
+        module: &str,
+        field: &str,
+        new_module: &str,
+        new_field: &str,
+    ) -> &mut Rename {
+        self.imports.insert(
+            (module.to_string(), field.to_string()),
+            (new_module.to_string(), new_field.to_string()),
+        );
+        self
+    }
+
+    /// Retargets every import from `module` to import from `new_module`
+    /// instead, keeping their field names.
+    pub fn import_module(&mut self, module: &str, new_module: &str) -> &mut Rename {
+        self.import_modules
+            .insert(module.to_string(), new_module.to_string());
+        self
+    }
+
+    /// Renames the export `name` to `new_name`.
+    pub fn export(&mut self, name: &str, new_name: &str) -> &mut Rename {
+        self.exports.insert(name.to_string(), new_name.to_string());
+        self
+    }
+
+    /// Applies these renamings to the module `wasm`, returning the new
+    /// module.
+    ///
+    /// Returns an error if renaming exports would leave the module with two
+    /// exports of the same name. Imports and exports of modules nested within
+    /// `wasm`, from the module linking proposal, are left untouched.
+    pub fn apply(&self, wasm: &[u8]) -> Result<Vec<u8>> {
+        let mut output = wasm[..8].to_vec();
+        for section in sections(wasm)? {
+            let contents = match section.id {
+                IMPORT => self.imports(&section)?,
+                EXPORT => self.exports(&section)?,
+                _ => {
+                    section.encode(&mut output);
+                    continue;
+                }
+            };
+            Section {
+                id: section.id,
+                contents: &contents,
+                offset: 0,
+            }
+            .encode(&mut output);
+        }
+        Ok(output)
+    }
+
+    fn imports(&self, section: &Section<'_>) -> Result<Vec<u8>> {
+        let mut reader = ImportSectionReader::new(section.contents, 0)?;
+        let mut contents = Vec::new();
+        write_u32(&mut contents, reader.get_count());
+        for _ in 0..reader.get_count() {
+            let start = reader.original_position();
+            let import = reader.read()?;
+            let entry = &section.contents[start..reader.original_position()];
+
+            // Skip over the names to find the encoding of the import's type,
+            // which is copied as-is.
+            let mut entry_reader = BinaryReader::new(entry);
+            entry_reader.read_string()?;
+            entry_reader.read_string()?;
+            if import.field.is_none() {
+                entry_reader.read_u8()?;
+            }
+            let ty = &entry[entry_reader.current_position()..];
+
+            let renamed = import.field.and_then(|field| {
+                self.imports
+                    .get(&(import.module.to_string(), field.to_string()))
+            });
+            let (module, field) = match renamed {
+                Some((module, field)) => (module.as_str(), Some(field.as_str())),
+                None => match self.import_modules.get(import.module) {
+                    Some(module) => (module.as_str(), import.field),
+                    None => (import.module, import.field),
+                },
+            };
+            write_str(&mut contents, module);
+            match field {
+                Some(field) => write_str(&mut contents, field),
+                None => contents.extend_from_slice(&[0x00, 0xff]),
+            }
+            contents.extend_from_slice(ty);
+        }
+        Ok(contents)
+    }
+
+    fn exports(&self, section: &Section<'_>) -> Result<Vec<u8>> {
+        let mut reader = ExportSectionReader::new(section.contents, 0)?;
+        let mut contents = Vec::new();
+        let mut names = HashSet::new();
+        write_u32(&mut contents, reader.get_count());
+        for _ in 0..reader.get_count() {
+            let start = reader.original_position();
+            let export = reader.read()?;
+            let entry = &section.contents[start..reader.original_position()];
+            let mut entry_reader = BinaryReader::new(entry);
+            entry_reader.read_string()?;
+            let kind_and_index = &entry[entry_reader.current_position()..];
+
+            let name = match self.exports.get(export.field) {
+                Some(name) => name.as_str(),
+                None => export.field,
+            };
+            if !names.insert(name) {
+                bail!("duplicate export name `{}` after renaming", name);
+            }
+            write_str(&mut contents, name);
+            contents.extend_from_slice(kind_and_index);
+        }
+        Ok(contents)
+    }
+}
@@ -0,0 +1,229 @@
+//! Support for tests which round trip modules between the text and binary
+//! formats.
+//!
+//! Prototyping a new proposal generally involves adding it to `wast`,
+//! `wasmparser` and `wasmprinter` at the same time, and the easiest way to
+//! check that they all agree is to convert a module back and forth between the
+//! two formats. This module performs that conversion and produces readable
+//! diffs when it goes wrong. It's only available with the `test-support`
+//! feature:
+//!
+//! ```
+//! use wasm_tools::roundtrip::RoundTrip;
+//!
+//! RoundTrip::new()
+//!     .wat("(module (func (export \"f\") (result i32) i32.const 1))")
+//!     .unwrap();
+//! ```
+
+use anyhow::{bail, Context, Result};
+use wasmparser::{Validator, WasmFeatures};
+
+/// A configurable text→binary→text→binary round trip.
+#[derive(Clone, Debug)]
+pub struct RoundTrip {
+    features: WasmFeatures,
+    semantic: bool,
+}
+
+impl RoundTrip {
+    /// Creates a new round trip which enables every proposal supported by
+    /// `wasmparser` and requires the round trip to preserve exact bytes.
+    pub fn new() -> RoundTrip {
+        RoundTrip {
+            features: WasmFeatures {
+                reference_types: true,
+                multi_value: true,
+                bulk_memory: true,
+                module_linking: true,
+                simd: true,
+                threads: true,
+                tail_call: true,
+                multi_memory: true,
+                exceptions: true,
+                memory64: true,
+                wide_arithmetic: true,
+                function_references: true,
+                ..WasmFeatures::default()
+            },
+            semantic: false,
+        }
+    }
+
+    /// Configures the proposals which binaries are validated against at each
+    /// step of the round trip.
+    pub fn wasm_features(&mut self, features: WasmFeatures) -> &mut RoundTrip {
+        self.features = features;
+        self
+    }
+
+    /// Configures whether the round trip only needs to preserve the meaning
+    /// of the module rather than its exact bytes.
+    ///
+    /// When enabled binaries are compared by their text format as printed by
+    /// `wasmprinter`, so differences such as the width of LEB128 encodings
+    /// are ignored. Defaults to `false`.
+    ///
+    /// ```
+    /// use wasm_tools::roundtrip::RoundTrip;
+    ///
+    /// // A module whose type section length is encoded in five bytes.
+    /// let wasm = [
+    ///     0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00,
+    ///     0x01, 0x84, 0x80, 0x80, 0x80, 0x00, 0x01, 0x60, 0x00, 0x00,
+    /// ];
+    /// assert!(RoundTrip::new().wasm(&wasm).is_err());
+    /// assert!(RoundTrip::new().semantic(true).wasm(&wasm).is_ok());
+    /// ```
+    pub fn semantic(&mut self, semantic: bool) -> &mut RoundTrip {
+        self.semantic = semantic;
+        self
+    }
+
+    /// Round trips the text format module `wat`, returning its binary
+    /// encoding.
+    ///
+    /// The module is assembled, printed, and then assembled again, and both
+    /// binaries are required to match.
+    pub fn wat(&self, wat: &str) -> Result<Vec<u8>> {
+        let wasm = wat::parse_str(wat).context("failed to parse the original text")?;
+        self.wasm(&wasm)?;
+        Ok(wasm)
+    }
+
+    /// Round trips the binary module `wasm`.
+    ///
+    /// The module is printed and assembled again, and the result is required
+    /// to match `wasm`. The new binary is then printed once more, and that
+    /// text is required to match the first text.
+    pub fn wasm(&self, wasm: &[u8]) -> Result<()> {
+        self.validate(wasm)
+            .context("the original binary is not valid")?;
+        let text = wasmprinter::print_bytes(wasm).context("failed to print the original binary")?;
+        let wasm2 = wat::parse_str(&text).context("failed to parse the printed text")?;
+        self.validate(&wasm2)
+            .context("the round tripped binary is not valid")?;
+        let text2 =
+            wasmprinter::print_bytes(&wasm2).context("failed to print the round tripped binary")?;
+
+        if !self.semantic {
+            binary_compare(&wasm2, wasm)
+                .context("the round tripped binary differs from the original")?;
+        }
+        text_compare(&text2, &text).context("the round tripped text differs from the original")?;
+        Ok(())
+    }
+
+    fn validate(&self, wasm: &[u8]) -> Result<()> {
+        let mut validator = Validator::new();
+        validator.wasm_features(self.features);
+        validator.validate_all(wasm)?;
+        Ok(())
+    }
+}
+
+impl Default for RoundTrip {
+    fn default() -> RoundTrip {
+        RoundTrip::new()
+    }
+}
+
+/// Compare the `actual` and `expected` binaries, asserting that they are the
+/// same.
+///
+/// If they are not equal this attempts to produce as nice of an error message
+/// as it can to help narrow down on where the differences lie.
+pub fn binary_compare(actual: &[u8], expected: &[u8]) -> Result<()> {
+    if actual == expected {
+        return Ok(());
+    }
+
+    let difference = actual
+        .iter()
+        .enumerate()
+        .zip(expected)
+        .find(|((_, actual), expected)| actual != expected);
+    let pos = match difference {
+        Some(((pos, _), _)) => format!("at byte {} ({0:#x})", pos),
+        None if actual.len() < expected.len() => format!("by being too small"),
+        None => format!("by being too large"),
+    };
+    let mut msg = format!("error: actual wasm differs {} from expected wasm\n", pos);
+
+    if let Some(((pos, _), _)) = difference {
+        for i in pos.saturating_sub(2)..pos {
+            msg.push_str(&format!("  {:4} |   {:#04x}\n", i, actual[i]));
+        }
+        msg.push_str(&format!("  {:4} | - {:#04x}\n", pos, expected[pos]));
+        msg.push_str(&format!("       | + {:#04x}\n", actual[pos]));
+    }
+
+    if let Ok(actual) = wasmparser_dump::dump_wasm(actual) {
+        if let Ok(expected) = wasmparser_dump::dump_wasm(expected) {
+            let mut actual = actual.lines();
+            let mut expected = expected.lines();
+            let mut differences = 0;
+            let mut last_dots = false;
+            while differences < 5 {
+                let actual_state = match actual.next() {
+                    Some(s) => s,
+                    None => break,
+                };
+                let expected_state = match expected.next() {
+                    Some(s) => s,
+                    None => break,
+                };
+
+                if actual_state == expected_state {
+                    if differences > 0 && !last_dots {
+                        msg.push_str(" ...\n");
+                        last_dots = true;
+                    }
+                    continue;
+                }
+                last_dots = false;
+
+                if differences == 0 {
+                    msg.push_str("\n\n");
+                }
+                msg.push_str(&format!("- {}\n", expected_state));
+                msg.push_str(&format!("+ {}\n", actual_state));
+                differences += 1;
+            }
+        }
+    }
+
+    bail!("{}", msg)
+}
+
+/// Compare the `actual` and `expected` text, asserting that they are the same.
+///
+/// If they are not equal the error contains a line-based diff of the two.
+pub fn text_compare(actual: &str, expected: &str) -> Result<()> {
+    let mut bad = false;
+    let mut result = String::new();
+    for diff in diff::lines(expected, actual) {
+        match diff {
+            diff::Result::Left(s) => {
+                bad = true;
+                result.push_str("-");
+                result.push_str(s);
+            }
+            diff::Result::Right(s) => {
+                bad = true;
+                result.push_str("+");
+                result.push_str(s);
+            }
+            diff::Result::Both(s, _) => {
+                result.push_str(" ");
+                result.push_str(s);
+            }
+        }
+        result.push_str("\n");
+    }
+    if bad {
+        bail!("expected != actual\n\n{}", result);
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+//! Helpers for working with the top-level sections of a module without
+//! parsing their contents.
+
+use anyhow::Result;
+use wasmparser::{leb128, BinaryReader};
+
+/// A top-level section of a module.
+pub(crate) struct Section<'a> {
+    pub(crate) id: u8,
+    pub(crate) contents: &'a [u8],
+    /// The offset of `contents` within the module.
+    pub(crate) offset: usize,
+}
+
+impl<'a> Section<'a> {
+    /// Returns the name of this section if it is a custom section.
+    pub(crate) fn custom_name(&self) -> Result<Option<&'a str>> {
+        if self.id != 0 {
+            return Ok(None);
+        }
+        Ok(Some(BinaryReader::new(self.contents).read_string()?))
+    }
+
+    /// Appends this section, including its header, to `output`.
+    pub(crate) fn encode(&self, output: &mut Vec<u8>) {
+        output.push(self.id);
+        leb128::write_u32(output, self.contents.len() as u32);
+        output.extend_from_slice(self.contents);
+    }
+}
+
+/// The order which the known sections of a module must appear in.
+const ORDER: &[u8] = &[1, 2, 3, 4, 5, 13, 6, 7, 8, 9, 12, 10, 11];
+
+/// Returns the position of the section `id` in the order which sections must
+/// appear in, with unknown sections last.
+pub(crate) fn rank(id: u8) -> usize {
+    ORDER.iter().position(|i| *i == id).unwrap_or(ORDER.len())
+}
+
+/// Splits `wasm` into its top-level sections.
+pub(crate) fn sections(wasm: &[u8]) -> Result<Vec<Section<'_>>> {
+    let mut reader = BinaryReader::new(wasm);
+    reader.read_bytes(8)?;
+    let mut ret = Vec::new();
+    while !reader.eof() {
+        let id = reader.read_u8()? as u8;
+        let len = reader.read_var_u32()?;
+        let offset = reader.current_position();
+        let contents = reader.read_bytes(len as usize)?;
+        ret.push(Section {
+            id,
+            contents,
+            offset,
+        });
+    }
+    Ok(ret)
+}
+
+/// Appends `s` to `output` as a length-prefixed UTF-8 string.
+pub(crate) fn write_str(output: &mut Vec<u8>, s: &str) {
+    leb128::write_u32(output, s.len() as u32);
+    output.extend_from_slice(s.as_bytes());
+}
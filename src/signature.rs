@@ -0,0 +1,411 @@
+//! Signing modules and verifying their signatures.
+//!
+//! Signatures follow the [wasm-signatures] design. A module is signed by
+//! signing the SHA-256 hash of everything in it except its signature section,
+//! which holds the hash along with the signatures of any number of keys. The
+//! signature section is either embedded in the module as a custom section
+//! named [`SECTION_NAME`] before all its other sections, or kept apart from it
+//! as a detached signature.
+//!
+//! Signing and verifying is done by implementations of [`Signer`] and
+//! [`Verifier`], so keys can be kept anywhere and any signature algorithm can
+//! be used. [`Ed25519Signer`] and [`Ed25519Verifier`] implement Ed25519, the
+//! algorithm which other implementations of the design support.
+//!
+//! ```
+//! use wasm_tools::signature::{self, Ed25519Signer, Ed25519Verifier};
+//!
+//! let wasm = wat::parse_str(r#"(module (func (export "f")))"#)?;
+//! let signer = Ed25519Signer::from_seed([7; 32], b"release");
+//! let verifier = Ed25519Verifier::new(signer.public_key(), b"release");
+//!
+//! let signed = signature::sign(&wasm, &signer)?;
+//! signature::verify(&signed, &verifier)?;
+//!
+//! let detached = signature::sign_detached(&wasm, &signer)?;
+//! signature::verify_detached(&wasm, &detached, &verifier)?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! [wasm-signatures]: https://github.com/wasm-signatures/design
+
+use crate::sections::{sections, Section};
+use anyhow::{bail, Result};
+use ed25519_compact::{KeyPair, PublicKey, Seed, Signature};
+use sha2::{Digest, Sha256};
+use wasmparser::leb128::write_u32;
+use wasmparser::BinaryReader;
+
+/// The name of the custom section holding the signatures of a module.
+pub const SECTION_NAME: &str = "signature";
+
+/// The id of the Ed25519 signature algorithm.
+pub const ED25519: u8 = 0x01;
+
+/// The version of the design which signature sections follow.
+const SPEC_VERSION: u8 = 0x01;
+
+/// The content type of signature sections of WebAssembly modules.
+const CONTENT_TYPE: u8 = 0x01;
+
+/// The id of the SHA-256 hash function.
+const SHA256: u8 = 0x01;
+
+/// A key which signs modules.
+pub trait Signer {
+    /// Returns the id of the signature algorithm, such as [`ED25519`].
+    fn algorithm(&self) -> u8;
+
+    /// Returns the id of the key, which is recorded with its signatures so
+    /// verifiers can tell them apart. It may be empty.
+    fn key_id(&self) -> &[u8];
+
+    /// Returns the signature of `message`.
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// A key which verifies the signatures of modules.
+pub trait Verifier {
+    /// Returns the id of the signature algorithm, such as [`ED25519`].
+    fn algorithm(&self) -> u8;
+
+    /// Returns the id of the key, or an empty slice to check signatures
+    /// recorded with any key id.
+    fn key_id(&self) -> &[u8];
+
+    /// Returns whether `signature` is a valid signature of `message`.
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool;
+}
+
+/// A [`Signer`] using an Ed25519 secret key.
+#[derive(Clone)]
+pub struct Ed25519Signer {
+    key_pair: KeyPair,
+    key_id: Vec<u8>,
+}
+
+impl Ed25519Signer {
+    /// Creates a signer from the 32-byte seed of an Ed25519 key, recording
+    /// `key_id` with its signatures.
+    pub fn from_seed(seed: [u8; 32], key_id: &[u8]) -> Ed25519Signer {
+        Ed25519Signer {
+            key_pair: KeyPair::from_seed(Seed::new(seed)),
+            key_id: key_id.to_vec(),
+        }
+    }
+
+    /// Returns the public key which verifies the signatures of this signer.
+    pub fn public_key(&self) -> [u8; 32] {
+        *self.key_pair.pk
+    }
+}
+
+impl Signer for Ed25519Signer {
+    fn algorithm(&self) -> u8 {
+        ED25519
+    }
+
+    fn key_id(&self) -> &[u8] {
+        &self.key_id
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>> {
+        Ok(self.key_pair.sk.sign(message, None).to_vec())
+    }
+}
+
+/// A [`Verifier`] using an Ed25519 public key.
+#[derive(Clone, Debug)]
+pub struct Ed25519Verifier {
+    public_key: PublicKey,
+    key_id: Vec<u8>,
+}
+
+impl Ed25519Verifier {
+    /// Creates a verifier from a 32-byte Ed25519 public key, checking only
+    /// signatures recorded with `key_id` unless it's empty.
+    pub fn new(public_key: [u8; 32], key_id: &[u8]) -> Ed25519Verifier {
+        Ed25519Verifier {
+            public_key: PublicKey::new(public_key),
+            key_id: key_id.to_vec(),
+        }
+    }
+}
+
+impl Verifier for Ed25519Verifier {
+    fn algorithm(&self) -> u8 {
+        ED25519
+    }
+
+    fn key_id(&self) -> &[u8] {
+        &self.key_id
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        match Signature::from_slice(signature) {
+            Ok(signature) => self.public_key.verify(message, &signature).is_ok(),
+            Err(_) => false,
+        }
+    }
+}
+
+/// Returns the SHA-256 hash of `wasm` which is signed, covering everything
+/// except its signature section.
+pub fn hash(wasm: &[u8]) -> Result<[u8; 32]> {
+    Ok(split(wasm)?.hash())
+}
+
+/// Signs `wasm` with `signer`, returning the module with its signature
+/// section embedded before all its other sections.
+///
+/// Signatures already embedded in `wasm` are kept, except those made with
+/// the same key id and algorithm as `signer`, so a module can be signed with
+/// several keys. Signatures which don't match the contents of the module, for
+/// example because it was changed after being signed, are removed. The rest of
+/// the module is copied unchanged.
+pub fn sign(wasm: &[u8], signer: &dyn Signer) -> Result<Vec<u8>> {
+    let module = split(wasm)?;
+    let mut data = match module.signature {
+        Some(contents) => SignatureData::parse(contents)?,
+        None => SignatureData::default(),
+    };
+    data.add(module.hash(), signer)?;
+
+    let mut contents = Vec::new();
+    write_u32(&mut contents, SECTION_NAME.len() as u32);
+    contents.extend_from_slice(SECTION_NAME.as_bytes());
+    data.encode(&mut contents);
+    let mut output = wasm[..8].to_vec();
+    Section {
+        id: 0,
+        contents: &contents,
+        offset: 0,
+    }
+    .encode(&mut output);
+    for section in module.sections {
+        output.extend_from_slice(section);
+    }
+    Ok(output)
+}
+
+/// Signs `wasm` with `signer`, returning a detached signature which can be
+/// checked with [`verify_detached`].
+///
+/// Any signature section embedded in `wasm` is neither signed nor copied into
+/// the detached signature.
+pub fn sign_detached(wasm: &[u8], signer: &dyn Signer) -> Result<Vec<u8>> {
+    let mut data = SignatureData::default();
+    data.add(split(wasm)?.hash(), signer)?;
+    let mut output = Vec::new();
+    data.encode(&mut output);
+    Ok(output)
+}
+
+/// Checks that `wasm` has a signature made by `verifier`'s key embedded in it.
+///
+/// Returns an error if it doesn't, or if the module was changed after it was
+/// signed.
+pub fn verify(wasm: &[u8], verifier: &dyn Verifier) -> Result<()> {
+    let module = split(wasm)?;
+    let data = match module.signature {
+        Some(contents) => SignatureData::parse(contents)?,
+        None => bail!("module has no `{}` section", SECTION_NAME),
+    };
+    data.verify(module.hash(), verifier)
+}
+
+/// Checks that `signature`, a detached signature of `wasm`, was made by
+/// `verifier`'s key.
+///
+/// Returns an error if it wasn't, or if the module was changed after it was
+/// signed.
+pub fn verify_detached(wasm: &[u8], signature: &[u8], verifier: &dyn Verifier) -> Result<()> {
+    SignatureData::parse(signature)?.verify(split(wasm)?.hash(), verifier)
+}
+
+/// A module split into its signature section and everything else.
+struct SplitModule<'a> {
+    header: &'a [u8],
+    /// The contents of the signature section after its name, if any.
+    signature: Option<&'a [u8]>,
+    /// The other sections of the module, including their ids and sizes.
+    sections: Vec<&'a [u8]>,
+}
+
+impl SplitModule<'_> {
+    fn hash(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(self.header);
+        for section in self.sections.iter() {
+            hasher.update(section);
+        }
+        hasher.finalize().into()
+    }
+}
+
+fn split(wasm: &[u8]) -> Result<SplitModule<'_>> {
+    let sections = sections(wasm)?;
+    let mut module = SplitModule {
+        header: &wasm[..8],
+        signature: None,
+        sections: Vec::new(),
+    };
+    let mut start = 8;
+    for section in sections {
+        let end = section.offset + section.contents.len();
+        if section.custom_name()? == Some(SECTION_NAME) {
+            if module.signature.is_some() {
+                bail!("module has more than one `{}` section", SECTION_NAME);
+            }
+            let mut reader = BinaryReader::new(section.contents);
+            reader.read_string()?;
+            module.signature = Some(&section.contents[reader.current_position()..]);
+        } else {
+            module.sections.push(&wasm[start..end]);
+        }
+        start = end;
+    }
+    Ok(module)
+}
+
+/// The contents of a signature section.
+#[derive(Default)]
+struct SignatureData {
+    signed_hashes: Vec<SignedHashes>,
+}
+
+/// A set of hashes and their signatures.
+struct SignedHashes {
+    hashes: Vec<[u8; 32]>,
+    signatures: Vec<SignatureEntry>,
+}
+
+struct SignatureEntry {
+    key_id: Vec<u8>,
+    algorithm: u8,
+    signature: Vec<u8>,
+}
+
+impl SignatureData {
+    fn parse(data: &[u8]) -> Result<SignatureData> {
+        let mut reader = BinaryReader::new(data);
+        let spec_version = reader.read_u8()? as u8;
+        let content_type = reader.read_u8()? as u8;
+        let hash_function = reader.read_u8()? as u8;
+        if spec_version != SPEC_VERSION {
+            bail!("unsupported signature version {}", spec_version);
+        }
+        if content_type != CONTENT_TYPE {
+            bail!("unsupported signature content type {}", content_type);
+        }
+        if hash_function != SHA256 {
+            bail!("unsupported signature hash function {}", hash_function);
+        }
+        let mut signed_hashes = Vec::new();
+        for _ in 0..reader.read_var_u32()? {
+            let mut hashes = Vec::new();
+            for _ in 0..reader.read_var_u32()? {
+                let mut hash = [0; 32];
+                hash.copy_from_slice(reader.read_bytes(32)?);
+                hashes.push(hash);
+            }
+            let mut signatures = Vec::new();
+            for _ in 0..reader.read_var_u32()? {
+                let len = reader.read_var_u32()?;
+                let key_id = reader.read_bytes(len as usize)?.to_vec();
+                let algorithm = reader.read_u8()? as u8;
+                let len = reader.read_var_u32()?;
+                let signature = reader.read_bytes(len as usize)?.to_vec();
+                signatures.push(SignatureEntry {
+                    key_id,
+                    algorithm,
+                    signature,
+                });
+            }
+            signed_hashes.push(SignedHashes { hashes, signatures });
+        }
+        if !reader.eof() {
+            bail!("unexpected data at the end of the signatures");
+        }
+        Ok(SignatureData { signed_hashes })
+    }
+
+    fn encode(&self, output: &mut Vec<u8>) {
+        output.extend_from_slice(&[SPEC_VERSION, CONTENT_TYPE, SHA256]);
+        write_u32(output, self.signed_hashes.len() as u32);
+        for signed in self.signed_hashes.iter() {
+            write_u32(output, signed.hashes.len() as u32);
+            for hash in signed.hashes.iter() {
+                output.extend_from_slice(hash);
+            }
+            write_u32(output, signed.signatures.len() as u32);
+            for entry in signed.signatures.iter() {
+                write_u32(output, entry.key_id.len() as u32);
+                output.extend_from_slice(&entry.key_id);
+                output.push(entry.algorithm);
+                write_u32(output, entry.signature.len() as u32);
+                output.extend_from_slice(&entry.signature);
+            }
+        }
+    }
+
+    /// Adds the signature of `hash` by `signer`, dropping signatures of any
+    /// other hashes and any made with the same key before.
+    fn add(&mut self, hash: [u8; 32], signer: &dyn Signer) -> Result<()> {
+        self.signed_hashes.retain(|s| s.hashes == [hash]);
+        let entry = SignatureEntry {
+            key_id: signer.key_id().to_vec(),
+            algorithm: signer.algorithm(),
+            signature: signer.sign(&message(&[hash]))?,
+        };
+        match self.signed_hashes.first_mut() {
+            Some(signed) => {
+                signed
+                    .signatures
+                    .retain(|s| s.key_id != entry.key_id || s.algorithm != entry.algorithm);
+                signed.signatures.push(entry);
+            }
+            None => self.signed_hashes.push(SignedHashes {
+                hashes: vec![hash],
+                signatures: vec![entry],
+            }),
+        }
+        Ok(())
+    }
+
+    fn verify(&self, hash: [u8; 32], verifier: &dyn Verifier) -> Result<()> {
+        let mut signed_hashes = self
+            .signed_hashes
+            .iter()
+            .filter(|s| s.hashes.contains(&hash))
+            .peekable();
+        if signed_hashes.peek().is_none() {
+            bail!("module was changed after it was signed");
+        }
+        for signed in signed_hashes {
+            let message = message(&signed.hashes);
+            for entry in signed.signatures.iter() {
+                if entry.algorithm != verifier.algorithm() {
+                    continue;
+                }
+                if !verifier.key_id().is_empty() && entry.key_id != verifier.key_id() {
+                    continue;
+                }
+                if verifier.verify(&message, &entry.signature) {
+                    return Ok(());
+                }
+            }
+        }
+        bail!("module has no valid signature made with the given key")
+    }
+}
+
+/// Returns the message which is signed to sign `hashes`.
+fn message(hashes: &[[u8; 32]]) -> Vec<u8> {
+    let mut message = b"wasmsig".to_vec();
+    message.extend_from_slice(&[SPEC_VERSION, CONTENT_TYPE, SHA256]);
+    for hash in hashes {
+        message.extend_from_slice(hash);
+    }
+    message
+}
@@ -0,0 +1,385 @@
+//! Snapshotting the contents of a linear memory into data segments.
+//!
+//! Initialization which doesn't depend on the environment, such as running
+//! constructors or parsing configuration, can be done once ahead of time and
+//! its results baked into the module. Given the contents of a memory after
+//! such initialization, [`Snapshot::apply`] replaces the memory's active data
+//! segments with ones which reproduce those contents when the module is
+//! instantiated.
+//!
+//! ```
+//! use wasm_tools::init::{initialize, Imports};
+//! use wasm_tools::snapshot::Snapshot;
+//!
+//! let wasm = wat::parse_str(r#"
+//!     (module
+//!       (memory 1)
+//!       (data (i32.const 0) "old"))
+//! "#)?;
+//! let mut image = vec![0; 65536];
+//! image[100..103].copy_from_slice(b"new");
+//! let wasm = Snapshot::new().apply(&wasm, &image)?;
+//! assert_eq!(initialize(&wasm, &Imports::new())?.memories[0], image);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::gc::{renumber, Map, Module};
+use crate::sections::Section;
+use anyhow::{bail, Result};
+use std::ops::Range;
+use wasmparser::leb128::{write_i64, write_u32, write_u64};
+use wasmparser::{
+    DataKind, DataSectionReader, ImportSectionEntryType, ImportSectionReader, MemorySectionReader,
+    MemoryType,
+};
+
+const CUSTOM: u8 = 0;
+const IMPORT: u8 = 2;
+const MEMORY: u8 = 5;
+const CODE: u8 = 10;
+const DATA: u8 = 11;
+const DATA_COUNT: u8 = 12;
+
+const PAGE_SIZE: u64 = 65536;
+
+/// How to snapshot the contents of a memory, see the
+/// [module documentation](self).
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    memory: u32,
+    min_gap: usize,
+    max_segments: usize,
+    max_segment_size: usize,
+}
+
+impl Default for Snapshot {
+    fn default() -> Snapshot {
+        Snapshot {
+            memory: 0,
+            min_gap: 8,
+            max_segments: 100_000,
+            max_segment_size: usize::MAX,
+        }
+    }
+}
+
+impl Snapshot {
+    /// Creates a new snapshot of the first memory of a module.
+    ///
+    /// By default runs of fewer than 8 zero bytes are included in segments,
+    /// since a separate segment would take more space than they do, and at
+    /// most 100,000 segments are created, which is the limit of most engines.
+    pub fn new() -> Snapshot {
+        Snapshot::default()
+    }
+
+    /// Sets the index of the memory to snapshot, including imported memories.
+    pub fn memory(&mut self, memory: u32) -> &mut Snapshot {
+        self.memory = memory;
+        self
+    }
+
+    /// Sets the length of the shortest run of zero bytes which separates two
+    /// segments.
+    pub fn min_gap(&mut self, min_gap: usize) -> &mut Snapshot {
+        self.min_gap = min_gap;
+        self
+    }
+
+    /// Sets the largest number of segments to create.
+    ///
+    /// If the contents of the memory need more segments, the segments which
+    /// are closest to each other are merged, including the zero bytes between
+    /// them.
+    pub fn max_segments(&mut self, max_segments: usize) -> &mut Snapshot {
+        self.max_segments = max_segments;
+        self
+    }
+
+    /// Sets the size of the largest segment to create, which is unlimited by
+    /// default. Larger segments are split up.
+    pub fn max_segment_size(&mut self, max_segment_size: usize) -> &mut Snapshot {
+        self.max_segment_size = max_segment_size;
+        self
+    }
+
+    /// Returns the module `wasm` with the active data segments of the memory
+    /// replaced by ones which initialize it with `image`.
+    ///
+    /// Active segments of other memories and passive segments are kept, and
+    /// instructions referring to them are renumbered. The minimum size of the
+    /// memory is increased to fit `image` if needed; the rest of the memory
+    /// is zero-filled.
+    ///
+    /// Returns an error if `image` doesn't fit in the memory, which for
+    /// imported memories means their minimum size, or if it would need more
+    /// than the maximum number of segments. Modules using the module linking
+    /// proposal aren't supported.
+    pub fn apply(&self, wasm: &[u8], image: &[u8]) -> Result<Vec<u8>> {
+        let module = Module::parse(wasm)?;
+        let segments = self.segments(image)?;
+        let pages = (image.len() as u64).div_ceil(PAGE_SIZE);
+
+        // Find the memory, and whether it's imported.
+        let mut memories = 0;
+        let mut ty = None;
+        for section in module.sections.iter() {
+            match section.id {
+                IMPORT => {
+                    for import in ImportSectionReader::new(section.contents, section.offset)? {
+                        if let ImportSectionEntryType::Memory(memory) = import?.ty {
+                            if memories == self.memory {
+                                ty = Some((memory, true));
+                            }
+                            memories += 1;
+                        }
+                    }
+                }
+                MEMORY => {
+                    for memory in MemorySectionReader::new(section.contents, section.offset)? {
+                        let memory = memory?;
+                        if memories == self.memory {
+                            ty = Some((memory, false));
+                        }
+                        memories += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let (ty, imported) = match ty {
+            Some(ty) => ty,
+            None => bail!("module has no memory {}", self.memory),
+        };
+        let (initial, maximum, memory64) = match ty {
+            MemoryType::M32 { limits, .. } => (
+                limits.initial.into(),
+                limits.maximum.map(u64::from).unwrap_or(PAGE_SIZE),
+                false,
+            ),
+            MemoryType::M64 { limits, .. } => {
+                (limits.initial, limits.maximum.unwrap_or(1 << 48), true)
+            }
+        };
+        if pages > maximum || (imported && pages > initial) {
+            bail!(
+                "memory image of {} bytes doesn't fit in memory {}",
+                image.len(),
+                self.memory
+            );
+        }
+
+        // Find which data segments are kept.
+        let mut kept = Vec::new();
+        for section in module.sections.iter().filter(|s| s.id == DATA) {
+            for data in DataSectionReader::new(section.contents, section.offset)? {
+                kept.push(match data?.kind {
+                    DataKind::Active { memory_index, .. } => memory_index != self.memory,
+                    DataKind::Passive => true,
+                });
+            }
+        }
+        let identity = |count: usize| renumber(&vec![true; count]);
+        let map = Map {
+            types: identity(module.types as usize),
+            funcs: identity(module.func_types.len()),
+            globals: identity(module.imported_globals as usize + module.global_inits.len()),
+            data: renumber(&kept),
+            tables: None,
+            events: None,
+            elements: None,
+        };
+        let data_count = map.data.count + segments.len() as u32;
+
+        let mut new_segments = Vec::new();
+        for range in segments.iter() {
+            if self.memory == 0 {
+                new_segments.push(0x00);
+            } else {
+                new_segments.push(0x02);
+                write_u32(&mut new_segments, self.memory);
+            }
+            if memory64 {
+                new_segments.push(0x42);
+                write_i64(&mut new_segments, range.start as i64);
+            } else {
+                new_segments.push(0x41);
+                write_i64(&mut new_segments, (range.start as u32 as i32).into());
+            }
+            new_segments.push(0x0b);
+            write_u32(&mut new_segments, range.len() as u32);
+            new_segments.extend_from_slice(&image[range.clone()]);
+        }
+
+        let has_data = module.sections.iter().any(|s| s.id == DATA);
+        let last = module.sections.iter().rposition(|s| s.id != CUSTOM);
+        let mut output = wasm[..8].to_vec();
+        for (i, section) in module.sections.iter().enumerate() {
+            let mut contents = Vec::new();
+            let rewritten = match section.id {
+                MEMORY if !imported => {
+                    let mut reader = MemorySectionReader::new(section.contents, section.offset)?;
+                    let first = memories - reader.get_count();
+                    write_u32(&mut contents, reader.get_count());
+                    for index in first..memories {
+                        let start = reader.original_position();
+                        let memory = reader.read()?;
+                        if index != self.memory || pages <= initial {
+                            contents.extend_from_slice(&wasm[start..reader.original_position()]);
+                            continue;
+                        }
+                        encode_memory_type(memory, pages, &mut contents);
+                    }
+                    true
+                }
+                DATA => {
+                    let mut reader = DataSectionReader::new(section.contents, section.offset)?;
+                    write_u32(&mut contents, data_count);
+                    for keep in kept.iter() {
+                        let start = reader.original_position();
+                        let data = reader.read()?;
+                        if !keep {
+                            continue;
+                        }
+                        match data.kind {
+                            DataKind::Active { init_expr, .. } => {
+                                let expr = init_expr.get_operators_reader();
+                                contents.extend_from_slice(&wasm[start..expr.original_position()]);
+                                map.expr(wasm, expr, &mut contents)?;
+                                write_u32(&mut contents, data.data.len() as u32);
+                                contents.extend_from_slice(data.data);
+                            }
+                            DataKind::Passive => {
+                                contents.extend_from_slice(&wasm[start..reader.original_position()])
+                            }
+                        }
+                    }
+                    contents.extend_from_slice(&new_segments);
+                    true
+                }
+                DATA_COUNT => {
+                    write_u32(&mut contents, data_count);
+                    true
+                }
+                CODE if map.data.count as usize != kept.len() => {
+                    write_u32(&mut contents, module.bodies.len() as u32);
+                    for body in module.bodies.iter() {
+                        let ops = body.get_operators_reader()?;
+                        let mut encoded =
+                            wasm[body.range().start..ops.original_position()].to_vec();
+                        map.expr(wasm, ops, &mut encoded)?;
+                        write_u32(&mut contents, encoded.len() as u32);
+                        contents.extend_from_slice(&encoded);
+                    }
+                    true
+                }
+                _ => false,
+            };
+            if rewritten {
+                encode_section(section.id, &contents, &mut output);
+            } else {
+                section.encode(&mut output);
+            }
+            if Some(i) == last && !has_data && !segments.is_empty() {
+                let mut contents = Vec::new();
+                write_u32(&mut contents, data_count);
+                contents.extend_from_slice(&new_segments);
+                encode_section(DATA, &contents, &mut output);
+            }
+        }
+        Ok(output)
+    }
+
+    /// Returns the ranges of `image` to create segments for.
+    fn segments(&self, image: &[u8]) -> Result<Vec<Range<usize>>> {
+        // Find the runs of non-zero bytes, joining runs separated by only a
+        // few zeros.
+        let mut runs: Vec<Range<usize>> = Vec::new();
+        let mut i = 0;
+        while i < image.len() {
+            if image[i] == 0 {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            while i < image.len() && image[i] != 0 {
+                i += 1;
+            }
+            match runs.last_mut() {
+                Some(last) if start - last.end < self.min_gap => last.end = i,
+                _ => runs.push(start..i),
+            }
+        }
+
+        // If there are too many runs, join the ones with the shortest gaps
+        // between them.
+        if runs.len() > self.max_segments {
+            let mut gaps = (1..runs.len()).collect::<Vec<_>>();
+            gaps.sort_by_key(|i| runs[*i].start - runs[*i - 1].end);
+            let mut join = vec![false; runs.len()];
+            for i in gaps.iter().take(runs.len() - self.max_segments) {
+                join[*i] = true;
+            }
+            let mut joined: Vec<Range<usize>> = Vec::new();
+            for (run, join) in runs.into_iter().zip(join) {
+                match joined.last_mut() {
+                    Some(last) if join => last.end = run.end,
+                    _ => joined.push(run),
+                }
+            }
+            runs = joined;
+        }
+
+        let mut segments = Vec::new();
+        for run in runs {
+            let mut start = run.start;
+            while start < run.end {
+                let end = run.end.min(start.saturating_add(self.max_segment_size));
+                segments.push(start..end);
+                start = end;
+            }
+        }
+        if segments.len() > self.max_segments {
+            bail!(
+                "memory image needs {} segments of at most {} bytes, more than the limit of {}",
+                segments.len(),
+                self.max_segment_size,
+                self.max_segments
+            );
+        }
+        Ok(segments)
+    }
+}
+
+/// Writes the memory type `ty` to `output` with its minimum size changed to
+/// `pages`.
+fn encode_memory_type(ty: MemoryType, pages: u64, output: &mut Vec<u8>) {
+    let (maximum, shared, memory64) = match ty {
+        MemoryType::M32 { limits, shared } => (limits.maximum.map(u64::from), shared, false),
+        MemoryType::M64 { limits, shared } => (limits.maximum, shared, true),
+    };
+    let mut flags = 0;
+    if maximum.is_some() {
+        flags |= 0x01;
+    }
+    if shared {
+        flags |= 0x02;
+    }
+    if memory64 {
+        flags |= 0x04;
+    }
+    output.push(flags);
+    write_u64(output, pages);
+    if let Some(maximum) = maximum {
+        write_u64(output, maximum);
+    }
+}
+
+fn encode_section(id: u8, contents: &[u8], output: &mut Vec<u8>) {
+    Section {
+        id,
+        contents,
+        offset: 0,
+    }
+    .encode(output);
+}
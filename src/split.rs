@@ -0,0 +1,681 @@
+//! Splitting of a module into a primary module and secondary modules which
+//! can be loaded later.
+//!
+//! Large applications often have features which most sessions never use,
+//! and deployments want to download and compile their code only once it's
+//! needed. Like Emscripten's and Binaryen's module splitting, [`Splitter`]
+//! moves the functions which are only reachable from chosen entry points,
+//! following the [`CallGraph`], into secondary modules:
+//!
+//! * The primary module keeps every function, so no index changes, but the
+//!   body of each moved function is replaced with a stub which forwards its
+//!   arguments with `call_indirect` through a slot of the first table
+//!   reserved for it. The table grows to hold the slots, or is created if
+//!   the module has none. The primary module exports what the secondary
+//!   modules need under names starting with `__split_`.
+//! * Each secondary module imports from the primary module, under the name
+//!   `primary` by default, the functions and globals its functions refer to
+//!   and all the tables, memories and events. It defines the moved
+//!   functions and an element segment which fills their slots.
+//!
+//! Until a secondary module is instantiated its slots are empty, and calling
+//! one of its functions traps. Functions which use passive data or element
+//! segments can't be moved, since segments can't be shared between modules,
+//! and the start function always stays in the primary module.
+//!
+//! ```
+//! use wasm_tools::split::Splitter;
+//!
+//! let wasm = wat::parse_str(r#"
+//!     (module
+//!       (func $main (export "main") (result i32) i32.const 1)
+//!       (func $settings (export "settings") (result i32) call $helper)
+//!       (func $helper (result i32) i32.const 2))
+//! "#)?;
+//! let split = Splitter::new().secondary(vec![1]).split(&wasm)?;
+//! let primary = wasmprinter::print_bytes(&split.primary)?;
+//! assert!(primary.contains("call_indirect (type 0)"));
+//! let secondary = wasmprinter::print_bytes(&split.secondaries[0])?;
+//! assert!(secondary.contains("(import \"primary\" \"__split_table_0\" (table"));
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::analysis::CallGraph;
+use crate::functions::References;
+use crate::gc::{renumber, Map, Module, Renumbering};
+use crate::names::Names;
+use crate::sections::{rank, write_str, Section};
+use anyhow::{bail, Result};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use wasmparser::leb128::{write_i64, write_u32};
+use wasmparser::{
+    BinaryReader, EventSectionReader, ExportSectionReader, GlobalSectionReader,
+    ImportSectionEntryType, ImportSectionReader, MemorySectionReader, Operator, TableSectionReader,
+    TableType, Type, TypeDef, TypeSectionReader,
+};
+
+const CUSTOM: u8 = 0;
+const TYPE: u8 = 1;
+const IMPORT: u8 = 2;
+const FUNCTION: u8 = 3;
+const TABLE: u8 = 4;
+const MEMORY: u8 = 5;
+const GLOBAL: u8 = 6;
+const EXPORT: u8 = 7;
+const ELEMENT: u8 = 9;
+const CODE: u8 = 10;
+const EVENT: u8 = 13;
+
+const FUNCREF: u8 = 0x70;
+
+/// How to split a module, see the [module documentation](self).
+#[derive(Clone, Debug)]
+pub struct Splitter {
+    primary_module: String,
+    secondaries: Vec<Vec<u32>>,
+}
+
+/// The modules a module is split into by [`Splitter::split`].
+#[derive(Clone, Debug)]
+pub struct Split {
+    /// The primary module, which replaces the original one.
+    pub primary: Vec<u8>,
+    /// The secondary modules, in the order they were added to the
+    /// [`Splitter`].
+    pub secondaries: Vec<Vec<u8>>,
+}
+
+impl Default for Splitter {
+    fn default() -> Splitter {
+        Splitter {
+            primary_module: "primary".to_string(),
+            secondaries: Vec::new(),
+        }
+    }
+}
+
+impl Splitter {
+    /// Creates a new configuration without any secondary modules.
+    pub fn new() -> Splitter {
+        Splitter::default()
+    }
+
+    /// Sets the module name under which secondary modules import from the
+    /// primary module.
+    pub fn primary_module(&mut self, name: &str) -> &mut Splitter {
+        self.primary_module = name.to_string();
+        self
+    }
+
+    /// Adds a secondary module, holding the functions `roots` and the
+    /// functions reachable from them.
+    ///
+    /// Functions which are also reachable from an entry point of the module
+    /// which isn't a root of a secondary module stay in the primary module.
+    /// Functions which are reachable from the roots of several secondary
+    /// modules go into the first of them.
+    pub fn secondary(&mut self, roots: impl IntoIterator<Item = u32>) -> &mut Splitter {
+        self.secondaries.push(roots.into_iter().collect());
+        self
+    }
+
+    /// Splits the module `wasm`.
+    ///
+    /// Function names in the `name` section are copied to the secondary
+    /// modules. The primary module keeps all custom sections.
+    ///
+    /// Returns an error if `wasm` is malformed, if a root is imported or out
+    /// of bounds, or if functions would be moved but the first table of the
+    /// module is imported or doesn't hold `funcref`s. Modules using the
+    /// module linking proposal aren't supported.
+    pub fn split(&self, wasm: &[u8]) -> Result<Split> {
+        let module = Module::parse(wasm)?;
+        let items = Items::parse(wasm, &module)?;
+        let mut references = Vec::new();
+        for body in module.bodies.iter() {
+            let mut refs = References::default();
+            refs.add(body.get_operators_reader()?)?;
+            references.push(refs);
+        }
+        let parts = self.partition(wasm, &module, &references)?;
+
+        // Reserve the slots of the moved functions after the existing
+        // elements of the first table.
+        let base = items.table.map_or(0, |t| t.limits.initial);
+        let mut slots = BTreeMap::new();
+        for f in parts.iter().flatten() {
+            slots.insert(*f, base + slots.len() as u32);
+        }
+        if !slots.is_empty() {
+            match (items.table, items.tables.is_empty()) {
+                (Some(ty), _) if ty.element_type != Type::FuncRef => {
+                    bail!("the first table of the module doesn't hold `funcref`s")
+                }
+                (None, false) => bail!("modules which import their first table can't be split"),
+                _ => {}
+            }
+        }
+        let new_table = if items.tables.is_empty() && !slots.is_empty() {
+            let mut ty = vec![FUNCREF, 0x00];
+            write_u32(&mut ty, slots.len() as u32);
+            Some(ty)
+        } else {
+            None
+        };
+        let tables = items.tables.len() as u32 + new_table.is_some() as u32;
+
+        // What each secondary module imports from the primary module, which
+        // exports the union of them.
+        let imports = parts
+            .iter()
+            .map(|part| {
+                let mut funcs = BTreeSet::new();
+                let mut globals = BTreeSet::new();
+                for f in part.iter() {
+                    let refs = &references[(f - module.imported_funcs) as usize];
+                    funcs.extend(refs.functions.difference(part));
+                    globals.extend(refs.globals.iter().copied());
+                }
+                (funcs, globals)
+            })
+            .collect::<Vec<_>>();
+        let mut exports = Vec::new();
+        let exported_funcs = imports
+            .iter()
+            .flat_map(|i| i.0.iter())
+            .collect::<BTreeSet<_>>();
+        for f in exported_funcs {
+            exports.push((format!("__split_func_{}", f), 0x00, *f));
+        }
+        if !slots.is_empty() {
+            exports.extend((0..tables).map(|i| (format!("__split_table_{}", i), 0x01, i)));
+            let memories = items.memories.len() as u32;
+            exports.extend((0..memories).map(|i| (format!("__split_memory_{}", i), 0x02, i)));
+        }
+        let exported_globals = imports
+            .iter()
+            .flat_map(|i| i.1.iter())
+            .collect::<BTreeSet<_>>();
+        for g in exported_globals {
+            exports.push((format!("__split_global_{}", g), 0x03, *g));
+        }
+        if !slots.is_empty() {
+            let events = items.events.len() as u32;
+            exports.extend((0..events).map(|i| (format!("__split_event_{}", i), 0x04, i)));
+        }
+
+        let primary = self.primary(
+            wasm,
+            &module,
+            &items,
+            &slots,
+            new_table.as_deref(),
+            &exports,
+        )?;
+        let names = Names::read(wasm)?;
+        let mut secondaries = Vec::new();
+        for (part, (funcs, globals)) in parts.iter().zip(imports.iter()) {
+            let secondary = Secondary {
+                part,
+                funcs,
+                globals,
+                slot: part.iter().next().map_or(base, |f| slots[f]),
+            };
+            secondaries.push(self.secondary_module(
+                wasm,
+                &module,
+                &items,
+                new_table.as_deref(),
+                &names,
+                &secondary,
+            )?);
+        }
+        Ok(Split {
+            primary,
+            secondaries,
+        })
+    }
+
+    /// Returns the functions which are moved to each secondary module.
+    fn partition(
+        &self,
+        wasm: &[u8],
+        module: &Module<'_>,
+        references: &[References],
+    ) -> Result<Vec<BTreeSet<u32>>> {
+        let graph = CallGraph::new(wasm)?;
+        let lazy = self.secondaries.iter().flatten().collect::<HashSet<_>>();
+        for f in lazy.iter() {
+            if **f >= graph.functions() {
+                bail!("function index {} out of bounds", f);
+            }
+            if **f < module.imported_funcs {
+                bail!("function {} is imported and can't be moved", f);
+            }
+        }
+        let roots = graph
+            .roots()
+            .iter()
+            .copied()
+            .filter(|f| !lazy.contains(f))
+            .chain(module.start);
+        let mut assigned = graph.reachable_from(roots);
+        let mut parts = Vec::new();
+        for roots in self.secondaries.iter() {
+            let part = graph
+                .reachable_from(roots.iter().copied())
+                .into_iter()
+                .filter(|f| {
+                    let movable = match f.checked_sub(module.imported_funcs) {
+                        Some(i) => {
+                            let refs = &references[i as usize];
+                            refs.data.is_empty() && refs.elements.is_empty()
+                        }
+                        None => false,
+                    };
+                    movable && !assigned.contains(f)
+                })
+                .collect::<BTreeSet<_>>();
+            assigned.extend(part.iter().copied());
+            parts.push(part);
+        }
+        Ok(parts)
+    }
+
+    /// Writes the primary module, with stubs for the functions in `slots`
+    /// and the `exports` added.
+    fn primary(
+        &self,
+        wasm: &[u8],
+        module: &Module<'_>,
+        items: &Items<'_>,
+        slots: &BTreeMap<u32, u32>,
+        new_table: Option<&[u8]>,
+        exports: &[(String, u8, u32)],
+    ) -> Result<Vec<u8>> {
+        let mut encoded_exports = Vec::new();
+        for (name, kind, index) in exports {
+            write_str(&mut encoded_exports, name);
+            encoded_exports.push(*kind);
+            write_u32(&mut encoded_exports, *index);
+        }
+
+        // Sections which the module doesn't have are added just before the
+        // first section which has to follow them.
+        let mut missing = Vec::new();
+        if let Some(ty) = new_table {
+            let mut contents = vec![1];
+            contents.extend_from_slice(ty);
+            missing.push((TABLE, contents));
+        }
+        if !exports.is_empty() && !module.sections.iter().any(|s| s.id == EXPORT) {
+            let mut contents = Vec::new();
+            write_u32(&mut contents, exports.len() as u32);
+            contents.extend_from_slice(&encoded_exports);
+            missing.push((EXPORT, contents));
+        }
+        missing.reverse();
+
+        let mut output = wasm[..8].to_vec();
+        for section in module.sections.iter() {
+            if section.id != CUSTOM {
+                while let Some((id, contents)) = missing.last() {
+                    if rank(*id) > rank(section.id) {
+                        break;
+                    }
+                    encode_section(*id, contents, &mut output);
+                    missing.pop();
+                }
+            }
+
+            let mut contents = Vec::new();
+            match section.id {
+                TABLE if !slots.is_empty() => {
+                    // The first table grows to hold the slots.
+                    let mut reader = TableSectionReader::new(section.contents, section.offset)?;
+                    write_u32(&mut contents, reader.get_count());
+                    let limits = reader.read()?.limits;
+                    let initial = limits.initial + slots.len() as u32;
+                    contents.push(FUNCREF);
+                    match limits.maximum {
+                        Some(maximum) => {
+                            contents.push(0x01);
+                            write_u32(&mut contents, initial);
+                            write_u32(&mut contents, maximum.max(initial));
+                        }
+                        None => {
+                            contents.push(0x00);
+                            write_u32(&mut contents, initial);
+                        }
+                    }
+                    let rest = reader.original_position() - section.offset;
+                    contents.extend_from_slice(&section.contents[rest..]);
+                }
+                EXPORT => {
+                    let mut reader = ExportSectionReader::new(section.contents, section.offset)?;
+                    let count = reader.get_count();
+                    let mut names = HashSet::new();
+                    for _ in 0..count {
+                        names.insert(reader.read()?.field);
+                    }
+                    for (name, _, _) in exports {
+                        if names.contains(name.as_str()) {
+                            bail!("module already has an export named `{}`", name);
+                        }
+                    }
+                    write_u32(&mut contents, count + exports.len() as u32);
+                    let mut reader = BinaryReader::new(section.contents);
+                    reader.read_var_u32()?;
+                    contents.extend_from_slice(&section.contents[reader.current_position()..]);
+                    contents.extend_from_slice(&encoded_exports);
+                }
+                CODE if !slots.is_empty() => {
+                    write_u32(&mut contents, module.bodies.len() as u32);
+                    for (i, body) in module.bodies.iter().enumerate() {
+                        let f = module.imported_funcs + i as u32;
+                        let slot = match slots.get(&f) {
+                            Some(slot) => *slot,
+                            None => {
+                                let range = body.range();
+                                write_u32(&mut contents, (range.end - range.start) as u32);
+                                contents.extend_from_slice(&wasm[range.start..range.end]);
+                                continue;
+                            }
+                        };
+                        // A stub forwarding the arguments to the function in
+                        // the slot.
+                        let ty = module.func_types[f as usize];
+                        let mut stub = vec![0x00];
+                        for param in 0..items.params[ty as usize] {
+                            stub.push(0x20);
+                            write_u32(&mut stub, param);
+                        }
+                        stub.push(0x41);
+                        write_i64(&mut stub, slot.into());
+                        stub.push(0x11);
+                        write_u32(&mut stub, ty);
+                        stub.extend_from_slice(&[0x00, 0x0b]);
+                        write_u32(&mut contents, stub.len() as u32);
+                        contents.extend_from_slice(&stub);
+                    }
+                }
+                _ => {
+                    section.encode(&mut output);
+                    continue;
+                }
+            }
+            encode_section(section.id, &contents, &mut output);
+        }
+        while let Some((id, contents)) = missing.pop() {
+            encode_section(id, &contents, &mut output);
+        }
+        Ok(output)
+    }
+
+    /// Writes a secondary module.
+    fn secondary_module(
+        &self,
+        wasm: &[u8],
+        module: &Module<'_>,
+        items: &Items<'_>,
+        new_table: Option<&[u8]>,
+        names: &Names,
+        secondary: &Secondary<'_>,
+    ) -> Result<Vec<u8>> {
+        let imported = secondary.funcs.len() as u32;
+        let mut funcs = vec![None; module.func_types.len()];
+        for (i, f) in secondary.funcs.iter().enumerate() {
+            funcs[*f as usize] = Some(i as u32);
+        }
+        for (i, f) in secondary.part.iter().enumerate() {
+            funcs[*f as usize] = Some(imported + i as u32);
+        }
+        let mut globals = vec![None; items.globals.len()];
+        for (i, g) in secondary.globals.iter().enumerate() {
+            globals[*g as usize] = Some(i as u32);
+        }
+        let map = Map {
+            types: renumber(&vec![true; module.types as usize]),
+            funcs: Renumbering {
+                count: imported + secondary.part.len() as u32,
+                indices: funcs,
+            },
+            globals: Renumbering {
+                count: secondary.globals.len() as u32,
+                indices: globals,
+            },
+            data: renumber(&[]),
+            tables: None,
+            events: None,
+            elements: None,
+        };
+
+        let mut output = wasm[..8].to_vec();
+        if let Some(section) = module.sections.iter().find(|s| s.id == TYPE) {
+            section.encode(&mut output);
+        }
+
+        let mut contents = Vec::new();
+        let mut count = 0;
+        let mut import = |field: String, kind: u8, ty: &[u8]| {
+            write_str(&mut contents, &self.primary_module);
+            write_str(&mut contents, &field);
+            contents.push(kind);
+            contents.extend_from_slice(ty);
+            count += 1;
+        };
+        for f in secondary.funcs.iter() {
+            let mut ty = Vec::new();
+            write_u32(&mut ty, module.func_types[*f as usize]);
+            import(format!("__split_func_{}", f), 0x00, &ty);
+        }
+        // Modules without any functions don't need anything else.
+        let empty = secondary.part.is_empty();
+        if !empty {
+            let tables = items.tables.iter().copied().chain(new_table);
+            for (i, ty) in tables.enumerate() {
+                import(format!("__split_table_{}", i), 0x01, ty);
+            }
+            for (i, ty) in items.memories.iter().enumerate() {
+                import(format!("__split_memory_{}", i), 0x02, ty);
+            }
+            for (i, ty) in items.events.iter().enumerate() {
+                import(format!("__split_event_{}", i), 0x04, ty);
+            }
+        }
+        for g in secondary.globals.iter() {
+            import(
+                format!("__split_global_{}", g),
+                0x03,
+                items.globals[*g as usize],
+            );
+        }
+        let mut section = Vec::new();
+        write_u32(&mut section, count);
+        section.extend_from_slice(&contents);
+        encode_section(IMPORT, &section, &mut output);
+
+        let mut contents = Vec::new();
+        write_u32(&mut contents, secondary.part.len() as u32);
+        for f in secondary.part.iter() {
+            write_u32(&mut contents, module.func_types[*f as usize]);
+        }
+        encode_section(FUNCTION, &contents, &mut output);
+
+        // An active segment fills the slots, and a declarative one declares
+        // the imported functions which are referenced with `ref.func`.
+        let mut referenced = BTreeSet::new();
+        for f in secondary.part.iter() {
+            let body = &module.bodies[(f - module.imported_funcs) as usize];
+            let mut reader = body.get_operators_reader()?;
+            while !reader.eof() {
+                if let Operator::RefFunc { function_index } = reader.read()? {
+                    if !secondary.part.contains(&function_index) {
+                        referenced.insert(map.funcs.get(function_index));
+                    }
+                }
+            }
+        }
+        let mut contents = Vec::new();
+        write_u32(&mut contents, !empty as u32 + !referenced.is_empty() as u32);
+        if !empty {
+            contents.extend_from_slice(&[0x00, 0x41]);
+            write_i64(&mut contents, secondary.slot.into());
+            contents.push(0x0b);
+            write_u32(&mut contents, secondary.part.len() as u32);
+            for i in 0..secondary.part.len() as u32 {
+                write_u32(&mut contents, imported + i);
+            }
+        }
+        if !referenced.is_empty() {
+            contents.extend_from_slice(&[0x03, 0x00]);
+            write_u32(&mut contents, referenced.len() as u32);
+            for f in referenced {
+                write_u32(&mut contents, f);
+            }
+        }
+        encode_section(ELEMENT, &contents, &mut output);
+
+        let mut contents = Vec::new();
+        write_u32(&mut contents, secondary.part.len() as u32);
+        for f in secondary.part.iter() {
+            let body = &module.bodies[(f - module.imported_funcs) as usize];
+            let ops = body.get_operators_reader()?;
+            let mut encoded = wasm[body.range().start..ops.original_position()].to_vec();
+            map.expr(wasm, ops, &mut encoded)?;
+            write_u32(&mut contents, encoded.len() as u32);
+            contents.extend_from_slice(&encoded);
+        }
+        encode_section(CODE, &contents, &mut output);
+
+        let mut secondary_names = Names::new();
+        for (f, name) in names.functions() {
+            if let Some(Some(index)) = map.funcs.indices.get(f as usize) {
+                secondary_names.set_function(*index, name);
+            }
+        }
+        secondary_names.apply(&output)
+    }
+}
+
+/// The contents of a secondary module.
+struct Secondary<'a> {
+    /// The functions moved to the module.
+    part: &'a BTreeSet<u32>,
+    /// The functions and globals it imports from the primary module.
+    funcs: &'a BTreeSet<u32>,
+    globals: &'a BTreeSet<u32>,
+    /// The slot of its first function.
+    slot: u32,
+}
+
+/// The encoded types of the items of a module which secondary modules
+/// import.
+struct Items<'a> {
+    tables: Vec<&'a [u8]>,
+    memories: Vec<&'a [u8]>,
+    globals: Vec<&'a [u8]>,
+    events: Vec<&'a [u8]>,
+    /// The type of the first table, if the module defines it.
+    table: Option<TableType>,
+    /// The number of parameters of each function type.
+    params: Vec<u32>,
+}
+
+impl<'a> Items<'a> {
+    fn parse(wasm: &'a [u8], module: &Module<'a>) -> Result<Items<'a>> {
+        let mut items = Items {
+            tables: Vec::new(),
+            memories: Vec::new(),
+            globals: Vec::new(),
+            events: Vec::new(),
+            table: None,
+            params: Vec::new(),
+        };
+        for section in module.sections.iter() {
+            match section.id {
+                TYPE => {
+                    for ty in TypeSectionReader::new(section.contents, section.offset)? {
+                        items.params.push(match ty? {
+                            TypeDef::Func(ty) => ty.params.len() as u32,
+                            _ => 0,
+                        });
+                    }
+                }
+                IMPORT => {
+                    let mut reader = ImportSectionReader::new(section.contents, section.offset)?;
+                    for _ in 0..reader.get_count() {
+                        let start = reader.original_position();
+                        let import = reader.read()?;
+                        if import.field.is_none() {
+                            bail!("modules using the module linking proposal are not supported");
+                        }
+                        // The type follows the names and the kind of the
+                        // import.
+                        let entry = &wasm[start..reader.original_position()];
+                        let mut entry_reader = BinaryReader::new(entry);
+                        entry_reader.read_string()?;
+                        entry_reader.read_string()?;
+                        entry_reader.read_u8()?;
+                        let ty = &entry[entry_reader.current_position()..];
+                        match import.ty {
+                            ImportSectionEntryType::Table(_) => items.tables.push(ty),
+                            ImportSectionEntryType::Memory(_) => items.memories.push(ty),
+                            ImportSectionEntryType::Global(_) => items.globals.push(ty),
+                            ImportSectionEntryType::Event(_) => items.events.push(ty),
+                            _ => {}
+                        }
+                    }
+                }
+                TABLE => {
+                    let mut reader = TableSectionReader::new(section.contents, section.offset)?;
+                    for _ in 0..reader.get_count() {
+                        let start = reader.original_position();
+                        let ty = reader.read()?;
+                        if items.tables.is_empty() {
+                            items.table = Some(ty);
+                        }
+                        items.tables.push(&wasm[start..reader.original_position()]);
+                    }
+                }
+                MEMORY => {
+                    let mut reader = MemorySectionReader::new(section.contents, section.offset)?;
+                    for _ in 0..reader.get_count() {
+                        let start = reader.original_position();
+                        reader.read()?;
+                        items
+                            .memories
+                            .push(&wasm[start..reader.original_position()]);
+                    }
+                }
+                GLOBAL => {
+                    let mut reader = GlobalSectionReader::new(section.contents, section.offset)?;
+                    for _ in 0..reader.get_count() {
+                        let start = reader.original_position();
+                        let expr = reader.read()?.init_expr.get_operators_reader();
+                        items.globals.push(&wasm[start..expr.original_position()]);
+                    }
+                }
+                EVENT => {
+                    let mut reader = EventSectionReader::new(section.contents, section.offset)?;
+                    for _ in 0..reader.get_count() {
+                        let start = reader.original_position();
+                        reader.read()?;
+                        items.events.push(&wasm[start..reader.original_position()]);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(items)
+    }
+}
+
+fn encode_section(id: u8, contents: &[u8], output: &mut Vec<u8>) {
+    Section {
+        id,
+        contents,
+        offset: 0,
+    }
+    .encode(output);
+}
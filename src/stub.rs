@@ -0,0 +1,358 @@
+//! Replacing imported functions with generated stubs.
+//!
+//! Modules frequently import more host functions than they actually call,
+//! which keeps them from being instantiated in environments which don't
+//! provide all of them. [`Stubs`] turns selected function imports into
+//! functions defined by the module, which trap, return constants or forward
+//! their arguments to a function in a table.
+//!
+//! ```
+//! use wasm_tools::interp::Value;
+//! use wasm_tools::stub::{Stub, Stubs};
+//!
+//! let wasm = wat::parse_str(r#"
+//!     (module
+//!       (import "env" "random" (func (result i32)))
+//!       (import "env" "abort" (func))
+//!       (func (export "main") (result i32) call 0))
+//! "#)?;
+//! let wasm = Stubs::new()
+//!     .stub("env", "random", Stub::Return(vec![Value::I32(4)]))
+//!     .stub("env", "abort", Stub::Trap)
+//!     .apply(&wasm)?;
+//! let text = wasmprinter::print_bytes(&wasm)?;
+//! assert!(!text.contains("import"));
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::gc::{renumber, Map, Module, Renumbering};
+use crate::interp::Value;
+use crate::sections::{rank, Section};
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use wasmparser::leb128::{write_i64, write_u32};
+use wasmparser::{
+    BinaryReader, ElementSectionReader, ExportSectionReader, FuncType, GlobalSectionReader,
+    ImportSectionEntryType, ImportSectionReader, Type, TypeDef, TypeSectionReader,
+};
+
+const CUSTOM: u8 = 0;
+const TYPE: u8 = 1;
+const IMPORT: u8 = 2;
+const FUNCTION: u8 = 3;
+const GLOBAL: u8 = 6;
+const EXPORT: u8 = 7;
+const START: u8 = 8;
+const ELEMENT: u8 = 9;
+const CODE: u8 = 10;
+
+/// The body of a function which replaces an import.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Stub {
+    /// Trap with `unreachable`.
+    Trap,
+    /// Return these values, which must have the types of the import's
+    /// results.
+    Return(Vec<Value>),
+    /// Call the function in slot `index` of `table` with the stub's
+    /// arguments, which must have the same type as the import.
+    Forward { table: u32, index: u32 },
+}
+
+/// A set of imported functions to replace with stubs.
+#[derive(Clone, Debug, Default)]
+pub struct Stubs {
+    stubs: HashMap<(String, String), Stub>,
+}
+
+impl Stubs {
+    /// Creates a new set of stubs which doesn't replace any imports.
+    pub fn new() -> Stubs {
+        Stubs::default()
+    }
+
+    /// Replaces the imported function `module`.`field` with `stub`.
+    pub fn stub(&mut self, module: &str, field: &str, stub: Stub) -> &mut Stubs {
+        self.stubs
+            .insert((module.to_string(), field.to_string()), stub);
+        self
+    }
+
+    /// Applies these stubs to the module `wasm`, returning the new module.
+    ///
+    /// The stubs are defined after all of the module's functions, so every
+    /// function is renumbered. Function and local names in the `name` section
+    /// are updated, but its other subsections are removed. All other custom
+    /// sections are copied verbatim, so any indices or offsets they contain
+    /// will be stale. Stubs for imports which the module doesn't have are
+    /// ignored.
+    ///
+    /// Returns an error if a stub is given for an import which isn't a
+    /// function, or if the values of a [`Stub::Return`] don't match the
+    /// import's results. Modules using the module linking proposal aren't
+    /// supported.
+    pub fn apply(&self, wasm: &[u8]) -> Result<Vec<u8>> {
+        let module = Module::parse(wasm)?;
+        let mut types = Vec::new();
+        let mut stubbed = Vec::new();
+        for section in module.sections.iter() {
+            match section.id {
+                TYPE => {
+                    for ty in TypeSectionReader::new(section.contents, section.offset)? {
+                        types.push(match ty? {
+                            TypeDef::Func(ty) => Some(ty),
+                            _ => None,
+                        });
+                    }
+                }
+                IMPORT => {
+                    for import in ImportSectionReader::new(section.contents, section.offset)? {
+                        let import = import?;
+                        let key = (
+                            import.module.to_string(),
+                            import.field.unwrap_or("").to_string(),
+                        );
+                        let stub = match self.stubs.get(&key) {
+                            Some(stub) => stub,
+                            None => {
+                                if let ImportSectionEntryType::Function(_) = import.ty {
+                                    stubbed.push(None);
+                                }
+                                continue;
+                            }
+                        };
+                        match import.ty {
+                            ImportSectionEntryType::Function(ty) => stubbed.push(Some((ty, stub))),
+                            _ => bail!("import `{}::{}` isn't a function", key.0, key.1),
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        if stubbed.iter().all(|s| s.is_none()) {
+            return Ok(wasm.to_vec());
+        }
+
+        // Kept imports keep their order, and are followed by the module's
+        // own functions and then the stubs.
+        let kept = stubbed.iter().filter(|s| s.is_none()).count() as u32;
+        let defined = module.bodies.len() as u32;
+        let mut next_import = 0;
+        let mut next_stub = kept + defined;
+        let mut indices = Vec::new();
+        for stub in stubbed.iter() {
+            let index = match stub {
+                Some(_) => &mut next_stub,
+                None => &mut next_import,
+            };
+            indices.push(Some(*index));
+            *index += 1;
+        }
+        indices.extend((kept..kept + defined).map(Some));
+        let identity = |count: usize| renumber(&vec![true; count]);
+        let map = Map {
+            types: identity(types.len()),
+            funcs: Renumbering {
+                indices,
+                count: module.func_types.len() as u32,
+            },
+            globals: identity(module.imported_globals as usize + module.global_inits.len()),
+            data: identity(module.data.len()),
+            tables: None,
+            events: None,
+            elements: None,
+        };
+
+        let mut stub_types = Vec::new();
+        let mut stub_bodies = Vec::new();
+        for (ty, stub) in stubbed.iter().filter_map(|s| *s) {
+            let func_type = match types.get(ty as usize) {
+                Some(Some(func_type)) => func_type,
+                _ => bail!("type index {} isn't a function type", ty),
+            };
+            write_u32(&mut stub_types, ty);
+            let mut body = vec![0x00];
+            encode_stub(ty, func_type, stub, &mut body)?;
+            write_u32(&mut stub_bodies, body.len() as u32);
+            stub_bodies.extend_from_slice(&body);
+        }
+        let stubs = module.func_types.len() as u32 - kept - defined;
+
+        // The module has neither a function nor a code section if it doesn't
+        // define any functions, in which case they're added just before the
+        // first section which has to follow them.
+        let mut missing = Vec::new();
+        for (id, entries) in [(FUNCTION, &stub_types), (CODE, &stub_bodies)].iter() {
+            if !module.sections.iter().any(|s| s.id == *id) {
+                let mut contents = Vec::new();
+                write_u32(&mut contents, stubs);
+                contents.extend_from_slice(entries);
+                missing.push((*id, contents));
+            }
+        }
+        missing.reverse();
+
+        let mut output = wasm[..8].to_vec();
+        for section in module.sections.iter() {
+            if section.id != CUSTOM {
+                while let Some((id, contents)) = missing.last() {
+                    if rank(*id) > rank(section.id) {
+                        break;
+                    }
+                    encode_section(*id, contents, &mut output);
+                    missing.pop();
+                }
+            }
+
+            let mut contents = Vec::new();
+            match section.id {
+                IMPORT => {
+                    let mut reader = ImportSectionReader::new(section.contents, section.offset)?;
+                    let count = reader.get_count() - (stubbed.len() as u32 - kept);
+                    if count == 0 {
+                        continue;
+                    }
+                    write_u32(&mut contents, count);
+                    let mut func = 0;
+                    for _ in 0..reader.get_count() {
+                        let start = reader.original_position();
+                        let import = reader.read()?;
+                        if let ImportSectionEntryType::Function(_) = import.ty {
+                            func += 1;
+                            if stubbed[func - 1].is_some() {
+                                continue;
+                            }
+                        }
+                        contents.extend_from_slice(&wasm[start..reader.original_position()]);
+                    }
+                }
+                FUNCTION => {
+                    let mut reader = BinaryReader::new(section.contents);
+                    write_u32(&mut contents, reader.read_var_u32()? + stubs);
+                    contents.extend_from_slice(&section.contents[reader.current_position()..]);
+                    contents.extend_from_slice(&stub_types);
+                }
+                GLOBAL => {
+                    let mut reader = GlobalSectionReader::new(section.contents, section.offset)?;
+                    write_u32(&mut contents, reader.get_count());
+                    for _ in 0..reader.get_count() {
+                        let start = reader.original_position();
+                        let expr = reader.read()?.init_expr.get_operators_reader();
+                        contents.extend_from_slice(&wasm[start..expr.original_position()]);
+                        map.expr(wasm, expr, &mut contents)?;
+                    }
+                }
+                EXPORT => {
+                    let mut reader = ExportSectionReader::new(section.contents, section.offset)?;
+                    write_u32(&mut contents, reader.get_count());
+                    for _ in 0..reader.get_count() {
+                        map.export(wasm, &mut reader, &mut contents)?;
+                    }
+                }
+                START => {
+                    let func = BinaryReader::new(section.contents).read_var_u32()?;
+                    write_u32(&mut contents, map.funcs.get(func));
+                }
+                ELEMENT => {
+                    let mut reader = ElementSectionReader::new(section.contents, section.offset)?;
+                    write_u32(&mut contents, reader.get_count());
+                    for _ in 0..reader.get_count() {
+                        map.element(wasm, &mut reader, &mut contents)?;
+                    }
+                }
+                CODE => {
+                    write_u32(&mut contents, defined + stubs);
+                    for body in module.bodies.iter() {
+                        let ops = body.get_operators_reader()?;
+                        let mut encoded =
+                            wasm[body.range().start..ops.original_position()].to_vec();
+                        map.expr(wasm, ops, &mut encoded)?;
+                        write_u32(&mut contents, encoded.len() as u32);
+                        contents.extend_from_slice(&encoded);
+                    }
+                    contents.extend_from_slice(&stub_bodies);
+                }
+                CUSTOM if section.custom_name()? == Some("name") => {
+                    match map.names(section.contents) {
+                        Ok(names) => contents = names,
+                        Err(_) => continue,
+                    }
+                }
+                _ => {
+                    section.encode(&mut output);
+                    continue;
+                }
+            }
+            encode_section(section.id, &contents, &mut output);
+        }
+        while let Some((id, contents)) = missing.pop() {
+            encode_section(id, &contents, &mut output);
+        }
+        Ok(output)
+    }
+}
+
+/// Writes the instructions of `stub`, a function of type `ty`, to `output`.
+fn encode_stub(ty: u32, func_type: &FuncType, stub: &Stub, output: &mut Vec<u8>) -> Result<()> {
+    match stub {
+        Stub::Trap => output.push(0x00),
+        Stub::Return(values) => {
+            let types = values.iter().map(|v| match v {
+                Value::I32(_) => Type::I32,
+                Value::I64(_) => Type::I64,
+                Value::F32(_) => Type::F32,
+                Value::F64(_) => Type::F64,
+            });
+            if !types.eq(func_type.returns.iter().copied()) {
+                bail!(
+                    "stub returns {:?} but the function's results are {:?}",
+                    values,
+                    func_type.returns
+                );
+            }
+            for value in values {
+                match *value {
+                    Value::I32(x) => {
+                        output.push(0x41);
+                        write_i64(output, x.into());
+                    }
+                    Value::I64(x) => {
+                        output.push(0x42);
+                        write_i64(output, x);
+                    }
+                    Value::F32(bits) => {
+                        output.push(0x43);
+                        output.extend_from_slice(&bits.to_le_bytes());
+                    }
+                    Value::F64(bits) => {
+                        output.push(0x44);
+                        output.extend_from_slice(&bits.to_le_bytes());
+                    }
+                }
+            }
+        }
+        Stub::Forward { table, index } => {
+            for local in 0..func_type.params.len() as u32 {
+                output.push(0x20);
+                write_u32(output, local);
+            }
+            output.push(0x41);
+            write_i64(output, (*index as i32).into());
+            output.push(0x11);
+            write_u32(output, ty);
+            write_u32(output, *table);
+        }
+    }
+    output.push(0x0b);
+    Ok(())
+}
+
+fn encode_section(id: u8, contents: &[u8], output: &mut Vec<u8>) {
+    Section {
+        id,
+        contents,
+        offset: 0,
+    }
+    .encode(output);
+}
@@ -0,0 +1,327 @@
+//! A machine-readable summary of a WebAssembly module, intended for tools such
+//! as CI scripts which want to assert properties of a built module without
+//! decoding it themselves.
+
+use anyhow::Result;
+#[cfg(feature = "json")]
+use serde::Serialize;
+use wasmparser::{
+    ExternalKind, ImportSectionEntryType, MemoryType, Parser, Payload::*, SectionReader,
+    SectionWithLimitedItems, TableType, Type, Validator, WasmFeatures,
+};
+
+/// A summary of the outermost module of a WebAssembly binary.
+///
+/// Nested modules, from the module linking proposal, only contribute to the
+/// `features` and to the size of the section they're defined in.
+#[derive(Default, Debug)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct Summary {
+    /// Every section of the module, in the order they appear.
+    pub sections: Vec<Section>,
+    /// Every import of the module.
+    pub imports: Vec<Import>,
+    /// Every export of the module.
+    pub exports: Vec<Export>,
+    /// All memories, imported ones first, in index order.
+    pub memories: Vec<Memory>,
+    /// All tables, imported ones first, in index order.
+    pub tables: Vec<Table>,
+    /// The WebAssembly proposals which this module requires to validate, for
+    /// example `"simd"` or `"threads"`.
+    pub features: Vec<&'static str>,
+}
+
+/// A section of a module.
+#[derive(Debug)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct Section {
+    /// The kind of section, such as `"code"` or `"custom"`.
+    pub kind: &'static str,
+    /// The name of a custom section.
+    #[cfg_attr(feature = "json", serde(skip_serializing_if = "Option::is_none"))]
+    pub name: Option<String>,
+    /// The offset of the section's contents in the binary.
+    pub offset: usize,
+    /// The size of the section's contents in bytes.
+    pub size: usize,
+    /// The number of items in sections which are a list of items.
+    #[cfg_attr(feature = "json", serde(skip_serializing_if = "Option::is_none"))]
+    pub count: Option<u32>,
+}
+
+/// An import of a module.
+#[derive(Debug)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct Import {
+    /// The module name of this import.
+    pub module: String,
+    /// The field name of this import, absent for module linking's
+    /// single-level imports.
+    pub field: Option<String>,
+    /// The kind of item imported, such as `"func"` or `"memory"`.
+    pub kind: &'static str,
+}
+
+/// An export of a module.
+#[derive(Debug)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct Export {
+    /// The name of this export.
+    pub name: String,
+    /// The kind of item exported, such as `"func"` or `"memory"`.
+    pub kind: &'static str,
+    /// The index of the exported item in its index space.
+    pub index: u32,
+}
+
+/// The type of a linear memory.
+#[derive(Debug)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct Memory {
+    /// Whether this memory was imported.
+    pub imported: bool,
+    /// The initial size, in pages.
+    pub initial: u64,
+    /// The maximum size, in pages, if any.
+    pub maximum: Option<u64>,
+    /// Whether this memory is shared between threads.
+    pub shared: bool,
+    /// Whether this memory is indexed with 64-bit addresses.
+    pub memory64: bool,
+}
+
+/// The type of a table.
+#[derive(Debug)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct Table {
+    /// Whether this table was imported.
+    pub imported: bool,
+    /// The type of the table's elements, such as `"funcref"`.
+    pub element_type: &'static str,
+    /// The initial number of elements.
+    pub initial: u32,
+    /// The maximum number of elements, if any.
+    pub maximum: Option<u32>,
+}
+
+/// Summarizes the WebAssembly binary `wasm`.
+///
+/// Returns an error if the binary is malformed or fails to validate even with
+/// all proposals enabled.
+pub fn summarize(wasm: &[u8]) -> Result<Summary> {
+    let mut summary = Summary::default();
+    let mut depth = 0;
+    for payload in Parser::new(0).parse_all(wasm) {
+        let payload = payload?;
+        match &payload {
+            Version { .. } => depth += 1,
+            End => depth -= 1,
+            _ => {}
+        }
+        if depth != 1 {
+            continue;
+        }
+        match payload {
+            TypeSection(s) => summary.section(&s, "type"),
+            ImportSection(s) => {
+                summary.section(&s, "import");
+                for import in s {
+                    let import = import?;
+                    match import.ty {
+                        ImportSectionEntryType::Memory(ty) => summary.memory(ty, true),
+                        ImportSectionEntryType::Table(ty) => summary.table(ty, true),
+                        _ => {}
+                    }
+                    summary.imports.push(Import {
+                        module: import.module.to_string(),
+                        field: import.field.map(|s| s.to_string()),
+                        kind: import_kind(&import.ty),
+                    });
+                }
+            }
+            AliasSection(s) => summary.section(&s, "alias"),
+            InstanceSection(s) => summary.section(&s, "instance"),
+            FunctionSection(s) => summary.section(&s, "function"),
+            TableSection(s) => {
+                summary.section(&s, "table");
+                for ty in s {
+                    summary.table(ty?, false);
+                }
+            }
+            MemorySection(s) => {
+                summary.section(&s, "memory");
+                for ty in s {
+                    summary.memory(ty?, false);
+                }
+            }
+            EventSection(s) => summary.section(&s, "event"),
+            GlobalSection(s) => summary.section(&s, "global"),
+            ExportSection(s) => {
+                summary.section(&s, "export");
+                for export in s {
+                    let export = export?;
+                    summary.exports.push(Export {
+                        name: export.field.to_string(),
+                        kind: export_kind(export.kind),
+                        index: export.index,
+                    });
+                }
+            }
+            StartSection { range, .. } => summary.section_raw(range, None, "start"),
+            ElementSection(s) => summary.section(&s, "element"),
+            DataCountSection { range, .. } => summary.section_raw(range, None, "data count"),
+            DataSection(s) => summary.section(&s, "data"),
+            CodeSectionStart { range, count, .. } => {
+                summary.section_raw(range, Some(count), "code")
+            }
+            ModuleSectionStart { range, count, .. } => {
+                summary.section_raw(range, Some(count), "module")
+            }
+            CustomSection {
+                name,
+                data_offset,
+                data,
+            } => summary.sections.push(Section {
+                kind: "custom",
+                name: Some(name.to_string()),
+                offset: data_offset,
+                size: data.len(),
+                count: None,
+            }),
+            UnknownSection { range, .. } => summary.section_raw(range, None, "unknown"),
+            Version { .. } | End | CodeSectionEntry(_) | ModuleSectionEntry { .. } => {}
+        }
+    }
+    summary.features = features(wasm)?;
+    Ok(summary)
+}
+
+impl Summary {
+    fn section<T>(&mut self, section: &T, kind: &'static str)
+    where
+        T: SectionWithLimitedItems + SectionReader,
+    {
+        self.section_raw(section.range(), Some(section.get_count()), kind)
+    }
+
+    fn section_raw(&mut self, range: wasmparser::Range, count: Option<u32>, kind: &'static str) {
+        self.sections.push(Section {
+            kind,
+            name: None,
+            offset: range.start,
+            size: range.end - range.start,
+            count,
+        });
+    }
+
+    fn memory(&mut self, ty: MemoryType, imported: bool) {
+        self.memories.push(match ty {
+            MemoryType::M32 { limits, shared } => Memory {
+                imported,
+                initial: limits.initial.into(),
+                maximum: limits.maximum.map(|m| m.into()),
+                shared,
+                memory64: false,
+            },
+            MemoryType::M64 { limits, shared } => Memory {
+                imported,
+                initial: limits.initial,
+                maximum: limits.maximum,
+                shared,
+                memory64: true,
+            },
+        });
+    }
+
+    fn table(&mut self, ty: TableType, imported: bool) {
+        self.tables.push(Table {
+            imported,
+            element_type: match ty.element_type {
+                Type::FuncRef => "funcref",
+                Type::ExternRef => "externref",
+                Type::ExnRef => "exnref",
+                _ => "unknown",
+            },
+            initial: ty.limits.initial,
+            maximum: ty.limits.maximum,
+        });
+    }
+}
+
+/// Returns the proposals required to validate `wasm`.
+///
+/// A proposal is considered to be required if the module validates with all
+/// proposals enabled but fails to validate once that one is disabled.
+pub(crate) fn features(wasm: &[u8]) -> Result<Vec<&'static str>> {
+    let all = WasmFeatures {
+        reference_types: true,
+        multi_value: true,
+        bulk_memory: true,
+        module_linking: true,
+        simd: true,
+        threads: true,
+        tail_call: true,
+        multi_memory: true,
+        exceptions: true,
+        memory64: true,
+        wide_arithmetic: true,
+        function_references: true,
+        ..WasmFeatures::default()
+    };
+    let validate = |features: WasmFeatures| {
+        let mut validator = Validator::new();
+        validator.wasm_features(features);
+        validator.validate_all(wasm)
+    };
+    validate(all)?;
+
+    let proposals: &[(&str, fn(&mut WasmFeatures))] = &[
+        ("reference-types", |f| f.reference_types = false),
+        ("multi-value", |f| f.multi_value = false),
+        ("bulk-memory", |f| f.bulk_memory = false),
+        ("module-linking", |f| f.module_linking = false),
+        ("simd", |f| f.simd = false),
+        ("threads", |f| f.threads = false),
+        ("tail-call", |f| f.tail_call = false),
+        ("multi-memory", |f| f.multi_memory = false),
+        ("exceptions", |f| f.exceptions = false),
+        ("memory64", |f| f.memory64 = false),
+        ("wide-arithmetic", |f| f.wide_arithmetic = false),
+        ("function-references", |f| f.function_references = false),
+    ];
+    let mut ret = Vec::new();
+    for (name, disable) in proposals {
+        let mut features = all;
+        disable(&mut features);
+        if validate(features).is_err() {
+            ret.push(*name);
+        }
+    }
+    Ok(ret)
+}
+
+pub(crate) fn import_kind(ty: &ImportSectionEntryType) -> &'static str {
+    match ty {
+        ImportSectionEntryType::Function(_) => "func",
+        ImportSectionEntryType::Table(_) => "table",
+        ImportSectionEntryType::Memory(_) => "memory",
+        ImportSectionEntryType::Event(_) => "event",
+        ImportSectionEntryType::Global(_) => "global",
+        ImportSectionEntryType::Module(_) => "module",
+        ImportSectionEntryType::Instance(_) => "instance",
+    }
+}
+
+fn export_kind(kind: ExternalKind) -> &'static str {
+    match kind {
+        ExternalKind::Function => "func",
+        ExternalKind::Table => "table",
+        ExternalKind::Memory => "memory",
+        ExternalKind::Event => "event",
+        ExternalKind::Global => "global",
+        ExternalKind::Type => "type",
+        ExternalKind::Module => "module",
+        ExternalKind::Instance => "instance",
+    }
+}
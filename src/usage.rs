@@ -0,0 +1,322 @@
+//! A report of the operators and proposals a module uses.
+//!
+//! Engines differ in the proposals they support, so before deploying a module
+//! it's useful to know not only which proposals it requires but how heavily it
+//! relies on each of them.
+//!
+//! ```
+//! use wasm_tools::usage::usage;
+//!
+//! let wasm = wat::parse_str(r#"
+//!     (module
+//!       (func (param v128) (result v128)
+//!         local.get 0
+//!         local.get 0
+//!         i32x4.add))
+//! "#)?;
+//! let usage = usage(&wasm)?;
+//! assert_eq!(usage.opcodes["I32x4Add"], 1);
+//! assert_eq!(usage.proposals["simd"], 1);
+//! assert_eq!(usage.proposals["threads"], 0);
+//! assert_eq!(usage.required, ["simd"]);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::Result;
+#[cfg(feature = "json")]
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use wasmparser::{
+    DataKind, ElementKind, ImportSectionEntryType, MemoryType, Operator, OperatorsReader, Parser,
+    Payload, TypeOrFuncType,
+};
+
+/// The proposals which [`Usage::proposals`] counts operators of.
+pub const PROPOSALS: &[&str] = &[
+    "sign-extension",
+    "saturating-float-to-int",
+    "multi-value",
+    "reference-types",
+    "bulk-memory",
+    "simd",
+    "threads",
+    "tail-call",
+    "exceptions",
+    "memory64",
+    "multi-memory",
+    "wide-arithmetic",
+    "function-references",
+];
+
+/// The operators and proposals used by a module, see [`usage`].
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct Usage {
+    /// The number of operators in the module.
+    pub operators: u64,
+    /// The number of times each operator is used, keyed by its name in
+    /// [`wasmparser::Operator`], such as `"I32Add"`. Unused operators are
+    /// omitted.
+    pub opcodes: BTreeMap<&'static str, u64>,
+    /// The number of operators which belong to each of the [`PROPOSALS`],
+    /// including the ones which aren't used at all.
+    ///
+    /// Operators are counted for every proposal they need: a `memory.grow` of
+    /// the second memory of a module counts towards `"multi-memory"`, and also
+    /// towards `"memory64"` if that memory has 64-bit addresses.
+    pub proposals: BTreeMap<&'static str, u64>,
+    /// The proposals required to validate the module, as in
+    /// [`Summary::features`](crate::summary::Summary::features).
+    ///
+    /// This also includes proposals required by the module's types and
+    /// definitions rather than its operators, such as a shared memory.
+    pub required: Vec<&'static str>,
+}
+
+/// Counts the operators used by the WebAssembly binary `wasm`, including the
+/// operators of constant expressions and of nested modules.
+///
+/// Returns an error if the binary is malformed or fails to validate even with
+/// all proposals enabled.
+pub fn usage(wasm: &[u8]) -> Result<Usage> {
+    let mut usage = Usage {
+        proposals: PROPOSALS.iter().map(|p| (*p, 0)).collect(),
+        required: crate::summary::features(wasm)?,
+        ..Usage::default()
+    };
+    // Whether each memory has 64-bit addresses, for each module being parsed.
+    let mut memories: Vec<Vec<bool>> = Vec::new();
+    for payload in Parser::new(0).parse_all(wasm) {
+        match payload? {
+            Payload::Version { .. } => memories.push(Vec::new()),
+            Payload::End => {
+                memories.pop();
+            }
+            Payload::ImportSection(s) => {
+                for import in s {
+                    if let ImportSectionEntryType::Memory(ty) = import?.ty {
+                        memories.last_mut().unwrap().push(is_memory64(ty));
+                    }
+                }
+            }
+            Payload::MemorySection(s) => {
+                for ty in s {
+                    memories.last_mut().unwrap().push(is_memory64(ty?));
+                }
+            }
+            Payload::GlobalSection(s) => {
+                for global in s {
+                    let reader = global?.init_expr.get_operators_reader();
+                    usage.count(reader, memories.last().unwrap())?;
+                }
+            }
+            Payload::ElementSection(s) => {
+                for element in s {
+                    if let ElementKind::Active { init_expr, .. } = element?.kind {
+                        usage.count(init_expr.get_operators_reader(), memories.last().unwrap())?;
+                    }
+                }
+            }
+            Payload::DataSection(s) => {
+                for data in s {
+                    if let DataKind::Active { init_expr, .. } = data?.kind {
+                        usage.count(init_expr.get_operators_reader(), memories.last().unwrap())?;
+                    }
+                }
+            }
+            Payload::CodeSectionEntry(body) => {
+                usage.count(body.get_operators_reader()?, memories.last().unwrap())?;
+            }
+            _ => {}
+        }
+    }
+    Ok(usage)
+}
+
+fn is_memory64(ty: MemoryType) -> bool {
+    matches!(ty, MemoryType::M64 { .. })
+}
+
+impl Usage {
+    fn count(&mut self, mut reader: OperatorsReader<'_>, memory64: &[bool]) -> Result<()> {
+        while !reader.eof() {
+            let op = reader.read()?;
+            let name = operator_name(&op);
+            self.operators += 1;
+            *self.opcodes.entry(name).or_insert(0) += 1;
+            let proposals = &mut self.proposals;
+            proposals_of(name, &op, memory64, |proposal| {
+                *proposals.get_mut(proposal).unwrap() += 1;
+            });
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Usage {
+    /// Formats the usage as one line per proposal, such as
+    /// `simd: 1204 operators (required)`, followed by the operators used.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "operators: {}", self.operators)?;
+        for (proposal, count) in self.proposals.iter() {
+            write!(f, "{}: {} operators", proposal, count)?;
+            if self.required.contains(proposal) {
+                write!(f, " (required)")?;
+            }
+            writeln!(f)?;
+        }
+        for proposal in self.required.iter() {
+            if !self.proposals.contains_key(proposal) {
+                writeln!(f, "{}: required", proposal)?;
+            }
+        }
+        writeln!(f, "opcodes:")?;
+        for (name, count) in self.opcodes.iter() {
+            writeln!(f, "  {}: {}", name, count)?;
+        }
+        Ok(())
+    }
+}
+
+/// Calls `f` with each proposal which the operator `op`, named `name`, needs.
+fn proposals_of(name: &str, op: &Operator<'_>, memory64: &[bool], mut f: impl FnMut(&'static str)) {
+    // SIMD operators are named after the `v128` type or one of its lane
+    // interpretations, and all the atomic operators of the threads proposal
+    // have `Atomic` in their name.
+    const SIMD_PREFIXES: &[&str] = &["V128", "I8x16", "I16x8", "I32x4", "I64x2", "F32x4", "F64x2"];
+    if SIMD_PREFIXES.iter().any(|p| name.starts_with(p)) {
+        f("simd");
+    }
+    if name.contains("Atomic") {
+        f("threads");
+    }
+    // `memory.copy` accesses two memories but still counts once.
+    let (mut multi_memory, mut is_memory64) = (false, false);
+    memory_indices(op, |index| {
+        multi_memory |= index != 0;
+        is_memory64 |= memory64.get(index as usize) == Some(&true);
+    });
+    if multi_memory {
+        f("multi-memory");
+    }
+    if is_memory64 {
+        f("memory64");
+    }
+    match *op {
+        Operator::I32Extend8S
+        | Operator::I32Extend16S
+        | Operator::I64Extend8S
+        | Operator::I64Extend16S
+        | Operator::I64Extend32S => f("sign-extension"),
+        Operator::I32TruncSatF32S
+        | Operator::I32TruncSatF32U
+        | Operator::I32TruncSatF64S
+        | Operator::I32TruncSatF64U
+        | Operator::I64TruncSatF32S
+        | Operator::I64TruncSatF32U
+        | Operator::I64TruncSatF64S
+        | Operator::I64TruncSatF64U => f("saturating-float-to-int"),
+        Operator::Block { ty } | Operator::Loop { ty } | Operator::If { ty } => {
+            if let TypeOrFuncType::FuncType(_) = ty {
+                f("multi-value");
+            }
+        }
+        Operator::Try { .. }
+        | Operator::Catch { .. }
+        | Operator::Throw { .. }
+        | Operator::Rethrow { .. }
+        | Operator::Unwind => f("exceptions"),
+        Operator::I64Add128
+        | Operator::I64Sub128
+        | Operator::I64MulWideS
+        | Operator::I64MulWideU => f("wide-arithmetic"),
+        Operator::Let { .. } => f("function-references"),
+        Operator::ReturnCall { .. } => f("tail-call"),
+        Operator::ReturnCallIndirect { table_index, .. } => {
+            f("tail-call");
+            if table_index != 0 {
+                f("reference-types");
+            }
+        }
+        Operator::CallIndirect { table_index, .. } if table_index != 0 => f("reference-types"),
+        Operator::TypedSelect { .. }
+        | Operator::RefNull { .. }
+        | Operator::RefIsNull
+        | Operator::RefFunc { .. }
+        | Operator::TableGet { .. }
+        | Operator::TableSet { .. }
+        | Operator::TableGrow { .. }
+        | Operator::TableSize { .. }
+        | Operator::TableFill { .. } => f("reference-types"),
+        Operator::MemoryInit { .. }
+        | Operator::DataDrop { .. }
+        | Operator::MemoryCopy { .. }
+        | Operator::MemoryFill { .. }
+        | Operator::ElemDrop { .. } => f("bulk-memory"),
+        Operator::TableInit { table, .. } => {
+            f("bulk-memory");
+            if table != 0 {
+                f("reference-types");
+            }
+        }
+        Operator::TableCopy {
+            dst_table,
+            src_table,
+        } => {
+            f("bulk-memory");
+            if dst_table != 0 || src_table != 0 {
+                f("reference-types");
+            }
+        }
+        _ => {}
+    }
+}
+
+macro_rules! define_operator_name {
+    ($($op:ident $({ $($arg:ident: $argty:ty),* })? => $visit:ident)*) => {
+        /// Returns the name of `op` in the [`Operator`] enumeration.
+        fn operator_name(op: &Operator<'_>) -> &'static str {
+            match op {
+                $(Operator::$op { .. } => stringify!($op),)*
+            }
+        }
+    };
+}
+wasmparser::for_each_operator!(define_operator_name);
+
+/// Calls `f` with the index of each memory an operator accesses, given the
+/// name of one of its payload fields and its value.
+macro_rules! memory_index {
+    (memarg, $value:expr, $f:expr) => {
+        $f($value.memory)
+    };
+    (mem, $value:expr, $f:expr) => {
+        $f(*$value)
+    };
+    (src, $value:expr, $f:expr) => {
+        $f(*$value)
+    };
+    (dst, $value:expr, $f:expr) => {
+        $f(*$value)
+    };
+    ($other:ident, $value:expr, $f:expr) => {
+        let _ = $value;
+    };
+}
+
+macro_rules! define_memory_indices {
+    ($($op:ident $({ $($arg:ident: $argty:ty),* })? => $visit:ident)*) => {
+        /// Calls `f` with the index of each memory `op` accesses.
+        fn memory_indices(op: &Operator<'_>, mut f: impl FnMut(u32)) {
+            match op {
+                $(
+                    Operator::$op $({ $($arg),* })? => {
+                        $($(memory_index!($arg, $arg, f);)*)?
+                    }
+                )*
+            }
+        }
+    };
+}
+wasmparser::for_each_operator!(define_memory_indices);
@@ -0,0 +1,99 @@
+use std::collections::BTreeSet;
+use wasm_tools::analysis::CallGraph;
+
+fn graph(wat: &str) -> anyhow::Result<CallGraph> {
+    CallGraph::new(&wat::parse_str(wat)?)
+}
+
+fn set(items: &[u32]) -> BTreeSet<u32> {
+    items.iter().copied().collect()
+}
+
+#[test]
+fn direct_calls() -> anyhow::Result<()> {
+    let graph = graph(
+        r#"
+            (module
+              (import "env" "log" (func $log))
+              (func $a call $b)
+              (func $b call $log call $b)
+              (func $c call $a)
+              (func $dead call $c)
+              (start $c))
+        "#,
+    )?;
+    assert_eq!(graph.functions(), 5);
+    assert_eq!(graph.imported_functions(), 1);
+    assert_eq!(*graph.direct_callees(2), set(&[0, 2]));
+    assert_eq!(graph.callers(2), set(&[1, 2]));
+    assert_eq!(*graph.roots(), set(&[3]));
+    assert_eq!(graph.reachable(), set(&[0, 1, 2, 3]));
+    assert!(graph.can_reach(4, 0));
+    assert!(!graph.can_reach(0, 4));
+    assert_eq!(graph.reachable_from(vec![1, 100]), set(&[1, 2, 0]));
+    Ok(())
+}
+
+#[test]
+fn indirect_calls() -> anyhow::Result<()> {
+    let graph = graph(
+        r#"
+            (module
+              (type $v (func))
+              (type $v2 (func))
+              (table $t1 1 funcref)
+              (table $t2 1 funcref)
+              (global funcref (ref.func $escaped))
+              (func $in_t1)
+              (func $in_t2)
+              (func $passive)
+              (func $escaped)
+              (func $declared)
+              (func $other_type (param i32))
+              (func $uses_t1 (export "t1")
+                i32.const 0
+                call_indirect $t1 (type $v2))
+              (func $uses_t2 (export "t2")
+                i32.const 0
+                return_call_indirect $t2 (type $v))
+              (elem (table $t1) (i32.const 0) func $in_t1 $other_type)
+              (elem (table $t2) (i32.const 0) func $in_t2)
+              (elem func $passive)
+              (elem declare func $declared))
+        "#,
+    )?;
+    let anywhere = [2, 3, 4];
+    let mut t1 = vec![0];
+    t1.extend_from_slice(&anywhere);
+    let mut t2 = vec![1];
+    t2.extend_from_slice(&anywhere);
+    assert_eq!(*graph.indirect_callees(6), set(&t1));
+    assert_eq!(*graph.indirect_callees(7), set(&t2));
+    assert!(graph.direct_callees(6).is_empty());
+    assert_eq!(graph.callees(6), set(&t1));
+    assert!(!graph.reachable().contains(&5));
+    Ok(())
+}
+
+#[test]
+fn shared_tables() -> anyhow::Result<()> {
+    let graph = graph(
+        r#"
+            (module
+              (import "env" "table" (table 1 funcref))
+              (func $exported (export "f"))
+              (func $hidden)
+              (func (export "g")
+                i32.const 0
+                call_indirect (type 0)))
+        "#,
+    )?;
+    assert_eq!(*graph.indirect_callees(2), set(&[0, 2]));
+    Ok(())
+}
+
+#[test]
+fn errors() {
+    let err = graph("(module (func call 0) (func call 5))").unwrap_err();
+    assert!(err.to_string().contains("out of bounds"), "{}", err);
+}
@@ -0,0 +1,121 @@
+use wasm_tools::canonicalize::canonicalize;
+
+// A module with one custom section named "b" followed by a type section whose
+// size is padded out to five bytes.
+const PADDED: &[u8] = &[
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+    0x00, 0x03, 0x01, b'b', 0x02, // custom section "b"
+    0x01, 0x84, 0x80, 0x80, 0x80, 0x00, 0x01, 0x60, 0x00, 0x00, // type section
+    0x00, 0x03, 0x01, b'a', 0x01, // custom section "a"
+];
+
+#[test]
+fn canonical_encoding() -> anyhow::Result<()> {
+    let expected = [
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // type section
+        0x00, 0x03, 0x01, b'a', 0x01, // custom section "a"
+        0x00, 0x03, 0x01, b'b', 0x02, // custom section "b"
+    ];
+    assert_eq!(canonicalize(PADDED)?, expected);
+    Ok(())
+}
+
+#[test]
+fn idempotent() -> anyhow::Result<()> {
+    let wasm = wat::parse_str(
+        r#"
+            (module $m
+              (import "env" "f" (func $f (param i32)))
+              (memory 1)
+              (func $g (export "g") (local $x i32) (local $y i32)
+                local.get $x
+                call $f)
+              (data (i32.const 0) "hello"))
+        "#,
+    )?;
+    let canonical = canonicalize(&wasm)?;
+    assert_eq!(canonicalize(&canonical)?, canonical);
+    assert_eq!(canonicalize(PADDED)?, canonicalize(&canonicalize(PADDED)?)?);
+    Ok(())
+}
+
+#[test]
+fn layout_dependent_sections() {
+    for name in ["dylink.0", "linking", "reloc.CODE", ".debug_info"].iter() {
+        let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+        wasm.push(0);
+        wasm.push(name.len() as u8 + 1);
+        wasm.push(name.len() as u8);
+        wasm.extend_from_slice(name.as_bytes());
+        let err = canonicalize(&wasm).unwrap_err();
+        assert!(err.to_string().contains(name), "{}", err);
+    }
+}
+
+fn custom_names(wasm: &[u8]) -> Vec<String> {
+    wasmparser::Parser::new(0)
+        .parse_all(wasm)
+        .filter_map(|payload| match payload.unwrap() {
+            wasmparser::Payload::CustomSection { name, .. } => Some(name.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn branch_hints() -> anyhow::Result<()> {
+    let wasm = wat::parse_str(
+        r#"
+            (module
+              (func (param i32)
+                local.get 0
+                (@metadata.code.branch_hint "\01")
+                if
+                end))
+        "#,
+    )?;
+    let canonical = canonicalize(&wasm)?;
+    assert_eq!(custom_names(&canonical), ["metadata.code.branch_hint"]);
+    assert_eq!(canonicalize(&canonical)?, canonical);
+    Ok(())
+}
+
+#[test]
+fn comments() -> anyhow::Result<()> {
+    // A module with a function whose local declarations are padded, with
+    // comments before the module and each of the function's instructions.
+    let mut wasm = vec![
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // type section
+        0x03, 0x02, 0x01, 0x00, // function section
+        0x0a, 0x0a, 0x01, 0x08, 0x01, 0x81, 0x00, 0x7f, // code section
+        0x20, 0x00, // local.get 0
+        0x1a, // drop
+        0x0b, // end
+    ];
+    let mut comments = vec![0x11];
+    comments.extend_from_slice(b"metadata.comments");
+    comments.extend_from_slice(&[0x03, 0x00, 0x09]);
+    comments.extend_from_slice(b";; before");
+    comments.extend_from_slice(&[0x02, 0x00, 0x04, 0x06]);
+    comments.extend_from_slice(b";; get");
+    comments.extend_from_slice(&[0x02, 0x00, 0x06, 0x07]);
+    comments.extend_from_slice(b";; drop");
+    wasm.push(0x00);
+    wasm.push(comments.len() as u8);
+    wasm.extend_from_slice(&comments);
+
+    let canonical = canonicalize(&wasm)?;
+    assert_eq!(custom_names(&canonical), ["metadata.comments"]);
+    assert_eq!(canonicalize(&canonical)?, canonical);
+    let text = wasmprinter::print_bytes(&canonical)?;
+    for expected in &[
+        ";; before\n(module",
+        ";; get\n    local.get 0",
+        ";; drop\n    drop",
+    ] {
+        assert!(text.contains(expected), "{} not in {}", expected, text);
+    }
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use serde_json::json;
+use wasm_tools::capabilities::capabilities;
+
+#[test]
+fn capabilities_report() -> anyhow::Result<()> {
+    let wasm = wat::parse_str(
+        r#"
+            (module
+              (import "env" "log" (func $log (param i32)))
+              (import "env" "unused" (func))
+              (import "env" "init" (func $init))
+              (import "env" "memory" (memory 1 1 shared))
+              (import "host" "clock" (global (mut i64)))
+              (import "host" "limit" (global i32))
+              (memory (export "heap") 1)
+              (memory 1 1 shared)
+              (table (export "table") 1 funcref)
+              (global (export "state") (mut i32) (i32.const 0))
+              (global (export "constant") i32 (i32.const 0))
+              (export "shared" (memory 0))
+              (export "shared2" (memory 0))
+              (start $init)
+              (func (export "run")
+                i32.const 0
+                call $helper)
+              (func $helper (param i32)
+                local.get 0
+                call $log))
+        "#,
+    )?;
+    let report = serde_json::to_value(capabilities(&wasm)?)?;
+    assert_eq!(
+        report["imports"],
+        json!({
+            "env": [
+                {"field": "log", "kind": "func", "called": true},
+                {"field": "unused", "kind": "func", "called": false},
+                {"field": "init", "kind": "func", "called": true},
+                {"field": "memory", "kind": "memory"},
+            ],
+            "host": [
+                {"field": "clock", "kind": "global"},
+                {"field": "limit", "kind": "global"},
+            ],
+        })
+    );
+    assert_eq!(
+        report["exported_mutable_globals"],
+        json!([{"name": "state", "index": 2}])
+    );
+    assert_eq!(report["imported_mutable_globals"], json!(["host.clock"]));
+    assert_eq!(
+        report["shared_memories"],
+        json!([
+            {"index": 0, "import": "env.memory", "exports": ["shared", "shared2"]},
+            {"index": 2, "import": null, "exports": []},
+        ])
+    );
+    assert_eq!(
+        report["exported_memories"],
+        json!(["heap", "shared", "shared2"])
+    );
+    assert_eq!(report["exported_tables"], json!(["table"]));
+    assert_eq!(report["start"], json!({"index": 2, "imported": true}));
+    Ok(())
+}
+
+#[test]
+fn empty_module() -> anyhow::Result<()> {
+    let report = serde_json::to_value(capabilities(&wat::parse_str("(module)")?)?)?;
+    assert_eq!(report["imports"], json!({}));
+    assert_eq!(report["shared_memories"], json!([]));
+    assert_eq!(report["start"], json!(null));
+    Ok(())
+}
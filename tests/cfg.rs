@@ -0,0 +1,126 @@
+use wasm_tools::cfg::Cfg;
+use wasm_tools::functions::extract;
+use wasmparser::FunctionBody;
+
+fn cfg(wat: &str) -> anyhow::Result<Cfg> {
+    let wasm = wat::parse_str(wat)?;
+    let func = extract(&wasm, 0)?;
+    Cfg::new(&FunctionBody::new(func.offset, func.body))
+}
+
+/// Returns the successors of each block and whether it exits.
+fn edges(cfg: &Cfg) -> Vec<(Vec<usize>, bool)> {
+    cfg.blocks()
+        .iter()
+        .map(|b| (b.successors.clone(), b.exits))
+        .collect()
+}
+
+#[test]
+fn straight_line() -> anyhow::Result<()> {
+    let cfg = cfg("(module (func (result i32) block i32.const 1 end))")?;
+    assert_eq!(edges(&cfg), [(vec![], true)]);
+    let block = &cfg.blocks()[0];
+    assert!(block.predecessors.is_empty());
+    assert_eq!(cfg.block_at(block.start), Some(0));
+    assert_eq!(cfg.block_at(block.end - 1), Some(0));
+    assert_eq!(cfg.block_at(block.end), None);
+    Ok(())
+}
+
+#[test]
+fn loops_and_branches() -> anyhow::Result<()> {
+    let cfg = cfg(r#"
+            (module
+              (func (param i32)
+                block $exit
+                  loop $top
+                    local.get 0
+                    br_if $exit
+                    local.get 0
+                    i32.const 1
+                    i32.sub
+                    local.set 0
+                    br $top
+                  end
+                end
+                nop))
+        "#)?;
+    // The entry, the loop header, the rest of the loop body and the block
+    // after `$exit`.
+    assert_eq!(
+        edges(&cfg),
+        [
+            (vec![1], false),
+            (vec![2, 3], false),
+            (vec![1], false),
+            (vec![], true),
+        ]
+    );
+    assert_eq!(cfg.blocks()[1].predecessors, [0, 2]);
+    Ok(())
+}
+
+#[test]
+fn br_table_and_dead_code() -> anyhow::Result<()> {
+    let cfg = cfg(r#"
+            (module
+              (func (param i32) (result i32)
+                block $a
+                  block $b
+                    local.get 0
+                    br_table $a $b $a 0
+                    i32.const 0
+                    drop
+                  end
+                  i32.const 1
+                  return
+                end
+                i32.const 2))
+        "#)?;
+    // The entry, the dead code after `br_table` which falls through to the
+    // block after `$b`, and the block after `$a`.
+    assert_eq!(
+        edges(&cfg),
+        [
+            (vec![2, 3], false),
+            (vec![2], false),
+            (vec![], true),
+            (vec![], true),
+        ]
+    );
+    assert!(cfg.blocks()[1].predecessors.is_empty());
+    Ok(())
+}
+
+#[test]
+fn if_without_else() -> anyhow::Result<()> {
+    let cfg = cfg(r#"
+            (module
+              (func (param i32)
+                local.get 0
+                if
+                  unreachable
+                end
+                local.get 0
+                br_if 0))
+        "#)?;
+    assert_eq!(
+        edges(&cfg),
+        [
+            (vec![1, 2], false),
+            (vec![], false),
+            (vec![3], true),
+            (vec![], true),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn invalid_branch() {
+    let wasm = wat::parse_str("(module (func br 3))").unwrap();
+    let func = extract(&wasm, 0).unwrap();
+    let err = Cfg::new(&FunctionBody::new(func.offset, func.body)).unwrap_err();
+    assert_eq!(err.to_string(), "branch to label 3 which doesn't exist");
+}
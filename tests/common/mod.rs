@@ -0,0 +1,46 @@
+//! Helpers shared by the tests of transformations, which check that both
+//! their input and output modules are valid.
+
+// Each test only uses some of the helpers.
+#![allow(dead_code)]
+
+use wasmparser::{Validator, WasmFeatures};
+
+/// The proposals which modules given to and produced by transformations in
+/// tests may use.
+pub fn features() -> WasmFeatures {
+    WasmFeatures {
+        reference_types: true,
+        bulk_memory: true,
+        tail_call: true,
+        multi_memory: true,
+        exceptions: true,
+        ..WasmFeatures::default()
+    }
+}
+
+/// Validates `wasm` with the proposals of [`features`].
+pub fn validate(wasm: &[u8]) -> anyhow::Result<()> {
+    validate_with(wasm, features())
+}
+
+/// Validates `wasm` with only the proposals of `features`.
+pub fn validate_with(wasm: &[u8], features: WasmFeatures) -> anyhow::Result<()> {
+    let mut validator = Validator::new();
+    validator.wasm_features(features);
+    validator.validate_all(wasm)?;
+    Ok(())
+}
+
+/// Parses `wat` and applies `transform` to it, checking that the module is
+/// valid before and after.
+pub fn transform(
+    wat: &str,
+    transform: impl FnOnce(&[u8]) -> anyhow::Result<Vec<u8>>,
+) -> anyhow::Result<Vec<u8>> {
+    let wasm = wat::parse_str(wat)?;
+    validate(&wasm)?;
+    let wasm = transform(&wasm)?;
+    validate(&wasm)?;
+    Ok(wasm)
+}
@@ -0,0 +1,63 @@
+use wasm_tools::functions::{extract, replace_body};
+use wasmparser::Validator;
+
+const MODULE: &str = r#"
+    (module
+      (import "env" "f" (func $f (param i32)))
+      (type $t (func (result i32)))
+      (memory 1)
+      (table 1 funcref)
+      (global $g (mut i32) (i32.const 0))
+      (func $a (result i32) i32.const 1)
+      (func $b (local i32)
+        global.get $g
+        i32.load offset=4
+        call $f
+        i32.const 0
+        call_indirect (type $t)
+        global.set $g
+        ref.func $a
+        drop)
+      (func $c (result i32) i32.const 3)
+      (elem declare func $a)
+    )
+"#;
+
+#[test]
+fn extract_body() -> anyhow::Result<()> {
+    let wasm = wat::parse_str(MODULE)?;
+    let func = extract(&wasm, 2)?;
+    assert_eq!(func.type_index, 2);
+    assert!(func.ty.params.is_empty());
+    assert_eq!(&wasm[func.offset..][..func.body.len()], func.body);
+
+    let refs = &func.references;
+    assert_eq!(refs.functions.iter().copied().collect::<Vec<_>>(), [0, 1]);
+    assert_eq!(refs.types.iter().copied().collect::<Vec<_>>(), [0]);
+    assert_eq!(refs.tables.iter().copied().collect::<Vec<_>>(), [0]);
+    assert_eq!(refs.memories.iter().copied().collect::<Vec<_>>(), [0]);
+    assert_eq!(refs.globals.iter().copied().collect::<Vec<_>>(), [0]);
+
+    assert!(extract(&wasm, 0).is_err());
+    assert!(extract(&wasm, 4).is_err());
+    Ok(())
+}
+
+#[test]
+fn replace() -> anyhow::Result<()> {
+    let wasm = wat::parse_str(MODULE)?;
+
+    // Replace `$a` with a body which is larger than the original, and make
+    // sure the functions after it are still intact.
+    let body = [0x01, 0x01, 0x7f, 0x41, 0xe4, 0x00, 0x0b];
+    let patched = replace_body(&wasm, 1, &body)?;
+    Validator::new().validate_all(&patched)?;
+    assert_eq!(extract(&patched, 1)?.body, body);
+    assert_eq!(extract(&patched, 2)?.body, extract(&wasm, 2)?.body);
+    assert_eq!(extract(&patched, 3)?.body, extract(&wasm, 3)?.body);
+
+    // Putting the original body back gives the original module.
+    let original = extract(&wasm, 1)?.body;
+    assert_eq!(replace_body(&patched, 1, original)?, wasm);
+    Ok(())
+}
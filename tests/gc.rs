@@ -0,0 +1,102 @@
+use wasm_tools::gc::gc;
+
+mod common;
+
+fn gc_wat(wat: &str) -> anyhow::Result<String> {
+    let wasm = common::transform(wat, gc)?;
+    assert_eq!(gc(&wasm)?, wasm);
+    Ok(wasmprinter::print_bytes(&wasm)?)
+}
+
+#[test]
+fn removes_unreachable_items() -> anyhow::Result<()> {
+    let text = gc_wat(
+        r#"
+            (module
+              (import "env" "dead_import" (func $dead_import (param f64)))
+              (import "env" "live_import" (func $live_import (param i32)))
+              (import "env" "dead_global" (global $dead_global i32))
+              (type $dead_type (func (param i64 i64)))
+              (type $indirect (func (result i32)))
+              (memory 1)
+              (table 1 funcref)
+              (global $live_global (mut i32) (i32.const 0))
+              (global $other_dead_global i32 (i32.const 1))
+              (func $dead (param i64 i64)
+                call $dead_import_caller)
+              (func $dead_import_caller
+                f64.const 0
+                call $dead_import)
+              (func $main (export "main")
+                global.get $live_global
+                call $live_import
+                i32.const 0
+                call_indirect (type $indirect)
+                global.set $live_global
+                i32.const 0 i32.const 0 i32.const 4
+                memory.init $live_data)
+              (data $dead_data "dead")
+              (data $live_data "live")
+            )
+        "#,
+    )?;
+    for dead in &[
+        "dead_import",
+        "dead_global",
+        "$dead ",
+        "dead_import_caller",
+        "i64 i64",
+        "\"dead\"",
+    ] {
+        assert!(!text.contains(dead), "{} in {}", dead, text);
+    }
+    for live in &["live_import", "$main", "\"live\"", "call $live_import"] {
+        assert!(text.contains(live), "{} not in {}", live, text);
+    }
+    Ok(())
+}
+
+#[test]
+fn keeps_element_and_start_functions() -> anyhow::Result<()> {
+    let text = gc_wat(
+        r#"
+            (module
+              (table 2 funcref)
+              (func $unused)
+              (func $start)
+              (func $in_table)
+              (func $referenced)
+              (start $start)
+              (elem (i32.const 0) $in_table)
+              (elem declare func $referenced)
+            )
+        "#,
+    )?;
+    assert!(!text.contains("$unused"));
+    assert!(text.contains("(start $start)"));
+    assert!(text.contains("$in_table"));
+    assert!(text.contains("$referenced"));
+    Ok(())
+}
+
+#[test]
+fn global_initializers() -> anyhow::Result<()> {
+    let text = gc_wat(
+        r#"
+            (module
+              (import "env" "unused" (global i32))
+              (import "env" "base" (global $base i32))
+              (global $g (export "g") funcref (ref.func $f))
+              (memory 1)
+              (func $unused)
+              (func $f)
+              (data (global.get $base) "hi")
+            )
+        "#,
+    )?;
+    assert!(!text.contains("unused"));
+    assert!(text.contains("(import \"env\" \"base\" (global (;0;) i32))"));
+    assert!(text.contains("(global.get 0)"));
+    assert!(text.contains("ref.func $f"));
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use wasm_tools::init::{initialize, Imports, TableEntry};
+use wasm_tools::interp::{Trap, Unsupported, Value};
+
+#[test]
+fn segments_and_globals() -> anyhow::Result<()> {
+    let wasm = wat::parse_str(
+        r#"
+            (module
+              (import "env" "base" (global $base i32))
+              (import "env" "f" (func $f))
+              (global $end i32 (i32.add (global.get $base) (i32.const 4)))
+              (global i64 (i64.mul (i64.const 3) (i64.const -2)))
+              (global funcref (ref.null func))
+              (memory 1)
+              (table 4 funcref)
+              (func $g)
+              (data (global.get $base) "ab")
+              (data (global.get $end) "cd")
+              (data "unused")
+              (elem (i32.const 1) func $g $f)
+              (elem (offset i32.const 4 i32.const 1 i32.sub) funcref (ref.null func))
+              (elem declare func $g))
+        "#,
+    )?;
+    let state = initialize(&wasm, Imports::new().global("env", "base", Value::I32(16)))?;
+    assert_eq!(
+        state.globals,
+        [
+            Some(Value::I32(16)),
+            Some(Value::I32(20)),
+            Some(Value::I64(-6)),
+            None,
+        ]
+    );
+    assert_eq!(state.memories.len(), 1);
+    assert_eq!(state.memories[0].len(), 65536);
+    assert_eq!(&state.memories[0][14..24], b"\0\0ab\0\0cd\0\0");
+    assert!(!state.memories[0].windows(6).any(|w| w == b"unused"));
+    assert_eq!(
+        state.tables,
+        [vec![
+            TableEntry::Null,
+            TableEntry::Func(1),
+            TableEntry::Func(0),
+            TableEntry::Null,
+        ]]
+    );
+    Ok(())
+}
+
+#[test]
+fn imported_memories_and_tables() -> anyhow::Result<()> {
+    let wasm = wat::parse_str(
+        r#"
+            (module
+              (import "env" "memory" (memory 1))
+              (import "env" "table" (table 2 funcref))
+              (import "env" "empty" (memory 0))
+              (data (memory 0) (i32.const 2) "x"))
+        "#,
+    )?;
+    let state = initialize(
+        &wasm,
+        Imports::new()
+            .memory("env", "memory", b"abcd".to_vec())
+            .table("env", "table", vec![TableEntry::Func(7); 3]),
+    )?;
+    assert_eq!(&state.memories[0][..5], b"abxd\0");
+    assert_eq!(state.memories[1].len(), 0);
+    assert_eq!(state.tables[0], [TableEntry::Func(7); 3]);
+    Ok(())
+}
+
+#[test]
+fn errors() -> anyhow::Result<()> {
+    let wasm = wat::parse_str(r#"(module (memory 1) (data (i32.const 65535) "ab"))"#)?;
+    let err = initialize(&wasm, &Imports::new()).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<Trap>().unwrap().message(),
+        "out of bounds memory access"
+    );
+
+    let wasm = wat::parse_str(r#"(module (table 1 funcref) (func) (elem (i32.const 1) 0))"#)?;
+    let err = initialize(&wasm, &Imports::new()).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<Trap>().unwrap().message(),
+        "out of bounds table access"
+    );
+
+    let wasm = wat::parse_str(r#"(module (import "env" "g" (global i32)))"#)?;
+    let err = initialize(&wasm, &Imports::new()).unwrap_err();
+    assert!(err.downcast_ref::<Unsupported>().is_some());
+    assert_eq!(
+        err.to_string(),
+        "unsupported by the interpreter: imported global `env::g` without a value"
+    );
+    Ok(())
+}
@@ -0,0 +1,158 @@
+use wasm_tools::instrument::Instrument;
+
+mod common;
+
+fn instrument_wat(instrument: &Instrument, wat: &str) -> anyhow::Result<String> {
+    let wasm = common::transform(wat, |wasm| instrument.apply(wasm))?;
+    wasmprinter::print_bytes(&wasm)
+}
+
+/// Returns the instructions of the function named `name` in `text`, one per
+/// line.
+fn body<'a>(text: &'a str, name: &str) -> Vec<&'a str> {
+    let start = text.find(&format!("(func ${} ", name)).unwrap();
+    text[start..]
+        .lines()
+        .skip(1)
+        .take_while(|line| !line.trim_start().starts_with("(func"))
+        .map(|line| match line.trim() {
+            line if line.ends_with(";)") => line,
+            line => line.trim_end_matches(')'),
+        })
+        .filter(|line| !line.starts_with('(') && !line.is_empty())
+        .collect()
+}
+
+#[test]
+fn entry_and_exit() -> anyhow::Result<()> {
+    let text = instrument_wat(
+        Instrument::new().entry(true).exit(true),
+        r#"
+            (module
+              (import "env" "f" (func $imported))
+              (func $main (param i32) (result i32)
+                block
+                  local.get 0
+                  br_if 0
+                  i32.const 1
+                  return
+                end
+                block
+                  i32.const 2
+                  br 1
+                end
+                i32.const 3))
+        "#,
+    )?;
+    assert!(text.contains(r#"(import "instrument" "enter" (func (;1;) (type 2)))"#));
+    assert!(text.contains(r#"(import "instrument" "exit" (func (;2;) (type 2)))"#));
+    assert_eq!(
+        body(&text, "main"),
+        [
+            "i32.const 3",
+            "call 1",
+            "block  ;; label = @1",
+            "local.get 0",
+            "br_if 0 (;@1;)",
+            "i32.const 1",
+            "i32.const 3",
+            "call 2",
+            "return",
+            "end",
+            "block  ;; label = @1",
+            "i32.const 2",
+            "i32.const 3",
+            "call 2",
+            "br 1 (;@0;)",
+            "end",
+            "i32.const 3",
+            "i32.const 3",
+            "call 2",
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn fuel() -> anyhow::Result<()> {
+    let text = instrument_wat(
+        Instrument::new().module("host").fuel(Some(2)),
+        r#"
+            (module
+              (func $main
+                nop
+                nop
+                nop
+                nop))
+        "#,
+    )?;
+    assert!(text.contains(r#"(import "host" "fuel" (func (;0;) (type 1)))"#));
+    assert_eq!(
+        body(&text, "main"),
+        [
+            "nop",
+            "nop",
+            "i32.const 2",
+            "call 0",
+            "nop",
+            "nop",
+            "i32.const 2",
+            "call 0"
+        ]
+    );
+
+    let wasm = wat::parse_str("(module)")?;
+    assert!(Instrument::new().fuel(Some(0)).apply(&wasm).is_err());
+    Ok(())
+}
+
+#[test]
+fn renumbers_functions() -> anyhow::Result<()> {
+    let text = instrument_wat(
+        Instrument::new().entry(true),
+        r#"
+            (module
+              (table 1 funcref)
+              (global funcref (ref.func $callee))
+              (func $callee)
+              (func $start
+                call $callee
+                ref.func $callee
+                drop
+                return_call $callee)
+              (start $start)
+              (elem (i32.const 0) $callee)
+              (export "callee" (func $callee)))
+        "#,
+    )?;
+    for expected in &[
+        "(global (;0;) funcref (ref.func $callee))",
+        "call $callee",
+        "ref.func $callee",
+        "return_call $callee",
+        "(start $start)",
+        "(elem (;0;) (i32.const 0) func $callee)",
+        "(export \"callee\" (func $callee))",
+        "(func $callee (type 0)\n    i32.const 1\n    call 0",
+    ] {
+        assert!(text.contains(expected), "{} not in {}", expected, text);
+    }
+    Ok(())
+}
+
+#[test]
+fn nothing_to_instrument() -> anyhow::Result<()> {
+    let wasm = wat::parse_str("(module (func))")?;
+    assert_eq!(Instrument::new().apply(&wasm)?, wasm);
+    Ok(())
+}
+
+#[test]
+fn adds_type_and_import_sections() -> anyhow::Result<()> {
+    let text = instrument_wat(
+        Instrument::new().exit(true),
+        r#"(module (memory 1) (export "m" (memory 0)))"#,
+    )?;
+    assert!(text.contains(r#"(import "instrument" "exit" (func (;0;) (type 0)))"#));
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use wasm_tools::integrity::{embed, embedded, hash, verify, SECTION_NAME};
+use wasm_tools::rename::Rename;
+
+const MODULE: &str = r#"
+    (module
+      (@custom "before" (before first) "a")
+      (memory 1)
+      (func (export "f") (result i32) i32.const 1)
+      (data (i32.const 0) "hello"))
+"#;
+
+#[test]
+fn embed_and_verify() -> anyhow::Result<()> {
+    let wasm = wat::parse_str(MODULE)?;
+    assert!(embedded(&wasm)?.is_none());
+    assert!(verify(&wasm).is_err());
+
+    let embedded_wasm = embed(&wasm)?;
+    assert_eq!(embedded(&embedded_wasm)?, Some(hash(&wasm)?));
+    assert_eq!(hash(&embedded_wasm)?, hash(&wasm)?);
+    verify(&embedded_wasm)?;
+    wasmparser::validate(&embedded_wasm)?;
+
+    // Embedding again replaces the hash rather than adding another one.
+    assert_eq!(embed(&embedded_wasm)?, embedded_wasm);
+    Ok(())
+}
+
+#[test]
+fn custom_sections_are_not_hashed() -> anyhow::Result<()> {
+    let wasm = embed(&wat::parse_str(MODULE)?)?;
+    let mut with_custom = wasm.clone();
+    with_custom.extend_from_slice(&[0, 4, 3, b'a', b'b', b'c']);
+    verify(&with_custom)?;
+
+    let stripped = wat::parse_str(
+        r#"
+        (module
+          (memory 1)
+          (func (export "f") (result i32) i32.const 1)
+          (data (i32.const 0) "hello"))
+        "#,
+    )?;
+    assert_eq!(hash(&stripped)?, hash(&wasm)?);
+    Ok(())
+}
+
+#[test]
+fn changes_are_detected() -> anyhow::Result<()> {
+    let wasm = embed(&wat::parse_str(MODULE)?)?;
+    let renamed = Rename::new().export("f", "g").apply(&wasm)?;
+    let err = verify(&renamed).unwrap_err().to_string();
+    assert!(err.contains("doesn't match its embedded hash"), "{}", err);
+
+    let pos = wasm
+        .windows(5)
+        .position(|w| w == b"hello")
+        .expect("data segment");
+    let mut changed = wasm.clone();
+    changed[pos] = b'j';
+    assert!(verify(&changed).is_err());
+    Ok(())
+}
+
+#[test]
+fn malformed_section() -> anyhow::Result<()> {
+    let mut wasm = wat::parse_str("(module)")?;
+    let mut contents = vec![SECTION_NAME.len() as u8];
+    contents.extend_from_slice(SECTION_NAME.as_bytes());
+    contents.extend_from_slice(b"\x03md5");
+    wasm.push(0);
+    wasm.push(contents.len() as u8);
+    wasm.extend_from_slice(&contents);
+    let err = embedded(&wasm).unwrap_err().to_string();
+    assert!(err.contains("unknown hash algorithm `md5`"), "{}", err);
+
+    let once = embed(&wat::parse_str("(module)")?)?;
+    let mut duplicated = once.clone();
+    duplicated.extend_from_slice(&once[8..]);
+    assert!(embedded(&duplicated).is_err());
+    Ok(())
+}
@@ -0,0 +1,284 @@
+use wasm_tools::interp::{run_wast, Instance, Trap, Unsupported, Value, WastStats};
+
+fn instantiate(wat: &str) -> anyhow::Result<Instance> {
+    Instance::new(&wat::parse_str(wat)?)
+}
+
+fn trap_message(result: anyhow::Result<Vec<Value>>) -> String {
+    let err = result.unwrap_err();
+    err.downcast_ref::<Trap>()
+        .unwrap_or_else(|| panic!("not a trap: {:?}", err))
+        .message()
+        .to_string()
+}
+
+#[test]
+fn constant_globals() -> anyhow::Result<()> {
+    let instance = instantiate(
+        r#"
+            (module
+              (global $a i32 (i32.const 7))
+              (global (export "a") i32 (global.get $a))
+              (global (export "b") i64 (i64.const -1))
+              (global (export "c") f32 (f32.const 1.5))
+              (global (export "d") (mut f64) (f64.const -0)))
+        "#,
+    )?;
+    assert_eq!(instance.global("a"), Some(Value::I32(7)));
+    assert_eq!(instance.global("b"), Some(Value::I64(-1)));
+    assert_eq!(instance.global("c"), Some(Value::F32(1.5f32.to_bits())));
+    assert_eq!(instance.global("d"), Some(Value::F64((-0.0f64).to_bits())));
+    assert_eq!(instance.global("missing"), None);
+    Ok(())
+}
+
+#[test]
+fn numeric_functions() -> anyhow::Result<()> {
+    let mut instance = instantiate(
+        r#"
+            (module
+              (global $counter (mut i32) (i32.const 0))
+              (func $square (param i64) (result i64)
+                local.get 0
+                local.get 0
+                i64.mul)
+              (func (export "hypot2") (param i64 i64) (result i64)
+                (i64.add (call $square (local.get 0)) (call $square (local.get 1))))
+              (func (export "bump") (result i32)
+                (local $old i32)
+                (local.tee $old (global.get $counter))
+                (global.set $counter (i32.add (local.get $old) (i32.const 1))))
+              (func (export "pick") (param i32) (result f64)
+                (select (f64.const 1) (f64.const 2) (local.get 0)))
+              (func (export "convert") (param f32) (result i32 i64)
+                (i32.trunc_sat_f32_s (local.get 0))
+                (i64.extend_i32_u (i32.const -1))))
+        "#,
+    )?;
+    assert_eq!(
+        instance.invoke("hypot2", &[Value::I64(3), Value::I64(4)])?,
+        [Value::I64(25)]
+    );
+    assert_eq!(instance.invoke("bump", &[])?, [Value::I32(0)]);
+    assert_eq!(instance.invoke("bump", &[])?, [Value::I32(1)]);
+    assert_eq!(
+        instance.invoke("pick", &[Value::I32(0)])?,
+        [Value::F64(2f64.to_bits())]
+    );
+    assert_eq!(
+        instance.invoke("convert", &[Value::F32(1e10f32.to_bits())])?,
+        [Value::I32(i32::MAX), Value::I64(0xffff_ffff)]
+    );
+    assert!(instance.invoke("hypot2", &[Value::I32(3)]).is_err());
+    assert!(instance.invoke("missing", &[]).is_err());
+    Ok(())
+}
+
+#[test]
+fn traps() -> anyhow::Result<()> {
+    let mut instance = instantiate(
+        r#"
+            (module
+              (func (export "div") (param i32 i32) (result i32)
+                (i32.div_s (local.get 0) (local.get 1)))
+              (func (export "rem") (param i32 i32) (result i32)
+                (i32.rem_s (local.get 0) (local.get 1)))
+              (func (export "trunc") (param f64) (result i32)
+                (i32.trunc_f64_u (local.get 0)))
+              (func (export "unreachable")
+                unreachable)
+              (func $recurse (export "recurse")
+                call $recurse))
+        "#,
+    )?;
+    let div =
+        |instance: &mut Instance, a, b| instance.invoke("div", &[Value::I32(a), Value::I32(b)]);
+    assert_eq!(
+        trap_message(div(&mut instance, 1, 0)),
+        "integer divide by zero"
+    );
+    assert_eq!(
+        trap_message(div(&mut instance, i32::MIN, -1)),
+        "integer overflow"
+    );
+    assert_eq!(
+        instance.invoke("rem", &[Value::I32(i32::MIN), Value::I32(-1)])?,
+        [Value::I32(0)]
+    );
+    let trunc =
+        |instance: &mut Instance, x: f64| instance.invoke("trunc", &[Value::F64(x.to_bits())]);
+    assert_eq!(trunc(&mut instance, -0.9)?, [Value::I32(0)]);
+    assert_eq!(trunc(&mut instance, 4294967295.9)?, [Value::I32(-1)]);
+    assert_eq!(
+        trap_message(trunc(&mut instance, 4294967296.0)),
+        "integer overflow"
+    );
+    assert_eq!(trap_message(trunc(&mut instance, -1.0)), "integer overflow");
+    assert_eq!(
+        trap_message(trunc(&mut instance, f64::NAN)),
+        "invalid conversion to integer"
+    );
+    assert_eq!(
+        trap_message(instance.invoke("unreachable", &[])),
+        "unreachable"
+    );
+    assert_eq!(
+        trap_message(instance.invoke("recurse", &[])),
+        "call stack exhausted"
+    );
+    Ok(())
+}
+
+#[test]
+fn float_edge_cases() -> anyhow::Result<()> {
+    let mut instance = instantiate(
+        r#"
+            (module
+              (func (export "min") (param f32 f32) (result f32)
+                (f32.min (local.get 0) (local.get 1)))
+              (func (export "max") (param f64 f64) (result f64)
+                (f64.max (local.get 0) (local.get 1)))
+              (func (export "nearest") (param f64) (result f64)
+                (f64.nearest (local.get 0)))
+              (func (export "neg") (param f32) (result f32)
+                (f32.neg (local.get 0))))
+        "#,
+    )?;
+    let f32s = |a: f32, b: f32| [Value::F32(a.to_bits()), Value::F32(b.to_bits())];
+    let f64s = |a: f64, b: f64| [Value::F64(a.to_bits()), Value::F64(b.to_bits())];
+    assert_eq!(
+        instance.invoke("min", &f32s(0.0, -0.0))?,
+        [Value::F32((-0.0f32).to_bits())]
+    );
+    assert_eq!(
+        instance.invoke("max", &f64s(-0.0, 0.0))?,
+        [Value::F64(0f64.to_bits())]
+    );
+    match instance.invoke("min", &f32s(f32::NAN, 1.0))?[..] {
+        [Value::F32(bits)] => assert!(f32::from_bits(bits).is_nan()),
+        ref other => panic!("unexpected results {:?}", other),
+    }
+    for (x, rounded) in [(2.5, 2.0), (3.5, 4.0), (-0.5, -0.0), (1.25, 1.0)].iter() {
+        assert_eq!(
+            instance.invoke("nearest", &[Value::F64(f64::to_bits(*x))])?,
+            [Value::F64(f64::to_bits(*rounded))]
+        );
+    }
+    // Negation flips the sign of NaNs too, without touching the payload.
+    assert_eq!(
+        instance.invoke("neg", &[Value::F32(0x7fc0_0001)])?,
+        [Value::F32(0xffc0_0001)]
+    );
+    Ok(())
+}
+
+#[test]
+fn segment_offsets() -> anyhow::Result<()> {
+    instantiate(
+        r#"
+            (module
+              (global $offset i32 (i32.const 65532))
+              (memory 1)
+              (data (global.get $offset) "abcd"))
+        "#,
+    )?;
+    let err = instantiate(
+        r#"
+            (module
+              (memory 1)
+              (data (i32.const 65533) "abcd"))
+        "#,
+    )
+    .err()
+    .unwrap();
+    assert_eq!(
+        err.downcast_ref::<Trap>().unwrap().message(),
+        "out of bounds memory access"
+    );
+    let err = instantiate(
+        r#"
+            (module
+              (table 1 funcref)
+              (func $f)
+              (elem (i32.const 1) $f))
+        "#,
+    )
+    .err()
+    .unwrap();
+    assert_eq!(
+        err.downcast_ref::<Trap>().unwrap().message(),
+        "out of bounds table access"
+    );
+    Ok(())
+}
+
+#[test]
+fn unsupported() -> anyhow::Result<()> {
+    let err = instantiate(r#"(module (import "env" "f" (func)))"#)
+        .err()
+        .unwrap();
+    assert!(err.is::<Unsupported>());
+
+    let mut instance = instantiate(
+        r#"
+            (module
+              (func (export "loop")
+                (loop)))
+        "#,
+    )?;
+    assert!(instance
+        .invoke("loop", &[])
+        .unwrap_err()
+        .is::<Unsupported>());
+    Ok(())
+}
+
+#[test]
+fn wast_script() -> anyhow::Result<()> {
+    let stats = run_wast(
+        r#"
+            (module $m
+              (func (export "add") (param i32 i32) (result i32)
+                (i32.add (local.get 0) (local.get 1)))
+              (func (export "div") (param i64 i64) (result i64)
+                (i64.div_u (local.get 0) (local.get 1)))
+              (func (export "sqrt") (param f32) (result f32)
+                (f32.sqrt (local.get 0)))
+              (global (export "g") f64 (f64.const 0.5)))
+
+            (assert_return (invoke "add" (i32.const 1) (i32.const 2)) (i32.const 3))
+            (assert_return (invoke $m "div" (i64.const -1) (i64.const 2))
+              (i64.const 0x7fffffffffffffff))
+            (assert_return (invoke "sqrt" (f32.const -1)) (f32.const nan:canonical))
+            (assert_return (invoke "sqrt" (f32.const 4)) (f32.const 2))
+            (assert_return (get "g") (f64.const 0.5))
+            (assert_trap (invoke "div" (i64.const 1) (i64.const 0)) "integer divide by zero")
+            (invoke "add" (i32.const 0) (i32.const 0))
+
+            ;; Control flow isn't supported, so only the module runs.
+            (module (func (export "f") (block)))
+            (assert_return (invoke "f"))
+            (assert_invalid (module (func (result i32))) "type mismatch")
+        "#,
+    )?;
+    assert_eq!(
+        stats,
+        WastStats {
+            passed: 9,
+            skipped: 2
+        }
+    );
+
+    let err = run_wast(
+        r#"
+            (module (func (export "one") (result i32) (i32.const 1)))
+            (assert_return (invoke "one") (i32.const 2))
+        "#,
+    )
+    .unwrap_err();
+    assert_eq!(
+        format!("{:#}", err),
+        "failed directive on line 3 column 14: expected I32(2), got I32(1)"
+    );
+    Ok(())
+}
@@ -0,0 +1,323 @@
+use wasm_encoder::*;
+use wasm_tools::link::Linker;
+
+mod common;
+
+/// A LEB128 of zero padded to 5 bytes, as reserved for relocated values.
+const PADDED: [u8; 5] = [0x80, 0x80, 0x80, 0x80, 0x00];
+
+/// Encodes a code section with the function bodies `bodies`, returning its
+/// contents and the offset of each body within them.
+fn code(bodies: &[&[u8]]) -> (Vec<u8>, Vec<u32>) {
+    let mut contents = vec![bodies.len() as u8];
+    let mut offsets = Vec::new();
+    for body in bodies {
+        contents.push(body.len() as u8);
+        offsets.push(contents.len() as u32);
+        contents.extend_from_slice(body);
+    }
+    (contents, offsets)
+}
+
+fn import_memory(imports: &mut ImportSection) {
+    imports.import(
+        "env",
+        Some("__linear_memory"),
+        MemoryType {
+            limits: Limits { min: 0, max: None },
+        },
+    );
+}
+
+/// An object whose function `main` loads the data symbol `msg` and passes it
+/// to the undefined function `add`.
+fn main_object() -> Vec<u8> {
+    let mut module = Module::new();
+    let mut types = TypeSection::new();
+    types.function(vec![], vec![ValType::I32]);
+    types.function(vec![ValType::I32, ValType::I32], vec![ValType::I32]);
+    module.section(&types);
+    let mut imports = ImportSection::new();
+    import_memory(&mut imports);
+    imports.import("env", Some("add"), EntityType::Function(1));
+    module.section(&imports);
+    let mut funcs = FunctionSection::new();
+    funcs.function(0);
+    module.section(&funcs);
+
+    let mut body = vec![0x00, 0x41];
+    body.extend_from_slice(&PADDED);
+    body.extend_from_slice(&[0x28, 0x02, 0x00, 0x41, 0x02, 0x10]);
+    body.extend_from_slice(&PADDED);
+    body.push(0x0b);
+    let (contents, offsets) = code(&[&body]);
+    module.section(&RawSection {
+        id: SectionId::Code.into(),
+        data: &contents,
+    });
+
+    let mut symbols = SymbolTable::new();
+    symbols.function(0, 1, Some("main"));
+    symbols.function(SymbolTable::UNDEFINED, 0, None);
+    symbols.data(SymbolTable::UNDEFINED, "msg", None);
+    let mut linking = LinkingSection::new();
+    linking.symbol_table(&symbols);
+    module.section(&linking);
+    let mut relocs = RelocSection::new("reloc.CODE", 3);
+    relocs.entry(RelocType::MemoryAddrSLEB, offsets[0] + 2, 2, Some(0));
+    relocs.entry(RelocType::FunctionIndexLEB, offsets[0] + 13, 1, None);
+    module.section(&relocs);
+    module.finish()
+}
+
+/// An object defining the function `add` and the data symbol `msg`, which
+/// follows an unaligned segment.
+fn lib_object() -> Vec<u8> {
+    let mut module = Module::new();
+    let mut types = TypeSection::new();
+    types.function(vec![ValType::I32, ValType::I32], vec![ValType::I32]);
+    module.section(&types);
+    let mut imports = ImportSection::new();
+    import_memory(&mut imports);
+    module.section(&imports);
+    let mut funcs = FunctionSection::new();
+    funcs.function(0);
+    module.section(&funcs);
+    let mut code = CodeSection::new();
+    let mut f = Function::new(vec![]);
+    f.instruction(Instruction::LocalGet(0));
+    f.instruction(Instruction::LocalGet(1));
+    f.instruction(Instruction::I32Add);
+    f.instruction(Instruction::End);
+    code.function(&f);
+    module.section(&code);
+    let mut data = DataSection::new();
+    data.active(0, Instruction::I32Const(0), vec![1, 2, 3]);
+    data.active(0, Instruction::I32Const(3), 40u32.to_le_bytes().to_vec());
+    module.section(&data);
+
+    let mut symbols = SymbolTable::new();
+    symbols.function(0, 0, Some("add"));
+    symbols.data(
+        0,
+        "msg",
+        Some(DataSymbolDefinition {
+            index: 1,
+            offset: 0,
+            size: 4,
+        }),
+    );
+    let mut linking = LinkingSection::new();
+    linking.segment_info(&[
+        SegmentInfo {
+            name: ".data.pad",
+            alignment: 0,
+            flags: 0,
+        },
+        SegmentInfo {
+            name: ".data.msg",
+            alignment: 2,
+            flags: 0,
+        },
+    ]);
+    linking.symbol_table(&symbols);
+    module.section(&linking);
+    module.finish()
+}
+
+/// An object defining the function `f`, returning `value`.
+fn f_object(flags: u32, value: i32) -> Vec<u8> {
+    let mut module = Module::new();
+    let mut types = TypeSection::new();
+    types.function(vec![], vec![ValType::I32]);
+    module.section(&types);
+    let mut funcs = FunctionSection::new();
+    funcs.function(0);
+    module.section(&funcs);
+    let mut code = CodeSection::new();
+    let mut f = Function::new(vec![]);
+    f.instruction(Instruction::I32Const(value));
+    f.instruction(Instruction::End);
+    code.function(&f);
+    module.section(&code);
+    let mut symbols = SymbolTable::new();
+    symbols.function(flags, 0, Some("f"));
+    let mut linking = LinkingSection::new();
+    linking.symbol_table(&symbols);
+    module.section(&linking);
+    module.finish()
+}
+
+#[test]
+fn resolves_symbols_and_lays_out_data() -> anyhow::Result<()> {
+    let wasm = Linker::new()
+        .export("main")
+        .link(&[&main_object(), &lib_object()])?;
+    common::validate(&wasm)?;
+    let text = wasmprinter::print_bytes(&wasm)?;
+    // `msg` follows the 3 bytes of the first segment, aligned to 4 bytes.
+    assert!(text.contains("i32.const 1028\n"), "{}", text);
+    assert!(text.contains("call 1)"), "{}", text);
+    assert!(text.contains("(data (;0;) (i32.const 1024) \"\\01\\02\\03\")"));
+    assert!(text.contains("(data (;1;) (i32.const 1028) \"(\\00\\00\\00\")"));
+    assert!(text.contains("(memory (;0;) 2)"), "{}", text);
+    assert!(text.contains("(export \"main\" (func 0))"), "{}", text);
+    assert!(!text.contains("import"), "{}", text);
+    Ok(())
+}
+
+#[test]
+fn imports_undefined_functions() -> anyhow::Result<()> {
+    let err = Linker::new().link(&[&main_object()]).unwrap_err();
+    assert_eq!(
+        format!("{:#}", err),
+        "in object 0: undefined data symbol `msg`"
+    );
+
+    // Without `lib_object`, `add` is imported but `msg` must still come from
+    // somewhere, so define it alone.
+    let mut module = Module::new();
+    let mut data = DataSection::new();
+    data.active(0, Instruction::I32Const(0), vec![0; 4]);
+    module.section(&data);
+    let mut symbols = SymbolTable::new();
+    symbols.data(
+        0,
+        "msg",
+        Some(DataSymbolDefinition {
+            index: 0,
+            offset: 0,
+            size: 4,
+        }),
+    );
+    let mut linking = LinkingSection::new();
+    linking.symbol_table(&symbols);
+    module.section(&linking);
+    let msg = module.finish();
+
+    let wasm = Linker::new().link(&[&main_object(), &msg])?;
+    common::validate(&wasm)?;
+    let text = wasmprinter::print_bytes(&wasm)?;
+    assert!(text.contains("(import \"env\" \"add\" (func (;0;) (type 1)))"));
+    assert!(text.contains("i32.const 1024\n"), "{}", text);
+    assert!(text.contains("call 0)"), "{}", text);
+    Ok(())
+}
+
+#[test]
+fn weak_symbols() -> anyhow::Result<()> {
+    let weak = f_object(SymbolTable::BINDING_WEAK, 1);
+    let strong = f_object(0, 2);
+
+    let wasm = Linker::new().export("f").link(&[&weak, &strong])?;
+    common::validate(&wasm)?;
+    let text = wasmprinter::print_bytes(&wasm)?;
+    assert!(text.contains("(export \"f\" (func 1))"), "{}", text);
+
+    let wasm = Linker::new().export("f").link(&[&weak, &weak])?;
+    let text = wasmprinter::print_bytes(&wasm)?;
+    assert!(text.contains("(export \"f\" (func 0))"), "{}", text);
+
+    let err = Linker::new().link(&[&strong, &strong]).unwrap_err();
+    assert_eq!(err.to_string(), "duplicate symbol `f` in objects 0 and 1");
+    Ok(())
+}
+
+#[test]
+fn table_stack_pointer_and_ctors() -> anyhow::Result<()> {
+    let mut module = Module::new();
+    let mut types = TypeSection::new();
+    types.function(vec![], vec![]);
+    types.function(vec![], vec![ValType::I32]);
+    module.section(&types);
+    let mut imports = ImportSection::new();
+    import_memory(&mut imports);
+    imports.import(
+        "env",
+        Some("__indirect_function_table"),
+        TableType {
+            element_type: ValType::FuncRef,
+            limits: Limits { min: 0, max: None },
+        },
+    );
+    imports.import(
+        "env",
+        Some("__stack_pointer"),
+        GlobalType {
+            val_type: ValType::I32,
+            mutable: true,
+        },
+    );
+    module.section(&imports);
+    let mut funcs = FunctionSection::new();
+    funcs.function(0);
+    funcs.function(1);
+    funcs.function(1);
+    module.section(&funcs);
+
+    // `init` does nothing, `callback` returns the stack pointer and `main`
+    // calls `callback` indirectly.
+    let init: &[u8] = &[0x00, 0x0b];
+    let mut callback = vec![0x00, 0x23];
+    callback.extend_from_slice(&PADDED);
+    callback.push(0x0b);
+    let mut main = vec![0x00, 0x41];
+    main.extend_from_slice(&PADDED);
+    main.push(0x11);
+    main.extend_from_slice(&PADDED);
+    main.extend_from_slice(&PADDED);
+    main.push(0x0b);
+    let (contents, offsets) = code(&[init, &callback, &main]);
+    module.section(&RawSection {
+        id: SectionId::Code.into(),
+        data: &contents,
+    });
+
+    let mut symbols = SymbolTable::new();
+    symbols.function(SymbolTable::BINDING_LOCAL, 0, Some("init"));
+    symbols.function(SymbolTable::BINDING_LOCAL, 1, Some("callback"));
+    symbols.function(SymbolTable::EXPORTED, 2, Some("main"));
+    symbols.global(SymbolTable::UNDEFINED, 0, None);
+    symbols.table(SymbolTable::UNDEFINED, 0, None);
+    let mut linking = LinkingSection::new();
+    linking.init_funcs(&[InitFunc {
+        priority: 65535,
+        symbol_index: 0,
+    }]);
+    linking.symbol_table(&symbols);
+    module.section(&linking);
+    let mut relocs = RelocSection::new("reloc.CODE", 3);
+    relocs.entry(RelocType::GlobalIndexLEB, offsets[1] + 2, 3, None);
+    relocs.entry(RelocType::TableIndexSLEB, offsets[2] + 2, 1, None);
+    relocs.entry(RelocType::TypeIndexLEB, offsets[2] + 8, 1, None);
+    relocs.entry(RelocType::TableNumberLEB, offsets[2] + 13, 4, None);
+    module.section(&relocs);
+    let object = module.finish();
+
+    let wasm = Linker::new()
+        .stack_size(1024)
+        .export("__wasm_call_ctors")
+        .link(&[&object])?;
+    common::validate(&wasm)?;
+    let text = wasmprinter::print_bytes(&wasm)?;
+    assert!(text.contains("(table (;0;) 2 2 funcref)"), "{}", text);
+    assert!(
+        text.contains("(elem (;0;) (i32.const 1) func 1)"),
+        "{}",
+        text
+    );
+    assert!(
+        text.contains("(global (;0;) (mut i32) (i32.const 2048))"),
+        "{}",
+        text
+    );
+    assert!(text.contains("call_indirect (type 1)"), "{}", text);
+    assert!(text.contains("(export \"main\" (func 2))"), "{}", text);
+    assert!(
+        text.contains("(export \"__wasm_call_ctors\" (func 3))"),
+        "{}",
+        text
+    );
+    assert!(text.contains("call 0)"), "{}", text);
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use wasm_tools::functions::extract;
+use wasm_tools::liveness::{liveness, Liveness};
+use wasmparser::{FunctionBody, Type};
+
+/// Ranges of instructions, as indices from the first to just past the last.
+type Ranges = Vec<(usize, usize)>;
+
+/// Returns the liveness of the first function of `wat`, and the ranges in
+/// which each of its locals is live.
+fn analyze(wat: &str) -> anyhow::Result<(Liveness, Vec<Ranges>)> {
+    let wasm = wat::parse_str(wat)?;
+    let function = liveness(&wasm)?.remove(0);
+    let func = extract(&wasm, 0)?;
+    let body = FunctionBody::new(func.offset, func.body);
+    let mut reader = body.get_operators_reader()?;
+    let mut offsets = Vec::new();
+    while !reader.eof() {
+        offsets.push(reader.original_position());
+        reader.read()?;
+    }
+    offsets.push(reader.original_position());
+    let index = |offset| offsets.iter().position(|o| *o == offset).unwrap();
+    let live = function
+        .locals
+        .iter()
+        .map(|local| {
+            local
+                .live
+                .iter()
+                .map(|r| (index(r.start), index(r.end)))
+                .collect()
+        })
+        .collect();
+    Ok((function, live))
+}
+
+#[test]
+fn straight_line() -> anyhow::Result<()> {
+    let (function, live) = analyze(
+        r#"
+            (module
+              (func (param i32 i64) (result i32) (local f32)
+                local.get 0
+                local.get 0
+                i32.add
+                local.tee 0
+                local.set 0
+                local.get 0))
+        "#,
+    )?;
+    assert_eq!(function.params, 2);
+    assert_eq!(function.max_stack_depth, 2);
+    assert_eq!(function.max_live, 1);
+    assert_eq!(function.max_pressure, 2);
+    let local = &function.locals[0];
+    assert_eq!(local.ty, Type::I32);
+    assert_eq!((local.gets, local.sets, local.tees), (3, 1, 1));
+    // Live up to the second `local.get`, and again after the `local.set`.
+    assert_eq!(live[0], [(0, 2), (5, 6)]);
+    assert!(live[1].is_empty());
+    assert_eq!(function.locals[2].ty, Type::F32);
+    assert!(live[2].is_empty());
+    Ok(())
+}
+
+#[test]
+fn loops() -> anyhow::Result<()> {
+    let (function, live) = analyze(
+        r#"
+            (module
+              (func (param $n i32) (result i32) (local $acc i32)
+                loop $l
+                  local.get $acc
+                  local.get $n
+                  i32.add
+                  local.set $acc
+                  local.get $n
+                  i32.const 1
+                  i32.sub
+                  local.tee $n
+                  br_if $l
+                end
+                local.get $acc))
+        "#,
+    )?;
+    // `$n` is read again after the back edge of the loop, but not after it.
+    assert_eq!(live[0], [(0, 6), (9, 10)]);
+    // `$acc` is read before it's set, so it's live from the start.
+    assert_eq!(live[1], [(0, 2), (5, 12)]);
+    assert_eq!(function.max_live, 2);
+    assert_eq!(function.max_pressure, 3);
+    Ok(())
+}
+
+#[test]
+fn branches_and_unreachable_code() -> anyhow::Result<()> {
+    let (function, live) = analyze(
+        r#"
+            (module
+              (func (param i32 i32) (result i32)
+                local.get 0
+                if (result i32)
+                  local.get 1
+                else
+                  unreachable
+                  local.get 0
+                end))
+        "#,
+    )?;
+    assert_eq!(live[0], [(0, 1)]);
+    // Only the `then` arm reads the second parameter.
+    assert_eq!(live[1], [(0, 3)]);
+    assert_eq!(function.locals[0].gets, 2);
+    Ok(())
+}
+
+#[test]
+fn function_indices() -> anyhow::Result<()> {
+    let wasm = wat::parse_str(
+        r#"
+            (module
+              (import "env" "f" (func))
+              (func)
+              (func (param f64)))
+        "#,
+    )?;
+    let functions = liveness(&wasm)?;
+    let indices = functions.iter().map(|f| f.index).collect::<Vec<_>>();
+    assert_eq!(indices, [1, 2]);
+    let text = functions[1].to_string();
+    assert!(
+        text.starts_with("func[2]: 1 params, 0 locals, max 0 live,"),
+        "{}",
+        text
+    );
+    assert!(text.contains("  local 0 (f64): 0 gets"), "{}", text);
+    Ok(())
+}
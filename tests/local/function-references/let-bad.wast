@@ -2,4 +2,33 @@
   (module
     (func let)
   )
-  "fill me in once wasmparser implements validation")
+  "control frames remain at end of function")
+
+(assert_invalid
+  (module
+    (func
+      i64.const 0
+      let (local i32)
+      end))
+  "type mismatch: expected i32, found i64")
+
+(assert_invalid
+  (module
+    (func
+      i32.const 0
+      let (local i32)
+      end
+      local.get 0
+      drop))
+  "unknown local 0: local index out of bounds")
+
+(assert_invalid
+  (module
+    (func (param i32)
+      i64.const 0
+      let (local i64)
+        local.get 1
+        i64.eqz
+        drop
+      end))
+  "type mismatch: expected i64, found i32")
@@ -36,11 +36,25 @@
     drop
   )
   (func
+    i32.const 0
     (let (local $x i32) local.get $x drop)
+    i32.const 0
     let (local $x i32) local.get $x drop end
+    i32.const 0
     let (local $x i32)
       local.get $x
       drop
     end
   )
+  (func (param $x i64) (result i64)
+    local.get $x
+    i32.const 1
+    let (param i64) (result i64) (local $y i32)
+      local.get $x
+      i64.add
+      local.get $y
+      i64.extend_i32_u
+      i64.add
+    end
+  )
 )
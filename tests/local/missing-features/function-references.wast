@@ -0,0 +1,6 @@
+(assert_invalid
+  (module
+    (func
+      let
+      end))
+  "function references support is not enabled")
@@ -0,0 +1,7 @@
+(assert_invalid
+  (module
+    (func (param i64 i64) (result i64 i64)
+      local.get 0
+      local.get 1
+      i64.mul_wide_u))
+  "wide arithmetic support is not enabled")
@@ -0,0 +1,148 @@
+use wasm_tools::lower::Lower;
+use wasm_tools::usage::usage;
+use wasmparser::{Validator, WasmFeatures};
+
+mod common;
+
+/// Validates `wasm` without any of the proposals which can be lowered.
+fn validate_mvp(wasm: &[u8]) -> anyhow::Result<()> {
+    let features = WasmFeatures {
+        bulk_memory: false,
+        ..WasmFeatures::default()
+    };
+    common::validate_with(wasm, features)
+}
+
+const MODULE: &str = r#"
+    (module
+      (memory 1)
+      (data (i32.const 0) "hello")
+      (func $extend (param i32 i64) (result i64) (local f64)
+        local.get 0
+        i32.extend16_s
+        i64.extend_i32_u
+        local.get 1
+        i64.extend32_s
+        i64.add)
+      (func $trunc (param f32 f64) (result i64)
+        local.get 0
+        i32.trunc_sat_f32_u
+        i64.extend_i32_s
+        local.get 1
+        i64.trunc_sat_f64_s
+        i64.add)
+      (func $memory (param i32 i32 i32)
+        local.get 0
+        i32.const 255
+        local.get 2
+        memory.fill
+        local.get 0
+        local.get 1
+        local.get 2
+        memory.copy)
+      (func $plain (param i32) (result i32)
+        local.get 0))
+"#;
+
+#[test]
+fn lowers_to_mvp() -> anyhow::Result<()> {
+    let wasm = wat::parse_str(MODULE)?;
+    assert!(validate_mvp(&wasm).is_err());
+
+    let lowered = Lower::new().apply(&wasm)?;
+    Validator::new().validate_all(&lowered)?;
+    validate_mvp(&lowered)?;
+    let usage = usage(&lowered)?;
+    assert_eq!(usage.proposals["sign-extension"], 0);
+    assert_eq!(usage.proposals["saturating-float-to-int"], 0);
+    assert_eq!(usage.proposals["bulk-memory"], 0);
+
+    let text = wasmprinter::print_bytes(&lowered)?;
+    assert!(
+        text.contains("i32.const 16\n    i32.shl\n    i32.const 16\n    i32.shr_s"),
+        "{}",
+        text
+    );
+    assert!(
+        text.contains("i64.const 32\n    i64.shl\n    i64.const 32\n    i64.shr_s"),
+        "{}",
+        text
+    );
+    assert!(text.contains("i32.trunc_f32_u"), "{}", text);
+    assert!(text.contains("i64.trunc_f64_s"), "{}", text);
+    assert!(text.contains("i32.store8"), "{}", text);
+    // Functions using nothing which is lowered are left unchanged.
+    assert!(
+        text.contains("(func $plain (type 3) (param i32) (result i32)\n    local.get 0)"),
+        "{}",
+        text
+    );
+    Ok(())
+}
+
+#[test]
+fn lowering_can_be_disabled() -> anyhow::Result<()> {
+    let wasm = wat::parse_str(MODULE)?;
+    let lowered = Lower::new()
+        .sign_extension(false)
+        .bulk_memory(false)
+        .apply(&wasm)?;
+    Validator::new().validate_all(&lowered)?;
+    let usage = usage(&lowered)?;
+    assert_eq!(usage.proposals["sign-extension"], 2);
+    assert_eq!(usage.proposals["saturating-float-to-int"], 0);
+    assert_eq!(usage.proposals["bulk-memory"], 2);
+
+    let mut none = Lower::new();
+    none.sign_extension(false)
+        .saturating_float_to_int(false)
+        .bulk_memory(false);
+    assert_eq!(none.apply(&wasm)?, wasm);
+    Ok(())
+}
+
+#[test]
+fn data_count_section() -> anyhow::Result<()> {
+    // Only `memory.init` and `data.drop` need the data count section, so it
+    // goes away once `memory.fill` is lowered.
+    let wasm = wat::parse_str(
+        r#"
+            (module
+              (memory 1)
+              (data (i32.const 0) "x")
+              (func
+                i32.const 0
+                i32.const 0
+                i32.const 1
+                memory.fill))
+        "#,
+    )?;
+    assert!(usage(&wasm)?.required.contains(&"bulk-memory"));
+    let lowered = Lower::new().apply(&wasm)?;
+    validate_mvp(&lowered)?;
+    assert!(!usage(&lowered)?.required.contains(&"bulk-memory"));
+
+    // `memory.init` can't be lowered, and keeps the data count section.
+    let wasm = wat::parse_str(
+        r#"
+            (module
+              (memory 1)
+              (data "x")
+              (func
+                i32.const 0
+                i32.const 0
+                i32.const 1
+                memory.init 0
+                i32.const 0
+                i32.const 0
+                i32.const 1
+                memory.fill))
+        "#,
+    )?;
+    let lowered = Lower::new().apply(&wasm)?;
+    Validator::new().validate_all(&lowered)?;
+    let usage = usage(&lowered)?;
+    assert_eq!(usage.opcodes["MemoryInit"], 1);
+    assert_eq!(usage.opcodes.get("MemoryFill"), None);
+    Ok(())
+}
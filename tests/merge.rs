@@ -0,0 +1,230 @@
+use wasm_tools::merge::merge;
+use wasm_tools::names::Names;
+use wasm_tools::split::Splitter;
+
+mod common;
+
+fn merge_wat(modules: &[(&str, &str)]) -> anyhow::Result<(Vec<u8>, String)> {
+    let mut binaries = Vec::new();
+    for (name, wat) in modules {
+        binaries.push((*name, wat::parse_str(wat)?));
+    }
+    let inputs = binaries
+        .iter()
+        .map(|(name, wasm)| (*name, &wasm[..]))
+        .collect::<Vec<_>>();
+    let wasm = merge(&inputs)?;
+    common::validate(&wasm)?;
+    let text = wasmprinter::print_bytes(&wasm)?;
+    Ok((wasm, text))
+}
+
+#[test]
+fn resolves_imports() -> anyhow::Result<()> {
+    let app = r#"
+        (module
+          (import "env" "log" (func $log (param i32)))
+          (import "lib" "memory" (memory 1))
+          (import "lib" "counter" (global $counter (mut i32)))
+          (import "lib" "bump" (func $bump (result i32)))
+          (table 1 funcref)
+          (elem (i32.const 0) $main)
+          (func $main (export "main")
+            call $bump
+            i32.const 0
+            call_indirect (param i32)
+            global.get $counter
+            call $log))
+    "#;
+    let lib = r#"
+        (module
+          (import "env" "log" (func $log (param i32)))
+          (memory (export "memory") 1)
+          (global $counter (export "counter") (mut i32) (i32.const 0))
+          (table 1 funcref)
+          (elem (i32.const 0) $log)
+          (func $bump (export "bump") (result i32)
+            global.get $counter
+            i32.const 1
+            i32.add
+            global.set $counter
+            global.get $counter
+            i32.const 0
+            call_indirect (param i32)
+            i32.const 0
+            i32.load))
+    "#;
+    let (_, text) = merge_wat(&[("app", app), ("lib", lib)])?;
+    // The import of `log` is shared, and the rest are resolved to `lib`.
+    assert_eq!(text.matches("(import").count(), 1, "{}", text);
+    assert!(
+        text.contains("(import \"env\" \"log\" (func $log"),
+        "{}",
+        text
+    );
+    let main = "(func $main (type 2)
+    call $bump
+    i32.const 0
+    call_indirect (type 0)
+    global.get 0
+    call $log)";
+    assert!(text.contains(main), "{}", text);
+    // Each module keeps its own table.
+    assert!(
+        text.contains("i32.const 0\n    call_indirect 1 (type 0)\n"),
+        "{}",
+        text
+    );
+    assert!(
+        text.contains("(elem (;0;) (i32.const 0) func $main)"),
+        "{}",
+        text
+    );
+    assert!(
+        text.contains("(elem (;1;) (table 1) (i32.const 0) func $log)"),
+        "{}",
+        text
+    );
+    for export in ["main", "memory", "counter", "bump"].iter() {
+        assert!(
+            text.contains(&format!("(export \"{}\"", export)),
+            "{}",
+            text
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn start_functions_and_segments() -> anyhow::Result<()> {
+    let a = r#"
+        (module
+          (import "env" "memory" (memory 1))
+          (data "a")
+          (func $init_a
+            i32.const 0
+            i32.const 0
+            i32.const 1
+            memory.init 0
+            data.drop 0)
+          (start $init_a))
+    "#;
+    let b = r#"
+        (module
+          (import "env" "memory" (memory 1))
+          (data (i32.const 8) "b")
+          (data "c")
+          (func $init_b
+            i32.const 0
+            i32.const 0
+            i32.const 1
+            memory.init 1)
+          (start $init_b))
+    "#;
+    let (_, text) = merge_wat(&[("a", a), ("b", b)])?;
+    assert_eq!(text.matches("(import").count(), 1, "{}", text);
+    assert!(text.contains("memory.init 2)"), "{}", text);
+    assert!(
+        text.contains("(data (;1;) (i32.const 8) \"b\")"),
+        "{}",
+        text
+    );
+    let start = "(func (;2;) (type 0)
+    call $init_a
+    call $init_b)";
+    assert!(text.contains(start), "{}", text);
+    assert!(text.contains("(start 2)"), "{}", text);
+    Ok(())
+}
+
+#[test]
+fn names_and_types() -> anyhow::Result<()> {
+    let a = r#"
+        (module
+          (import "b" "g" (func $imported_g (param i64) (result i64)))
+          (func $f (export "f") (param $x i64) (result i64)
+            local.get $x
+            call $imported_g))
+    "#;
+    let b = r#"
+        (module
+          (type (func))
+          (func $g (export "g") (param $y i64) (result i64)
+            local.get $y))
+    "#;
+    let (wasm, text) = merge_wat(&[("a", a), ("b", b)])?;
+    // The function type shared by both modules is only defined once.
+    assert_eq!(text.matches("(type (;").count(), 2, "{}", text);
+    let names = Names::read(&wasm)?;
+    let functions = names.functions().collect::<Vec<_>>();
+    assert_eq!(functions, [(0, "f"), (1, "g")]);
+    assert_eq!(names.local(0, 0), Some("x"));
+    assert_eq!(names.local(1, 0), Some("y"));
+    Ok(())
+}
+
+#[test]
+fn split_and_merge() -> anyhow::Result<()> {
+    let wasm = wat::parse_str(
+        r#"
+            (module
+              (global $g (mut i32) (i32.const 0))
+              (func $main (export "main") (result i32) global.get $g)
+              (func $lazy (export "lazy") (param i32) (result i32)
+                local.get 0
+                global.set $g
+                call $main))
+        "#,
+    )?;
+    let split = Splitter::new().secondary(vec![1]).split(&wasm)?;
+    let merged = merge(&[
+        ("primary", &split.primary),
+        ("secondary", &split.secondaries[0]),
+    ])?;
+    common::validate(&merged)?;
+    let text = wasmprinter::print_bytes(&merged)?;
+    assert!(!text.contains("(import"), "{}", text);
+    // The stub remains, but now calls the function of the secondary module,
+    // which has the same name, through the shared table.
+    assert!(text.contains("call_indirect (type 1)"), "{}", text);
+    assert!(
+        text.contains("(elem (;0;) (i32.const 0) func $lazy_1)"),
+        "{}",
+        text
+    );
+    Ok(())
+}
+
+#[test]
+fn errors() -> anyhow::Result<()> {
+    let err = |modules: &[(&str, &str)]| merge_wat(modules).unwrap_err().to_string();
+    let a = r#"(module (import "b" "f" (func (param i32))))"#;
+    assert_eq!(
+        err(&[("a", a), ("b", "(module)")]),
+        "module `b` has no function export named `f`"
+    );
+    assert_eq!(
+        err(&[("a", a), ("b", r#"(module (func (export "f")))"#)]),
+        "module `a` imports function `f` from `b` with the wrong type"
+    );
+    let b = r#"(module (import "a" "f" (func (param i32))) (export "f" (func 0)))"#;
+    let a = r#"(module (import "b" "f" (func (param i32))) (export "f" (func 0)))"#;
+    assert_eq!(
+        err(&[("a", a), ("b", b)]),
+        "module `a` imports function `f` from `b` in a cycle"
+    );
+    let a = r#"(module (func (export "f")))"#;
+    assert_eq!(
+        err(&[("a", a), ("b", a)]),
+        "modules `a` and `b` both export `f`"
+    );
+    assert_eq!(
+        err(&[("a", "(module (memory 1))"), ("b", "(module (memory 1))")]),
+        "merging would renumber memory 0 of module `b`, which isn't supported"
+    );
+    assert_eq!(
+        err(&[("a", "(module)"), ("a", "(module)")]),
+        "more than one module is named `a`"
+    );
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use wasm_tools::metering::{Exhausted, Metering};
+use wasmparser::Operator;
+
+mod common;
+
+fn meter_wat(metering: &Metering, wat: &str) -> anyhow::Result<String> {
+    let wasm = common::transform(wat, |wasm| metering.apply(wasm))?;
+    wasmprinter::print_bytes(&wasm)
+}
+
+/// Returns the amounts of fuel charged by `text`, in order.
+fn charges(text: &str) -> Vec<u64> {
+    let lines = text.lines().map(|l| l.trim()).collect::<Vec<_>>();
+    lines
+        .windows(3)
+        .filter(|w| w[0].starts_with("global.get") && w[2] == "i64.sub")
+        .map(|w| w[1].trim_start_matches("i64.const ").parse().unwrap())
+        .collect()
+}
+
+const COUNT: &str = r#"
+    (module
+      (import "env" "f" (func $f))
+      (func $count (param i32) (result i32)
+        loop $l
+          local.get 0
+          i32.const 1
+          i32.sub
+          local.tee 0
+          br_if $l
+        end
+        local.get 0
+        if (result i32)
+          i32.const 1
+        else
+          call $f
+          i32.const 2
+        end)
+      (export "count" (func $count)))
+"#;
+
+#[test]
+fn charges_basic_blocks() -> anyhow::Result<()> {
+    let text = meter_wat(Metering::new().initial_fuel(1000), COUNT)?;
+    // The entry, the body of the loop, the code after it, both arms of the
+    // `if` and the final `end`.
+    assert_eq!(charges(&text), [1, 5, 3, 2, 3, 1]);
+    assert_eq!(text.matches("unreachable").count(), 6);
+    assert!(text.contains("(global (;0;) (mut i64) (i64.const 1000))"));
+    assert!(text.contains("(export \"fuel\" (global 0))"));
+    Ok(())
+}
+
+#[test]
+fn custom_costs() -> anyhow::Result<()> {
+    let text = meter_wat(
+        Metering::new().costs(|op| match op {
+            Operator::Call { .. } => 100,
+            Operator::End | Operator::Else => 0,
+            _ => 1,
+        }),
+        COUNT,
+    )?;
+    // The final `end` costs nothing so it isn't charged at all.
+    assert_eq!(charges(&text), [1, 5, 2, 1, 101]);
+    Ok(())
+}
+
+#[test]
+fn call_when_exhausted() -> anyhow::Result<()> {
+    let text = meter_wat(
+        Metering::new().export("gas").exhausted(Exhausted::Call {
+            module: "env".to_string(),
+            field: "out_of_gas".to_string(),
+        }),
+        COUNT,
+    )?;
+    assert!(text.contains("(import \"env\" \"out_of_gas\" (func (;1;) (type 2)))"));
+    assert_eq!(text.matches("call 1\n").count(), 6);
+    assert!(text.contains("call $f"));
+    assert!(text.contains("(export \"count\" (func $count))"));
+    assert!(text.contains("(export \"gas\" (global 0))"));
+    assert!(!text.contains("unreachable"));
+    Ok(())
+}
+
+#[test]
+fn duplicate_export() -> anyhow::Result<()> {
+    let wasm = wat::parse_str(r#"(module (memory (export "fuel") 1))"#)?;
+    let err = Metering::new().apply(&wasm).unwrap_err();
+    assert!(err.to_string().contains("`fuel`"), "{}", err);
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use std::collections::BTreeMap;
+use wasm_tools::names::{parse_symbol_map, Names};
+use wasmparser::Validator;
+
+const MODULE: &str = r#"
+    (module $m
+      (import "env" "log" (func $log (param i32)))
+      (global (mut i32) (i32.const 0))
+      (func $main (param $x i32) (local $y i32)
+        local.get $x
+        call $log)
+      (func (export "helper"))
+      (data "hello")
+    )
+"#;
+
+/// Returns `wasm` without its name section.
+fn strip_names(wasm: &[u8]) -> anyhow::Result<Vec<u8>> {
+    Names::new().apply(wasm)
+}
+
+#[test]
+fn read_and_edit() -> anyhow::Result<()> {
+    let wasm = wat::parse_str(MODULE)?;
+    let mut names = Names::read(&wasm)?;
+    assert_eq!(names.module(), Some("m"));
+    assert_eq!(names.function(0), Some("log"));
+    assert_eq!(names.function(1), Some("main"));
+    assert_eq!(names.function(2), None);
+    assert_eq!(names.local(1, 0), Some("x"));
+    assert_eq!(names.local(1, 1), Some("y"));
+
+    names
+        .set_module("renamed")
+        .set_function(1, "start")
+        .set_local(1, 1, "tmp")
+        .set_global(0, "counter");
+    let edited = names.apply(&wasm)?;
+    Validator::new().validate_all(&edited)?;
+    assert_eq!(strip_names(&edited)?, strip_names(&wasm)?);
+    assert_eq!(Names::read(&edited)?, names);
+
+    let text = wasmprinter::print_bytes(&edited)?;
+    assert!(text.starts_with("(module $renamed"), "{}", text);
+    assert!(
+        text.contains("(func $start (type 0) (param $x i32)"),
+        "{}",
+        text
+    );
+    assert!(text.contains("(local $tmp i32)"), "{}", text);
+    Ok(())
+}
+
+#[test]
+fn symbol_map() -> anyhow::Result<()> {
+    let wasm = wat::parse_str("(module (func) (func) (func))")?;
+    let wasm = strip_names(&wasm)?;
+    let mut names = Names::read(&wasm)?;
+    assert_eq!(names, Names::new());
+
+    let map = "0:main\n\n2:_ZN3foo3barE\n";
+    assert_eq!(names.add_symbol_map(map)?, 2);
+    // Names which are already there aren't replaced.
+    assert_eq!(names.add_symbol_map("0:other\n1:helper\n")?, 1);
+    let wasm = names.apply(&wasm)?;
+    let functions = Names::read(&wasm)?
+        .functions()
+        .map(|(i, name)| (i, name.to_string()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        functions,
+        [
+            (0, "main".to_string()),
+            (1, "helper".to_string()),
+            (2, "_ZN3foo3barE".to_string()),
+        ]
+    );
+
+    let err = names.add_symbol_map("0:main\nmain\n").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid symbol map entry on line 2: `main`"
+    );
+    Ok(())
+}
+
+#[test]
+fn unknown_subsections_are_kept() -> anyhow::Result<()> {
+    let mut wasm = strip_names(&wat::parse_str("(module (func $f))")?)?;
+    // A custom section `name` with a function name, a label name subsection
+    // (3) which isn't understood and a global name.
+    let name_section = [
+        4, b'n', b'a', b'm', b'e', //
+        1, 4, 1, 0, 1, b'f', //
+        3, 5, 1, 0, 1, 0, 0, //
+        7, 4, 1, 0, 1, b'g',
+    ];
+    wasm.push(0);
+    wasm.push(name_section.len() as u8);
+    wasm.extend_from_slice(&name_section);
+
+    let mut names = Names::read(&wasm)?;
+    assert_eq!(names.function(0), Some("f"));
+    assert_eq!(names.global(0), Some("g"));
+    names.set_function(0, "h");
+    let edited = names.apply(&wasm)?;
+    let mut expected = wasm.clone();
+    let f = expected.len() - name_section.len() + 10;
+    assert_eq!(expected[f], b'f');
+    expected[f] = b'h';
+    assert_eq!(edited, expected);
+    Ok(())
+}
+
+#[test]
+fn symbol_map_formats() -> anyhow::Result<()> {
+    let expected = [(0, "main".to_string()), (12, "_start".to_string())]
+        .iter()
+        .cloned()
+        .collect::<BTreeMap<_, _>>();
+    assert_eq!(parse_symbol_map("0:main\n12:_start\n")?, expected);
+    assert_eq!(
+        parse_symbol_map(r#" {"0": "main", "12": "_start"} "#)?,
+        expected
+    );
+
+    let err = parse_symbol_map(r#"{"main": "main"}"#).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid function index `main` in symbol map"
+    );
+    let err = parse_symbol_map(r#"{"0": 1}"#).unwrap_err();
+    assert_eq!(err.to_string(), "invalid JSON symbol map");
+    Ok(())
+}
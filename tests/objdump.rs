@@ -0,0 +1,73 @@
+use std::fs;
+use std::process::{Command, Output};
+
+const MANGLED: &str = "_ZN3foo3bar17h0123456789abcdefE";
+
+/// Runs `objdump` with `args` followed by a module whose only function is
+/// named with a mangled Rust symbol, with both `wasm-tools objdump` and
+/// `wasm-objdump-rs`, and checks that they agree.
+fn objdump(args: &[&str]) -> anyhow::Result<Output> {
+    let dir = tempfile::TempDir::new()?;
+    let wasm = dir.path().join("module.wasm");
+    fs::write(
+        &wasm,
+        wat::parse_str(&format!("(module (func ${}))", MANGLED))?,
+    )?;
+    let symbols = dir.path().join("module.symbols");
+    fs::write(&symbols, "")?;
+    let args = args
+        .iter()
+        .map(|arg| match *arg {
+            "SYMBOLS" => symbols.to_str().unwrap(),
+            arg => arg,
+        })
+        .collect::<Vec<_>>();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_wasm-tools"))
+        .arg("objdump")
+        .args(&args)
+        .arg(&wasm)
+        .output()?;
+    let standalone = Command::new(env!("CARGO_BIN_EXE_wasm-objdump-rs"))
+        .args(&args)
+        .arg(&wasm)
+        .output()?;
+    assert_eq!(output.status.success(), standalone.status.success());
+    assert_eq!(output.stdout, standalone.stdout);
+    Ok(output)
+}
+
+#[test]
+fn demangles_names_with_symbols() -> anyhow::Result<()> {
+    let output = objdump(&["--symbols", "SYMBOLS"])?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("func[0] <foo::bar>"), "{}", stdout);
+
+    let output = objdump(&["--symbols", "SYMBOLS", "--no-demangle"])?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(
+        stdout.contains(&format!("func[0] <{}>", MANGLED)),
+        "{}",
+        stdout
+    );
+    Ok(())
+}
+
+#[test]
+fn no_demangle_requires_symbols() -> anyhow::Result<()> {
+    let output = objdump(&[])?;
+    assert!(output.status.success());
+    assert!(!String::from_utf8(output.stdout)?.contains("func[0]"));
+
+    let output = objdump(&["--no-demangle"])?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(
+        stderr.contains("`--no-demangle` requires `--symbols`"),
+        "{}",
+        stderr
+    );
+    Ok(())
+}
@@ -0,0 +1,140 @@
+use wasm_tools::optimize::{Optimize, Stats};
+use wasmparser::Validator;
+
+fn optimize_wat(optimize: &Optimize, wat: &str) -> anyhow::Result<(String, Stats)> {
+    let wasm = wat::parse_str(wat)?;
+    Validator::new().validate_all(&wasm)?;
+    let (wasm, stats) = optimize.apply_with_stats(&wasm)?;
+    Validator::new().validate_all(&wasm)?;
+    Ok((wasmprinter::print_bytes(&wasm)?, stats))
+}
+
+#[test]
+fn fold_constants() -> anyhow::Result<()> {
+    let (text, stats) = optimize_wat(
+        &Optimize::new(),
+        r#"
+            (module
+              (func (result i32)
+                i32.const 1
+                i32.const 2
+                i32.add
+                i32.const 3
+                i32.shl)
+              (func (result i64)
+                i64.const -1
+                i64.const 60
+                i64.shr_u)
+              (func (result i32)
+                i32.const 1
+                i32.const 0
+                i32.div_u)
+              (func (param i32) (result i32)
+                local.get 0
+                i32.const 2
+                i32.add))
+        "#,
+    )?;
+    assert_eq!(stats.folded, 3);
+    assert!(text.contains("(result i32)\n    i32.const 24)"), "{}", text);
+    assert!(text.contains("(result i64)\n    i64.const 15)"), "{}", text);
+    // Division may trap, so it's left alone.
+    assert!(text.contains("i32.div_u"), "{}", text);
+    assert!(text.contains("i32.const 2\n    i32.add"), "{}", text);
+    Ok(())
+}
+
+#[test]
+fn tee_locals() -> anyhow::Result<()> {
+    let (text, stats) = optimize_wat(
+        &Optimize::new(),
+        r#"
+            (module
+              (func (param i32 i32) (result i32)
+                local.get 0
+                local.set 1
+                local.get 1
+                local.set 0
+                local.get 1))
+        "#,
+    )?;
+    assert_eq!(stats.tees, 1);
+    assert!(
+        text.contains("local.get 0\n    local.tee 1\n    local.set 0\n    local.get 1)"),
+        "{}",
+        text
+    );
+    Ok(())
+}
+
+#[test]
+fn remove_dead_code() -> anyhow::Result<()> {
+    let (text, stats) = optimize_wat(
+        &Optimize::new(),
+        r#"
+            (module
+              (func (param i32) (result i32)
+                block (result i32)
+                  nop
+                  local.get 0
+                  if (result i32)
+                    i32.const 1
+                    br 2
+                    block
+                      br 0
+                    end
+                    i32.const 2
+                  else
+                    i32.const 3
+                  end
+                  unreachable
+                  drop
+                end))
+        "#,
+    )?;
+    assert_eq!(
+        stats,
+        Stats {
+            folded: 0,
+            tees: 0,
+            nops: 1,
+            dead: 5,
+        }
+    );
+    let expected = "\
+    block (result i32)  ;; label = @1
+      local.get 0
+      if (result i32)  ;; label = @2
+        i32.const 1
+        br 2 (;@0;)
+      else
+        i32.const 3
+      end
+      unreachable
+    end)";
+    assert!(text.contains(expected), "{}", text);
+    Ok(())
+}
+
+#[test]
+fn unchanged() -> anyhow::Result<()> {
+    let wat = r#"
+        (module
+          (func (param i32) (result i32)
+            nop
+            local.get 0
+            i32.const 1
+            i32.add))
+    "#;
+    let wasm = wat::parse_str(wat)?;
+    let mut optimize = Optimize::new();
+    optimize.remove_dead_code(false);
+    let (optimized, stats) = optimize.apply_with_stats(&wasm)?;
+    assert_eq!(stats, Stats::default());
+    assert_eq!(optimized, wasm);
+
+    let (text, stats) = optimize_wat(&Optimize::new(), wat)?;
+    assert_eq!(stats.nops, 1);
+    assert!(!text.contains("nop"), "{}", text);
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use wasm_tools::rename::Rename;
+use wasmparser::{Parser, Payload, Validator};
+
+const MODULE: &str = r#"
+    (module
+      (import "env" "fd_write" (func (param i32 i32 i32 i32) (result i32)))
+      (import "env" "memory" (memory 1))
+      (import "other" "g" (global i32))
+      (func $main (export "main"))
+      (func (export "helper"))
+    )
+"#;
+
+fn imports_and_exports(wasm: &[u8]) -> anyhow::Result<(Vec<String>, Vec<String>)> {
+    let mut imports = Vec::new();
+    let mut exports = Vec::new();
+    for payload in Parser::new(0).parse_all(wasm) {
+        match payload? {
+            Payload::ImportSection(s) => {
+                for import in s {
+                    let import = import?;
+                    imports.push(format!("{}.{}", import.module, import.field.unwrap()));
+                }
+            }
+            Payload::ExportSection(s) => {
+                for export in s {
+                    exports.push(export?.field.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok((imports, exports))
+}
+
+#[test]
+fn rename() -> anyhow::Result<()> {
+    let wasm = wat::parse_str(MODULE)?;
+    let renamed = Rename::new()
+        .import_module("env", "wasi_snapshot_preview1")
+        .import("env", "memory", "js", "mem")
+        .export("main", "_start")
+        .apply(&wasm)?;
+    Validator::new().validate_all(&renamed)?;
+    let (imports, exports) = imports_and_exports(&renamed)?;
+    assert_eq!(
+        imports,
+        ["wasi_snapshot_preview1.fd_write", "js.mem", "other.g"]
+    );
+    assert_eq!(exports, ["_start", "helper"]);
+    Ok(())
+}
+
+#[test]
+fn nothing_to_rename() -> anyhow::Result<()> {
+    let wasm = wat::parse_str(MODULE)?;
+    assert_eq!(Rename::new().apply(&wasm)?, wasm);
+    Ok(())
+}
+
+#[test]
+fn duplicate_export() -> anyhow::Result<()> {
+    let wasm = wat::parse_str(MODULE)?;
+    assert!(Rename::new().export("main", "helper").apply(&wasm).is_err());
+    Ok(())
+}
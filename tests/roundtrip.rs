@@ -294,6 +294,7 @@ impl TestState {
             && !test.ends_with("atomic-align.txt")
             && !test.ends_with("atomic.wast")
             && !test.ends_with("local/memory64.wast")
+            && !test.ends_with("local/wide-arithmetic.wast")
 
             // FIXME uses simd instrs not implemented in wabt yet.
             && !test.ends_with("local/simd.wat")
@@ -318,6 +319,18 @@ impl TestState {
             self.bump_ntests();
             self.binary_compare(&binary2, contents, false)
                 .context("failed to compare original `wat` with roundtrip `wat`")?;
+
+            // Folding instructions doesn't change what they assemble to.
+            let mut printer = wasmprinter::Printer::new();
+            printer.fold_instructions(true);
+            let folded = printer
+                .print(contents)
+                .context("failed to print folded wasm")?;
+            let binary3 = wat::parse_str(&folded)
+                .context("failed to parse folded `wat` from `wasmprinter`")?;
+            self.bump_ntests();
+            self.binary_compare(&binary3, &binary2, false)
+                .context("failed to compare unfolded `wat` with folded `wat`")?;
         }
 
         Ok(())
@@ -528,32 +541,7 @@ impl TestState {
             return s;
         }
 
-        let mut bad = false;
-        let mut result = String::new();
-        for diff in diff::lines(&expected, &actual) {
-            match diff {
-                diff::Result::Left(s) => {
-                    bad = true;
-                    result.push_str("-");
-                    result.push_str(s);
-                }
-                diff::Result::Right(s) => {
-                    bad = true;
-                    result.push_str("+");
-                    result.push_str(s);
-                }
-                diff::Result::Both(s, _) => {
-                    result.push_str(" ");
-                    result.push_str(s);
-                }
-            }
-            result.push_str("\n");
-        }
-        if bad {
-            bail!("expected != actual\n\n{}", result);
-        } else {
-            Ok(())
-        }
+        wasm_tools::roundtrip::text_compare(&actual, &expected)
     }
 
     /// Parses a quoted module, then asserts that it's valid.
@@ -591,65 +579,9 @@ impl TestState {
             actual.to_vec()
         };
 
-        if actual == expected {
-            self.bump_ntests();
-            return Ok(());
-        }
-
-        let difference = actual
-            .iter()
-            .enumerate()
-            .zip(expected)
-            .find(|((_, actual), expected)| actual != expected);
-        let pos = match difference {
-            Some(((pos, _), _)) => format!("at byte {} ({0:#x})", pos),
-            None => format!("by being too small"),
-        };
-        let mut msg = format!("error: actual wasm differs {} from expected wasm\n", pos);
-
-        if let Some(((pos, _), _)) = difference {
-            msg.push_str(&format!("  {:4} |   {:#04x}\n", pos - 2, actual[pos - 2]));
-            msg.push_str(&format!("  {:4} |   {:#04x}\n", pos - 1, actual[pos - 1]));
-            msg.push_str(&format!("  {:4} | - {:#04x}\n", pos, expected[pos]));
-            msg.push_str(&format!("       | + {:#04x}\n", actual[pos]));
-        }
-
-        if let Ok(actual) = wasmparser_dump::dump_wasm(&actual) {
-            if let Ok(expected) = wasmparser_dump::dump_wasm(&expected) {
-                let mut actual = actual.lines();
-                let mut expected = expected.lines();
-                let mut differences = 0;
-                let mut last_dots = false;
-                while differences < 5 {
-                    let actual_state = match actual.next() {
-                        Some(s) => s,
-                        None => break,
-                    };
-                    let expected_state = match expected.next() {
-                        Some(s) => s,
-                        None => break,
-                    };
-
-                    if actual_state == expected_state {
-                        if differences > 0 && !last_dots {
-                            msg.push_str(&format!(" ...\n"));
-                            last_dots = true;
-                        }
-                        continue;
-                    }
-                    last_dots = false;
-
-                    if differences == 0 {
-                        msg.push_str("\n\n");
-                    }
-                    msg.push_str(&format!("- {}\n", expected_state));
-                    msg.push_str(&format!("+ {}\n", actual_state));
-                    differences += 1;
-                }
-            }
-        }
-
-        bail!("{}", msg);
+        wasm_tools::roundtrip::binary_compare(&actual, expected)?;
+        self.bump_ntests();
+        return Ok(());
 
         fn remove_name_section(bytes: &[u8]) -> Vec<u8> {
             let mut p = Parser::new(0);
@@ -690,6 +622,8 @@ impl TestState {
             multi_value: true,
             multi_memory: true,
             memory64: true,
+            wide_arithmetic: true,
+            function_references: true,
         };
         for part in test.iter().filter_map(|t| t.to_str()) {
             match part {
@@ -716,6 +650,8 @@ impl TestState {
                     features.bulk_memory = true;
                 }
                 "module-linking" => features.module_linking = true,
+                "wide-arithmetic" => features.wide_arithmetic = true,
+                "function-references" => features.function_references = true,
                 _ => {}
             }
         }
@@ -0,0 +1,159 @@
+use anyhow::Result;
+use wasm_tools::signature::{
+    hash, sign, sign_detached, verify, verify_detached, Ed25519Signer, Ed25519Verifier, Signer,
+    Verifier, SECTION_NAME,
+};
+use wasmparser::{Parser, Payload};
+
+const MODULE: &str = r#"
+    (module
+      (@custom "name-like" "a")
+      (memory 1)
+      (func (export "f") (result i32) i32.const 1)
+      (data (i32.const 0) "hello"))
+"#;
+
+fn keys(seed: u8, key_id: &[u8]) -> (Ed25519Signer, Ed25519Verifier) {
+    let signer = Ed25519Signer::from_seed([seed; 32], key_id);
+    let verifier = Ed25519Verifier::new(signer.public_key(), key_id);
+    (signer, verifier)
+}
+
+fn custom_sections(wasm: &[u8]) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    for payload in Parser::new(0).parse_all(wasm) {
+        if let Payload::CustomSection { name, .. } = payload? {
+            names.push(name.to_string());
+        }
+    }
+    Ok(names)
+}
+
+#[test]
+fn embedded() -> Result<()> {
+    let wasm = wat::parse_str(MODULE)?;
+    let (signer, verifier) = keys(1, b"a");
+    assert!(verify(&wasm, &verifier).is_err());
+
+    let signed = sign(&wasm, &signer)?;
+    verify(&signed, &verifier)?;
+    wasmparser::validate(&signed)?;
+    assert_eq!(custom_sections(&signed)?, [SECTION_NAME, "name-like"]);
+    assert_eq!(hash(&signed)?, hash(&wasm)?);
+    // Everything but the signature section is copied unchanged.
+    assert!(signed.ends_with(&wasm[8..]));
+
+    // Signing again with the same key replaces its signature.
+    assert_eq!(sign(&signed, &signer)?, signed);
+    Ok(())
+}
+
+#[test]
+fn several_keys() -> Result<()> {
+    let wasm = wat::parse_str(MODULE)?;
+    let (signer1, verifier1) = keys(1, b"a");
+    let (signer2, verifier2) = keys(2, b"b");
+    let (_, verifier3) = keys(3, b"c");
+    let signed = sign(&sign(&wasm, &signer1)?, &signer2)?;
+    verify(&signed, &verifier1)?;
+    verify(&signed, &verifier2)?;
+    let err = verify(&signed, &verifier3).unwrap_err().to_string();
+    assert!(err.contains("no valid signature"), "{}", err);
+
+    // A verifier with the right public key and no key id accepts any key id,
+    // but the wrong key id is rejected.
+    let any_id = Ed25519Verifier::new(signer2.public_key(), b"");
+    verify(&signed, &any_id)?;
+    let wrong_id = Ed25519Verifier::new(signer2.public_key(), b"a");
+    assert!(verify(&signed, &wrong_id).is_err());
+    Ok(())
+}
+
+#[test]
+fn changes_are_detected() -> Result<()> {
+    let wasm = wat::parse_str(MODULE)?;
+    let (signer, verifier) = keys(1, b"a");
+    let signed = sign(&wasm, &signer)?;
+
+    let pos = signed.windows(5).position(|w| w == b"hello").unwrap();
+    let mut changed = signed.clone();
+    changed[pos] = b'j';
+    let err = verify(&changed, &verifier).unwrap_err().to_string();
+    assert!(err.contains("changed after it was signed"), "{}", err);
+
+    // Unlike integrity hashes, signatures cover custom sections too.
+    let mut with_custom = signed.clone();
+    with_custom.extend_from_slice(&[0, 2, 1, b'x']);
+    assert!(verify(&with_custom, &verifier).is_err());
+
+    // Signing a changed module drops the signatures which no longer match.
+    let (signer2, verifier2) = keys(2, b"b");
+    let resigned = sign(&changed, &signer2)?;
+    verify(&resigned, &verifier2)?;
+    assert!(verify(&resigned, &verifier).is_err());
+    Ok(())
+}
+
+#[test]
+fn detached() -> Result<()> {
+    let wasm = wat::parse_str(MODULE)?;
+    let (signer, verifier) = keys(1, b"a");
+    let signature = sign_detached(&wasm, &signer)?;
+    verify_detached(&wasm, &signature, &verifier)?;
+    assert!(verify(&wasm, &verifier).is_err());
+
+    // A detached signature also verifies the module with the signature
+    // embedded, since signature sections aren't signed.
+    let signed = sign(&wasm, &keys(2, b"b").0)?;
+    verify_detached(&signed, &signature, &verifier)?;
+
+    let other = wat::parse_str("(module)")?;
+    assert!(verify_detached(&other, &signature, &verifier).is_err());
+    assert!(verify_detached(&wasm, &signature[..signature.len() - 1], &verifier).is_err());
+    Ok(())
+}
+
+/// A toy signature algorithm showing how other backends are plugged in.
+struct Xor(u8);
+
+impl Signer for Xor {
+    fn algorithm(&self) -> u8 {
+        0x80
+    }
+
+    fn key_id(&self) -> &[u8] {
+        b"xor"
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>> {
+        Ok(message.iter().map(|b| b ^ self.0).collect())
+    }
+}
+
+impl Verifier for Xor {
+    fn algorithm(&self) -> u8 {
+        0x80
+    }
+
+    fn key_id(&self) -> &[u8] {
+        b"xor"
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        message
+            .iter()
+            .map(|b| b ^ self.0)
+            .eq(signature.iter().copied())
+    }
+}
+
+#[test]
+fn custom_backend() -> Result<()> {
+    let wasm = wat::parse_str(MODULE)?;
+    let (signer, verifier) = keys(1, b"xor");
+    let signed = sign(&sign(&wasm, &Xor(0x5a))?, &signer)?;
+    verify(&signed, &Xor(0x5a))?;
+    verify(&signed, &verifier)?;
+    assert!(verify(&signed, &Xor(0x5b)).is_err());
+    Ok(())
+}
@@ -0,0 +1,119 @@
+use wasm_tools::init::{initialize, Imports};
+use wasm_tools::snapshot::Snapshot;
+use wasmparser::{DataKind, Parser, Payload};
+
+mod common;
+
+/// Returns the memory, offset and length of each active data segment of
+/// `wasm`, or `None` for passive ones.
+fn data_segments(wasm: &[u8]) -> anyhow::Result<Vec<Option<(u32, usize)>>> {
+    let mut segments = Vec::new();
+    for payload in Parser::new(0).parse_all(wasm) {
+        if let Payload::DataSection(s) = payload? {
+            for data in s {
+                let data = data?;
+                segments.push(match data.kind {
+                    DataKind::Active { memory_index, .. } => Some((memory_index, data.data.len())),
+                    DataKind::Passive => None,
+                });
+            }
+        }
+    }
+    Ok(segments)
+}
+
+fn snapshot(snapshot: &Snapshot, wat: &str, image: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let wasm = common::transform(wat, |wasm| snapshot.apply(wasm, image))?;
+    let memory = &initialize(&wasm, &Imports::new())?.memories[0];
+    assert_eq!(&memory[..image.len()], image);
+    assert!(memory[image.len()..].iter().all(|b| *b == 0));
+    Ok(wasm)
+}
+
+#[test]
+fn replaces_active_segments() -> anyhow::Result<()> {
+    let mut image = vec![0; 100];
+    image[10..13].copy_from_slice(b"abc");
+    image[50..52].copy_from_slice(b"de");
+    let wasm = snapshot(
+        &Snapshot::new(),
+        r#"
+            (module
+              (memory 1)
+              (memory 1)
+              (data (i32.const 0) "old")
+              (data $passive "passive")
+              (data (memory 1) (i32.const 0) "other")
+              (func
+                i32.const 0
+                i32.const 0
+                i32.const 1
+                memory.init $passive
+                data.drop $passive))
+        "#,
+        &image,
+    )?;
+    assert_eq!(
+        data_segments(&wasm)?,
+        [None, Some((1, 5)), Some((0, 3)), Some((0, 2))]
+    );
+    let text = wasmprinter::print_bytes(&wasm)?;
+    assert!(text.contains("memory.init 0"), "{}", text);
+    assert!(text.contains("data.drop 0"), "{}", text);
+    Ok(())
+}
+
+#[test]
+fn grows_memory() -> anyhow::Result<()> {
+    let mut image = vec![0; 65536 * 2];
+    image[65536] = 1;
+    let wasm = snapshot(&Snapshot::new(), "(module (memory 1 3))", &image)?;
+    assert!(wasmprinter::print_bytes(&wasm)?.contains("(memory (;0;) 2 3)"));
+
+    let wasm = wat::parse_str("(module (memory 1 1))")?;
+    assert!(Snapshot::new().apply(&wasm, &image).is_err());
+    let wasm = wat::parse_str(r#"(module (import "env" "m" (memory 1)))"#)?;
+    assert!(Snapshot::new().apply(&wasm, &image).is_err());
+    Ok(())
+}
+
+#[test]
+fn merges_and_splits_segments() -> anyhow::Result<()> {
+    let mut image = vec![0; 1000];
+    image[0] = 1;
+    image[4] = 1;
+    image[100] = 1;
+    image[120] = 1;
+    image[500..520].iter_mut().for_each(|b| *b = 1);
+    let wat = "(module (memory 1))";
+
+    // The bytes at 0 and 4 are close enough to share a segment.
+    let wasm = snapshot(&Snapshot::new(), wat, &image)?;
+    assert_eq!(
+        data_segments(&wasm)?,
+        [Some((0, 5)), Some((0, 1)), Some((0, 1)), Some((0, 20))]
+    );
+
+    let wasm = snapshot(Snapshot::new().max_segments(2), wat, &image)?;
+    assert_eq!(data_segments(&wasm)?, [Some((0, 121)), Some((0, 20))]);
+
+    let wasm = snapshot(Snapshot::new().min_gap(0).max_segment_size(8), wat, &image)?;
+    assert_eq!(
+        data_segments(&wasm)?,
+        [
+            Some((0, 1)),
+            Some((0, 1)),
+            Some((0, 1)),
+            Some((0, 1)),
+            Some((0, 8)),
+            Some((0, 8)),
+            Some((0, 4)),
+        ]
+    );
+
+    let wasm = wat::parse_str(wat)?;
+    let mut snapshot = Snapshot::new();
+    snapshot.max_segments(2).max_segment_size(8);
+    assert!(snapshot.apply(&wasm, &image).is_err());
+    Ok(())
+}
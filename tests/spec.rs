@@ -0,0 +1,263 @@
+//! Runs the upstream spec test suite against the text parser, the encoder and
+//! the validator.
+//!
+//! The suite is vendored as the `tests/testsuite` submodule, which also
+//! contains the tests of proposals in `proposals/*`. Unlike the `roundtrip`
+//! test this doesn't need wabt, and only checks that every directive succeeds
+//! or fails the way the spec says it should:
+//!
+//! * `module` directives parse, encode and validate.
+//! * `assert_invalid` modules encode but fail to validate.
+//! * `assert_malformed` modules fail to parse, encode or validate.
+//!
+//! Error messages aren't compared, the `roundtrip` test does that.
+//!
+//! Each proposal is run with the features it needs, see `features`. Proposals
+//! which aren't supported at all are skipped, and files which are known to
+//! fail are listed in `KNOWN_FAILURES` so gaps in support are tracked
+//! explicitly. A listed file which starts passing is an error too, so the list
+//! only ever shrinks.
+//!
+//! You can run this test suite with:
+//!
+//!     cargo test --test spec
+//!
+//! An argument can be passed as well to filter, based on filename, which test
+//! to run
+//!
+//!     cargo test --test spec proposals/simd
+
+use anyhow::{bail, Context, Result};
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str;
+use wasmparser::{Validator, WasmFeatures};
+use wast::parser::{self, ParseBuffer};
+use wast::{QuoteModule, Wast, WastDirective, Wat};
+
+const TESTSUITE: &str = "tests/testsuite";
+
+/// Proposals whose tests aren't run because they aren't supported yet.
+const UNSUPPORTED_PROPOSALS: &[&str] = &["function-references", "gc"];
+
+/// Files, relative to `tests/testsuite`, which are known to fail.
+const KNOWN_FAILURES: &[&str] = &[
+    // These still use `iNxM.any_true`, which was replaced by `v128.any_true`.
+    "proposals/simd/simd_boolean.wast",
+    "proposals/simd/simd_lane.wast",
+];
+
+fn main() {
+    let filter = std::env::args().nth(1);
+    let tests = find_tests()
+        .into_iter()
+        .filter(|test| match (&filter, test.to_str()) {
+            (Some(filter), Some(s)) => s.contains(filter.as_str()),
+            _ => true,
+        })
+        .collect::<Vec<_>>();
+    if tests.is_empty() {
+        println!(
+            "no spec tests found, is the `{}` submodule checked out?",
+            TESTSUITE
+        );
+        return;
+    }
+    println!("running {} spec test files\n", tests.len());
+
+    let results = tests
+        .par_iter()
+        .map(|test| (test, run_test(test)))
+        .collect::<Vec<_>>();
+
+    let mut summary = BTreeMap::new();
+    let mut errors = Vec::new();
+    for (test, result) in results {
+        let relative = test.strip_prefix(TESTSUITE).unwrap();
+        let known_failure = KNOWN_FAILURES.iter().any(|f| relative == Path::new(f));
+        let entry = summary
+            .entry(proposal(test).unwrap_or("spec"))
+            .or_insert((0, 0));
+        match (result, known_failure) {
+            (Ok(n), false) => entry.0 += n,
+            (Err(_), true) => entry.1 += 1,
+            (Ok(_), true) => errors.push(format!(
+                "{} passes now, remove it from `KNOWN_FAILURES`",
+                test.display()
+            )),
+            (Err(e), false) => errors.push(format!("{:?}", e)),
+        }
+    }
+
+    for (proposal, (passed, known_failures)) in summary {
+        println!(
+            "{}: {} directives passed, {} known failing files",
+            proposal, passed, known_failures
+        );
+    }
+    if !errors.is_empty() {
+        for msg in errors.iter() {
+            eprintln!("{}\n", msg);
+        }
+        panic!("{} tests failed", errors.len());
+    }
+}
+
+/// Recursively finds all `*.wast` files of the test suite, skipping those of
+/// unsupported proposals.
+fn find_tests() -> Vec<PathBuf> {
+    let mut tests = Vec::new();
+    if Path::new(TESTSUITE).exists() {
+        find_tests(TESTSUITE.as_ref(), &mut tests);
+    }
+    tests.retain(|test| match proposal(test) {
+        Some(proposal) => !UNSUPPORTED_PROPOSALS.contains(&proposal),
+        None => true,
+    });
+    tests.sort();
+    return tests;
+
+    fn find_tests(path: &Path, tests: &mut Vec<PathBuf>) {
+        for f in path.read_dir().unwrap() {
+            let f = f.unwrap();
+            if f.file_type().unwrap().is_dir() {
+                find_tests(&f.path(), tests);
+                continue;
+            }
+            if f.path().extension().and_then(|s| s.to_str()) == Some("wast") {
+                tests.push(f.path());
+            }
+        }
+    }
+}
+
+/// Returns the name of the proposal `test` belongs to, if any.
+fn proposal(test: &Path) -> Option<&str> {
+    let mut parts = test.strip_prefix(TESTSUITE).ok()?.iter();
+    match parts.next()?.to_str()? {
+        "proposals" => parts.next()?.to_str(),
+        _ => None,
+    }
+}
+
+/// Returns the features to validate the tests of `proposal` with, or the
+/// tests of the spec itself if it's `None`.
+fn features(proposal: Option<&str>) -> WasmFeatures {
+    let mut features = WasmFeatures {
+        bulk_memory: false,
+        reference_types: false,
+        ..WasmFeatures::default()
+    };
+    match proposal {
+        None => {}
+        Some("threads") => features.threads = true,
+        Some("simd") => features.simd = true,
+        Some("reference-types") => {
+            features.bulk_memory = true;
+            features.reference_types = true;
+        }
+        Some("exception-handling") => {
+            features.bulk_memory = true;
+            features.reference_types = true;
+            features.exceptions = true;
+        }
+        Some("bulk-memory-operations") => features.bulk_memory = true,
+        Some("tail-call") => features.tail_call = true,
+        Some("memory64") => {
+            features.memory64 = true;
+            features.bulk_memory = true;
+        }
+        Some("multi-memory") => {
+            features.multi_memory = true;
+            features.bulk_memory = true;
+        }
+        Some("module-linking") => features.module_linking = true,
+        Some("wide-arithmetic") => features.wide_arithmetic = true,
+        Some("function-references") => features.function_references = true,
+        Some(_) => {}
+    }
+    features
+}
+
+/// Runs every directive of `test`, returning how many were checked.
+fn run_test(test: &Path) -> Result<usize> {
+    let contents = fs::read_to_string(test)?;
+    let adjust = |mut e: wast::Error| {
+        e.set_path(test);
+        e.set_text(&contents);
+        e
+    };
+    let buf = ParseBuffer::new(&contents).map_err(adjust)?;
+    let wast = parser::parse::<Wast>(&buf).map_err(adjust)?;
+    let features = features(proposal(test));
+
+    let mut n = 0;
+    for directive in wast.directives {
+        let (line, col) = directive.span().linecol_in(&contents);
+        run_directive(directive, features).with_context(|| {
+            format!(
+                "failed directive on {}:{}:{}",
+                test.display(),
+                line + 1,
+                col + 1
+            )
+        })?;
+        n += 1;
+    }
+    Ok(n)
+}
+
+fn run_directive(directive: WastDirective<'_>, features: WasmFeatures) -> Result<()> {
+    match directive {
+        WastDirective::Module(mut module) => validate(&module.encode()?, features),
+        WastDirective::QuoteModule { source, .. } => {
+            validate(&parse_quote_module(&source)?, features)
+        }
+        WastDirective::AssertInvalid {
+            mut module,
+            message,
+            ..
+        } => {
+            let wasm = module.encode()?;
+            if validate(&wasm, features).is_ok() {
+                bail!("validated but should have failed with: {}", message);
+            }
+            Ok(())
+        }
+        WastDirective::AssertMalformed {
+            module, message, ..
+        } => {
+            let result = match module {
+                QuoteModule::Quote(source) => parse_quote_module(&source),
+                QuoteModule::Module(mut module) => module.encode().map_err(|e| e.into()),
+            };
+            if let Ok(wasm) = result {
+                if validate(&wasm, features).is_ok() {
+                    bail!("validated but should have failed with: {}", message);
+                }
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn parse_quote_module(source: &[&[u8]]) -> Result<Vec<u8>> {
+    let mut text = String::new();
+    for src in source {
+        text.push_str(str::from_utf8(src).context("malformed UTF-8 encoding")?);
+        text.push_str(" ");
+    }
+    let buf = ParseBuffer::new(&text)?;
+    let mut wat = parser::parse::<Wat>(&buf)?;
+    Ok(wat.module.encode()?)
+}
+
+fn validate(wasm: &[u8], features: WasmFeatures) -> Result<()> {
+    let mut validator = Validator::new();
+    validator.wasm_features(features);
+    validator.validate_all(wasm)?;
+    Ok(())
+}
@@ -0,0 +1,165 @@
+use wasm_tools::split::{Split, Splitter};
+
+mod common;
+
+fn split(splitter: &Splitter, wat: &str) -> anyhow::Result<(Split, String, Vec<String>)> {
+    let wasm = wat::parse_str(wat)?;
+    let split = splitter.split(&wasm)?;
+    common::validate(&split.primary)?;
+    let primary = wasmprinter::print_bytes(&split.primary)?;
+    let mut secondaries = Vec::new();
+    for secondary in split.secondaries.iter() {
+        common::validate(secondary)?;
+        secondaries.push(wasmprinter::print_bytes(secondary)?);
+    }
+    Ok((split, primary, secondaries))
+}
+
+const MODULE: &str = r#"
+    (module
+      (import "env" "log" (func $log (param i32)))
+      (memory (export "memory") 1)
+      (global $counter (mut i32) (i32.const 0))
+      (table 2 funcref)
+      (elem (i32.const 0) $main $shared)
+      (func $main (export "main") (param i32) (result i32)
+        local.get 0
+        call $shared)
+      (func $shared (param i32) (result i32)
+        local.get 0
+        i32.const 1
+        i32.add)
+      (func $settings (export "settings") (param i32 i32)
+        local.get 0
+        call $shared
+        local.get 1
+        i32.store
+        global.get $counter
+        call $helper)
+      (func $helper (param i32)
+        local.get 0
+        call $log))
+"#;
+
+#[test]
+fn moves_reachable_functions() -> anyhow::Result<()> {
+    let (_, primary, secondaries) = split(Splitter::new().secondary(vec![3]), MODULE)?;
+
+    // The moved functions are stubs calling through slots 2 and 3 of the
+    // table, which grew to hold them.
+    assert!(primary.contains("(table (;0;) 4 funcref)"), "{}", primary);
+    let settings = "(func $settings (type 2) (param i32 i32)
+    local.get 0
+    local.get 1
+    i32.const 2
+    call_indirect (type 2))";
+    assert!(primary.contains(settings), "{}", primary);
+    assert!(
+        primary.contains("i32.const 3\n    call_indirect (type 0))"),
+        "{}",
+        primary
+    );
+    for export in [
+        "(export \"__split_func_0\" (func $log))",
+        "(export \"__split_func_2\" (func $shared))",
+        "(export \"__split_table_0\" (table 0))",
+        "(export \"__split_memory_0\" (memory 0))",
+        "(export \"__split_global_0\" (global 0))",
+    ]
+    .iter()
+    {
+        assert!(primary.contains(export), "{}", primary);
+    }
+
+    let secondary = &secondaries[0];
+    for import in [
+        "(import \"primary\" \"__split_func_0\" (func $log (type 0)))",
+        "(import \"primary\" \"__split_func_2\" (func $shared (type 1)))",
+        "(import \"primary\" \"__split_table_0\" (table (;0;) 2 funcref))",
+        "(import \"primary\" \"__split_memory_0\" (memory (;0;) 1))",
+        "(import \"primary\" \"__split_global_0\" (global (;0;) (mut i32)))",
+    ]
+    .iter()
+    {
+        assert!(secondary.contains(import), "{}", secondary);
+    }
+    let settings = "(func $settings (type 2) (param i32 i32)
+    local.get 0
+    call $shared
+    local.get 1
+    i32.store
+    global.get 0
+    call $helper)";
+    assert!(secondary.contains(settings), "{}", secondary);
+    assert!(
+        secondary.contains("(elem (;0;) (i32.const 2) func $settings $helper)"),
+        "{}",
+        secondary
+    );
+    Ok(())
+}
+
+#[test]
+fn several_secondary_modules() -> anyhow::Result<()> {
+    let wat = r#"
+        (module
+          (func $a (export "a") call $common)
+          (func $b (export "b") call $common call $only_b)
+          (func $common)
+          (func $only_b))
+    "#;
+    let (_, primary, secondaries) =
+        split(Splitter::new().secondary(vec![0]).secondary(vec![1]), wat)?;
+    // Without a table, one is created for the slots.
+    assert!(primary.contains("(table (;0;) 4 funcref)"), "{}", primary);
+    assert!(
+        secondaries[0].contains("(elem (;0;) (i32.const 0) func $a $common)"),
+        "{}",
+        secondaries[0]
+    );
+    // `$common` went to the first module, so the second one calls it through
+    // its stub in the primary module.
+    assert!(
+        secondaries[1].contains("(import \"primary\" \"__split_func_2\" (func $common"),
+        "{}",
+        secondaries[1]
+    );
+    assert!(
+        secondaries[1].contains("(elem (;0;) (i32.const 2) func $b $only_b)"),
+        "{}",
+        secondaries[1]
+    );
+    Ok(())
+}
+
+#[test]
+fn functions_which_stay() -> anyhow::Result<()> {
+    let wat = r#"
+        (module
+          (memory 1)
+          (data "x")
+          (func $start call $lazy)
+          (func $lazy (export "lazy") call $init)
+          (func $init
+            i32.const 0
+            i32.const 0
+            i32.const 1
+            memory.init 0)
+          (start $start))
+    "#;
+    // `$lazy` is reachable from the start function, and `$init` uses a
+    // passive data segment, so nothing is moved.
+    let mut splitter = Splitter::new();
+    splitter.primary_module("app").secondary(vec![1]);
+    let (split, _, secondaries) = split(&splitter, wat)?;
+    assert_eq!(split.primary, wat::parse_str(wat)?);
+    assert!(!secondaries[0].contains("(elem"), "{}", secondaries[0]);
+
+    let wasm = wat::parse_str(wat)?;
+    let err = Splitter::new().secondary(vec![4]).split(&wasm).unwrap_err();
+    assert_eq!(err.to_string(), "function index 4 out of bounds");
+    let wasm = wat::parse_str(r#"(module (import "env" "f" (func)))"#)?;
+    let err = Splitter::new().secondary(vec![0]).split(&wasm).unwrap_err();
+    assert_eq!(err.to_string(), "function 0 is imported and can't be moved");
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use wasm_tools::interp::{Instance, Value};
+use wasm_tools::stub::{Stub, Stubs};
+
+mod common;
+
+fn stub_wat(stubs: &Stubs, wat: &str) -> anyhow::Result<Vec<u8>> {
+    common::transform(wat, |wasm| stubs.apply(wasm))
+}
+
+#[test]
+fn return_and_trap() -> anyhow::Result<()> {
+    let wasm = stub_wat(
+        Stubs::new()
+            .stub(
+                "env",
+                "pair",
+                Stub::Return(vec![Value::I32(4), Value::F64(0)]),
+            )
+            .stub("env", "abort", Stub::Trap),
+        r#"
+            (module
+              (import "env" "pair" (func $pair (result i32 f64)))
+              (import "env" "kept" (func $kept))
+              (import "env" "abort" (func $abort))
+              (func $get (export "get") (result i32)
+                call $pair
+                drop)
+              (func (export "abort")
+                call $abort))
+        "#,
+    )?;
+    let text = wasmprinter::print_bytes(&wasm)?;
+    assert!(text.contains(r#"(import "env" "kept" (func $kept (type"#));
+    assert!(!text.contains(r#"(import "env" "pair""#));
+    assert!(!text.contains(r#"(import "env" "abort""#));
+    assert!(text.contains("(func $get (type"));
+    assert!(text.contains("call $pair"));
+    assert!(text.contains("(func $pair (type"));
+
+    let wasm = stub_wat(
+        Stubs::new().stub("env", "abort", Stub::Trap).stub(
+            "env",
+            "random",
+            Stub::Return(vec![Value::I32(4)]),
+        ),
+        r#"
+            (module
+              (import "env" "random" (func $random (result i32)))
+              (import "env" "abort" (func $abort))
+              (func (export "random") (result i32)
+                call $random)
+              (func (export "abort")
+                call $abort))
+        "#,
+    )?;
+    let mut instance = Instance::new(&wasm)?;
+    assert_eq!(instance.invoke("random", &[])?, [Value::I32(4)]);
+    assert!(instance.invoke("abort", &[]).is_err());
+    Ok(())
+}
+
+#[test]
+fn forward() -> anyhow::Result<()> {
+    let wasm = stub_wat(
+        Stubs::new().stub("env", "add", Stub::Forward { table: 1, index: 3 }),
+        r#"
+            (module
+              (import "env" "add" (func (param i32 i64)))
+              (table 0 funcref)
+              (table 4 funcref))
+        "#,
+    )?;
+    let text = wasmprinter::print_bytes(&wasm)?;
+    assert!(!text.contains("import"));
+    assert!(text
+        .contains("local.get 0\n    local.get 1\n    i32.const 3\n    call_indirect 1 (type 0)"));
+    Ok(())
+}
+
+#[test]
+fn errors() -> anyhow::Result<()> {
+    let wasm = wat::parse_str(
+        r#"
+            (module
+              (import "env" "f" (func (result i32)))
+              (import "env" "g" (global i32)))
+        "#,
+    )?;
+    let stub = Stub::Return(vec![Value::I64(1)]);
+    assert!(Stubs::new().stub("env", "f", stub).apply(&wasm).is_err());
+    assert!(Stubs::new()
+        .stub("env", "g", Stub::Trap)
+        .apply(&wasm)
+        .is_err());
+    assert_eq!(
+        Stubs::new().stub("env", "h", Stub::Trap).apply(&wasm)?,
+        wasm
+    );
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use wasm_tools::summary::summarize;
+
+#[test]
+fn summary() -> anyhow::Result<()> {
+    let wasm = wat::parse_str(
+        r#"
+            (module
+              (import "env" "mem" (memory 1 2 shared))
+              (table 3 funcref)
+              (func (export "f") (result i32 i32) i32.const 0 i32.const 1))
+        "#,
+    )?;
+    let summary = serde_json::to_value(summarize(&wasm)?)?;
+    assert_eq!(
+        summary["sections"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["kind"].as_str().unwrap())
+            .collect::<Vec<_>>(),
+        ["type", "import", "function", "table", "export", "code"],
+    );
+    assert_eq!(
+        summary["imports"],
+        serde_json::json!([{"module": "env", "field": "mem", "kind": "memory"}]),
+    );
+    assert_eq!(
+        summary["exports"],
+        serde_json::json!([{"name": "f", "kind": "func", "index": 0}]),
+    );
+    assert_eq!(summary["memories"][0]["maximum"], 2);
+    assert_eq!(summary["memories"][0]["shared"], true);
+    assert_eq!(summary["tables"][0]["element_type"], "funcref");
+    assert_eq!(
+        summary["features"],
+        serde_json::json!(["multi-value", "threads"])
+    );
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use wasm_tools::usage::{usage, PROPOSALS};
+
+#[test]
+fn counts_operators_and_proposals() -> anyhow::Result<()> {
+    let wasm = wat::parse_str(
+        r#"
+            (module
+              (memory $big i64 1)
+              (memory $small 1)
+              (global i32 (i32.const 1))
+              (data (i64.const 0) "x")
+              (func (param i32) (result i32)
+                local.get 0
+                i32.extend8_s
+                i64.const 0
+                i32.const 0
+                i32.const 0
+                memory.copy $big $small
+                i64.const 0
+                i32.atomic.load
+                i32.add
+                return_call 0))
+        "#,
+    )?;
+    let usage = usage(&wasm)?;
+    // 10 operators and `end` in the function, and 2 in each constant
+    // expression.
+    assert_eq!(usage.operators, 15);
+    assert_eq!(usage.opcodes["I32Const"], 3);
+    assert_eq!(usage.opcodes["I64Const"], 3);
+    assert_eq!(usage.opcodes["End"], 3);
+    assert_eq!(usage.opcodes["I32AtomicLoad"], 1);
+    assert!(!usage.opcodes.contains_key("I64Add"));
+    assert_eq!(usage.proposals.len(), PROPOSALS.len());
+    assert_eq!(usage.proposals["sign-extension"], 1);
+    assert_eq!(usage.proposals["bulk-memory"], 1);
+    assert_eq!(usage.proposals["threads"], 1);
+    assert_eq!(usage.proposals["tail-call"], 1);
+    assert_eq!(usage.proposals["multi-memory"], 1);
+    assert_eq!(usage.proposals["memory64"], 2);
+    assert_eq!(usage.proposals["simd"], 0);
+    assert_eq!(
+        usage.required,
+        ["bulk-memory", "threads", "tail-call", "multi-memory", "memory64"]
+    );
+
+    let json = serde_json::to_value(&usage)?;
+    assert_eq!(json["proposals"]["memory64"], 2);
+    assert_eq!(json["opcodes"]["ReturnCall"], 1);
+
+    let text = usage.to_string();
+    assert!(text.contains("threads: 1 operators (required)\n"), "{}", text);
+    assert!(text.contains("simd: 0 operators\n"), "{}", text);
+    Ok(())
+}